@@ -0,0 +1,116 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use chobitlibs::chobit_ai::*;
+use chobitlibs::chobit_rand::*;
+
+const OUT: usize = 32;
+const MIDDLE: usize = 256;
+const IN: usize = 128;
+const BATCH: usize = 64;
+
+fn gen_data() -> (
+    ChobitAi<OUT, MIDDLE, IN>,
+    Vec<MathVec<IN>>,
+    Vec<MathVec<OUT>>
+) {
+    let mut rng = ChobitRand::new(b"chobit_ai_batch_bench");
+
+    let mut ai = ChobitAi::<OUT, MIDDLE, IN>::new(Activation::Sigmoid);
+    ai.for_each_weight_mut(|val| {*val = (rng.next_f64() as f32) - 0.5});
+
+    let mut inputs = vec![MathVec::<IN>::new(); BATCH];
+    inputs.iter_mut().for_each(|input| {
+        input.iter_mut().for_each(|val| {*val = rng.next_f64() as f32});
+    });
+
+    let mut train_out = vec![MathVec::<OUT>::new(); BATCH];
+    train_out.iter_mut().for_each(|output| {
+        output.iter_mut().for_each(|val| {*val = rng.next_f64() as f32});
+    });
+
+    (ai, inputs, train_out)
+}
+
+fn chobit_ai_calc_bench(c: &mut Criterion) {
+    let (ai, inputs, _) = gen_data();
+
+    let mut output = MathVec::<OUT>::new();
+    let mut tmpbuf = MathVec::<MIDDLE>::new();
+
+    let mut total: f32 = 0.0;
+    c.bench_function("chobit_ai_calc_bench", |b| b.iter(|| {
+        inputs.iter().for_each(|input| {
+            ai.calc(input, &mut output, &mut tmpbuf);
+
+            total += output[0];
+        });
+    }));
+
+    println!("{}", total);
+}
+
+fn chobit_ai_calc_batch_bench(c: &mut Criterion) {
+    let (ai, inputs, _) = gen_data();
+
+    let mut outputs = vec![MathVec::<OUT>::new(); BATCH];
+    let mut tmpbufs = vec![MathVec::<MIDDLE>::new(); BATCH];
+
+    let mut total: f32 = 0.0;
+    c.bench_function("chobit_ai_calc_batch_bench", |b| b.iter(|| {
+        ai.calc_batch(&inputs, &mut outputs, &mut tmpbufs);
+
+        outputs.iter().for_each(|output| {total += output[0];});
+    }));
+
+    println!("{}", total);
+}
+
+fn chobit_ml_ai_study_bench(c: &mut Criterion) {
+    let (ai, inputs, train_out) = gen_data();
+
+    let mut ai = ChobitMlAi::<OUT, MIDDLE, IN>::new(ai);
+    let mut cache = MlAiCache::<OUT, MIDDLE, IN>::new();
+    let mut output_error = MathVec::<OUT>::new();
+    let mut input_error = MathVec::<IN>::new();
+
+    c.bench_function("chobit_ml_ai_study_bench", |b| b.iter(|| {
+        inputs.iter().zip(train_out.iter()).for_each(
+            |(input, train_out_one)| {
+                ai.ready(input, &mut cache);
+                cache.calc_output_error(train_out_one, &mut output_error);
+                ai.study(&output_error, &cache, &mut input_error);
+            }
+        );
+    }));
+
+    let mut total: f32 = 0.0;
+    ai.for_each_total_grad(|val| {total += *val});
+    println!("{}", total);
+}
+
+fn chobit_ml_ai_study_batch_bench(c: &mut Criterion) {
+    let (ai, inputs, train_out) = gen_data();
+
+    let mut ai = ChobitMlAi::<OUT, MIDDLE, IN>::new(ai);
+    let mut cache = MlAiBatchCache::<OUT, MIDDLE, IN>::new();
+    let mut output_errors = vec![MathVec::<OUT>::new(); BATCH];
+    let mut input_errors = vec![MathVec::<IN>::new(); BATCH];
+
+    c.bench_function("chobit_ml_ai_study_batch_bench", |b| b.iter(|| {
+        ai.ready_batch(&inputs, &mut cache);
+        cache.calc_output_error(&train_out, &mut output_errors);
+        ai.study_batch(&output_errors, &cache, &mut input_errors);
+    }));
+
+    let mut total: f32 = 0.0;
+    ai.for_each_total_grad(|val| {total += *val});
+    println!("{}", total);
+}
+
+criterion_group!(
+    chobit_ai_batch_benches,
+    chobit_ai_calc_bench,
+    chobit_ai_calc_batch_bench,
+    chobit_ml_ai_study_bench,
+    chobit_ml_ai_study_batch_bench
+);
+criterion_main!(chobit_ai_batch_benches);
@@ -0,0 +1,121 @@
+extern crate chobitlibs;
+
+use std::thread;
+
+use chobitlibs::chobit_ai::{
+    MathVec,
+    Activation,
+    ChobitAi,
+    ChobitMlAi,
+    ChobitDataParallel,
+    Loss
+};
+
+use chobitlibs::chobit_rand::ChobitRand;
+
+fn japanese_letter(rng: &mut ChobitRand) -> char {
+    let letters = [
+        'あ', 'い', 'う', 'え', 'お',
+        'か', 'き', 'く', 'け', 'こ',
+        'さ', 'し', 'す', 'せ', 'そ'
+    ];
+
+    letters[(rng.next_u64() as usize) % letters.len()]
+}
+
+fn english_letter(rng: &mut ChobitRand) -> char {
+    let letters = [
+        'a', 'b', 'c', 'd', 'e',
+        'f', 'g', 'h', 'i', 'j',
+        'k', 'l', 'm', 'n', 'o'
+    ];
+
+    letters[(rng.next_u64() as usize) % letters.len()]
+}
+
+const JAPANESE_ID: char = '日';
+const ENGLISH_ID: char = 'E';
+
+fn main() {
+    const OUT: usize = 32;
+    const MIDDLE: usize = 64;
+    const IN: usize = 32;
+
+    const NUM_WORKERS: usize = 4;
+
+    let mut rng = ChobitRand::new(b"ChobitAi Example");
+
+    let mut ai = ChobitAi::<OUT, MIDDLE, IN>::new(Activation::SoftSign);
+
+    // Randomises weights.
+    ai.for_each_weight_mut(|weight| {
+        *weight = ((rng.next_f64() as f32) * 2.0) - 1.0;
+    });
+
+    // Copies AI into workers.
+    let mut parallel = ChobitDataParallel::<OUT, MIDDLE, IN>::new(
+        ChobitMlAi::<OUT, MIDDLE, IN>::new(ai),
+        NUM_WORKERS
+    );
+
+    // Runs each task of workers on its own thread.
+    let mut runner = |tasks: &mut [&mut (dyn FnMut() + Send)]| {
+        thread::scope(|scope| {
+            tasks.iter_mut().for_each(|task| {
+                scope.spawn(move || task());
+            });
+        });
+    };
+
+    const EPOCH: usize = 1000;
+    const BATCH_SIZE: usize = 100;
+    const RATE: f32 = 0.01;
+
+    let mut inputs = vec![MathVec::<IN>::new(); BATCH_SIZE * 2];
+    let mut train_out = vec![MathVec::<OUT>::new(); BATCH_SIZE * 2];
+
+    for _ in 0..EPOCH {
+        inputs.chunks_exact_mut(2).zip(train_out.chunks_exact_mut(2))
+            .for_each(|(input, output)| {
+                //--- Japanese ---//
+                input[0].load_u32_label(japanese_letter(&mut rng) as u32);
+                output[0].load_u32_label(JAPANESE_ID as u32);
+
+                //--- English ---//
+                input[1].load_u32_label(english_letter(&mut rng) as u32);
+                output[1].load_u32_label(ENGLISH_ID as u32);
+            });
+
+        // Each worker studies its own part of samples.
+        parallel.study(&inputs, &train_out, &Loss::Mse, &mut runner);
+
+        // Gathers gradients into master, updates it,
+        // and copies its weights into workers.
+        parallel.update(RATE);
+    }
+
+    // Unwrap AI.
+    let ai = parallel.drop().drop();
+
+    let mut input = MathVec::<IN>::new();
+    let mut output = MathVec::<OUT>::new();
+    let mut tmpbuf = MathVec::<MIDDLE>::new();
+
+    // Tests Japanese.
+    for _ in 0..10 {
+        input.load_u32_label(japanese_letter(&mut rng) as u32);
+
+        ai.calc(&input, &mut output, &mut tmpbuf);
+
+        assert_eq!(output.to_u32_label(), JAPANESE_ID as u32);
+    }
+
+    // Tests English.
+    for _ in 0..10 {
+        input.load_u32_label(english_letter(&mut rng) as u32);
+
+        ai.calc(&input, &mut output, &mut tmpbuf);
+
+        assert_eq!(output.to_u32_label(), ENGLISH_ID as u32);
+    }
+}
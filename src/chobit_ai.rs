@@ -28,8 +28,12 @@
 //! This AI works on single thread, but is able to work on `no_std`.  
 //! (But you can devise to do machine learning in multithread. See the following list.)
 //!
+//! With `simd` feature, inner products and gradients of [`Weights`] and [`MathVec`] are calculated with SIMD of `core::arch`. (SSE2 or AVX2 on x86_64, simd128 on wasm32. On other targets, the same as without the feature.)  
+//! Inner products are accumulated in different order, so results may differ slightly from those without the feature.
+//!
 //! - [Example of Single Thread Machine Learning](#example-of-single-thread-machine-learning)
 //! - [Example of Multithread Machine Learning](#example-of-multithread-machine-learning)
+//! - [Example of Data-Parallel Machine Learning](#example-of-data-parallel-machine-learning)
 //!
 //!
 //! # Example of Single Thread Machine Learning
@@ -526,6 +530,59 @@
 //! ```
 //!
 //!
+//! # Example of Data-Parallel Machine Learning
+//!
+//! [`ChobitDataParallel`] does the above gradient sharing for [`ChobitMlAi`].  
+//! Threads are given by user with [`ParallelRunner`], so this library itself stays `no_std`.
+//!
+//! ```ignore
+//! use std::thread;
+//!
+//! use chobitlibs::chobit_ai::{
+//!     MathVec,
+//!     Activation,
+//!     ChobitAi,
+//!     ChobitMlAi,
+//!     ChobitDataParallel,
+//!     Loss
+//! };
+//!
+//! const OUT: usize = 32;
+//! const MIDDLE: usize = 64;
+//! const IN: usize = 32;
+//!
+//! let ai = ChobitAi::<OUT, MIDDLE, IN>::new(Activation::SoftSign);
+//!
+//! // Copies AI into 4 workers.
+//! let mut parallel = ChobitDataParallel::<OUT, MIDDLE, IN>::new(
+//!     ChobitMlAi::<OUT, MIDDLE, IN>::new(ai),
+//!     4
+//! );
+//!
+//! // Runs each task of workers on its own thread.
+//! let mut runner = |tasks: &mut [&mut (dyn FnMut() + Send)]| {
+//!     thread::scope(|scope| {
+//!         tasks.iter_mut().for_each(|task| {
+//!             scope.spawn(move || task());
+//!         });
+//!     });
+//! };
+//!
+//! let inputs = vec![MathVec::<IN>::new(); 200];
+//! let train_out = vec![MathVec::<OUT>::new(); 200];
+//!
+//! for _ in 0..1000 {
+//!     // Each worker studies its own part of samples.
+//!     parallel.study(&inputs, &train_out, &Loss::Mse, &mut runner);
+//!
+//!     // Gathers gradients into master, updates it,
+//!     // and copies its weights into workers.
+//!     parallel.update(0.01);
+//! }
+//!
+//! let ai = parallel.drop().drop();
+//! ```
+//!
 
 use alloc::{boxed::Box, vec, vec::Vec};
 
@@ -543,11 +600,359 @@ use core::{
         Rem,
         RemAssign,
         Deref,
-        DerefMut
+        DerefMut,
+        Range
     },
-    iter::Iterator
+    iter::Iterator,
+    fmt,
+    mem
 };
 
+/// Error for [`chobit_ai`](self).
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChobitAiError {
+    /// Data doesn't start with [`FORMAT_MAGIC`].
+    InvalidMagic,
+
+    /// Version of data is not supported.
+    ///
+    /// - `version` : Version written in data.
+    UnsupportedVersion {version: u32},
+
+    /// Data is for other model.
+    ///
+    /// - `expected` : Model kind of receiver.
+    /// - `found` : Model kind written in data.
+    WrongModelKind {expected: ModelKind, found: u8},
+
+    /// Dimension written in data doesn't match the generic parameter.
+    ///
+    /// - `name` : Name of the generic parameter or the data.
+    /// - `expected` : Dimension of receiver.
+    /// - `found` : Dimension written in data.
+    DimensionMismatch {name: &'static str, expected: usize, found: usize},
+
+    /// Activation function written in data is unknown.
+    ///
+    /// - `tag` : Tag of activation function.
+    UnknownActivation {tag: u8},
+
+    /// Optimizer written in data is unknown.
+    ///
+    /// - `tag` : Tag of optimizer.
+    UnknownOptimizer {tag: u8},
+
+    /// Non-finite number (NaN or infinity) is found in machine learning.
+    ///
+    /// `check_finite()` of each machine learning type checks total gradients first, from the layer nearest to output, so the error points to the layer which first produced non-finite gradient in backpropagation.
+    ///
+    /// - `layer` : Name of the layer. (e.g. `"output_layer"`, `"lstm.f_gate"`)
+    /// - `part` : `"total_grad"` or `"weights"`.
+    NonFinite {layer: &'static str, part: &'static str},
+
+    /// Data ended before reading all items.
+    UnexpectedEnd
+}
+
+impl fmt::Display for ChobitAiError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, r#"{{"error":"ChobitAiError","kind":"#)?;
+
+        match self {
+            Self::InvalidMagic => {
+                write!(formatter, r#""InvalidMagic""#)?;
+            },
+
+            Self::UnsupportedVersion {version} => {
+                write!(
+                    formatter,
+                    r#""UnsupportedVersion","version":{}"#,
+                    version
+                )?;
+            },
+
+            Self::WrongModelKind {expected, found} => {
+                write!(
+                    formatter,
+                    r#""WrongModelKind","expected":{},"found":{}"#,
+                    *expected as u8,
+                    found
+                )?;
+            },
+
+            Self::DimensionMismatch {name, expected, found} => {
+                write!(
+                    formatter,
+                    r#""DimensionMismatch","name":"{}","expected":{},"found":{}"#,
+                    name,
+                    expected,
+                    found
+                )?;
+            },
+
+            Self::UnknownActivation {tag} => {
+                write!(formatter, r#""UnknownActivation","tag":{}"#, tag)?;
+            },
+
+            Self::UnknownOptimizer {tag} => {
+                write!(formatter, r#""UnknownOptimizer","tag":{}"#, tag)?;
+            },
+
+            Self::NonFinite {layer, part} => {
+                write!(
+                    formatter,
+                    r#""NonFinite","layer":"{}","part":"{}""#,
+                    layer,
+                    part
+                )?;
+            },
+
+            Self::UnexpectedEnd => {
+                write!(formatter, r#""UnexpectedEnd""#)?;
+            }
+        }
+
+        write!(formatter, "}}")
+    }
+}
+
+/// Magic number at the head of data written by `save()`.
+pub const FORMAT_MAGIC: [u8; 4] = *b"CHAI";
+
+/// Version of data written by `save()`.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Kind of model written in data.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum ModelKind {
+    /// [`ChobitAi`]
+    ChobitAi = 1,
+
+    /// [`Lstm`]
+    Lstm = 2,
+
+    /// [`ChobitEncoder`]
+    ChobitEncoder = 3,
+
+    /// [`ChobitDecoder`]
+    ChobitDecoder = 4,
+
+    /// [`ChobitSeqAi`]
+    ChobitSeqAi = 5,
+
+    /// Checkpoint of [`MlLayer`].
+    MlLayer = 6,
+
+    /// Checkpoint of [`ChobitMlAi`].
+    ChobitMlAi = 7,
+
+    /// Checkpoint of [`MlLstm`].
+    MlLstm = 8,
+
+    /// Checkpoint of [`ChobitMlEncoder`].
+    ChobitMlEncoder = 9,
+
+    /// Checkpoint of [`ChobitMlDecoder`].
+    ChobitMlDecoder = 10,
+
+    /// Checkpoint of [`ChobitMlSeqAi`].
+    ChobitMlSeqAi = 11,
+
+    /// [`Gru`]
+    Gru = 12,
+
+    /// Checkpoint of [`MlGru`].
+    MlGru = 13,
+
+    /// [`ChobitGruEncoder`]
+    ChobitGruEncoder = 14,
+
+    /// Checkpoint of [`ChobitMlGruEncoder`].
+    ChobitMlGruEncoder = 15,
+
+    /// [`ChobitGruDecoder`]
+    ChobitGruDecoder = 16,
+
+    /// Checkpoint of [`ChobitMlGruDecoder`].
+    ChobitMlGruDecoder = 17,
+
+    /// [`ChobitGruSeqAi`]
+    ChobitGruSeqAi = 18,
+
+    /// Checkpoint of [`ChobitMlGruSeqAi`].
+    ChobitMlGruSeqAi = 19,
+
+    /// [`ChobitStackedEncoder`]
+    ChobitStackedEncoder = 20,
+
+    /// Checkpoint of [`ChobitMlStackedEncoder`].
+    ChobitMlStackedEncoder = 21,
+
+    /// [`ChobitBiEncoder`]
+    ChobitBiEncoder = 22,
+
+    /// Checkpoint of [`ChobitMlBiEncoder`].
+    ChobitMlBiEncoder = 23,
+
+    /// [`ChobitAttentionSeqAi`]
+    ChobitAttentionSeqAi = 24,
+
+    /// Checkpoint of [`ChobitMlAttentionSeqAi`].
+    ChobitMlAttentionSeqAi = 25,
+
+    /// [`ChobitTransformerBlock`]
+    ChobitTransformerBlock = 26,
+
+    /// Checkpoint of [`ChobitMlTransformerBlock`].
+    ChobitMlTransformerBlock = 27,
+
+    /// [`Embedding`]
+    Embedding = 28,
+
+    /// Checkpoint of [`MlEmbedding`].
+    MlEmbedding = 29,
+
+    /// [`QuantizedChobitAi`]
+    QuantizedChobitAi = 30,
+
+    /// [`QuantizedLstm`]
+    QuantizedLstm = 31,
+
+    /// [`SparseChobitAi`]
+    SparseChobitAi = 32
+}
+
+#[inline]
+fn write_u8(buffer: &mut Vec<u8>, value: u8) {
+    buffer.push(value);
+}
+
+#[inline]
+fn write_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+#[inline]
+fn write_u64(buffer: &mut Vec<u8>, value: u64) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+#[inline]
+fn write_f32(buffer: &mut Vec<u8>, value: f32) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+fn write_header(buffer: &mut Vec<u8>, kind: ModelKind, dims: &[usize]) {
+    buffer.extend_from_slice(&FORMAT_MAGIC);
+    write_u32(buffer, FORMAT_VERSION);
+    write_u8(buffer, kind as u8);
+
+    write_u8(buffer, dims.len() as u8);
+    dims.iter().for_each(|dim| {write_u32(buffer, *dim as u32);});
+}
+
+struct ByteReader<'a> {
+    data: &'a [u8],
+    pos: usize
+}
+
+impl<'a> ByteReader<'a> {
+    #[inline]
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data: data,
+            pos: 0
+        }
+    }
+
+    #[inline]
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], ChobitAiError> {
+        let ret = self.data.get(self.pos..(self.pos + len)).ok_or(
+            ChobitAiError::UnexpectedEnd
+        )?;
+
+        self.pos += len;
+
+        Ok(ret)
+    }
+
+    #[inline]
+    fn read_u8(&mut self) -> Result<u8, ChobitAiError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    #[inline]
+    fn read_u32(&mut self) -> Result<u32, ChobitAiError> {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(self.read_bytes(4)?);
+
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    #[inline]
+    fn read_u64(&mut self) -> Result<u64, ChobitAiError> {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(self.read_bytes(8)?);
+
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    #[inline]
+    fn read_f32(&mut self) -> Result<f32, ChobitAiError> {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(self.read_bytes(4)?);
+
+        Ok(f32::from_le_bytes(bytes))
+    }
+
+    fn read_header(
+        &mut self,
+        kind: ModelKind,
+        dims: &[(&'static str, usize)]
+    ) -> Result<(), ChobitAiError> {
+        if self.read_bytes(FORMAT_MAGIC.len())? != FORMAT_MAGIC {
+            return Err(ChobitAiError::InvalidMagic);
+        }
+
+        let version = self.read_u32()?;
+        if version != FORMAT_VERSION {
+            return Err(ChobitAiError::UnsupportedVersion {version: version});
+        }
+
+        let found = self.read_u8()?;
+        if found != kind as u8 {
+            return Err(ChobitAiError::WrongModelKind {
+                expected: kind,
+                found: found
+            });
+        }
+
+        let dims_len = self.read_u8()? as usize;
+        if dims_len != dims.len() {
+            return Err(ChobitAiError::DimensionMismatch {
+                name: "dims",
+                expected: dims.len(),
+                found: dims_len
+            });
+        }
+
+        for (name, expected) in dims {
+            let found = self.read_u32()? as usize;
+
+            if found != *expected {
+                return Err(ChobitAiError::DimensionMismatch {
+                    name: name,
+                    expected: *expected,
+                    found: found
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
 #[inline]
 fn abs(x: f32) -> f32 {
     f32::from_bits(x.to_bits() & 0x7fffffff)
@@ -564,6 +969,93 @@ fn sqrt(x: f32) -> f32 {
     y * (MAGIC_2 * (MAGIC_3 - (x * y * y))) * x
 }
 
+#[inline]
+fn exp(x: f32) -> f32 {
+    const LN_2: f32 = core::f32::consts::LN_2;
+    const LOG2_E: f32 = core::f32::consts::LOG2_E;
+
+    if x > 88.0 {
+        return f32::INFINITY;
+    } else if x < -87.0 {
+        return 0.0;
+    }
+
+    // x = k * ln(2) + r, |r| <= ln(2) / 2
+    let k = ((x * LOG2_E) + if x < 0.0 {-0.5} else {0.5}) as i32;
+    let r = x - ((k as f32) * LN_2);
+
+    // Taylor series of exp(r) to r^7.
+    let mut term: f32 = 1.0;
+    let mut ret: f32 = 1.0;
+
+    for i in 1..8 {
+        term *= r / (i as f32);
+        ret += term;
+    }
+
+    ret * f32::from_bits(((k + 127) as u32) << 23)
+}
+
+#[inline]
+fn softmax_slice(values: &mut [f32]) {
+    let max = values.iter().fold(f32::NEG_INFINITY, |a, b| a.max(*b));
+
+    let mut sum: f32 = 0.0;
+
+    values.iter_mut().for_each(|val| {
+        *val = exp(*val - max);
+        sum += *val;
+    });
+
+    let sum_inv = sum.recip();
+
+    values.iter_mut().for_each(|val| {*val *= sum_inv;});
+}
+
+#[inline]
+fn ln(x: f32) -> f32 {
+    const LN_2: f32 = core::f32::consts::LN_2;
+    const SQRT_2: f32 = core::f32::consts::SQRT_2;
+
+    if x.is_nan() || (x < 0.0) {
+        return f32::NAN;
+    } else if x == 0.0 {
+        return f32::NEG_INFINITY;
+    } else if x == f32::INFINITY {
+        return f32::INFINITY;
+    }
+
+    // x = m * 2^e
+    let bits = x.to_bits();
+    let mut e = ((bits >> 23) & 0xff) as i32 - 127;
+    let mut m = f32::from_bits((bits & 0x007fffff) | 0x3f800000);
+
+    if e == -127 {
+        // subnormal number.
+        let y = x * ((1u64 << 23) as f32);
+        return ln(y) - (23.0 * LN_2);
+    }
+
+    if m > SQRT_2 {
+        m *= 0.5;
+        e += 1;
+    }
+
+    // ln(m) = 2 * atanh((m - 1) / (m + 1))
+    let z = (m - 1.0) / (m + 1.0);
+    let z2 = z * z;
+
+    let mut term = z;
+    let mut sum = z;
+
+    for i in 1..6 {
+        term *= z2;
+        sum += term / ((2 * i + 1) as f32);
+    }
+
+    (2.0 * sum) + ((e as f32) * LN_2)
+}
+
 macro_rules! pointwise_op {
     ($self:expr, $other:expr, $ops:tt) => {{
         $self.body.iter_mut().zip($other.body.iter()).for_each(
@@ -582,69 +1074,286 @@ macro_rules! scalar_op {
     }};
 }
 
-/// Vector for mathematics.
-///
-/// - `N` : Dimension.
-///
-/// # Example
-///
-/// ```ignore
-/// const DIM: usize = 3;
-///
-/// let mut v_1 = MathVec::<DIM>::new();
-///
-/// v_1[0] = 1.0;
-/// v_1[1] = 2.0;
-/// v_1[2] = 3.0;
-///
-/// let mut v_2 = MathVec::<DIM>::new();
-///
-/// v_2[0] = 4.0;
-/// v_2[1] = 5.0;
-/// v_2[2] = 6.0;
-///
-/// let x: f32 = 10.0;
-/// ```
-///
-/// ## Addition
-///
-/// ```ignore
-/// // Add
-/// let mut v_3 = &v_1 + &v_2;
-/// assert_eq!(v_3[0], 1.0 + 4.0);
-/// assert_eq!(v_3[1], 2.0 + 5.0);
-/// assert_eq!(v_3[2], 3.0 + 6.0);
-///
-/// // Add assign
-/// v_3 += &v_1;
-/// assert_eq!(v_3[0], 1.0 + 4.0 + 1.0);
-/// assert_eq!(v_3[1], 2.0 + 5.0 + 2.0);
-/// assert_eq!(v_3[2], 3.0 + 6.0 + 3.0);
-/// ```
-///
-/// ## Subtract
-///
-/// ```ignore
-/// // Sub
-/// let mut v_3 = &v_1 - &v_2;
-/// assert_eq!(v_3[0], 1.0 - 4.0);
-/// assert_eq!(v_3[1], 2.0 - 5.0);
-/// assert_eq!(v_3[2], 3.0 - 6.0);
-///
-/// // Sub assign
-/// v_3 -= &v_1;
-/// assert_eq!(v_3[0], 1.0 - 4.0 - 1.0);
-/// assert_eq!(v_3[1], 2.0 - 5.0 - 2.0);
-/// assert_eq!(v_3[2], 3.0 - 6.0 - 3.0);
-/// ```
-///
-/// ## Multiply
-///
-/// ```ignore
-/// // Multiply.
-/// let mut v_3 = &v_1 * x;
-/// assert_eq!(v_3[0], 1.0 * 10.0);
-/// assert_eq!(v_3[1], 2.0 * 10.0);
+// Primitives of SIMD for `simd` feature. Each target has the same items.
+// Without `simd` feature or on other targets, scalar one is used.
+#[cfg(all(
+    feature = "simd",
+    target_arch = "x86_64",
+    target_feature = "avx2"
+))]
+mod simd {
+    use core::arch::x86_64::*;
+
+    pub(super) const ENABLED: bool = true;
+
+    pub(super) const LANES: usize = 8;
+
+    pub(super) type Lanes = __m256;
+
+    #[inline]
+    pub(super) unsafe fn zero() -> Lanes {_mm256_setzero_ps()}
+
+    #[inline]
+    pub(super) unsafe fn splat(x: f32) -> Lanes {_mm256_set1_ps(x)}
+
+    #[inline]
+    pub(super) unsafe fn load(ptr: *const f32) -> Lanes {
+        _mm256_loadu_ps(ptr)
+    }
+
+    #[inline]
+    pub(super) unsafe fn store(ptr: *mut f32, x: Lanes) {
+        _mm256_storeu_ps(ptr, x)
+    }
+
+    #[inline]
+    pub(super) unsafe fn add(x: Lanes, y: Lanes) -> Lanes {
+        _mm256_add_ps(x, y)
+    }
+
+    #[inline]
+    pub(super) unsafe fn mul(x: Lanes, y: Lanes) -> Lanes {
+        _mm256_mul_ps(x, y)
+    }
+}
+
+#[cfg(all(
+    feature = "simd",
+    target_arch = "x86_64",
+    not(target_feature = "avx2")
+))]
+mod simd {
+    use core::arch::x86_64::*;
+
+    pub(super) const ENABLED: bool = true;
+
+    pub(super) const LANES: usize = 4;
+
+    pub(super) type Lanes = __m128;
+
+    #[inline]
+    pub(super) unsafe fn zero() -> Lanes {_mm_setzero_ps()}
+
+    #[inline]
+    pub(super) unsafe fn splat(x: f32) -> Lanes {_mm_set1_ps(x)}
+
+    #[inline]
+    pub(super) unsafe fn load(ptr: *const f32) -> Lanes {_mm_loadu_ps(ptr)}
+
+    #[inline]
+    pub(super) unsafe fn store(ptr: *mut f32, x: Lanes) {
+        _mm_storeu_ps(ptr, x)
+    }
+
+    #[inline]
+    pub(super) unsafe fn add(x: Lanes, y: Lanes) -> Lanes {_mm_add_ps(x, y)}
+
+    #[inline]
+    pub(super) unsafe fn mul(x: Lanes, y: Lanes) -> Lanes {_mm_mul_ps(x, y)}
+}
+
+#[cfg(all(
+    feature = "simd",
+    target_arch = "wasm32",
+    target_feature = "simd128"
+))]
+mod simd {
+    use core::arch::wasm32::*;
+
+    pub(super) const ENABLED: bool = true;
+
+    pub(super) const LANES: usize = 4;
+
+    pub(super) type Lanes = v128;
+
+    #[inline]
+    pub(super) unsafe fn zero() -> Lanes {f32x4_splat(0.0)}
+
+    #[inline]
+    pub(super) unsafe fn splat(x: f32) -> Lanes {f32x4_splat(x)}
+
+    #[inline]
+    pub(super) unsafe fn load(ptr: *const f32) -> Lanes {
+        v128_load(ptr as *const v128)
+    }
+
+    #[inline]
+    pub(super) unsafe fn store(ptr: *mut f32, x: Lanes) {
+        v128_store(ptr as *mut v128, x)
+    }
+
+    #[inline]
+    pub(super) unsafe fn add(x: Lanes, y: Lanes) -> Lanes {f32x4_add(x, y)}
+
+    #[inline]
+    pub(super) unsafe fn mul(x: Lanes, y: Lanes) -> Lanes {f32x4_mul(x, y)}
+}
+
+#[cfg(not(any(
+    all(feature = "simd", target_arch = "x86_64"),
+    all(feature = "simd", target_arch = "wasm32", target_feature = "simd128")
+)))]
+mod simd {
+    pub(super) const ENABLED: bool = false;
+
+    pub(super) const LANES: usize = 1;
+
+    pub(super) type Lanes = f32;
+
+    #[inline]
+    pub(super) unsafe fn zero() -> Lanes {0.0}
+
+    #[inline]
+    pub(super) unsafe fn splat(x: f32) -> Lanes {x}
+
+    #[inline]
+    pub(super) unsafe fn load(ptr: *const f32) -> Lanes {*ptr}
+
+    #[inline]
+    pub(super) unsafe fn store(ptr: *mut f32, x: Lanes) {*ptr = x;}
+
+    #[inline]
+    pub(super) unsafe fn add(x: Lanes, y: Lanes) -> Lanes {x + y}
+
+    #[inline]
+    pub(super) unsafe fn mul(x: Lanes, y: Lanes) -> Lanes {x * y}
+}
+
+// Returns `init + Σ(a_i * b_i)`.
+// Without SIMD, products are added to `init` one by one in order.
+#[inline]
+fn add_dot(init: f32, a: &[f32], b: &[f32]) -> f32 {
+    if !simd::ENABLED {
+        return a.iter().zip(b.iter()).fold(
+            init,
+            |sum, (x, y)| sum + (*x * *y)
+        );
+    }
+
+    let len = a.len().min(b.len());
+
+    let a_chunks = a[..len].chunks_exact(simd::LANES);
+    let b_chunks = b[..len].chunks_exact(simd::LANES);
+
+    let init = a_chunks.remainder().iter().zip(
+        b_chunks.remainder().iter()
+    ).fold(init, |sum, (x, y)| sum + (*x * *y));
+
+    let mut lanes = [f32::default(); simd::LANES];
+
+    unsafe {
+        let mut sum = simd::zero();
+
+        a_chunks.zip(b_chunks).for_each(|(x, y)| {
+            sum = simd::add(
+                sum,
+                simd::mul(simd::load(x.as_ptr()), simd::load(y.as_ptr()))
+            );
+        });
+
+        simd::store(lanes.as_mut_ptr(), sum);
+    }
+
+    lanes.iter().fold(init, |sum, x| sum + *x)
+}
+
+// Adds `c * x` to `y`. Result is the same with and without SIMD.
+#[inline]
+fn add_scaled(y: &mut [f32], c: f32, x: &[f32]) {
+    if !simd::ENABLED {
+        y.iter_mut().zip(x.iter()).for_each(|(y, x)| {*y += c * *x;});
+
+        return;
+    }
+
+    let len = y.len().min(x.len());
+
+    let mut y_chunks = y[..len].chunks_exact_mut(simd::LANES);
+    let x_chunks = x[..len].chunks_exact(simd::LANES);
+
+    let x_rest = x_chunks.remainder();
+
+    unsafe {
+        let c_lanes = simd::splat(c);
+
+        (&mut y_chunks).zip(x_chunks).for_each(|(y, x)| {
+            let ptr = y.as_mut_ptr();
+
+            simd::store(ptr, simd::add(
+                simd::load(ptr),
+                simd::mul(c_lanes, simd::load(x.as_ptr()))
+            ));
+        });
+    }
+
+    y_chunks.into_remainder().iter_mut().zip(x_rest.iter()).for_each(
+        |(y, x)| {*y += c * *x;}
+    );
+}
+
+/// Vector for mathematics.
+///
+/// - `N` : Dimension.
+///
+/// # Example
+///
+/// ```ignore
+/// const DIM: usize = 3;
+///
+/// let mut v_1 = MathVec::<DIM>::new();
+///
+/// v_1[0] = 1.0;
+/// v_1[1] = 2.0;
+/// v_1[2] = 3.0;
+///
+/// let mut v_2 = MathVec::<DIM>::new();
+///
+/// v_2[0] = 4.0;
+/// v_2[1] = 5.0;
+/// v_2[2] = 6.0;
+///
+/// let x: f32 = 10.0;
+/// ```
+///
+/// ## Addition
+///
+/// ```ignore
+/// // Add
+/// let mut v_3 = &v_1 + &v_2;
+/// assert_eq!(v_3[0], 1.0 + 4.0);
+/// assert_eq!(v_3[1], 2.0 + 5.0);
+/// assert_eq!(v_3[2], 3.0 + 6.0);
+///
+/// // Add assign
+/// v_3 += &v_1;
+/// assert_eq!(v_3[0], 1.0 + 4.0 + 1.0);
+/// assert_eq!(v_3[1], 2.0 + 5.0 + 2.0);
+/// assert_eq!(v_3[2], 3.0 + 6.0 + 3.0);
+/// ```
+///
+/// ## Subtract
+///
+/// ```ignore
+/// // Sub
+/// let mut v_3 = &v_1 - &v_2;
+/// assert_eq!(v_3[0], 1.0 - 4.0);
+/// assert_eq!(v_3[1], 2.0 - 5.0);
+/// assert_eq!(v_3[2], 3.0 - 6.0);
+///
+/// // Sub assign
+/// v_3 -= &v_1;
+/// assert_eq!(v_3[0], 1.0 - 4.0 - 1.0);
+/// assert_eq!(v_3[1], 2.0 - 5.0 - 2.0);
+/// assert_eq!(v_3[2], 3.0 - 6.0 - 3.0);
+/// ```
+///
+/// ## Multiply
+///
+/// ```ignore
+/// // Multiply.
+/// let mut v_3 = &v_1 * x;
+/// assert_eq!(v_3[0], 1.0 * 10.0);
+/// assert_eq!(v_3[1], 2.0 * 10.0);
 /// assert_eq!(v_3[2], 3.0 * 10.0);
 ///
 /// // Multiply assign.
@@ -834,6 +1543,81 @@ impl<const N: usize> MathVec<N> {
     pub fn copy_to(&self, other: &mut Self) {
         other.body.copy_from_slice(&*self.body);
     }
+
+    /// Applies softmax function to this vector.
+    ///
+    /// After this, each element is in `(0.0, 1.0)` and sum of elements is `1.0`.
+    #[inline]
+    pub fn softmax(&mut self) {
+        softmax_slice(&mut self.body);
+    }
+
+    /// Adds sinusoidal positional encoding to this vector.
+    ///
+    /// | Formula |
+    /// |:-:|
+    /// | `x_2i += sin(pos / 10000^(2i / N))` |
+    /// | `x_2i+1 += cos(pos / 10000^(2i / N))` |
+    ///
+    /// Input of [`ChobitTransformerBlock`] has no information on order, so this is usually added to each input before the first block.
+    ///
+    /// - `position` : Position in sequence.
+    pub fn add_positional_encoding(&mut self, position: usize) {
+        // ln(10000)
+        const LN_BASE: f32 = 9.210340372;
+
+        let position = position as f32;
+        let len = N as f32;
+
+        self.body.iter_mut().enumerate().for_each(|(i, val)| {
+            let angle = position * exp(-(((i & !1) as f32) / len) * LN_BASE);
+
+            *val += if (i & 1) == 0 {sin(angle)} else {cos(angle)};
+        });
+    }
+
+    /// Gets index of max element.
+    ///
+    /// - _Return_ : Index of max element. If there are some max elements, the first index. If `N` is `0`, `0`.
+    #[inline]
+    pub fn argmax(&self) -> usize {
+        let mut ret: usize = 0;
+
+        for i in 1..N {
+            if self.body[i] > self.body[ret] {
+                ret = i;
+            }
+        }
+
+        ret
+    }
+
+    fn write_body(&self, buffer: &mut Vec<u8>) {
+        write_u32(buffer, N as u32);
+
+        self.body.iter().for_each(|val| {write_f32(buffer, *val);});
+    }
+
+    fn read_body(
+        &mut self,
+        reader: &mut ByteReader
+    ) -> Result<(), ChobitAiError> {
+        let len = reader.read_u32()? as usize;
+
+        if len != N {
+            return Err(ChobitAiError::DimensionMismatch {
+                name: "vector",
+                expected: N,
+                found: len
+            });
+        }
+
+        for val in self.body.iter_mut() {
+            *val = reader.read_f32()?;
+        }
+
+        Ok(())
+    }
 }
 
 unsafe impl<const N: usize> Send for MathVec<N> {}
@@ -967,15 +1751,7 @@ impl<const N: usize> Mul<&MathVec<N>> for &MathVec<N> {
 
     #[inline]
     fn mul(self, other: &MathVec<N>) -> f32 {
-        let mut ret = 0.0;
-
-        for i in 0..N {
-            ret += unsafe {
-                *self.body.get_unchecked(i) * *other.body.get_unchecked(i)
-            };
-        }
-
-        ret
+        add_dot(0.0, &self.body, &other.body)
     }
 }
 
@@ -1148,6 +1924,9 @@ impl MathVec<128> {
     }
 }
 
+// Number of samples calculated together by batch functions of Weights.
+const BATCH_BLOCK: usize = 8;
+
 /// Weights of a linear function.
 ///
 /// | Formula |
@@ -1301,6 +2080,37 @@ impl<const OUT: usize, const IN: usize> Weights<OUT, IN> {
     #[inline]
     pub fn clear(&mut self) {self.body.fill(f32::default());}
 
+    /// Prunes weights by magnitude.
+    ///
+    /// Pruned weights become `0.0`. Bias is not pruned.
+    ///
+    /// - `pruning` : Way of pruning.
+    /// - _Return_ : Number of pruned weights.
+    pub fn prune(&mut self, pruning: Pruning) -> usize {
+        match pruning.threshold(self.non_bias().iter()) {
+            Some(threshold) => {
+                self.non_bias_mut().iter_mut().fold(0, |count, weight| {
+                    if abs(*weight) <= threshold {
+                        *weight = 0.0;
+
+                        count + 1
+                    } else {
+                        count
+                    }
+                })
+            },
+
+            None => 0
+        }
+    }
+
+    // Weights for input and weights for state.
+    #[inline]
+    fn non_bias(&self) -> &[f32] {&self.body[OUT..]}
+
+    #[inline]
+    fn non_bias_mut(&mut self) -> &mut [f32] {&mut self.body[OUT..]}
+
     /// Gets immutable bias.
     ///
     /// - _Return_ : bias.
@@ -1379,9 +2189,7 @@ impl<const OUT: usize, const IN: usize> Weights<OUT, IN> {
         self.input_weights().iter().zip(
             output.as_mut_array().iter_mut()
         ).for_each(|(weights, output_one)| {
-            weights.iter().zip(input.as_array().iter()).for_each(
-                |(w, i)| {*output_one += *w * *i;}
-            );
+            *output_one = add_dot(*output_one, weights, input);
         });
     }
 
@@ -1391,9 +2199,7 @@ impl<const OUT: usize, const IN: usize> Weights<OUT, IN> {
             state_weights.iter().zip(
                 output.as_mut_array().iter_mut()
             ).for_each(|(weights, output_one)| {
-                weights.iter().zip(state.as_array().iter()).for_each(
-                    |(w, s)| {*output_one += *w * *s;}
-                );
+                *output_one = add_dot(*output_one, weights, state);
             });
         }
     }
@@ -1417,11 +2223,7 @@ impl<const OUT: usize, const IN: usize> Weights<OUT, IN> {
         self.input_weights().iter().zip(
             coefficient.as_array().iter()
         ).for_each(
-            |(weights, c)| {
-                weights.iter().zip(grad.as_mut_array().iter_mut()).for_each(
-                    |(w, g)| {*g += *c * *w;}
-                );
-            }
+            |(weights, c)| {add_scaled(grad, *c, weights);}
         );
     }
 
@@ -1445,11 +2247,7 @@ impl<const OUT: usize, const IN: usize> Weights<OUT, IN> {
             state_weights.iter().zip(
                 coefficient.as_array().iter()
             ).for_each(
-                |(weights, c)| {
-                    weights.iter().zip(grad.as_mut_array().iter_mut()).for_each(
-                        |(w, g)| {*g += *c * *w;}
-                    );
-                }
+                |(weights, c)| {add_scaled(grad, *c, weights);}
             );
         }
     }
@@ -1495,11 +2293,7 @@ impl<const OUT: usize, const IN: usize> Weights<OUT, IN> {
         grad.input_weights_mut().iter_mut().zip(
             coefficient.as_array().iter()
         ).for_each(
-            |(grad_i, c)| {
-                grad_i.iter_mut().zip(input.as_array().iter()).for_each(
-                    |(g, i)| {*g += *c * *i;}
-                );
-            }
+            |(grad_i, c)| {add_scaled(grad_i, *c, input);}
         );
     }
 
@@ -1513,39 +2307,270 @@ impl<const OUT: usize, const IN: usize> Weights<OUT, IN> {
             state_weights.iter_mut().zip(
                 coefficient.as_array().iter()
             ).for_each(
-                |(grad_s, c)| {
-                    grad_s.iter_mut().zip(state.as_array().iter()).for_each(
-                        |(g, i)| {*g += *c * *i;}
-                    );
-                }
+                |(grad_s, c)| {add_scaled(grad_s, *c, state);}
             );
         }
     }
 
-    /// Copies from other weights.
+    /// Calculates linear function for each sample of batch.
     ///
-    /// - `other` : Other weights.
-    #[inline]
-    pub fn copy_from(&mut self, other: &Self) {
-        let len = self.body.len().min(other.body.len());
+    /// Result of each sample is the same as [`calc()`](Self::calc()). Samples are calculated in small blocks, so each row of weights is used for several samples while it is in cache.
+    ///
+    /// - `inputs` : Inputs.
+    /// - `states` : States for RNN. Each state is for input of the same index.
+    /// - `outputs` : Buffer for outputs. Batch size is the shorter of `inputs` and `outputs`.
+    pub fn calc_batch(
+        &self,
+        inputs: &[MathVec<IN>],
+        states: Option<&[MathVec<OUT>]>,
+        outputs: &mut [MathVec<OUT>]
+    ) {
+        let len = inputs.len().min(outputs.len());
+        let outputs = &mut outputs[..len];
 
-        self.body[..len].copy_from_slice(&other[..len])
-    }
+        outputs.iter_mut().for_each(
+            |output| {self.init_output_with_bias(output);}
+        );
 
-    /// Copies to other weights.
-    ///
-    /// - `other` : Other weights.
-    #[inline]
-    pub fn copy_to(&self, other: &mut Self) {
-        other.copy_from(self);
+        Self::add_product_batch(self.input_weights(), inputs, outputs);
+
+        if let (Some(states), Some(state_weights)) =
+            (states, self.state_weights())
+        {
+            Self::add_product_batch(state_weights, states, outputs);
+        }
     }
-}
 
-unsafe impl<const OUT: usize, const IN: usize> Sync for Weights<OUT, IN> {}
-unsafe impl<const OUT: usize, const IN: usize> Send for Weights<OUT, IN> {}
+    // Adds `matrix * vector` to each output. Each element is accumulated in
+    // the same order as calc_input() and calc_state(). Vectors of a block are
+    // transposed, so the inner loop calculates all samples of the block.
+    // With SIMD, add_dot() is used for each sample instead.
+    fn add_product_batch<const N: usize>(
+        matrix: &[[f32; N]; OUT],
+        vectors: &[MathVec<N>],
+        outputs: &mut [MathVec<OUT>]
+    ) {
+        if simd::ENABLED {
+            vectors.chunks(BATCH_BLOCK).zip(
+                outputs.chunks_mut(BATCH_BLOCK)
+            ).for_each(|(vectors, outputs)| {
+                matrix.iter().enumerate().for_each(|(i, row)| {
+                    vectors.iter().zip(outputs.iter_mut()).for_each(
+                        |(vector, output)| {
+                            output[i] = add_dot(output[i], row, vector);
+                        }
+                    );
+                });
+            });
 
-impl<const OUT: usize, const IN: usize> Clone for Weights<OUT, IN> {
-    #[inline]
+            return;
+        }
+
+        let mut block = vec![f32::default(); N * BATCH_BLOCK];
+
+        vectors.chunks(BATCH_BLOCK).zip(
+            outputs.chunks_mut(BATCH_BLOCK)
+        ).for_each(|(vectors, outputs)| {
+            vectors.iter().enumerate().for_each(|(k, vector)| {
+                vector.iter().zip(block.iter_mut().skip(k).step_by(
+                    BATCH_BLOCK
+                )).for_each(|(x, b)| {*b = *x;});
+            });
+
+            matrix.iter().enumerate().for_each(|(i, row)| {
+                let mut sums = [f32::default(); BATCH_BLOCK];
+
+                sums.iter_mut().zip(outputs.iter()).for_each(
+                    |(sum, output)| {*sum = output[i];}
+                );
+
+                row.iter().zip(block.chunks_exact(BATCH_BLOCK)).for_each(
+                    |(w, xs)| {
+                        sums.iter_mut().zip(xs.iter()).for_each(
+                            |(sum, x)| {*sum += *w * *x;}
+                        );
+                    }
+                );
+
+                sums.iter().zip(outputs.iter_mut()).for_each(
+                    |(sum, output)| {output[i] = *sum;}
+                );
+            });
+        });
+    }
+
+    /// Calculates gradient with respect to input for each sample of batch.
+    ///
+    /// Result of each sample is the same as [`grad_with_input()`](Self::grad_with_input()).
+    ///
+    /// - `coefficients` : Coefficients.
+    /// - `grads` : Buffer for output. Batch size is the shorter of `coefficients` and `grads`.
+    #[inline]
+    pub fn grad_with_input_batch(
+        &self,
+        coefficients: &[MathVec<OUT>],
+        grads: &mut [MathVec<IN>]
+    ) {
+        Self::transposed_product_batch(
+            self.input_weights(),
+            coefficients,
+            grads
+        );
+    }
+
+    /// Calculates gradient with respect to state for each sample of batch.
+    ///
+    /// Result of each sample is the same as [`grad_with_state()`](Self::grad_with_state()).
+    ///
+    /// - `coefficients` : Coefficients.
+    /// - `grads` : Buffer for output. Batch size is the shorter of `coefficients` and `grads`.
+    pub fn grad_with_state_batch(
+        &self,
+        coefficients: &[MathVec<OUT>],
+        grads: &mut [MathVec<OUT>]
+    ) {
+        match self.state_weights() {
+            Some(state_weights) => Self::transposed_product_batch(
+                state_weights,
+                coefficients,
+                grads
+            ),
+
+            None => {
+                let len = coefficients.len().min(grads.len());
+
+                grads[..len].iter_mut().for_each(|grad| {grad.clear();});
+            }
+        }
+    }
+
+    // Writes `transpose(matrix) * coefficient` into each grad. Each element is
+    // accumulated in the same order as grad_with_input().
+    fn transposed_product_batch<const N: usize>(
+        matrix: &[[f32; N]; OUT],
+        coefficients: &[MathVec<OUT>],
+        grads: &mut [MathVec<N>]
+    ) {
+        let len = coefficients.len().min(grads.len());
+
+        coefficients[..len].chunks(BATCH_BLOCK).zip(
+            grads[..len].chunks_mut(BATCH_BLOCK)
+        ).for_each(|(coefficients, grads)| {
+            grads.iter_mut().for_each(|grad| {grad.clear();});
+
+            matrix.iter().enumerate().for_each(|(i, row)| {
+                coefficients.iter().zip(grads.iter_mut()).for_each(
+                    |(coefficient, grad)| {
+                        add_scaled(grad, coefficient[i], row);
+                    }
+                );
+            });
+        });
+    }
+
+    /// Adds gradient with respect to weights of each sample of batch into `grad`.
+    ///
+    /// Unlike [`grad_with_weights()`](Self::grad_with_weights()), `grad` is not cleared. Gradients are added in order of samples, so the result is the same as adding result of [`grad_with_weights()`](Self::grad_with_weights()) of each sample one by one.
+    ///
+    /// - `coefficients` : Coefficients.
+    /// - `inputs` : Inputs.
+    /// - `states` : States for RNN.
+    /// - `grad` : Gradient to be added to. Batch size is the shorter of `coefficients` and `inputs`.
+    pub fn add_grad_with_weights_batch(
+        coefficients: &[MathVec<OUT>],
+        inputs: &[MathVec<IN>],
+        states: Option<&[MathVec<OUT>]>,
+        grad: &mut Self
+    ) {
+        let len = coefficients.len().min(inputs.len());
+        let coefficients = &coefficients[..len];
+
+        coefficients.iter().for_each(|coefficient| {
+            grad.bias_mut().iter_mut().zip(coefficient.iter()).for_each(
+                |(g, c)| {*g += *c;}
+            );
+        });
+
+        Self::add_outer_product_batch(
+            grad.input_weights_mut(),
+            coefficients,
+            inputs
+        );
+
+        if let (Some(states), Some(state_weights)) =
+            (states, grad.state_weights_mut())
+        {
+            Self::add_outer_product_batch(state_weights, coefficients, states);
+        }
+    }
+
+    // Adds `coefficient * transpose(vector)` of each sample into matrix. Each
+    // row is kept in cache while all samples are added.
+    fn add_outer_product_batch<const N: usize>(
+        matrix: &mut [[f32; N]; OUT],
+        coefficients: &[MathVec<OUT>],
+        vectors: &[MathVec<N>]
+    ) {
+        matrix.iter_mut().enumerate().for_each(|(i, row)| {
+            coefficients.iter().zip(vectors.iter()).for_each(
+                |(coefficient, vector)| {
+                    add_scaled(row, coefficient[i], vector);
+                }
+            );
+        });
+    }
+
+    /// Copies from other weights.
+    ///
+    /// - `other` : Other weights.
+    #[inline]
+    pub fn copy_from(&mut self, other: &Self) {
+        let len = self.body.len().min(other.body.len());
+
+        self.body[..len].copy_from_slice(&other[..len])
+    }
+
+    /// Copies to other weights.
+    ///
+    /// - `other` : Other weights.
+    #[inline]
+    pub fn copy_to(&self, other: &mut Self) {
+        other.copy_from(self);
+    }
+
+    fn write_body(&self, buffer: &mut Vec<u8>) {
+        write_u32(buffer, self.body.len() as u32);
+
+        self.body.iter().for_each(|val| {write_f32(buffer, *val);});
+    }
+
+    fn read_body(
+        &mut self,
+        reader: &mut ByteReader
+    ) -> Result<(), ChobitAiError> {
+        let len = reader.read_u32()? as usize;
+
+        if len != self.body.len() {
+            return Err(ChobitAiError::DimensionMismatch {
+                name: "weights",
+                expected: self.body.len(),
+                found: len
+            });
+        }
+
+        for val in self.body.iter_mut() {
+            *val = reader.read_f32()?;
+        }
+
+        Ok(())
+    }
+}
+
+unsafe impl<const OUT: usize, const IN: usize> Sync for Weights<OUT, IN> {}
+unsafe impl<const OUT: usize, const IN: usize> Send for Weights<OUT, IN> {}
+
+impl<const OUT: usize, const IN: usize> Clone for Weights<OUT, IN> {
+    #[inline]
     fn clone(&self) -> Self {
         let mut ret = Self::new(self.ptr_s.is_some());
 
@@ -1587,7 +2612,52 @@ pub enum Activation {
     SoftSign,
 
     /// Sigmoid : `(-inf, +inf) -> (0.0, +1.0)`
-    Sigmoid
+    ///
+    /// This is `(softsign(x) + 1) / 2`, not logistic function.
+    Sigmoid,
+
+    /// Leaky ReLU : `(-inf, +inf) -> (-inf, +inf)`
+    ///
+    /// `x` if `x > 0`, otherwise `slope * x`. (Slope is fixed. See [`Activation::PReLU`] for learnable slope.)
+    ///
+    /// - `0` : Slope for negative input.
+    LeakyReLU(f32),
+
+    /// PReLU : `(-inf, +inf) -> (-inf, +inf)`
+    ///
+    /// The same as [`Activation::LeakyReLU`], but [`MlLayer`] learns the slope shared by all outputs of the layer. The slope is a part of weights, e.g. [`ChobitAi::for_each_weight()`], and its gradient is a part of total gradients, e.g. [`MlLayer::slope_grad()`] and [`ChobitMlAi::for_each_total_grad()`]. Both are accessed after weights of the layer, so they are in the same order.
+    /// Other Ml types that use activation functions without [`MlLayer`] treat the slope as fixed.
+    ///
+    /// - `0` : Slope for negative input.
+    PReLU(f32),
+
+    /// ELU : `(-inf, +inf) -> (-alpha, +inf)`
+    ///
+    /// `x` if `x > 0`, otherwise `alpha * (exp(x) - 1)`.
+    ///
+    /// - `0` : Alpha.
+    ELU(f32),
+
+    /// GELU (tanh approximation) : `(-inf, +inf) -> (-0.17, +inf)`
+    GELU,
+
+    /// Swish (SiLU) : `(-inf, +inf) -> (-0.28, +inf)`
+    ///
+    /// `x * logistic(x)`
+    Swish,
+
+    /// Hyperbolic tangent : `(-inf, +inf) -> (-1.0, +1.0)`
+    Tanh,
+
+    /// Hard sigmoid : `(-inf, +inf) -> [0.0, +1.0]`
+    ///
+    /// `clamp(x / 6 + 1 / 2, 0, 1)`
+    HardSigmoid,
+
+    /// Hard tanh : `(-inf, +inf) -> [-1.0, +1.0]`
+    ///
+    /// `clamp(x, -1, 1)`
+    HardTanh
 }
 
 impl Activation {
@@ -1601,7 +2671,16 @@ impl Activation {
             Self::Linear => x,
             Self::ReLU => x.max(0.0),
             Self::SoftSign => Self::softsign(x),
-            Self::Sigmoid => Self::sigmoid(x)
+            Self::Sigmoid => Self::sigmoid(x),
+            Self::LeakyReLU(slope) | Self::PReLU(slope) => {
+                if x > 0.0 {x} else {*slope * x}
+            },
+            Self::ELU(alpha) => if x > 0.0 {x} else {*alpha * (exp(x) - 1.0)},
+            Self::GELU => Self::gelu(x),
+            Self::Swish => x * Self::logistic(x),
+            Self::Tanh => Self::tanh(x),
+            Self::HardSigmoid => ((x / 6.0) + 0.5).clamp(0.0, 1.0),
+            Self::HardTanh => x.clamp(-1.0, 1.0)
         }
     }
 
@@ -1618,7 +2697,40 @@ impl Activation {
 
             Self::SoftSign => Self::d_softsign(x),
 
-            Self::Sigmoid => Self::d_sigmoid(x)
+            Self::Sigmoid => Self::d_sigmoid(x),
+
+            Self::LeakyReLU(slope) | Self::PReLU(slope) => {
+                if x > 0.0 {1.0} else {*slope}
+            },
+
+            Self::ELU(alpha) => if x > 0.0 {1.0} else {*alpha * exp(x)},
+
+            Self::GELU => Self::d_gelu(x),
+
+            Self::Swish => {
+                let sig = Self::logistic(x);
+                sig + (x * sig * (1.0 - sig))
+            },
+
+            Self::Tanh => {
+                let tanh = Self::tanh(x);
+                1.0 - (tanh * tanh)
+            },
+
+            Self::HardSigmoid => {
+                if (-3.0 < x) && (x < 3.0) {1.0 / 6.0} else {0.0}
+            },
+
+            Self::HardTanh => if (-1.0 < x) && (x < 1.0) {1.0} else {0.0}
+        }
+    }
+
+    // Derivative by learnable parameter. (Slope of PReLU)
+    #[inline]
+    fn d_slope(&self, x: f32) -> f32 {
+        match self {
+            Self::PReLU(_) => if x > 0.0 {0.0} else {x},
+            _ => 0.0
         }
     }
 
@@ -1647,168 +2759,447 @@ impl Activation {
     fn d_sigmoid(x: f32) -> f32 {
         Self::d_softsign(x) * 0.5
     }
-}
-
-/// Layer for neural network only for calculating.
-///
-/// | Formula |
-/// |:-:|
-/// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mrow> <msub> <mi>y</mi> <mi>i</mi> </msub> <mo stretchy="false">=</mo> <mi>φ</mi> </mrow> <mrow> <mo fence="true" form="prefix" stretchy="true">(</mo> <mrow> <mrow> <mrow> <munder> <mo stretchy="false">∑</mo> <mi>j</mi> </munder> <msub> <mi>W</mi> <mi mathvariant="italic">ij</mi> </msub> </mrow> <mrow> <msub> <mi>x</mi> <mi>j</mi> </msub> <mo stretchy="false">+</mo> <mrow> <munder> <mo stretchy="false">∑</mo> <mi>k</mi> </munder> <msub> <mi>U</mi> <mi mathvariant="italic">ik</mi> </msub> </mrow> </mrow> <mrow> <msub> <mi>s</mi> <mi>k</mi> </msub> <mo stretchy="false">+</mo> <msub> <mi>b</mi> <mi>i</mi> </msub> </mrow> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="true">)</mo> </mrow> </mrow> </semantics> </math> |
-/// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mtable columnalign="left"> <mtr> <mtd> <mrow> <mi>i</mi> <mo stretchy="false">≝</mo> <mtext>Index of output.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>j</mi> <mo stretchy="false">≝</mo> <mtext>Index of input.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mrow> <mi>k</mi> <mo stretchy="false">≝</mo> <mtext>Index of state.</mtext> </mrow> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <mrow> <mtext>dim</mtext> <mspace width="0.5em"/> <mrow> <mi>k</mi> <mo stretchy="false">=</mo> <mtext>dim</mtext> </mrow> <mspace width="0.5em"/> <mi>i</mi> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msub> <mi>y</mi> <mi>i</mi> </msub> <mo stretchy="false">≝</mo> <mtext>Output.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>φ</mi> <mo stretchy="false">≝</mo> <mtext>Activation functin.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msub> <mi>W</mi> <mi mathvariant="italic">ij</mi> </msub> <mo stretchy="false">≝</mo> <mtext>Weights for input.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msub> <mi>x</mi> <mi>j</mi> </msub> <mo stretchy="false">≝</mo> <mtext>Input.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msub> <mi>U</mi> <mi mathvariant="italic">ik</mi> </msub> <mo stretchy="false">≝</mo> <mtext>Weights for state.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msub> <mi>s</mi> <mi>k</mi> </msub> <mo stretchy="false">≝</mo> <mtext>State.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msub> <mi>b</mi> <mi>i</mi> </msub> <mo stretchy="false">≝</mo> <mtext>Bias.</mtext> </mrow> </mtd> </mtr> </mtable> </semantics> </math> ||
-///
-/// - `OUT` : Dimension of output.
-/// - `IN` : Dimension of input.
-#[derive(Debug, Clone, PartialEq)]
-pub struct Layer<const OUT: usize, const IN: usize> {
-    weights: Weights<OUT, IN>,
-    activation: Activation
-}
 
-impl<const OUT: usize, const IN: usize> Layer<OUT, IN> {
-    /// Creates Layer.
-    ///
-    /// - `activation` : Activation function.
-    /// - `accept_state` : If `true`, this accepts state.
-    /// - _Return_ : Neuron.
     #[inline]
-    pub fn new(activation: Activation, accept_state: bool) -> Self {
-        Self {
-            weights: Weights::<OUT, IN>::new(accept_state),
-            activation: activation
-        }
+    fn logistic(x: f32) -> f32 {
+        (1.0 + exp(-x)).recip()
     }
 
-    /// Gets immutable weights.
-    ///
-    /// - _Return_ : Weights.
     #[inline]
-    pub fn weights(&self) -> &Weights<OUT, IN> {&self.weights}
+    fn tanh(x: f32) -> f32 {
+        1.0 - (2.0 / (exp(2.0 * x) + 1.0))
+    }
 
-    /// Gets mutable weights.
-    ///
-    /// - _Return_ : Weights.
-    #[inline]
-    pub fn mut_weights(&mut self) -> &mut Weights<OUT, IN> {&mut self.weights}
+    const GELU_K: f32 = 0.797_884_6;  // sqrt(2 / PI)
+    const GELU_A: f32 = 0.044715;
 
-    /// Gets immutable activation function.
-    ///
-    /// - _Return_ : Weights.
     #[inline]
-    pub fn activation(&self) -> &Activation {&self.activation}
+    fn gelu(x: f32) -> f32 {
+        let inner = Self::GELU_K * (x + (Self::GELU_A * x * x * x));
 
-    /// Gets mutable activation function.
-    ///
-    /// - _Return_ : Weights.
-    #[inline]
-    pub fn mut_activation(&mut self) -> &mut Activation {&mut self.activation}
+        0.5 * x * (1.0 + Self::tanh(inner))
+    }
 
-    /// Calculates neural network layer.
-    ///
-    /// - `input` : Input.
-    /// - `state` : State if it exists.
-    /// - `output` : Buffer for output.
     #[inline]
-    pub fn calc(
-        &self,
-        input: &MathVec<IN>,
-        state: Option<&MathVec<OUT>>,
-        output: &mut MathVec<OUT>
-    ) {
-        self.weights.calc(input, state, output);
+    fn d_gelu(x: f32) -> f32 {
+        let inner = Self::GELU_K * (x + (Self::GELU_A * x * x * x));
+        let tanh = Self::tanh(inner);
 
-        output.iter_mut().for_each(
-            |val| {*val = self.activation.activate(*val);}
-        );
-    }
-}
+        let d_inner = Self::GELU_K * (1.0 + (3.0 * Self::GELU_A * x * x));
 
-/// Cache for [`MlLayer`].
-///
-/// - `OUT` : `OUT` of [`MlLayer`].
-/// - `IN` : `IN` of [`MlLayer`].
-#[derive(Debug, Clone, PartialEq)]
-pub struct MlCache<const OUT: usize, const IN: usize> {
-    input: MathVec<IN>,
-    state: MathVec<OUT>,
-    has_state: bool,
+        (0.5 * (1.0 + tanh)) + (0.5 * x * (1.0 - (tanh * tanh)) * d_inner)
+    }
 
-    middle_value: MathVec<OUT>,
+    fn write(&self, buffer: &mut Vec<u8>) {
+        let (tag, param) = match self {
+            Self::Linear => (0, 0.0),
+            Self::ReLU => (1, 0.0),
+            Self::SoftSign => (2, 0.0),
+            Self::Sigmoid => (3, 0.0),
+            Self::LeakyReLU(slope) => (4, *slope),
+            Self::ELU(alpha) => (5, *alpha),
+            Self::GELU => (6, 0.0),
+            Self::Swish => (7, 0.0),
+            Self::Tanh => (8, 0.0),
+            Self::HardSigmoid => (9, 0.0),
+            Self::HardTanh => (10, 0.0),
+            Self::PReLU(slope) => (11, *slope)
+        };
+
+        write_u8(buffer, tag);
+        write_f32(buffer, param);
+    }
 
-    d_activate: MathVec<OUT>,
-    output: MathVec<OUT>
+    fn read(reader: &mut ByteReader) -> Result<Self, ChobitAiError> {
+        let tag = reader.read_u8()?;
+        let param = reader.read_f32()?;
+
+        match tag {
+            0 => Ok(Self::Linear),
+            1 => Ok(Self::ReLU),
+            2 => Ok(Self::SoftSign),
+            3 => Ok(Self::Sigmoid),
+            4 => Ok(Self::LeakyReLU(param)),
+            5 => Ok(Self::ELU(param)),
+            6 => Ok(Self::GELU),
+            7 => Ok(Self::Swish),
+            8 => Ok(Self::Tanh),
+            9 => Ok(Self::HardSigmoid),
+            10 => Ok(Self::HardTanh),
+            11 => Ok(Self::PReLU(param)),
+            _ => Err(ChobitAiError::UnknownActivation {tag: tag})
+        }
+    }
 }
 
-impl<const OUT: usize, const IN: usize> MlCache<OUT, IN> {
-    /// Creates MlCache.
+/// Loss function for machine learning.
+///
+/// It calculates output error that is passed to `study()` of each Ml type, e.g. [`ChobitMlAi::study()`].
+///
+/// [`Loss::CategoricalCrossEntropy`] and [`Loss::BinaryCrossEntropy`] treat output as logits. So activation function of output layer should be [`Activation::Linear`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Loss {
+    /// Mean squared error.
     ///
-    /// - _Return_ : MlCache.
-    #[inline]
-    pub fn new() -> Self {
-        Self {
-            input: MathVec::<IN>::new(),
-            state: MathVec::<OUT>::new(),
-            has_state: false,
+    /// - Loss : `sum((o - t)^2) / 2`
+    /// - Error : `o - t`
+    ///
+    /// This is the same as `calc_output_error()` of each cache.
+    Mse,
 
-            middle_value: MathVec::<OUT>::new(),
+    /// Huber loss.
+    ///
+    /// - Loss : `(o - t)^2 / 2` if `|o - t| <= delta`, otherwise `delta * (|o - t| - delta / 2)`
+    /// - Error : `clamp(o - t, -delta, delta)`
+    ///
+    /// - `0` : Delta. Negative value is treated as its absolute value. If NaN, this is the same as [`Loss::Mse`].
+    Huber(f32),
 
-            d_activate: MathVec::<OUT>::new(),
-            output: MathVec::<OUT>::new()
-        }
-    }
+    /// Softmax and categorical cross entropy.
+    ///
+    /// Target is probability distribution, e.g. one-hot vector.
+    /// Prediction is `softmax(o)`. (See [`predict()`](Self::predict()), [`MathVec::softmax()`] and [`MathVec::argmax()`].)
+    ///
+    /// - Loss : `-sum(t * ln(softmax(o)))`
+    /// - Error : `softmax(o) - t`
+    CategoricalCrossEntropy,
 
-    /// Calculates output error.
+    /// Logistic function and binary cross entropy of each element.
     ///
-    /// | Formula |
-    /// |:-:|
-    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mi>e</mi> <mo stretchy="false">=</mo> <mrow> <mi>o</mi> <mo stretchy="false">−</mo> <mi>t</mi> </mrow> </mrow> </semantics> </math> |
-    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mtable columnalign="left"> <mtr> <mtd> <mrow> <mi>e</mi> <mo stretchy="false">≝</mo> <mtext>Error.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>o</mi> <mo stretchy="false">≝</mo> <mtext>Actual output.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>t</mi> <mo stretchy="false">≝</mo> <mtext>Correct output.</mtext> </mrow> </mtd> </mtr> </mtable> </semantics> </math> |
+    /// Target is `-1.0` (false) or `1.0` (true) like [`MathVec::load_u8_label()`] writes, and is converted to probability `p = (t + 1) / 2`.
+    /// Prediction of each element is true if `o >= 0`, so [`MathVec::to_u8_label()`] decodes output directly. [`predict()`](Self::predict()) gives `2 * logistic(o) - 1` in the same range as target.
+    ///
+    /// - Loss : `-sum(p * ln(logistic(o)) + (1 - p) * ln(1 - logistic(o)))`
+    /// - Error : `logistic(o) - p`
+    BinaryCrossEntropy
+}
+
+impl Loss {
+    /// Calculates output error.
     ///
+    /// - `output` : Actual output.
     /// - `train_out` : Correct output.
     /// - `output_error` : Buffer for output error.
-    #[inline]
-    pub fn calc_output_error(
+    pub fn calc_error<const N: usize>(
         &self,
-        train_out: &MathVec<OUT>,
-        output_error: &mut MathVec<OUT>
+        output: &MathVec<N>,
+        train_out: &MathVec<N>,
+        output_error: &mut MathVec<N>
     ) {
-        output_error.copy_from(&self.output);
-        *output_error -= train_out;
-    }
+        match self {
+            Self::Mse => {
+                output_error.copy_from(output);
+                *output_error -= train_out;
+            },
 
-    /// Gets input.
-    ///
-    /// - _Return_ : Input.
-    #[inline]
-    pub fn input(&self) -> &MathVec<IN> {&self.input}
+            Self::Huber(delta) => {
+                let delta = abs(*delta);
 
-    /// Gets state.
+                output_error.copy_from(output);
+                *output_error -= train_out;
+
+                // Not clamp(), because it panics if delta is NaN.
+                output_error.iter_mut().for_each(|val| {
+                    if *val > delta {
+                        *val = delta;
+                    } else if *val < -delta {
+                        *val = -delta;
+                    }
+                });
+            },
+
+            Self::CategoricalCrossEntropy => {
+                output_error.copy_from(output);
+                output_error.softmax();
+
+                *output_error -= train_out;
+            },
+
+            Self::BinaryCrossEntropy => {
+                output_error.iter_mut().zip(
+                    output.iter().zip(train_out.iter())
+                ).for_each(|(error, (o, t))| {
+                    *error = (1.0 + exp(-*o)).recip() - ((*t + 1.0) * 0.5);
+                });
+            }
+        }
+    }
+
+    /// Calculates value of loss.
     ///
-    /// - _Return_ : State.
-    #[inline]
-    pub fn state(&self) -> Option<&MathVec<OUT>> {
-        self.has_state.then(|| &self.state)
+    /// - `output` : Actual output.
+    /// - `train_out` : Correct output.
+    /// - _Return_ : Loss.
+    pub fn calc_loss<const N: usize>(
+        &self,
+        output: &MathVec<N>,
+        train_out: &MathVec<N>
+    ) -> f32 {
+        match self {
+            Self::Mse => output.iter().zip(train_out.iter()).fold(
+                0.0,
+                |sum, (o, t)| sum + ((*o - *t) * (*o - *t) * 0.5)
+            ),
+
+            Self::Huber(delta) => {
+                let delta = abs(*delta);
+
+                output.iter().zip(train_out.iter()).fold(0.0, |sum, (o, t)| {
+                    let diff = abs(*o - *t);
+
+                    sum + if diff > delta {
+                        delta * (diff - (delta * 0.5))
+                    } else {
+                        diff * diff * 0.5
+                    }
+                })
+            },
+
+            Self::CategoricalCrossEntropy => {
+                let max = output.iter().fold(
+                    f32::NEG_INFINITY,
+                    |a, b| a.max(*b)
+                );
+
+                let log_sum_exp = max + ln(
+                    output.iter().fold(0.0, |sum, o| sum + exp(*o - max))
+                );
+
+                output.iter().zip(train_out.iter()).fold(
+                    0.0,
+                    |sum, (o, t)| sum - (*t * (*o - log_sum_exp))
+                )
+            },
+
+            Self::BinaryCrossEntropy => {
+                output.iter().zip(train_out.iter()).fold(0.0, |sum, (o, t)| {
+                    let p = (*t + 1.0) * 0.5;
+
+                    // softplus(o) - p * o
+                    let softplus = o.max(0.0) + ln(1.0 + exp(-abs(*o)));
+
+                    sum + softplus - (p * *o)
+                })
+            }
+        }
     }
 
-    /// Gets middle value. (Output before activate.)
+    /// Converts output into prediction in the same form as correct output.
     ///
-    /// - _Return_ : Middle value.
-    #[inline]
-    pub fn middle_value(&self) -> &MathVec<OUT> {&self.middle_value}
+    /// Inference with this gives the same output as machine learning with this loss function assumes.
+    ///
+    /// | Loss | Prediction |
+    /// |:-:|:-:|
+    /// | [`Loss::Mse`], [`Loss::Huber`] | `o` |
+    /// | [`Loss::CategoricalCrossEntropy`] | `softmax(o)` |
+    /// | [`Loss::BinaryCrossEntropy`] | `2 * logistic(o) - 1` |
+    ///
+    /// - `output` : Output. It is overwritten by prediction.
+    pub fn predict<const N: usize>(&self, output: &mut MathVec<N>) {
+        match self {
+            Self::Mse | Self::Huber(_) => (),
 
-    /// Gets derivative of activation function.
+            Self::CategoricalCrossEntropy => output.softmax(),
+
+            Self::BinaryCrossEntropy => output.iter_mut().for_each(|o| {
+                *o = (2.0 * (1.0 + exp(-*o)).recip()) - 1.0;
+            })
+        }
+    }
+}
+
+#[inline]
+fn accurate_sqrt(x: f32) -> f32 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+
+    let y = sqrt(x);
+
+    // Newton's method.
+    0.5 * (y + (x / y))
+}
+
+/// Initializer of weights.
+///
+/// Bias is initialized to `0.0`.
+///
+/// Random numbers are given by closure that returns uniform random number in `[0.0, 1.0)`. For example, `|| rng.next_f64() as f32` with [`ChobitRand`](crate::chobit_rand::ChobitRand).
+///
+/// ```ignore
+/// use chobitlibs::chobit_ai::{Activation, Layer, Initializer};
+/// use chobitlibs::chobit_rand::ChobitRand;
+///
+/// let mut rng = ChobitRand::new(b"seed");
+///
+/// let mut layer = Layer::<32, 16>::new(Activation::ReLU, false);
+/// layer.init_weights(Initializer::HeNormal, || rng.next_f64() as f32);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Initializer {
+    /// Xavier (Glorot) uniform : `U(-a, a)`, `a = sqrt(6 / (fan_in + fan_out))`
+    XavierUniform,
+
+    /// Xavier (Glorot) normal : `N(0, s^2)`, `s = sqrt(2 / (fan_in + fan_out))`
+    XavierNormal,
+
+    /// He (Kaiming) uniform : `U(-a, a)`, `a = sqrt(6 / fan_in)`
+    HeUniform,
+
+    /// He (Kaiming) normal : `N(0, s^2)`, `s = sqrt(2 / fan_in)`
+    HeNormal,
+
+    /// Orthogonal matrix made from normal random matrix by Gram-Schmidt process.
     ///
-    /// - _Return_ : Derivative of activation function.
-    #[inline]
-    pub fn d_activate(&self) -> &MathVec<OUT> {&self.d_activate}
+    /// If the matrix is not square, its rows or its columns, whichever fewer, are orthonormal.
+    Orthogonal
+}
 
-    /// Gets output. (Output after activate.)
+impl Initializer {
+    /// Initializes weights.
     ///
-    /// - _Return_ : Output.
+    /// Input weights and state weights are initialized separately as matrices of `OUT x IN` and `OUT x OUT`.
+    ///
+    /// - `weights` : Weights.
+    /// - `rand` : Closure that returns uniform random number in `[0.0, 1.0)`.
+    pub fn init<const OUT: usize, const IN: usize, F>(
+        &self,
+        weights: &mut Weights<OUT, IN>,
+        mut rand: F
+    ) where F: FnMut() -> f32 {
+        weights.bias_mut().fill(0.0);
+
+        self.init_matrix(
+            weights.input_weights_mut().as_flattened_mut(),
+            OUT,
+            IN,
+            &mut rand
+        );
+
+        self.init_state(weights, rand);
+    }
+
+    /// Initializes only state weights.
+    ///
+    /// Nothing is done if weights don't have state weights.
+    ///
+    /// - `weights` : Weights.
+    /// - `rand` : Closure that returns uniform random number in `[0.0, 1.0)`.
+    pub fn init_state<const OUT: usize, const IN: usize, F>(
+        &self,
+        weights: &mut Weights<OUT, IN>,
+        mut rand: F
+    ) where F: FnMut() -> f32 {
+        if let Some(state_weights) = weights.state_weights_mut() {
+            self.init_matrix(
+                state_weights.as_flattened_mut(),
+                OUT,
+                OUT,
+                &mut rand
+            );
+        }
+    }
+
+    fn init_matrix<F>(
+        &self,
+        matrix: &mut [f32],
+        rows: usize,
+        cols: usize,
+        rand: &mut F
+    ) where F: FnMut() -> f32 {
+        let fan_in = cols as f32;
+        let fan_out = rows as f32;
+
+        match self {
+            Self::XavierUniform => {
+                let a = accurate_sqrt(6.0 / (fan_in + fan_out));
+                Self::fill_uniform(matrix, a, rand);
+            },
+
+            Self::XavierNormal => {
+                let s = accurate_sqrt(2.0 / (fan_in + fan_out));
+                Self::fill_normal(matrix, s, rand);
+            },
+
+            Self::HeUniform => {
+                let a = accurate_sqrt(6.0 / fan_in);
+                Self::fill_uniform(matrix, a, rand);
+            },
+
+            Self::HeNormal => {
+                let s = accurate_sqrt(2.0 / fan_in);
+                Self::fill_normal(matrix, s, rand);
+            },
+
+            Self::Orthogonal => {
+                Self::fill_normal(matrix, 1.0, rand);
+                Self::orthonormalize(matrix, rows, cols);
+            }
+        }
+    }
+
     #[inline]
-    pub fn output(&self) -> &MathVec<OUT> {&self.output}
+    fn fill_uniform<F>(matrix: &mut [f32], a: f32, rand: &mut F)
+    where F: FnMut() -> f32 {
+        matrix.iter_mut().for_each(|val| {
+            *val = ((rand() * 2.0) - 1.0) * a;
+        });
+    }
+
+    #[inline]
+    fn fill_normal<F>(matrix: &mut [f32], s: f32, rand: &mut F)
+    where F: FnMut() -> f32 {
+        const TAU: f32 = core::f32::consts::TAU;
+
+        // Box-Muller transform.
+        matrix.iter_mut().for_each(|val| {
+            let u_1 = 1.0 - rand();  // (0.0, 1.0]
+            let u_2 = rand();
+
+            *val = accurate_sqrt(-2.0 * ln(u_1)) * cos(TAU * u_2) * s;
+        });
+    }
+
+    fn orthonormalize(matrix: &mut [f32], rows: usize, cols: usize) {
+        // Vectors to be orthonormalized are rows if rows <= cols,
+        // otherwise columns.
+        let (count, len, stride, step) = if rows <= cols {
+            (rows, cols, cols, 1)
+        } else {
+            (cols, rows, 1, cols)
+        };
+
+        let at = |vec: usize, i: usize| (vec * stride) + (i * step);
+
+        for v in 0..count {
+            for u in 0..v {
+                let mut dot: f32 = 0.0;
+
+                for i in 0..len {
+                    dot += matrix[at(v, i)] * matrix[at(u, i)];
+                }
+
+                for i in 0..len {
+                    matrix[at(v, i)] -= dot * matrix[at(u, i)];
+                }
+            }
+
+            let mut norm: f32 = 0.0;
+
+            for i in 0..len {
+                norm += matrix[at(v, i)] * matrix[at(v, i)];
+            }
+
+            let norm_inv = accurate_sqrt(norm).recip();
+
+            for i in 0..len {
+                matrix[at(v, i)] *= norm_inv;
+            }
+        }
+    }
 }
 
-/// Layer for neural network only for machine learning.
+/// Layer for neural network only for calculating.
 ///
 /// | Formula |
 /// |:-:|
@@ -1818,1536 +3209,2025 @@ impl<const OUT: usize, const IN: usize> MlCache<OUT, IN> {
 /// - `OUT` : Dimension of output.
 /// - `IN` : Dimension of input.
 #[derive(Debug, Clone, PartialEq)]
-pub struct MlLayer<const OUT: usize, const IN: usize> {
-    layer: Layer<OUT, IN>,
-
-    total_grad: Weights<OUT, IN>,
-    momentum_1: Weights<OUT, IN>,
-    momentum_2: MathVec<OUT>,
-
-    tmp_error: MathVec<OUT>,
-    tmp_grad: Weights<OUT, IN>
+pub struct Layer<const OUT: usize, const IN: usize> {
+    weights: Weights<OUT, IN>,
+    activation: Activation
 }
 
-const BETA_1: f32 = 0.9;
-const BETA_INV_1: f32 = 1.0 - BETA_1;
-
-const BETA_2: f32 = 0.999;
-const BETA_INV_2: f32 = 1.0 - BETA_2;
-impl<const OUT: usize, const IN: usize> MlLayer<OUT, IN> {
-    /// Creates MlLayer.
+impl<const OUT: usize, const IN: usize> Layer<OUT, IN> {
+    /// Creates Layer.
     ///
-    /// - `layer` : Base [`Layer`].
-    /// - _Return_ : MlLayer.
+    /// - `activation` : Activation function.
+    /// - `accept_state` : If `true`, this accepts state.
+    /// - _Return_ : Neuron.
     #[inline]
-    pub fn new(layer: Layer<OUT, IN>) -> Self {
-        let has_state_weights = layer.weights.ptr_s.is_some();
-
+    pub fn new(activation: Activation, accept_state: bool) -> Self {
         Self {
-            layer: layer,
-
-            total_grad: Weights::<OUT, IN>::new(has_state_weights),
-            momentum_1: Weights::<OUT, IN>::new(has_state_weights),
-            momentum_2: MathVec::<OUT>::default(),
-
-            tmp_error: MathVec::<OUT>::default(),
-            tmp_grad: Weights::<OUT, IN>::new(has_state_weights)
+            weights: Weights::<OUT, IN>::new(accept_state),
+            activation: activation
         }
     }
 
-    /// Drops Base [`Layer`].
+    /// Gets immutable weights.
     ///
-    /// - _Return_ : [`Layer`].
+    /// - _Return_ : Weights.
     #[inline]
-    pub fn drop(self) -> Layer<OUT, IN> {self.layer}
+    pub fn weights(&self) -> &Weights<OUT, IN> {&self.weights}
 
-    /// Clears internal data for study.
+    /// Gets mutable weights.
+    ///
+    /// - _Return_ : Weights.
     #[inline]
-    pub fn clear_study_data(&mut self) {
-        self.total_grad.clear();
-        self.momentum_1.clear();
-        self.momentum_2.clear();
-    }
+    pub fn mut_weights(&mut self) -> &mut Weights<OUT, IN> {&mut self.weights}
 
-    /// Gets immutable total gradient.
-    ///
-    /// Total gradient is increased every time to call [`study()`](Self::study()).
+    /// Gets immutable activation function.
     ///
-    /// - _Return_ : Total gradient.
+    /// - _Return_ : Weights.
     #[inline]
-    pub fn total_grad(&self) -> &Weights<OUT, IN> {&self.total_grad}
+    pub fn activation(&self) -> &Activation {&self.activation}
 
-    /// Gets mutable total gradient.
+    /// Gets mutable activation function.
     ///
-    /// Total gradient is increased every time to call [`study()`](Self::study()).
+    /// - _Return_ : Weights.
+    #[inline]
+    pub fn mut_activation(&mut self) -> &mut Activation {&mut self.activation}
+
+    /// Initializes weights with [`Initializer`].
     ///
-    /// - _Return_ : Total gradient.
+    /// - `initializer` : Initializer.
+    /// - `rand` : Closure that returns uniform random number in `[0.0, 1.0)`.
     #[inline]
-    pub fn total_grad_mut(&mut self) -> &mut Weights<OUT, IN> {
-        &mut self.total_grad
+    pub fn init_weights<F>(&mut self, initializer: Initializer, rand: F)
+    where F: FnMut() -> f32 {
+        initializer.init(&mut self.weights, rand);
     }
 
-    /// Writes infomation on [`MlCache`] for [`study()`](Self::study()).
+    /// Calculates neural network layer.
     ///
     /// - `input` : Input.
     /// - `state` : State if it exists.
-    /// - `cache` : Cache.
-    pub fn ready(
+    /// - `output` : Buffer for output.
+    #[inline]
+    pub fn calc(
         &self,
         input: &MathVec<IN>,
         state: Option<&MathVec<OUT>>,
-        cache: &mut MlCache<OUT, IN>
+        output: &mut MathVec<OUT>
     ) {
-        cache.input.copy_from(input);
+        self.weights.calc(input, state, output);
 
-        match state {
-            Some(state) => {
-                cache.state.copy_from(state);
-                cache.has_state = true;
-            },
+        output.iter_mut().for_each(
+            |val| {*val = self.activation.activate(*val);}
+        );
+    }
 
-            None => {
-                cache.has_state = false;
-            }
-        }
+    /// Calculates neural network layer for each sample of batch.
+    ///
+    /// Result of each sample is the same as [`calc()`](Self::calc()). See [`Weights::calc_batch()`] for details.
+    ///
+    /// - `inputs` : Inputs.
+    /// - `states` : States if they exist.
+    /// - `outputs` : Buffer for outputs. Batch size is the shorter of `inputs` and `outputs`.
+    pub fn calc_batch(
+        &self,
+        inputs: &[MathVec<IN>],
+        states: Option<&[MathVec<OUT>]>,
+        outputs: &mut [MathVec<OUT>]
+    ) {
+        self.weights.calc_batch(inputs, states, outputs);
 
-        self.layer.weights.calc(input, state, &mut cache.middle_value);
+        let len = inputs.len().min(outputs.len());
 
-        cache.d_activate.as_mut_array().iter_mut().zip(
-            cache.middle_value.as_array().iter()
-        ).for_each(|(d_f, m_value)| {
-            *d_f = self.layer.activation.d_activate(*m_value);
+        outputs[..len].iter_mut().for_each(|output| {
+            output.iter_mut().for_each(
+                |val| {*val = self.activation.activate(*val);}
+            );
         });
+    }
 
-        cache.output.as_mut_array().iter_mut().zip(
-            cache.middle_value.as_array().iter()
-        ).for_each(|(output_one, m_value)| {
-            *output_one = self.layer.activation.activate(*m_value);
-        });
+    fn write_descriptor(&self, buffer: &mut Vec<u8>) {
+        self.activation.write(buffer);
+        write_u8(buffer, self.weights.ptr_s.is_some() as u8);
     }
 
-    /// Accumulates gradient.
-    ///
-    /// - `output_error` : Backpropagated output error.
-    /// - `next_state_error` : Backpropagated state error if it exists.
-    /// - `cache` : Cache generated by [MlLayer::ready].
-    /// - `input_error` : Error for previous output.
-    /// - `prev_state_error` : Error for previous state.
-    pub fn study(
-        &mut self,
-        output_error: &MathVec<OUT>,
-        next_state_error: Option<&MathVec<OUT>>,
-        cache: &MlCache<OUT, IN>,
-        input_error: &mut MathVec<IN>,
-        prev_state_error: Option<&mut MathVec<OUT>>
-    ) {
-        self.calc_tmp_error(output_error, next_state_error, cache);
+    fn read_descriptor(reader: &mut ByteReader) -> Result<Self, ChobitAiError> {
+        let activation = Activation::read(reader)?;
+        let accept_state = reader.read_u8()? != 0;
 
-        // add self.total_grad ----------
-        Weights::grad_with_weights(
-            &self.tmp_error,
-            &cache.input,
-            cache.has_state.then(|| &cache.state),
-            &mut self.tmp_grad
-        );
+        Ok(Self::new(activation, accept_state))
+    }
+}
 
-        self.total_grad.iter_mut().zip(self.tmp_grad.iter()).for_each(
-            |(total_g, tmp_g)| {
-                *total_g += *tmp_g;
-            }
-        );
+/// Cache for [`MlLayer`].
+///
+/// - `OUT` : `OUT` of [`MlLayer`].
+/// - `IN` : `IN` of [`MlLayer`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlCache<const OUT: usize, const IN: usize> {
+    input: MathVec<IN>,
+    state: MathVec<OUT>,
+    has_state: bool,
 
-        // calc errors ----------
-        self.layer.weights.grad_with_input(&self.tmp_error, input_error);
+    middle_value: MathVec<OUT>,
 
-        if let Some(prev_state_error) = prev_state_error {
-            if cache.has_state {
-                self.layer.weights.grad_with_state(
-                    &self.tmp_error,
-                    prev_state_error
-                );
-            }
+    d_activate: MathVec<OUT>,
+    d_slope: MathVec<OUT>,
+    output: MathVec<OUT>
+}
+
+impl<const OUT: usize, const IN: usize> MlCache<OUT, IN> {
+    /// Creates MlCache.
+    ///
+    /// - _Return_ : MlCache.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            input: MathVec::<IN>::new(),
+            state: MathVec::<OUT>::new(),
+            has_state: false,
+
+            middle_value: MathVec::<OUT>::new(),
+
+            d_activate: MathVec::<OUT>::new(),
+            d_slope: MathVec::<OUT>::new(),
+            output: MathVec::<OUT>::new()
         }
     }
 
+    /// Calculates output error.
+    ///
+    /// | Formula |
+    /// |:-:|
+    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mi>e</mi> <mo stretchy="false">=</mo> <mrow> <mi>o</mi> <mo stretchy="false">−</mo> <mi>t</mi> </mrow> </mrow> </semantics> </math> |
+    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mtable columnalign="left"> <mtr> <mtd> <mrow> <mi>e</mi> <mo stretchy="false">≝</mo> <mtext>Error.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>o</mi> <mo stretchy="false">≝</mo> <mtext>Actual output.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>t</mi> <mo stretchy="false">≝</mo> <mtext>Correct output.</mtext> </mrow> </mtd> </mtr> </mtable> </semantics> </math> |
+    ///
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
     #[inline]
-    fn calc_tmp_error(
-        &mut self,
-        output_error: &MathVec<OUT>,
-        next_state_error: Option<&MathVec<OUT>>,
-        cache: &MlCache<OUT, IN>
+    pub fn calc_output_error(
+        &self,
+        train_out: &MathVec<OUT>,
+        output_error: &mut MathVec<OUT>
     ) {
-        match next_state_error {
-            Some(next_state_error) => {
-                self.tmp_error.copy_from(next_state_error);
-            },
+        output_error.copy_from(&self.output);
+        *output_error -= train_out;
+    }
 
-            None => {
-                self.tmp_error.clear();
-            }
-        }
+    /// Calculates output error with loss function.
+    ///
+    /// - `loss` : Loss function.
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error_with_loss(
+        &self,
+        loss: &Loss,
+        train_out: &MathVec<OUT>,
+        output_error: &mut MathVec<OUT>
+    ) {
+        loss.calc_error(&self.output, train_out, output_error);
+    }
 
-        self.tmp_error.as_mut_array().iter_mut().zip(
-            output_error.as_array().iter()
-        ).zip(
-            cache.d_activate.as_array().iter()
-        ).for_each(
-            |((tmp_e, output_e), d_f)| {
-                *tmp_e += *output_e * *d_f;
-            }
-        );
+    /// Gets input.
+    ///
+    /// - _Return_ : Input.
+    #[inline]
+    pub fn input(&self) -> &MathVec<IN> {&self.input}
+
+    /// Gets state.
+    ///
+    /// - _Return_ : State.
+    #[inline]
+    pub fn state(&self) -> Option<&MathVec<OUT>> {
+        self.has_state.then(|| &self.state)
     }
 
-    /// Updates weights with Adam.
+    /// Gets middle value. (Output before activate.)
     ///
-    /// | Formula |
-    /// |:-:|
-    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mstyle mathvariant="bold"> <mover accent="true"> <mi>v</mi> <mo stretchy="false">^</mo> </mover> </mstyle> <mo stretchy="false">=</mo> <mrow> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <mrow> <msub> <mi>β</mi> <mn>1</mn> </msub> <mstyle mathvariant="bold"> <mi>v</mi> </mstyle> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> <mo stretchy="false">+</mo> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <mrow> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <mrow> <mn>1</mn> <mo stretchy="false">−</mo> <msub> <mi>β</mi> <mn>1</mn> </msub> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> <mstyle mathvariant="bold"> <mi>G</mi> </mstyle> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> </mrow> </mrow> </semantics> </math> |
-    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mover accent="true"> <mi>s</mi> <mo stretchy="false">^</mo> </mover> <mo stretchy="false">=</mo> <mrow> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <mrow> <msub> <mi>β</mi> <mn>2</mn> </msub> <mi>s</mi> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> <mo stretchy="false">+</mo> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <mrow> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <mrow> <mn>1</mn> <mo stretchy="false">−</mo> <msub> <mi>β</mi> <mn>2</mn> </msub> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> <mstyle mathvariant="bold"> <msup> <mi>G</mi> <mn>2</mn> </msup> </mstyle> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> </mrow> </mrow> </semantics> </math> |
-    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mrow> <mstyle mathvariant="bold"> <mover accent="true"> <mi>W</mi> <mo stretchy="false">^</mo> </mover> </mstyle> <mo stretchy="false">=</mo> <mrow> <mstyle mathvariant="bold"> <mi>W</mi> </mstyle> <mo stretchy="false">−</mo> <mfrac> <mi>η</mi> <mrow> <msqrt> <mover accent="true"> <mi>s</mi> <mo stretchy="false">^</mo> </mover> </msqrt> <mo stretchy="false">+</mo> <mi>ε</mi> </mrow> </mfrac> </mrow> </mrow> <mstyle mathvariant="bold"> <mover accent="true"> <mi>v</mi> <mo stretchy="false">^</mo> </mover> </mstyle> </mrow> </semantics> </math> |
-    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mtable columnalign="left"> <mtr> <mtd> <mrow> <mstyle mathvariant="bold"> <mi>G</mi> </mstyle> <mo stretchy="false">≝</mo> <mtext>Total gradient.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mstyle mathvariant="bold"> <mi>v</mi> </mstyle> <mo stretchy="false">≝</mo> <mtext>Previous momentum 1.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mstyle mathvariant="bold"> <mover accent="true"> <mi>v</mi> <mo stretchy="false">^</mo> </mover> </mstyle> <mo stretchy="false">≝</mo> <mtext>Next momentum 1.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>s</mi> <mo stretchy="false">≝</mo> <mtext>Previous momentum 2.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mover accent="true"> <mi>s</mi> <mo stretchy="false">^</mo> </mover> <mo stretchy="false">≝</mo> <mtext>Next momentum 2.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mrow> <msub> <mi>β</mi> <mn>1</mn> </msub> <mo stretchy="false">≝</mo> <mtext>Rate of momentum 1.</mtext> </mrow> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <mrow> <msub> <mi>β</mi> <mn>1</mn> </msub> <mo stretchy="false">=</mo> <mn>0.9</mn> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mrow> <msub> <mi>β</mi> <mn>2</mn> </msub> <mo stretchy="false">≝</mo> <mtext>Rate of momentum 2.</mtext> </mrow> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <mrow> <msub> <mi>β</mi> <mn>2</mn> </msub> <mo stretchy="false">=</mo> <mn>0.999</mn> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mstyle mathvariant="bold"> <mi>W</mi> </mstyle> <mo stretchy="false">≝</mo> <mtext>Previous weights.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mstyle mathvariant="bold"> <mover accent="true"> <mi>W</mi> <mo stretchy="false">^</mo> </mover> </mstyle> <mo stretchy="false">≝</mo> <mtext>Next weights.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>η</mi> <mo stretchy="false">≝</mo> <mtext>Learning rate.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>ε</mi> <mo stretchy="false">≝</mo> <mtext>Machine epsilon.</mtext> </mrow> </mtd> </mtr> </mtable> </semantics> </math> |
+    /// - _Return_ : Middle value.
+    #[inline]
+    pub fn middle_value(&self) -> &MathVec<OUT> {&self.middle_value}
+
+    /// Gets derivative of activation function.
     ///
-    /// - `rate` : Learning rate.
-    pub fn update(&mut self, rate: f32) {
-        self.next_momentum_1();
-        self.next_momentum_2();
+    /// - _Return_ : Derivative of activation function.
+    #[inline]
+    pub fn d_activate(&self) -> &MathVec<OUT> {&self.d_activate}
 
-        self.total_grad.copy_from(&self.momentum_1);
+    /// Gets output. (Output after activate.)
+    ///
+    /// - _Return_ : Output.
+    #[inline]
+    pub fn output(&self) -> &MathVec<OUT> {&self.output}
+}
 
-        // calc delta weights.
-        for i in 0..OUT {
-            debug_assert!(self.momentum_2.get(i).is_some());
-            debug_assert!(self.total_grad.input_weights().get(i).is_some());
-            debug_assert!(self.total_grad.bias().get(i).is_some());
+/// Cache for batch functions of [`MlLayer`].
+///
+/// It has data of [`MlCache`] for each sample of batch.
+///
+/// - `OUT` : `OUT` of [`MlLayer`].
+/// - `IN` : `IN` of [`MlLayer`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlBatchCache<const OUT: usize, const IN: usize> {
+    inputs: Vec<MathVec<IN>>,
+    states: Vec<MathVec<OUT>>,
+    has_state: bool,
 
-            unsafe {
-                let rate_2 = rate
-                    / (sqrt(*self.momentum_2.get_unchecked(i)) + f32::EPSILON);
-
-                *self.total_grad.bias_mut().get_unchecked_mut(i) *= rate_2;
-
-                self.total_grad
-                    .input_weights_mut()
-                    .get_unchecked_mut(i)
-                    .iter_mut()
-                    .for_each(|g| {*g *= rate_2;});
-
-                if let Some(state_weights) =
-                    self.total_grad.state_weights_mut()
-                {
-                    debug_assert!(state_weights.get(i).is_some());
-
-                    state_weights
-                        .get_unchecked_mut(i)
-                        .iter_mut()
-                        .for_each(|g| {*g *= rate_2;});
-                }
-            }
+    middle_values: Vec<MathVec<OUT>>,
+
+    d_activates: Vec<MathVec<OUT>>,
+    d_slopes: Vec<MathVec<OUT>>,
+    outputs: Vec<MathVec<OUT>>
+}
+
+impl<const OUT: usize, const IN: usize> MlBatchCache<OUT, IN> {
+    /// Creates MlBatchCache.
+    ///
+    /// - _Return_ : MlBatchCache.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            inputs: Vec::<MathVec<IN>>::new(),
+            states: Vec::<MathVec<OUT>>::new(),
+            has_state: false,
+
+            middle_values: Vec::<MathVec<OUT>>::new(),
+
+            d_activates: Vec::<MathVec<OUT>>::new(),
+            d_slopes: Vec::<MathVec<OUT>>::new(),
+            outputs: Vec::<MathVec<OUT>>::new()
         }
+    }
 
-        // updates weights.
-        self.layer.weights.iter_mut().zip(self.total_grad.iter()).for_each(
-            |(w, g)| {*w -= *g;}
-        );
+    fn resize(&mut self, batch_size: usize) {
+        self.inputs.resize(batch_size, MathVec::<IN>::new());
+        self.states.resize(batch_size, MathVec::<OUT>::new());
+        self.middle_values.resize(batch_size, MathVec::<OUT>::new());
+        self.d_activates.resize(batch_size, MathVec::<OUT>::new());
+        self.d_slopes.resize(batch_size, MathVec::<OUT>::new());
+        self.outputs.resize(batch_size, MathVec::<OUT>::new());
+    }
 
-        self.total_grad.clear();
+    /// Gets batch size.
+    ///
+    /// - _Return_ : Batch size.
+    #[inline]
+    pub fn batch_size(&self) -> usize {self.outputs.len()}
+
+    /// Calculates output error of each sample.
+    ///
+    /// See [`MlCache::calc_output_error()`] for details.
+    ///
+    /// - `train_out` : Correct outputs.
+    /// - `output_error` : Buffer for output errors.
+    #[inline]
+    pub fn calc_output_error(
+        &self,
+        train_out: &[MathVec<OUT>],
+        output_error: &mut [MathVec<OUT>]
+    ) {
+        self.outputs.iter().zip(train_out.iter()).zip(
+            output_error.iter_mut()
+        ).for_each(|((output, train_out_one), output_error_one)| {
+            output_error_one.copy_from(output);
+            *output_error_one -= train_out_one;
+        });
     }
 
+    /// Calculates output error of each sample with loss function.
+    ///
+    /// - `loss` : Loss function.
+    /// - `train_out` : Correct outputs.
+    /// - `output_error` : Buffer for output errors.
     #[inline]
-    fn next_momentum_1(&mut self) {
-        self.momentum_1.iter_mut().zip(self.total_grad.iter()).for_each(
-            |(mom, grad)| {
-                *mom = (BETA_1 * *mom) + (BETA_INV_1 * *grad);
-            }
-        );
+    pub fn calc_output_error_with_loss(
+        &self,
+        loss: &Loss,
+        train_out: &[MathVec<OUT>],
+        output_error: &mut [MathVec<OUT>]
+    ) {
+        self.outputs.iter().zip(train_out.iter()).zip(
+            output_error.iter_mut()
+        ).for_each(|((output, train_out_one), output_error_one)| {
+            loss.calc_error(output, train_out_one, output_error_one);
+        });
     }
 
+    /// Gets inputs.
+    ///
+    /// - _Return_ : Inputs.
+    #[inline]
+    pub fn inputs(&self) -> &[MathVec<IN>] {&self.inputs}
+
+    /// Gets states.
+    ///
+    /// - _Return_ : States.
     #[inline]
-    fn next_momentum_2(&mut self) {
-        for i in 0..OUT {
-            debug_assert!(self.momentum_2.get(i).is_some());
-            debug_assert!(self.total_grad.input_weights().get(i).is_some());
-            debug_assert!(self.total_grad.bias().get(i).is_some());
+    pub fn states(&self) -> Option<&[MathVec<OUT>]> {
+        self.has_state.then(|| self.states.as_slice())
+    }
 
-            let mut dot_product: f32 = 0.0;
+    /// Gets middle values. (Outputs before activate.)
+    ///
+    /// - _Return_ : Middle values.
+    #[inline]
+    pub fn middle_values(&self) -> &[MathVec<OUT>] {&self.middle_values}
 
-            unsafe {
-                let bias = *self.total_grad.bias().get_unchecked(i);
-                dot_product += bias * bias;
-
-                self.total_grad
-                    .input_weights()
-                    .get_unchecked(i)
-                    .iter()
-                    .for_each(|val| {dot_product += *val * *val;});
-
-                if let Some(state_weights) = self.total_grad.state_weights() {
-                    debug_assert!(state_weights.get(i).is_some());
-                    state_weights
-                        .get_unchecked(i)
-                        .iter()
-                        .for_each(|val| {dot_product += *val * *val;});
-                }
+    /// Gets derivatives of activation function.
+    ///
+    /// - _Return_ : Derivatives of activation function.
+    #[inline]
+    pub fn d_activates(&self) -> &[MathVec<OUT>] {&self.d_activates}
 
-                *self.momentum_2.get_unchecked_mut(i) =
-                    (BETA_2 * *self.momentum_2.get_unchecked(i))
-                    + (BETA_INV_2 * dot_product);
-            }
+    /// Gets outputs. (Outputs after activate.)
+    ///
+    /// - _Return_ : Outputs.
+    #[inline]
+    pub fn outputs(&self) -> &[MathVec<OUT>] {&self.outputs}
+}
+
+/// Optimizer to update weights in [`MlLayer::update()`].
+///
+/// Default is [`Optimizer::Adam`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Optimizer {
+    /// Adam without bias correction. Momentum 2 is shared by each row of weights. (`beta_1 = 0.9`, `beta_2 = 0.999`)
+    ///
+    /// This is the original optimizer of this library.
+    Adam,
+
+    /// Stochastic gradient descent.
+    ///
+    /// `W = W - rate * G`
+    Sgd,
+
+    /// Stochastic gradient descent with momentum.
+    ///
+    /// `v = momentum * v + G`
+    ///
+    /// If `nesterov` is `false` : `W = W - rate * v`
+    ///
+    /// If `nesterov` is `true` : `W = W - rate * (G + momentum * v)`
+    ///
+    /// - `momentum` : Rate of momentum.
+    /// - `nesterov` : If `true`, uses Nesterov momentum.
+    Momentum {momentum: f32, nesterov: bool},
+
+    /// RMSProp.
+    ///
+    /// `s = decay * s + (1 - decay) * G^2`
+    ///
+    /// `W = W - rate * G / (sqrt(s) + epsilon)`
+    ///
+    /// - `decay` : Rate of moving average of squared gradient.
+    /// - `epsilon` : Small number for stability.
+    RmsProp {decay: f32, epsilon: f32},
+
+    /// Adam with bias correction.
+    ///
+    /// `v = beta_1 * v + (1 - beta_1) * G`
+    ///
+    /// `s = beta_2 * s + (1 - beta_2) * G^2`
+    ///
+    /// `W = W - rate * (v / (1 - beta_1^t)) / (sqrt(s / (1 - beta_2^t)) + epsilon)`
+    ///
+    /// - `beta_1` : Rate of momentum 1.
+    /// - `beta_2` : Rate of momentum 2.
+    /// - `epsilon` : Small number for stability.
+    BiasCorrectedAdam {beta_1: f32, beta_2: f32, epsilon: f32},
+
+    /// Adam with bias correction and decoupled weight decay.
+    ///
+    /// Before the step of [`Optimizer::BiasCorrectedAdam`], weights are decayed by `W = W - rate * weight_decay * W`.
+    ///
+    /// - `beta_1` : Rate of momentum 1.
+    /// - `beta_2` : Rate of momentum 2.
+    /// - `epsilon` : Small number for stability.
+    /// - `weight_decay` : Rate of weight decay.
+    AdamW {beta_1: f32, beta_2: f32, epsilon: f32, weight_decay: f32}
+}
+
+impl Default for Optimizer {
+    #[inline]
+    fn default() -> Self {Self::Adam}
+}
+
+impl Optimizer {
+    /// Creates [`Optimizer::Momentum`] with `momentum = 0.9` and `nesterov = false`.
+    ///
+    /// - _Return_ : Optimizer.
+    #[inline]
+    pub fn momentum() -> Self {
+        Self::Momentum {momentum: 0.9, nesterov: false}
+    }
+
+    /// Creates [`Optimizer::RmsProp`] with `decay = 0.99` and `epsilon = 1e-8`.
+    ///
+    /// - _Return_ : Optimizer.
+    #[inline]
+    pub fn rms_prop() -> Self {
+        Self::RmsProp {decay: 0.99, epsilon: 1e-8}
+    }
+
+    /// Creates [`Optimizer::BiasCorrectedAdam`] with `beta_1 = 0.9`, `beta_2 = 0.999` and `epsilon = 1e-8`.
+    ///
+    /// - _Return_ : Optimizer.
+    #[inline]
+    pub fn bias_corrected_adam() -> Self {
+        Self::BiasCorrectedAdam {beta_1: 0.9, beta_2: 0.999, epsilon: 1e-8}
+    }
+
+    /// Creates [`Optimizer::AdamW`] with `beta_1 = 0.9`, `beta_2 = 0.999`, `epsilon = 1e-8` and `weight_decay = 0.01`.
+    ///
+    /// - _Return_ : Optimizer.
+    #[inline]
+    pub fn adam_w() -> Self {
+        Self::AdamW {
+            beta_1: 0.9,
+            beta_2: 0.999,
+            epsilon: 1e-8,
+            weight_decay: 0.01
+        }
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>) {
+        let (tag, params) = match *self {
+            Self::Adam => (0, [0.0; 4]),
+            Self::Sgd => (1, [0.0; 4]),
+            Self::Momentum {momentum, nesterov} => (
+                2,
+                [momentum, if nesterov {1.0} else {0.0}, 0.0, 0.0]
+            ),
+            Self::RmsProp {decay, epsilon} => (3, [decay, epsilon, 0.0, 0.0]),
+            Self::BiasCorrectedAdam {beta_1, beta_2, epsilon} => (
+                4,
+                [beta_1, beta_2, epsilon, 0.0]
+            ),
+            Self::AdamW {beta_1, beta_2, epsilon, weight_decay} => (
+                5,
+                [beta_1, beta_2, epsilon, weight_decay]
+            )
+        };
+
+        write_u8(buffer, tag);
+        params.iter().for_each(|param| {write_f32(buffer, *param);});
+    }
+
+    fn read(reader: &mut ByteReader) -> Result<Self, ChobitAiError> {
+        let tag = reader.read_u8()?;
+
+        let mut params = [0.0f32; 4];
+        for param in params.iter_mut() {
+            *param = reader.read_f32()?;
+        }
 
+        match tag {
+            0 => Ok(Self::Adam),
+            1 => Ok(Self::Sgd),
+            2 => Ok(Self::Momentum {
+                momentum: params[0],
+                nesterov: params[1] != 0.0
+            }),
+            3 => Ok(Self::RmsProp {decay: params[0], epsilon: params[1]}),
+            4 => Ok(Self::BiasCorrectedAdam {
+                beta_1: params[0],
+                beta_2: params[1],
+                epsilon: params[2]
+            }),
+            5 => Ok(Self::AdamW {
+                beta_1: params[0],
+                beta_2: params[1],
+                epsilon: params[2],
+                weight_decay: params[3]
+            }),
+            _ => Err(ChobitAiError::UnknownOptimizer {tag: tag})
         }
     }
 }
 
-/// AI for fixed length data.
-///
-/// - `OUT` : Dimension of output.
-/// - `MIDDLE` : Dimension of hidden layer.
-/// - `IN` : Dimension of input.
-///
-/// # Example
-///
-/// Letter classifier.
-/// - If inputs Japanese letter, outputs '日'.
-/// - If inputs English letter, outputs 'E'.
-///
-/// (1) Defines letter generator and ID.
-///
-/// ```ignore
-/// extern crate chobitlibs;
-/// 
-/// use chobitlibs::chobit_ai::{
-///     MathVec,
-///     Activation,
-///     ChobitAi,
-///     ChobitMlAi,
-///     MlAiCache
-/// };
-/// 
-/// use chobitlibs::chobit_rand::ChobitRand;
-/// 
-/// fn japanese_letter(rng: &mut ChobitRand) -> char {
-///     let letters = [
-///         'あ', 'い', 'う', 'え', 'お',
-///         'か', 'き', 'く', 'け', 'こ',
-///         'さ', 'し', 'す', 'せ', 'そ'
-///     ];
-/// 
-///     letters[(rng.next_u64() as usize) % letters.len()]
-/// }
-/// 
-/// fn english_letter(rng: &mut ChobitRand) -> char {
-///     let letters = [
-///         'a', 'b', 'c', 'd', 'e',
-///         'f', 'g', 'h', 'i', 'j',
-///         'k', 'l', 'm', 'n', 'o'
-///     ];
-/// 
-///     letters[(rng.next_u64() as usize) % letters.len()]
-/// }
-/// 
-/// const JAPANESE_ID: char = '日';
-/// const ENGLISH_ID: char = 'E';
-/// ```
-///
-/// (2) Creates [`ChobitAi`] and randomises weights.
-///
-/// ```ignore
-/// const OUT: usize = 32;
-/// const MIDDLE: usize = 64;
-/// const IN: usize = 32;
-///
-/// let mut rng = ChobitRand::new(b"ChobitAi Example");
-///
-/// let mut ai = ChobitAi::<OUT, MIDDLE, IN>::new(Activation::SoftSign);
-///
-/// // Randomises weights.
-/// ai.for_each_weight_mut(|weight| {
-///     *weight = ((rng.next_f64() as f32) * 2.0) - 1.0;
-/// });
-///
-/// let mut input = MathVec::<IN>::new();
-/// let mut output = MathVec::<OUT>::new();
-/// ```
-///
-/// (3) Wraps AI with [`ChobitMlAi`] for machine learning.
-///
-/// ```ignore
-/// let mut ai = ChobitMlAi::<OUT, MIDDLE, IN>::new(ai);
-/// let mut cache = MlAiCache::<OUT, MIDDLE, IN>::new();
-///
-/// let mut input_error = MathVec::<IN>::new();
-/// let mut output_error = MathVec::<OUT>::new();
-/// ```
-///
-/// (4) Machine learning.
-///
-/// ```ignore
-/// const EPOCH: usize = 1000;
-/// const BATCH_SIZE: usize = 100;
-/// const RATE: f32 = 0.01;
-///
-/// for _ in 0..EPOCH {
-///     for _ in 0..BATCH_SIZE {
-///         //--- Learns Japanese ---//
-///         input.load_u32_label(japanese_letter(&mut rng) as u32);
-///         output.load_u32_label(JAPANESE_ID as u32);
-///
-///         // Writes cache.
-///         ai.ready(&input, &mut cache);
-///
-///         // Calculates error.
-///         cache.calc_output_error(&output, &mut output_error);
-///
-///         // Studies.
-///         ai.study(&output_error, &cache, &mut input_error);
-///
-///         //--- Learns English ---//
-///         input.load_u32_label(english_letter(&mut rng) as u32);
-///         output.load_u32_label(ENGLISH_ID as u32);
-///
-///         // Writes cache.
-///         ai.ready(&input, &mut cache);
-///
-///         // Calculates error.
-///         cache.calc_output_error(&output, &mut output_error);
-///
-///         // Studies.
-///         ai.study(&output_error, &cache, &mut input_error);
-///     }
-///
-///     // Updates weights.
-///     ai.update(RATE);
-/// }
-/// ```
-///
-/// (5) Tests AI.
-///
-/// ```ignore
-/// // Unwrap AI.
-/// let ai = ai.drop();
-///
-/// let mut tmpbuf = MathVec::<MIDDLE>::new();
-///
-/// // Tests Japanese.
-/// for _ in 0..10 {
-///     input.load_u32_label(japanese_letter(&mut rng) as u32);
-///
-///     ai.calc(&input, &mut output, &mut tmpbuf);
-///
-///     assert_eq!(output.to_u32_label(), JAPANESE_ID as u32);
-/// }
-///
-/// // Tests English.
-/// for _ in 0..10 {
-///     input.load_u32_label(english_letter(&mut rng) as u32);
-///
-///     ai.calc(&input, &mut output, &mut tmpbuf);
+#[inline]
+fn powi(x: f32, n: u64) -> f32 {
+    let mut base = x;
+    let mut n = n;
+    let mut ret: f32 = 1.0;
+
+    while n > 0 {
+        if (n & 1) == 1 {
+            ret *= base;
+        }
+
+        base *= base;
+        n >>= 1;
+    }
+
+    ret
+}
+
+#[inline]
+fn cos(x: f32) -> f32 {
+    const PI: f32 = core::f32::consts::PI;
+    const TAU: f32 = core::f32::consts::TAU;
+
+    let mut x = abs(x) % TAU;
+
+    if x > PI {
+        x = TAU - x;
+    }
+
+    let (x, sign) = if x > (PI / 2.0) {(PI - x, -1.0)} else {(x, 1.0)};
+
+    let x2 = x * x;
+
+    // Taylor series to x^12.
+    let mut term: f32 = 1.0;
+    let mut ret: f32 = 1.0;
+
+    for i in 1..7 {
+        let n = (2 * i) as f32;
+
+        term *= -x2 / ((n - 1.0) * n);
+        ret += term;
+    }
+
+    sign * ret
+}
+
+#[inline]
+fn sin(x: f32) -> f32 {
+    cos(x - core::f32::consts::FRAC_PI_2)
+}
+
+/// Schedule of learning rate.
 ///
-///     assert_eq!(output.to_u32_label(), ENGLISH_ID as u32);
-/// }
-/// ```
+/// `step` is count of [`LrSchedule::rate()`] argument. It is count of update or count of epoch, as you like.
 #[derive(Debug, Clone, PartialEq)]
-pub struct ChobitAi<const OUT: usize, const MIDDLE: usize, const IN: usize> {
-    middle_layer: Layer<MIDDLE, IN>,
-    output_layer: Layer<OUT, MIDDLE>
+pub enum LrSchedule {
+    /// Constant learning rate.
+    ///
+    /// - `rate` : Learning rate.
+    Constant {rate: f32},
+
+    /// Learning rate is multiplied by `gamma` every `step_size` steps.
+    ///
+    /// `rate * gamma^(step / step_size)`
+    ///
+    /// - `rate` : Initial learning rate.
+    /// - `gamma` : Decay rate.
+    /// - `step_size` : Interval of decay.
+    StepDecay {rate: f32, gamma: f32, step_size: u64},
+
+    /// Learning rate is multiplied by `gamma` every step.
+    ///
+    /// `rate * gamma^step`
+    ///
+    /// - `rate` : Initial learning rate.
+    /// - `gamma` : Decay rate.
+    Exponential {rate: f32, gamma: f32},
+
+    /// Cosine annealing with warm restarts. (SGDR)
+    ///
+    /// `min_rate + (max_rate - min_rate) * (1 + cos(PI * t / T)) / 2`
+    ///
+    /// `t` is count of steps since the last restart. `T` is length of the current period. It starts from `period` and is multiplied by `period_mult` at every restart.
+    ///
+    /// - `max_rate` : Learning rate at the start of period.
+    /// - `min_rate` : Learning rate at the end of period.
+    /// - `period` : Length of the first period. If `0`, it is treated as `1`.
+    /// - `period_mult` : Multiplier of period. If `0`, it is treated as `1`.
+    CosineAnnealing {
+        max_rate: f32,
+        min_rate: f32,
+        period: u64,
+        period_mult: u64
+    },
+
+    /// Linear warmup before other schedule.
+    ///
+    /// While `step < warmup_steps`, learning rate is `schedule.rate(0) * (step + 1) / warmup_steps`.
+    /// After that, learning rate is `schedule.rate(step - warmup_steps)`.
+    ///
+    /// - `warmup_steps` : Length of warmup.
+    /// - `schedule` : Schedule after warmup.
+    Warmup {warmup_steps: u64, schedule: Box<LrSchedule>},
+
+    /// One cycle policy.
+    ///
+    /// Learning rate increases from `max_rate / div_factor` to `max_rate` in the first `total_steps * pct_start` steps, then decreases to `max_rate / (div_factor * final_div_factor)` until `total_steps` by cosine annealing.
+    /// After `total_steps`, learning rate stays at the final value.
+    ///
+    /// - `max_rate` : Maximum learning rate.
+    /// - `total_steps` : Length of cycle.
+    /// - `pct_start` : Ratio of increasing phase. (`[0.0, 1.0]`)
+    /// - `div_factor` : Initial learning rate is `max_rate / div_factor`.
+    /// - `final_div_factor` : Final learning rate is `max_rate / (div_factor * final_div_factor)`.
+    OneCycle {
+        max_rate: f32,
+        total_steps: u64,
+        pct_start: f32,
+        div_factor: f32,
+        final_div_factor: f32
+    }
 }
 
-impl<
-    const OUT: usize,
-    const MIDDLE: usize,
-    const IN: usize
-> ChobitAi<OUT, MIDDLE, IN> {
-    /// Creates ChobitAi.
+impl LrSchedule {
+    /// Creates [`LrSchedule::Warmup`].
     ///
-    /// - `activation` : Activation function for output layer.
-    /// - _Return_ : ChobitAi.
+    /// - `warmup_steps` : Length of warmup.
+    /// - `schedule` : Schedule after warmup.
+    /// - _Return_ : LrSchedule.
     #[inline]
-    pub fn new(activation: Activation) -> Self {
-        Self {
-            middle_layer: Layer::<MIDDLE, IN>::new(Activation::ReLU, false),
-            output_layer: Layer::<OUT, MIDDLE>::new(activation, false)
+    pub fn warmup(warmup_steps: u64, schedule: LrSchedule) -> Self {
+        Self::Warmup {
+            warmup_steps: warmup_steps,
+            schedule: Box::new(schedule)
         }
     }
 
-    /// Gets immutable middle layer.
+    /// Creates [`LrSchedule::OneCycle`] with `pct_start = 0.3`, `div_factor = 25.0` and `final_div_factor = 1e4`.
     ///
-    /// - _Return_ : Middle layer.
+    /// - `max_rate` : Maximum learning rate.
+    /// - `total_steps` : Length of cycle.
+    /// - _Return_ : LrSchedule.
     #[inline]
-    pub fn middle_layer(&self) -> &Layer<MIDDLE, IN> {&self.middle_layer}
+    pub fn one_cycle(max_rate: f32, total_steps: u64) -> Self {
+        Self::OneCycle {
+            max_rate: max_rate,
+            total_steps: total_steps,
+            pct_start: 0.3,
+            div_factor: 25.0,
+            final_div_factor: 1.0e4
+        }
+    }
 
-    /// Gets mutable middle layer.
+    /// Calculates learning rate.
     ///
-    /// - _Return_ : Middle layer.
-    #[inline]
-    pub fn middle_layer_mut(&mut self) -> &mut Layer<MIDDLE, IN> {
-        &mut self.middle_layer
+    /// - `step` : Count of step.
+    /// - _Return_ : Learning rate.
+    pub fn rate(&self, step: u64) -> f32 {
+        match self {
+            Self::Constant {rate} => *rate,
+
+            Self::StepDecay {rate, gamma, step_size} => {
+                *rate * powi(*gamma, step / (*step_size).max(1))
+            },
+
+            Self::Exponential {rate, gamma} => *rate * powi(*gamma, step),
+
+            Self::CosineAnnealing {max_rate, min_rate, period, period_mult} => {
+                let period_mult = (*period_mult).max(1) as u128;
+                let period = (*period).max(1) as u128;
+                let step = step as u128;
+
+                let (t, period) = if period_mult == 1 {
+                    (step % period, period)
+                } else {
+                    // Number of restarts n is the largest n that satisfies
+                    // period * (period_mult^n - 1) / (period_mult - 1)
+                    // <= step.
+                    let n = (((step * (period_mult - 1)) / period) + 1)
+                        .ilog(period_mult);
+
+                    let mult_n = period_mult.pow(n);
+                    let start = (period * (mult_n - 1)) / (period_mult - 1);
+
+                    (step - start, period * mult_n)
+                };
+
+                Self::cosine(
+                    *max_rate,
+                    *min_rate,
+                    t as u64,
+                    period.min(u64::MAX as u128) as u64
+                )
+            },
+
+            Self::Warmup {warmup_steps, schedule} => {
+                if step < *warmup_steps {
+                    schedule.rate(0) * ((step + 1) as f32)
+                        / (*warmup_steps as f32)
+                } else {
+                    schedule.rate(step - *warmup_steps)
+                }
+            },
+
+            Self::OneCycle {
+                max_rate,
+                total_steps,
+                pct_start,
+                div_factor,
+                final_div_factor
+            } => {
+                let initial_rate = *max_rate / *div_factor;
+                let final_rate = initial_rate / *final_div_factor;
+
+                let up_steps = ((*total_steps as f32) * *pct_start) as u64;
+                let down_steps = total_steps.saturating_sub(up_steps);
+
+                if step < up_steps {
+                    // from initial_rate (t = up_steps) to max_rate (t = 0).
+                    Self::cosine(
+                        *max_rate,
+                        initial_rate,
+                        up_steps - step,
+                        up_steps
+                    )
+                } else if step < *total_steps {
+                    Self::cosine(
+                        *max_rate,
+                        final_rate,
+                        step - up_steps,
+                        down_steps
+                    )
+                } else {
+                    final_rate
+                }
+            }
+        }
     }
 
-    /// Gets immutable output layer.
-    ///
-    /// - _Return_ : Output layer.
     #[inline]
-    pub fn output_layer(&self) -> &Layer<OUT, MIDDLE> {&self.output_layer}
+    fn cosine(start: f32, end: f32, t: u64, period: u64) -> f32 {
+        const PI: f32 = core::f32::consts::PI;
 
-    /// Gets mutable output layer.
+        let ratio = (t as f32) / (period.max(1) as f32);
+
+        end + ((start - end) * (1.0 + cos(PI * ratio)) / 2.0)
+    }
+}
+
+/// [`LrSchedule`] with step counter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LrScheduler {
+    schedule: LrSchedule,
+    step: u64
+}
+
+impl LrScheduler {
+    /// Creates LrScheduler.
     ///
-    /// - _Return_ : Output layer.
+    /// - `schedule` : Schedule of learning rate.
+    /// - _Return_ : LrScheduler.
     #[inline]
-    pub fn output_layer_mut(&mut self) -> &mut Layer<OUT, MIDDLE> {
-        &mut self.output_layer
+    pub fn new(schedule: LrSchedule) -> Self {
+        Self {
+            schedule: schedule,
+            step: 0
+        }
     }
 
-    /// Accesses each immutable weight with closure.
+    /// Gets schedule.
     ///
-    /// - `f` : Closure.
+    /// - _Return_ : Schedule.
     #[inline]
-    pub fn for_each_weight<F>(&self, mut f: F) where F: FnMut(&f32) {
-        self.middle_layer.weights().iter().for_each(|val| {f(val)});
-        self.output_layer.weights().iter().for_each(|val| {f(val)});
-    }
+    pub fn schedule(&self) -> &LrSchedule {&self.schedule}
 
-    /// Accesses each mutable weight with closure.
+    /// Gets current step.
     ///
-    /// - `f` : Closure.
+    /// - _Return_ : Current step.
     #[inline]
-    pub fn for_each_weight_mut<F>(
-        &mut self,
-        mut f: F
-    ) where F: FnMut(&mut f32) {
-        self.middle_layer.mut_weights().iter_mut().for_each(|val| {f(val)});
-        self.output_layer.mut_weights().iter_mut().for_each(|val| {f(val)});
-    }
+    pub fn step(&self) -> u64 {self.step}
 
-    /// Calculates
+    /// Sets current step. (e.g. To resume machine learning.)
     ///
-    /// - `input` : Input.
-    /// - `output` : Buffer for output.
-    /// - `tmpbuf` : Temporary buffer for this function to work.
+    /// - `step` : Step.
     #[inline]
-    pub fn calc(
-        &self,
-        input: &MathVec<IN>,
-        output: &mut MathVec<OUT>,
-        tmpbuf: &mut MathVec<MIDDLE>
-    ) {
-        self.middle_layer.calc(input, None, tmpbuf);
-        self.output_layer.calc(tmpbuf, None, output);
+    pub fn set_step(&mut self, step: u64) {self.step = step;}
+
+    /// Gets learning rate of current step.
+    ///
+    /// - _Return_ : Learning rate.
+    #[inline]
+    pub fn rate(&self) -> f32 {self.schedule.rate(self.step)}
+
+    /// Gets learning rate of current step and advances step.
+    ///
+    /// - _Return_ : Learning rate.
+    #[inline]
+    pub fn next_rate(&mut self) -> f32 {
+        let ret = self.rate();
+
+        self.step += 1;
+
+        ret
     }
 }
 
-/// Cache for [`ChobitMlAi`].
+/// Helper for early stopping.
 ///
-/// - `OUT` : `OUT` of [`ChobitMlAi`].
-/// - `MIDDLE` : `MIDDLE` of [`ChobitMlAi`].
-/// - `IN` : `IN` of [`ChobitMlAi`].
+/// Tracks validation loss across epochs and keeps a snapshot of the model with the best loss.
+///
+/// ```ignore
+/// let mut early_stopping = EarlyStopping::new(10, 0.0);
+///
+/// for _ in 0..EPOCH {
+///     // Machine learning...
+///
+///     if early_stopping.check(validation_loss, &ai) {break;}
+/// }
+///
+/// early_stopping.restore(&mut ai);
+/// ```
+///
+/// - `T` : Model. (e.g. [`ChobitAi`], [`ChobitMlAi`])
 #[derive(Debug, Clone, PartialEq)]
-pub struct MlAiCache<const OUT: usize, const MIDDLE: usize, const IN: usize> {
-    middle_cache: MlCache<MIDDLE, IN>,
-    output_cache: MlCache<OUT, MIDDLE>
+pub struct EarlyStopping<T: Clone> {
+    patience: u32,
+    min_delta: f32,
+
+    epoch: u32,
+    best_epoch: u32,
+    best_loss: f32,
+    best: Option<T>
 }
 
-impl<
-    const OUT: usize,
-    const MIDDLE: usize,
-    const IN: usize
-> MlAiCache<OUT, MIDDLE, IN> {
-    /// Creates MlAiCache.
+impl<T: Clone> EarlyStopping<T> {
+    /// Creates EarlyStopping.
     ///
-    /// - _Return_ : MlAiCache.
+    /// - `patience` : Count of epochs without improvement before stopping.
+    /// - `min_delta` : Minimum decrease of loss to be counted as improvement.
+    /// - _Return_ : EarlyStopping.
     #[inline]
-    pub fn new() -> Self {
+    pub fn new(patience: u32, min_delta: f32) -> Self {
         Self {
-            middle_cache: MlCache::<MIDDLE, IN>::new(),
-            output_cache: MlCache::<OUT, MIDDLE>::new()
+            patience: patience,
+            min_delta: min_delta,
+
+            epoch: 0,
+            best_epoch: 0,
+            best_loss: f32::INFINITY,
+            best: None
         }
     }
 
-    /// Calculates output error.
+    /// Gets patience.
     ///
-    /// | Formula |
-    /// |:-:|
-    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mi>e</mi> <mo stretchy="false">=</mo> <mrow> <mi>o</mi> <mo stretchy="false">−</mo> <mi>t</mi> </mrow> </mrow> </semantics> </math> |
-    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mtable columnalign="left"> <mtr> <mtd> <mrow> <mi>e</mi> <mo stretchy="false">≝</mo> <mtext>Error.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>o</mi> <mo stretchy="false">≝</mo> <mtext>Actual output.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>t</mi> <mo stretchy="false">≝</mo> <mtext>Correct output.</mtext> </mrow> </mtd> </mtr> </mtable> </semantics> </math> |
-    ///
-    /// - `train_out` : Correct output.
-    /// - `output_error` : Buffer for output error.
+    /// - _Return_ : Patience.
     #[inline]
-    pub fn calc_output_error(
-        &self,
-        train_out: &MathVec<OUT>,
-        error: &mut MathVec<OUT>
-    ) {
-        error.copy_from(&self.output_cache.output);
-        *error -= train_out;
-    }
+    pub fn patience(&self) -> u32 {self.patience}
 
-    /// Gets cache for middle layer.
+    /// Gets minimum decrease of loss.
     ///
-    /// - _Return_ : Cache for middle layer.
+    /// - _Return_ : Minimum decrease of loss.
     #[inline]
-    pub fn middle_cache(&self) -> &MlCache<MIDDLE, IN> {&self.middle_cache}
+    pub fn min_delta(&self) -> f32 {self.min_delta}
 
-    /// Gets cache for output layer.
+    /// Gets count of checked epochs.
     ///
-    /// - _Return_ : Cache for output layer.
+    /// - _Return_ : Count of checked epochs.
     #[inline]
-    pub fn output_cache(&self) -> &MlCache<OUT, MIDDLE> {&self.output_cache}
+    pub fn epoch(&self) -> u32 {self.epoch}
 
-    /// Gets output.
+    /// Gets epoch of the best loss. (1 is the first epoch.)
     ///
-    /// - _Return_ : Output.
+    /// - _Return_ : Epoch of the best loss. 0 if no loss has been improved.
     #[inline]
-    pub fn output(&self) -> &MathVec<OUT> {&self.output_cache.output}
-}
-
-/// Wrapper of [`ChobitAi`] for machine learning.
-///
-/// See [`ChobitAi`] for details.
-///
-/// - `OUT` : Dimension of output.
-/// - `MIDDLE` : Dimension of hidden layer.
-/// - `IN` : Dimension of input.
-#[derive(Debug, Clone, PartialEq)]
-pub struct ChobitMlAi<const OUT: usize, const MIDDLE: usize, const IN: usize> {
-    middle_layer: MlLayer<MIDDLE, IN>,
-    output_layer: MlLayer<OUT, MIDDLE>,
-
-    middle_error: MathVec<MIDDLE>,
-    output_error: MathVec<OUT>
-}
+    pub fn best_epoch(&self) -> u32 {self.best_epoch}
 
-impl<
-    const OUT: usize,
-    const MIDDLE: usize,
-    const IN: usize
-> ChobitMlAi<OUT, MIDDLE, IN> {
-    /// Creates ChobitMlAi.
+    /// Gets the best loss.
     ///
-    /// - `ai` : Base [`ChobitAi`].
-    /// - _Return_ : ChobitMlAi.
+    /// - _Return_ : The best loss. `f32::INFINITY` if no loss has been improved.
     #[inline]
-    pub fn new(ai: ChobitAi<OUT, MIDDLE, IN>) -> Self {
-        let ChobitAi::<OUT, MIDDLE, IN> {middle_layer, output_layer} = ai;
-
-        Self {
-            middle_layer: MlLayer::<MIDDLE, IN>::new(middle_layer),
-            output_layer: MlLayer::<OUT, MIDDLE>::new(output_layer),
-
-            middle_error: MathVec::<MIDDLE>::new(),
-            output_error: MathVec::<OUT>::new(),
-        }
-    }
+    pub fn best_loss(&self) -> f32 {self.best_loss}
 
-    /// Drops base [`ChobitAi`].
+    /// Gets snapshot of the model with the best loss.
     ///
-    /// - _Return_ : [`ChobitAi`].
+    /// - _Return_ : Snapshot.
     #[inline]
-    pub fn drop(self) -> ChobitAi<OUT, MIDDLE, IN> {
-        let Self {middle_layer, output_layer, ..} = self;
+    pub fn best(&self) -> Option<&T> {self.best.as_ref()}
 
-        ChobitAi::<OUT, MIDDLE, IN> {
-            middle_layer: middle_layer.drop(),
-            output_layer: output_layer.drop()
+    /// Checks validation loss of an epoch.
+    ///
+    /// If the loss is less than the best loss by more than `min_delta`, snapshot of the model is taken. Non-finite loss is never counted as improvement.
+    ///
+    /// - `loss` : Validation loss.
+    /// - `model` : Current model.
+    /// - _Return_ : `true` if machine learning should stop.
+    pub fn check(&mut self, loss: f32, model: &T) -> bool {
+        self.epoch += 1;
+
+        if loss.is_finite() && (loss < (self.best_loss - self.min_delta)) {
+            self.best_epoch = self.epoch;
+            self.best_loss = loss;
+
+            match &mut self.best {
+                Some(best) => best.clone_from(model),
+                None => {self.best = Some(model.clone());}
+            }
         }
-    }
 
-    /// Clears internal data for study.
-    #[inline]
-    pub fn clear_study_data(&mut self) {
-        self.middle_layer.clear_study_data();
-        self.output_layer.clear_study_data();
+        self.should_stop()
     }
 
-    /// Writes information on [`MlAiCache`] for [`study()`](Self::study()).
+    /// Checks whether machine learning should stop.
     ///
-    /// - `input` : Input.
-    /// - `cache` : Cache.
+    /// - _Return_ : `true` if loss has not been improved for `patience` epochs.
     #[inline]
-    pub fn ready(
-        &self,
-        input: &MathVec<IN>,
-        cache: &mut MlAiCache<OUT, MIDDLE, IN>
-    ) {
-        self.middle_layer.ready(input, None, &mut cache.middle_cache);
-
-        self.output_layer.ready(
-            &cache.middle_cache.output,
-            None,
-            &mut cache.output_cache
-        );
+    pub fn should_stop(&self) -> bool {
+        (self.epoch - self.best_epoch) >= self.patience
     }
 
-    /// Accumulates gradient.
+    /// Restores the model with the best loss.
     ///
-    /// - `output_error` : Backpropagated ouput error.
-    /// - `cache` : Cache.
-    /// - `input_error` : Error to backpropagate for previous output error.
+    /// - `model` : Model to be overwritten.
+    /// - _Return_ : `true` if snapshot exists and is restored.
     #[inline]
-    pub fn study(
-        &mut self,
-        output_error: &MathVec<OUT>,
-        cache: &MlAiCache<OUT, MIDDLE, IN>,
-        input_error: &mut MathVec<IN>
-    ) {
-        self.output_layer.study(
-            output_error,
-            None,
-            &cache.output_cache,
-            &mut self.middle_error,
-            None
-        );
+    pub fn restore(&self, model: &mut T) -> bool {
+        match &self.best {
+            Some(best) => {
+                model.clone_from(best);
+                true
+            },
 
-        self.middle_layer.study(
-            &self.middle_error,
-            None,
-            &cache.middle_cache,
-            input_error,
-            None
-        );
+            None => false
+        }
     }
 
-    /// Updates weights.
-    ///
-    /// - `rate` : Learning rate.
+    /// Clears epochs, the best loss and snapshot.
     #[inline]
-    pub fn update(&mut self, rate: f32) {
-        self.middle_layer.update(rate);
-        self.output_layer.update(rate);
+    pub fn reset(&mut self) {
+        self.epoch = 0;
+        self.best_epoch = 0;
+        self.best_loss = f32::INFINITY;
+        self.best = None;
     }
+}
 
-    /// Accesses each immutable total gradient with closure.
-    ///
-    /// - `f` : Closure.
-    #[inline]
-    pub fn for_each_total_grad<F>(&self, mut f: F) where F: FnMut(&f32) {
-        self.middle_layer.total_grad.iter().for_each(|val| {f(val)});
-        self.output_layer.total_grad.iter().for_each(|val| {f(val)});
-    }
+/// Way of magnitude pruning.
+///
+/// See [`Weights::prune()`], [`MlLayer::prune()`] and [`ChobitMlAi::prune_global()`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pruning {
+    /// Prunes weights whose absolute values are the threshold or less.
+    Threshold(f32),
 
-    /// Accesses each mutable total gradient with closure.
+    /// Prunes the percentage `[0.0, 100.0]` of weights that have the smallest absolute values.
     ///
-    /// - `f` : Closure.
-    #[inline]
-    pub fn for_each_total_grad_mut<F>(
-        &mut self,
-        mut f: F
-    ) where F: FnMut(&mut f32) {
-        self.middle_layer.total_grad.iter_mut().for_each(|val| {f(val)});
-        self.output_layer.total_grad.iter_mut().for_each(|val| {f(val)});
+    /// Weights whose absolute values are the same as the largest pruned one are also pruned.
+    Percentage(f32)
+}
+
+impl Pruning {
+    // Weights whose absolute values are the threshold or less are pruned.
+    // None if nothing is pruned.
+    fn threshold<'a, I>(&self, weights: I) -> Option<f32>
+    where I: Iterator<Item = &'a f32> {
+        match *self {
+            Self::Threshold(threshold) => Some(threshold),
+
+            Self::Percentage(percentage) => {
+                let mut values =
+                    weights.map(|weight| abs(*weight)).collect::<Vec<f32>>();
+
+                let count = (values.len() as f32)
+                    * (percentage.clamp(0.0, 100.0) / 100.0);
+
+                // Rounds up.
+                let count = match count as usize {
+                    floor if (floor as f32) < count => floor + 1,
+                    floor => floor
+                }.min(values.len());
+
+                if count == 0 {
+                    return None;
+                }
+
+                let (_, threshold, _) = values.select_nth_unstable_by(
+                    count - 1,
+                    |a, b| a.total_cmp(b)
+                );
+
+                Some(*threshold)
+            }
+        }
     }
 }
 
-/// [Peephole LSTM](https://en.wikipedia.org/wiki/Long_short-term_memory#Peephole_Lstm)
+/// Layer for neural network only for machine learning.
 ///
 /// | Formula |
 /// |:-:|
-/// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mrow> <msub> <mi>m</mi> <mi>i</mi> </msub> <mo stretchy="false">=</mo> <mi>tanh</mi> </mrow> <mrow> <mo fence="true" form="prefix" stretchy="true">(</mo> <mrow> <mrow> <mrow> <munder> <mo stretchy="false">∑</mo> <mi>j</mi> </munder> <msubsup> <mi>W</mi> <mi mathvariant="italic">ij</mi> <mi>m</mi> </msubsup> </mrow> <mrow> <msub> <mi>x</mi> <mi>j</mi> </msub> <mo stretchy="false">+</mo> <mrow> <munder> <mo stretchy="false">∑</mo> <mi>k</mi> </munder> <msubsup> <mi>U</mi> <mi mathvariant="italic">ik</mi> <mi>m</mi> </msubsup> </mrow> </mrow> <mrow> <mover accent="true"> <msub> <mi>s</mi> <mi>k</mi> </msub> <mo>¯ </mo> </mover> <mo stretchy="false">+</mo> <msubsup> <mi>b</mi> <mi>i</mi> <mi>m</mi> </msubsup> </mrow> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="true">)</mo> </mrow> </mrow> </semantics> </math> |
-/// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mrow> <msub> <mi>f</mi> <mi>i</mi> </msub> <mo stretchy="false">=</mo> <mi>&sigma;</mi> </mrow> <mrow> <mo fence="true" form="prefix" stretchy="true">(</mo> <mrow> <mrow> <mrow> <munder> <mo stretchy="false">∑</mo> <mi>j</mi> </munder> <msubsup> <mi>W</mi> <mi mathvariant="italic">ij</mi> <mi>f</mi> </msubsup> </mrow> <mrow> <msub> <mi>x</mi> <mi>j</mi> </msub> <mo stretchy="false">+</mo> <mrow> <munder> <mo stretchy="false">∑</mo> <mi>k</mi> </munder> <msubsup> <mi>U</mi> <mi mathvariant="italic">ik</mi> <mi>f</mi> </msubsup> </mrow> </mrow> <mrow> <mover accent="true"> <msub> <mi>s</mi> <mi>k</mi> </msub> <mo>¯ </mo> </mover> <mo stretchy="false">+</mo> <msubsup> <mi>b</mi> <mi>i</mi> <mi>f</mi> </msubsup> </mrow> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="true">)</mo> </mrow> </mrow> </semantics> </math> |
-/// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mrow> <msub> <mi>i</mi> <mi>i</mi> </msub> <mo stretchy="false">=</mo> <mi>&sigma;</mi> </mrow> <mrow> <mo fence="true" form="prefix" stretchy="true">(</mo> <mrow> <mrow> <mrow> <munder> <mo stretchy="false">∑</mo> <mi>j</mi> </munder> <msubsup> <mi>W</mi> <mi mathvariant="italic">ij</mi> <mi>i</mi> </msubsup> </mrow> <mrow> <msub> <mi>x</mi> <mi>j</mi> </msub> <mo stretchy="false">+</mo> <mrow> <munder> <mo stretchy="false">∑</mo> <mi>k</mi> </munder> <msubsup> <mi>U</mi> <mi mathvariant="italic">ik</mi> <mi>i</mi> </msubsup> </mrow> </mrow> <mrow> <mover accent="true"> <msub> <mi>s</mi> <mi>k</mi> </msub> <mo>¯ </mo> </mover> <mo stretchy="false">+</mo> <msubsup> <mi>b</mi> <mi>i</mi> <mi>i</mi> </msubsup> </mrow> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="true">)</mo> </mrow> </mrow> </semantics> </math> |
-/// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mrow> <msub> <mi>o</mi> <mi>i</mi> </msub> <mo stretchy="false">=</mo> <mi>&sigma;</mi> </mrow> <mrow> <mo fence="true" form="prefix" stretchy="true">(</mo> <mrow> <mrow> <mrow> <munder> <mo stretchy="false">∑</mo> <mi>j</mi> </munder> <msubsup> <mi>W</mi> <mi mathvariant="italic">ij</mi> <mi>o</mi> </msubsup> </mrow> <mrow> <msub> <mi>x</mi> <mi>j</mi> </msub> <mo stretchy="false">+</mo> <mrow> <munder> <mo stretchy="false">∑</mo> <mi>k</mi> </munder> <msubsup> <mi>U</mi> <mi mathvariant="italic">ik</mi> <mi>o</mi> </msubsup> </mrow> </mrow> <mrow> <mover accent="true"> <msub> <mi>s</mi> <mi>k</mi> </msub> <mo>¯ </mo> </mover> <mo stretchy="false">+</mo> <msubsup> <mi>b</mi> <mi>i</mi> <mi>o</mi> </msubsup> </mrow> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="true">)</mo> </mrow> </mrow> </semantics> </math> |
-/// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mrow> <msub> <mi>s</mi> <mi>i</mi> </msub> <mo stretchy="false">=</mo> <msub> <mi>f</mi> <mi>i</mi> </msub> </mrow> <mrow> <mrow> <mo fence="true" form="prefix" stretchy="true">(</mo> <mrow> <mrow> <mrow> <munder> <mo stretchy="false">∑</mo> <mi>k</mi> </munder> <msub> <mi>δ</mi> <mi mathvariant="italic">ik</mi> </msub> </mrow> <mover accent="true"> <msub> <mi>s</mi> <mi>k</mi> </msub> <mo>¯ </mo> </mover> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="true">)</mo> </mrow> <mo stretchy="false">+</mo> <msub> <mi>i</mi> <mi>i</mi> </msub> </mrow> <msub> <mi>m</mi> <mi>i</mi> </msub> </mrow> </semantics> </math> |
-/// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mrow> <msub> <mi>y</mi> <mi>i</mi> </msub> <mo stretchy="false">=</mo> <msub> <mi>o</mi> <mi>i</mi> </msub> </mrow> <mi>tanh</mi> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <msub> <mi>s</mi> <mi>i</mi> </msub> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> </mrow> </semantics> </math> |
-/// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mtable columnalign="left"> <mtr> <mtd> <mrow> <mi>i</mi> <mo stretchy="false">≝</mo> <mtext>Index of output.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>j</mi> <mo stretchy="false">≝</mo> <mtext>Index of input.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mrow> <mi>k</mi> <mo stretchy="false">≝</mo> <mtext>Index of state.</mtext> </mrow> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <mrow> <mtext>dim</mtext> <mspace width="0.5em"/> <mrow> <mi>k</mi> <mo stretchy="false">=</mo> <mtext>dim</mtext> </mrow> <mspace width="0.5em"/> <mi>i</mi> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msub> <mi>δ</mi> <mi mathvariant="italic">ik</mi> </msub> <mo stretchy="false">≝</mo> <mtext>Kronecker&apos;s delta</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mrow> <mi>tanh</mi> <mo stretchy="false">≝</mo> <mtext>Hyperbolic tangent.</mtext> </mrow> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <mrow> <mi>tanh</mi> <mo stretchy="false">→</mo> <mtext>soft sign</mtext> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mrow> <mi>&sigma;</mi> <mo stretchy="false">≝</mo> <mtext>Sigmoid function.</mtext> </mrow> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <mrow> <mi>&sigma;</mi> <mo stretchy="false">→</mo> <mrow> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <mrow> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <mtext>soft sign</mtext> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> <mo stretchy="false">+</mo> <mn>1</mn> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> <mo stretchy="false">÷</mo> <mn>2</mn> </mrow> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msub> <mi>x</mi> <mi>j</mi> </msub> <mo stretchy="false">≝</mo> <mtext>Input.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mover accent="true"> <msub> <mi>s</mi> <mi>k</mi> </msub> <mo>¯ </mo> </mover> <mo stretchy="false">≝</mo> <mtext>Previous state.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msub> <mi>m</mi> <mi>i</mi> </msub> <mo stretchy="false">≝</mo> <mtext>Output of main layer.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msubsup> <mi>W</mi> <mi mathvariant="italic">ij</mi> <mi>m</mi> </msubsup> <mo stretchy="false">≝</mo> <mtext>Weights of main layer for input.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msubsup> <mi>U</mi> <mi mathvariant="italic">ik</mi> <mi>m</mi> </msubsup> <mo stretchy="false">≝</mo> <mtext>Weights of main layer for state.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msubsup> <mi>b</mi> <mi>i</mi> <mi>m</mi> </msubsup> <mo stretchy="false">≝</mo> <mtext>Bias of main layer.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msub> <mi>f</mi> <mi>i</mi> </msub> <mo stretchy="false">≝</mo> <mtext>Output of forget gate.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msubsup> <mi>W</mi> <mi mathvariant="italic">ij</mi> <mi>f</mi> </msubsup> <mo stretchy="false">≝</mo> <mtext>Weights of forget gate for input.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msubsup> <mi>U</mi> <mi mathvariant="italic">ik</mi> <mi>f</mi> </msubsup> <mo stretchy="false">≝</mo> <mtext>Weights of forget gate for state.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msubsup> <mi>b</mi> <mi>i</mi> <mi>f</mi> </msubsup> <mo stretchy="false">≝</mo> <mtext>Bias of forget gate.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msub> <mi>i</mi> <mi>i</mi> </msub> <mo stretchy="false">≝</mo> <mtext>Output of input gate.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msubsup> <mi>W</mi> <mi mathvariant="italic">ij</mi> <mi>i</mi> </msubsup> <mo stretchy="false">≝</mo> <mtext>Weights of input gate for input.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msubsup> <mi>U</mi> <mi mathvariant="italic">ik</mi> <mi>i</mi> </msubsup> <mo stretchy="false">≝</mo> <mtext>Weights of input gate for state.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msubsup> <mi>b</mi> <mi>i</mi> <mi>i</mi> </msubsup> <mo stretchy="false">≝</mo> <mtext>Bias of input gate.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msub> <mi>o</mi> <mi>i</mi> </msub> <mo stretchy="false">≝</mo> <mtext>Output of output gate.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msubsup> <mi>W</mi> <mi mathvariant="italic">ij</mi> <mi>o</mi> </msubsup> <mo stretchy="false">≝</mo> <mtext>Weights of output gate for input.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msubsup> <mi>U</mi> <mi mathvariant="italic">ik</mi> <mi>o</mi> </msubsup> <mo stretchy="false">≝</mo> <mtext>Weights of output gate for state.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msubsup> <mi>b</mi> <mi>i</mi> <mi>o</mi> </msubsup> <mo stretchy="false">≝</mo> <mtext>Bias of output gate.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mrow> <msub> <mi>y</mi> <mi>i</mi> </msub> <mo stretchy="false">≝</mo> <mtext>Output</mtext> </mrow> <mi>.</mi> </mrow> </mtd> </mtr> </mtable> </semantics> </math> |
+/// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mrow> <msub> <mi>y</mi> <mi>i</mi> </msub> <mo stretchy="false">=</mo> <mi>φ</mi> </mrow> <mrow> <mo fence="true" form="prefix" stretchy="true">(</mo> <mrow> <mrow> <mrow> <munder> <mo stretchy="false">∑</mo> <mi>j</mi> </munder> <msub> <mi>W</mi> <mi mathvariant="italic">ij</mi> </msub> </mrow> <mrow> <msub> <mi>x</mi> <mi>j</mi> </msub> <mo stretchy="false">+</mo> <mrow> <munder> <mo stretchy="false">∑</mo> <mi>k</mi> </munder> <msub> <mi>U</mi> <mi mathvariant="italic">ik</mi> </msub> </mrow> </mrow> <mrow> <msub> <mi>s</mi> <mi>k</mi> </msub> <mo stretchy="false">+</mo> <msub> <mi>b</mi> <mi>i</mi> </msub> </mrow> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="true">)</mo> </mrow> </mrow> </semantics> </math> |
+/// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mtable columnalign="left"> <mtr> <mtd> <mrow> <mi>i</mi> <mo stretchy="false">≝</mo> <mtext>Index of output.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>j</mi> <mo stretchy="false">≝</mo> <mtext>Index of input.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mrow> <mi>k</mi> <mo stretchy="false">≝</mo> <mtext>Index of state.</mtext> </mrow> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <mrow> <mtext>dim</mtext> <mspace width="0.5em"/> <mrow> <mi>k</mi> <mo stretchy="false">=</mo> <mtext>dim</mtext> </mrow> <mspace width="0.5em"/> <mi>i</mi> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msub> <mi>y</mi> <mi>i</mi> </msub> <mo stretchy="false">≝</mo> <mtext>Output.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>φ</mi> <mo stretchy="false">≝</mo> <mtext>Activation functin.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msub> <mi>W</mi> <mi mathvariant="italic">ij</mi> </msub> <mo stretchy="false">≝</mo> <mtext>Weights for input.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msub> <mi>x</mi> <mi>j</mi> </msub> <mo stretchy="false">≝</mo> <mtext>Input.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msub> <mi>U</mi> <mi mathvariant="italic">ik</mi> </msub> <mo stretchy="false">≝</mo> <mtext>Weights for state.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msub> <mi>s</mi> <mi>k</mi> </msub> <mo stretchy="false">≝</mo> <mtext>State.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msub> <mi>b</mi> <mi>i</mi> </msub> <mo stretchy="false">≝</mo> <mtext>Bias.</mtext> </mrow> </mtd> </mtr> </mtable> </semantics> </math> ||
 ///
 /// - `OUT` : Dimension of output.
 /// - `IN` : Dimension of input.
 #[derive(Debug, Clone, PartialEq)]
-pub struct Lstm<const OUT: usize, const IN: usize> {
-    main_layer: Layer<OUT, IN>,
+pub struct MlLayer<const OUT: usize, const IN: usize> {
+    layer: Layer<OUT, IN>,
 
-    f_gate: Layer<OUT, IN>,
-    i_gate: Layer<OUT, IN>,
-    o_gate: Layer<OUT, IN>,
+    total_grad: Weights<OUT, IN>,
+    momentum_1: Weights<OUT, IN>,
+    momentum_2: MathVec<OUT>,
+    square_average: Weights<OUT, IN>,
+    step: u64,
+    optimizer: Optimizer,
 
-    tanh: Activation
-}
+    tmp_error: MathVec<OUT>,
+    tmp_grad: Weights<OUT, IN>,
+    tmp_errors: Vec<MathVec<OUT>>,
 
-impl<const OUT: usize, const IN: usize> Lstm<OUT, IN> {
-    /// Creates Lstm.
-    ///
-    /// - _Return_ : Lstm.
-    #[inline]
-    pub fn new() -> Self {
-        Self {
-            main_layer: Layer::<OUT, IN>::new(Activation::SoftSign, true),
+    mask: Option<Vec<bool>>,
 
-            f_gate: Layer::<OUT, IN>::new(Activation::Sigmoid, true),
-            i_gate: Layer::<OUT, IN>::new(Activation::Sigmoid, true),
-            o_gate: Layer::<OUT, IN>::new(Activation::Sigmoid, true),
+    // For slope of Activation::PReLU.
+    slope_grad: f32,
+    slope_momentum_1: f32,
+    slope_momentum_2: f32,
+    slope_square_average: f32
+}
 
-            tanh: Activation::SoftSign
-        }
+#[inline]
+fn penalty_grad(weight: f32, l1: f32, l2: f32) -> f32 {
+    let sign = if weight > 0.0 {
+        1.0
+    } else if weight < 0.0 {
+        -1.0
+    } else {
+        0.0
+    };
+
+    (l1 * sign) + (l2 * weight)
+}
+
+// Clips each value given by for_each into [-|limit|, |limit|].
+#[inline]
+fn clip_by_value<F>(limit: f32, for_each: F)
+where F: FnOnce(&mut dyn FnMut(&mut f32)) {
+    if limit.is_nan() {
+        return;
     }
 
-    /// Gets immutable main layer.
-    ///
-    /// - _Return_ : Main layer.
-    #[inline]
-    pub fn main_layer(&self) -> &Layer<OUT, IN> {&self.main_layer}
+    let limit = abs(limit);
 
-    /// Gets mutable main layer.
-    ///
-    /// - _Return_ : Main layer.
-    #[inline]
-    pub fn main_layer_mut(&mut self) -> &mut Layer<OUT, IN> {
-        &mut self.main_layer
+    for_each(&mut |val| {*val = val.clamp(-limit, limit);});
+}
+
+// Scales each value given by for_each so that L2 norm of them is
+// |max_norm| or less, and returns L2 norm before scaling.
+//
+// Values are divided by the max absolute value before squaring,
+// so sum of squares does not overflow even if gradients are large.
+fn clip_by_global_norm<F>(max_norm: f32, mut for_each: F) -> f32
+where F: FnMut(&mut dyn FnMut(&mut f32)) {
+    let mut max: f32 = 0.0;
+
+    for_each(&mut |val| {
+        max = if val.is_nan() || max.is_nan() {
+            f32::NAN
+        } else {
+            max.max(abs(*val))
+        };
+    });
+
+    if !max.is_finite() || (max == 0.0) {
+        return max;
     }
 
-    /// Gets immutable forget gate.
-    ///
-    /// - _Return_ : Forget gate.
-    #[inline]
-    pub fn f_gate(&self) -> &Layer<OUT, IN> {&self.f_gate}
+    let mut sum: f32 = 0.0;
 
-    /// Gets mutable forget gate.
-    ///
-    /// - _Return_ : Forget gate.
-    #[inline]
-    pub fn f_gate_mut(&mut self) -> &mut Layer<OUT, IN> {&mut self.f_gate}
+    for_each(&mut |val| {
+        let val = *val / max;
 
-    /// Gets immutable input gate.
-    ///
-    /// - _Return_ : Input gate.
-    #[inline]
-    pub fn i_gate(&self) -> &Layer<OUT, IN> {&self.i_gate}
+        sum += val * val;
+    });
 
-    /// Gets mutable input gate.
-    ///
-    /// - _Return_ : Input gate.
-    #[inline]
-    pub fn i_gate_mut(&mut self) -> &mut Layer<OUT, IN> {&mut self.i_gate}
+    // norm = max * scaled_norm
+    let scaled_norm = sqrt(sum);
+    let max_norm = abs(max_norm);
 
-    /// Gets immutable output gate.
-    ///
-    /// - _Return_ : Output gate.
-    #[inline]
-    pub fn o_gate(&self) -> &Layer<OUT, IN> {&self.o_gate}
+    if scaled_norm > (max_norm / max) {
+        let scale = (max_norm / max) / scaled_norm;
 
-    /// Gets mutable output gate.
-    ///
-    /// - _Return_ : Output gate.
-    #[inline]
-    pub fn o_gate_mut(&mut self) -> &mut Layer<OUT, IN> {&mut self.o_gate}
+        for_each(&mut |val| {*val *= scale;});
+    }
 
-    /// Calculates only state.
-    ///
-    /// - `input` : Input.
-    /// - `prev_state` : Previous state.
-    /// - `next_state` : Buffer for next state.
-    /// - `tmpbuf` : Temporary buffer for this function to work.
-    pub fn calc_state(
-        &self,
-        input: &MathVec<IN>,
-        prev_state: &MathVec<OUT>,
-        next_state: &mut MathVec<OUT>,
-        tmpbuf: &mut MathVec<OUT>
-    ) {
-        // state = (f_gate * prev_state) + (i_gate * main_layer);
-        self.main_layer.calc(input, Some(prev_state), next_state);
-        self.i_gate.calc(input, Some(prev_state), tmpbuf);
-        next_state.pointwise_mul_assign(tmpbuf);
+    max * scaled_norm
+}
 
-        self.f_gate.calc(input, Some(prev_state), tmpbuf);
-        tmpbuf.pointwise_mul_assign(prev_state);
+const BETA_1: f32 = 0.9;
+const BETA_INV_1: f32 = 1.0 - BETA_1;
 
-        *next_state += tmpbuf;
-    }
+const BETA_2: f32 = 0.999;
+const BETA_INV_2: f32 = 1.0 - BETA_2;
 
-    /// Calculates state and output.
-    ///
-    /// - `input` : Input.
-    /// - `prev_state` : Previous state.
-    /// - `output` : Buffer for output.
-    /// - `next_state` : Buffer for next state.
-    /// - `tmpbuf` : Temporary buffer for this function to work.
-    pub fn calc(
+// Parameters updated by Optimizer::step(), and states of optimizer for them.
+struct OptimizerSlices<'a> {
+    weights: &'a mut [f32],
+    grads: &'a [f32],
+    momentum_1: &'a mut [f32],
+    square_average: &'a mut [f32]
+}
+
+// Updates momentum 2 of Optimizer::Adam shared by a group of parameters,
+// and returns learning rate for the group.
+//
+// - dot_product : Sum of squares of gradients of the group.
+#[inline]
+fn next_adam_rate(rate: f32, momentum_2: &mut f32, dot_product: f32) -> f32 {
+    *momentum_2 = (BETA_2 * *momentum_2) + (BETA_INV_2 * dot_product);
+
+    rate / (sqrt(*momentum_2) + f32::EPSILON)
+}
+
+impl Optimizer {
+    // Updates parameters by one step.
+    //
+    // - adam_rate : Learning rate from next_adam_rate(). Only for Adam.
+    // - step : Count of update before this.
+    fn step(
         &self,
-        input: &MathVec<IN>,
-        prev_state: &MathVec<OUT>,
-        output: &mut MathVec<OUT>,
-        next_state: &mut MathVec<OUT>,
-        tmpbuf: &mut MathVec<OUT>
+        rate: f32,
+        adam_rate: f32,
+        step: u64,
+        slices: OptimizerSlices
     ) {
-        self.calc_state(input, prev_state, next_state, tmpbuf);
+        let iter = slices.weights.iter_mut().zip(
+            slices.grads.iter().zip(
+                slices.momentum_1.iter_mut().zip(
+                    slices.square_average.iter_mut()
+                )
+            )
+        );
 
-        // output = o_gate * tanh(state)
-        self.o_gate.calc(input, Some(prev_state), output);
+        match *self {
+            Self::Adam => {
+                iter.for_each(|(w, (g, (v, _)))| {
+                    *v = (BETA_1 * *v) + (BETA_INV_1 * *g);
 
-        output.as_mut_array().iter_mut().zip(
-            next_state.as_array().iter()
-        ).for_each(|(output_one, next_s)| {
-            *output_one *= self.tanh.activate(*next_s);
-        });
-    }
+                    *w -= adam_rate * *v;
+                });
+            },
 
-    /// Accesses each immutable weight with closure.
-    ///
-    /// - `f` : Closure.
-    #[inline]
-    pub fn for_each_weight<F>(&self, mut f: F) where F: FnMut(&f32) {
-        self.main_layer.weights().iter().for_each(|val| {f(val)});
-        self.f_gate.weights().iter().for_each(|val| {f(val)});
-        self.i_gate.weights().iter().for_each(|val| {f(val)});
-        self.o_gate.weights().iter().for_each(|val| {f(val)});
-    }
+            Self::Sgd => iter.for_each(|(w, (g, _))| {*w -= rate * *g;}),
 
-    /// Accesses each mutable weight with closure.
-    ///
-    /// - `f` : Closure.
-    #[inline]
-    pub fn for_each_weight_mut<F>(
-        &mut self,
-        mut f: F
-    ) where F: FnMut(&mut f32) {
-        self.main_layer.mut_weights().iter_mut().for_each(|val| {f(val)});
-        self.f_gate.mut_weights().iter_mut().for_each(|val| {f(val)});
-        self.i_gate.mut_weights().iter_mut().for_each(|val| {f(val)});
-        self.o_gate.mut_weights().iter_mut().for_each(|val| {f(val)});
-    }
-}
+            Self::Momentum {momentum, nesterov} => {
+                iter.for_each(|(w, (g, (v, _)))| {
+                    *v = (momentum * *v) + *g;
 
-/// Cache for state error of [`MlLstm`].
-///
-/// - `OUT` : Output of [`MlLstm`].
-/// - `IN` : Input of [`MlLstm`].
-#[derive(Debug, Clone, PartialEq)]
-pub struct MlLstmStateCache<const OUT: usize, const IN: usize> {
-    input: MathVec<IN>,
-    prev_state: MathVec<OUT>,
+                    *w -= if nesterov {
+                        rate * (*g + (momentum * *v))
+                    } else {
+                        rate * *v
+                    };
+                });
+            },
 
-    main_layer_cache: MlCache<OUT, IN>,
+            Self::RmsProp {decay, epsilon} => {
+                let decay_inv = 1.0 - decay;
 
-    f_gate_cache: MlCache<OUT, IN>,
-    i_gate_cache: MlCache<OUT, IN>,
+                iter.for_each(|(w, (g, (_, s)))| {
+                    *s = (decay * *s) + (decay_inv * *g * *g);
 
-    state: MathVec<OUT>
-}
+                    *w -= rate * *g / (sqrt(*s) + epsilon);
+                });
+            },
 
-impl<const OUT: usize, const IN: usize> MlLstmStateCache<OUT, IN> {
-    /// Creates MlLstmStateCache.
+            Self::BiasCorrectedAdam {beta_1, beta_2, epsilon} |
+            Self::AdamW {beta_1, beta_2, epsilon, ..} => {
+                let decay = match *self {
+                    Self::AdamW {weight_decay, ..} =>
+                        1.0 - (rate * weight_decay),
+
+                    _ => 1.0
+                };
+
+                let beta_inv_1 = 1.0 - beta_1;
+                let beta_inv_2 = 1.0 - beta_2;
+
+                let correction_1 = 1.0 / (1.0 - powi(beta_1, step + 1));
+                let correction_2 = 1.0 / (1.0 - powi(beta_2, step + 1));
+
+                iter.for_each(|(w, (g, (v, s)))| {
+                    *w *= decay;
+
+                    *v = (beta_1 * *v) + (beta_inv_1 * *g);
+                    *s = (beta_2 * *s) + (beta_inv_2 * *g * *g);
+
+                    let v_hat = *v * correction_1;
+                    let s_hat = *s * correction_2;
+
+                    *w -= rate * v_hat / (sqrt(s_hat) + epsilon);
+                });
+            }
+        }
+    }
+}
+impl<const OUT: usize, const IN: usize> MlLayer<OUT, IN> {
+    /// Creates MlLayer.
     ///
-    /// - _Return_ : MlLstmStateCache.
+    /// - `layer` : Base [`Layer`].
+    /// - _Return_ : MlLayer.
     #[inline]
-    pub fn new() -> Self {
+    pub fn new(layer: Layer<OUT, IN>) -> Self {
+        let has_state_weights = layer.weights.ptr_s.is_some();
+
         Self {
-            input: MathVec::<IN>::new(),
-            prev_state: MathVec::<OUT>::new(),
+            layer: layer,
 
-            main_layer_cache: MlCache::<OUT, IN>::new(),
-            f_gate_cache: MlCache::<OUT, IN>::new(),
-            i_gate_cache: MlCache::<OUT, IN>::new(),
+            total_grad: Weights::<OUT, IN>::new(has_state_weights),
+            momentum_1: Weights::<OUT, IN>::new(has_state_weights),
+            momentum_2: MathVec::<OUT>::default(),
+            square_average: Weights::<OUT, IN>::new(has_state_weights),
+            step: 0,
+            optimizer: Optimizer::default(),
 
-            state: MathVec::<OUT>::new()
+            tmp_error: MathVec::<OUT>::default(),
+            tmp_grad: Weights::<OUT, IN>::new(has_state_weights),
+            tmp_errors: Vec::<MathVec<OUT>>::new(),
+
+            mask: None,
+
+            slope_grad: 0.0,
+            slope_momentum_1: 0.0,
+            slope_momentum_2: 0.0,
+            slope_square_average: 0.0
         }
     }
 
-    /// Gets input.
+    /// Drops Base [`Layer`].
     ///
-    /// - _Return_ : Input.
+    /// - _Return_ : [`Layer`].
     #[inline]
-    pub fn input(&self) -> &MathVec<IN> {&self.input}
+    pub fn drop(self) -> Layer<OUT, IN> {self.layer}
 
-    /// Gets previous state.
+    /// Clears internal data for study.
+    #[inline]
+    pub fn clear_study_data(&mut self) {
+        self.total_grad.clear();
+        self.momentum_1.clear();
+        self.momentum_2.clear();
+        self.square_average.clear();
+        self.step = 0;
+
+        self.slope_grad = 0.0;
+        self.slope_momentum_1 = 0.0;
+        self.slope_momentum_2 = 0.0;
+        self.slope_square_average = 0.0;
+    }
+
+    /// Gets optimizer.
     ///
-    /// - _Return_ : Previous state.
+    /// - _Return_ : Optimizer.
     #[inline]
-    pub fn prev_state(&self) -> &MathVec<OUT> {&self.prev_state}
+    pub fn optimizer(&self) -> &Optimizer {&self.optimizer}
 
-    /// Gets cache of main layer.
+    /// Sets optimizer.
     ///
-    /// - _Return_ : Cache of main layer.
+    /// Momenta of previous optimizer are not cleared. Call [`clear_study_data()`](Self::clear_study_data()) if you need.
+    ///
+    /// - `optimizer` : Optimizer.
     #[inline]
-    pub fn main_layer_cache(&self) -> &MlCache<OUT, IN> {
-        &self.main_layer_cache
+    pub fn set_optimizer(&mut self, optimizer: Optimizer) {
+        self.optimizer = optimizer;
     }
 
-    /// Gets cache of forget gate.
+    /// Gets count of [`update()`](Self::update()) since creation or [`clear_study_data()`](Self::clear_study_data()).
     ///
-    /// - _Return_ : Cache of forget gate.
+    /// - _Return_ : Count of update.
     #[inline]
-    pub fn f_gate_cache(&self) -> &MlCache<OUT, IN> {&self.f_gate_cache}
+    pub fn step(&self) -> u64 {self.step}
 
-    /// Gets cache of input gate.
+    /// Writes full state for machine learning into buffer as binary data.
     ///
-    /// - _Return_ : Cache of input gate.
-    #[inline]
-    pub fn i_gate_cache(&self) -> &MlCache<OUT, IN> {&self.i_gate_cache}
+    /// The data contains weights, total gradient, momenta of optimizer, count of update and mask of pruning, so machine learning can be resumed exactly by [`load_checkpoint()`](Self::load_checkpoint()).
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save_checkpoint(&self, buffer: &mut Vec<u8>) {
+        write_header(buffer, ModelKind::MlLayer, &[OUT, IN]);
 
-    /// Gets state.
+        self.write_state(buffer);
+    }
+
+    /// Creates MlLayer from binary data written by [`save_checkpoint()`](Self::save_checkpoint()).
     ///
-    /// - _Return_ : State.
-    #[inline]
-    pub fn state(&self) -> &MathVec<OUT> {&self.state}
-}
+    /// - `data` : Binary data.
+    /// - _Return_ : MlLayer.
+    pub fn load_checkpoint(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
 
-/// Cache for output error of [`MlLstm`].
-///
-/// - `OUT` : Output of [`MlLstm`].
-/// - `IN` : Input of [`MlLstm`].
-#[derive(Debug, Clone, PartialEq)]
-pub struct MlLstmOutputCache<const OUT: usize, const IN: usize> {
-    o_gate_cache: MlCache<OUT, IN>,
+        reader.read_header(ModelKind::MlLayer, &[("OUT", OUT), ("IN", IN)])?;
 
-    tanh_s: MathVec<OUT>,
-    d_tanh_s: MathVec<OUT>,
+        Self::read_state(&mut reader)
+    }
 
-    output: MathVec<OUT>
-}
+    fn write_state(&self, buffer: &mut Vec<u8>) {
+        self.layer.write_descriptor(buffer);
 
-impl<const OUT: usize, const IN: usize> MlLstmOutputCache<OUT, IN> {
-    /// Creates MlLstmOutputCache.
-    ///
-    /// - _Return_ : MlLstmOutputCache.
-    #[inline]
-    pub fn new() -> Self {
-        Self {
-            o_gate_cache: MlCache::<OUT, IN>::new(),
+        self.layer.weights.write_body(buffer);
+        self.total_grad.write_body(buffer);
+        self.momentum_1.write_body(buffer);
+        self.momentum_2.write_body(buffer);
+        self.square_average.write_body(buffer);
 
-            tanh_s: MathVec::<OUT>::new(),
-            d_tanh_s: MathVec::<OUT>::new(),
+        write_u64(buffer, self.step);
+        self.optimizer.write(buffer);
 
-            output: MathVec::<OUT>::new()
+        // Only PReLU has them, so other data keep the same format.
+        if self.has_slope() {
+            write_f32(buffer, self.slope_grad);
+            write_f32(buffer, self.slope_momentum_1);
+            write_f32(buffer, self.slope_momentum_2);
+            write_f32(buffer, self.slope_square_average);
+        }
+
+        // Flag, then 1 bit for each weight.
+        match &self.mask {
+            Some(mask) => {
+                write_u8(buffer, 1);
+
+                mask.chunks(8).for_each(|chunk| {
+                    write_u8(buffer, chunk.iter().enumerate().fold(
+                        0,
+                        |byte, (i, kept)| byte | ((*kept as u8) << i)
+                    ));
+                });
+            },
+
+            None => write_u8(buffer, 0)
         }
     }
 
-    /// Calculates output error.
+    fn read_state(reader: &mut ByteReader) -> Result<Self, ChobitAiError> {
+        let mut ret = Self::new(Layer::<OUT, IN>::read_descriptor(reader)?);
+
+        ret.layer.weights.read_body(reader)?;
+        ret.total_grad.read_body(reader)?;
+        ret.momentum_1.read_body(reader)?;
+        ret.momentum_2.read_body(reader)?;
+        ret.square_average.read_body(reader)?;
+
+        ret.step = reader.read_u64()?;
+        ret.optimizer = Optimizer::read(reader)?;
+
+        if ret.has_slope() {
+            ret.slope_grad = reader.read_f32()?;
+            ret.slope_momentum_1 = reader.read_f32()?;
+            ret.slope_momentum_2 = reader.read_f32()?;
+            ret.slope_square_average = reader.read_f32()?;
+        }
+
+        if reader.read_u8()? != 0 {
+            let len = ret.layer.weights.non_bias().len();
+            let bytes = reader.read_bytes(len.div_ceil(8))?;
+
+            ret.mask = Some(
+                (0..len).map(|i| ((bytes[i / 8] >> (i % 8)) & 1) != 0)
+                    .collect()
+            );
+        }
+
+        Ok(ret)
+    }
+
+    /// Gets immutable total gradient.
     ///
-    /// | Formula |
-    /// |:-:|
-    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mi>e</mi> <mo stretchy="false">=</mo> <mrow> <mi>o</mi> <mo stretchy="false">−</mo> <mi>t</mi> </mrow> </mrow> </semantics> </math> |
-    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mtable columnalign="left"> <mtr> <mtd> <mrow> <mi>e</mi> <mo stretchy="false">≝</mo> <mtext>Error.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>o</mi> <mo stretchy="false">≝</mo> <mtext>Actual output.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>t</mi> <mo stretchy="false">≝</mo> <mtext>Correct output.</mtext> </mrow> </mtd> </mtr> </mtable> </semantics> </math> |
+    /// Total gradient is increased every time to call [`study()`](Self::study()).
     ///
-    /// - `train_out` : Correct output.
-    /// - `output_error` : Buffer for output error.
+    /// - _Return_ : Total gradient.
     #[inline]
-    pub fn calc_output_error(
-        &self,
-        train_out: &MathVec<OUT>,
-        output_error: &mut MathVec<OUT>
-    ) {
-        output_error.copy_from(&self.output);
-        *output_error -= train_out;
-    }
+    pub fn total_grad(&self) -> &Weights<OUT, IN> {&self.total_grad}
 
-    /// Gets cache of output gate.
+    /// Gets mutable total gradient.
     ///
-    /// - _Return_ : Cache of output gate.
+    /// Total gradient is increased every time to call [`study()`](Self::study()).
+    ///
+    /// - _Return_ : Total gradient.
     #[inline]
-    pub fn o_gate_cache(&self) -> &MlCache<OUT, IN> {&self.o_gate_cache}
+    pub fn total_grad_mut(&mut self) -> &mut Weights<OUT, IN> {
+        &mut self.total_grad
+    }
 
-    /// Gets output of tanh(state).
+    /// Gets total gradient of slope of [`Activation::PReLU`].
     ///
-    /// - _Return_ : Output of tanh(state).
+    /// - _Return_ : Total gradient of slope. `None` if activation function is not [`Activation::PReLU`].
     #[inline]
-    pub fn tanh_s(&self) -> &MathVec<OUT> {&self.tanh_s}
+    pub fn slope_grad(&self) -> Option<f32> {
+        self.has_slope().then_some(self.slope_grad)
+    }
 
-    /// Gets derivative of tanh(state).
+    /// Gets mutable total gradient of slope of [`Activation::PReLU`].
     ///
-    /// - _Return_ : Derivative of tanh(state).
+    /// - _Return_ : Total gradient of slope. `None` if activation function is not [`Activation::PReLU`].
     #[inline]
-    pub fn d_tanh_s(&self) -> &MathVec<OUT> {&self.d_tanh_s}
+    pub fn slope_grad_mut(&mut self) -> Option<&mut f32> {
+        self.has_slope().then_some(&mut self.slope_grad)
+    }
 
-    /// Gets output.
-    ///
-    /// - _Return_ : Output.
     #[inline]
-    pub fn output(&self) -> &MathVec<OUT> {&self.output}
-}
+    fn has_slope(&self) -> bool {
+        matches!(self.layer.activation, Activation::PReLU(_))
+    }
 
-/// LSTM for machine learning.
-///
-/// See [`Lstm`] for details.
-///
-/// - `OUT` : Dimension of output.
-/// - `IN` : Dimension of input.
-#[derive(Debug, Clone, PartialEq)]
-pub struct MlLstm<const OUT: usize, const IN: usize> {
-    main_layer: MlLayer<OUT, IN>,
-    f_gate: MlLayer<OUT, IN>,
-    i_gate: MlLayer<OUT, IN>,
-    o_gate: MlLayer<OUT, IN>,
-    tanh: Activation,
+    // Total gradient and total gradient of slope if it exists.
+    #[inline]
+    fn for_each_grad<F>(&self, f: &mut F) where F: FnMut(&f32) + ?Sized {
+        self.total_grad.iter().for_each(&mut *f);
 
-    input_error_main_by_output_error: MathVec<IN>,
-    input_error_main_by_state_error: MathVec<IN>,
-    input_error_f_by_output_error: MathVec<IN>,
-    input_error_f_by_state_error: MathVec<IN>,
-    input_error_i_by_output_error: MathVec<IN>,
-    input_error_i_by_state_error: MathVec<IN>,
-    input_error_o_by_output_error: MathVec<IN>,
-    input_error_o_by_state_error: MathVec<IN>,
+        if self.has_slope() {
+            f(&self.slope_grad);
+        }
+    }
 
-    prev_state_error_main_by_output_error: MathVec<OUT>,
-    prev_state_error_main_by_state_error: MathVec<OUT>,
-    prev_state_error_f_by_output_error: MathVec<OUT>,
-    prev_state_error_f_by_state_error: MathVec<OUT>,
-    prev_state_error_i_by_output_error: MathVec<OUT>,
-    prev_state_error_i_by_state_error: MathVec<OUT>,
-    prev_state_error_o_by_output_error: MathVec<OUT>,
-    prev_state_error_o_by_state_error: MathVec<OUT>,
+    #[inline]
+    fn for_each_grad_mut<F>(
+        &mut self,
+        f: &mut F
+    ) where F: FnMut(&mut f32) + ?Sized {
+        self.total_grad.iter_mut().for_each(&mut *f);
 
-    tmp_error: MathVec<OUT>
-}
+        if self.has_slope() {
+            f(&mut self.slope_grad);
+        }
+    }
 
-impl<const OUT: usize, const IN: usize> MlLstm<OUT, IN> {
-    /// Creates MlLstm.
+    /// Clips each total gradient into `[-limit, limit]`.
     ///
-    /// - `lstm` : Base [`Lstm`].
-    /// - _Return_ : MlLstm.
+    /// - `limit` : Limit of absolute value. Negative value is treated as its absolute value. If NaN, nothing is clipped.
     #[inline]
-    pub fn new(lstm: Lstm<OUT, IN>) -> Self {
-        let Lstm::<OUT, IN> {main_layer, f_gate, i_gate, o_gate, tanh} = lstm;
+    pub fn clip_grad_by_value(&mut self, limit: f32) {
+        clip_by_value(limit, |f| self.for_each_grad_mut(f));
+    }
 
-        Self {
-            main_layer: MlLayer::<OUT, IN>::new(main_layer),
-            f_gate: MlLayer::<OUT, IN>::new(f_gate),
-            i_gate: MlLayer::<OUT, IN>::new(i_gate),
-            o_gate: MlLayer::<OUT, IN>::new(o_gate),
-            tanh: tanh,
+    /// Scales total gradient so that L2 norm of it is `max_norm` or less.
+    ///
+    /// - `max_norm` : Max L2 norm. Negative value is treated as its absolute value.
+    /// - _Return_ : L2 norm before clipping. If it is not finite, nothing is scaled.
+    #[inline]
+    pub fn clip_grad_by_global_norm(&mut self, max_norm: f32) -> f32 {
+        clip_by_global_norm(max_norm, |f| self.for_each_grad_mut(f))
+    }
 
-            input_error_main_by_output_error: MathVec::<IN>::new(),
-            input_error_main_by_state_error: MathVec::<IN>::new(),
-            input_error_f_by_output_error: MathVec::<IN>::new(),
-            input_error_f_by_state_error: MathVec::<IN>::new(),
-            input_error_i_by_output_error: MathVec::<IN>::new(),
-            input_error_i_by_state_error: MathVec::<IN>::new(),
-            input_error_o_by_output_error: MathVec::<IN>::new(),
-            input_error_o_by_state_error: MathVec::<IN>::new(),
+    /// Checks that total gradient and weights are finite.
+    ///
+    /// - _Return_ : [`ChobitAiError::NonFinite`] if non-finite number is found. Name of the layer is `"layer"`.
+    #[inline]
+    pub fn check_finite(&self) -> Result<(), ChobitAiError> {
+        self.check_grad("layer")?;
+        self.check_weights("layer")
+    }
 
-            prev_state_error_main_by_output_error: MathVec::<OUT>::new(),
-            prev_state_error_main_by_state_error: MathVec::<OUT>::new(),
-            prev_state_error_f_by_output_error: MathVec::<OUT>::new(),
-            prev_state_error_f_by_state_error: MathVec::<OUT>::new(),
-            prev_state_error_i_by_output_error: MathVec::<OUT>::new(),
-            prev_state_error_i_by_state_error: MathVec::<OUT>::new(),
-            prev_state_error_o_by_output_error: MathVec::<OUT>::new(),
-            prev_state_error_o_by_state_error: MathVec::<OUT>::new(),
+    fn check_grad(&self, name: &'static str) -> Result<(), ChobitAiError> {
+        let mut is_finite = true;
 
-            tmp_error: MathVec::<OUT>::new()
+        self.for_each_grad(&mut |val| {is_finite &= val.is_finite();});
+
+        if is_finite {
+            Ok(())
+        } else {
+            Err(ChobitAiError::NonFinite {layer: name, part: "total_grad"})
         }
     }
 
-    /// Drops base [`Lstm`].
-    ///
-    /// - _Return_ : [`Lstm`].
+    fn check_weights(&self, name: &'static str) -> Result<(), ChobitAiError> {
+        let slope = match self.layer.activation {
+            Activation::PReLU(slope) => slope,
+            _ => 0.0
+        };
+
+        if self.layer.weights.iter().all(|val| val.is_finite())
+            && slope.is_finite()
+        {
+            Ok(())
+        } else {
+            Err(ChobitAiError::NonFinite {layer: name, part: "weights"})
+        }
+    }
+
+    // Adds total gradient of other into self, and clears that of other.
     #[inline]
-    pub fn drop(self) -> Lstm<OUT, IN> {
-        let Self {main_layer, f_gate, i_gate, o_gate, tanh, ..} = self;
+    fn take_grad_from(&mut self, other: &mut Self) {
+        self.total_grad.iter_mut().zip(other.total_grad.iter()).for_each(
+            |(grad, other_grad)| {*grad += *other_grad;}
+        );
 
-        Lstm::<OUT, IN> {
-            main_layer: main_layer.drop(),
+        other.total_grad.clear();
 
-            f_gate: f_gate.drop(),
-            i_gate: i_gate.drop(),
-            o_gate: o_gate.drop(),
+        self.slope_grad += other.slope_grad;
+        other.slope_grad = 0.0;
+    }
+
+    #[inline]
+    fn copy_weights_from(&mut self, other: &Self) {
+        self.layer.weights.copy_from(&other.layer.weights);
+        self.layer.activation = other.layer.activation;
+    }
+
+    /// Adds gradient of L1 and L2 penalty of weights into total gradient.
+    ///
+    /// Penalty is `l1 * Σ|W| + (l2 / 2) * ΣW²`, so `l1 * sign(W) + l2 * W` is added. Bias is not penalized.
+    /// Call this after [`study()`](Self::study()) and before [`update()`](Self::update()).
+    ///
+    /// - `l1` : Coefficient of L1 penalty.
+    /// - `l2` : Coefficient of L2 penalty.
+    pub fn add_weight_penalty(&mut self, l1: f32, l2: f32) {
+        let weights = &self.layer.weights;
+
+        self.total_grad.input_weights_mut().as_flattened_mut().iter_mut().zip(
+            weights.input_weights().as_flattened().iter()
+        ).for_each(|(grad, weight)| {
+            *grad += penalty_grad(*weight, l1, l2);
+        });
 
-            tanh: tanh
+        if let (Some(total_grad), Some(weights)) = (
+            self.total_grad.state_weights_mut(),
+            weights.state_weights()
+        ) {
+            total_grad.as_flattened_mut().iter_mut().zip(
+                weights.as_flattened().iter()
+            ).for_each(|(grad, weight)| {
+                *grad += penalty_grad(*weight, l1, l2);
+            });
         }
     }
 
-    /// Clears internal data for study.
+    /// Prunes weights by magnitude, and masks them.
+    ///
+    /// Pruned weights become `0.0`, and [`update()`](Self::update()) keeps them `0.0` until [`clear_mask()`](Self::clear_mask()). Bias is not pruned.  
+    /// Weights masked before are still masked. Mask is written by [`save_checkpoint()`](Self::save_checkpoint()).
+    ///
+    /// - `pruning` : Way of pruning.
+    /// - _Return_ : Number of masked weights.
+    pub fn prune(&mut self, pruning: Pruning) -> usize {
+        if let Some(threshold) =
+            pruning.threshold(self.layer.weights.non_bias().iter())
+        {
+            let weights = self.layer.weights.non_bias_mut();
+
+            let mask = self.mask.get_or_insert_with(
+                || vec![true; weights.len()]
+            );
+
+            mask.iter_mut().zip(weights.iter_mut()).for_each(
+                |(kept, weight)| if abs(*weight) <= threshold {
+                    *weight = 0.0;
+                    *kept = false;
+                }
+            );
+        }
+
+        self.masked_len()
+    }
+
+    /// Gets mask of pruning.
+    ///
+    /// - _Return_ : Mask of weights for input and weights for state in this order. `false` means pruned. `None` if nothing has been pruned.
     #[inline]
-    pub fn clear_study_data(&mut self) {
-        self.main_layer.clear_study_data();
-        self.f_gate.clear_study_data();
-        self.i_gate.clear_study_data();
-        self.o_gate.clear_study_data();
+    pub fn mask(&self) -> Option<&[bool]> {self.mask.as_deref()}
+
+    /// Clears mask of pruning, so pruned weights are updated again.
+    #[inline]
+    pub fn clear_mask(&mut self) {self.mask = None;}
+
+    #[inline]
+    fn masked_len(&self) -> usize {
+        self.mask.as_ref().map_or(
+            0,
+            |mask| mask.iter().filter(|kept| !**kept).count()
+        )
     }
 
-    /// Writes information on [`MlLstmStateCache`] for [`study_state()`](Self::study_state()) or [`MlLstm::study()`].
+    // Keeps masked values 0.
+    #[inline]
+    fn apply_mask(mask: Option<&[bool]>, values: &mut Weights<OUT, IN>) {
+        if let Some(mask) = mask {
+            values.non_bias_mut().iter_mut().zip(mask.iter()).for_each(
+                |(val, kept)| if !*kept {
+                    *val = 0.0;
+                }
+            );
+        }
+    }
+
+    /// Writes infomation on [`MlCache`] for [`study()`](Self::study()).
     ///
     /// - `input` : Input.
-    /// - `prev_state` : Previous state.
+    /// - `state` : State if it exists.
     /// - `cache` : Cache.
-    pub fn ready_state_cache(
+    pub fn ready(
         &self,
         input: &MathVec<IN>,
-        prev_state: &MathVec<OUT>,
-        cache: &mut MlLstmStateCache<OUT, IN>
+        state: Option<&MathVec<OUT>>,
+        cache: &mut MlCache<OUT, IN>
     ) {
         cache.input.copy_from(input);
-        cache.prev_state.copy_from(prev_state);
 
-        self.main_layer.ready(
-            input,
-            Some(prev_state),
-            &mut cache.main_layer_cache
-        );
-        self.f_gate.ready(input, Some(prev_state), &mut cache.f_gate_cache);
-        self.i_gate.ready(input, Some(prev_state), &mut cache.i_gate_cache);
+        match state {
+            Some(state) => {
+                cache.state.copy_from(state);
+                cache.has_state = true;
+            },
 
-        cache.state.as_mut_array().iter_mut().zip(
-            prev_state.as_array().iter()
-        ).zip(
-            cache.f_gate_cache.output.as_array().iter()
-        ).zip(
-            cache.i_gate_cache.output.as_array().iter()
-        ).zip(
-            cache.main_layer_cache.output.as_array().iter()
-        ).for_each(|((((state_one, p_state), f_out), i_out), main_out)| {
-            *state_one = (*p_state * *f_out) + (*i_out * *main_out);
+            None => {
+                cache.has_state = false;
+            }
+        }
+
+        self.layer.weights.calc(input, state, &mut cache.middle_value);
+
+        cache.d_activate.as_mut_array().iter_mut().zip(
+            cache.middle_value.as_array().iter()
+        ).for_each(|(d_f, m_value)| {
+            *d_f = self.layer.activation.d_activate(*m_value);
+        });
+
+        cache.d_slope.as_mut_array().iter_mut().zip(
+            cache.middle_value.as_array().iter()
+        ).for_each(|(d_slope, m_value)| {
+            *d_slope = self.layer.activation.d_slope(*m_value);
+        });
+
+        cache.output.as_mut_array().iter_mut().zip(
+            cache.middle_value.as_array().iter()
+        ).for_each(|(output_one, m_value)| {
+            *output_one = self.layer.activation.activate(*m_value);
         });
     }
 
-    /// Writes information on [`MlLstmOutputCache`] for [`study()`](Self::study()).
+    /// Writes infomation on [`MlCache`] for [`study()`](Self::study()) with dropout.
     ///
-    /// - `last_state_cache` : Cache written at [`ready_state_cache()`](Self::ready_state_cache()) just before.
+    /// Each output is dropped to 0 with probability `dropout`, and the others are scaled by `1 / (1 - dropout)`.
+    /// Derivative of activation function in the cache is masked in the same way, so [`study()`](Self::study()) backpropagates only through the kept outputs.
+    /// The mask is made from `rand`, so the same random sequence gives the same mask.
+    ///
+    /// - `input` : Input.
+    /// - `state` : State if it exists.
     /// - `cache` : Cache.
-    pub fn ready_output_cache(
+    /// - `dropout` : Probability of dropping. `[0.0, 1.0)`.
+    /// - `rand` : Generator of uniform random number in `[0.0, 1.0)`.
+    pub fn ready_with_dropout<F>(
         &self,
-        last_state_cache: &MlLstmStateCache<OUT, IN>,
-        output_cache: &mut MlLstmOutputCache<OUT, IN>
-    ) {
-        self.o_gate.ready(
-            &last_state_cache.input,
-            Some(&last_state_cache.prev_state),
-            &mut output_cache.o_gate_cache
-        );
+        input: &MathVec<IN>,
+        state: Option<&MathVec<OUT>>,
+        cache: &mut MlCache<OUT, IN>,
+        dropout: f32,
+        mut rand: F
+    ) where F: FnMut() -> f32 {
+        self.ready(input, state, cache);
 
-        last_state_cache.state.as_array().iter().zip(
-            output_cache.tanh_s.as_mut_array().iter_mut()
-        ).zip(
-            output_cache.d_tanh_s.as_mut_array().iter_mut()
-        ).zip(
-            output_cache.output.as_mut_array().iter_mut()
+        let scale = 1.0 / (1.0 - dropout);
+
+        cache.output.as_mut_array().iter_mut().zip(
+            cache.d_activate.as_mut_array().iter_mut()
         ).zip(
-            output_cache.o_gate_cache.output.as_array().iter()
-        ).for_each(
-            |((((s, tanh_s_one), d_tanh_s_one), output_one), o_out)| {
-                *tanh_s_one = self.tanh.activate(*s);
-                *d_tanh_s_one = self.tanh.d_activate(*s);
-                *output_one = *o_out * *tanh_s_one;
-            }
-        );
+            cache.d_slope.as_mut_array().iter_mut()
+        ).for_each(|((output_one, d_f), d_slope)| {
+            let mask = if rand() < dropout {0.0} else {scale};
+
+            *output_one *= mask;
+            *d_f *= mask;
+            *d_slope *= mask;
+        });
     }
 
     /// Accumulates gradient.
     ///
-    /// - `state_error` : Backpropagated state error.
-    /// - `cache` : Cache written at [`ready_state_cache()`](Self::ready_state_cache()).
-    /// - `input_error` : Error to backpropagate for previous output error.
-    /// - `prev_state_error` : Error to backpropagate previous state error.
-    pub fn study_state(
+    /// - `output_error` : Backpropagated output error.
+    /// - `next_state_error` : Backpropagated state error if it exists.
+    /// - `cache` : Cache generated by [MlLayer::ready].
+    /// - `input_error` : Error for previous output.
+    /// - `prev_state_error` : Error for previous state.
+    pub fn study(
         &mut self,
-        state_error: &MathVec<OUT>,
-        cache: &MlLstmStateCache<OUT, IN>,
+        output_error: &MathVec<OUT>,
+        next_state_error: Option<&MathVec<OUT>>,
+        cache: &MlCache<OUT, IN>,
         input_error: &mut MathVec<IN>,
-        prev_state_error: &mut MathVec<OUT>
+        prev_state_error: Option<&mut MathVec<OUT>>
     ) {
-        self.study_main_layer_with_state_error(state_error, cache);
-        self.study_f_gate_with_state_error(state_error, cache);
-        self.study_i_gate_with_state_error(state_error, cache);
-
-        input_error.copy_from(&self.input_error_main_by_state_error);
-        *input_error += &self.input_error_f_by_state_error;
-        *input_error += &self.input_error_i_by_state_error;
-
-        prev_state_error.copy_from(&self.prev_state_error_main_by_state_error);
-        *prev_state_error += &self.prev_state_error_f_by_state_error;
-        *prev_state_error += &self.prev_state_error_i_by_state_error;
+        self.calc_tmp_error(output_error, next_state_error, cache);
+        self.add_slope_grad(output_error, &cache.d_slope);
 
-        prev_state_error.as_mut_array().iter_mut().zip(
-            state_error.as_array().iter()
-        ).zip(
-            cache.f_gate_cache.output.as_array().iter()
-        ).for_each(|((p_state_e, state_e), f_out)| {
-            *p_state_e += *state_e * *f_out;
-        });
+        self.study_tmp_error(cache, input_error, prev_state_error);
     }
 
-    fn study_main_layer_with_state_error(
+    // Accumulates gradient and calculates errors by error of middle value
+    // in self.tmp_error.
+    fn study_tmp_error(
         &mut self,
-        state_error: &MathVec<OUT>,
-        cache: &MlLstmStateCache<OUT, IN>
+        cache: &MlCache<OUT, IN>,
+        input_error: &mut MathVec<IN>,
+        prev_state_error: Option<&mut MathVec<OUT>>
     ) {
-        self.tmp_error.as_mut_array().iter_mut().zip(
-            state_error.as_array().iter()
-        ).zip(
-            cache.i_gate_cache.output.as_array().iter()
-        ).for_each(|((tmp_e, state_e), i_out)| {
-            *tmp_e = *state_e * *i_out;
-        });
-
-        self.main_layer.study(
+        // add self.total_grad ----------
+        Weights::grad_with_weights(
             &self.tmp_error,
-            None,
-            &cache.main_layer_cache,
-            &mut self.input_error_main_by_state_error,
-            Some(&mut self.prev_state_error_main_by_state_error)
+            &cache.input,
+            cache.has_state.then(|| &cache.state),
+            &mut self.tmp_grad
         );
-    }
-
-    fn study_f_gate_with_state_error(
-        &mut self,
-        state_error: &MathVec<OUT>,
-        cache: &MlLstmStateCache<OUT, IN>
-    ) {
-        self.tmp_error.as_mut_array().iter_mut().zip(
-            state_error.as_array().iter()
-        ).zip(
-            cache.prev_state.as_array().iter()
-        ).for_each(|((tmp_e, state_e), p_state)| {
-            *tmp_e = *state_e * *p_state;
-        });
 
-        self.f_gate.study(
-            &self.tmp_error,
-            None,
-            &cache.f_gate_cache,
-            &mut self.input_error_f_by_state_error,
-            Some(&mut self.prev_state_error_f_by_state_error)
+        self.total_grad.iter_mut().zip(self.tmp_grad.iter()).for_each(
+            |(total_g, tmp_g)| {
+                *total_g += *tmp_g;
+            }
         );
-    }
 
-    fn study_i_gate_with_state_error(
-        &mut self,
-        state_error: &MathVec<OUT>,
-        cache: &MlLstmStateCache<OUT, IN>
-    ) {
-        self.tmp_error.as_mut_array().iter_mut().zip(
-            state_error.as_array().iter()
-        ).zip(
-            cache.main_layer_cache.output.as_array().iter()
-        ).for_each(|((tmp_e, state_e), main_out)| {
-            *tmp_e = *state_e * *main_out;
-        });
+        // calc errors ----------
+        self.layer.weights.grad_with_input(&self.tmp_error, input_error);
 
-        self.i_gate.study(
-            &self.tmp_error,
-            None,
-            &cache.i_gate_cache,
-            &mut self.input_error_i_by_state_error,
-            Some(&mut self.prev_state_error_i_by_state_error)
-        );
+        if let Some(prev_state_error) = prev_state_error {
+            if cache.has_state {
+                self.layer.weights.grad_with_state(
+                    &self.tmp_error,
+                    prev_state_error
+                );
+            }
+        }
     }
 
-    /// Studies weights with output error and state_error.
+    /// Writes infomation on [`MlBatchCache`] for [`study_batch()`](Self::study_batch()).
     ///
-    /// - `output_error` : Backpropagated output error.
-    /// - `state_error` : Backpropagated state error.
-    /// - `state_cache` : Cache written at [`ready_state_cache()`](Self::ready_state_cache()).
-    /// - `output_cache` : Cache written at [`ready_output_cache()`](Self::ready_output_cache()).
-    /// - `input_error` : Error to backpropagate for previous output error.
-    /// - `prev_state_error` : Error to backpropagate for previous state error.
-    pub fn study(
-        &mut self,
-        output_error: &MathVec<OUT>,
-        state_error: &MathVec<OUT>,
-        state_cache: &MlLstmStateCache<OUT, IN>,
-        output_cache: &MlLstmOutputCache<OUT, IN>,
-        input_error: &mut MathVec<IN>,
-        prev_state_error: &mut MathVec<OUT>
+    /// Result of each sample is the same as [`ready()`](Self::ready()). See [`Weights::calc_batch()`] for details.
+    ///
+    /// - `inputs` : Inputs. Its length is batch size.
+    /// - `states` : States if they exist.
+    /// - `cache` : Cache.
+    pub fn ready_batch(
+        &self,
+        inputs: &[MathVec<IN>],
+        states: Option<&[MathVec<OUT>]>,
+        cache: &mut MlBatchCache<OUT, IN>
     ) {
-        self.study_main_layer(
-            output_error,
-            state_error,
-            state_cache,
-            output_cache,
-        );
-        self.study_f_gate(
-            output_error,
-            state_error,
-            state_cache,
-            output_cache,
-        );
-        self.study_i_gate(
-            output_error,
-            state_error,
-            state_cache,
-            output_cache,
-        );
-        self.study_o_gate(
-            output_error,
-            output_cache,
+        cache.resize(inputs.len());
+
+        cache.inputs.iter_mut().zip(inputs.iter()).for_each(
+            |(cache_input, input)| {cache_input.copy_from(input);}
         );
 
-        input_error.copy_from(&self.input_error_main_by_output_error);
-        *input_error += &self.input_error_f_by_output_error;
-        *input_error += &self.input_error_i_by_output_error;
-        *input_error += &self.input_error_o_by_output_error;
+        match states {
+            Some(states) => {
+                cache.states.iter_mut().zip(states.iter()).for_each(
+                    |(cache_state, state)| {cache_state.copy_from(state);}
+                );
 
-        prev_state_error.copy_from(
-            &self.prev_state_error_main_by_output_error
+                cache.has_state = true;
+            },
+
+            None => {
+                cache.has_state = false;
+            }
+        }
+
+        self.layer.weights.calc_batch(
+            inputs,
+            states,
+            &mut cache.middle_values
         );
-        *prev_state_error += &self.prev_state_error_f_by_output_error;
-        *prev_state_error += &self.prev_state_error_i_by_output_error;
-        *prev_state_error += &self.prev_state_error_o_by_output_error;
 
-        prev_state_error.as_mut_array().iter_mut().zip(
-            output_error.as_array().iter()
-        ).zip(
-            output_cache.o_gate_cache.output.as_array().iter()
+        cache.middle_values.iter().zip(
+            cache.d_activates.iter_mut()
         ).zip(
-            output_cache.d_tanh_s.as_array().iter()
+            cache.d_slopes.iter_mut()
         ).zip(
-            state_cache.f_gate_cache.output.as_array().iter()
-        ).for_each(|((((p_state_e, out_e), o_out), d_tanh_s_one), f_out)| {
-            *p_state_e += *out_e * *o_out * *d_tanh_s_one * *f_out;
+            cache.outputs.iter_mut()
+        ).for_each(|(((middle_value, d_activate), d_slope), output)| {
+            middle_value.iter().zip(d_activate.iter_mut()).zip(
+                d_slope.iter_mut()
+            ).zip(
+                output.iter_mut()
+            ).for_each(|(((m_value, d_f), d_slope_one), output_one)| {
+                *d_f = self.layer.activation.d_activate(*m_value);
+                *d_slope_one = self.layer.activation.d_slope(*m_value);
+                *output_one = self.layer.activation.activate(*m_value);
+            });
         });
     }
 
-    fn study_main_layer(
+    /// Accumulates gradient of batch.
+    ///
+    /// Total gradient and errors are the same as calling [`study()`](Self::study()) for each sample in order.
+    ///
+    /// - `output_errors` : Backpropagated output errors.
+    /// - `next_state_errors` : Backpropagated state errors if they exist.
+    /// - `cache` : Cache generated by [`MlLayer::ready_batch()`].
+    /// - `input_errors` : Errors for previous outputs.
+    /// - `prev_state_errors` : Errors for previous states.
+    pub fn study_batch(
         &mut self,
-        output_error: &MathVec<OUT>,
-        state_error: &MathVec<OUT>,
-        state_cache: &MlLstmStateCache<OUT, IN>,
-        output_cache: &MlLstmOutputCache<OUT, IN>
+        output_errors: &[MathVec<OUT>],
+        next_state_errors: Option<&[MathVec<OUT>]>,
+        cache: &MlBatchCache<OUT, IN>,
+        input_errors: &mut [MathVec<IN>],
+        prev_state_errors: Option<&mut [MathVec<OUT>]>
     ) {
-        self.tmp_error.as_mut_array().iter_mut().zip(
-            output_error.as_array().iter()
-        ).zip(
-            output_cache.o_gate_cache.output.as_array().iter()
-        ).zip(
-            output_cache.d_tanh_s.as_array().iter()
-        ).zip(
-            state_cache.i_gate_cache.output.as_array().iter()
-        ).zip(
-            state_error.as_array().iter()
-        ).for_each(
-            |(((((tmp_e, out_e), o_out), d_tanh_s_one), i_out), state_e)| {
-                *tmp_e = *out_e * *o_out * *d_tanh_s_one * *i_out;
-                *tmp_e += *state_e * *i_out;
+        let len = cache.batch_size().min(output_errors.len());
+
+        self.tmp_errors.resize(len, MathVec::<OUT>::new());
+
+        self.tmp_errors.iter_mut().enumerate().for_each(|(i, tmp_error)| {
+            match next_state_errors.and_then(|errors| errors.get(i)) {
+                Some(next_state_error) => {
+                    tmp_error.copy_from(next_state_error);
+                },
+
+                None => {
+                    tmp_error.clear();
+                }
             }
-        );
 
-        self.main_layer.study(
-            &self.tmp_error,
-            None,
-            &state_cache.main_layer_cache,
-            &mut self.input_error_main_by_output_error,
-            Some(&mut self.prev_state_error_main_by_output_error)
+            tmp_error.iter_mut().zip(output_errors[i].iter()).zip(
+                cache.d_activates[i].iter()
+            ).for_each(|((tmp_e, output_e), d_f)| {
+                *tmp_e += *output_e * *d_f;
+            });
+        });
+
+        // add self.total_grad ----------
+        Weights::add_grad_with_weights_batch(
+            &self.tmp_errors,
+            &cache.inputs,
+            cache.states(),
+            &mut self.total_grad
         );
-    }
 
-    fn study_f_gate(
-        &mut self,
-        output_error: &MathVec<OUT>,
-        state_error: &MathVec<OUT>,
-        state_cache: &MlLstmStateCache<OUT, IN>,
-        output_cache: &MlLstmOutputCache<OUT, IN>
-    ) {
-        self.tmp_error.as_mut_array().iter_mut().zip(
-            output_error.as_array().iter()
-        ).zip(
-            output_cache.o_gate_cache.output.as_array().iter()
-        ).zip(
-            output_cache.d_tanh_s.as_array().iter()
-        ).zip(
-            state_cache.prev_state.as_array().iter()
-        ).zip(
-            state_error.as_array().iter()
-        ).for_each(
-            |(((((tmp_e, out_e), o_out), d_tanh_s_one), p_state), state_e)| {
-                *tmp_e = *out_e * *o_out * *d_tanh_s_one * *p_state;
-                *tmp_e += *state_e * *p_state;
+        output_errors[..len].iter().zip(cache.d_slopes.iter()).for_each(
+            |(output_error, d_slope)| {
+                self.add_slope_grad(output_error, d_slope);
             }
         );
 
-        self.f_gate.study(
-            &self.tmp_error,
-            None,
-            &state_cache.f_gate_cache,
-            &mut self.input_error_f_by_output_error,
-            Some(&mut self.prev_state_error_f_by_output_error)
+        // calc errors ----------
+        self.layer.weights.grad_with_input_batch(
+            &self.tmp_errors,
+            input_errors
         );
+
+        if let Some(prev_state_errors) = prev_state_errors {
+            if cache.has_state {
+                self.layer.weights.grad_with_state_batch(
+                    &self.tmp_errors,
+                    prev_state_errors
+                );
+            }
+        }
     }
 
-    fn study_i_gate(
+    #[inline]
+    fn add_slope_grad(
         &mut self,
         output_error: &MathVec<OUT>,
-        state_error: &MathVec<OUT>,
-        state_cache: &MlLstmStateCache<OUT, IN>,
-        output_cache: &MlLstmOutputCache<OUT, IN>
+        d_slope: &MathVec<OUT>
     ) {
-        self.tmp_error.as_mut_array().iter_mut().zip(
-            output_error.as_array().iter()
-        ).zip(
-            output_cache.o_gate_cache.output.as_array().iter()
-        ).zip(
-            output_cache.d_tanh_s.as_array().iter()
-        ).zip(
-            state_cache.main_layer_cache.output.as_array().iter()
-        ).zip(
-            state_error.as_array().iter()
-        ).for_each(
-            |(((((tmp_e, out_e), o_out), d_tanh_s_one), main_out), state_e)| {
-                *tmp_e = *out_e * *o_out * *d_tanh_s_one * *main_out;
-                *tmp_e += *state_e * *main_out;
-            }
-        );
-
-        self.i_gate.study(
-            &self.tmp_error,
-            None,
-            &state_cache.i_gate_cache,
-            &mut self.input_error_i_by_output_error,
-            Some(&mut self.prev_state_error_i_by_output_error)
-        );
+        if self.has_slope() {
+            self.slope_grad = add_dot(
+                self.slope_grad,
+                output_error.as_array(),
+                d_slope.as_array()
+            );
+        }
     }
 
-    fn study_o_gate(
+    #[inline]
+    fn calc_tmp_error(
         &mut self,
         output_error: &MathVec<OUT>,
-        cache: &MlLstmOutputCache<OUT, IN>
+        next_state_error: Option<&MathVec<OUT>>,
+        cache: &MlCache<OUT, IN>
     ) {
+        match next_state_error {
+            Some(next_state_error) => {
+                self.tmp_error.copy_from(next_state_error);
+            },
+
+            None => {
+                self.tmp_error.clear();
+            }
+        }
+
         self.tmp_error.as_mut_array().iter_mut().zip(
             output_error.as_array().iter()
         ).zip(
-            cache.tanh_s.as_array().iter()
-        ).for_each(|((tmp_e, out_e),  tanh_s_one)| {
-            *tmp_e = *out_e * *tanh_s_one;
-        });
-
-        self.o_gate.study(
-            &self.tmp_error,
-            None,
-            &cache.o_gate_cache,
-            &mut self.input_error_o_by_output_error,
-            Some(&mut self.prev_state_error_o_by_output_error)
+            cache.d_activate.as_array().iter()
+        ).for_each(
+            |((tmp_e, output_e), d_f)| {
+                *tmp_e += *output_e * *d_f;
+            }
         );
     }
 
-    /// Update weights.
+    /// Updates weights with learning rate from [`LrScheduler`], and advances step of scheduler.
     ///
-    /// - `rate` : Learning rate.
+    /// - `scheduler` : Scheduler of learning rate.
     #[inline]
-    pub fn update(&mut self, rate: f32) {
-        self.main_layer.update(rate);
-        self.f_gate.update(rate);
-        self.i_gate.update(rate);
-        self.o_gate.update(rate);
+    pub fn update_with_scheduler(&mut self, scheduler: &mut LrScheduler) {
+        self.update(scheduler.next_rate());
     }
 
-    /// Accesses each immutable total gradient with closure.
+    /// Updates weights with [`Optimizer`].
     ///
-    /// - `f` : Closure.
-    #[inline]
-    pub fn for_each_total_grad<F>(
-        &self,
-        mut f: F
-    ) where F: FnMut(&f32) {
-        self.main_layer.total_grad.iter().for_each(|val| {f(val)});
-        self.f_gate.total_grad.iter().for_each(|val| {f(val)});
-        self.i_gate.total_grad.iter().for_each(|val| {f(val)});
-        self.o_gate.total_grad.iter().for_each(|val| {f(val)});
-    }
-
-    /// Accesses each mutable total gradient with closure.
+    /// Weights masked by [`prune()`](Self::prune()) are kept `0.0`.
     ///
-    /// - `f` : Closure.
+    /// The following formula is of default optimizer, [`Optimizer::Adam`].
+    ///
+    /// | Formula |
+    /// |:-:|
+    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mstyle mathvariant="bold"> <mover accent="true"> <mi>v</mi> <mo stretchy="false">^</mo> </mover> </mstyle> <mo stretchy="false">=</mo> <mrow> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <mrow> <msub> <mi>β</mi> <mn>1</mn> </msub> <mstyle mathvariant="bold"> <mi>v</mi> </mstyle> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> <mo stretchy="false">+</mo> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <mrow> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <mrow> <mn>1</mn> <mo stretchy="false">−</mo> <msub> <mi>β</mi> <mn>1</mn> </msub> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> <mstyle mathvariant="bold"> <mi>G</mi> </mstyle> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> </mrow> </mrow> </semantics> </math> |
+    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mover accent="true"> <mi>s</mi> <mo stretchy="false">^</mo> </mover> <mo stretchy="false">=</mo> <mrow> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <mrow> <msub> <mi>β</mi> <mn>2</mn> </msub> <mi>s</mi> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> <mo stretchy="false">+</mo> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <mrow> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <mrow> <mn>1</mn> <mo stretchy="false">−</mo> <msub> <mi>β</mi> <mn>2</mn> </msub> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> <mstyle mathvariant="bold"> <msup> <mi>G</mi> <mn>2</mn> </msup> </mstyle> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> </mrow> </mrow> </semantics> </math> |
+    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mrow> <mstyle mathvariant="bold"> <mover accent="true"> <mi>W</mi> <mo stretchy="false">^</mo> </mover> </mstyle> <mo stretchy="false">=</mo> <mrow> <mstyle mathvariant="bold"> <mi>W</mi> </mstyle> <mo stretchy="false">−</mo> <mfrac> <mi>η</mi> <mrow> <msqrt> <mover accent="true"> <mi>s</mi> <mo stretchy="false">^</mo> </mover> </msqrt> <mo stretchy="false">+</mo> <mi>ε</mi> </mrow> </mfrac> </mrow> </mrow> <mstyle mathvariant="bold"> <mover accent="true"> <mi>v</mi> <mo stretchy="false">^</mo> </mover> </mstyle> </mrow> </semantics> </math> |
+    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mtable columnalign="left"> <mtr> <mtd> <mrow> <mstyle mathvariant="bold"> <mi>G</mi> </mstyle> <mo stretchy="false">≝</mo> <mtext>Total gradient.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mstyle mathvariant="bold"> <mi>v</mi> </mstyle> <mo stretchy="false">≝</mo> <mtext>Previous momentum 1.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mstyle mathvariant="bold"> <mover accent="true"> <mi>v</mi> <mo stretchy="false">^</mo> </mover> </mstyle> <mo stretchy="false">≝</mo> <mtext>Next momentum 1.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>s</mi> <mo stretchy="false">≝</mo> <mtext>Previous momentum 2.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mover accent="true"> <mi>s</mi> <mo stretchy="false">^</mo> </mover> <mo stretchy="false">≝</mo> <mtext>Next momentum 2.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mrow> <msub> <mi>β</mi> <mn>1</mn> </msub> <mo stretchy="false">≝</mo> <mtext>Rate of momentum 1.</mtext> </mrow> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <mrow> <msub> <mi>β</mi> <mn>1</mn> </msub> <mo stretchy="false">=</mo> <mn>0.9</mn> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mrow> <msub> <mi>β</mi> <mn>2</mn> </msub> <mo stretchy="false">≝</mo> <mtext>Rate of momentum 2.</mtext> </mrow> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <mrow> <msub> <mi>β</mi> <mn>2</mn> </msub> <mo stretchy="false">=</mo> <mn>0.999</mn> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mstyle mathvariant="bold"> <mi>W</mi> </mstyle> <mo stretchy="false">≝</mo> <mtext>Previous weights.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mstyle mathvariant="bold"> <mover accent="true"> <mi>W</mi> <mo stretchy="false">^</mo> </mover> </mstyle> <mo stretchy="false">≝</mo> <mtext>Next weights.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>η</mi> <mo stretchy="false">≝</mo> <mtext>Learning rate.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>ε</mi> <mo stretchy="false">≝</mo> <mtext>Machine epsilon.</mtext> </mrow> </mtd> </mtr> </mtable> </semantics> </math> |
+    ///
+    /// - `rate` : Learning rate.
+    pub fn update(&mut self, rate: f32) {
+        Self::apply_mask(self.mask.as_deref(), &mut self.total_grad);
+
+        let has_state = self.total_grad.state_weights().is_some();
+
+        let weights = self.layer.weights.as_mut_slice();
+        let grads = self.total_grad.as_slice();
+        let momentum_1 = self.momentum_1.as_mut_slice();
+        let square_average = self.square_average.as_mut_slice();
+
+        // Each row of bias, weights for input and weights for state
+        // shares momentum 2 of Optimizer::Adam.
+        for i in 0..OUT {
+            let ranges = Self::row_ranges(i, has_state);
+
+            let adam_rate = match self.optimizer {
+                Optimizer::Adam => next_adam_rate(
+                    rate,
+                    &mut self.momentum_2[i],
+                    ranges.iter().fold(0.0, |sum, range| {
+                        grads[range.clone()].iter().fold(
+                            sum,
+                            |sum, g| sum + (*g * *g)
+                        )
+                    })
+                ),
+
+                _ => 0.0
+            };
+
+            ranges.into_iter().for_each(|range| {
+                self.optimizer.step(
+                    rate,
+                    adam_rate,
+                    self.step,
+                    OptimizerSlices {
+                        weights: &mut weights[range.clone()],
+                        grads: &grads[range.clone()],
+                        momentum_1: &mut momentum_1[range.clone()],
+                        square_average: &mut square_average[range]
+                    }
+                );
+            });
+        }
+
+        Self::apply_mask(self.mask.as_deref(), &mut self.layer.weights);
+
+        self.update_slope(rate);
+
+        self.total_grad.clear();
+
+        self.step += 1;
+    }
+
+    fn update_slope(&mut self, rate: f32) {
+        if let Activation::PReLU(ref mut slope) = self.layer.activation {
+            let adam_rate = match self.optimizer {
+                Optimizer::Adam => next_adam_rate(
+                    rate,
+                    &mut self.slope_momentum_2,
+                    self.slope_grad * self.slope_grad
+                ),
+
+                _ => 0.0
+            };
+
+            self.optimizer.step(
+                rate,
+                adam_rate,
+                self.step,
+                OptimizerSlices {
+                    weights: core::slice::from_mut(slope),
+                    grads: core::slice::from_ref(&self.slope_grad),
+                    momentum_1: core::slice::from_mut(
+                        &mut self.slope_momentum_1
+                    ),
+                    square_average: core::slice::from_mut(
+                        &mut self.slope_square_average
+                    )
+                }
+            );
+        }
+
+        self.slope_grad = 0.0;
+    }
+
+    // Ranges of bias, weights for input and weights for state of a row
+    // in body of Weights.
     #[inline]
-    pub fn for_each_total_grad_mut<F>(
-        &mut self,
-        mut f: F
-    ) where F: FnMut(&mut f32) {
-        self.main_layer.total_grad.iter_mut().for_each(|val| {f(val)});
-        self.f_gate.total_grad.iter_mut().for_each(|val| {f(val)});
-        self.i_gate.total_grad.iter_mut().for_each(|val| {f(val)});
-        self.o_gate.total_grad.iter_mut().for_each(|val| {f(val)});
+    fn row_ranges(i: usize, has_state: bool) -> [Range<usize>; 3] {
+        let input_start = OUT + (i * IN);
+        let state_start = OUT + (OUT * IN) + (i * OUT);
+
+        [
+            i..(i + 1),
+            input_start..(input_start + IN),
+            if has_state {state_start..(state_start + OUT)} else {0..0}
+        ]
     }
 }
 
-/// Encoder from sequence data to fixed length data.
+/// AI for fixed length data.
 ///
 /// - `OUT` : Dimension of output.
 /// - `MIDDLE` : Dimension of hidden layer.
@@ -3355,11 +5235,11 @@ impl<const OUT: usize, const IN: usize> MlLstm<OUT, IN> {
 ///
 /// # Example
 ///
-/// Variable length word classifier.
-/// - If inputs Japanese word, outputs '日'.
-/// - If inputs English word, outputs 'E'.
+/// Letter classifier.
+/// - If inputs Japanese letter, outputs '日'.
+/// - If inputs English letter, outputs 'E'.
 ///
-/// (1) Defines word generator and ID.
+/// (1) Defines letter generator and ID.
 ///
 /// ```ignore
 /// extern crate chobitlibs;
@@ -3367,9 +5247,9 @@ impl<const OUT: usize, const IN: usize> MlLstm<OUT, IN> {
 /// use chobitlibs::chobit_ai::{
 ///     MathVec,
 ///     Activation,
-///     ChobitEncoder,
-///     ChobitMlEncoder,
-///     MlEncoderCache
+///     ChobitAi,
+///     ChobitMlAi,
+///     MlAiCache
 /// };
 /// 
 /// use chobitlibs::chobit_rand::ChobitRand;
@@ -3396,63 +5276,36 @@ impl<const OUT: usize, const IN: usize> MlLstm<OUT, IN> {
 /// 
 /// const JAPANESE_ID: char = '日';
 /// const ENGLISH_ID: char = 'E';
-/// 
-/// fn gen_word(
-///     f: fn(&mut ChobitRand) -> char,
-///     rng: &mut ChobitRand,
-///     max_len: usize
-/// ) -> String {
-///     let word_len = ((rng.next_u64() as usize) % max_len) + 1;
-/// 
-///     let mut ret = String::with_capacity(word_len);
-/// 
-///     for _ in 0..word_len {
-///         ret.push(f(rng));
-///     }
-/// 
-///     ret
-/// }
-/// 
-/// fn write_string_to_slice(string: &str, slice: &mut [MathVec<32>]) {
-///     string.chars().zip(slice.iter_mut()).for_each(|(c, s)| {
-///         s.load_u32_label(c as u32);
-///     });
-/// }
 /// ```
 ///
-/// (2) Creates [`ChobitEncoder`] and randomises weights.
+/// (2) Creates [`ChobitAi`] and randomises weights.
 ///
 /// ```ignore
 /// const OUT: usize = 32;
 /// const MIDDLE: usize = 64;
 /// const IN: usize = 32;
 ///
-/// const MAX_WORD_LEN: usize = 10;
-///
-/// let mut rng = ChobitRand::new(b"ChobitEncoder Example");
+/// let mut rng = ChobitRand::new(b"ChobitAi Example");
 ///
-/// let mut encoder =
-///     ChobitEncoder::<OUT, MIDDLE, IN>::new(Activation::SoftSign);
+/// let mut ai = ChobitAi::<OUT, MIDDLE, IN>::new(Activation::SoftSign);
 ///
 /// // Randomises weights.
-/// encoder.for_each_weight_mut(|weight| {
+/// ai.for_each_weight_mut(|weight| {
 ///     *weight = ((rng.next_f64() as f32) * 2.0) - 1.0;
 /// });
 ///
-/// let mut input = vec![MathVec::<IN>::new(); MAX_WORD_LEN];
+/// let mut input = MathVec::<IN>::new();
 /// let mut output = MathVec::<OUT>::new();
-/// let initial_state = MathVec::<MIDDLE>::new();
 /// ```
 ///
-/// (3) Wraps AI with [`ChobitMlEncoder`] for machine learning.
+/// (3) Wraps AI with [`ChobitMlAi`] for machine learning.
 ///
 /// ```ignore
-/// let mut encoder = ChobitMlEncoder::<OUT, MIDDLE, IN>::new(encoder);
-/// let mut cache = MlEncoderCache::<OUT, MIDDLE, IN>::new(MAX_WORD_LEN);
+/// let mut ai = ChobitMlAi::<OUT, MIDDLE, IN>::new(ai);
+/// let mut cache = MlAiCache::<OUT, MIDDLE, IN>::new();
 ///
-/// let mut input_error = vec![MathVec::<IN>::new(); MAX_WORD_LEN];
+/// let mut input_error = MathVec::<IN>::new();
 /// let mut output_error = MathVec::<OUT>::new();
-/// let mut prev_state_error = MathVec::<MIDDLE>::new();
 /// ```
 ///
 /// (4) Machine learning.
@@ -3465,159 +5318,127 @@ impl<const OUT: usize, const IN: usize> MlLstm<OUT, IN> {
 /// for _ in 0..EPOCH {
 ///     for _ in 0..BATCH_SIZE {
 ///         //--- Learns Japanese ---//
-///         let string = gen_word(japanese_letter, &mut rng, MAX_WORD_LEN);
-///
-///         write_string_to_slice(&string, &mut input);
-///
+///         input.load_u32_label(japanese_letter(&mut rng) as u32);
 ///         output.load_u32_label(JAPANESE_ID as u32);
 ///
 ///         // Writes cache.
-///         encoder.ready(
-///             &input[..string.chars().count()],
-///             &initial_state,
-///             &mut cache
-///         );
+///         ai.ready(&input, &mut cache);
 ///
 ///         // Calculates error.
 ///         cache.calc_output_error(&output, &mut output_error);
 ///
 ///         // Studies.
-///         encoder.study(
-///             &output_error,
-///             &cache,
-///             &mut input_error,
-///             &mut prev_state_error
-///         );
+///         ai.study(&output_error, &cache, &mut input_error);
 ///
 ///         //--- Learns English ---//
-///         let string = gen_word(english_letter, &mut rng, MAX_WORD_LEN);
-///
-///         write_string_to_slice(&string, &mut input);
-///
+///         input.load_u32_label(english_letter(&mut rng) as u32);
 ///         output.load_u32_label(ENGLISH_ID as u32);
 ///
 ///         // Writes cache.
-///         encoder.ready(
-///             &input[..string.chars().count()],
-///             &initial_state,
-///             &mut cache
-///         );
+///         ai.ready(&input, &mut cache);
 ///
 ///         // Calculates error.
 ///         cache.calc_output_error(&output, &mut output_error);
 ///
 ///         // Studies.
-///         encoder.study(
-///             &output_error,
-///             &cache,
-///             &mut input_error,
-///             &mut prev_state_error
-///         );
+///         ai.study(&output_error, &cache, &mut input_error);
 ///     }
 ///
 ///     // Updates weights.
-///     encoder.update(RATE);
+///     ai.update(RATE);
 /// }
 /// ```
 ///
-/// (5) Tests encoder.
+/// (5) Tests AI.
 ///
 /// ```ignore
-/// // Unwrap Encoder.
-/// let mut encoder = encoder.drop();
+/// // Unwrap AI.
+/// let ai = ai.drop();
+///
+/// let mut tmpbuf = MathVec::<MIDDLE>::new();
 ///
 /// // Tests Japanese.
 /// for _ in 0..10 {
-///     let string = gen_word(japanese_letter, &mut rng, MAX_WORD_LEN);
-///
-///     write_string_to_slice(&string, &mut input);
-///
-///     // Initializes state.
-///     encoder.state_mut().copy_from(&initial_state);
-///
-///     // Inputs for each one.
-///     input[..string.chars().count()].iter().for_each(|input_one| {
-///         encoder.input_next(input_one)
-///     });
+///     input.load_u32_label(japanese_letter(&mut rng) as u32);
 ///
-///     // Outputs.
-///     encoder.output(&mut output);
+///     ai.calc(&input, &mut output, &mut tmpbuf);
 ///
 ///     assert_eq!(output.to_u32_label(), JAPANESE_ID as u32);
 /// }
 ///
 /// // Tests English.
 /// for _ in 0..10 {
-///     let string = gen_word(english_letter, &mut rng, MAX_WORD_LEN);
-///
-///     write_string_to_slice(&string, &mut input);
-///
-///     // Initializes state.
-///     encoder.state_mut().copy_from(&initial_state);
-///
-///     // Inputs for each one.
-///     input[..string.chars().count()].iter().for_each(|input_one| {
-///         encoder.input_next(input_one)
-///     });
+///     input.load_u32_label(english_letter(&mut rng) as u32);
 ///
-///     // Outputs.
-///     encoder.output(&mut output);
+///     ai.calc(&input, &mut output, &mut tmpbuf);
 ///
 ///     assert_eq!(output.to_u32_label(), ENGLISH_ID as u32);
 /// }
 /// ```
 #[derive(Debug, Clone, PartialEq)]
-pub struct ChobitEncoder<
-    const OUT: usize,
-    const MIDDLE: usize,
-    const IN: usize
-> {
-    lstm: Lstm<MIDDLE, IN>,
-    output_layer: Layer<OUT, MIDDLE>,
-
-    prev_state: MathVec<MIDDLE>,
-    state: MathVec<MIDDLE>,
-    last_input: MathVec<IN>,
-
-    middle_output: MathVec<MIDDLE>,
-    tmpbuf: MathVec<MIDDLE>
+pub struct ChobitAi<const OUT: usize, const MIDDLE: usize, const IN: usize> {
+    middle_layer: Layer<MIDDLE, IN>,
+    output_layer: Layer<OUT, MIDDLE>
 }
 
 impl<
     const OUT: usize,
     const MIDDLE: usize,
     const IN: usize
-> ChobitEncoder<OUT, MIDDLE, IN> {
-    /// Creates ChobitEncoder.
+> ChobitAi<OUT, MIDDLE, IN> {
+    /// Creates ChobitAi.
     ///
     /// - `activation` : Activation function for output layer.
-    /// - _Return_ : ChobitEncoder.
+    /// - _Return_ : ChobitAi.
     #[inline]
     pub fn new(activation: Activation) -> Self {
-        Self {
-            lstm: Lstm::<MIDDLE, IN>::new(),
-            output_layer: Layer::<OUT, MIDDLE>::new(activation, false),
+        Self::with_activations(Activation::ReLU, activation)
+    }
 
-            prev_state: MathVec::<MIDDLE>::new(),
-            state: MathVec::<MIDDLE>::new(),
-            last_input: MathVec::<IN>::new(),
+    /// Creates ChobitAi with activation functions.
+    ///
+    /// - `hidden` : Activation function for middle layer.
+    /// - `output` : Activation function for output layer.
+    /// - _Return_ : ChobitAi.
+    #[inline]
+    pub fn with_activations(hidden: Activation, output: Activation) -> Self {
+        Self::with_layers(
+            Layer::<MIDDLE, IN>::new(hidden, false),
+            Layer::<OUT, MIDDLE>::new(output, false)
+        )
+    }
 
-            middle_output: MathVec::<MIDDLE>::new(),
-            tmpbuf: MathVec::<MIDDLE>::new()
+    /// Creates ChobitAi with layers.
+    ///
+    /// Each layer can have any activation function. State weights of layer are not used, because ChobitAi doesn't have state.
+    ///
+    /// - `middle_layer` : Middle layer.
+    /// - `output_layer` : Output layer.
+    /// - _Return_ : ChobitAi.
+    #[inline]
+    pub fn with_layers(
+        middle_layer: Layer<MIDDLE, IN>,
+        output_layer: Layer<OUT, MIDDLE>
+    ) -> Self {
+        Self {
+            middle_layer: middle_layer,
+            output_layer: output_layer
         }
     }
 
-    /// Gets immutable Lstm.
+    /// Gets immutable middle layer.
     ///
-    /// - _Return_ : Lstm.
+    /// - _Return_ : Middle layer.
     #[inline]
-    pub fn lstm(&self) -> &Lstm<MIDDLE, IN> {&self.lstm}
+    pub fn middle_layer(&self) -> &Layer<MIDDLE, IN> {&self.middle_layer}
 
-    /// Gets mutable Lstm.
+    /// Gets mutable middle layer.
     ///
-    /// - _Return_ : Lstm.
+    /// - _Return_ : Middle layer.
     #[inline]
-    pub fn lstm_mut(&mut self) -> &mut Lstm<MIDDLE, IN> {&mut self.lstm}
+    pub fn middle_layer_mut(&mut self) -> &mut Layer<MIDDLE, IN> {
+        &mut self.middle_layer
+    }
 
     /// Gets immutable output layer.
     ///
@@ -3633,35 +5454,23 @@ impl<
         &mut self.output_layer
     }
 
-    /// Gets immutable state.
-    ///
-    /// This should be initialized before the first [`input_next()`](Self::input_next).
-    ///
-    /// - _Return_ : State.
-    #[inline]
-    pub fn state(&self) -> &MathVec<MIDDLE> {&self.state}
-
-    /// Gets mutable state.
-    ///
-    /// This should be initialized before the first [`input_next()`](Self::input_next).
-    ///
-    /// - _Return_ : State.
-    #[inline]
-    pub fn state_mut(&mut self) -> &mut MathVec<MIDDLE> {&mut self.state}
-
-    /// Gets last input.
+    /// Initializes weights with [`Initializer`].
     ///
-    /// - _Return_ : Last input.
-    #[inline]
-    pub fn last_input(&self) -> &MathVec<IN> {&self.last_input}
+    /// - `initializer` : Initializer for both layers.
+    /// - `rand` : Closure that returns uniform random number in `[0.0, 1.0)`.
+    pub fn init_weights<F>(&mut self, initializer: Initializer, mut rand: F)
+    where F: FnMut() -> f32 {
+        self.middle_layer.init_weights(initializer, &mut rand);
+        self.output_layer.init_weights(initializer, &mut rand);
+    }
 
     /// Accesses each immutable weight with closure.
     ///
     /// - `f` : Closure.
     #[inline]
     pub fn for_each_weight<F>(&self, mut f: F) where F: FnMut(&f32) {
-        self.lstm.for_each_weight(|val| {f(val)});
-        self.output_layer.weights().iter().for_each(|val| {f(val)});
+        self.middle_layer.for_each_weight(|val| {f(val)});
+        self.output_layer.for_each_weight(|val| {f(val)});
     }
 
     /// Accesses each mutable weight with closure.
@@ -3672,83 +5481,131 @@ impl<
         &mut self,
         mut f: F
     ) where F: FnMut(&mut f32) {
-        self.lstm.for_each_weight_mut(|val| {f(val)});
-        self.output_layer.mut_weights().iter_mut().for_each(|val| {f(val)});
+        self.middle_layer.for_each_weight_mut(|val| {f(val)});
+        self.output_layer.for_each_weight_mut(|val| {f(val)});
     }
 
-    /// Input next data.
+    /// Calculates
     ///
-    /// - `input` : Next data.
+    /// - `input` : Input.
+    /// - `output` : Buffer for output.
+    /// - `tmpbuf` : Temporary buffer for this function to work.
     #[inline]
-    pub fn input_next(&mut self, input: &MathVec<IN>) {
-        self.prev_state.copy_from(&self.state);
-        self.last_input.copy_from(input);
-
-        self.lstm.calc_state(
-            input,
-            &self.prev_state,
-            &mut self.state,
-            &mut self.tmpbuf
-        );
+    pub fn calc(
+        &self,
+        input: &MathVec<IN>,
+        output: &mut MathVec<OUT>,
+        tmpbuf: &mut MathVec<MIDDLE>
+    ) {
+        self.middle_layer.calc(input, None, tmpbuf);
+        self.output_layer.calc(tmpbuf, None, output);
     }
 
-    /// Output data calculated by current state and last input.
+    /// Calculates prediction for loss function used in machine learning.
     ///
-    /// - `output` : Buffer for output.
+    /// This is [`calc()`](Self::calc()) and [`Loss::predict()`], e.g. output is softmax for [`Loss::CategoricalCrossEntropy`].
+    ///
+    /// - `loss` : Loss function used in machine learning.
+    /// - `input` : Input.
+    /// - `output` : Buffer for prediction.
+    /// - `tmpbuf` : Temporary buffer for this function to work.
     #[inline]
-    pub fn output(&mut self, output: &mut MathVec<OUT>) {
-        self.prev_state.copy_from(&self.state);
+    pub fn calc_with_loss(
+        &self,
+        loss: &Loss,
+        input: &MathVec<IN>,
+        output: &mut MathVec<OUT>,
+        tmpbuf: &mut MathVec<MIDDLE>
+    ) {
+        self.calc(input, output, tmpbuf);
+        loss.predict(output);
+    }
 
-        self.lstm.calc(
-            &self.last_input,
-            &self.prev_state,
-            &mut self.middle_output,
-            &mut self.state,
-            &mut self.tmpbuf
-        );
+    /// Calculates each sample of batch.
+    ///
+    /// Result of each sample is the same as [`calc()`](Self::calc()). See [`Weights::calc_batch()`] for details.
+    ///
+    /// - `inputs` : Inputs.
+    /// - `outputs` : Buffer for outputs.
+    /// - `tmpbufs` : Temporary buffers for this function to work. Batch size is the shortest of `inputs`, `outputs` and `tmpbufs`.
+    pub fn calc_batch(
+        &self,
+        inputs: &[MathVec<IN>],
+        outputs: &mut [MathVec<OUT>],
+        tmpbufs: &mut [MathVec<MIDDLE>]
+    ) {
+        let len = inputs.len().min(outputs.len()).min(tmpbufs.len());
 
-        self.output_layer.calc(&self.middle_output, None, output);
+        self.middle_layer.calc_batch(&inputs[..len], None, tmpbufs);
+        self.output_layer.calc_batch(&tmpbufs[..len], None, outputs);
+    }
+
+    /// Writes this AI into buffer as binary data.
+    ///
+    /// The data contains [`FORMAT_MAGIC`], [`FORMAT_VERSION`], [`ModelKind`], dimensions, activation functions, flags of state weights and weights.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save(&self, buffer: &mut Vec<u8>) {
+        write_header(buffer, ModelKind::ChobitAi, &[OUT, MIDDLE, IN]);
+
+        self.middle_layer.write_descriptor(buffer);
+        self.output_layer.write_descriptor(buffer);
+
+        self.middle_layer.weights.write_body(buffer);
+        self.output_layer.weights.write_body(buffer);
+    }
+
+    /// Creates ChobitAi from binary data written by [`save()`](Self::save()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : ChobitAi.
+    pub fn load(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(
+            ModelKind::ChobitAi,
+            &[("OUT", OUT), ("MIDDLE", MIDDLE), ("IN", IN)]
+        )?;
+
+        let mut middle_layer =
+            Layer::<MIDDLE, IN>::read_descriptor(&mut reader)?;
+        let mut output_layer =
+            Layer::<OUT, MIDDLE>::read_descriptor(&mut reader)?;
+
+        middle_layer.weights.read_body(&mut reader)?;
+        output_layer.weights.read_body(&mut reader)?;
+
+        Ok(Self {
+            middle_layer: middle_layer,
+            output_layer: output_layer
+        })
     }
 }
 
-/// Cache for [`ChobitMlEncoder`].
+/// Cache for [`ChobitMlAi`].
 ///
-/// - `OUT` : `OUT` of [`ChobitMlEncoder`].
-/// - `MIDDLE` : `MIDDLE` of [`ChobitMlEncoder`].
-/// - `IN` : `IN` of [`ChobitMlEncoder`].
+/// - `OUT` : `OUT` of [`ChobitMlAi`].
+/// - `MIDDLE` : `MIDDLE` of [`ChobitMlAi`].
+/// - `IN` : `IN` of [`ChobitMlAi`].
 #[derive(Debug, Clone, PartialEq)]
-pub struct MlEncoderCache<
-    const OUT: usize,
-    const MIDDLE: usize,
-    const IN: usize
-> {
-    lstm_state_caches: Vec<MlLstmStateCache<MIDDLE, IN>>,
-    lstm_state_caches_len: usize,
-
-    lstm_output_cache: MlLstmOutputCache<MIDDLE, IN>,
-
-    output_layer_cache: MlCache<OUT, MIDDLE>
+pub struct MlAiCache<const OUT: usize, const MIDDLE: usize, const IN: usize> {
+    middle_cache: MlCache<MIDDLE, IN>,
+    output_cache: MlCache<OUT, MIDDLE>
 }
 
 impl<
     const OUT: usize,
     const MIDDLE: usize,
     const IN: usize
-> MlEncoderCache<OUT, MIDDLE, IN> {
-    /// Creates MlEncoderCache.
+> MlAiCache<OUT, MIDDLE, IN> {
+    /// Creates MlAiCache.
     ///
-    /// - _Return_ : MlEncoderCache.
+    /// - _Return_ : MlAiCache.
     #[inline]
-    pub fn new(capacity: usize) -> Self {
+    pub fn new() -> Self {
         Self {
-            lstm_state_caches: vec![
-                MlLstmStateCache::<MIDDLE, IN>::new(); capacity
-            ],
-            lstm_state_caches_len: 0,
-
-            lstm_output_cache: MlLstmOutputCache::<MIDDLE, IN>::new(),
-
-            output_layer_cache: MlCache::<OUT, MIDDLE>::new()
+            middle_cache: MlCache::<MIDDLE, IN>::new(),
+            output_cache: MlCache::<OUT, MIDDLE>::new()
         }
     }
 
@@ -3765,209 +5622,261 @@ impl<
     pub fn calc_output_error(
         &self,
         train_out: &MathVec<OUT>,
-        output_error: &mut MathVec<OUT>
+        error: &mut MathVec<OUT>
     ) {
-        output_error.copy_from(&self.output_layer_cache.output);
-        *output_error -= train_out;
+        error.copy_from(&self.output_cache.output);
+        *error -= train_out;
     }
 
-    /// Gets slice of ['MlLstmStateCache'] of lstm layer.
+    /// Calculates output error with loss function.
     ///
-    /// - _Return_ : Slice of ['MlLstmStateCache'].
+    /// - `loss` : Loss function.
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
     #[inline]
-    pub fn lstm_state_caches(&self) -> &[MlLstmStateCache<MIDDLE, IN>] {
-        &self.lstm_state_caches[..self.lstm_state_caches_len]
+    pub fn calc_output_error_with_loss(
+        &self,
+        loss: &Loss,
+        train_out: &MathVec<OUT>,
+        output_error: &mut MathVec<OUT>
+    ) {
+        loss.calc_error(&self.output_cache.output, train_out, output_error);
     }
 
-    /// Gets ['MlLstmOutputCache'] of lstm layer.
+    /// Gets cache for middle layer.
     ///
-    /// - _Return_ : ['MlLstmOutputCache'].
+    /// - _Return_ : Cache for middle layer.
     #[inline]
-    pub fn lstm_output_cache(&self) -> &MlLstmOutputCache<MIDDLE, IN> {
-        &self.lstm_output_cache
-    }
+    pub fn middle_cache(&self) -> &MlCache<MIDDLE, IN> {&self.middle_cache}
 
-    /// Gets ['MlCache'] of output layer.
+    /// Gets cache for output layer.
     ///
-    /// - _Return_ : [`MlCache`].
+    /// - _Return_ : Cache for output layer.
     #[inline]
-    pub fn output_layer_cache(&self) -> &MlCache<OUT, MIDDLE> {
-        &self.output_layer_cache
-    }
+    pub fn output_cache(&self) -> &MlCache<OUT, MIDDLE> {&self.output_cache}
 
     /// Gets output.
     ///
-    /// - _Return_ : output.
+    /// - _Return_ : Output.
     #[inline]
-    pub fn output(&self) -> &MathVec<OUT> {
-        &self.output_layer_cache.output
-    }
+    pub fn output(&self) -> &MathVec<OUT> {&self.output_cache.output}
 }
 
-/// Wrapper of [`ChobitEncoder`] for machine learning.
+/// Cache for batch functions of [`ChobitMlAi`].
 ///
-/// See [`ChobitEncoder`] for details.
+/// It has data of [`MlAiCache`] for each sample of batch.
 ///
-/// - `OUT` : Dimension of output.
-/// - `MIDDLE` : Dimension of hidden layer.
-/// - `IN` : Dimension of input.
+/// - `OUT` : `OUT` of [`ChobitMlAi`].
+/// - `MIDDLE` : `MIDDLE` of [`ChobitMlAi`].
+/// - `IN` : `IN` of [`ChobitMlAi`].
 #[derive(Debug, Clone, PartialEq)]
-pub struct ChobitMlEncoder<
+pub struct MlAiBatchCache<
     const OUT: usize,
     const MIDDLE: usize,
     const IN: usize
 > {
-    lstm: MlLstm<MIDDLE, IN>,
-    output_layer: MlLayer<OUT, MIDDLE>,
-
-    prev_state: MathVec<MIDDLE>,
-
-    tmp_middle_output_error: MathVec<MIDDLE>,
-    tmp_state_error: MathVec<MIDDLE>,
-
-    original_prev_state: MathVec<MIDDLE>,
-    original_state: MathVec<MIDDLE>,
-    original_last_input: MathVec<IN>,
-
-    original_middle_output: MathVec<MIDDLE>,
-    original_tmpbuf: MathVec<MIDDLE>
+    middle_cache: MlBatchCache<MIDDLE, IN>,
+    output_cache: MlBatchCache<OUT, MIDDLE>
 }
 
 impl<
     const OUT: usize,
     const MIDDLE: usize,
     const IN: usize
-> ChobitMlEncoder<OUT, MIDDLE, IN> {
-    /// Creates ChobitMlEncoder.
+> MlAiBatchCache<OUT, MIDDLE, IN> {
+    /// Creates MlAiBatchCache.
     ///
-    /// - `encoder` : Base [`ChobitEncoder`].
-    /// - _Return_ : ChobitMlEncoder.
+    /// - _Return_ : MlAiBatchCache.
     #[inline]
-    pub fn new(encoder: ChobitEncoder<OUT, MIDDLE, IN>) -> Self {
-        let ChobitEncoder::<OUT, MIDDLE, IN> {
-            lstm,
-            output_layer,
-            prev_state,
-            state,
-            last_input,
-            middle_output,
-            tmpbuf
-        } = encoder;
-
+    pub fn new() -> Self {
         Self {
-            lstm: MlLstm::<MIDDLE, IN>::new(lstm),
-            output_layer: MlLayer::<OUT, MIDDLE>::new(output_layer),
-
-            prev_state: MathVec::<MIDDLE>::new(),
-
-            tmp_middle_output_error: MathVec::<MIDDLE>::new(),
-            tmp_state_error: MathVec::<MIDDLE>::new(),
-
-            original_prev_state: prev_state,
-            original_state: state,
-            original_last_input: last_input,
-            original_middle_output: middle_output,
-            original_tmpbuf: tmpbuf
+            middle_cache: MlBatchCache::<MIDDLE, IN>::new(),
+            output_cache: MlBatchCache::<OUT, MIDDLE>::new()
         }
     }
 
-    /// Drops base [`ChobitEncoder`].
+    /// Gets batch size.
     ///
-    /// - _Return_ : [`ChobitEncoder`].
+    /// - _Return_ : Batch size.
     #[inline]
-    pub fn drop(self) -> ChobitEncoder<OUT, MIDDLE, IN> {
-        let Self {
-            lstm,
-            output_layer,
-            original_prev_state,
-            original_state,
-            original_last_input,
-            original_middle_output,
-            original_tmpbuf,
-            ..
-        } = self;
+    pub fn batch_size(&self) -> usize {self.output_cache.batch_size()}
 
-        ChobitEncoder::<OUT, MIDDLE, IN> {
-            lstm: lstm.drop(),
-            output_layer: output_layer.drop(),
-            prev_state: original_prev_state,
-            state: original_state,
-            last_input: original_last_input,
-            middle_output: original_middle_output,
-            tmpbuf: original_tmpbuf
-        }
+    /// Calculates output error of each sample.
+    ///
+    /// See [`MlAiCache::calc_output_error()`] for details.
+    ///
+    /// - `train_out` : Correct outputs.
+    /// - `output_error` : Buffer for output errors.
+    #[inline]
+    pub fn calc_output_error(
+        &self,
+        train_out: &[MathVec<OUT>],
+        output_error: &mut [MathVec<OUT>]
+    ) {
+        self.output_cache.calc_output_error(train_out, output_error);
     }
 
-    /// Clears internal data for study.
+    /// Calculates output error of each sample with loss function.
+    ///
+    /// - `loss` : Loss function.
+    /// - `train_out` : Correct outputs.
+    /// - `output_error` : Buffer for output errors.
     #[inline]
-    pub fn clear_study_data(&mut self) {
-        self.lstm.clear_study_data();
-        self.output_layer.clear_study_data();
+    pub fn calc_output_error_with_loss(
+        &self,
+        loss: &Loss,
+        train_out: &[MathVec<OUT>],
+        output_error: &mut [MathVec<OUT>]
+    ) {
+        self.output_cache.calc_output_error_with_loss(
+            loss,
+            train_out,
+            output_error
+        );
     }
 
-    /// Writes information on [`MlEncoderCache`] for [`study()`](Self::study()).
+    /// Gets cache for middle layer.
     ///
-    /// - `train_in` : Input data sequence.
-    /// - `prev_state` : Previous state.
-    /// - `cache` : Cache.
+    /// - _Return_ : Cache for middle layer.
     #[inline]
-    pub fn ready(
-        &mut self,
-        train_in: &[MathVec<IN>],
-        prev_state: &MathVec<MIDDLE>,
-        cache: &mut MlEncoderCache<OUT, MIDDLE, IN>
-    ) {
-        self.ready_state_cache(train_in, prev_state, cache);
-        self.ready_output_cache(cache);
+    pub fn middle_cache(&self) -> &MlBatchCache<MIDDLE, IN> {
+        &self.middle_cache
     }
 
-    fn ready_state_cache(
-        &mut self,
-        train_in: &[MathVec<IN>],
-        prev_state: &MathVec<MIDDLE>,
-        cache: &mut MlEncoderCache<OUT, MIDDLE, IN>
-    ) {
-        cache.lstm_state_caches_len = train_in.len();
-        if cache.lstm_state_caches.len() < train_in.len() {
-            cache.lstm_state_caches.resize(
-                train_in.len(),
-                MlLstmStateCache::<MIDDLE, IN>::new()
-            );
+    /// Gets cache for output layer.
+    ///
+    /// - _Return_ : Cache for output layer.
+    #[inline]
+    pub fn output_cache(&self) -> &MlBatchCache<OUT, MIDDLE> {
+        &self.output_cache
+    }
+
+    /// Gets outputs.
+    ///
+    /// - _Return_ : Outputs.
+    #[inline]
+    pub fn outputs(&self) -> &[MathVec<OUT>] {self.output_cache.outputs()}
+}
+
+/// Wrapper of [`ChobitAi`] for machine learning.
+///
+/// See [`ChobitAi`] for details.
+///
+/// - `OUT` : Dimension of output.
+/// - `MIDDLE` : Dimension of hidden layer.
+/// - `IN` : Dimension of input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChobitMlAi<const OUT: usize, const MIDDLE: usize, const IN: usize> {
+    middle_layer: MlLayer<MIDDLE, IN>,
+    output_layer: MlLayer<OUT, MIDDLE>,
+
+    middle_error: MathVec<MIDDLE>,
+    output_error: MathVec<OUT>,
+    middle_errors: Vec<MathVec<MIDDLE>>
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> ChobitMlAi<OUT, MIDDLE, IN> {
+    /// Creates ChobitMlAi.
+    ///
+    /// - `ai` : Base [`ChobitAi`].
+    /// - _Return_ : ChobitMlAi.
+    #[inline]
+    pub fn new(ai: ChobitAi<OUT, MIDDLE, IN>) -> Self {
+        let ChobitAi::<OUT, MIDDLE, IN> {middle_layer, output_layer} = ai;
+
+        Self {
+            middle_layer: MlLayer::<MIDDLE, IN>::new(middle_layer),
+            output_layer: MlLayer::<OUT, MIDDLE>::new(output_layer),
+
+            middle_error: MathVec::<MIDDLE>::new(),
+            output_error: MathVec::<OUT>::new(),
+            middle_errors: Vec::<MathVec<MIDDLE>>::new()
         }
+    }
 
-        self.prev_state.copy_from(prev_state);
+    /// Drops base [`ChobitAi`].
+    ///
+    /// - _Return_ : [`ChobitAi`].
+    #[inline]
+    pub fn drop(self) -> ChobitAi<OUT, MIDDLE, IN> {
+        let Self {middle_layer, output_layer, ..} = self;
 
-        train_in.iter().zip(
-            cache.lstm_state_caches.iter_mut()
-        ).for_each(|(train_in_one, cache)| {
-            self.lstm.ready_state_cache(
-                train_in_one,
-                &self.prev_state,
-                cache
-            );
+        ChobitAi::<OUT, MIDDLE, IN> {
+            middle_layer: middle_layer.drop(),
+            output_layer: output_layer.drop()
+        }
+    }
 
-            self.prev_state.copy_from(&cache.state);
-        })
+    /// Clears internal data for study.
+    #[inline]
+    pub fn clear_study_data(&mut self) {
+        self.middle_layer.clear_study_data();
+        self.output_layer.clear_study_data();
     }
 
-    fn ready_output_cache(
+    /// Sets [`Optimizer`] to all layers.
+    ///
+    /// See [`MlLayer::set_optimizer()`] for details.
+    ///
+    /// - `optimizer` : Optimizer.
+    #[inline]
+    pub fn set_optimizer(&mut self, optimizer: Optimizer) {
+        self.middle_layer.set_optimizer(optimizer);
+        self.output_layer.set_optimizer(optimizer);
+    }
+
+    /// Writes information on [`MlAiCache`] for [`study()`](Self::study()).
+    ///
+    /// - `input` : Input.
+    /// - `cache` : Cache.
+    #[inline]
+    pub fn ready(
         &self,
-        cache: &mut MlEncoderCache<OUT, MIDDLE, IN>
+        input: &MathVec<IN>,
+        cache: &mut MlAiCache<OUT, MIDDLE, IN>
     ) {
-        if let Some(last_state_cache) = cache.lstm_state_caches.get(
-            cache.lstm_state_caches_len.wrapping_sub(1)
-        ) {
-            self.lstm.ready_output_cache(
-                last_state_cache,
-                &mut cache.lstm_output_cache
-            );
+        self.middle_layer.ready(input, None, &mut cache.middle_cache);
 
-            self.output_layer.ready(
-                &cache.lstm_output_cache.output,
-                None,
-                &mut cache.output_layer_cache
-            )
-        }
+        self.output_layer.ready(
+            &cache.middle_cache.output,
+            None,
+            &mut cache.output_cache
+        );
+    }
+
+    /// Writes information on [`MlAiCache`] for [`study()`](Self::study()) with dropout on hidden layer.
+    ///
+    /// See [`MlLayer::ready_with_dropout()`] for details.
+    ///
+    /// - `input` : Input.
+    /// - `cache` : Cache.
+    /// - `dropout` : Probability of dropping. `[0.0, 1.0)`.
+    /// - `rand` : Generator of uniform random number in `[0.0, 1.0)`.
+    #[inline]
+    pub fn ready_with_dropout<F>(
+        &self,
+        input: &MathVec<IN>,
+        cache: &mut MlAiCache<OUT, MIDDLE, IN>,
+        dropout: f32,
+        rand: F
+    ) where F: FnMut() -> f32 {
+        self.middle_layer.ready_with_dropout(
+            input,
+            None,
+            &mut cache.middle_cache,
+            dropout,
+            rand
+        );
+
+        self.output_layer.ready(
+            &cache.middle_cache.output,
+            None,
+            &mut cache.output_cache
+        );
     }
 
     /// Accumulates gradient.
@@ -3975,51 +5884,92 @@ impl<
     /// - `output_error` : Backpropagated ouput error.
     /// - `cache` : Cache.
     /// - `input_error` : Error to backpropagate for previous output error.
-    /// - `prev_state_error` : Error to backpropagate for previous state error.
+    #[inline]
     pub fn study(
         &mut self,
         output_error: &MathVec<OUT>,
-        cache: &MlEncoderCache<OUT, MIDDLE, IN>,
-        input_error: &mut [MathVec<IN>],
-        prev_state_error: &mut MathVec<MIDDLE>
+        cache: &MlAiCache<OUT, MIDDLE, IN>,
+        input_error: &mut MathVec<IN>
     ) {
-        let mut iter = cache.lstm_state_caches().iter().zip(
-            input_error.iter_mut()
-        ).rev();
+        self.output_layer.study(
+            output_error,
+            None,
+            &cache.output_cache,
+            &mut self.middle_error,
+            None
+        );
 
-        self.tmp_state_error.clear();
+        self.middle_layer.study(
+            &self.middle_error,
+            None,
+            &cache.middle_cache,
+            input_error,
+            None
+        );
+    }
 
-        if let Some((lstm_state_cache, input_error)) = iter.next() {
-            self.output_layer.study(
-                &output_error,
-                None,
-                &cache.output_layer_cache,
-                &mut self.tmp_middle_output_error,
-                None
-            );
+    /// Writes information on [`MlAiBatchCache`] for [`study_batch()`](Self::study_batch()).
+    ///
+    /// Result of each sample is the same as [`ready()`](Self::ready()). See [`Weights::calc_batch()`] for details.
+    ///
+    /// - `inputs` : Inputs. Its length is batch size.
+    /// - `cache` : Cache.
+    #[inline]
+    pub fn ready_batch(
+        &self,
+        inputs: &[MathVec<IN>],
+        cache: &mut MlAiBatchCache<OUT, MIDDLE, IN>
+    ) {
+        self.middle_layer.ready_batch(inputs, None, &mut cache.middle_cache);
 
-            self.lstm.study(
-                &self.tmp_middle_output_error,
-                &self.tmp_state_error,
-                &lstm_state_cache,
-                &cache.lstm_output_cache,
-                input_error,
-                prev_state_error
-            );
-        }
+        self.output_layer.ready_batch(
+            &cache.middle_cache.outputs,
+            None,
+            &mut cache.output_cache
+        );
+    }
 
-        self.tmp_state_error.copy_from(prev_state_error);
+    /// Accumulates gradient of batch.
+    ///
+    /// Total gradient and errors are the same as calling [`study()`](Self::study()) for each sample in order.
+    ///
+    /// - `output_errors` : Backpropagated ouput errors.
+    /// - `cache` : Cache.
+    /// - `input_errors` : Errors to backpropagate for previous output errors.
+    pub fn study_batch(
+        &mut self,
+        output_errors: &[MathVec<OUT>],
+        cache: &MlAiBatchCache<OUT, MIDDLE, IN>,
+        input_errors: &mut [MathVec<IN>]
+    ) {
+        self.middle_errors.resize(
+            cache.batch_size().min(output_errors.len()),
+            MathVec::<MIDDLE>::new()
+        );
 
-        iter.for_each(|(lstm_state_cache, input_error)| {
-            self.lstm.study_state(
-                &self.tmp_state_error,
-                lstm_state_cache,
-                input_error,
-                prev_state_error
-            );
+        self.output_layer.study_batch(
+            output_errors,
+            None,
+            &cache.output_cache,
+            &mut self.middle_errors,
+            None
+        );
 
-            self.tmp_state_error.copy_from(prev_state_error);
-        });
+        self.middle_layer.study_batch(
+            &self.middle_errors,
+            None,
+            &cache.middle_cache,
+            input_errors,
+            None
+        );
+    }
+
+    /// Updates weights with learning rate from [`LrScheduler`], and advances step of scheduler.
+    ///
+    /// - `scheduler` : Scheduler of learning rate.
+    #[inline]
+    pub fn update_with_scheduler(&mut self, scheduler: &mut LrScheduler) {
+        self.update(scheduler.next_rate());
     }
 
     /// Updates weights.
@@ -4027,7 +5977,7 @@ impl<
     /// - `rate` : Learning rate.
     #[inline]
     pub fn update(&mut self, rate: f32) {
-        self.lstm.update(rate);
+        self.middle_layer.update(rate);
         self.output_layer.update(rate);
     }
 
@@ -4035,12 +5985,9 @@ impl<
     ///
     /// - `f` : Closure.
     #[inline]
-    pub fn for_each_total_grad<F>(
-        &self,
-        mut f: F
-    ) where F: FnMut(&f32) {
-        self.lstm.for_each_total_grad(|val| {f(val)});
-        self.output_layer.total_grad.iter().for_each(|val| {f(val)});
+    pub fn for_each_total_grad<F>(&self, mut f: F) where F: FnMut(&f32) {
+        self.middle_layer.for_each_grad(&mut f);
+        self.output_layer.for_each_grad(&mut f);
     }
 
     /// Accesses each mutable total gradient with closure.
@@ -4051,1564 +5998,18486 @@ impl<
         &mut self,
         mut f: F
     ) where F: FnMut(&mut f32) {
-        self.lstm.for_each_total_grad_mut(|val| {f(val)});
-        self.output_layer.total_grad.iter_mut().for_each(|val| {f(val)});
+        self.middle_layer.for_each_grad_mut(&mut f);
+        self.output_layer.for_each_grad_mut(&mut f);
     }
-}
 
-/// Decoder from fixed length data to sequence data.
-///
-/// - `OUT` : Dimension of output.
-/// - `MIDDLE` : Dimension of hidden layer.
-/// - `IN` : Dimension of input.
-///
-/// # Example
-///
-/// Letter commentator.
-/// - If inputs '日', outputs "これは日本語です。"
-/// - If inputs 'E', outputs "This is English."
-///
-/// (1) Defines ID and messages.
-///
-/// ```ignore
-/// extern crate chobitlibs;
-/// 
-/// use chobitlibs::chobit_ai::{
-///     MathVec,
-///     Activation,
-///     ChobitDecoder,
-///     ChobitMlDecoder,
-///     MlDecoderCache
-/// };
-/// 
-/// use chobitlibs::chobit_rand::ChobitRand;
-/// 
-/// const JAPANESE_ID: char = '日';
-/// const ENGLISH_ID: char = 'E';
-/// 
-/// const JAPANESE_MESSAGE: &str = "これは日本語です。";
-/// const ENGLISH_MESSAGE: &str = "This is English.";
-/// 
-/// fn write_string_to_slice(string: &str, slice: &mut [MathVec<32>]) {
-///     string.chars().zip(slice.iter_mut()).for_each(|(c, s)| {
-///         s.load_u32_label(c as u32);
-///     });
-/// }
-/// ```
+    /// Clips each total gradient into `[-limit, limit]`.
+    ///
+    /// - `limit` : Limit of absolute value. Negative value is treated as its absolute value. If NaN, nothing is clipped.
+    #[inline]
+    pub fn clip_grad_by_value(&mut self, limit: f32) {
+        clip_by_value(limit, |f| self.for_each_total_grad_mut(f));
+    }
+
+    /// Scales all total gradients so that L2 norm of them is `max_norm` or less.
+    ///
+    /// - `max_norm` : Max L2 norm. Negative value is treated as its absolute value.
+    /// - _Return_ : L2 norm before clipping. If it is not finite, nothing is scaled.
+    #[inline]
+    pub fn clip_grad_by_global_norm(&mut self, max_norm: f32) -> f32 {
+        clip_by_global_norm(max_norm, |f| self.for_each_total_grad_mut(f))
+    }
+
+    /// Adds gradient of L1 and L2 penalty of weights into total gradients of all layers.
+    ///
+    /// See [`MlLayer::add_weight_penalty()`] for details.
+    ///
+    /// - `l1` : Coefficient of L1 penalty.
+    /// - `l2` : Coefficient of L2 penalty.
+    #[inline]
+    pub fn add_weight_penalty(&mut self, l1: f32, l2: f32) {
+        self.middle_layer.add_weight_penalty(l1, l2);
+        self.output_layer.add_weight_penalty(l1, l2);
+    }
+
+    /// Prunes weights of each layer by magnitude, and masks them.
+    ///
+    /// [`Pruning::Percentage`] is applied to each layer. See [`MlLayer::prune()`] for details.
+    ///
+    /// - `pruning` : Way of pruning.
+    /// - _Return_ : Number of masked weights of all layers.
+    #[inline]
+    pub fn prune(&mut self, pruning: Pruning) -> usize {
+        self.middle_layer.prune(pruning) + self.output_layer.prune(pruning)
+    }
+
+    /// Prunes weights of all layers by magnitude with one threshold, and masks them.
+    ///
+    /// [`Pruning::Percentage`] is applied to weights of all layers together. See [`MlLayer::prune()`] for details.
+    ///
+    /// - `pruning` : Way of pruning.
+    /// - _Return_ : Number of masked weights of all layers.
+    pub fn prune_global(&mut self, pruning: Pruning) -> usize {
+        let threshold = pruning.threshold(
+            self.middle_layer.layer.weights.non_bias().iter().chain(
+                self.output_layer.layer.weights.non_bias().iter()
+            )
+        );
+
+        match threshold {
+            Some(threshold) => self.prune(Pruning::Threshold(threshold)),
+
+            None => {
+                self.middle_layer.masked_len() + self.output_layer.masked_len()
+            }
+        }
+    }
+
+    /// Clears mask of pruning of all layers.
+    #[inline]
+    pub fn clear_mask(&mut self) {
+        self.middle_layer.clear_mask();
+        self.output_layer.clear_mask();
+    }
+
+    /// Checks that all total gradients and all weights are finite.
+    ///
+    /// See [`ChobitAiError::NonFinite`] for order of checking.
+    /// If error is returned, you can abort machine learning, or skip the batch by clearing total gradients with [`for_each_total_grad_mut()`](Self::for_each_total_grad_mut()).
+    ///
+    /// - _Return_ : [`ChobitAiError::NonFinite`] if non-finite number is found.
+    pub fn check_finite(&self) -> Result<(), ChobitAiError> {
+        self.output_layer.check_grad("output_layer")?;
+        self.middle_layer.check_grad("middle_layer")?;
+
+        self.output_layer.check_weights("output_layer")?;
+        self.middle_layer.check_weights("middle_layer")?;
+
+        Ok(())
+    }
+
+    /// Writes full state for machine learning into buffer as binary data.
+    ///
+    /// See [`MlLayer::save_checkpoint()`] for details.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save_checkpoint(&self, buffer: &mut Vec<u8>) {
+        write_header(buffer, ModelKind::ChobitMlAi, &[OUT, MIDDLE, IN]);
+
+        self.middle_layer.write_state(buffer);
+        self.output_layer.write_state(buffer);
+    }
+
+    /// Creates ChobitMlAi from binary data written by [`save_checkpoint()`](Self::save_checkpoint()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : ChobitMlAi.
+    pub fn load_checkpoint(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(
+            ModelKind::ChobitMlAi,
+            &[("OUT", OUT), ("MIDDLE", MIDDLE), ("IN", IN)]
+        )?;
+
+        let middle_layer = MlLayer::<MIDDLE, IN>::read_state(&mut reader)?;
+        let output_layer = MlLayer::<OUT, MIDDLE>::read_state(&mut reader)?;
+
+        Ok(Self {
+            middle_layer: middle_layer,
+            output_layer: output_layer,
+
+            middle_error: MathVec::<MIDDLE>::new(),
+            output_error: MathVec::<OUT>::new(),
+            middle_errors: Vec::<MathVec<MIDDLE>>::new()
+        })
+    }
+}
+
+/// Runner of tasks for [`ChobitDataParallel`].
 ///
-/// (2) Creates [`ChobitDecoder`] and randomises weights.
+/// This library doesn't create threads, so user gives a way to run tasks, e.g. `std::thread::scope()`.  
+/// Closure of `FnMut(&mut [&mut (dyn FnMut() + Send)])` implements this trait.
 ///
 /// ```ignore
-/// const OUT: usize = 32;
-/// const MIDDLE: usize = 64;
-/// const IN: usize = 32;
-///
-/// let max_message_len = JAPANESE_MESSAGE.len().max(ENGLISH_MESSAGE.len());
-///
-/// let mut rng = ChobitRand::new(b"ChobitDecoder Example");
-///
-/// let mut decoder =
-///     ChobitDecoder::<OUT, MIDDLE, IN>::new(Activation::SoftSign);
-///
-/// // Randomises weights.
-/// decoder.for_each_weight_mut(|weight| {
-///     *weight = ((rng.next_f64() as f32) * 2.0) - 1.0;
-/// });
-///
-/// let mut input = MathVec::<IN>::new();
-/// let mut output = vec![MathVec::<OUT>::new(); max_message_len];
-/// let initial_state = MathVec::<MIDDLE>::new();
+/// let mut runner = |tasks: &mut [&mut (dyn FnMut() + Send)]| {
+///     std::thread::scope(|scope| {
+///         tasks.iter_mut().for_each(|task| {
+///             scope.spawn(move || task());
+///         });
+///     });
+/// };
 /// ```
+pub trait ParallelRunner {
+    /// Runs all tasks, and returns after all tasks finish.
+    ///
+    /// - `tasks` : Tasks. Each task must be called just once.
+    fn run(&mut self, tasks: &mut [&mut (dyn FnMut() + Send)]);
+}
+
+impl<F> ParallelRunner for F
+where F: FnMut(&mut [&mut (dyn FnMut() + Send)]) {
+    #[inline]
+    fn run(&mut self, tasks: &mut [&mut (dyn FnMut() + Send)]) {
+        self(tasks);
+    }
+}
+
+/// [`ParallelRunner`] that runs tasks one by one on current thread.
 ///
-/// (3) Wraps AI with [`ChobitMlDecoder`] for machine learning.
+/// It is for `no_std` environment and for debugging.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SequentialRunner;
+
+impl ParallelRunner for SequentialRunner {
+    #[inline]
+    fn run(&mut self, tasks: &mut [&mut (dyn FnMut() + Send)]) {
+        tasks.iter_mut().for_each(|task| {task();});
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct DataParallelWorker<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> {
+    ai: ChobitMlAi<OUT, MIDDLE, IN>,
+    cache: MlAiBatchCache<OUT, MIDDLE, IN>,
+
+    output_errors: Vec<MathVec<OUT>>,
+    input_errors: Vec<MathVec<IN>>
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> DataParallelWorker<OUT, MIDDLE, IN> {
+    #[inline]
+    fn new(ai: ChobitAi<OUT, MIDDLE, IN>) -> Self {
+        Self {
+            ai: ChobitMlAi::<OUT, MIDDLE, IN>::new(ai),
+            cache: MlAiBatchCache::<OUT, MIDDLE, IN>::new(),
+
+            output_errors: Vec::<MathVec<OUT>>::new(),
+            input_errors: Vec::<MathVec<IN>>::new()
+        }
+    }
+
+    fn study(
+        &mut self,
+        inputs: &[MathVec<IN>],
+        train_out: &[MathVec<OUT>],
+        loss: &Loss
+    ) {
+        self.output_errors.resize(inputs.len(), MathVec::<OUT>::new());
+        self.input_errors.resize(inputs.len(), MathVec::<IN>::new());
+
+        self.ai.ready_batch(inputs, &mut self.cache);
+
+        self.cache.calc_output_error_with_loss(
+            loss,
+            train_out,
+            &mut self.output_errors
+        );
+
+        self.ai.study_batch(
+            &self.output_errors,
+            &self.cache,
+            &mut self.input_errors
+        );
+    }
+}
+
+/// Data-parallel machine learning of [`ChobitMlAi`].
 ///
-/// ```ignore
-/// let mut decoder = ChobitMlDecoder::<OUT, MIDDLE, IN>::new(decoder);
-/// let mut cache = MlDecoderCache::<OUT, MIDDLE, IN>::new(max_message_len);
+/// It has a master [`ChobitMlAi`] and workers that are copies of the master.
 ///
-/// let mut input_error = MathVec::<IN>::new();
-/// let mut output_error = vec![MathVec::<OUT>::new(); max_message_len];
-/// let mut prev_state_error = MathVec::<MIDDLE>::new();
-/// ```
+/// 1. [`study()`](Self::study()) splits samples into workers, and each worker accumulates its own total gradient with [`ChobitMlAi::study_batch()`]. Workers run as tasks of [`ParallelRunner`].
+/// 2. [`all_reduce()`](Self::all_reduce()) adds total gradients of all workers into the master.
+/// 3. The master updates its weights.
+/// 4. [`broadcast()`](Self::broadcast()) copies weights of the master into all workers.
 ///
-/// (4) Machine learning.
+/// [`update()`](Self::update()) does 2, 3 and 4.  
+/// Gradients of workers are added in order of workers, so result doesn't depend on how tasks are run.
 ///
 /// ```ignore
-/// const EPOCH: usize = 10000;
-/// const BATCH_SIZE: usize = 1;
-/// const RATE: f32 = 0.01;
-///
-/// let japanese_message_len = JAPANESE_MESSAGE.chars().count();
-/// let english_message_len = ENGLISH_MESSAGE.chars().count();
-///
-/// for _ in 0..EPOCH {
-///     for _ in 0..BATCH_SIZE {
-///         //--- Learns Japanese ---//
-///         input.load_u32_label(JAPANESE_ID as u32);
-///
-///         write_string_to_slice(&JAPANESE_MESSAGE, &mut output);
-///
-///         // Writes cache.
-///         decoder.ready(
-///             &input,
-///             &initial_state,
-///             japanese_message_len,
-///             &mut cache
-///         );
-///
-///         // Calculates error.
-///         cache.calc_output_error(
-///             &output[..japanese_message_len],
-///             &mut output_error[..japanese_message_len]
-///         );
-///
-///         // Studies.
-///         decoder.study(
-///             &output_error[..japanese_message_len],
-///             &cache,
-///             &mut input_error,
-///             &mut prev_state_error
-///         );
+/// use chobitlibs::chobit_ai::{
+///     MathVec,
+///     Activation,
+///     ChobitAi,
+///     ChobitMlAi,
+///     ChobitDataParallel,
+///     Loss
+/// };
 ///
-///         //--- Learns English ---//
-///         input.load_u32_label(ENGLISH_ID as u32);
+/// let ai = ChobitAi::<8, 32, 16>::new(Activation::SoftSign);
 ///
-///         write_string_to_slice(&ENGLISH_MESSAGE, &mut output);
+/// let mut parallel =
+///     ChobitDataParallel::<8, 32, 16>::new(ChobitMlAi::new(ai), 4);
 ///
-///         // Writes cache.
-///         decoder.ready(
-///             &input,
-///             &initial_state,
-///             english_message_len,
-///             &mut cache
-///         );
+/// let mut runner = |tasks: &mut [&mut (dyn FnMut() + Send)]| {
+///     std::thread::scope(|scope| {
+///         tasks.iter_mut().for_each(|task| {
+///             scope.spawn(move || task());
+///         });
+///     });
+/// };
 ///
-///         // Calculates error.
-///         cache.calc_output_error(
-///             &output[..english_message_len],
-///             &mut output_error[..english_message_len]
-///         );
+/// let inputs = vec![MathVec::<16>::new(); 100];
+/// let train_out = vec![MathVec::<8>::new(); 100];
 ///
-///         // Studies.
-///         decoder.study(
-///             &output_error[..english_message_len],
-///             &cache,
-///             &mut input_error,
-///             &mut prev_state_error
-///         );
-///     }
+/// parallel.study(&inputs, &train_out, &Loss::Mse, &mut runner);
+/// parallel.update(0.01);
 ///
-///     // Updates weights.
-///     decoder.update(RATE);
-/// }
+/// let ai = parallel.drop().drop();
 /// ```
 ///
-/// (5) Tests AI.
-///
-/// ```ignore
-/// // Unwrap Decoder.
-/// let mut decoder = decoder.drop();
-///
-/// let mut output = MathVec::<OUT>::new();
-///
-/// // Tests Japanese.
-/// // Sets input.
-/// decoder.input_mut().load_u32_label(JAPANESE_ID as u32);
-///
-/// // Initializes state.
-/// decoder.state_mut().copy_from(&initial_state);
-///
-/// // Outputs for each one.
-/// JAPANESE_MESSAGE.chars().for_each(|c| {
-///     decoder.output_next(&mut output);
-///
-///     assert_eq!(output.to_u32_label(), c as u32);
-/// });
-///
-/// // Tests English.
-/// // Sets input.
-/// decoder.input_mut().load_u32_label(ENGLISH_ID as u32);
-///
-/// // Initializes state.
-/// decoder.state_mut().copy_from(&initial_state);
-///
-/// // Outputs for each one.
-/// ENGLISH_MESSAGE.chars().for_each(|c| {
-///     decoder.output_next(&mut output);
-///
-///     assert_eq!(output.to_u32_label(), c as u32);
-/// });
-/// ```
+/// - `OUT` : Dimension of output.
+/// - `MIDDLE` : Dimension of hidden layer.
+/// - `IN` : Dimension of input.
 #[derive(Debug, Clone, PartialEq)]
-pub struct ChobitDecoder<
+pub struct ChobitDataParallel<
     const OUT: usize,
     const MIDDLE: usize,
     const IN: usize
 > {
-    lstm: Lstm<MIDDLE, IN>,
-    output_layer: Layer<OUT, MIDDLE>,
-
-    input: MathVec<IN>,
-    prev_state: MathVec<MIDDLE>,
-    state: MathVec<MIDDLE>,
-
-    middle_output: MathVec<MIDDLE>,
-    tmpbuf: MathVec<MIDDLE>
+    master: ChobitMlAi<OUT, MIDDLE, IN>,
+    workers: Vec<DataParallelWorker<OUT, MIDDLE, IN>>
 }
 
 impl<
     const OUT: usize,
     const MIDDLE: usize,
     const IN: usize
-> ChobitDecoder<OUT, MIDDLE, IN> {
-    /// Creates ChobitDecoder.
+> ChobitDataParallel<OUT, MIDDLE, IN> {
+    /// Creates ChobitDataParallel.
     ///
-    /// - `activation` : Activation function for output layer.
-    /// - _Return_ : ChobitDecoder.
-    pub fn new(activation: Activation) -> Self {
+    /// - `ai` : Master.
+    /// - `num_workers` : Number of workers. If 0, 1 worker is created.
+    /// - _Return_ : ChobitDataParallel.
+    pub fn new(ai: ChobitMlAi<OUT, MIDDLE, IN>, num_workers: usize) -> Self {
+        let base = ai.clone().drop();
+
         Self {
-            lstm: Lstm::<MIDDLE, IN>::new(),
-            output_layer: Layer::<OUT, MIDDLE>::new(activation, false),
+            master: ai,
+            workers: (0..num_workers.max(1)).map(
+                |_| DataParallelWorker::<OUT, MIDDLE, IN>::new(base.clone())
+            ).collect()
+        }
+    }
 
-            input: MathVec::<IN>::new(),
-            prev_state: MathVec::<MIDDLE>::new(),
-            state: MathVec::<MIDDLE>::new(),
-
-            middle_output: MathVec::<MIDDLE>::new(),
-            tmpbuf: MathVec::<MIDDLE>::new()
-        }
-    }
-
-    /// Gets immutable Lstm.
+    /// Drops the master.
     ///
-    /// - _Return_ : Lstm.
+    /// - _Return_ : The master.
     #[inline]
-    pub fn lstm(&self) -> &Lstm<MIDDLE, IN> {&self.lstm}
+    pub fn drop(self) -> ChobitMlAi<OUT, MIDDLE, IN> {self.master}
 
-    /// Gets mutable Lstm.
+    /// Gets immutable the master.
     ///
-    /// - _Return_ : Lstm.
+    /// - _Return_ : The master.
     #[inline]
-    pub fn lstm_mut(&mut self) -> &mut Lstm<MIDDLE, IN> {&mut self.lstm}
+    pub fn master(&self) -> &ChobitMlAi<OUT, MIDDLE, IN> {&self.master}
 
-    /// Gets immutable output layer.
+    /// Gets mutable the master.
     ///
-    /// - _Return_ : Output layer.
-    #[inline]
-    pub fn output_layer(&self) -> &Layer<OUT, MIDDLE> {&self.output_layer}
-
-    /// Gets mutable output layer.
+    /// If you change weights of the master, call [`broadcast()`](Self::broadcast()) before [`study()`](Self::study()).
     ///
-    /// - _Return_ : Output layer.
+    /// - _Return_ : The master.
     #[inline]
-    pub fn output_layer_mut(&mut self) -> &mut Layer<OUT, MIDDLE> {
-        &mut self.output_layer
+    pub fn master_mut(&mut self) -> &mut ChobitMlAi<OUT, MIDDLE, IN> {
+        &mut self.master
     }
 
-    /// Gets immutable input.
+    /// Gets number of workers.
     ///
-    /// This should be set before to call [`output_next()`](Self::output_next()).
-    ///
-    /// - _Return_ : Input.
+    /// - _Return_ : Number of workers.
     #[inline]
-    pub fn input(&self) -> &MathVec<IN> {&self.input}
+    pub fn num_workers(&self) -> usize {self.workers.len()}
 
-    /// Gets mutable input.
+    /// Accumulates total gradient of each worker.
     ///
-    /// This should be set before to call [`output_next()`](Self::output_next()).
+    /// Samples are split into contiguous chunks, one for each worker, and tasks of workers are given to `runner`.  
+    /// If samples are fewer than workers, some workers do nothing.
     ///
-    /// - _Return_ : Input.
-    #[inline]
-    pub fn input_mut(&mut self) -> &mut MathVec<IN> {&mut self.input}
+    /// - `inputs` : Inputs.
+    /// - `train_out` : Correct outputs. If lengths of `inputs` and `train_out` are different, the shorter one is used.
+    /// - `loss` : Loss function.
+    /// - `runner` : Runner of tasks.
+    pub fn study<R>(
+        &mut self,
+        inputs: &[MathVec<IN>],
+        train_out: &[MathVec<OUT>],
+        loss: &Loss,
+        runner: &mut R
+    ) where R: ParallelRunner + ?Sized {
+        let len = inputs.len().min(train_out.len());
 
-    /// Gets immutable state.
-    ///
-    /// This should be initialized before to call [`output_next()`](Self::output_next()).
-    ///
-    /// - _Return_ : State.
-    #[inline]
-    pub fn state(&self) -> &MathVec<MIDDLE> {&self.state}
+        if len == 0 {
+            return;
+        }
 
-    /// Gets mutable state.
-    ///
-    /// This should be initialized before to call [`output_next()`](Self::output_next()).
-    ///
-    /// - _Return_ : State.
-    #[inline]
-    pub fn state_mut(&mut self) -> &mut MathVec<MIDDLE> {&mut self.state}
+        let chunk_size = len.div_ceil(self.workers.len());
 
-    /// Output next data calculated by current state and input.
-    ///
-    /// - `output` : Buffer for next data.
-    pub fn output_next(&mut self, output: &mut MathVec<OUT>) {
-        self.prev_state.copy_from(&self.state);
+        let mut tasks = self.workers.iter_mut().zip(
+            inputs[..len].chunks(chunk_size)
+        ).zip(
+            train_out[..len].chunks(chunk_size)
+        ).map(|((worker, inputs), train_out)| {
+            move || worker.study(inputs, train_out, loss)
+        }).collect::<Vec<_>>();
 
-        self.lstm.calc(
-            &self.input,
-            &self.prev_state,
-            &mut self.middle_output,
-            &mut self.state,
-            &mut self.tmpbuf,
-        );
+        let mut tasks = tasks.iter_mut().map(
+            |task| task as &mut (dyn FnMut() + Send)
+        ).collect::<Vec<_>>();
 
-        self.output_layer.calc(&self.middle_output, None, output);
+        runner.run(&mut tasks);
     }
 
-    /// Accesses each immutable weight with closure.
+    /// Adds total gradients of all workers into the master in order of workers, and clears those of workers.
+    pub fn all_reduce(&mut self) {
+        let master = &mut self.master;
+
+        self.workers.iter_mut().for_each(|worker| {
+            master.middle_layer.take_grad_from(&mut worker.ai.middle_layer);
+            master.output_layer.take_grad_from(&mut worker.ai.output_layer);
+        });
+    }
+
+    /// Copies weights of the master into all workers.
+    pub fn broadcast(&mut self) {
+        let master = &self.master;
+
+        self.workers.iter_mut().for_each(|worker| {
+            worker.ai.middle_layer.copy_weights_from(&master.middle_layer);
+            worker.ai.output_layer.copy_weights_from(&master.output_layer);
+        });
+    }
+
+    /// Updates weights with learning rate from [`LrScheduler`], and advances step of scheduler.
     ///
-    /// - `f` : Closure.
+    /// - `scheduler` : Scheduler of learning rate.
     #[inline]
-    pub fn for_each_weight<F>(&self, mut f: F) where F: FnMut(&f32) {
-        self.lstm.for_each_weight(|val| {f(val)});
-        self.output_layer.weights().iter().for_each(|val| {f(val)});
+    pub fn update_with_scheduler(&mut self, scheduler: &mut LrScheduler) {
+        self.update(scheduler.next_rate());
     }
 
-    /// Accesses each mutable weight with closure.
+    /// Does [`all_reduce()`](Self::all_reduce()), updates the master and does [`broadcast()`](Self::broadcast()).
     ///
-    /// - `f` : Closure.
+    /// - `rate` : Learning rate.
     #[inline]
-    pub fn for_each_weight_mut<F>(
-        &mut self,
-        mut f: F
-    ) where F: FnMut(&mut f32) {
-        self.lstm.for_each_weight_mut(|val| {f(val)});
-        self.output_layer.mut_weights().iter_mut().for_each(|val| {f(val)});
+    pub fn update(&mut self, rate: f32) {
+        self.all_reduce();
+        self.master.update(rate);
+        self.broadcast();
     }
 }
 
-/// Cache for [`ChobitMlDecoder`].
+/// Neural network only for calculating, that can be stacked.
 ///
-/// - `OUT` : `OUT` of [`ChobitMlDecoder`].
-/// - `MIDDLE` : `MIDDLE` of [`ChobitMlDecoder`].
-/// - `IN` : `IN` of [`ChobitMlDecoder`].
-#[derive(Debug, Clone, PartialEq)]
-pub struct MlDecoderCache<
-    const OUT: usize,
-    const MIDDLE: usize,
-    const IN: usize
-> {
-    caches: Vec<(
-        MlLstmStateCache<MIDDLE, IN>,
-        MlLstmOutputCache<MIDDLE, IN>,
-        MlCache<OUT, MIDDLE>
-    )>,
+/// It is implemented by [`Layer`], [`ChobitAi`], [`LayerNorm`], [`Conv1d`], [`Pool1d`], [`StackedLayer`] and [`StackedNetwork`]. Deep network is built with [`stack()`](Network::stack()) and [`stack_network()`](Network::stack_network()).
+///
+/// ```ignore
+/// use chobitlibs::chobit_ai::{
+///     MathVec,
+///     Activation,
+///     Layer,
+///     Network
+/// };
+///
+/// // 16 -> 32 -> 32 -> 8
+/// let network = Layer::<32, 16>::new(Activation::ReLU, false)
+///     .stack(Layer::<32, 32>::new(Activation::ReLU, false))
+///     .stack(Layer::<8, 32>::new(Activation::Linear, false));
+///
+/// let input = MathVec::<16>::new();
+/// let mut output = MathVec::<8>::new();
+/// let mut buffer = network.new_buffer();
+///
+/// network.calc(&input, &mut output, &mut buffer);
+/// ```
+///
+/// - `OUT` : Dimension of output.
+/// - `IN` : Dimension of input.
+pub trait Network<const OUT: usize, const IN: usize> {
+    /// Buffer for middle values of [`calc()`](Network::calc()).
+    type Buffer;
 
-    caches_len: usize
-}
+    /// Wrapper for machine learning.
+    type Ml: MlNetwork<OUT, IN, Network = Self>;
 
-impl<
-    const OUT: usize,
-    const MIDDLE: usize,
-    const IN: usize
-> MlDecoderCache<OUT, MIDDLE, IN> {
-    /// Creates MlDecoderCache.
+    /// Creates buffer for [`calc()`](Network::calc()).
     ///
-    /// - _Return_ : MlDecoderCache.
-    #[inline]
-    pub fn new(capacity: usize) -> Self {
-        Self {
-            caches: vec![
-                (
-                    MlLstmStateCache::<MIDDLE, IN>::new(),
-                    MlLstmOutputCache::<MIDDLE, IN>::new(),
-                    MlCache::<OUT, MIDDLE>::new(),
-                ); capacity
-            ],
-            caches_len: 0
-        }
-    }
+    /// - _Return_ : Buffer.
+    fn new_buffer(&self) -> Self::Buffer;
 
-    /// Calculates output error.
+    /// Calculates output.
     ///
-    /// | Formula |
-    /// |:-:|
-    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mi>e</mi> <mo stretchy="false">=</mo> <mrow> <mi>o</mi> <mo stretchy="false">−</mo> <mi>t</mi> </mrow> </mrow> </semantics> </math> |
-    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mtable columnalign="left"> <mtr> <mtd> <mrow> <mi>e</mi> <mo stretchy="false">≝</mo> <mtext>Error.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>o</mi> <mo stretchy="false">≝</mo> <mtext>Actual output.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>t</mi> <mo stretchy="false">≝</mo> <mtext>Correct output.</mtext> </mrow> </mtd> </mtr> </mtable> </semantics> </math> |
-    ///
-    /// - `train_out` : Correct output.
-    /// - `output_error` : Buffer for output error.
-    #[inline]
-    pub fn calc_output_error(
+    /// - `input` : Input.
+    /// - `output` : Buffer for output.
+    /// - `buffer` : Buffer for middle values.
+    fn calc(
         &self,
-        train_out: &[MathVec<OUT>],
-        output_error: &mut [MathVec<OUT>]
-    ) {
-        train_out.iter().zip(
-            &self.caches
-        ).zip(
-            output_error
-        ).for_each(|(
-            (
-                train_out_one,
-                (_, _, output_layer_cache)
-            ),
-            output_error_one
-        )| {
-            output_error_one.copy_from(&output_layer_cache.output);
-            *output_error_one -= train_out_one;
-        });
-    }
+        input: &MathVec<IN>,
+        output: &mut MathVec<OUT>,
+        buffer: &mut Self::Buffer
+    );
 
-    /// Gets immutable slice of caches.
+    /// Accesses each immutable weight with closure.
     ///
-    /// - _Return_ : Slice of caches.
-    #[inline]
-    pub fn caches(&self) -> &[(
-        MlLstmStateCache<MIDDLE, IN>,
-        MlLstmOutputCache<MIDDLE, IN>,
-        MlCache<OUT, MIDDLE>
-    )] {
-        &self.caches[..self.caches_len]
-    }
+    /// - `f` : Closure.
+    fn for_each_weight<F>(&self, f: F) where F: FnMut(&f32);
 
-    /// Gets mutable slice of caches.
+    /// Accesses each mutable weight with closure.
     ///
-    /// - _Return_ : Slice of caches.
+    /// - `f` : Closure.
+    fn for_each_weight_mut<F>(&mut self, f: F) where F: FnMut(&mut f32);
+
+    /// Creates wrapper for machine learning.
+    ///
+    /// - _Return_ : Wrapper for machine learning.
+    fn into_ml(self) -> Self::Ml;
+
+    /// Stacks a layer on this network.
+    ///
+    /// - `layer` : Layer that receives output of this network.
+    /// - _Return_ : Stacked network.
     #[inline]
-    pub(self) fn caches_mut(&mut self) -> &mut [(
-        MlLstmStateCache<MIDDLE, IN>,
-        MlLstmOutputCache<MIDDLE, IN>,
-        MlCache<OUT, MIDDLE>
-    )] {
-        &mut self.caches[..self.caches_len]
+    fn stack<const NEXT: usize>(
+        self,
+        layer: Layer<NEXT, OUT>
+    ) -> StackedLayer<NEXT, OUT, IN, Self> where Self: Sized {
+        StackedLayer::<NEXT, OUT, IN, Self>::new(self, layer)
     }
 
-    /// Accesses each output with closure.
+    /// Stacks a network on this network.
     ///
-    /// - `f` : Closure.
+    /// - `upper` : Network that receives output of this network.
+    /// - _Return_ : Stacked network.
     #[inline]
-    pub fn for_each_output<F>(&self, mut f: F) where F: FnMut(&MathVec<OUT>) {
-        self.caches[..self.caches_len].iter().for_each(
-            |(_, _, cache)| {f(&cache.output);}
-        );
+    fn stack_network<const NEXT: usize, Upper: Network<NEXT, OUT>>(
+        self,
+        upper: Upper
+    ) -> StackedNetwork<NEXT, OUT, IN, Self, Upper> where Self: Sized {
+        StackedNetwork::<NEXT, OUT, IN, Self, Upper>::new(self, upper)
     }
 }
 
-/// Wrapper of [`ChobitDecoder`] for machine learning.
+/// Neural network for machine learning, that can be stacked.
 ///
-/// See [`ChobitDecoder`] for details.
+/// It is implemented by [`MlLayer`], [`ChobitMlAi`], [`MlLayerNorm`], [`MlConv1d`], [`MlPool1d`], [`MlStackedLayer`] and [`MlStackedNetwork`].
 ///
 /// - `OUT` : Dimension of output.
-/// - `MIDDLE` : Dimension of hidden layer.
 /// - `IN` : Dimension of input.
-#[derive(Debug, Clone, PartialEq)]
-pub struct ChobitMlDecoder<
-    const OUT: usize,
-    const MIDDLE: usize,
-    const IN: usize
-> {
-    lstm: MlLstm<MIDDLE, IN>,
-    output_layer: MlLayer<OUT, MIDDLE>,
+pub trait MlNetwork<const OUT: usize, const IN: usize> {
+    /// Cache for [`study()`](MlNetwork::study()).
+    type Cache;
 
-    prev_state: MathVec<MIDDLE>,
+    /// Base network.
+    type Network: Network<OUT, IN, Ml = Self>;
 
-    tmp_middle_output_error: MathVec<MIDDLE>,
-    tmp_state_error: MathVec<MIDDLE>,
-    tmp_input_error: MathVec<IN>,
+    /// Drops base network.
+    ///
+    /// - _Return_ : Base network.
+    fn drop(self) -> Self::Network;
 
-    original_input: MathVec<IN>,
-    original_prev_state: MathVec<MIDDLE>,
-    original_state: MathVec<MIDDLE>,
+    /// Creates cache for [`ready()`](MlNetwork::ready()).
+    ///
+    /// - _Return_ : Cache.
+    fn new_cache(&self) -> Self::Cache;
 
-    original_middle_output: MathVec<MIDDLE>,
-    original_tmpbuf: MathVec<MIDDLE>
-}
+    /// Gets output from cache.
+    ///
+    /// - `cache` : Cache.
+    /// - _Return_ : Output.
+    fn cache_output(cache: &Self::Cache) -> &MathVec<OUT>;
 
-impl<
-    const OUT: usize,
-    const MIDDLE: usize,
-    const IN: usize
-> ChobitMlDecoder<OUT, MIDDLE, IN> {
-    /// Creates ChobitMlDecoder.
+    /// Writes information on cache for [`study()`](MlNetwork::study()).
     ///
-    /// - `decoder` : Base [`ChobitDecoder`].
-    /// - _Return_ : ChobitMlDecoder.
-    #[inline]
-    pub fn new(decoder: ChobitDecoder<OUT, MIDDLE, IN>) -> Self {
-        let ChobitDecoder::<OUT, MIDDLE, IN> {
-            lstm,
-            output_layer,
-            input,
-            prev_state,
-            state,
-            middle_output,
-            tmpbuf
-        } = decoder;
+    /// - `input` : Input.
+    /// - `cache` : Cache.
+    fn ready(&self, input: &MathVec<IN>, cache: &mut Self::Cache);
 
-        Self {
-            lstm: MlLstm::<MIDDLE, IN>::new(lstm),
-            output_layer: MlLayer::<OUT, MIDDLE>::new(output_layer),
+    /// Accumulates gradient.
+    ///
+    /// - `output_error` : Backpropagated output error.
+    /// - `cache` : Cache generated by [`ready()`](MlNetwork::ready()).
+    /// - `input_error` : Error for input.
+    fn study(
+        &mut self,
+        output_error: &MathVec<OUT>,
+        cache: &Self::Cache,
+        input_error: &mut MathVec<IN>
+    );
 
-            prev_state: MathVec::<MIDDLE>::new(),
+    /// Updates weights.
+    ///
+    /// - `rate` : Learning rate.
+    fn update(&mut self, rate: f32);
 
-            tmp_middle_output_error: MathVec::<MIDDLE>::new(),
-            tmp_state_error: MathVec::<MIDDLE>::new(),
-            tmp_input_error: MathVec::<IN>::new(),
+    /// Clears internal data for study.
+    fn clear_study_data(&mut self);
 
-            original_input: input,
-            original_prev_state: prev_state,
-            original_state: state,
-            original_middle_output: middle_output,
-            original_tmpbuf: tmpbuf
+    /// Sets [`Optimizer`] to all layers.
+    ///
+    /// - `optimizer` : Optimizer.
+    fn set_optimizer(&mut self, optimizer: Optimizer);
+
+    /// Accesses each immutable total gradient with closure.
+    ///
+    /// - `f` : Closure.
+    fn for_each_total_grad<F>(&self, f: F) where F: FnMut(&f32);
+
+    /// Accesses each mutable total gradient with closure.
+    ///
+    /// - `f` : Closure.
+    fn for_each_total_grad_mut<F>(&mut self, f: F) where F: FnMut(&mut f32);
+}
+
+impl<const OUT: usize, const IN: usize> Network<OUT, IN> for Layer<OUT, IN> {
+    type Buffer = ();
+    type Ml = MlLayer<OUT, IN>;
+
+    #[inline]
+    fn new_buffer(&self) -> Self::Buffer {}
+
+    #[inline]
+    fn calc(
+        &self,
+        input: &MathVec<IN>,
+        output: &mut MathVec<OUT>,
+        _buffer: &mut Self::Buffer
+    ) {
+        Layer::calc(self, input, None, output);
+    }
+
+    #[inline]
+    fn for_each_weight<F>(&self, mut f: F) where F: FnMut(&f32) {
+        self.weights.iter().for_each(&mut f);
+
+        if let Activation::PReLU(slope) = &self.activation {
+            f(slope);
         }
     }
 
-    /// Drops base [`ChobitDecoder`].
-    ///
-    /// - _Return_ : [`ChobitDecoder`].
     #[inline]
-    pub fn drop(self) -> ChobitDecoder<OUT, MIDDLE, IN> {
-        let Self {
-            lstm,
-            output_layer,
-            original_input,
-            original_prev_state,
-            original_state,
-            original_middle_output,
-            original_tmpbuf,
-            ..
-        } = self;
+    fn for_each_weight_mut<F>(&mut self, mut f: F) where F: FnMut(&mut f32) {
+        self.weights.iter_mut().for_each(&mut f);
 
-        ChobitDecoder::<OUT, MIDDLE, IN> {
-            lstm: lstm.drop(),
-            output_layer: output_layer.drop(),
-            input: original_input,
-            prev_state: original_prev_state,
-            state: original_state,
-            middle_output: original_middle_output,
-            tmpbuf: original_tmpbuf
+        if let Activation::PReLU(slope) = &mut self.activation {
+            f(slope);
         }
     }
 
-    /// Clears internal data for study.
     #[inline]
-    pub fn clear_study_data(&mut self) {
-        self.lstm.clear_study_data();
-        self.output_layer.clear_study_data();
+    fn into_ml(self) -> Self::Ml {MlLayer::<OUT, IN>::new(self)}
+}
+
+impl<
+    const OUT: usize,
+    const IN: usize
+> MlNetwork<OUT, IN> for MlLayer<OUT, IN> {
+    type Cache = MlCache<OUT, IN>;
+    type Network = Layer<OUT, IN>;
+
+    #[inline]
+    fn drop(self) -> Self::Network {MlLayer::drop(self)}
+
+    #[inline]
+    fn new_cache(&self) -> Self::Cache {MlCache::<OUT, IN>::new()}
+
+    #[inline]
+    fn cache_output(cache: &Self::Cache) -> &MathVec<OUT> {&cache.output}
+
+    #[inline]
+    fn ready(&self, input: &MathVec<IN>, cache: &mut Self::Cache) {
+        MlLayer::ready(self, input, None, cache);
     }
 
-    /// Writes information on [`MlDecoderCache`] for [`study()`](Self::study()).
-    ///
-    /// - `input` : Input.
-    /// - `prev_state` : Previous state.
-    /// - `output_len` : Length of output sequence.
-    /// - `cache` : Cache.
-    pub fn ready(
+    #[inline]
+    fn study(
         &mut self,
-        input: &MathVec<IN>,
-        prev_state: &MathVec<MIDDLE>,
-        output_len: usize,
-        cache: &mut MlDecoderCache<OUT, MIDDLE, IN>
+        output_error: &MathVec<OUT>,
+        cache: &Self::Cache,
+        input_error: &mut MathVec<IN>
     ) {
-        cache.caches_len = output_len;
-        if cache.caches.len() < output_len {
-            cache.caches.resize(
-                output_len,
-                (
-                    MlLstmStateCache::<MIDDLE, IN>::new(),
-                    MlLstmOutputCache::<MIDDLE, IN>::new(),
-                    MlCache::<OUT, MIDDLE>::new()
-                )
-            );
-        }
+        MlLayer::study(self, output_error, None, cache, input_error, None);
+    }
 
-        self.prev_state.copy_from(prev_state);
+    #[inline]
+    fn update(&mut self, rate: f32) {MlLayer::update(self, rate);}
 
-        cache.caches_mut().iter_mut().for_each(|(
-            lstm_state_cache,
-            lstm_output_cache,
-            output_layer_cache
-        )| {
-            self.lstm.ready_state_cache(
-                input,
-                &self.prev_state,
-                lstm_state_cache
-            );
-            self.prev_state.copy_from(&lstm_state_cache.state);
+    #[inline]
+    fn clear_study_data(&mut self) {MlLayer::clear_study_data(self);}
 
-            self.lstm.ready_output_cache(
-                lstm_state_cache,
-                lstm_output_cache
-            );
+    #[inline]
+    fn set_optimizer(&mut self, optimizer: Optimizer) {
+        MlLayer::set_optimizer(self, optimizer);
+    }
 
-            self.output_layer.ready(
-                &lstm_output_cache.output,
-                None,
-                output_layer_cache
-            );
-        });
+    #[inline]
+    fn for_each_total_grad<F>(&self, mut f: F) where F: FnMut(&f32) {
+        self.for_each_grad(&mut f);
     }
 
-    /// Accumulates gradient.
-    ///
-    /// - `output_error` : Backpropagated ouput error.
-    /// - `cache` : Cache.
-    /// - `input_error` : Error to backpropagate for previous output error.
-    /// - `prev_state_error` : Error to backpropagate for previous state error.
-    pub fn study(
+    #[inline]
+    fn for_each_total_grad_mut<F>(
         &mut self,
-        output_error: &[MathVec<OUT>],
-        cache: &MlDecoderCache<OUT, MIDDLE, IN>,
-        input_error: &mut MathVec<IN>,
-        prev_state_error: &mut MathVec<MIDDLE>
-    ) {
-        self.tmp_state_error.clear();
-        input_error.clear();
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.for_each_grad_mut(&mut f);
+    }
+}
 
-        cache.caches().iter().zip(
-            output_error.iter()
-        ).rev().for_each(|(
-            (
-                lstm_state_cache,
-                lstm_output_cache,
-                output_layer_cache
-            ),
-            output_error_one
-        )| {
-            self.output_layer.study(
-                output_error_one,
-                None,
-                &output_layer_cache,
-                &mut self.tmp_middle_output_error,
-                None
-            );
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> Network<OUT, IN> for ChobitAi<OUT, MIDDLE, IN> {
+    type Buffer = MathVec<MIDDLE>;
+    type Ml = ChobitMlAi<OUT, MIDDLE, IN>;
 
-            self.lstm.study(
-                &self.tmp_middle_output_error,
-                &self.tmp_state_error,
-                lstm_state_cache,
-                lstm_output_cache,
-                &mut self.tmp_input_error,
-                prev_state_error
-            );
-            self.tmp_state_error.copy_from(prev_state_error);
-            *input_error += &self.tmp_input_error;
-        });
+    #[inline]
+    fn new_buffer(&self) -> Self::Buffer {MathVec::<MIDDLE>::new()}
+
+    #[inline]
+    fn calc(
+        &self,
+        input: &MathVec<IN>,
+        output: &mut MathVec<OUT>,
+        buffer: &mut Self::Buffer
+    ) {
+        ChobitAi::calc(self, input, output, buffer);
     }
 
-    /// Updates weights.
-    ///
-    /// - `rate` : Learning rate.
     #[inline]
-    pub fn update(&mut self, rate: f32) {
-        self.lstm.update(rate);
-        self.output_layer.update(rate);
+    fn for_each_weight<F>(&self, f: F) where F: FnMut(&f32) {
+        ChobitAi::for_each_weight(self, f);
     }
 
-    /// Accesses each immutable total gradient with closure.
-    ///
-    /// - `f` : Closure.
     #[inline]
-    pub fn for_each_total_grad<F>(
-        &self,
-        mut f: F
-    ) where F: FnMut(&f32) {
-        self.lstm.for_each_total_grad(|val| {f(val)});
-        self.output_layer.total_grad.iter().for_each(|val| {f(val)});
+    fn for_each_weight_mut<F>(&mut self, f: F) where F: FnMut(&mut f32) {
+        ChobitAi::for_each_weight_mut(self, f);
     }
 
-    /// Accesses each mutable total gradient with closure.
-    ///
-    /// - `f` : Closure.
     #[inline]
-    pub fn for_each_total_grad_mut<F>(
+    fn into_ml(self) -> Self::Ml {ChobitMlAi::<OUT, MIDDLE, IN>::new(self)}
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> MlNetwork<OUT, IN> for ChobitMlAi<OUT, MIDDLE, IN> {
+    type Cache = MlAiCache<OUT, MIDDLE, IN>;
+    type Network = ChobitAi<OUT, MIDDLE, IN>;
+
+    #[inline]
+    fn drop(self) -> Self::Network {ChobitMlAi::drop(self)}
+
+    #[inline]
+    fn new_cache(&self) -> Self::Cache {MlAiCache::<OUT, MIDDLE, IN>::new()}
+
+    #[inline]
+    fn cache_output(cache: &Self::Cache) -> &MathVec<OUT> {cache.output()}
+
+    #[inline]
+    fn ready(&self, input: &MathVec<IN>, cache: &mut Self::Cache) {
+        ChobitMlAi::ready(self, input, cache);
+    }
+
+    #[inline]
+    fn study(
         &mut self,
-        mut f: F
-    ) where F: FnMut(&mut f32) {
-        self.lstm.for_each_total_grad_mut(|val| {f(val)});
-        self.output_layer.total_grad.iter_mut().for_each(|val| {f(val)});
+        output_error: &MathVec<OUT>,
+        cache: &Self::Cache,
+        input_error: &mut MathVec<IN>
+    ) {
+        ChobitMlAi::study(self, output_error, cache, input_error);
+    }
+
+    #[inline]
+    fn update(&mut self, rate: f32) {ChobitMlAi::update(self, rate);}
+
+    #[inline]
+    fn clear_study_data(&mut self) {ChobitMlAi::clear_study_data(self);}
+
+    #[inline]
+    fn set_optimizer(&mut self, optimizer: Optimizer) {
+        ChobitMlAi::set_optimizer(self, optimizer);
+    }
+
+    #[inline]
+    fn for_each_total_grad<F>(&self, f: F) where F: FnMut(&f32) {
+        ChobitMlAi::for_each_total_grad(self, f);
+    }
+
+    #[inline]
+    fn for_each_total_grad_mut<F>(&mut self, f: F) where F: FnMut(&mut f32) {
+        ChobitMlAi::for_each_total_grad_mut(self, f);
     }
 }
 
-/// Seq2Seq AI.
+/// [`Layer`] stacked on other [`Network`].
+///
+/// Usually this is created by [`Network::stack()`].
 ///
 /// - `OUT` : Dimension of output.
-/// - `MIDDLE` : Dimension of hidden layer.
+/// - `MIDDLE` : Dimension of output of lower network.
 /// - `IN` : Dimension of input.
+/// - `Lower` : Lower network.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackedLayer<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize,
+    Lower: Network<MIDDLE, IN>
+> {
+    lower: Lower,
+    layer: Layer<OUT, MIDDLE>
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize,
+    Lower: Network<MIDDLE, IN>
+> StackedLayer<OUT, MIDDLE, IN, Lower> {
+    /// Creates StackedLayer.
+    ///
+    /// - `lower` : Lower network.
+    /// - `layer` : Layer that receives output of lower network.
+    /// - _Return_ : StackedLayer.
+    #[inline]
+    pub fn new(lower: Lower, layer: Layer<OUT, MIDDLE>) -> Self {
+        Self {
+            lower: lower,
+            layer: layer
+        }
+    }
+
+    /// Gets immutable lower network.
+    ///
+    /// - _Return_ : Lower network.
+    #[inline]
+    pub fn lower(&self) -> &Lower {&self.lower}
+
+    /// Gets mutable lower network.
+    ///
+    /// - _Return_ : Lower network.
+    #[inline]
+    pub fn lower_mut(&mut self) -> &mut Lower {&mut self.lower}
+
+    /// Gets immutable top layer.
+    ///
+    /// - _Return_ : Top layer.
+    #[inline]
+    pub fn layer(&self) -> &Layer<OUT, MIDDLE> {&self.layer}
+
+    /// Gets mutable top layer.
+    ///
+    /// - _Return_ : Top layer.
+    #[inline]
+    pub fn layer_mut(&mut self) -> &mut Layer<OUT, MIDDLE> {&mut self.layer}
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize,
+    Lower: Network<MIDDLE, IN>
+> Network<OUT, IN> for StackedLayer<OUT, MIDDLE, IN, Lower> {
+    type Buffer = (MathVec<MIDDLE>, Lower::Buffer);
+    type Ml = MlStackedLayer<OUT, MIDDLE, IN, Lower::Ml>;
+
+    #[inline]
+    fn new_buffer(&self) -> Self::Buffer {
+        (MathVec::<MIDDLE>::new(), self.lower.new_buffer())
+    }
+
+    #[inline]
+    fn calc(
+        &self,
+        input: &MathVec<IN>,
+        output: &mut MathVec<OUT>,
+        buffer: &mut Self::Buffer
+    ) {
+        let (middle, lower_buffer) = buffer;
+
+        self.lower.calc(input, middle, lower_buffer);
+        self.layer.calc(middle, None, output);
+    }
+
+    #[inline]
+    fn for_each_weight<F>(&self, mut f: F) where F: FnMut(&f32) {
+        self.lower.for_each_weight(&mut f);
+        self.layer.for_each_weight(f);
+    }
+
+    #[inline]
+    fn for_each_weight_mut<F>(&mut self, mut f: F) where F: FnMut(&mut f32) {
+        self.lower.for_each_weight_mut(&mut f);
+        self.layer.for_each_weight_mut(f);
+    }
+
+    #[inline]
+    fn into_ml(self) -> Self::Ml {
+        MlStackedLayer::<OUT, MIDDLE, IN, Lower::Ml>::new(self)
+    }
+}
+
+/// Cache for [`MlStackedLayer`].
 ///
-/// # Example
-///
-/// ChatCHOBIT
-/// - If inputs Japanese word, outputs "これは日本語です。"
-/// - If inputs English word, outputs "This is English."
-///
-/// (1) Defines word generator and messages.
-///
-/// ```ignore
-/// extern crate chobitlibs;
-/// 
-/// use chobitlibs::chobit_ai::{
-///     MathVec,
-///     Activation,
-///     ChobitSeqAi,
-///     ChobitMlSeqAi,
-///     MlSeqAiCache
-/// };
-/// 
-/// use chobitlibs::chobit_rand::ChobitRand;
-/// 
-/// fn japanese_letter(rng: &mut ChobitRand) -> char {
-///     let letters = [
-///         'あ', 'い', 'う', 'え', 'お',
-///         'か', 'き', 'く', 'け', 'こ',
-///         'さ', 'し', 'す', 'せ', 'そ'
-///     ];
-/// 
-///     letters[(rng.next_u64() as usize) % letters.len()]
-/// }
-/// 
-/// fn english_letter(rng: &mut ChobitRand) -> char {
-///     let letters = [
-///         'a', 'b', 'c', 'd', 'e',
-///         'f', 'g', 'h', 'i', 'j',
-///         'k', 'l', 'm', 'n', 'o'
-///     ];
-/// 
-///     letters[(rng.next_u64() as usize) % letters.len()]
-/// }
-/// 
-/// fn gen_word(
-///     f: fn(&mut ChobitRand) -> char,
-///     rng: &mut ChobitRand,
-///     max_len: usize
-/// ) -> String {
-///     let word_len = ((rng.next_u64() as usize) % max_len) + 1;
-/// 
-///     let mut ret = String::with_capacity(word_len);
-/// 
-///     for _ in 0..word_len {
-///         ret.push(f(rng));
-///     }
-/// 
-///     ret
-/// }
-/// 
-/// fn write_string_to_slice(string: &str, slice: &mut [MathVec<32>]) {
-///     string.chars().zip(slice.iter_mut()).for_each(|(c, s)| {
-///         s.load_u32_label(c as u32);
-///     });
-/// }
-/// 
-/// const JAPANESE_MESSAGE: &str = "これは日本語です。";
-/// const ENGLISH_MESSAGE: &str = "This is English.";
-/// ```
-///
-/// (2) Creates [`ChobitSeqAi`] and randomises weights.
-///
-/// ```ignore
-/// const OUT: usize = 32;
-/// const MIDDLE: usize = 64;
-/// const IN: usize = 32;
-///
-/// const MAX_WORD_LEN: usize = 10;
-/// let max_message_len = JAPANESE_MESSAGE.len().max(ENGLISH_MESSAGE.len());
-///
-/// let mut rng = ChobitRand::new(b"ChobitSeqAi Example");
-///
-/// let mut ai = ChobitSeqAi::<OUT, MIDDLE, IN>::new(Activation::SoftSign);
+/// - `OUT` : Dimension of output.
+/// - `MIDDLE` : Dimension of output of lower network.
+/// - `LowerCache` : Cache of lower network.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlStackedCache<const OUT: usize, const MIDDLE: usize, LowerCache> {
+    lower_cache: LowerCache,
+    cache: MlCache<OUT, MIDDLE>
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    LowerCache
+> MlStackedCache<OUT, MIDDLE, LowerCache> {
+    /// Calculates output error.
+    ///
+    /// See [`MlCache::calc_output_error()`].
+    ///
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error(
+        &self,
+        train_out: &MathVec<OUT>,
+        output_error: &mut MathVec<OUT>
+    ) {
+        self.cache.calc_output_error(train_out, output_error);
+    }
+
+    /// Calculates output error with loss function.
+    ///
+    /// - `loss` : Loss function.
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error_with_loss(
+        &self,
+        loss: &Loss,
+        train_out: &MathVec<OUT>,
+        output_error: &mut MathVec<OUT>
+    ) {
+        self.cache.calc_output_error_with_loss(loss, train_out, output_error);
+    }
+
+    /// Gets cache of lower network.
+    ///
+    /// - _Return_ : Cache of lower network.
+    #[inline]
+    pub fn lower_cache(&self) -> &LowerCache {&self.lower_cache}
+
+    /// Gets cache of top layer.
+    ///
+    /// - _Return_ : Cache of top layer.
+    #[inline]
+    pub fn cache(&self) -> &MlCache<OUT, MIDDLE> {&self.cache}
+
+    /// Gets output.
+    ///
+    /// - _Return_ : Output.
+    #[inline]
+    pub fn output(&self) -> &MathVec<OUT> {&self.cache.output}
+}
+
+/// Wrapper of [`StackedLayer`] for machine learning.
 ///
-/// // Randomises weights.
-/// ai.for_each_weight_mut(|weight| {
-///     *weight = ((rng.next_f64() as f32) * 2.0) - 1.0;
-/// });
+/// Usually this is created by [`Network::into_ml()`].
 ///
-/// let mut input = vec![MathVec::<IN>::new(); MAX_WORD_LEN];
-/// let mut output = vec![MathVec::<OUT>::new(); max_message_len];
-/// let initial_state = MathVec::<MIDDLE>::new();
-/// ```
+/// - `OUT` : Dimension of output.
+/// - `MIDDLE` : Dimension of output of lower network.
+/// - `IN` : Dimension of input.
+/// - `Lower` : Lower network for machine learning.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlStackedLayer<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize,
+    Lower: MlNetwork<MIDDLE, IN>
+> {
+    lower: Lower,
+    layer: MlLayer<OUT, MIDDLE>,
+
+    middle_error: MathVec<MIDDLE>
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize,
+    Lower: MlNetwork<MIDDLE, IN>
+> MlStackedLayer<OUT, MIDDLE, IN, Lower> {
+    /// Creates MlStackedLayer.
+    ///
+    /// - `network` : Base [`StackedLayer`].
+    /// - _Return_ : MlStackedLayer.
+    #[inline]
+    pub fn new(network: StackedLayer<OUT, MIDDLE, IN, Lower::Network>) -> Self {
+        let StackedLayer::<OUT, MIDDLE, IN, Lower::Network> {lower, layer} =
+            network;
+
+        Self {
+            lower: lower.into_ml(),
+            layer: MlLayer::<OUT, MIDDLE>::new(layer),
+
+            middle_error: MathVec::<MIDDLE>::new()
+        }
+    }
+
+    /// Gets immutable lower network.
+    ///
+    /// - _Return_ : Lower network.
+    #[inline]
+    pub fn lower(&self) -> &Lower {&self.lower}
+
+    /// Gets mutable lower network.
+    ///
+    /// - _Return_ : Lower network.
+    #[inline]
+    pub fn lower_mut(&mut self) -> &mut Lower {&mut self.lower}
+
+    /// Gets immutable top layer.
+    ///
+    /// - _Return_ : Top layer.
+    #[inline]
+    pub fn layer(&self) -> &MlLayer<OUT, MIDDLE> {&self.layer}
+
+    /// Gets mutable top layer.
+    ///
+    /// - _Return_ : Top layer.
+    #[inline]
+    pub fn layer_mut(&mut self) -> &mut MlLayer<OUT, MIDDLE> {&mut self.layer}
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize,
+    Lower: MlNetwork<MIDDLE, IN>
+> MlNetwork<OUT, IN> for MlStackedLayer<OUT, MIDDLE, IN, Lower> {
+    type Cache = MlStackedCache<OUT, MIDDLE, Lower::Cache>;
+    type Network = StackedLayer<OUT, MIDDLE, IN, Lower::Network>;
+
+    #[inline]
+    fn drop(self) -> Self::Network {
+        let Self {lower, layer, ..} = self;
+
+        StackedLayer::<OUT, MIDDLE, IN, Lower::Network> {
+            lower: lower.drop(),
+            layer: layer.drop()
+        }
+    }
+
+    #[inline]
+    fn new_cache(&self) -> Self::Cache {
+        MlStackedCache::<OUT, MIDDLE, Lower::Cache> {
+            lower_cache: self.lower.new_cache(),
+            cache: MlCache::<OUT, MIDDLE>::new()
+        }
+    }
+
+    #[inline]
+    fn cache_output(cache: &Self::Cache) -> &MathVec<OUT> {&cache.cache.output}
+
+    #[inline]
+    fn ready(&self, input: &MathVec<IN>, cache: &mut Self::Cache) {
+        self.lower.ready(input, &mut cache.lower_cache);
+
+        self.layer.ready(
+            Lower::cache_output(&cache.lower_cache),
+            None,
+            &mut cache.cache
+        );
+    }
+
+    #[inline]
+    fn study(
+        &mut self,
+        output_error: &MathVec<OUT>,
+        cache: &Self::Cache,
+        input_error: &mut MathVec<IN>
+    ) {
+        self.layer.study(
+            output_error,
+            None,
+            &cache.cache,
+            &mut self.middle_error,
+            None
+        );
+
+        self.lower.study(&self.middle_error, &cache.lower_cache, input_error);
+    }
+
+    #[inline]
+    fn update(&mut self, rate: f32) {
+        self.lower.update(rate);
+        self.layer.update(rate);
+    }
+
+    #[inline]
+    fn clear_study_data(&mut self) {
+        self.lower.clear_study_data();
+        self.layer.clear_study_data();
+    }
+
+    #[inline]
+    fn set_optimizer(&mut self, optimizer: Optimizer) {
+        self.lower.set_optimizer(optimizer);
+        self.layer.set_optimizer(optimizer);
+    }
+
+    #[inline]
+    fn for_each_total_grad<F>(&self, mut f: F) where F: FnMut(&f32) {
+        self.lower.for_each_total_grad(&mut f);
+        self.layer.for_each_grad(&mut f);
+    }
+
+    #[inline]
+    fn for_each_total_grad_mut<F>(
+        &mut self,
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.lower.for_each_total_grad_mut(&mut f);
+        self.layer.for_each_grad_mut(&mut f);
+    }
+}
+
+/// [`Network`] stacked on other [`Network`].
 ///
-/// (3) Wraps AI with [`ChobitMlSeqAi`] for machine learning.
+/// Usually this is created by [`Network::stack_network()`]. Unlike [`StackedLayer`], upper network is not limited to [`Layer`], so [`Conv1d`] and [`Pool1d`] can be stacked.
 ///
-/// ```ignore
-/// let mut ai = ChobitMlSeqAi::<OUT, MIDDLE, IN>::new(ai);
-/// let mut cache = MlSeqAiCache::<OUT, MIDDLE, IN>::new(
-///     MAX_WORD_LEN,
-///     max_message_len
-/// );
-///
-/// let mut input_error = vec![MathVec::<IN>::new(); MAX_WORD_LEN];
-/// let mut output_error = vec![MathVec::<OUT>::new(); max_message_len];
-/// let mut prev_state_error = MathVec::<MIDDLE>::new();
-/// ```
-///
-/// (4) Machine learning.
-///
-/// ```ignore
-/// const EPOCH: usize = 10000;
-/// const BATCH_SIZE: usize = 10;
-/// const RATE: f32 = 0.01;
-///
-/// let japanese_message_len = JAPANESE_MESSAGE.chars().count();
-/// let english_message_len = ENGLISH_MESSAGE.chars().count();
+/// - `OUT` : Dimension of output.
+/// - `MIDDLE` : Dimension of output of lower network.
+/// - `IN` : Dimension of input.
+/// - `Lower` : Lower network.
+/// - `Upper` : Upper network.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackedNetwork<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize,
+    Lower: Network<MIDDLE, IN>,
+    Upper: Network<OUT, MIDDLE>
+> {
+    lower: Lower,
+    upper: Upper
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize,
+    Lower: Network<MIDDLE, IN>,
+    Upper: Network<OUT, MIDDLE>
+> StackedNetwork<OUT, MIDDLE, IN, Lower, Upper> {
+    /// Creates StackedNetwork.
+    ///
+    /// - `lower` : Lower network.
+    /// - `upper` : Network that receives output of lower network.
+    /// - _Return_ : StackedNetwork.
+    #[inline]
+    pub fn new(lower: Lower, upper: Upper) -> Self {
+        Self {
+            lower: lower,
+            upper: upper
+        }
+    }
+
+    /// Gets immutable lower network.
+    ///
+    /// - _Return_ : Lower network.
+    #[inline]
+    pub fn lower(&self) -> &Lower {&self.lower}
+
+    /// Gets mutable lower network.
+    ///
+    /// - _Return_ : Lower network.
+    #[inline]
+    pub fn lower_mut(&mut self) -> &mut Lower {&mut self.lower}
+
+    /// Gets immutable upper network.
+    ///
+    /// - _Return_ : Upper network.
+    #[inline]
+    pub fn upper(&self) -> &Upper {&self.upper}
+
+    /// Gets mutable upper network.
+    ///
+    /// - _Return_ : Upper network.
+    #[inline]
+    pub fn upper_mut(&mut self) -> &mut Upper {&mut self.upper}
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize,
+    Lower: Network<MIDDLE, IN>,
+    Upper: Network<OUT, MIDDLE>
+> Network<OUT, IN> for StackedNetwork<OUT, MIDDLE, IN, Lower, Upper> {
+    type Buffer = (MathVec<MIDDLE>, Lower::Buffer, Upper::Buffer);
+    type Ml = MlStackedNetwork<OUT, MIDDLE, IN, Lower::Ml, Upper::Ml>;
+
+    #[inline]
+    fn new_buffer(&self) -> Self::Buffer {
+        (
+            MathVec::<MIDDLE>::new(),
+            self.lower.new_buffer(),
+            self.upper.new_buffer()
+        )
+    }
+
+    #[inline]
+    fn calc(
+        &self,
+        input: &MathVec<IN>,
+        output: &mut MathVec<OUT>,
+        buffer: &mut Self::Buffer
+    ) {
+        let (middle, lower_buffer, upper_buffer) = buffer;
+
+        self.lower.calc(input, middle, lower_buffer);
+        self.upper.calc(middle, output, upper_buffer);
+    }
+
+    #[inline]
+    fn for_each_weight<F>(&self, mut f: F) where F: FnMut(&f32) {
+        self.lower.for_each_weight(&mut f);
+        self.upper.for_each_weight(f);
+    }
+
+    #[inline]
+    fn for_each_weight_mut<F>(&mut self, mut f: F) where F: FnMut(&mut f32) {
+        self.lower.for_each_weight_mut(&mut f);
+        self.upper.for_each_weight_mut(f);
+    }
+
+    #[inline]
+    fn into_ml(self) -> Self::Ml {
+        MlStackedNetwork::<OUT, MIDDLE, IN, Lower::Ml, Upper::Ml>::new(self)
+    }
+}
+
+/// Cache for [`MlStackedNetwork`].
 ///
-/// for _ in 0..EPOCH {
-///     for _ in 0..BATCH_SIZE {
-///         //--- Learns Japanese ---//
-///         let string = gen_word(japanese_letter, &mut rng, MAX_WORD_LEN);
+/// - `LowerCache` : Cache of lower network.
+/// - `UpperCache` : Cache of upper network.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlStackedNetworkCache<LowerCache, UpperCache> {
+    lower_cache: LowerCache,
+    upper_cache: UpperCache
+}
+
+impl<LowerCache, UpperCache> MlStackedNetworkCache<LowerCache, UpperCache> {
+    /// Gets cache of lower network.
+    ///
+    /// - _Return_ : Cache of lower network.
+    #[inline]
+    pub fn lower_cache(&self) -> &LowerCache {&self.lower_cache}
+
+    /// Gets cache of upper network.
+    ///
+    /// - _Return_ : Cache of upper network.
+    #[inline]
+    pub fn upper_cache(&self) -> &UpperCache {&self.upper_cache}
+}
+
+/// Wrapper of [`StackedNetwork`] for machine learning.
 ///
-///         write_string_to_slice(&string, &mut input);
-///         write_string_to_slice(&JAPANESE_MESSAGE, &mut output);
+/// Usually this is created by [`Network::into_ml()`].
 ///
-///         // Writes cache.
-///         ai.ready(
-///             &input[..string.chars().count()],
-///             &initial_state,
-///             japanese_message_len,
-///             &mut cache
-///         );
+/// - `OUT` : Dimension of output.
+/// - `MIDDLE` : Dimension of output of lower network.
+/// - `IN` : Dimension of input.
+/// - `Lower` : Lower network for machine learning.
+/// - `Upper` : Upper network for machine learning.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlStackedNetwork<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize,
+    Lower: MlNetwork<MIDDLE, IN>,
+    Upper: MlNetwork<OUT, MIDDLE>
+> {
+    lower: Lower,
+    upper: Upper,
+
+    middle_error: MathVec<MIDDLE>
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize,
+    Lower: MlNetwork<MIDDLE, IN>,
+    Upper: MlNetwork<OUT, MIDDLE>
+> MlStackedNetwork<OUT, MIDDLE, IN, Lower, Upper> {
+    /// Creates MlStackedNetwork.
+    ///
+    /// - `network` : Base [`StackedNetwork`].
+    /// - _Return_ : MlStackedNetwork.
+    #[inline]
+    pub fn new(
+        network: StackedNetwork<
+            OUT,
+            MIDDLE,
+            IN,
+            Lower::Network,
+            Upper::Network
+        >
+    ) -> Self {
+        let StackedNetwork::<
+            OUT,
+            MIDDLE,
+            IN,
+            Lower::Network,
+            Upper::Network
+        > {lower, upper} = network;
+
+        Self {
+            lower: lower.into_ml(),
+            upper: upper.into_ml(),
+
+            middle_error: MathVec::<MIDDLE>::new()
+        }
+    }
+
+    /// Gets immutable lower network.
+    ///
+    /// - _Return_ : Lower network.
+    #[inline]
+    pub fn lower(&self) -> &Lower {&self.lower}
+
+    /// Gets mutable lower network.
+    ///
+    /// - _Return_ : Lower network.
+    #[inline]
+    pub fn lower_mut(&mut self) -> &mut Lower {&mut self.lower}
+
+    /// Gets immutable upper network.
+    ///
+    /// - _Return_ : Upper network.
+    #[inline]
+    pub fn upper(&self) -> &Upper {&self.upper}
+
+    /// Gets mutable upper network.
+    ///
+    /// - _Return_ : Upper network.
+    #[inline]
+    pub fn upper_mut(&mut self) -> &mut Upper {&mut self.upper}
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize,
+    Lower: MlNetwork<MIDDLE, IN>,
+    Upper: MlNetwork<OUT, MIDDLE>
+> MlNetwork<OUT, IN> for MlStackedNetwork<OUT, MIDDLE, IN, Lower, Upper> {
+    type Cache = MlStackedNetworkCache<Lower::Cache, Upper::Cache>;
+    type Network = StackedNetwork<
+        OUT,
+        MIDDLE,
+        IN,
+        Lower::Network,
+        Upper::Network
+    >;
+
+    #[inline]
+    fn drop(self) -> Self::Network {
+        let Self {lower, upper, ..} = self;
+
+        StackedNetwork::<OUT, MIDDLE, IN, Lower::Network, Upper::Network> {
+            lower: lower.drop(),
+            upper: upper.drop()
+        }
+    }
+
+    #[inline]
+    fn new_cache(&self) -> Self::Cache {
+        MlStackedNetworkCache::<Lower::Cache, Upper::Cache> {
+            lower_cache: self.lower.new_cache(),
+            upper_cache: self.upper.new_cache()
+        }
+    }
+
+    #[inline]
+    fn cache_output(cache: &Self::Cache) -> &MathVec<OUT> {
+        Upper::cache_output(&cache.upper_cache)
+    }
+
+    #[inline]
+    fn ready(&self, input: &MathVec<IN>, cache: &mut Self::Cache) {
+        self.lower.ready(input, &mut cache.lower_cache);
+
+        self.upper.ready(
+            Lower::cache_output(&cache.lower_cache),
+            &mut cache.upper_cache
+        );
+    }
+
+    #[inline]
+    fn study(
+        &mut self,
+        output_error: &MathVec<OUT>,
+        cache: &Self::Cache,
+        input_error: &mut MathVec<IN>
+    ) {
+        self.upper.study(
+            output_error,
+            &cache.upper_cache,
+            &mut self.middle_error
+        );
+        self.lower.study(&self.middle_error, &cache.lower_cache, input_error);
+    }
+
+    #[inline]
+    fn update(&mut self, rate: f32) {
+        self.lower.update(rate);
+        self.upper.update(rate);
+    }
+
+    #[inline]
+    fn clear_study_data(&mut self) {
+        self.lower.clear_study_data();
+        self.upper.clear_study_data();
+    }
+
+    #[inline]
+    fn set_optimizer(&mut self, optimizer: Optimizer) {
+        self.lower.set_optimizer(optimizer);
+        self.upper.set_optimizer(optimizer);
+    }
+
+    #[inline]
+    fn for_each_total_grad<F>(&self, mut f: F) where F: FnMut(&f32) {
+        self.lower.for_each_total_grad(&mut f);
+        self.upper.for_each_total_grad(f);
+    }
+
+    #[inline]
+    fn for_each_total_grad_mut<F>(
+        &mut self,
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.lower.for_each_total_grad_mut(&mut f);
+        self.upper.for_each_total_grad_mut(f);
+    }
+}
+
+/// 1D convolution layer for signal data.
 ///
-///         // Calculates error.
-///         cache.calc_output_error(
-///             &output[..japanese_message_len],
-///             &mut output_error[..japanese_message_len]
-///         );
+/// Input and output are sequences of frames flattened into [`MathVec`]. Element `c` of frame `t` is at `t * CH + c`.
 ///
-///         // Studies.
-///         ai.study(
-///             &output_error[..japanese_message_len],
-///             &cache,
-///             &mut input_error,
-///             &mut prev_state_error
-///         );
+/// The same kernel of [`Layer`] is applied to each window of `KERNEL` frames, sliding by `STRIDE` frames.
 ///
-///         //--- Learns English ---//
-///         let string = gen_word(english_letter, &mut rng, MAX_WORD_LEN);
+/// | Formula |
+/// |:-:|
+/// | `y_t = φ(W [x_(t * STRIDE), ..., x_(t * STRIDE + KERNEL - 1)] + b)` |
+/// | `OUT_LEN = ((IN / IN_CH) - KERNEL) / STRIDE + 1` |
 ///
-///         write_string_to_slice(&string, &mut input);
-///         write_string_to_slice(&ENGLISH_MESSAGE, &mut output);
+/// It implements [`Network`], so [`Layer`] can be stacked on it. To use it before [`ChobitEncoder`], input each frame of output (`output.chunks(OUT_CH)`) to the encoder.
 ///
-///         // Writes cache.
-///         ai.ready(
-///             &input[..string.chars().count()],
-///             &initial_state,
-///             english_message_len,
-///             &mut cache
-///         );
+/// ```ignore
+/// use chobitlibs::chobit_ai::{
+///     MathVec,
+///     Activation,
+///     Layer,
+///     Conv1d,
+///     Pool1d,
+///     Pooling,
+///     Network
+/// };
 ///
-///         // Calculates error.
-///         cache.calc_output_error(
-///             &output[..english_message_len],
-///             &mut output_error[..english_message_len]
-///         );
+/// // 16 frames of 2 channels -> 7 frames of 4 channels -> 3 frames of 4 channels -> 8
+/// let network = Conv1d::<28, 32, 4, 2, 3, 2, 6>::new(Activation::ReLU)
+///     .stack_network(Pool1d::<12, 28, 4, 2, 2>::new(Pooling::Max))
+///     .stack(Layer::<8, 12>::new(Activation::Linear, false));
 ///
-///         // Studies.
-///         ai.study(
-///             &output_error[..english_message_len],
-///             &cache,
-///             &mut input_error,
-///             &mut prev_state_error
-///         );
-///     }
+/// let input = MathVec::<32>::new();
+/// let mut output = MathVec::<8>::new();
+/// let mut buffer = network.new_buffer();
 ///
-///     // Updates weights.
-///     ai.update(RATE);
-/// }
+/// network.calc(&input, &mut output, &mut buffer);
 /// ```
 ///
-/// (5) Tests AI.
-///
-/// ```ignore
-/// // Unwrap AI.
-/// let mut ai = ai.drop();
-///
-/// let mut output = MathVec::<OUT>::new();
+/// - `OUT` : Dimension of output. It must be `OUT_LEN * OUT_CH`.
+/// - `IN` : Dimension of input. It must be divisible by `IN_CH`.
+/// - `OUT_CH` : Number of output channels.
+/// - `IN_CH` : Number of input channels.
+/// - `KERNEL` : Kernel size (number of frames of a window).
+/// - `STRIDE` : Stride (number of frames).
+/// - `WINDOW` : Dimension of a window. It must be `KERNEL * IN_CH`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conv1d<
+    const OUT: usize,
+    const IN: usize,
+    const OUT_CH: usize,
+    const IN_CH: usize,
+    const KERNEL: usize,
+    const STRIDE: usize,
+    const WINDOW: usize
+> {
+    kernel: Layer<OUT_CH, WINDOW>
+}
+
+impl<
+    const OUT: usize,
+    const IN: usize,
+    const OUT_CH: usize,
+    const IN_CH: usize,
+    const KERNEL: usize,
+    const STRIDE: usize,
+    const WINDOW: usize
+> Conv1d<OUT, IN, OUT_CH, IN_CH, KERNEL, STRIDE, WINDOW> {
+    const OUT_LEN: usize = {
+        assert!(IN_CH > 0, "IN_CH must be more than 0");
+        assert!(KERNEL > 0, "KERNEL must be more than 0");
+        assert!(STRIDE > 0, "STRIDE must be more than 0");
+        assert!(WINDOW == KERNEL * IN_CH, "WINDOW must be KERNEL * IN_CH");
+        assert!(IN.is_multiple_of(IN_CH), "IN must be divisible by IN_CH");
+        assert!(IN / IN_CH >= KERNEL, "IN / IN_CH must be KERNEL or more");
+
+        let out_len = (((IN / IN_CH) - KERNEL) / STRIDE) + 1;
+
+        assert!(OUT == out_len * OUT_CH, "OUT must be OUT_LEN * OUT_CH");
+
+        out_len
+    };
+
+    /// Creates Conv1d.
+    ///
+    /// - `activation` : Activation function.
+    /// - _Return_ : Conv1d.
+    #[inline]
+    pub fn new(activation: Activation) -> Self {
+        Self::with_kernel(Layer::<OUT_CH, WINDOW>::new(activation, false))
+    }
+
+    /// Creates Conv1d with kernel.
+    ///
+    /// - `kernel` : Kernel. State weights of it are not used.
+    /// - _Return_ : Conv1d.
+    #[inline]
+    pub fn with_kernel(kernel: Layer<OUT_CH, WINDOW>) -> Self {
+        Self {
+            kernel: kernel
+        }
+    }
+
+    /// Gets number of output frames.
+    ///
+    /// - _Return_ : `OUT_LEN`.
+    #[inline]
+    pub fn output_len(&self) -> usize {Self::OUT_LEN}
+
+    /// Gets immutable kernel.
+    ///
+    /// - _Return_ : Kernel.
+    #[inline]
+    pub fn kernel(&self) -> &Layer<OUT_CH, WINDOW> {&self.kernel}
+
+    /// Gets mutable kernel.
+    ///
+    /// - _Return_ : Kernel.
+    #[inline]
+    pub fn kernel_mut(&mut self) -> &mut Layer<OUT_CH, WINDOW> {
+        &mut self.kernel
+    }
+
+    /// Initializes weights with [`Initializer`].
+    ///
+    /// - `initializer` : Initializer.
+    /// - `rand` : Closure that returns uniform random number in `[0.0, 1.0)`.
+    #[inline]
+    pub fn init_weights<F>(&mut self, initializer: Initializer, rand: F)
+    where F: FnMut() -> f32 {
+        self.kernel.init_weights(initializer, rand);
+    }
+
+    /// Calculates output.
+    ///
+    /// - `input` : Input.
+    /// - `output` : Buffer for output.
+    pub fn calc(&self, input: &MathVec<IN>, output: &mut MathVec<OUT>) {
+        let activation = self.kernel.activation();
+        let weights = &self.kernel.weights;
+
+        output.chunks_exact_mut(OUT_CH).enumerate().take(Self::OUT_LEN)
+            .for_each(|(t, output_one)| {
+                let window = Self::window(input, t);
+
+                output_one.iter_mut().zip(
+                    weights.input_weights().iter()
+                ).zip(
+                    weights.bias().iter()
+                ).for_each(|((output_one, w), b)| {
+                    *output_one =
+                        activation.activate(add_dot(*b, w, window));
+                });
+            });
+    }
+
+    #[inline]
+    fn window(input: &MathVec<IN>, position: usize) -> &[f32] {
+        let start = position * STRIDE * IN_CH;
+
+        &input[start..(start + WINDOW)]
+    }
+}
+
+impl<
+    const OUT: usize,
+    const IN: usize,
+    const OUT_CH: usize,
+    const IN_CH: usize,
+    const KERNEL: usize,
+    const STRIDE: usize,
+    const WINDOW: usize
+> Network<OUT, IN> for Conv1d<OUT, IN, OUT_CH, IN_CH, KERNEL, STRIDE, WINDOW> {
+    type Buffer = ();
+    type Ml = MlConv1d<OUT, IN, OUT_CH, IN_CH, KERNEL, STRIDE, WINDOW>;
+
+    #[inline]
+    fn new_buffer(&self) -> Self::Buffer {}
+
+    #[inline]
+    fn calc(
+        &self,
+        input: &MathVec<IN>,
+        output: &mut MathVec<OUT>,
+        _buffer: &mut Self::Buffer
+    ) {
+        Conv1d::calc(self, input, output);
+    }
+
+    #[inline]
+    fn for_each_weight<F>(&self, f: F) where F: FnMut(&f32) {
+        self.kernel.for_each_weight(f);
+    }
+
+    #[inline]
+    fn for_each_weight_mut<F>(&mut self, f: F) where F: FnMut(&mut f32) {
+        self.kernel.for_each_weight_mut(f);
+    }
+
+    #[inline]
+    fn into_ml(self) -> Self::Ml {
+        MlConv1d::<OUT, IN, OUT_CH, IN_CH, KERNEL, STRIDE, WINDOW>::new(self)
+    }
+}
+
+/// Cache for [`MlConv1d`].
+///
+/// - `OUT` : `OUT` of [`MlConv1d`].
+/// - `OUT_CH` : `OUT_CH` of [`MlConv1d`].
+/// - `WINDOW` : `WINDOW` of [`MlConv1d`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlConv1dCache<
+    const OUT: usize,
+    const OUT_CH: usize,
+    const WINDOW: usize
+> {
+    window: MathVec<WINDOW>,
+    caches: Vec<MlCache<OUT_CH, WINDOW>>,
+    output: MathVec<OUT>
+}
+
+impl<
+    const OUT: usize,
+    const OUT_CH: usize,
+    const WINDOW: usize
+> MlConv1dCache<OUT, OUT_CH, WINDOW> {
+    /// Creates MlConv1dCache.
+    ///
+    /// - _Return_ : MlConv1dCache.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            window: MathVec::<WINDOW>::new(),
+            caches: vec![MlCache::<OUT_CH, WINDOW>::new(); OUT / OUT_CH.max(1)],
+            output: MathVec::<OUT>::new()
+        }
+    }
+
+    /// Calculates output error.
+    ///
+    /// See [`MlCache::calc_output_error()`] for details.
+    ///
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error(
+        &self,
+        train_out: &MathVec<OUT>,
+        output_error: &mut MathVec<OUT>
+    ) {
+        output_error.copy_from(&self.output);
+        *output_error -= train_out;
+    }
+
+    /// Calculates output error with loss function.
+    ///
+    /// - `loss` : Loss function.
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error_with_loss(
+        &self,
+        loss: &Loss,
+        train_out: &MathVec<OUT>,
+        output_error: &mut MathVec<OUT>
+    ) {
+        loss.calc_error(&self.output, train_out, output_error);
+    }
+
+    /// Gets slice of [`MlCache`] of kernel for each output frame.
+    ///
+    /// - _Return_ : Slice of [`MlCache`].
+    #[inline]
+    pub fn caches(&self) -> &[MlCache<OUT_CH, WINDOW>] {&self.caches}
+
+    /// Gets output.
+    ///
+    /// - _Return_ : Output.
+    #[inline]
+    pub fn output(&self) -> &MathVec<OUT> {&self.output}
+}
+
+/// Wrapper of [`Conv1d`] for machine learning.
+///
+/// See [`Conv1d`] for details.
+///
+/// - `OUT` : Dimension of output.
+/// - `IN` : Dimension of input.
+/// - `OUT_CH` : Number of output channels.
+/// - `IN_CH` : Number of input channels.
+/// - `KERNEL` : Kernel size.
+/// - `STRIDE` : Stride.
+/// - `WINDOW` : Dimension of a window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlConv1d<
+    const OUT: usize,
+    const IN: usize,
+    const OUT_CH: usize,
+    const IN_CH: usize,
+    const KERNEL: usize,
+    const STRIDE: usize,
+    const WINDOW: usize
+> {
+    kernel: MlLayer<OUT_CH, WINDOW>,
+
+    tmp_output_error: MathVec<OUT_CH>,
+    tmp_window_error: MathVec<WINDOW>
+}
+
+impl<
+    const OUT: usize,
+    const IN: usize,
+    const OUT_CH: usize,
+    const IN_CH: usize,
+    const KERNEL: usize,
+    const STRIDE: usize,
+    const WINDOW: usize
+> MlConv1d<OUT, IN, OUT_CH, IN_CH, KERNEL, STRIDE, WINDOW> {
+    /// Creates MlConv1d.
+    ///
+    /// - `conv` : Base [`Conv1d`].
+    /// - _Return_ : MlConv1d.
+    #[inline]
+    pub fn new(
+        conv: Conv1d<OUT, IN, OUT_CH, IN_CH, KERNEL, STRIDE, WINDOW>
+    ) -> Self {
+        Self {
+            kernel: MlLayer::<OUT_CH, WINDOW>::new(conv.kernel),
+
+            tmp_output_error: MathVec::<OUT_CH>::new(),
+            tmp_window_error: MathVec::<WINDOW>::new()
+        }
+    }
+
+    /// Drops base [`Conv1d`].
+    ///
+    /// - _Return_ : [`Conv1d`].
+    #[inline]
+    pub fn drop(
+        self
+    ) -> Conv1d<OUT, IN, OUT_CH, IN_CH, KERNEL, STRIDE, WINDOW> {
+        Conv1d::<OUT, IN, OUT_CH, IN_CH, KERNEL, STRIDE, WINDOW>::with_kernel(
+            self.kernel.drop()
+        )
+    }
+
+    /// Gets immutable kernel.
+    ///
+    /// - _Return_ : Kernel.
+    #[inline]
+    pub fn kernel(&self) -> &MlLayer<OUT_CH, WINDOW> {&self.kernel}
+
+    /// Gets mutable kernel.
+    ///
+    /// - _Return_ : Kernel.
+    #[inline]
+    pub fn kernel_mut(&mut self) -> &mut MlLayer<OUT_CH, WINDOW> {
+        &mut self.kernel
+    }
+
+    /// Clears internal data for study.
+    #[inline]
+    pub fn clear_study_data(&mut self) {
+        self.kernel.clear_study_data();
+    }
+
+    /// Sets [`Optimizer`].
+    ///
+    /// See [`MlLayer::set_optimizer()`] for details.
+    ///
+    /// - `optimizer` : Optimizer.
+    #[inline]
+    pub fn set_optimizer(&mut self, optimizer: Optimizer) {
+        self.kernel.set_optimizer(optimizer);
+    }
+
+    /// Writes information on [`MlConv1dCache`] for [`study()`](Self::study()).
+    ///
+    /// - `input` : Input.
+    /// - `cache` : Cache.
+    pub fn ready(
+        &self,
+        input: &MathVec<IN>,
+        cache: &mut MlConv1dCache<OUT, OUT_CH, WINDOW>
+    ) {
+        let out_len =
+            Conv1d::<OUT, IN, OUT_CH, IN_CH, KERNEL, STRIDE, WINDOW>::OUT_LEN;
+
+        if cache.caches.len() < out_len {
+            cache.caches.resize(out_len, MlCache::<OUT_CH, WINDOW>::new());
+        }
+
+        cache.caches.iter_mut().zip(
+            cache.output.chunks_exact_mut(OUT_CH)
+        ).enumerate().take(out_len).for_each(|(t, (layer_cache, output))| {
+            cache.window.copy_from_slice(
+                Conv1d::<OUT, IN, OUT_CH, IN_CH, KERNEL, STRIDE, WINDOW>
+                    ::window(input, t)
+            );
+
+            self.kernel.ready(&cache.window, None, layer_cache);
+
+            output.copy_from_slice(&layer_cache.output);
+        });
+    }
+
+    /// Accumulates gradient.
+    ///
+    /// - `output_error` : Backpropagated output error.
+    /// - `cache` : Cache.
+    /// - `input_error` : Error for input.
+    pub fn study(
+        &mut self,
+        output_error: &MathVec<OUT>,
+        cache: &MlConv1dCache<OUT, OUT_CH, WINDOW>,
+        input_error: &mut MathVec<IN>
+    ) {
+        let out_len =
+            Conv1d::<OUT, IN, OUT_CH, IN_CH, KERNEL, STRIDE, WINDOW>::OUT_LEN;
+
+        input_error.clear();
+
+        cache.caches.iter().zip(
+            output_error.chunks_exact(OUT_CH)
+        ).enumerate().take(out_len).for_each(
+            |(t, (layer_cache, output_error_one))| {
+                self.tmp_output_error.copy_from_slice(output_error_one);
+
+                self.kernel.study(
+                    &self.tmp_output_error,
+                    None,
+                    layer_cache,
+                    &mut self.tmp_window_error,
+                    None
+                );
+
+                let start = t * STRIDE * IN_CH;
+
+                input_error[start..(start + WINDOW)].iter_mut().zip(
+                    self.tmp_window_error.iter()
+                ).for_each(|(input_e, window_e)| {*input_e += *window_e;});
+            }
+        );
+    }
+
+    /// Updates weights with learning rate from [`LrScheduler`], and advances step of scheduler.
+    ///
+    /// - `scheduler` : Scheduler of learning rate.
+    #[inline]
+    pub fn update_with_scheduler(&mut self, scheduler: &mut LrScheduler) {
+        self.update(scheduler.next_rate());
+    }
+
+    /// Updates weights.
+    ///
+    /// - `rate` : Learning rate.
+    #[inline]
+    pub fn update(&mut self, rate: f32) {
+        self.kernel.update(rate);
+    }
+}
+
+impl<
+    const OUT: usize,
+    const IN: usize,
+    const OUT_CH: usize,
+    const IN_CH: usize,
+    const KERNEL: usize,
+    const STRIDE: usize,
+    const WINDOW: usize
+> MlNetwork<OUT, IN>
+for MlConv1d<OUT, IN, OUT_CH, IN_CH, KERNEL, STRIDE, WINDOW> {
+    type Cache = MlConv1dCache<OUT, OUT_CH, WINDOW>;
+    type Network = Conv1d<OUT, IN, OUT_CH, IN_CH, KERNEL, STRIDE, WINDOW>;
+
+    #[inline]
+    fn drop(self) -> Self::Network {MlConv1d::drop(self)}
+
+    #[inline]
+    fn new_cache(&self) -> Self::Cache {
+        MlConv1dCache::<OUT, OUT_CH, WINDOW>::new()
+    }
+
+    #[inline]
+    fn cache_output(cache: &Self::Cache) -> &MathVec<OUT> {&cache.output}
+
+    #[inline]
+    fn ready(&self, input: &MathVec<IN>, cache: &mut Self::Cache) {
+        MlConv1d::ready(self, input, cache);
+    }
+
+    #[inline]
+    fn study(
+        &mut self,
+        output_error: &MathVec<OUT>,
+        cache: &Self::Cache,
+        input_error: &mut MathVec<IN>
+    ) {
+        MlConv1d::study(self, output_error, cache, input_error);
+    }
+
+    #[inline]
+    fn update(&mut self, rate: f32) {MlConv1d::update(self, rate);}
+
+    #[inline]
+    fn clear_study_data(&mut self) {MlConv1d::clear_study_data(self);}
+
+    #[inline]
+    fn set_optimizer(&mut self, optimizer: Optimizer) {
+        MlConv1d::set_optimizer(self, optimizer);
+    }
+
+    #[inline]
+    fn for_each_total_grad<F>(&self, mut f: F) where F: FnMut(&f32) {
+        self.kernel.for_each_grad(&mut f);
+    }
+
+    #[inline]
+    fn for_each_total_grad_mut<F>(
+        &mut self,
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.kernel.for_each_grad_mut(&mut f);
+    }
+}
+
+/// Kind of pooling for [`Pool1d`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pooling {
+    /// Max value in a window.
+    Max,
+
+    /// Average of a window.
+    Average
+}
+
+/// 1D pooling layer for signal data.
+///
+/// Each channel of a window of `SIZE` frames is reduced to one value, sliding by `STRIDE` frames. Layout of frames is the same as [`Conv1d`].
+///
+/// | Formula |
+/// |:-:|
+/// | `OUT_LEN = ((IN / CH) - SIZE) / STRIDE + 1` |
+///
+/// It has no weights.
+///
+/// - `OUT` : Dimension of output. It must be `OUT_LEN * CH`.
+/// - `IN` : Dimension of input. It must be divisible by `CH`.
+/// - `CH` : Number of channels.
+/// - `SIZE` : Window size (number of frames).
+/// - `STRIDE` : Stride (number of frames).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pool1d<
+    const OUT: usize,
+    const IN: usize,
+    const CH: usize,
+    const SIZE: usize,
+    const STRIDE: usize
+> {
+    pooling: Pooling
+}
+
+impl<
+    const OUT: usize,
+    const IN: usize,
+    const CH: usize,
+    const SIZE: usize,
+    const STRIDE: usize
+> Pool1d<OUT, IN, CH, SIZE, STRIDE> {
+    const OUT_LEN: usize = {
+        assert!(CH > 0, "CH must be more than 0");
+        assert!(SIZE > 0, "SIZE must be more than 0");
+        assert!(STRIDE > 0, "STRIDE must be more than 0");
+        assert!(IN.is_multiple_of(CH), "IN must be divisible by CH");
+        assert!(IN / CH >= SIZE, "IN / CH must be SIZE or more");
+
+        let out_len = (((IN / CH) - SIZE) / STRIDE) + 1;
+
+        assert!(OUT == out_len * CH, "OUT must be OUT_LEN * CH");
+
+        out_len
+    };
+
+    /// Creates Pool1d.
+    ///
+    /// - `pooling` : Kind of pooling.
+    /// - _Return_ : Pool1d.
+    #[inline]
+    pub fn new(pooling: Pooling) -> Self {
+        Self {
+            pooling: pooling
+        }
+    }
+
+    /// Gets kind of pooling.
+    ///
+    /// - _Return_ : Kind of pooling.
+    #[inline]
+    pub fn pooling(&self) -> Pooling {self.pooling}
+
+    /// Gets number of output frames.
+    ///
+    /// - _Return_ : `OUT_LEN`.
+    #[inline]
+    pub fn output_len(&self) -> usize {Self::OUT_LEN}
+
+    /// Calculates output.
+    ///
+    /// - `input` : Input.
+    /// - `output` : Buffer for output.
+    pub fn calc(&self, input: &MathVec<IN>, output: &mut MathVec<OUT>) {
+        self.pool(input, output, |_, _| {});
+    }
+
+    // `record` receives index of output and index of max input.
+    fn pool<F>(
+        &self,
+        input: &MathVec<IN>,
+        output: &mut MathVec<OUT>,
+        mut record: F
+    ) where F: FnMut(usize, usize) {
+        let size_inv = (SIZE as f32).recip();
+
+        output.iter_mut().enumerate().take(Self::OUT_LEN * CH).for_each(
+            |(i, output_one)| {
+                let (t, c) = (i / CH, i % CH);
+                let start = (t * STRIDE * CH) + c;
+
+                let indices = (start..(start + (SIZE * CH))).step_by(CH);
+
+                match self.pooling {
+                    Pooling::Max => {
+                        let (index, max) = indices.fold(
+                            (start, f32::NEG_INFINITY),
+                            |(index, max), j| if input[j] > max {
+                                (j, input[j])
+                            } else {
+                                (index, max)
+                            }
+                        );
+
+                        *output_one = max;
+                        record(i, index);
+                    },
+
+                    Pooling::Average => {
+                        *output_one =
+                            indices.map(|j| input[j]).sum::<f32>() * size_inv;
+                    }
+                }
+            }
+        );
+    }
+}
+
+impl<
+    const OUT: usize,
+    const IN: usize,
+    const CH: usize,
+    const SIZE: usize,
+    const STRIDE: usize
+> Network<OUT, IN> for Pool1d<OUT, IN, CH, SIZE, STRIDE> {
+    type Buffer = ();
+    type Ml = MlPool1d<OUT, IN, CH, SIZE, STRIDE>;
+
+    #[inline]
+    fn new_buffer(&self) -> Self::Buffer {}
+
+    #[inline]
+    fn calc(
+        &self,
+        input: &MathVec<IN>,
+        output: &mut MathVec<OUT>,
+        _buffer: &mut Self::Buffer
+    ) {
+        Pool1d::calc(self, input, output);
+    }
+
+    #[inline]
+    fn for_each_weight<F>(&self, _f: F) where F: FnMut(&f32) {}
+
+    #[inline]
+    fn for_each_weight_mut<F>(&mut self, _f: F) where F: FnMut(&mut f32) {}
+
+    #[inline]
+    fn into_ml(self) -> Self::Ml {
+        MlPool1d::<OUT, IN, CH, SIZE, STRIDE>::new(self)
+    }
+}
+
+/// Cache for [`MlPool1d`].
+///
+/// - `OUT` : `OUT` of [`MlPool1d`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlPool1dCache<const OUT: usize> {
+    indices: Vec<usize>,
+    output: MathVec<OUT>
+}
+
+impl<const OUT: usize> MlPool1dCache<OUT> {
+    /// Creates MlPool1dCache.
+    ///
+    /// - _Return_ : MlPool1dCache.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            indices: vec![0; OUT],
+            output: MathVec::<OUT>::new()
+        }
+    }
+
+    /// Gets output.
+    ///
+    /// - _Return_ : Output.
+    #[inline]
+    pub fn output(&self) -> &MathVec<OUT> {&self.output}
+}
+
+/// Wrapper of [`Pool1d`] for machine learning.
+///
+/// Max pooling passes error only to the max element of each window. Average pooling distributes error equally.
+///
+/// - `OUT` : Dimension of output.
+/// - `IN` : Dimension of input.
+/// - `CH` : Number of channels.
+/// - `SIZE` : Window size.
+/// - `STRIDE` : Stride.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlPool1d<
+    const OUT: usize,
+    const IN: usize,
+    const CH: usize,
+    const SIZE: usize,
+    const STRIDE: usize
+> {
+    pool: Pool1d<OUT, IN, CH, SIZE, STRIDE>
+}
+
+impl<
+    const OUT: usize,
+    const IN: usize,
+    const CH: usize,
+    const SIZE: usize,
+    const STRIDE: usize
+> MlPool1d<OUT, IN, CH, SIZE, STRIDE> {
+    /// Creates MlPool1d.
+    ///
+    /// - `pool` : Base [`Pool1d`].
+    /// - _Return_ : MlPool1d.
+    #[inline]
+    pub fn new(pool: Pool1d<OUT, IN, CH, SIZE, STRIDE>) -> Self {
+        Self {
+            pool: pool
+        }
+    }
+
+    /// Drops base [`Pool1d`].
+    ///
+    /// - _Return_ : [`Pool1d`].
+    #[inline]
+    pub fn drop(self) -> Pool1d<OUT, IN, CH, SIZE, STRIDE> {self.pool}
+
+    /// Writes information on [`MlPool1dCache`] for [`study()`](Self::study()).
+    ///
+    /// - `input` : Input.
+    /// - `cache` : Cache.
+    #[inline]
+    pub fn ready(&self, input: &MathVec<IN>, cache: &mut MlPool1dCache<OUT>) {
+        let indices = &mut cache.indices;
+
+        self.pool.pool(input, &mut cache.output, |i, index| {
+            indices[i] = index;
+        });
+    }
+
+    /// Calculates input error.
+    ///
+    /// - `output_error` : Backpropagated output error.
+    /// - `cache` : Cache.
+    /// - `input_error` : Error for input.
+    pub fn study(
+        &self,
+        output_error: &MathVec<OUT>,
+        cache: &MlPool1dCache<OUT>,
+        input_error: &mut MathVec<IN>
+    ) {
+        let out_len = Pool1d::<OUT, IN, CH, SIZE, STRIDE>::OUT_LEN;
+        let size_inv = (SIZE as f32).recip();
+
+        input_error.clear();
+
+        output_error.iter().enumerate().take(out_len * CH).for_each(
+            |(i, output_e)| match self.pool.pooling {
+                Pooling::Max => {
+                    input_error[cache.indices[i]] += *output_e;
+                },
+
+                Pooling::Average => {
+                    let (t, c) = (i / CH, i % CH);
+                    let start = (t * STRIDE * CH) + c;
+
+                    (start..(start + (SIZE * CH))).step_by(CH).for_each(
+                        |j| {input_error[j] += *output_e * size_inv;}
+                    );
+                }
+            }
+        );
+    }
+}
+
+impl<
+    const OUT: usize,
+    const IN: usize,
+    const CH: usize,
+    const SIZE: usize,
+    const STRIDE: usize
+> MlNetwork<OUT, IN> for MlPool1d<OUT, IN, CH, SIZE, STRIDE> {
+    type Cache = MlPool1dCache<OUT>;
+    type Network = Pool1d<OUT, IN, CH, SIZE, STRIDE>;
+
+    #[inline]
+    fn drop(self) -> Self::Network {MlPool1d::drop(self)}
+
+    #[inline]
+    fn new_cache(&self) -> Self::Cache {MlPool1dCache::<OUT>::new()}
+
+    #[inline]
+    fn cache_output(cache: &Self::Cache) -> &MathVec<OUT> {&cache.output}
+
+    #[inline]
+    fn ready(&self, input: &MathVec<IN>, cache: &mut Self::Cache) {
+        MlPool1d::ready(self, input, cache);
+    }
+
+    #[inline]
+    fn study(
+        &mut self,
+        output_error: &MathVec<OUT>,
+        cache: &Self::Cache,
+        input_error: &mut MathVec<IN>
+    ) {
+        MlPool1d::study(self, output_error, cache, input_error);
+    }
+
+    #[inline]
+    fn update(&mut self, _rate: f32) {}
+
+    #[inline]
+    fn clear_study_data(&mut self) {}
+
+    #[inline]
+    fn set_optimizer(&mut self, _optimizer: Optimizer) {}
+
+    #[inline]
+    fn for_each_total_grad<F>(&self, _f: F) where F: FnMut(&f32) {}
+
+    #[inline]
+    fn for_each_total_grad_mut<F>(&mut self, _f: F) where F: FnMut(&mut f32) {}
+}
+
+/// Lookup table from token ID to vector.
+///
+/// Unlike [`MathVec::load_u8_label()`] and [`MathVec::load_u32_label()`], each token has its own trainable vector.
+///
+/// Vectors are stored in a table of `VOCAB` rows and `DIM` columns. Row `id` is the vector of token `id`.
+///
+/// ```ignore
+/// use chobitlibs::chobit_ai::{
+///     MathVec,
+///     Activation,
+///     Embedding,
+///     ChobitEncoder
+/// };
+///
+/// let embedding = Embedding::<256, 16>::new();
+/// let mut encoder = ChobitEncoder::<8, 32, 16>::new(Activation::SoftSign);
+///
+/// let mut input = MathVec::<16>::new();
+///
+/// "Hello".bytes().for_each(|id| {
+///     embedding.calc(id as usize, &mut input);
+///     encoder.input_next(&input);
+/// });
+/// ```
+///
+/// - `VOCAB` : Number of tokens.
+/// - `DIM` : Dimension of vector.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Embedding<const VOCAB: usize, const DIM: usize> {
+    table: Vec<f32>
+}
+
+impl<const VOCAB: usize, const DIM: usize> Embedding<VOCAB, DIM> {
+    /// Creates Embedding. All vectors are zero.
+    ///
+    /// - _Return_ : Embedding.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            table: vec![0.0; VOCAB * DIM]
+        }
+    }
+
+    /// Gets immutable table. Row `id` is `table[(id * DIM)..((id + 1) * DIM)]`.
+    ///
+    /// - _Return_ : Table.
+    #[inline]
+    pub fn table(&self) -> &[f32] {&self.table}
+
+    /// Gets mutable table. Row `id` is `table[(id * DIM)..((id + 1) * DIM)]`.
+    ///
+    /// - _Return_ : Table.
+    #[inline]
+    pub fn table_mut(&mut self) -> &mut [f32] {&mut self.table}
+
+    /// Gets immutable vector of a token.
+    ///
+    /// - `id` : Token ID.
+    /// - _Return_ : Vector. If `id` is `VOCAB` or more, returns `None`.
+    #[inline]
+    pub fn row(&self, id: usize) -> Option<&[f32]> {
+        (id < VOCAB).then(|| &self.table[(id * DIM)..((id + 1) * DIM)])
+    }
+
+    /// Gets mutable vector of a token.
+    ///
+    /// - `id` : Token ID.
+    /// - _Return_ : Vector. If `id` is `VOCAB` or more, returns `None`.
+    #[inline]
+    pub fn row_mut(&mut self, id: usize) -> Option<&mut [f32]> {
+        (id < VOCAB).then(|| &mut self.table[(id * DIM)..((id + 1) * DIM)])
+    }
+
+    /// Initializes table with [`Initializer`].
+    ///
+    /// Table is initialized as a matrix of `VOCAB x DIM`.
+    ///
+    /// - `initializer` : Initializer.
+    /// - `rand` : Closure that returns uniform random number in `[0.0, 1.0)`.
+    #[inline]
+    pub fn init_weights<F>(&mut self, initializer: Initializer, mut rand: F)
+    where F: FnMut() -> f32 {
+        initializer.init_matrix(&mut self.table, VOCAB, DIM, &mut rand);
+    }
+
+    /// Accesses each immutable weight with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_weight<F>(&self, f: F) where F: FnMut(&f32) {
+        self.table.iter().for_each(f);
+    }
+
+    /// Accesses each mutable weight with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_weight_mut<F>(&mut self, f: F) where F: FnMut(&mut f32) {
+        self.table.iter_mut().for_each(f);
+    }
+
+    /// Calculates output.
+    ///
+    /// If `id` is `VOCAB` or more, output is zero vector.
+    ///
+    /// - `id` : Token ID.
+    /// - `output` : Buffer for output.
+    #[inline]
+    pub fn calc(&self, id: usize, output: &mut MathVec<DIM>) {
+        match self.row(id) {
+            Some(row) => output.copy_from_slice(row),
+            None => output.clear()
+        }
+    }
+
+    /// Writes weights into buffer as binary data.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save(&self, buffer: &mut Vec<u8>) {
+        write_header(buffer, ModelKind::Embedding, &[VOCAB, DIM]);
+
+        self.write_body(buffer);
+    }
+
+    /// Creates Embedding from binary data written by [`save()`](Self::save()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : Embedding.
+    pub fn load(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(
+            ModelKind::Embedding,
+            &[("VOCAB", VOCAB), ("DIM", DIM)]
+        )?;
+
+        let mut ret = Self::new();
+
+        ret.read_body(&mut reader)?;
+
+        Ok(ret)
+    }
+
+    #[inline]
+    fn write_body(&self, buffer: &mut Vec<u8>) {
+        self.table.iter().for_each(|val| {write_f32(buffer, *val);});
+    }
+
+    fn read_body(
+        &mut self,
+        reader: &mut ByteReader
+    ) -> Result<(), ChobitAiError> {
+        for val in self.table.iter_mut() {
+            *val = reader.read_f32()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Wrapper of [`Embedding`] for machine learning.
+///
+/// Gradient is accumulated only into rows of tokens given to [`study()`](Self::study()), and [`update()`](Self::update()) touches only those rows. Momenta of other rows are not decayed.
+///
+/// - `VOCAB` : Number of tokens.
+/// - `DIM` : Dimension of vector.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlEmbedding<const VOCAB: usize, const DIM: usize> {
+    embedding: Embedding<VOCAB, DIM>,
+
+    total_grad: Vec<f32>,
+    momentum_1: Vec<f32>,
+    momentum_2: Vec<f32>,
+    square_average: Vec<f32>,
+    step: u64,
+    optimizer: Optimizer,
+
+    used: Vec<bool>,
+    used_ids: Vec<usize>
+}
+
+impl<const VOCAB: usize, const DIM: usize> MlEmbedding<VOCAB, DIM> {
+    /// Creates MlEmbedding.
+    ///
+    /// - `embedding` : Base [`Embedding`].
+    /// - _Return_ : MlEmbedding.
+    #[inline]
+    pub fn new(embedding: Embedding<VOCAB, DIM>) -> Self {
+        Self {
+            embedding: embedding,
+
+            total_grad: vec![0.0; VOCAB * DIM],
+            momentum_1: vec![0.0; VOCAB * DIM],
+            momentum_2: vec![0.0; VOCAB],
+            square_average: vec![0.0; VOCAB * DIM],
+            step: 0,
+            optimizer: Optimizer::default(),
+
+            used: vec![false; VOCAB],
+            used_ids: Vec::<usize>::new()
+        }
+    }
+
+    /// Drops Base [`Embedding`].
+    ///
+    /// - _Return_ : [`Embedding`].
+    #[inline]
+    pub fn drop(self) -> Embedding<VOCAB, DIM> {self.embedding}
+
+    /// Gets immutable base [`Embedding`].
+    ///
+    /// - _Return_ : [`Embedding`].
+    #[inline]
+    pub fn embedding(&self) -> &Embedding<VOCAB, DIM> {&self.embedding}
+
+    /// Clears internal data for study.
+    #[inline]
+    pub fn clear_study_data(&mut self) {
+        self.total_grad.fill(0.0);
+        self.momentum_1.fill(0.0);
+        self.momentum_2.fill(0.0);
+        self.square_average.fill(0.0);
+        self.step = 0;
+
+        self.used.fill(false);
+        self.used_ids.clear();
+    }
+
+    /// Gets optimizer.
+    ///
+    /// - _Return_ : Optimizer.
+    #[inline]
+    pub fn optimizer(&self) -> &Optimizer {&self.optimizer}
+
+    /// Sets optimizer.
+    ///
+    /// Momenta of previous optimizer are not cleared. Call [`clear_study_data()`](Self::clear_study_data()) if you need.
+    ///
+    /// - `optimizer` : Optimizer.
+    #[inline]
+    pub fn set_optimizer(&mut self, optimizer: Optimizer) {
+        self.optimizer = optimizer;
+    }
+
+    /// Gets count of [`update()`](Self::update()) since creation or [`clear_study_data()`](Self::clear_study_data()).
+    ///
+    /// - _Return_ : Count of update.
+    #[inline]
+    pub fn step(&self) -> u64 {self.step}
+
+    /// Gets token IDs given to [`study()`](Self::study()) since last [`update()`](Self::update()).
+    ///
+    /// - _Return_ : Token IDs in order of first study.
+    #[inline]
+    pub fn used_ids(&self) -> &[usize] {&self.used_ids}
+
+    /// Writes full state for machine learning into buffer as binary data.
+    ///
+    /// The data contains weights, total gradient, momenta of optimizer and count of update, so machine learning can be resumed exactly by [`load_checkpoint()`](Self::load_checkpoint()).
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save_checkpoint(&self, buffer: &mut Vec<u8>) {
+        write_header(buffer, ModelKind::MlEmbedding, &[VOCAB, DIM]);
+
+        self.embedding.write_body(buffer);
+
+        [
+            &self.total_grad,
+            &self.momentum_1,
+            &self.momentum_2,
+            &self.square_average
+        ].iter().for_each(|vec| {
+            vec.iter().for_each(|val| {write_f32(buffer, *val);});
+        });
+
+        write_u64(buffer, self.step);
+        self.optimizer.write(buffer);
+
+        write_u32(buffer, self.used_ids.len() as u32);
+        self.used_ids.iter().for_each(|id| {write_u32(buffer, *id as u32);});
+    }
+
+    /// Creates MlEmbedding from binary data written by [`save_checkpoint()`](Self::save_checkpoint()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : MlEmbedding.
+    pub fn load_checkpoint(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(
+            ModelKind::MlEmbedding,
+            &[("VOCAB", VOCAB), ("DIM", DIM)]
+        )?;
+
+        let mut ret = Self::new(Embedding::<VOCAB, DIM>::new());
+
+        ret.embedding.read_body(&mut reader)?;
+
+        for vec in [
+            &mut ret.total_grad,
+            &mut ret.momentum_1,
+            &mut ret.momentum_2,
+            &mut ret.square_average
+        ] {
+            for val in vec.iter_mut() {
+                *val = reader.read_f32()?;
+            }
+        }
+
+        ret.step = reader.read_u64()?;
+        ret.optimizer = Optimizer::read(&mut reader)?;
+
+        let len = reader.read_u32()? as usize;
+
+        for _ in 0..len {
+            let id = reader.read_u32()? as usize;
+
+            if id >= VOCAB {
+                return Err(ChobitAiError::DimensionMismatch {
+                    name: "VOCAB",
+                    expected: VOCAB,
+                    found: id
+                });
+            }
+
+            ret.mark_used(id);
+        }
+
+        Ok(ret)
+    }
+
+    /// Gets immutable total gradient. Layout is the same as [`Embedding::table()`].
+    ///
+    /// Total gradient is increased every time to call [`study()`](Self::study()).
+    ///
+    /// - _Return_ : Total gradient.
+    #[inline]
+    pub fn total_grad(&self) -> &[f32] {&self.total_grad}
+
+    /// Gets mutable total gradient. Layout is the same as [`Embedding::table()`].
+    ///
+    /// Only rows of [`used_ids()`](Self::used_ids()) are used by [`update()`](Self::update()).
+    ///
+    /// - _Return_ : Total gradient.
+    #[inline]
+    pub fn total_grad_mut(&mut self) -> &mut [f32] {&mut self.total_grad}
+
+    /// Accesses each immutable total gradient with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_total_grad<F>(&self, f: F) where F: FnMut(&f32) {
+        self.total_grad.iter().for_each(f);
+    }
+
+    /// Accesses each mutable total gradient with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_total_grad_mut<F>(
+        &mut self,
+        f: F
+    ) where F: FnMut(&mut f32) {
+        self.total_grad.iter_mut().for_each(f);
+    }
+
+    /// Clips each total gradient into `[-limit, limit]`.
+    ///
+    /// - `limit` : Limit of absolute value. Negative value is treated as its absolute value. If NaN, nothing is clipped.
+    #[inline]
+    pub fn clip_grad_by_value(&mut self, limit: f32) {
+        clip_by_value(limit, |f| self.total_grad.iter_mut().for_each(f));
+    }
+
+    /// Scales total gradient so that L2 norm of it is `max_norm` or less.
+    ///
+    /// - `max_norm` : Max L2 norm. Negative value is treated as its absolute value.
+    /// - _Return_ : L2 norm before clipping. If it is not finite, nothing is scaled.
+    #[inline]
+    pub fn clip_grad_by_global_norm(&mut self, max_norm: f32) -> f32 {
+        clip_by_global_norm(
+            max_norm,
+            |f| self.total_grad.iter_mut().for_each(f)
+        )
+    }
+
+    /// Checks that total gradient and weights are finite.
+    ///
+    /// - _Return_ : [`ChobitAiError::NonFinite`] if non-finite number is found. Name of the layer is `"embedding"`.
+    pub fn check_finite(&self) -> Result<(), ChobitAiError> {
+        if !self.total_grad.iter().all(|val| val.is_finite()) {
+            return Err(ChobitAiError::NonFinite {
+                layer: "embedding",
+                part: "total_grad"
+            });
+        }
+
+        if !self.embedding.table.iter().all(|val| val.is_finite()) {
+            return Err(ChobitAiError::NonFinite {
+                layer: "embedding",
+                part: "weights"
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Adds gradient of L1 and L2 penalty of weights into total gradient.
+    ///
+    /// Penalty is added only to rows of [`used_ids()`](Self::used_ids()).
+    /// Call this after [`study()`](Self::study()) and before [`update()`](Self::update()).
+    ///
+    /// - `l1` : Coefficient of L1 penalty.
+    /// - `l2` : Coefficient of L2 penalty.
+    pub fn add_weight_penalty(&mut self, l1: f32, l2: f32) {
+        self.used_ids.iter().for_each(|id| {
+            let range = (*id * DIM)..((*id + 1) * DIM);
+
+            self.total_grad[range.clone()].iter_mut().zip(
+                self.embedding.table[range].iter()
+            ).for_each(|(grad, weight)| {
+                *grad += penalty_grad(*weight, l1, l2);
+            });
+        });
+    }
+
+    /// Accumulates gradient of a token.
+    ///
+    /// If `id` is `VOCAB` or more, nothing is done.
+    ///
+    /// - `id` : Token ID given to [`Embedding::calc()`].
+    /// - `output_error` : Backpropagated output error.
+    pub fn study(&mut self, id: usize, output_error: &MathVec<DIM>) {
+        if id >= VOCAB {return;}
+
+        self.mark_used(id);
+
+        self.total_grad[(id * DIM)..((id + 1) * DIM)].iter_mut().zip(
+            output_error.iter()
+        ).for_each(|(grad, error)| {*grad += *error;});
+    }
+
+    #[inline]
+    fn mark_used(&mut self, id: usize) {
+        if !self.used[id] {
+            self.used[id] = true;
+            self.used_ids.push(id);
+        }
+    }
+
+    /// Updates weights with learning rate from [`LrScheduler`], and advances step of scheduler.
+    ///
+    /// - `scheduler` : Scheduler of learning rate.
+    #[inline]
+    pub fn update_with_scheduler(&mut self, scheduler: &mut LrScheduler) {
+        self.update(scheduler.next_rate());
+    }
+
+    /// Updates rows of [`used_ids()`](Self::used_ids()).
+    ///
+    /// Each row is updated in the same way as [`MlLayer::update()`], with [`Optimizer`] of this. For [`Optimizer::Adam`], momentum 2 is shared by each row.
+    ///
+    /// - `rate` : Learning rate.
+    pub fn update(&mut self, rate: f32) {
+        let mut used_ids = core::mem::take(&mut self.used_ids);
+
+        used_ids.iter().for_each(|id| {
+            self.update_row(*id, rate);
+
+            self.total_grad[(*id * DIM)..((*id + 1) * DIM)].fill(0.0);
+            self.used[*id] = false;
+        });
+
+        used_ids.clear();
+        self.used_ids = used_ids;
+
+        self.step += 1;
+    }
+
+    fn update_row(&mut self, id: usize, rate: f32) {
+        let range = (id * DIM)..((id + 1) * DIM);
+
+        let grads = &self.total_grad[range.clone()];
+
+        let adam_rate = match self.optimizer {
+            Optimizer::Adam => next_adam_rate(
+                rate,
+                &mut self.momentum_2[id],
+                grads.iter().fold(0.0, |sum, g| sum + (*g * *g))
+            ),
+
+            _ => 0.0
+        };
+
+        self.optimizer.step(
+            rate,
+            adam_rate,
+            self.step,
+            OptimizerSlices {
+                weights: &mut self.embedding.table[range.clone()],
+                grads: grads,
+                momentum_1: &mut self.momentum_1[range.clone()],
+                square_average: &mut self.square_average[range]
+            }
+        );
+    }
+}
+
+const NORM_EPSILON: f32 = 1.0e-5;
+const NORM_MOMENTUM: f32 = 0.1;
+
+#[inline]
+fn new_norm_layer<const N: usize>() -> Layer<N, 1> {
+    let mut ret = Layer::<N, 1>::new(Activation::Linear, false);
+
+    ret.weights.input_weights_mut().as_flattened_mut().fill(1.0);
+
+    ret
+}
+
+#[inline]
+fn scale_and_shift<const N: usize>(
+    weights: &Weights<N, 1>,
+    normalized: &MathVec<N>,
+    output: &mut MathVec<N>
+) {
+    output.as_mut_array().iter_mut().zip(
+        normalized.as_array().iter()
+    ).zip(
+        weights.input_weights().iter().zip(weights.bias().iter())
+    ).for_each(|((output_one, x_hat), (gain, shift))| {
+        *output_one = (gain[0] * *x_hat) + *shift;
+    });
+}
+
+#[inline]
+fn accumulate_norm_grad<const N: usize>(
+    total_grad: &mut Weights<N, 1>,
+    output_error: &MathVec<N>,
+    normalized: &MathVec<N>
+) {
+    output_error.as_array().iter().zip(
+        normalized.as_array().iter()
+    ).zip(
+        total_grad.input_weights_mut().as_flattened_mut().iter_mut()
+    ).for_each(|((output_e, x_hat), grad)| {
+        *grad += *output_e * *x_hat;
+    });
+
+    total_grad.bias_mut().iter_mut().zip(
+        output_error.as_array().iter()
+    ).for_each(|(grad, output_e)| {
+        *grad += *output_e;
+    });
+}
+
+/// [Layer normalization](https://arxiv.org/abs/1607.06450)
+///
+/// Normalizes a vector by mean and variance of its own elements, then scales and shifts each element.
+///
+/// `y_i = γ_i * (x_i - μ) / sqrt(σ² + ε) + β_i`
+///
+/// The statistics don't depend on other data in a batch or on time step, so it can normalize outputs of [`Layer`] in a deep stack, or output of a sequence model at each step.
+///
+/// [`Lstm::with_layer_norm()`] creates LSTM which normalizes each of its layers and its state by LayerNorm.
+///
+/// - `N` : Dimension of input and output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerNorm<const N: usize> {
+    layer: Layer<N, 1>,
+    epsilon: f32
+}
+
+impl<const N: usize> LayerNorm<N> {
+    /// Creates LayerNorm.
+    ///
+    /// γ is 1, β is 0 and ε is `1.0e-5`.
+    ///
+    /// - _Return_ : LayerNorm.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            layer: new_norm_layer::<N>(),
+            epsilon: NORM_EPSILON
+        }
+    }
+
+    /// Gets weights.
+    ///
+    /// Bias is β and input weights are γ. (`input_weights()[i][0]` is γ of `i`.)
+    ///
+    /// - _Return_ : Weights.
+    #[inline]
+    pub fn weights(&self) -> &Weights<N, 1> {&self.layer.weights}
+
+    /// Gets mutable weights.
+    ///
+    /// - _Return_ : Weights.
+    #[inline]
+    pub fn weights_mut(&mut self) -> &mut Weights<N, 1> {
+        &mut self.layer.weights
+    }
+
+    /// Gets ε.
+    ///
+    /// - _Return_ : ε.
+    #[inline]
+    pub fn epsilon(&self) -> f32 {self.epsilon}
+
+    /// Gets mutable ε.
+    ///
+    /// - _Return_ : ε.
+    #[inline]
+    pub fn epsilon_mut(&mut self) -> &mut f32 {&mut self.epsilon}
+
+    /// Calculates output.
+    ///
+    /// - `input` : Input.
+    /// - `output` : Buffer for output.
+    #[inline]
+    pub fn calc(&self, input: &MathVec<N>, output: &mut MathVec<N>) {
+        Self::normalize(input, self.epsilon, output);
+
+        output.as_mut_array().iter_mut().zip(
+            self.layer.weights.input_weights().iter().zip(
+                self.layer.weights.bias().iter()
+            )
+        ).for_each(|(output_one, (gain, shift))| {
+            *output_one = (gain[0] * *output_one) + *shift;
+        });
+    }
+
+    // Same as calc(), but input is overwritten by output.
+    #[inline]
+    fn calc_in_place(&self, values: &mut MathVec<N>) {
+        let (mean, inv_std) = Self::statistics(values, self.epsilon);
+
+        values.as_mut_array().iter_mut().zip(
+            self.layer.weights.input_weights().iter().zip(
+                self.layer.weights.bias().iter()
+            )
+        ).for_each(|(value, (gain, shift))| {
+            *value = (gain[0] * ((*value - mean) * inv_std)) + *shift;
+        });
+    }
+
+    // Mean and 1 / sqrt(σ² + ε).
+    #[inline]
+    fn statistics(input: &MathVec<N>, epsilon: f32) -> (f32, f32) {
+        let len = N.max(1) as f32;
+
+        let mean = input.as_array().iter().sum::<f32>() / len;
+
+        let variance = input.as_array().iter().map(
+            |x| (*x - mean) * (*x - mean)
+        ).sum::<f32>() / len;
+
+        (mean, 1.0 / accurate_sqrt(variance + epsilon))
+    }
+
+    fn normalize(
+        input: &MathVec<N>,
+        epsilon: f32,
+        normalized: &mut MathVec<N>
+    ) -> f32 {
+        let (mean, inv_std) = Self::statistics(input, epsilon);
+
+        normalized.as_mut_array().iter_mut().zip(
+            input.as_array().iter()
+        ).for_each(|(x_hat, x)| {
+            *x_hat = (*x - mean) * inv_std;
+        });
+
+        inv_std
+    }
+
+    fn write_descriptor(&self, buffer: &mut Vec<u8>) {
+        write_f32(buffer, self.epsilon);
+    }
+
+    fn read_descriptor(reader: &mut ByteReader) -> Result<Self, ChobitAiError> {
+        let mut ret = Self::new();
+
+        ret.epsilon = reader.read_f32()?;
+
+        Ok(ret)
+    }
+
+    // Flag of existence, and descriptor if it exists.
+    fn write_optional_descriptor(norm: Option<&Self>, buffer: &mut Vec<u8>) {
+        write_u8(buffer, norm.is_some() as u8);
+
+        if let Some(norm) = norm {
+            norm.write_descriptor(buffer);
+        }
+    }
+
+    fn read_optional_descriptor(
+        reader: &mut ByteReader
+    ) -> Result<Option<Self>, ChobitAiError> {
+        if reader.read_u8()? != 0 {
+            Ok(Some(Self::read_descriptor(reader)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn write_body(&self, buffer: &mut Vec<u8>) {
+        self.layer.weights.write_body(buffer);
+    }
+
+    fn read_body(
+        &mut self,
+        reader: &mut ByteReader
+    ) -> Result<(), ChobitAiError> {
+        self.layer.weights.read_body(reader)
+    }
+}
+
+/// Cache for [`MlLayerNorm`].
+///
+/// - `N` : `N` of [`MlLayerNorm`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlLayerNormCache<const N: usize> {
+    normalized: MathVec<N>,
+    inv_std: f32,
+
+    output: MathVec<N>
+}
+
+impl<const N: usize> MlLayerNormCache<N> {
+    /// Creates MlLayerNormCache.
+    ///
+    /// - _Return_ : MlLayerNormCache.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            normalized: MathVec::<N>::new(),
+            inv_std: 0.0,
+
+            output: MathVec::<N>::new()
+        }
+    }
+
+    /// Calculates output error.
+    ///
+    /// See [`MlCache::calc_output_error()`] for details.
+    ///
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error(
+        &self,
+        train_out: &MathVec<N>,
+        output_error: &mut MathVec<N>
+    ) {
+        output_error.copy_from(&self.output);
+        *output_error -= train_out;
+    }
+
+    /// Calculates output error with loss function.
+    ///
+    /// - `loss` : Loss function.
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error_with_loss(
+        &self,
+        loss: &Loss,
+        train_out: &MathVec<N>,
+        output_error: &mut MathVec<N>
+    ) {
+        loss.calc_error(&self.output, train_out, output_error);
+    }
+
+    /// Gets normalized input. (Input before scale and shift.)
+    ///
+    /// - _Return_ : Normalized input.
+    #[inline]
+    pub fn normalized(&self) -> &MathVec<N> {&self.normalized}
+
+    /// Gets `1 / sqrt(σ² + ε)` of input.
+    ///
+    /// - _Return_ : Inverse of standard deviation.
+    #[inline]
+    pub fn inv_std(&self) -> f32 {self.inv_std}
+
+    /// Gets output.
+    ///
+    /// - _Return_ : Output.
+    #[inline]
+    pub fn output(&self) -> &MathVec<N> {&self.output}
+}
+
+/// Wrapper of [`LayerNorm`] for machine learning.
+///
+/// γ and β are updated by [`Optimizer`] in the same way as weights of [`MlLayer`].
+///
+/// - `N` : Dimension of input and output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlLayerNorm<const N: usize> {
+    layer: MlLayer<N, 1>,
+    epsilon: f32,
+
+    tmp_error: MathVec<N>
+}
+
+impl<const N: usize> MlLayerNorm<N> {
+    /// Creates MlLayerNorm.
+    ///
+    /// - `layer_norm` : Base [`LayerNorm`].
+    /// - _Return_ : MlLayerNorm.
+    #[inline]
+    pub fn new(layer_norm: LayerNorm<N>) -> Self {
+        let LayerNorm::<N> {layer, epsilon} = layer_norm;
+
+        Self {
+            layer: MlLayer::<N, 1>::new(layer),
+            epsilon: epsilon,
+
+            tmp_error: MathVec::<N>::new()
+        }
+    }
+
+    /// Drops base [`LayerNorm`].
+    ///
+    /// - _Return_ : [`LayerNorm`].
+    #[inline]
+    pub fn drop(self) -> LayerNorm<N> {
+        LayerNorm::<N> {
+            layer: self.layer.drop(),
+            epsilon: self.epsilon
+        }
+    }
+
+    /// Clears internal data for study.
+    #[inline]
+    pub fn clear_study_data(&mut self) {self.layer.clear_study_data();}
+
+    /// Sets [`Optimizer`].
+    ///
+    /// See [`MlLayer::set_optimizer()`] for details.
+    ///
+    /// - `optimizer` : Optimizer.
+    #[inline]
+    pub fn set_optimizer(&mut self, optimizer: Optimizer) {
+        self.layer.set_optimizer(optimizer);
+    }
+
+    /// Gets total gradient. (Bias is of β and input weights are of γ.)
+    ///
+    /// - _Return_ : Total gradient.
+    #[inline]
+    pub fn total_grad(&self) -> &Weights<N, 1> {self.layer.total_grad()}
+
+    /// Gets mutable total gradient.
+    ///
+    /// - _Return_ : Total gradient.
+    #[inline]
+    pub fn total_grad_mut(&mut self) -> &mut Weights<N, 1> {
+        self.layer.total_grad_mut()
+    }
+
+    /// Writes information on [`MlLayerNormCache`] for [`study()`](Self::study()).
+    ///
+    /// - `input` : Input.
+    /// - `cache` : Cache.
+    #[inline]
+    pub fn ready(&self, input: &MathVec<N>, cache: &mut MlLayerNormCache<N>) {
+        cache.inv_std = LayerNorm::<N>::normalize(
+            input,
+            self.epsilon,
+            &mut cache.normalized
+        );
+
+        scale_and_shift(
+            &self.layer.layer.weights,
+            &cache.normalized,
+            &mut cache.output
+        );
+    }
+
+    /// Accumulates gradient.
+    ///
+    /// | Formula |
+    /// |:-:|
+    /// | `dx_i = (dx̂_i - mean(dx̂) - x̂_i * mean(dx̂ ⊙ x̂)) / sqrt(σ² + ε)` |
+    ///
+    /// - `output_error` : Backpropagated output error.
+    /// - `cache` : Cache generated by [`ready()`](Self::ready()).
+    /// - `input_error` : Error for input.
+    #[inline]
+    pub fn study(
+        &mut self,
+        output_error: &MathVec<N>,
+        cache: &MlLayerNormCache<N>,
+        input_error: &mut MathVec<N>
+    ) {
+        input_error.copy_from(output_error);
+
+        self.study_in_place(input_error, cache);
+    }
+
+    // Same as study(), but output error is overwritten by input error.
+    fn study_in_place(
+        &mut self,
+        error: &mut MathVec<N>,
+        cache: &MlLayerNormCache<N>
+    ) {
+        accumulate_norm_grad(
+            self.layer.total_grad_mut(),
+            error,
+            &cache.normalized
+        );
+
+        // error of normalized input.
+        self.tmp_error.as_mut_array().iter_mut().zip(
+            error.as_array().iter()
+        ).zip(
+            self.layer.layer.weights.input_weights().iter()
+        ).for_each(|((tmp_e, output_e), gain)| {
+            *tmp_e = *output_e * gain[0];
+        });
+
+        let len = N.max(1) as f32;
+
+        let mean_error = self.tmp_error.as_array().iter().sum::<f32>() / len;
+
+        let mean_error_x_hat = self.tmp_error.as_array().iter().zip(
+            cache.normalized.as_array().iter()
+        ).map(|(tmp_e, x_hat)| *tmp_e * *x_hat).sum::<f32>() / len;
+
+        error.as_mut_array().iter_mut().zip(
+            self.tmp_error.as_array().iter()
+        ).zip(
+            cache.normalized.as_array().iter()
+        ).for_each(|((input_e, tmp_e), x_hat)| {
+            *input_e = cache.inv_std
+                * (*tmp_e - mean_error - (*x_hat * mean_error_x_hat));
+        });
+    }
+
+    /// Updates γ and β with learning rate from [`LrScheduler`], and advances step of scheduler.
+    ///
+    /// - `scheduler` : Scheduler of learning rate.
+    #[inline]
+    pub fn update_with_scheduler(&mut self, scheduler: &mut LrScheduler) {
+        self.update(scheduler.next_rate());
+    }
+
+    /// Updates γ and β.
+    ///
+    /// - `rate` : Learning rate.
+    #[inline]
+    pub fn update(&mut self, rate: f32) {self.layer.update(rate);}
+
+    fn write_state(&self, buffer: &mut Vec<u8>) {
+        write_f32(buffer, self.epsilon);
+
+        self.layer.write_state(buffer);
+    }
+
+    fn read_state(reader: &mut ByteReader) -> Result<Self, ChobitAiError> {
+        let epsilon = reader.read_f32()?;
+        let layer = MlLayer::<N, 1>::read_state(reader)?;
+
+        Ok(Self {
+            layer: layer,
+            epsilon: epsilon,
+            tmp_error: MathVec::<N>::new()
+        })
+    }
+}
+
+impl<const N: usize> Network<N, N> for LayerNorm<N> {
+    type Buffer = ();
+    type Ml = MlLayerNorm<N>;
+
+    #[inline]
+    fn new_buffer(&self) -> Self::Buffer {}
+
+    #[inline]
+    fn calc(
+        &self,
+        input: &MathVec<N>,
+        output: &mut MathVec<N>,
+        _buffer: &mut Self::Buffer
+    ) {
+        LayerNorm::calc(self, input, output);
+    }
+
+    #[inline]
+    fn for_each_weight<F>(&self, f: F) where F: FnMut(&f32) {
+        self.layer.weights.iter().for_each(f);
+    }
+
+    #[inline]
+    fn for_each_weight_mut<F>(&mut self, f: F) where F: FnMut(&mut f32) {
+        self.layer.weights.iter_mut().for_each(f);
+    }
+
+    #[inline]
+    fn into_ml(self) -> Self::Ml {MlLayerNorm::<N>::new(self)}
+}
+
+impl<const N: usize> MlNetwork<N, N> for MlLayerNorm<N> {
+    type Cache = MlLayerNormCache<N>;
+    type Network = LayerNorm<N>;
+
+    #[inline]
+    fn drop(self) -> Self::Network {MlLayerNorm::drop(self)}
+
+    #[inline]
+    fn new_cache(&self) -> Self::Cache {MlLayerNormCache::<N>::new()}
+
+    #[inline]
+    fn cache_output(cache: &Self::Cache) -> &MathVec<N> {&cache.output}
+
+    #[inline]
+    fn ready(&self, input: &MathVec<N>, cache: &mut Self::Cache) {
+        MlLayerNorm::ready(self, input, cache);
+    }
+
+    #[inline]
+    fn study(
+        &mut self,
+        output_error: &MathVec<N>,
+        cache: &Self::Cache,
+        input_error: &mut MathVec<N>
+    ) {
+        MlLayerNorm::study(self, output_error, cache, input_error);
+    }
+
+    #[inline]
+    fn update(&mut self, rate: f32) {MlLayerNorm::update(self, rate);}
+
+    #[inline]
+    fn clear_study_data(&mut self) {MlLayerNorm::clear_study_data(self);}
+
+    #[inline]
+    fn set_optimizer(&mut self, optimizer: Optimizer) {
+        MlLayerNorm::set_optimizer(self, optimizer);
+    }
+
+    #[inline]
+    fn for_each_total_grad<F>(&self, f: F) where F: FnMut(&f32) {
+        self.layer.total_grad().iter().for_each(f);
+    }
+
+    #[inline]
+    fn for_each_total_grad_mut<F>(&mut self, f: F) where F: FnMut(&mut f32) {
+        self.layer.total_grad_mut().iter_mut().for_each(f);
+    }
+}
+
+/// [Batch normalization](https://arxiv.org/abs/1502.03167)
+///
+/// In machine learning, [`MlBatchNorm`] normalizes each element by mean and variance over a batch, and accumulates running mean and running variance.
+/// [`calc()`](Self::calc()) normalizes by the running statistics, so output doesn't depend on other data.
+///
+/// `y_i = γ_i * (x_i - μ_i) / sqrt(σ_i² + ε) + β_i`
+///
+/// - `N` : Dimension of input and output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchNorm<const N: usize> {
+    layer: Layer<N, 1>,
+    epsilon: f32,
+    momentum: f32,
+
+    running_mean: MathVec<N>,
+    running_var: MathVec<N>
+}
+
+impl<const N: usize> BatchNorm<N> {
+    /// Creates BatchNorm.
+    ///
+    /// γ is 1, β is 0, ε is `1.0e-5` and momentum is `0.1`. Running mean is 0 and running variance is 1.
+    ///
+    /// - _Return_ : BatchNorm.
+    #[inline]
+    pub fn new() -> Self {
+        let mut running_var = MathVec::<N>::new();
+        running_var.as_mut_array().fill(1.0);
+
+        Self {
+            layer: new_norm_layer::<N>(),
+            epsilon: NORM_EPSILON,
+            momentum: NORM_MOMENTUM,
+
+            running_mean: MathVec::<N>::new(),
+            running_var: running_var
+        }
+    }
+
+    /// Gets weights.
+    ///
+    /// Bias is β and input weights are γ. (`input_weights()[i][0]` is γ of `i`.)
+    ///
+    /// - _Return_ : Weights.
+    #[inline]
+    pub fn weights(&self) -> &Weights<N, 1> {&self.layer.weights}
+
+    /// Gets mutable weights.
+    ///
+    /// - _Return_ : Weights.
+    #[inline]
+    pub fn weights_mut(&mut self) -> &mut Weights<N, 1> {
+        &mut self.layer.weights
+    }
+
+    /// Gets ε.
+    ///
+    /// - _Return_ : ε.
+    #[inline]
+    pub fn epsilon(&self) -> f32 {self.epsilon}
+
+    /// Gets mutable ε.
+    ///
+    /// - _Return_ : ε.
+    #[inline]
+    pub fn epsilon_mut(&mut self) -> &mut f32 {&mut self.epsilon}
+
+    /// Gets momentum of running statistics.
+    ///
+    /// `running = ((1 - momentum) * running) + (momentum * batch)`
+    ///
+    /// - _Return_ : Momentum.
+    #[inline]
+    pub fn momentum(&self) -> f32 {self.momentum}
+
+    /// Gets mutable momentum of running statistics.
+    ///
+    /// - _Return_ : Momentum.
+    #[inline]
+    pub fn momentum_mut(&mut self) -> &mut f32 {&mut self.momentum}
+
+    /// Gets running mean.
+    ///
+    /// - _Return_ : Running mean.
+    #[inline]
+    pub fn running_mean(&self) -> &MathVec<N> {&self.running_mean}
+
+    /// Gets mutable running mean.
+    ///
+    /// - _Return_ : Running mean.
+    #[inline]
+    pub fn running_mean_mut(&mut self) -> &mut MathVec<N> {
+        &mut self.running_mean
+    }
+
+    /// Gets running variance.
+    ///
+    /// - _Return_ : Running variance.
+    #[inline]
+    pub fn running_var(&self) -> &MathVec<N> {&self.running_var}
+
+    /// Gets mutable running variance.
+    ///
+    /// - _Return_ : Running variance.
+    #[inline]
+    pub fn running_var_mut(&mut self) -> &mut MathVec<N> {
+        &mut self.running_var
+    }
+
+    /// Calculates output with running statistics.
+    ///
+    /// - `input` : Input.
+    /// - `output` : Buffer for output.
+    pub fn calc(&self, input: &MathVec<N>, output: &mut MathVec<N>) {
+        output.as_mut_array().iter_mut().zip(
+            input.as_array().iter()
+        ).zip(
+            self.running_mean.as_array().iter().zip(
+                self.running_var.as_array().iter()
+            )
+        ).for_each(|((output_one, x), (mean, var))| {
+            *output_one = (*x - *mean) / accurate_sqrt(*var + self.epsilon);
+        });
+
+        output.as_mut_array().iter_mut().zip(
+            self.layer.weights.input_weights().iter().zip(
+                self.layer.weights.bias().iter()
+            )
+        ).for_each(|(output_one, (gain, shift))| {
+            *output_one = (gain[0] * *output_one) + *shift;
+        });
+    }
+}
+
+/// Cache for [`MlBatchNorm`].
+///
+/// It holds information on a whole batch.
+///
+/// - `N` : `N` of [`MlBatchNorm`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlBatchNormCache<const N: usize> {
+    normalized: Vec<MathVec<N>>,
+    output: Vec<MathVec<N>>,
+    len: usize,
+
+    mean: MathVec<N>,
+    inv_std: MathVec<N>
+}
+
+impl<const N: usize> MlBatchNormCache<N> {
+    /// Creates MlBatchNormCache.
+    ///
+    /// - `capacity` : Initial capacity of batch size.
+    /// - _Return_ : MlBatchNormCache.
+    #[inline]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            normalized: vec![MathVec::<N>::new(); capacity],
+            output: vec![MathVec::<N>::new(); capacity],
+            len: 0,
+
+            mean: MathVec::<N>::new(),
+            inv_std: MathVec::<N>::new()
+        }
+    }
+
+    /// Calculates output error.
+    ///
+    /// See [`MlCache::calc_output_error()`] for details.
+    ///
+    /// - `train_out` : Correct output of each data in the batch.
+    /// - `output_error` : Buffer for output error of each data in the batch.
+    #[inline]
+    pub fn calc_output_error(
+        &self,
+        train_out: &[MathVec<N>],
+        output_error: &mut [MathVec<N>]
+    ) {
+        train_out.iter().zip(
+            self.output()
+        ).zip(
+            output_error
+        ).for_each(|((train_out_one, output_one), output_error_one)| {
+            output_error_one.copy_from(output_one);
+            *output_error_one -= train_out_one;
+        });
+    }
+
+    /// Calculates output error with loss function.
+    ///
+    /// - `loss` : Loss function.
+    /// - `train_out` : Correct output of each data in the batch.
+    /// - `output_error` : Buffer for output error of each data in the batch.
+    #[inline]
+    pub fn calc_output_error_with_loss(
+        &self,
+        loss: &Loss,
+        train_out: &[MathVec<N>],
+        output_error: &mut [MathVec<N>]
+    ) {
+        train_out.iter().zip(
+            self.output()
+        ).zip(
+            output_error
+        ).for_each(|((train_out_one, output_one), output_error_one)| {
+            loss.calc_error(output_one, train_out_one, output_error_one);
+        });
+    }
+
+    /// Gets normalized input of each data. (Input before scale and shift.)
+    ///
+    /// - _Return_ : Normalized input.
+    #[inline]
+    pub fn normalized(&self) -> &[MathVec<N>] {&self.normalized[..self.len]}
+
+    /// Gets output of each data.
+    ///
+    /// - _Return_ : Output.
+    #[inline]
+    pub fn output(&self) -> &[MathVec<N>] {&self.output[..self.len]}
+
+    /// Gets mean of the batch.
+    ///
+    /// - _Return_ : Mean.
+    #[inline]
+    pub fn mean(&self) -> &MathVec<N> {&self.mean}
+
+    /// Gets `1 / sqrt(σ² + ε)` of the batch.
+    ///
+    /// - _Return_ : Inverse of standard deviation.
+    #[inline]
+    pub fn inv_std(&self) -> &MathVec<N> {&self.inv_std}
+}
+
+/// Wrapper of [`BatchNorm`] for machine learning.
+///
+/// γ and β are updated by [`Optimizer`] in the same way as weights of [`MlLayer`].
+///
+/// - `N` : Dimension of input and output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlBatchNorm<const N: usize> {
+    layer: MlLayer<N, 1>,
+    epsilon: f32,
+    momentum: f32,
+
+    running_mean: MathVec<N>,
+    running_var: MathVec<N>,
+
+    tmp_error: MathVec<N>,
+    tmp_sum_error: MathVec<N>,
+    tmp_sum_error_x_hat: MathVec<N>
+}
+
+impl<const N: usize> MlBatchNorm<N> {
+    /// Creates MlBatchNorm.
+    ///
+    /// - `batch_norm` : Base [`BatchNorm`].
+    /// - _Return_ : MlBatchNorm.
+    #[inline]
+    pub fn new(batch_norm: BatchNorm<N>) -> Self {
+        let BatchNorm::<N> {
+            layer,
+            epsilon,
+            momentum,
+            running_mean,
+            running_var
+        } = batch_norm;
+
+        Self {
+            layer: MlLayer::<N, 1>::new(layer),
+            epsilon: epsilon,
+            momentum: momentum,
+
+            running_mean: running_mean,
+            running_var: running_var,
+
+            tmp_error: MathVec::<N>::new(),
+            tmp_sum_error: MathVec::<N>::new(),
+            tmp_sum_error_x_hat: MathVec::<N>::new()
+        }
+    }
+
+    /// Drops base [`BatchNorm`].
+    ///
+    /// - _Return_ : [`BatchNorm`] with updated running statistics.
+    #[inline]
+    pub fn drop(self) -> BatchNorm<N> {
+        BatchNorm::<N> {
+            layer: self.layer.drop(),
+            epsilon: self.epsilon,
+            momentum: self.momentum,
+
+            running_mean: self.running_mean,
+            running_var: self.running_var
+        }
+    }
+
+    /// Clears internal data for study.
+    ///
+    /// Running statistics are not cleared.
+    #[inline]
+    pub fn clear_study_data(&mut self) {self.layer.clear_study_data();}
+
+    /// Sets [`Optimizer`].
+    ///
+    /// See [`MlLayer::set_optimizer()`] for details.
+    ///
+    /// - `optimizer` : Optimizer.
+    #[inline]
+    pub fn set_optimizer(&mut self, optimizer: Optimizer) {
+        self.layer.set_optimizer(optimizer);
+    }
+
+    /// Gets running mean.
+    ///
+    /// - _Return_ : Running mean.
+    #[inline]
+    pub fn running_mean(&self) -> &MathVec<N> {&self.running_mean}
+
+    /// Gets running variance.
+    ///
+    /// - _Return_ : Running variance.
+    #[inline]
+    pub fn running_var(&self) -> &MathVec<N> {&self.running_var}
+
+    /// Gets total gradient. (Bias is of β and input weights are of γ.)
+    ///
+    /// - _Return_ : Total gradient.
+    #[inline]
+    pub fn total_grad(&self) -> &Weights<N, 1> {self.layer.total_grad()}
+
+    /// Gets mutable total gradient.
+    ///
+    /// - _Return_ : Total gradient.
+    #[inline]
+    pub fn total_grad_mut(&mut self) -> &mut Weights<N, 1> {
+        self.layer.total_grad_mut()
+    }
+
+    /// Writes information on [`MlBatchNormCache`] for [`study()`](Self::study()), and updates running statistics.
+    ///
+    /// Running variance is updated with unbiased variance of the batch.
+    ///
+    /// - `train_in` : Input of each data in the batch.
+    /// - `cache` : Cache.
+    pub fn ready(
+        &mut self,
+        train_in: &[MathVec<N>],
+        cache: &mut MlBatchNormCache<N>
+    ) {
+        let len = train_in.len();
+
+        cache.len = len;
+        if cache.normalized.len() < len {
+            cache.normalized.resize(len, MathVec::<N>::new());
+            cache.output.resize(len, MathVec::<N>::new());
+        }
+
+        if len == 0 {
+            return;
+        }
+
+        // mean ----------
+        cache.mean.clear();
+        train_in.iter().for_each(|input| {cache.mean += input;});
+        cache.mean /= len as f32;
+
+        // variance ----------
+        self.tmp_error.clear();
+        train_in.iter().for_each(|input| {
+            self.tmp_error.as_mut_array().iter_mut().zip(
+                input.as_array().iter().zip(cache.mean.as_array().iter())
+            ).for_each(|(var, (x, mean))| {
+                *var += (*x - *mean) * (*x - *mean);
+            });
+        });
+
+        cache.inv_std.as_mut_array().iter_mut().zip(
+            self.tmp_error.as_array().iter()
+        ).for_each(|(inv_std, sum)| {
+            *inv_std =
+                1.0 / accurate_sqrt((*sum / (len as f32)) + self.epsilon);
+        });
+
+        // running statistics ----------
+        let unbiased = 1.0 / (len.max(2) - 1) as f32;
+
+        self.running_mean.as_mut_array().iter_mut().zip(
+            cache.mean.as_array().iter()
+        ).for_each(|(running, mean)| {
+            *running = ((1.0 - self.momentum) * *running)
+                + (self.momentum * *mean);
+        });
+
+        self.running_var.as_mut_array().iter_mut().zip(
+            self.tmp_error.as_array().iter()
+        ).for_each(|(running, sum)| {
+            *running = ((1.0 - self.momentum) * *running)
+                + (self.momentum * *sum * unbiased);
+        });
+
+        // output ----------
+        train_in.iter().zip(
+            cache.normalized.iter_mut().zip(cache.output.iter_mut())
+        ).for_each(|(input, (normalized, output))| {
+            normalized.as_mut_array().iter_mut().zip(
+                input.as_array().iter()
+            ).zip(
+                cache.mean.as_array().iter().zip(
+                    cache.inv_std.as_array().iter()
+                )
+            ).for_each(|((x_hat, x), (mean, inv_std))| {
+                *x_hat = (*x - *mean) * *inv_std;
+            });
+
+            scale_and_shift(&self.layer.layer.weights, normalized, output);
+        });
+    }
+
+    /// Accumulates gradient.
+    ///
+    /// | Formula |
+    /// |:-:|
+    /// | `dx_i = (dx̂_i - mean(dx̂) - x̂_i * mean(dx̂ ⊙ x̂)) / sqrt(σ² + ε)` (means over the batch) |
+    ///
+    /// - `output_error` : Backpropagated output error of each data in the batch.
+    /// - `cache` : Cache generated by [`ready()`](Self::ready()).
+    /// - `input_error` : Error for input of each data in the batch.
+    pub fn study(
+        &mut self,
+        output_error: &[MathVec<N>],
+        cache: &MlBatchNormCache<N>,
+        input_error: &mut [MathVec<N>]
+    ) {
+        let len = cache.len.max(1) as f32;
+
+        self.tmp_sum_error.clear();
+        self.tmp_sum_error_x_hat.clear();
+
+        output_error.iter().zip(
+            cache.normalized()
+        ).for_each(|(output_error_one, normalized)| {
+            accumulate_norm_grad(
+                self.layer.total_grad_mut(),
+                output_error_one,
+                normalized
+            );
+
+            Self::calc_normalized_error(
+                &self.layer.layer.weights,
+                output_error_one,
+                &mut self.tmp_error
+            );
+
+            self.tmp_sum_error += &self.tmp_error;
+
+            self.tmp_sum_error_x_hat.as_mut_array().iter_mut().zip(
+                self.tmp_error.as_array().iter().zip(
+                    normalized.as_array().iter()
+                )
+            ).for_each(|(sum, (tmp_e, x_hat))| {
+                *sum += *tmp_e * *x_hat;
+            });
+        });
+
+        output_error.iter().zip(
+            cache.normalized()
+        ).zip(
+            input_error.iter_mut()
+        ).for_each(|((output_error_one, normalized), input_error_one)| {
+            Self::calc_normalized_error(
+                &self.layer.layer.weights,
+                output_error_one,
+                &mut self.tmp_error
+            );
+
+            input_error_one.as_mut_array().iter_mut().zip(
+                self.tmp_error.as_array().iter().zip(
+                    normalized.as_array().iter()
+                )
+            ).zip(
+                cache.inv_std.as_array().iter().zip(
+                    self.tmp_sum_error.as_array().iter().zip(
+                        self.tmp_sum_error_x_hat.as_array().iter()
+                    )
+                )
+            ).for_each(|(
+                (input_e, (tmp_e, x_hat)),
+                (inv_std, (sum_e, sum_e_x_hat))
+            )| {
+                *input_e = *inv_std * (
+                    *tmp_e - (*sum_e / len) - (*x_hat * (*sum_e_x_hat / len))
+                );
+            });
+        });
+    }
+
+    #[inline]
+    fn calc_normalized_error(
+        weights: &Weights<N, 1>,
+        output_error: &MathVec<N>,
+        normalized_error: &mut MathVec<N>
+    ) {
+        normalized_error.as_mut_array().iter_mut().zip(
+            output_error.as_array().iter()
+        ).zip(
+            weights.input_weights().iter()
+        ).for_each(|((normalized_e, output_e), gain)| {
+            *normalized_e = *output_e * gain[0];
+        });
+    }
+
+    /// Updates γ and β with learning rate from [`LrScheduler`], and advances step of scheduler.
+    ///
+    /// - `scheduler` : Scheduler of learning rate.
+    #[inline]
+    pub fn update_with_scheduler(&mut self, scheduler: &mut LrScheduler) {
+        self.update(scheduler.next_rate());
+    }
+
+    /// Updates γ and β.
+    ///
+    /// - `rate` : Learning rate.
+    #[inline]
+    pub fn update(&mut self, rate: f32) {self.layer.update(rate);}
+}
+
+/// [Peephole LSTM](https://en.wikipedia.org/wiki/Long_short-term_memory#Peephole_Lstm)
+///
+/// | Formula |
+/// |:-:|
+/// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mrow> <msub> <mi>m</mi> <mi>i</mi> </msub> <mo stretchy="false">=</mo> <mi>tanh</mi> </mrow> <mrow> <mo fence="true" form="prefix" stretchy="true">(</mo> <mrow> <mrow> <mrow> <munder> <mo stretchy="false">∑</mo> <mi>j</mi> </munder> <msubsup> <mi>W</mi> <mi mathvariant="italic">ij</mi> <mi>m</mi> </msubsup> </mrow> <mrow> <msub> <mi>x</mi> <mi>j</mi> </msub> <mo stretchy="false">+</mo> <mrow> <munder> <mo stretchy="false">∑</mo> <mi>k</mi> </munder> <msubsup> <mi>U</mi> <mi mathvariant="italic">ik</mi> <mi>m</mi> </msubsup> </mrow> </mrow> <mrow> <mover accent="true"> <msub> <mi>s</mi> <mi>k</mi> </msub> <mo>¯ </mo> </mover> <mo stretchy="false">+</mo> <msubsup> <mi>b</mi> <mi>i</mi> <mi>m</mi> </msubsup> </mrow> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="true">)</mo> </mrow> </mrow> </semantics> </math> |
+/// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mrow> <msub> <mi>f</mi> <mi>i</mi> </msub> <mo stretchy="false">=</mo> <mi>&sigma;</mi> </mrow> <mrow> <mo fence="true" form="prefix" stretchy="true">(</mo> <mrow> <mrow> <mrow> <munder> <mo stretchy="false">∑</mo> <mi>j</mi> </munder> <msubsup> <mi>W</mi> <mi mathvariant="italic">ij</mi> <mi>f</mi> </msubsup> </mrow> <mrow> <msub> <mi>x</mi> <mi>j</mi> </msub> <mo stretchy="false">+</mo> <mrow> <munder> <mo stretchy="false">∑</mo> <mi>k</mi> </munder> <msubsup> <mi>U</mi> <mi mathvariant="italic">ik</mi> <mi>f</mi> </msubsup> </mrow> </mrow> <mrow> <mover accent="true"> <msub> <mi>s</mi> <mi>k</mi> </msub> <mo>¯ </mo> </mover> <mo stretchy="false">+</mo> <msubsup> <mi>b</mi> <mi>i</mi> <mi>f</mi> </msubsup> </mrow> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="true">)</mo> </mrow> </mrow> </semantics> </math> |
+/// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mrow> <msub> <mi>i</mi> <mi>i</mi> </msub> <mo stretchy="false">=</mo> <mi>&sigma;</mi> </mrow> <mrow> <mo fence="true" form="prefix" stretchy="true">(</mo> <mrow> <mrow> <mrow> <munder> <mo stretchy="false">∑</mo> <mi>j</mi> </munder> <msubsup> <mi>W</mi> <mi mathvariant="italic">ij</mi> <mi>i</mi> </msubsup> </mrow> <mrow> <msub> <mi>x</mi> <mi>j</mi> </msub> <mo stretchy="false">+</mo> <mrow> <munder> <mo stretchy="false">∑</mo> <mi>k</mi> </munder> <msubsup> <mi>U</mi> <mi mathvariant="italic">ik</mi> <mi>i</mi> </msubsup> </mrow> </mrow> <mrow> <mover accent="true"> <msub> <mi>s</mi> <mi>k</mi> </msub> <mo>¯ </mo> </mover> <mo stretchy="false">+</mo> <msubsup> <mi>b</mi> <mi>i</mi> <mi>i</mi> </msubsup> </mrow> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="true">)</mo> </mrow> </mrow> </semantics> </math> |
+/// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mrow> <msub> <mi>o</mi> <mi>i</mi> </msub> <mo stretchy="false">=</mo> <mi>&sigma;</mi> </mrow> <mrow> <mo fence="true" form="prefix" stretchy="true">(</mo> <mrow> <mrow> <mrow> <munder> <mo stretchy="false">∑</mo> <mi>j</mi> </munder> <msubsup> <mi>W</mi> <mi mathvariant="italic">ij</mi> <mi>o</mi> </msubsup> </mrow> <mrow> <msub> <mi>x</mi> <mi>j</mi> </msub> <mo stretchy="false">+</mo> <mrow> <munder> <mo stretchy="false">∑</mo> <mi>k</mi> </munder> <msubsup> <mi>U</mi> <mi mathvariant="italic">ik</mi> <mi>o</mi> </msubsup> </mrow> </mrow> <mrow> <mover accent="true"> <msub> <mi>s</mi> <mi>k</mi> </msub> <mo>¯ </mo> </mover> <mo stretchy="false">+</mo> <msubsup> <mi>b</mi> <mi>i</mi> <mi>o</mi> </msubsup> </mrow> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="true">)</mo> </mrow> </mrow> </semantics> </math> |
+/// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mrow> <msub> <mi>s</mi> <mi>i</mi> </msub> <mo stretchy="false">=</mo> <msub> <mi>f</mi> <mi>i</mi> </msub> </mrow> <mrow> <mrow> <mo fence="true" form="prefix" stretchy="true">(</mo> <mrow> <mrow> <mrow> <munder> <mo stretchy="false">∑</mo> <mi>k</mi> </munder> <msub> <mi>δ</mi> <mi mathvariant="italic">ik</mi> </msub> </mrow> <mover accent="true"> <msub> <mi>s</mi> <mi>k</mi> </msub> <mo>¯ </mo> </mover> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="true">)</mo> </mrow> <mo stretchy="false">+</mo> <msub> <mi>i</mi> <mi>i</mi> </msub> </mrow> <msub> <mi>m</mi> <mi>i</mi> </msub> </mrow> </semantics> </math> |
+/// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mrow> <msub> <mi>y</mi> <mi>i</mi> </msub> <mo stretchy="false">=</mo> <msub> <mi>o</mi> <mi>i</mi> </msub> </mrow> <mi>tanh</mi> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <msub> <mi>s</mi> <mi>i</mi> </msub> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> </mrow> </semantics> </math> |
+/// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mtable columnalign="left"> <mtr> <mtd> <mrow> <mi>i</mi> <mo stretchy="false">≝</mo> <mtext>Index of output.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>j</mi> <mo stretchy="false">≝</mo> <mtext>Index of input.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mrow> <mi>k</mi> <mo stretchy="false">≝</mo> <mtext>Index of state.</mtext> </mrow> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <mrow> <mtext>dim</mtext> <mspace width="0.5em"/> <mrow> <mi>k</mi> <mo stretchy="false">=</mo> <mtext>dim</mtext> </mrow> <mspace width="0.5em"/> <mi>i</mi> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msub> <mi>δ</mi> <mi mathvariant="italic">ik</mi> </msub> <mo stretchy="false">≝</mo> <mtext>Kronecker&apos;s delta</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mrow> <mi>tanh</mi> <mo stretchy="false">≝</mo> <mtext>Hyperbolic tangent.</mtext> </mrow> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <mrow> <mi>tanh</mi> <mo stretchy="false">→</mo> <mtext>soft sign</mtext> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mrow> <mi>&sigma;</mi> <mo stretchy="false">≝</mo> <mtext>Sigmoid function.</mtext> </mrow> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <mrow> <mi>&sigma;</mi> <mo stretchy="false">→</mo> <mrow> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <mrow> <mrow> <mo fence="true" form="prefix" stretchy="false">(</mo> <mrow> <mtext>soft sign</mtext> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> <mo stretchy="false">+</mo> <mn>1</mn> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> <mo stretchy="false">÷</mo> <mn>2</mn> </mrow> </mrow> </mrow> <mo fence="true" form="postfix" stretchy="false">)</mo> </mrow> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msub> <mi>x</mi> <mi>j</mi> </msub> <mo stretchy="false">≝</mo> <mtext>Input.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mover accent="true"> <msub> <mi>s</mi> <mi>k</mi> </msub> <mo>¯ </mo> </mover> <mo stretchy="false">≝</mo> <mtext>Previous state.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msub> <mi>m</mi> <mi>i</mi> </msub> <mo stretchy="false">≝</mo> <mtext>Output of main layer.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msubsup> <mi>W</mi> <mi mathvariant="italic">ij</mi> <mi>m</mi> </msubsup> <mo stretchy="false">≝</mo> <mtext>Weights of main layer for input.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msubsup> <mi>U</mi> <mi mathvariant="italic">ik</mi> <mi>m</mi> </msubsup> <mo stretchy="false">≝</mo> <mtext>Weights of main layer for state.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msubsup> <mi>b</mi> <mi>i</mi> <mi>m</mi> </msubsup> <mo stretchy="false">≝</mo> <mtext>Bias of main layer.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msub> <mi>f</mi> <mi>i</mi> </msub> <mo stretchy="false">≝</mo> <mtext>Output of forget gate.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msubsup> <mi>W</mi> <mi mathvariant="italic">ij</mi> <mi>f</mi> </msubsup> <mo stretchy="false">≝</mo> <mtext>Weights of forget gate for input.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msubsup> <mi>U</mi> <mi mathvariant="italic">ik</mi> <mi>f</mi> </msubsup> <mo stretchy="false">≝</mo> <mtext>Weights of forget gate for state.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msubsup> <mi>b</mi> <mi>i</mi> <mi>f</mi> </msubsup> <mo stretchy="false">≝</mo> <mtext>Bias of forget gate.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msub> <mi>i</mi> <mi>i</mi> </msub> <mo stretchy="false">≝</mo> <mtext>Output of input gate.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msubsup> <mi>W</mi> <mi mathvariant="italic">ij</mi> <mi>i</mi> </msubsup> <mo stretchy="false">≝</mo> <mtext>Weights of input gate for input.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msubsup> <mi>U</mi> <mi mathvariant="italic">ik</mi> <mi>i</mi> </msubsup> <mo stretchy="false">≝</mo> <mtext>Weights of input gate for state.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msubsup> <mi>b</mi> <mi>i</mi> <mi>i</mi> </msubsup> <mo stretchy="false">≝</mo> <mtext>Bias of input gate.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msub> <mi>o</mi> <mi>i</mi> </msub> <mo stretchy="false">≝</mo> <mtext>Output of output gate.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msubsup> <mi>W</mi> <mi mathvariant="italic">ij</mi> <mi>o</mi> </msubsup> <mo stretchy="false">≝</mo> <mtext>Weights of output gate for input.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msubsup> <mi>U</mi> <mi mathvariant="italic">ik</mi> <mi>o</mi> </msubsup> <mo stretchy="false">≝</mo> <mtext>Weights of output gate for state.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <msubsup> <mi>b</mi> <mi>i</mi> <mi>o</mi> </msubsup> <mo stretchy="false">≝</mo> <mtext>Bias of output gate.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mrow> <msub> <mi>y</mi> <mi>i</mi> </msub> <mo stretchy="false">≝</mo> <mtext>Output</mtext> </mrow> <mi>.</mi> </mrow> </mtd> </mtr> </mtable> </semantics> </math> |
+///
+/// Lstm created by [`with_layer_norm()`](Self::with_layer_norm()) has [`LayerNorm`] for each layer and for state. ([Ba et al.](https://arxiv.org/abs/1607.06450))
+/// Each layer normalizes `Σ_j W_ij x_j + Σ_k U_ik s̄_k + b_i` before its activation function, and output is `o_i tanh(LN(s)_i)`.
+/// State passed to the next step is not normalized.
+///
+/// - `OUT` : Dimension of output.
+/// - `IN` : Dimension of input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lstm<const OUT: usize, const IN: usize> {
+    main_layer: Layer<OUT, IN>,
+
+    f_gate: Layer<OUT, IN>,
+    i_gate: Layer<OUT, IN>,
+    o_gate: Layer<OUT, IN>,
+
+    tanh: Activation,
+
+    main_layer_norm: Option<LayerNorm<OUT>>,
+    f_gate_norm: Option<LayerNorm<OUT>>,
+    i_gate_norm: Option<LayerNorm<OUT>>,
+    o_gate_norm: Option<LayerNorm<OUT>>,
+    state_norm: Option<LayerNorm<OUT>>
+}
+
+// Calculates layer of Lstm, normalizing middle value if norm exists.
+#[inline]
+fn calc_lstm_layer<const OUT: usize, const IN: usize>(
+    layer: &Layer<OUT, IN>,
+    norm: Option<&LayerNorm<OUT>>,
+    input: &MathVec<IN>,
+    prev_state: &MathVec<OUT>,
+    output: &mut MathVec<OUT>
+) {
+    match norm {
+        Some(norm) => {
+            layer.weights.calc(input, Some(prev_state), output);
+            norm.calc_in_place(output);
+
+            output.iter_mut().for_each(
+                |val| {*val = layer.activation.activate(*val);}
+            );
+        },
+
+        None => {
+            layer.calc(input, Some(prev_state), output);
+        }
+    }
+}
+
+impl<const OUT: usize, const IN: usize> Lstm<OUT, IN> {
+    /// Creates Lstm.
+    ///
+    /// - _Return_ : Lstm.
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_activations(
+            Activation::SoftSign,
+            Activation::Sigmoid,
+            Activation::SoftSign
+        )
+    }
+
+    /// Creates Lstm with activation functions.
+    ///
+    /// [`new()`](Self::new()) is the same as `with_activations(Activation::SoftSign, Activation::Sigmoid, Activation::SoftSign)`.
+    ///
+    /// - `main` : Activation function of main layer.
+    /// - `gate` : Activation function of forget gate, input gate and output gate.
+    /// - `tanh` : Activation function applied to state before output gate.
+    /// - _Return_ : Lstm.
+    #[inline]
+    pub fn with_activations(
+        main: Activation,
+        gate: Activation,
+        tanh: Activation
+    ) -> Self {
+        Self {
+            main_layer: Layer::<OUT, IN>::new(main, true),
+
+            f_gate: Layer::<OUT, IN>::new(gate, true),
+            i_gate: Layer::<OUT, IN>::new(gate, true),
+            o_gate: Layer::<OUT, IN>::new(gate, true),
+
+            tanh: tanh,
+
+            main_layer_norm: None,
+            f_gate_norm: None,
+            i_gate_norm: None,
+            o_gate_norm: None,
+            state_norm: None
+        }
+    }
+
+    /// Creates Lstm with layer normalization.
+    ///
+    /// Activation functions are the same as [`new()`](Self::new()). See [`Lstm`] for where [`LayerNorm`] is applied.
+    ///
+    /// - _Return_ : Lstm.
+    #[inline]
+    pub fn with_layer_norm() -> Self {
+        let mut ret = Self::new();
+
+        ret.main_layer_norm = Some(LayerNorm::<OUT>::new());
+        ret.f_gate_norm = Some(LayerNorm::<OUT>::new());
+        ret.i_gate_norm = Some(LayerNorm::<OUT>::new());
+        ret.o_gate_norm = Some(LayerNorm::<OUT>::new());
+        ret.state_norm = Some(LayerNorm::<OUT>::new());
+
+        ret
+    }
+
+    /// Gets activation function applied to state before output gate.
+    ///
+    /// - _Return_ : Activation function.
+    #[inline]
+    pub fn tanh(&self) -> &Activation {&self.tanh}
+
+    /// Gets mutable activation function applied to state before output gate.
+    ///
+    /// - _Return_ : Activation function.
+    #[inline]
+    pub fn tanh_mut(&mut self) -> &mut Activation {&mut self.tanh}
+
+    /// Gets layer normalization of main layer.
+    ///
+    /// - _Return_ : Layer normalization if it exists.
+    #[inline]
+    pub fn main_layer_norm(&self) -> Option<&LayerNorm<OUT>> {
+        self.main_layer_norm.as_ref()
+    }
+
+    /// Gets mutable layer normalization of main layer.
+    ///
+    /// - _Return_ : Layer normalization if it exists.
+    #[inline]
+    pub fn main_layer_norm_mut(&mut self) -> Option<&mut LayerNorm<OUT>> {
+        self.main_layer_norm.as_mut()
+    }
+
+    /// Gets layer normalization of forget gate.
+    ///
+    /// - _Return_ : Layer normalization if it exists.
+    #[inline]
+    pub fn f_gate_norm(&self) -> Option<&LayerNorm<OUT>> {
+        self.f_gate_norm.as_ref()
+    }
+
+    /// Gets mutable layer normalization of forget gate.
+    ///
+    /// - _Return_ : Layer normalization if it exists.
+    #[inline]
+    pub fn f_gate_norm_mut(&mut self) -> Option<&mut LayerNorm<OUT>> {
+        self.f_gate_norm.as_mut()
+    }
+
+    /// Gets layer normalization of input gate.
+    ///
+    /// - _Return_ : Layer normalization if it exists.
+    #[inline]
+    pub fn i_gate_norm(&self) -> Option<&LayerNorm<OUT>> {
+        self.i_gate_norm.as_ref()
+    }
+
+    /// Gets mutable layer normalization of input gate.
+    ///
+    /// - _Return_ : Layer normalization if it exists.
+    #[inline]
+    pub fn i_gate_norm_mut(&mut self) -> Option<&mut LayerNorm<OUT>> {
+        self.i_gate_norm.as_mut()
+    }
+
+    /// Gets layer normalization of output gate.
+    ///
+    /// - _Return_ : Layer normalization if it exists.
+    #[inline]
+    pub fn o_gate_norm(&self) -> Option<&LayerNorm<OUT>> {
+        self.o_gate_norm.as_ref()
+    }
+
+    /// Gets mutable layer normalization of output gate.
+    ///
+    /// - _Return_ : Layer normalization if it exists.
+    #[inline]
+    pub fn o_gate_norm_mut(&mut self) -> Option<&mut LayerNorm<OUT>> {
+        self.o_gate_norm.as_mut()
+    }
+
+    /// Gets layer normalization of state before output gate.
+    ///
+    /// - _Return_ : Layer normalization if it exists.
+    #[inline]
+    pub fn state_norm(&self) -> Option<&LayerNorm<OUT>> {
+        self.state_norm.as_ref()
+    }
+
+    /// Gets mutable layer normalization of state before output gate.
+    ///
+    /// - _Return_ : Layer normalization if it exists.
+    #[inline]
+    pub fn state_norm_mut(&mut self) -> Option<&mut LayerNorm<OUT>> {
+        self.state_norm.as_mut()
+    }
+
+    // Layer normalizations in order of main layer, forget gate, input gate,
+    // output gate and state.
+    #[inline]
+    fn norms(&self) -> [&Option<LayerNorm<OUT>>; 5] {
+        [
+            &self.main_layer_norm,
+            &self.f_gate_norm,
+            &self.i_gate_norm,
+            &self.o_gate_norm,
+            &self.state_norm
+        ]
+    }
+
+    #[inline]
+    fn norms_mut(&mut self) -> [&mut Option<LayerNorm<OUT>>; 5] {
+        [
+            &mut self.main_layer_norm,
+            &mut self.f_gate_norm,
+            &mut self.i_gate_norm,
+            &mut self.o_gate_norm,
+            &mut self.state_norm
+        ]
+    }
+
+    /// Gets immutable main layer.
+    ///
+    /// - _Return_ : Main layer.
+    #[inline]
+    pub fn main_layer(&self) -> &Layer<OUT, IN> {&self.main_layer}
+
+    /// Gets mutable main layer.
+    ///
+    /// - _Return_ : Main layer.
+    #[inline]
+    pub fn main_layer_mut(&mut self) -> &mut Layer<OUT, IN> {
+        &mut self.main_layer
+    }
+
+    /// Gets immutable forget gate.
+    ///
+    /// - _Return_ : Forget gate.
+    #[inline]
+    pub fn f_gate(&self) -> &Layer<OUT, IN> {&self.f_gate}
+
+    /// Gets mutable forget gate.
+    ///
+    /// - _Return_ : Forget gate.
+    #[inline]
+    pub fn f_gate_mut(&mut self) -> &mut Layer<OUT, IN> {&mut self.f_gate}
+
+    /// Gets immutable input gate.
+    ///
+    /// - _Return_ : Input gate.
+    #[inline]
+    pub fn i_gate(&self) -> &Layer<OUT, IN> {&self.i_gate}
+
+    /// Gets mutable input gate.
+    ///
+    /// - _Return_ : Input gate.
+    #[inline]
+    pub fn i_gate_mut(&mut self) -> &mut Layer<OUT, IN> {&mut self.i_gate}
+
+    /// Gets immutable output gate.
+    ///
+    /// - _Return_ : Output gate.
+    #[inline]
+    pub fn o_gate(&self) -> &Layer<OUT, IN> {&self.o_gate}
+
+    /// Gets mutable output gate.
+    ///
+    /// - _Return_ : Output gate.
+    #[inline]
+    pub fn o_gate_mut(&mut self) -> &mut Layer<OUT, IN> {&mut self.o_gate}
+
+    /// Initializes weights of all layers with [`Initializer`].
+    ///
+    /// Bias of forget gate is set to `1.0`, so Lstm remembers state at the beginning of machine learning. Other biases are set to `0.0`.
+    ///
+    /// If layer normalization exists, γ is set to `1.0` and β is set to `0.0`, but β of forget gate is set to `1.0` instead of its bias. (Normalization removes the bias.)
+    ///
+    /// - `input_initializer` : Initializer for input weights.
+    /// - `state_initializer` : Initializer for state weights. [`Initializer::Orthogonal`] is usual.
+    /// - `rand` : Closure that returns uniform random number in `[0.0, 1.0)`.
+    pub fn init_weights<F>(
+        &mut self,
+        input_initializer: Initializer,
+        state_initializer: Initializer,
+        mut rand: F
+    ) where F: FnMut() -> f32 {
+        for layer in [
+            &mut self.main_layer,
+            &mut self.f_gate,
+            &mut self.i_gate,
+            &mut self.o_gate
+        ] {
+            input_initializer.init(&mut layer.weights, &mut rand);
+            state_initializer.init_state(&mut layer.weights, &mut rand);
+        }
+
+        self.f_gate.weights.bias_mut().fill(1.0);
+
+        self.norms_mut().into_iter().flatten().for_each(|norm| {
+            norm.layer = new_norm_layer::<OUT>();
+        });
+
+        if let Some(norm) = &mut self.f_gate_norm {
+            norm.layer.weights.bias_mut().fill(1.0);
+        }
+    }
+
+    /// Calculates only state.
+    ///
+    /// - `input` : Input.
+    /// - `prev_state` : Previous state.
+    /// - `next_state` : Buffer for next state.
+    /// - `tmpbuf` : Temporary buffer for this function to work.
+    pub fn calc_state(
+        &self,
+        input: &MathVec<IN>,
+        prev_state: &MathVec<OUT>,
+        next_state: &mut MathVec<OUT>,
+        tmpbuf: &mut MathVec<OUT>
+    ) {
+        // state = (f_gate * prev_state) + (i_gate * main_layer);
+        calc_lstm_layer(
+            &self.main_layer,
+            self.main_layer_norm.as_ref(),
+            input,
+            prev_state,
+            next_state
+        );
+        calc_lstm_layer(
+            &self.i_gate,
+            self.i_gate_norm.as_ref(),
+            input,
+            prev_state,
+            tmpbuf
+        );
+        next_state.pointwise_mul_assign(tmpbuf);
+
+        calc_lstm_layer(
+            &self.f_gate,
+            self.f_gate_norm.as_ref(),
+            input,
+            prev_state,
+            tmpbuf
+        );
+        tmpbuf.pointwise_mul_assign(prev_state);
+
+        *next_state += tmpbuf;
+    }
+
+    /// Calculates state and output.
+    ///
+    /// - `input` : Input.
+    /// - `prev_state` : Previous state.
+    /// - `output` : Buffer for output.
+    /// - `next_state` : Buffer for next state.
+    /// - `tmpbuf` : Temporary buffer for this function to work.
+    pub fn calc(
+        &self,
+        input: &MathVec<IN>,
+        prev_state: &MathVec<OUT>,
+        output: &mut MathVec<OUT>,
+        next_state: &mut MathVec<OUT>,
+        tmpbuf: &mut MathVec<OUT>
+    ) {
+        self.calc_state(input, prev_state, next_state, tmpbuf);
+
+        // output = o_gate * tanh(state)
+        calc_lstm_layer(
+            &self.o_gate,
+            self.o_gate_norm.as_ref(),
+            input,
+            prev_state,
+            output
+        );
+
+        tmpbuf.copy_from(next_state);
+
+        if let Some(norm) = &self.state_norm {
+            norm.calc_in_place(tmpbuf);
+        }
+
+        output.as_mut_array().iter_mut().zip(
+            tmpbuf.as_array().iter()
+        ).for_each(|(output_one, next_s)| {
+            *output_one *= self.tanh.activate(*next_s);
+        });
+    }
+
+    /// Accesses each immutable weight with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_weight<F>(&self, mut f: F) where F: FnMut(&f32) {
+        self.main_layer.for_each_weight(|val| {f(val)});
+        self.f_gate.for_each_weight(|val| {f(val)});
+        self.i_gate.for_each_weight(|val| {f(val)});
+        self.o_gate.for_each_weight(|val| {f(val)});
+
+        self.norms().into_iter().flatten().for_each(|norm| {
+            norm.layer.weights.iter().for_each(|val| {f(val)});
+        });
+    }
+
+    /// Accesses each mutable weight with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_weight_mut<F>(
+        &mut self,
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.main_layer.for_each_weight_mut(|val| {f(val)});
+        self.f_gate.for_each_weight_mut(|val| {f(val)});
+        self.i_gate.for_each_weight_mut(|val| {f(val)});
+        self.o_gate.for_each_weight_mut(|val| {f(val)});
+
+        self.norms_mut().into_iter().flatten().for_each(|norm| {
+            norm.layer.weights.iter_mut().for_each(|val| {f(val)});
+        });
+    }
+
+    /// Writes this LSTM into buffer as binary data.
+    ///
+    /// See [`ChobitAi::save()`] for details.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save(&self, buffer: &mut Vec<u8>) {
+        write_header(buffer, ModelKind::Lstm, &[OUT, IN]);
+
+        self.write_descriptor(buffer);
+        self.write_body(buffer);
+    }
+
+    /// Creates Lstm from binary data written by [`save()`](Self::save()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : Lstm.
+    pub fn load(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(ModelKind::Lstm, &[("OUT", OUT), ("IN", IN)])?;
+
+        let mut ret = Self::read_descriptor(&mut reader)?;
+        ret.read_body(&mut reader)?;
+
+        Ok(ret)
+    }
+
+    fn write_descriptor(&self, buffer: &mut Vec<u8>) {
+        self.main_layer.write_descriptor(buffer);
+        self.f_gate.write_descriptor(buffer);
+        self.i_gate.write_descriptor(buffer);
+        self.o_gate.write_descriptor(buffer);
+
+        self.tanh.write(buffer);
+
+        for norm in self.norms() {
+            LayerNorm::write_optional_descriptor(norm.as_ref(), buffer);
+        }
+    }
+
+    fn read_descriptor(reader: &mut ByteReader) -> Result<Self, ChobitAiError> {
+        let mut ret = Self {
+            main_layer: Layer::<OUT, IN>::read_descriptor(reader)?,
+
+            f_gate: Layer::<OUT, IN>::read_descriptor(reader)?,
+            i_gate: Layer::<OUT, IN>::read_descriptor(reader)?,
+            o_gate: Layer::<OUT, IN>::read_descriptor(reader)?,
+
+            tanh: Activation::read(reader)?,
+
+            main_layer_norm: None,
+            f_gate_norm: None,
+            i_gate_norm: None,
+            o_gate_norm: None,
+            state_norm: None
+        };
+
+        for norm in ret.norms_mut() {
+            *norm = LayerNorm::read_optional_descriptor(reader)?;
+        }
+
+        Ok(ret)
+    }
+
+    fn write_body(&self, buffer: &mut Vec<u8>) {
+        self.main_layer.weights.write_body(buffer);
+        self.f_gate.weights.write_body(buffer);
+        self.i_gate.weights.write_body(buffer);
+        self.o_gate.weights.write_body(buffer);
+
+        self.norms().into_iter().flatten().for_each(|norm| {
+            norm.write_body(buffer);
+        });
+    }
+
+    fn read_body(
+        &mut self,
+        reader: &mut ByteReader
+    ) -> Result<(), ChobitAiError> {
+        self.main_layer.weights.read_body(reader)?;
+        self.f_gate.weights.read_body(reader)?;
+        self.i_gate.weights.read_body(reader)?;
+        self.o_gate.weights.read_body(reader)?;
+
+        for norm in self.norms_mut().into_iter().flatten() {
+            norm.read_body(reader)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Cache for state error of [`MlLstm`].
+///
+/// - `OUT` : Output of [`MlLstm`].
+/// - `IN` : Input of [`MlLstm`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlLstmStateCache<const OUT: usize, const IN: usize> {
+    input: MathVec<IN>,
+    prev_state: MathVec<OUT>,
+
+    main_layer_cache: MlCache<OUT, IN>,
+
+    f_gate_cache: MlCache<OUT, IN>,
+    i_gate_cache: MlCache<OUT, IN>,
+
+    main_layer_norm_cache: MlLayerNormCache<OUT>,
+    f_gate_norm_cache: MlLayerNormCache<OUT>,
+    i_gate_norm_cache: MlLayerNormCache<OUT>,
+
+    state: MathVec<OUT>
+}
+
+impl<const OUT: usize, const IN: usize> MlLstmStateCache<OUT, IN> {
+    /// Creates MlLstmStateCache.
+    ///
+    /// - _Return_ : MlLstmStateCache.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            input: MathVec::<IN>::new(),
+            prev_state: MathVec::<OUT>::new(),
+
+            main_layer_cache: MlCache::<OUT, IN>::new(),
+            f_gate_cache: MlCache::<OUT, IN>::new(),
+            i_gate_cache: MlCache::<OUT, IN>::new(),
+
+            main_layer_norm_cache: MlLayerNormCache::<OUT>::new(),
+            f_gate_norm_cache: MlLayerNormCache::<OUT>::new(),
+            i_gate_norm_cache: MlLayerNormCache::<OUT>::new(),
+
+            state: MathVec::<OUT>::new()
+        }
+    }
+
+    /// Gets input.
+    ///
+    /// - _Return_ : Input.
+    #[inline]
+    pub fn input(&self) -> &MathVec<IN> {&self.input}
+
+    /// Gets previous state.
+    ///
+    /// - _Return_ : Previous state.
+    #[inline]
+    pub fn prev_state(&self) -> &MathVec<OUT> {&self.prev_state}
+
+    /// Gets cache of main layer.
+    ///
+    /// - _Return_ : Cache of main layer.
+    #[inline]
+    pub fn main_layer_cache(&self) -> &MlCache<OUT, IN> {
+        &self.main_layer_cache
+    }
+
+    /// Gets cache of forget gate.
+    ///
+    /// - _Return_ : Cache of forget gate.
+    #[inline]
+    pub fn f_gate_cache(&self) -> &MlCache<OUT, IN> {&self.f_gate_cache}
+
+    /// Gets cache of input gate.
+    ///
+    /// - _Return_ : Cache of input gate.
+    #[inline]
+    pub fn i_gate_cache(&self) -> &MlCache<OUT, IN> {&self.i_gate_cache}
+
+    /// Gets cache of layer normalization of main layer.
+    ///
+    /// It is not written if [`MlLstm`] has no layer normalization.
+    ///
+    /// - _Return_ : Cache of layer normalization of main layer.
+    #[inline]
+    pub fn main_layer_norm_cache(&self) -> &MlLayerNormCache<OUT> {
+        &self.main_layer_norm_cache
+    }
+
+    /// Gets cache of layer normalization of forget gate.
+    ///
+    /// It is not written if [`MlLstm`] has no layer normalization.
+    ///
+    /// - _Return_ : Cache of layer normalization of forget gate.
+    #[inline]
+    pub fn f_gate_norm_cache(&self) -> &MlLayerNormCache<OUT> {
+        &self.f_gate_norm_cache
+    }
+
+    /// Gets cache of layer normalization of input gate.
+    ///
+    /// It is not written if [`MlLstm`] has no layer normalization.
+    ///
+    /// - _Return_ : Cache of layer normalization of input gate.
+    #[inline]
+    pub fn i_gate_norm_cache(&self) -> &MlLayerNormCache<OUT> {
+        &self.i_gate_norm_cache
+    }
+
+    /// Gets state.
+    ///
+    /// - _Return_ : State.
+    #[inline]
+    pub fn state(&self) -> &MathVec<OUT> {&self.state}
+}
+
+/// Cache for output error of [`MlLstm`].
+///
+/// - `OUT` : Output of [`MlLstm`].
+/// - `IN` : Input of [`MlLstm`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlLstmOutputCache<const OUT: usize, const IN: usize> {
+    o_gate_cache: MlCache<OUT, IN>,
+
+    o_gate_norm_cache: MlLayerNormCache<OUT>,
+    state_norm_cache: MlLayerNormCache<OUT>,
+
+    tanh_s: MathVec<OUT>,
+    d_tanh_s: MathVec<OUT>,
+
+    output: MathVec<OUT>
+}
+
+impl<const OUT: usize, const IN: usize> MlLstmOutputCache<OUT, IN> {
+    /// Creates MlLstmOutputCache.
+    ///
+    /// - _Return_ : MlLstmOutputCache.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            o_gate_cache: MlCache::<OUT, IN>::new(),
+
+            o_gate_norm_cache: MlLayerNormCache::<OUT>::new(),
+            state_norm_cache: MlLayerNormCache::<OUT>::new(),
+
+            tanh_s: MathVec::<OUT>::new(),
+            d_tanh_s: MathVec::<OUT>::new(),
+
+            output: MathVec::<OUT>::new()
+        }
+    }
+
+    /// Calculates output error.
+    ///
+    /// | Formula |
+    /// |:-:|
+    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mi>e</mi> <mo stretchy="false">=</mo> <mrow> <mi>o</mi> <mo stretchy="false">−</mo> <mi>t</mi> </mrow> </mrow> </semantics> </math> |
+    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mtable columnalign="left"> <mtr> <mtd> <mrow> <mi>e</mi> <mo stretchy="false">≝</mo> <mtext>Error.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>o</mi> <mo stretchy="false">≝</mo> <mtext>Actual output.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>t</mi> <mo stretchy="false">≝</mo> <mtext>Correct output.</mtext> </mrow> </mtd> </mtr> </mtable> </semantics> </math> |
+    ///
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error(
+        &self,
+        train_out: &MathVec<OUT>,
+        output_error: &mut MathVec<OUT>
+    ) {
+        output_error.copy_from(&self.output);
+        *output_error -= train_out;
+    }
+
+    /// Calculates output error with loss function.
+    ///
+    /// - `loss` : Loss function.
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error_with_loss(
+        &self,
+        loss: &Loss,
+        train_out: &MathVec<OUT>,
+        output_error: &mut MathVec<OUT>
+    ) {
+        loss.calc_error(&self.output, train_out, output_error);
+    }
+
+    /// Gets cache of output gate.
+    ///
+    /// - _Return_ : Cache of output gate.
+    #[inline]
+    pub fn o_gate_cache(&self) -> &MlCache<OUT, IN> {&self.o_gate_cache}
+
+    /// Gets cache of layer normalization of output gate.
+    ///
+    /// It is not written if [`MlLstm`] has no layer normalization.
+    ///
+    /// - _Return_ : Cache of layer normalization of output gate.
+    #[inline]
+    pub fn o_gate_norm_cache(&self) -> &MlLayerNormCache<OUT> {
+        &self.o_gate_norm_cache
+    }
+
+    /// Gets cache of layer normalization of state.
+    ///
+    /// It is not written if [`MlLstm`] has no layer normalization.
+    ///
+    /// - _Return_ : Cache of layer normalization of state.
+    #[inline]
+    pub fn state_norm_cache(&self) -> &MlLayerNormCache<OUT> {
+        &self.state_norm_cache
+    }
+
+    /// Gets output of tanh(state).
+    ///
+    /// If [`MlLstm`] has layer normalization, state is normalized before tanh.
+    ///
+    /// - _Return_ : Output of tanh(state).
+    #[inline]
+    pub fn tanh_s(&self) -> &MathVec<OUT> {&self.tanh_s}
+
+    /// Gets derivative of tanh(state).
+    ///
+    /// If [`MlLstm`] has layer normalization, state is normalized before tanh.
+    ///
+    /// - _Return_ : Derivative of tanh(state).
+    #[inline]
+    pub fn d_tanh_s(&self) -> &MathVec<OUT> {&self.d_tanh_s}
+
+    /// Gets output.
+    ///
+    /// - _Return_ : Output.
+    #[inline]
+    pub fn output(&self) -> &MathVec<OUT> {&self.output}
+}
+
+// Same as MlLayer::ready(), but normalizes middle value if norm exists.
+// Middle value in cache is the one before normalization,
+// and the others are of the normalized value.
+#[inline]
+fn ready_lstm_layer<const OUT: usize, const IN: usize>(
+    layer: &MlLayer<OUT, IN>,
+    norm: Option<&MlLayerNorm<OUT>>,
+    input: &MathVec<IN>,
+    prev_state: &MathVec<OUT>,
+    cache: &mut MlCache<OUT, IN>,
+    norm_cache: &mut MlLayerNormCache<OUT>
+) {
+    layer.ready(input, Some(prev_state), cache);
+
+    if let Some(norm) = norm {
+        norm.ready(&cache.middle_value, norm_cache);
+
+        let activation = &layer.layer.activation;
+
+        norm_cache.output.as_array().iter().zip(
+            cache.d_activate.as_mut_array().iter_mut()
+        ).zip(
+            cache.d_slope.as_mut_array().iter_mut()
+        ).zip(
+            cache.output.as_mut_array().iter_mut()
+        ).for_each(|(((n_value, d_f), d_slope), output_one)| {
+            *d_f = activation.d_activate(*n_value);
+            *d_slope = activation.d_slope(*n_value);
+            *output_one = activation.activate(*n_value);
+        });
+    }
+}
+
+// Same as MlLayer::study(), but backpropagates through norm if it exists.
+#[inline]
+fn study_lstm_layer<const OUT: usize, const IN: usize>(
+    layer: &mut MlLayer<OUT, IN>,
+    norm: Option<&mut MlLayerNorm<OUT>>,
+    output_error: &MathVec<OUT>,
+    cache: &MlCache<OUT, IN>,
+    norm_cache: &MlLayerNormCache<OUT>,
+    input_error: &mut MathVec<IN>,
+    prev_state_error: &mut MathVec<OUT>
+) {
+    match norm {
+        Some(norm) => {
+            layer.calc_tmp_error(output_error, None, cache);
+            layer.add_slope_grad(output_error, &cache.d_slope);
+
+            norm.study_in_place(&mut layer.tmp_error, norm_cache);
+
+            layer.study_tmp_error(cache, input_error, Some(prev_state_error));
+        },
+
+        None => {
+            layer.study(
+                output_error,
+                None,
+                cache,
+                input_error,
+                Some(prev_state_error)
+            );
+        }
+    }
+}
+
+/// LSTM for machine learning.
+///
+/// See [`Lstm`] for details.
+///
+/// - `OUT` : Dimension of output.
+/// - `IN` : Dimension of input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlLstm<const OUT: usize, const IN: usize> {
+    main_layer: MlLayer<OUT, IN>,
+    f_gate: MlLayer<OUT, IN>,
+    i_gate: MlLayer<OUT, IN>,
+    o_gate: MlLayer<OUT, IN>,
+    tanh: Activation,
+
+    main_layer_norm: Option<MlLayerNorm<OUT>>,
+    f_gate_norm: Option<MlLayerNorm<OUT>>,
+    i_gate_norm: Option<MlLayerNorm<OUT>>,
+    o_gate_norm: Option<MlLayerNorm<OUT>>,
+    state_norm: Option<MlLayerNorm<OUT>>,
+
+    input_error_main_by_output_error: MathVec<IN>,
+    input_error_main_by_state_error: MathVec<IN>,
+    input_error_f_by_output_error: MathVec<IN>,
+    input_error_f_by_state_error: MathVec<IN>,
+    input_error_i_by_output_error: MathVec<IN>,
+    input_error_i_by_state_error: MathVec<IN>,
+    input_error_o_by_output_error: MathVec<IN>,
+    input_error_o_by_state_error: MathVec<IN>,
+
+    prev_state_error_main_by_output_error: MathVec<OUT>,
+    prev_state_error_main_by_state_error: MathVec<OUT>,
+    prev_state_error_f_by_output_error: MathVec<OUT>,
+    prev_state_error_f_by_state_error: MathVec<OUT>,
+    prev_state_error_i_by_output_error: MathVec<OUT>,
+    prev_state_error_i_by_state_error: MathVec<OUT>,
+    prev_state_error_o_by_output_error: MathVec<OUT>,
+    prev_state_error_o_by_state_error: MathVec<OUT>,
+
+    state_error_by_output_error: MathVec<OUT>,
+
+    tmp_error: MathVec<OUT>
+}
+
+impl<const OUT: usize, const IN: usize> MlLstm<OUT, IN> {
+    /// Creates MlLstm.
+    ///
+    /// - `lstm` : Base [`Lstm`].
+    /// - _Return_ : MlLstm.
+    #[inline]
+    pub fn new(lstm: Lstm<OUT, IN>) -> Self {
+        let Lstm::<OUT, IN> {
+            main_layer,
+            f_gate,
+            i_gate,
+            o_gate,
+            tanh,
+            main_layer_norm,
+            f_gate_norm,
+            i_gate_norm,
+            o_gate_norm,
+            state_norm
+        } = lstm;
+
+        Self {
+            main_layer: MlLayer::<OUT, IN>::new(main_layer),
+            f_gate: MlLayer::<OUT, IN>::new(f_gate),
+            i_gate: MlLayer::<OUT, IN>::new(i_gate),
+            o_gate: MlLayer::<OUT, IN>::new(o_gate),
+            tanh: tanh,
+
+            main_layer_norm: main_layer_norm.map(MlLayerNorm::<OUT>::new),
+            f_gate_norm: f_gate_norm.map(MlLayerNorm::<OUT>::new),
+            i_gate_norm: i_gate_norm.map(MlLayerNorm::<OUT>::new),
+            o_gate_norm: o_gate_norm.map(MlLayerNorm::<OUT>::new),
+            state_norm: state_norm.map(MlLayerNorm::<OUT>::new),
+
+            input_error_main_by_output_error: MathVec::<IN>::new(),
+            input_error_main_by_state_error: MathVec::<IN>::new(),
+            input_error_f_by_output_error: MathVec::<IN>::new(),
+            input_error_f_by_state_error: MathVec::<IN>::new(),
+            input_error_i_by_output_error: MathVec::<IN>::new(),
+            input_error_i_by_state_error: MathVec::<IN>::new(),
+            input_error_o_by_output_error: MathVec::<IN>::new(),
+            input_error_o_by_state_error: MathVec::<IN>::new(),
+
+            prev_state_error_main_by_output_error: MathVec::<OUT>::new(),
+            prev_state_error_main_by_state_error: MathVec::<OUT>::new(),
+            prev_state_error_f_by_output_error: MathVec::<OUT>::new(),
+            prev_state_error_f_by_state_error: MathVec::<OUT>::new(),
+            prev_state_error_i_by_output_error: MathVec::<OUT>::new(),
+            prev_state_error_i_by_state_error: MathVec::<OUT>::new(),
+            prev_state_error_o_by_output_error: MathVec::<OUT>::new(),
+            prev_state_error_o_by_state_error: MathVec::<OUT>::new(),
+
+            state_error_by_output_error: MathVec::<OUT>::new(),
+
+            tmp_error: MathVec::<OUT>::new()
+        }
+    }
+
+    /// Drops base [`Lstm`].
+    ///
+    /// - _Return_ : [`Lstm`].
+    #[inline]
+    pub fn drop(self) -> Lstm<OUT, IN> {
+        let Self {
+            main_layer,
+            f_gate,
+            i_gate,
+            o_gate,
+            tanh,
+            main_layer_norm,
+            f_gate_norm,
+            i_gate_norm,
+            o_gate_norm,
+            state_norm,
+            ..
+        } = self;
+
+        Lstm::<OUT, IN> {
+            main_layer: main_layer.drop(),
+
+            f_gate: f_gate.drop(),
+            i_gate: i_gate.drop(),
+            o_gate: o_gate.drop(),
+
+            tanh: tanh,
+
+            main_layer_norm: main_layer_norm.map(MlLayerNorm::<OUT>::drop),
+            f_gate_norm: f_gate_norm.map(MlLayerNorm::<OUT>::drop),
+            i_gate_norm: i_gate_norm.map(MlLayerNorm::<OUT>::drop),
+            o_gate_norm: o_gate_norm.map(MlLayerNorm::<OUT>::drop),
+            state_norm: state_norm.map(MlLayerNorm::<OUT>::drop)
+        }
+    }
+
+    // Layer normalizations in order of main layer, forget gate, input gate,
+    // output gate and state.
+    #[inline]
+    fn norms(&self) -> [&Option<MlLayerNorm<OUT>>; 5] {
+        [
+            &self.main_layer_norm,
+            &self.f_gate_norm,
+            &self.i_gate_norm,
+            &self.o_gate_norm,
+            &self.state_norm
+        ]
+    }
+
+    #[inline]
+    fn norms_mut(&mut self) -> [&mut Option<MlLayerNorm<OUT>>; 5] {
+        [
+            &mut self.main_layer_norm,
+            &mut self.f_gate_norm,
+            &mut self.i_gate_norm,
+            &mut self.o_gate_norm,
+            &mut self.state_norm
+        ]
+    }
+
+    /// Clears internal data for study.
+    #[inline]
+    pub fn clear_study_data(&mut self) {
+        self.main_layer.clear_study_data();
+        self.f_gate.clear_study_data();
+        self.i_gate.clear_study_data();
+        self.o_gate.clear_study_data();
+
+        self.norms_mut().into_iter().flatten().for_each(|norm| {
+            norm.clear_study_data();
+        });
+    }
+
+    /// Sets [`Optimizer`] to all layers.
+    ///
+    /// See [`MlLayer::set_optimizer()`] for details.
+    ///
+    /// - `optimizer` : Optimizer.
+    #[inline]
+    pub fn set_optimizer(&mut self, optimizer: Optimizer) {
+        self.main_layer.set_optimizer(optimizer);
+        self.f_gate.set_optimizer(optimizer);
+        self.i_gate.set_optimizer(optimizer);
+        self.o_gate.set_optimizer(optimizer);
+
+        self.norms_mut().into_iter().flatten().for_each(|norm| {
+            norm.set_optimizer(optimizer);
+        });
+    }
+
+    /// Writes information on [`MlLstmStateCache`] for [`study_state()`](Self::study_state()) or [`MlLstm::study()`].
+    ///
+    /// - `input` : Input.
+    /// - `prev_state` : Previous state.
+    /// - `cache` : Cache.
+    pub fn ready_state_cache(
+        &self,
+        input: &MathVec<IN>,
+        prev_state: &MathVec<OUT>,
+        cache: &mut MlLstmStateCache<OUT, IN>
+    ) {
+        cache.input.copy_from(input);
+        cache.prev_state.copy_from(prev_state);
+
+        ready_lstm_layer(
+            &self.main_layer,
+            self.main_layer_norm.as_ref(),
+            input,
+            prev_state,
+            &mut cache.main_layer_cache,
+            &mut cache.main_layer_norm_cache
+        );
+        ready_lstm_layer(
+            &self.f_gate,
+            self.f_gate_norm.as_ref(),
+            input,
+            prev_state,
+            &mut cache.f_gate_cache,
+            &mut cache.f_gate_norm_cache
+        );
+        ready_lstm_layer(
+            &self.i_gate,
+            self.i_gate_norm.as_ref(),
+            input,
+            prev_state,
+            &mut cache.i_gate_cache,
+            &mut cache.i_gate_norm_cache
+        );
+
+        cache.state.as_mut_array().iter_mut().zip(
+            prev_state.as_array().iter()
+        ).zip(
+            cache.f_gate_cache.output.as_array().iter()
+        ).zip(
+            cache.i_gate_cache.output.as_array().iter()
+        ).zip(
+            cache.main_layer_cache.output.as_array().iter()
+        ).for_each(|((((state_one, p_state), f_out), i_out), main_out)| {
+            *state_one = (*p_state * *f_out) + (*i_out * *main_out);
+        });
+    }
+
+    /// Writes information on [`MlLstmOutputCache`] for [`study()`](Self::study()).
+    ///
+    /// - `last_state_cache` : Cache written at [`ready_state_cache()`](Self::ready_state_cache()) just before.
+    /// - `cache` : Cache.
+    pub fn ready_output_cache(
+        &self,
+        last_state_cache: &MlLstmStateCache<OUT, IN>,
+        output_cache: &mut MlLstmOutputCache<OUT, IN>
+    ) {
+        ready_lstm_layer(
+            &self.o_gate,
+            self.o_gate_norm.as_ref(),
+            &last_state_cache.input,
+            &last_state_cache.prev_state,
+            &mut output_cache.o_gate_cache,
+            &mut output_cache.o_gate_norm_cache
+        );
+
+        let state = match &self.state_norm {
+            Some(norm) => {
+                norm.ready(
+                    &last_state_cache.state,
+                    &mut output_cache.state_norm_cache
+                );
+
+                &output_cache.state_norm_cache.output
+            },
+
+            None => &last_state_cache.state
+        };
+
+        state.as_array().iter().zip(
+            output_cache.tanh_s.as_mut_array().iter_mut()
+        ).zip(
+            output_cache.d_tanh_s.as_mut_array().iter_mut()
+        ).zip(
+            output_cache.output.as_mut_array().iter_mut()
+        ).zip(
+            output_cache.o_gate_cache.output.as_array().iter()
+        ).for_each(
+            |((((s, tanh_s_one), d_tanh_s_one), output_one), o_out)| {
+                *tanh_s_one = self.tanh.activate(*s);
+                *d_tanh_s_one = self.tanh.d_activate(*s);
+                *output_one = *o_out * *tanh_s_one;
+            }
+        );
+    }
+
+    /// Accumulates gradient.
+    ///
+    /// - `state_error` : Backpropagated state error.
+    /// - `cache` : Cache written at [`ready_state_cache()`](Self::ready_state_cache()).
+    /// - `input_error` : Error to backpropagate for previous output error.
+    /// - `prev_state_error` : Error to backpropagate previous state error.
+    pub fn study_state(
+        &mut self,
+        state_error: &MathVec<OUT>,
+        cache: &MlLstmStateCache<OUT, IN>,
+        input_error: &mut MathVec<IN>,
+        prev_state_error: &mut MathVec<OUT>
+    ) {
+        self.study_main_layer_with_state_error(state_error, cache);
+        self.study_f_gate_with_state_error(state_error, cache);
+        self.study_i_gate_with_state_error(state_error, cache);
+
+        input_error.copy_from(&self.input_error_main_by_state_error);
+        *input_error += &self.input_error_f_by_state_error;
+        *input_error += &self.input_error_i_by_state_error;
+
+        prev_state_error.copy_from(&self.prev_state_error_main_by_state_error);
+        *prev_state_error += &self.prev_state_error_f_by_state_error;
+        *prev_state_error += &self.prev_state_error_i_by_state_error;
+
+        prev_state_error.as_mut_array().iter_mut().zip(
+            state_error.as_array().iter()
+        ).zip(
+            cache.f_gate_cache.output.as_array().iter()
+        ).for_each(|((p_state_e, state_e), f_out)| {
+            *p_state_e += *state_e * *f_out;
+        });
+    }
+
+    fn study_main_layer_with_state_error(
+        &mut self,
+        state_error: &MathVec<OUT>,
+        cache: &MlLstmStateCache<OUT, IN>
+    ) {
+        self.tmp_error.as_mut_array().iter_mut().zip(
+            state_error.as_array().iter()
+        ).zip(
+            cache.i_gate_cache.output.as_array().iter()
+        ).for_each(|((tmp_e, state_e), i_out)| {
+            *tmp_e = *state_e * *i_out;
+        });
+
+        study_lstm_layer(
+            &mut self.main_layer,
+            self.main_layer_norm.as_mut(),
+            &self.tmp_error,
+            &cache.main_layer_cache,
+            &cache.main_layer_norm_cache,
+            &mut self.input_error_main_by_state_error,
+            &mut self.prev_state_error_main_by_state_error
+        );
+    }
+
+    fn study_f_gate_with_state_error(
+        &mut self,
+        state_error: &MathVec<OUT>,
+        cache: &MlLstmStateCache<OUT, IN>
+    ) {
+        self.tmp_error.as_mut_array().iter_mut().zip(
+            state_error.as_array().iter()
+        ).zip(
+            cache.prev_state.as_array().iter()
+        ).for_each(|((tmp_e, state_e), p_state)| {
+            *tmp_e = *state_e * *p_state;
+        });
+
+        study_lstm_layer(
+            &mut self.f_gate,
+            self.f_gate_norm.as_mut(),
+            &self.tmp_error,
+            &cache.f_gate_cache,
+            &cache.f_gate_norm_cache,
+            &mut self.input_error_f_by_state_error,
+            &mut self.prev_state_error_f_by_state_error
+        );
+    }
+
+    fn study_i_gate_with_state_error(
+        &mut self,
+        state_error: &MathVec<OUT>,
+        cache: &MlLstmStateCache<OUT, IN>
+    ) {
+        self.tmp_error.as_mut_array().iter_mut().zip(
+            state_error.as_array().iter()
+        ).zip(
+            cache.main_layer_cache.output.as_array().iter()
+        ).for_each(|((tmp_e, state_e), main_out)| {
+            *tmp_e = *state_e * *main_out;
+        });
+
+        study_lstm_layer(
+            &mut self.i_gate,
+            self.i_gate_norm.as_mut(),
+            &self.tmp_error,
+            &cache.i_gate_cache,
+            &cache.i_gate_norm_cache,
+            &mut self.input_error_i_by_state_error,
+            &mut self.prev_state_error_i_by_state_error
+        );
+    }
+
+    /// Studies weights with output error and state_error.
+    ///
+    /// - `output_error` : Backpropagated output error.
+    /// - `state_error` : Backpropagated state error.
+    /// - `state_cache` : Cache written at [`ready_state_cache()`](Self::ready_state_cache()).
+    /// - `output_cache` : Cache written at [`ready_output_cache()`](Self::ready_output_cache()).
+    /// - `input_error` : Error to backpropagate for previous output error.
+    /// - `prev_state_error` : Error to backpropagate for previous state error.
+    pub fn study(
+        &mut self,
+        output_error: &MathVec<OUT>,
+        state_error: &MathVec<OUT>,
+        state_cache: &MlLstmStateCache<OUT, IN>,
+        output_cache: &MlLstmOutputCache<OUT, IN>,
+        input_error: &mut MathVec<IN>,
+        prev_state_error: &mut MathVec<OUT>
+    ) {
+        self.calc_state_error_by_output_error(output_error, output_cache);
+
+        self.study_main_layer(state_error, state_cache);
+        self.study_f_gate(state_error, state_cache);
+        self.study_i_gate(state_error, state_cache);
+        self.study_o_gate(
+            output_error,
+            output_cache,
+        );
+
+        input_error.copy_from(&self.input_error_main_by_output_error);
+        *input_error += &self.input_error_f_by_output_error;
+        *input_error += &self.input_error_i_by_output_error;
+        *input_error += &self.input_error_o_by_output_error;
+
+        prev_state_error.copy_from(
+            &self.prev_state_error_main_by_output_error
+        );
+        *prev_state_error += &self.prev_state_error_f_by_output_error;
+        *prev_state_error += &self.prev_state_error_i_by_output_error;
+        *prev_state_error += &self.prev_state_error_o_by_output_error;
+
+        prev_state_error.as_mut_array().iter_mut().zip(
+            self.state_error_by_output_error.as_array().iter()
+        ).zip(
+            state_error.as_array().iter()
+        ).zip(
+            state_cache.f_gate_cache.output.as_array().iter()
+        ).for_each(|(((p_state_e, s_out_e), state_e), f_out)| {
+            *p_state_e += (*s_out_e + *state_e) * *f_out;
+        });
+    }
+
+    // Error of state backpropagated from output.
+    fn calc_state_error_by_output_error(
+        &mut self,
+        output_error: &MathVec<OUT>,
+        output_cache: &MlLstmOutputCache<OUT, IN>
+    ) {
+        self.state_error_by_output_error.as_mut_array().iter_mut().zip(
+            output_error.as_array().iter()
+        ).zip(
+            output_cache.o_gate_cache.output.as_array().iter()
+        ).zip(
+            output_cache.d_tanh_s.as_array().iter()
+        ).for_each(|(((s_out_e, out_e), o_out), d_tanh_s_one)| {
+            *s_out_e = *out_e * *o_out * *d_tanh_s_one;
+        });
+
+        if let Some(norm) = &mut self.state_norm {
+            norm.study_in_place(
+                &mut self.state_error_by_output_error,
+                &output_cache.state_norm_cache
+            );
+        }
+    }
+
+    fn study_main_layer(
+        &mut self,
+        state_error: &MathVec<OUT>,
+        state_cache: &MlLstmStateCache<OUT, IN>
+    ) {
+        self.tmp_error.as_mut_array().iter_mut().zip(
+            self.state_error_by_output_error.as_array().iter()
+        ).zip(
+            state_cache.i_gate_cache.output.as_array().iter()
+        ).zip(
+            state_error.as_array().iter()
+        ).for_each(|(((tmp_e, s_out_e), i_out), state_e)| {
+            *tmp_e = *s_out_e * *i_out;
+            *tmp_e += *state_e * *i_out;
+        });
+
+        study_lstm_layer(
+            &mut self.main_layer,
+            self.main_layer_norm.as_mut(),
+            &self.tmp_error,
+            &state_cache.main_layer_cache,
+            &state_cache.main_layer_norm_cache,
+            &mut self.input_error_main_by_output_error,
+            &mut self.prev_state_error_main_by_output_error
+        );
+    }
+
+    fn study_f_gate(
+        &mut self,
+        state_error: &MathVec<OUT>,
+        state_cache: &MlLstmStateCache<OUT, IN>
+    ) {
+        self.tmp_error.as_mut_array().iter_mut().zip(
+            self.state_error_by_output_error.as_array().iter()
+        ).zip(
+            state_cache.prev_state.as_array().iter()
+        ).zip(
+            state_error.as_array().iter()
+        ).for_each(|(((tmp_e, s_out_e), p_state), state_e)| {
+            *tmp_e = *s_out_e * *p_state;
+            *tmp_e += *state_e * *p_state;
+        });
+
+        study_lstm_layer(
+            &mut self.f_gate,
+            self.f_gate_norm.as_mut(),
+            &self.tmp_error,
+            &state_cache.f_gate_cache,
+            &state_cache.f_gate_norm_cache,
+            &mut self.input_error_f_by_output_error,
+            &mut self.prev_state_error_f_by_output_error
+        );
+    }
+
+    fn study_i_gate(
+        &mut self,
+        state_error: &MathVec<OUT>,
+        state_cache: &MlLstmStateCache<OUT, IN>
+    ) {
+        self.tmp_error.as_mut_array().iter_mut().zip(
+            self.state_error_by_output_error.as_array().iter()
+        ).zip(
+            state_cache.main_layer_cache.output.as_array().iter()
+        ).zip(
+            state_error.as_array().iter()
+        ).for_each(|(((tmp_e, s_out_e), main_out), state_e)| {
+            *tmp_e = *s_out_e * *main_out;
+            *tmp_e += *state_e * *main_out;
+        });
+
+        study_lstm_layer(
+            &mut self.i_gate,
+            self.i_gate_norm.as_mut(),
+            &self.tmp_error,
+            &state_cache.i_gate_cache,
+            &state_cache.i_gate_norm_cache,
+            &mut self.input_error_i_by_output_error,
+            &mut self.prev_state_error_i_by_output_error
+        );
+    }
+
+    fn study_o_gate(
+        &mut self,
+        output_error: &MathVec<OUT>,
+        cache: &MlLstmOutputCache<OUT, IN>
+    ) {
+        self.tmp_error.as_mut_array().iter_mut().zip(
+            output_error.as_array().iter()
+        ).zip(
+            cache.tanh_s.as_array().iter()
+        ).for_each(|((tmp_e, out_e),  tanh_s_one)| {
+            *tmp_e = *out_e * *tanh_s_one;
+        });
+
+        study_lstm_layer(
+            &mut self.o_gate,
+            self.o_gate_norm.as_mut(),
+            &self.tmp_error,
+            &cache.o_gate_cache,
+            &cache.o_gate_norm_cache,
+            &mut self.input_error_o_by_output_error,
+            &mut self.prev_state_error_o_by_output_error
+        );
+    }
+
+    /// Updates weights with learning rate from [`LrScheduler`], and advances step of scheduler.
+    ///
+    /// - `scheduler` : Scheduler of learning rate.
+    #[inline]
+    pub fn update_with_scheduler(&mut self, scheduler: &mut LrScheduler) {
+        self.update(scheduler.next_rate());
+    }
+
+    /// Update weights.
+    ///
+    /// - `rate` : Learning rate.
+    #[inline]
+    pub fn update(&mut self, rate: f32) {
+        self.main_layer.update(rate);
+        self.f_gate.update(rate);
+        self.i_gate.update(rate);
+        self.o_gate.update(rate);
+
+        self.norms_mut().into_iter().flatten().for_each(|norm| {
+            norm.update(rate);
+        });
+    }
+
+    /// Accesses each immutable total gradient with closure.
+    ///
+    /// Order is the same as [`Lstm::for_each_weight()`].
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_total_grad<F>(
+        &self,
+        mut f: F
+    ) where F: FnMut(&f32) {
+        self.main_layer.for_each_grad(&mut f);
+        self.f_gate.for_each_grad(&mut f);
+        self.i_gate.for_each_grad(&mut f);
+        self.o_gate.for_each_grad(&mut f);
+
+        self.norms().into_iter().flatten().for_each(|norm| {
+            norm.layer.for_each_grad(&mut f);
+        });
+    }
+
+    /// Accesses each mutable total gradient with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_total_grad_mut<F>(
+        &mut self,
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.main_layer.for_each_grad_mut(&mut f);
+        self.f_gate.for_each_grad_mut(&mut f);
+        self.i_gate.for_each_grad_mut(&mut f);
+        self.o_gate.for_each_grad_mut(&mut f);
+
+        self.norms_mut().into_iter().flatten().for_each(|norm| {
+            norm.layer.for_each_grad_mut(&mut f);
+        });
+    }
+
+    /// Clips each total gradient into `[-limit, limit]`.
+    ///
+    /// - `limit` : Limit of absolute value. Negative value is treated as its absolute value. If NaN, nothing is clipped.
+    #[inline]
+    pub fn clip_grad_by_value(&mut self, limit: f32) {
+        clip_by_value(limit, |f| self.for_each_total_grad_mut(f));
+    }
+
+    /// Scales all total gradients so that L2 norm of them is `max_norm` or less.
+    ///
+    /// - `max_norm` : Max L2 norm. Negative value is treated as its absolute value.
+    /// - _Return_ : L2 norm before clipping. If it is not finite, nothing is scaled.
+    #[inline]
+    pub fn clip_grad_by_global_norm(&mut self, max_norm: f32) -> f32 {
+        clip_by_global_norm(max_norm, |f| self.for_each_total_grad_mut(f))
+    }
+
+    /// Adds gradient of L1 and L2 penalty of weights into total gradients of all layers.
+    ///
+    /// See [`MlLayer::add_weight_penalty()`] for details. γ and β of layer normalization are not penalized.
+    ///
+    /// - `l1` : Coefficient of L1 penalty.
+    /// - `l2` : Coefficient of L2 penalty.
+    #[inline]
+    pub fn add_weight_penalty(&mut self, l1: f32, l2: f32) {
+        self.main_layer.add_weight_penalty(l1, l2);
+        self.f_gate.add_weight_penalty(l1, l2);
+        self.i_gate.add_weight_penalty(l1, l2);
+        self.o_gate.add_weight_penalty(l1, l2);
+    }
+
+    /// Checks that all total gradients and all weights are finite.
+    ///
+    /// Total gradients are checked first, in order of main layer, forget gate, input gate and output gate, and then layer normalizations if they exist.
+    /// If error is returned, you can abort machine learning, or skip the batch by clearing total gradients with [`for_each_total_grad_mut()`](Self::for_each_total_grad_mut()).
+    ///
+    /// - _Return_ : [`ChobitAiError::NonFinite`] if non-finite number is found.
+    pub fn check_finite(&self) -> Result<(), ChobitAiError> {
+        const NAMES: [&str; 9] = [
+            "main_layer",
+            "f_gate",
+            "i_gate",
+            "o_gate",
+            "main_layer_norm",
+            "f_gate_norm",
+            "i_gate_norm",
+            "o_gate_norm",
+            "state_norm"
+        ];
+
+        self.check_grad(&NAMES)?;
+        self.check_weights(&NAMES)?;
+
+        Ok(())
+    }
+
+    fn check_grad(
+        &self,
+        names: &[&'static str; 9]
+    ) -> Result<(), ChobitAiError> {
+        self.main_layer.check_grad(names[0])?;
+        self.f_gate.check_grad(names[1])?;
+        self.i_gate.check_grad(names[2])?;
+        self.o_gate.check_grad(names[3])?;
+
+        for (norm, name) in self.norms().into_iter().zip(&names[4..]) {
+            if let Some(norm) = norm {
+                norm.layer.check_grad(name)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_weights(
+        &self,
+        names: &[&'static str; 9]
+    ) -> Result<(), ChobitAiError> {
+        self.main_layer.check_weights(names[0])?;
+        self.f_gate.check_weights(names[1])?;
+        self.i_gate.check_weights(names[2])?;
+        self.o_gate.check_weights(names[3])?;
+
+        for (norm, name) in self.norms().into_iter().zip(&names[4..]) {
+            if let Some(norm) = norm {
+                norm.layer.check_weights(name)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes full state for machine learning into buffer as binary data.
+    ///
+    /// See [`MlLayer::save_checkpoint()`] for details.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save_checkpoint(&self, buffer: &mut Vec<u8>) {
+        write_header(buffer, ModelKind::MlLstm, &[OUT, IN]);
+
+        self.write_state(buffer);
+    }
+
+    /// Creates MlLstm from binary data written by [`save_checkpoint()`](Self::save_checkpoint()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : MlLstm.
+    pub fn load_checkpoint(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(ModelKind::MlLstm, &[("OUT", OUT), ("IN", IN)])?;
+
+        Self::read_state(&mut reader)
+    }
+
+    fn write_state(&self, buffer: &mut Vec<u8>) {
+        self.main_layer.write_state(buffer);
+        self.f_gate.write_state(buffer);
+        self.i_gate.write_state(buffer);
+        self.o_gate.write_state(buffer);
+
+        self.tanh.write(buffer);
+
+        for norm in self.norms() {
+            write_u8(buffer, norm.is_some() as u8);
+
+            if let Some(norm) = norm {
+                norm.write_state(buffer);
+            }
+        }
+    }
+
+    fn read_state(reader: &mut ByteReader) -> Result<Self, ChobitAiError> {
+        let mut ret = Self::new(Lstm::<OUT, IN>::new());
+
+        ret.main_layer = MlLayer::<OUT, IN>::read_state(reader)?;
+        ret.f_gate = MlLayer::<OUT, IN>::read_state(reader)?;
+        ret.i_gate = MlLayer::<OUT, IN>::read_state(reader)?;
+        ret.o_gate = MlLayer::<OUT, IN>::read_state(reader)?;
+
+        ret.tanh = Activation::read(reader)?;
+
+        for norm in ret.norms_mut() {
+            if reader.read_u8()? != 0 {
+                *norm = Some(MlLayerNorm::<OUT>::read_state(reader)?);
+            }
+        }
+
+        Ok(ret)
+    }
+}
+
+/// [GRU](https://en.wikipedia.org/wiki/Gated_recurrent_unit)
+///
+/// It has 3 layers while [`Lstm`] has 4, and state is output itself.
+///
+/// | Formula |
+/// |:-:|
+/// | `z_i = σ(Σ_j W^z_ij x_j + Σ_k U^z_ik s̄_k + b^z_i)` |
+/// | `r_i = σ(Σ_j W^r_ij x_j + Σ_k U^r_ik s̄_k + b^r_i)` |
+/// | `m_i = tanh(Σ_j W^m_ij x_j + Σ_k U^m_ik (r_k s̄_k) + b^m_i)` |
+/// | `s_i = ((1 - z_i) s̄_i) + (z_i m_i)` |
+/// | `y_i = s_i` |
+///
+/// - `x` : Input.
+/// - `s̄` : Previous state.
+/// - `s` : State.
+/// - `y` : Output.
+/// - `z` : Output of update gate.
+/// - `r` : Output of reset gate.
+/// - `m` : Output of main layer.
+/// - `σ` : Sigmoid function. (Activation function of gates.)
+/// - `tanh` : Hyperbolic tangent. (Activation function of main layer.)
+///
+/// - `OUT` : Dimension of output.
+/// - `IN` : Dimension of input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gru<const OUT: usize, const IN: usize> {
+    main_layer: Layer<OUT, IN>,
+
+    z_gate: Layer<OUT, IN>,
+    r_gate: Layer<OUT, IN>
+}
+
+impl<const OUT: usize, const IN: usize> Gru<OUT, IN> {
+    /// Creates Gru.
+    ///
+    /// - _Return_ : Gru.
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_activations(Activation::SoftSign, Activation::Sigmoid)
+    }
+
+    /// Creates Gru with activation functions.
+    ///
+    /// [`new()`](Self::new()) is the same as `with_activations(Activation::SoftSign, Activation::Sigmoid)`.
+    ///
+    /// - `main` : Activation function of main layer.
+    /// - `gate` : Activation function of update gate and reset gate.
+    /// - _Return_ : Gru.
+    #[inline]
+    pub fn with_activations(main: Activation, gate: Activation) -> Self {
+        Self {
+            main_layer: Layer::<OUT, IN>::new(main, true),
+
+            z_gate: Layer::<OUT, IN>::new(gate, true),
+            r_gate: Layer::<OUT, IN>::new(gate, true)
+        }
+    }
+
+    /// Gets immutable main layer.
+    ///
+    /// - _Return_ : Main layer.
+    #[inline]
+    pub fn main_layer(&self) -> &Layer<OUT, IN> {&self.main_layer}
+
+    /// Gets mutable main layer.
+    ///
+    /// - _Return_ : Main layer.
+    #[inline]
+    pub fn main_layer_mut(&mut self) -> &mut Layer<OUT, IN> {
+        &mut self.main_layer
+    }
+
+    /// Gets immutable update gate.
+    ///
+    /// - _Return_ : Update gate.
+    #[inline]
+    pub fn z_gate(&self) -> &Layer<OUT, IN> {&self.z_gate}
+
+    /// Gets mutable update gate.
+    ///
+    /// - _Return_ : Update gate.
+    #[inline]
+    pub fn z_gate_mut(&mut self) -> &mut Layer<OUT, IN> {&mut self.z_gate}
+
+    /// Gets immutable reset gate.
+    ///
+    /// - _Return_ : Reset gate.
+    #[inline]
+    pub fn r_gate(&self) -> &Layer<OUT, IN> {&self.r_gate}
+
+    /// Gets mutable reset gate.
+    ///
+    /// - _Return_ : Reset gate.
+    #[inline]
+    pub fn r_gate_mut(&mut self) -> &mut Layer<OUT, IN> {&mut self.r_gate}
+
+    /// Initializes weights of all layers with [`Initializer`].
+    ///
+    /// Bias of update gate is set to `-1.0`, so Gru remembers state at the beginning of machine learning. Other biases are set to `0.0`.
+    ///
+    /// - `input_initializer` : Initializer for input weights.
+    /// - `state_initializer` : Initializer for state weights. [`Initializer::Orthogonal`] is usual.
+    /// - `rand` : Closure that returns uniform random number in `[0.0, 1.0)`.
+    pub fn init_weights<F>(
+        &mut self,
+        input_initializer: Initializer,
+        state_initializer: Initializer,
+        mut rand: F
+    ) where F: FnMut() -> f32 {
+        for layer in [
+            &mut self.main_layer,
+            &mut self.z_gate,
+            &mut self.r_gate
+        ] {
+            input_initializer.init(&mut layer.weights, &mut rand);
+            state_initializer.init_state(&mut layer.weights, &mut rand);
+        }
+
+        self.z_gate.weights.bias_mut().fill(-1.0);
+    }
+
+    /// Calculates only state.
+    ///
+    /// - `input` : Input.
+    /// - `prev_state` : Previous state.
+    /// - `next_state` : Buffer for next state.
+    /// - `tmpbuf` : Temporary buffer for this function to work.
+    pub fn calc_state(
+        &self,
+        input: &MathVec<IN>,
+        prev_state: &MathVec<OUT>,
+        next_state: &mut MathVec<OUT>,
+        tmpbuf: &mut MathVec<OUT>
+    ) {
+        // tmpbuf = r_gate * prev_state
+        self.r_gate.calc(input, Some(prev_state), tmpbuf);
+        tmpbuf.pointwise_mul_assign(prev_state);
+
+        // next_state = main_layer
+        self.main_layer.calc(input, Some(tmpbuf), next_state);
+
+        // state = ((1 - z_gate) * prev_state) + (z_gate * main_layer)
+        self.z_gate.calc(input, Some(prev_state), tmpbuf);
+
+        next_state.as_mut_array().iter_mut().zip(
+            prev_state.as_array().iter()
+        ).zip(
+            tmpbuf.as_array().iter()
+        ).for_each(|((state_one, p_state), z_out)| {
+            *state_one = ((1.0 - *z_out) * *p_state) + (*z_out * *state_one);
+        });
+    }
+
+    /// Calculates state and output.
+    ///
+    /// Output is the same as next state. This exists for the same usage as [`Lstm::calc()`].
+    ///
+    /// - `input` : Input.
+    /// - `prev_state` : Previous state.
+    /// - `output` : Buffer for output.
+    /// - `next_state` : Buffer for next state.
+    /// - `tmpbuf` : Temporary buffer for this function to work.
+    #[inline]
+    pub fn calc(
+        &self,
+        input: &MathVec<IN>,
+        prev_state: &MathVec<OUT>,
+        output: &mut MathVec<OUT>,
+        next_state: &mut MathVec<OUT>,
+        tmpbuf: &mut MathVec<OUT>
+    ) {
+        self.calc_state(input, prev_state, next_state, tmpbuf);
+
+        output.copy_from(next_state);
+    }
+
+    /// Accesses each immutable weight with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_weight<F>(&self, mut f: F) where F: FnMut(&f32) {
+        self.main_layer.for_each_weight(|val| {f(val)});
+        self.z_gate.for_each_weight(|val| {f(val)});
+        self.r_gate.for_each_weight(|val| {f(val)});
+    }
+
+    /// Accesses each mutable weight with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_weight_mut<F>(
+        &mut self,
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.main_layer.for_each_weight_mut(|val| {f(val)});
+        self.z_gate.for_each_weight_mut(|val| {f(val)});
+        self.r_gate.for_each_weight_mut(|val| {f(val)});
+    }
+
+    /// Writes this GRU into buffer as binary data.
+    ///
+    /// See [`ChobitAi::save()`] for details.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save(&self, buffer: &mut Vec<u8>) {
+        write_header(buffer, ModelKind::Gru, &[OUT, IN]);
+
+        self.write_descriptor(buffer);
+        self.write_body(buffer);
+    }
+
+    /// Creates Gru from binary data written by [`save()`](Self::save()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : Gru.
+    pub fn load(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(ModelKind::Gru, &[("OUT", OUT), ("IN", IN)])?;
+
+        let mut ret = Self::read_descriptor(&mut reader)?;
+        ret.read_body(&mut reader)?;
+
+        Ok(ret)
+    }
+
+    fn write_descriptor(&self, buffer: &mut Vec<u8>) {
+        self.main_layer.write_descriptor(buffer);
+        self.z_gate.write_descriptor(buffer);
+        self.r_gate.write_descriptor(buffer);
+    }
+
+    fn read_descriptor(reader: &mut ByteReader) -> Result<Self, ChobitAiError> {
+        Ok(Self {
+            main_layer: Layer::<OUT, IN>::read_descriptor(reader)?,
+
+            z_gate: Layer::<OUT, IN>::read_descriptor(reader)?,
+            r_gate: Layer::<OUT, IN>::read_descriptor(reader)?
+        })
+    }
+
+    fn write_body(&self, buffer: &mut Vec<u8>) {
+        self.main_layer.weights.write_body(buffer);
+        self.z_gate.weights.write_body(buffer);
+        self.r_gate.weights.write_body(buffer);
+    }
+
+    fn read_body(
+        &mut self,
+        reader: &mut ByteReader
+    ) -> Result<(), ChobitAiError> {
+        self.main_layer.weights.read_body(reader)?;
+        self.z_gate.weights.read_body(reader)?;
+        self.r_gate.weights.read_body(reader)
+    }
+}
+
+/// Cache for state error of [`MlGru`].
+///
+/// - `OUT` : Output of [`MlGru`].
+/// - `IN` : Input of [`MlGru`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlGruStateCache<const OUT: usize, const IN: usize> {
+    input: MathVec<IN>,
+    prev_state: MathVec<OUT>,
+
+    z_gate_cache: MlCache<OUT, IN>,
+    r_gate_cache: MlCache<OUT, IN>,
+
+    main_layer_cache: MlCache<OUT, IN>,
+
+    state: MathVec<OUT>
+}
+
+impl<const OUT: usize, const IN: usize> MlGruStateCache<OUT, IN> {
+    /// Creates MlGruStateCache.
+    ///
+    /// - _Return_ : MlGruStateCache.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            input: MathVec::<IN>::new(),
+            prev_state: MathVec::<OUT>::new(),
+
+            z_gate_cache: MlCache::<OUT, IN>::new(),
+            r_gate_cache: MlCache::<OUT, IN>::new(),
+
+            main_layer_cache: MlCache::<OUT, IN>::new(),
+
+            state: MathVec::<OUT>::new()
+        }
+    }
+
+    /// Gets input.
+    ///
+    /// - _Return_ : Input.
+    #[inline]
+    pub fn input(&self) -> &MathVec<IN> {&self.input}
+
+    /// Gets previous state.
+    ///
+    /// - _Return_ : Previous state.
+    #[inline]
+    pub fn prev_state(&self) -> &MathVec<OUT> {&self.prev_state}
+
+    /// Gets cache of update gate.
+    ///
+    /// - _Return_ : Cache of update gate.
+    #[inline]
+    pub fn z_gate_cache(&self) -> &MlCache<OUT, IN> {&self.z_gate_cache}
+
+    /// Gets cache of reset gate.
+    ///
+    /// - _Return_ : Cache of reset gate.
+    #[inline]
+    pub fn r_gate_cache(&self) -> &MlCache<OUT, IN> {&self.r_gate_cache}
+
+    /// Gets cache of main layer.
+    ///
+    /// State of the cache is previous state multiplied by output of reset gate.
+    ///
+    /// - _Return_ : Cache of main layer.
+    #[inline]
+    pub fn main_layer_cache(&self) -> &MlCache<OUT, IN> {
+        &self.main_layer_cache
+    }
+
+    /// Gets state.
+    ///
+    /// - _Return_ : State.
+    #[inline]
+    pub fn state(&self) -> &MathVec<OUT> {&self.state}
+}
+
+/// Cache for output error of [`MlGru`].
+///
+/// Output of [`Gru`] is the same as state, so this holds only output.
+///
+/// - `OUT` : Output of [`MlGru`].
+/// - `IN` : Input of [`MlGru`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlGruOutputCache<const OUT: usize, const IN: usize> {
+    output: MathVec<OUT>
+}
+
+impl<const OUT: usize, const IN: usize> MlGruOutputCache<OUT, IN> {
+    /// Creates MlGruOutputCache.
+    ///
+    /// - _Return_ : MlGruOutputCache.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            output: MathVec::<OUT>::new()
+        }
+    }
+
+    /// Calculates output error.
+    ///
+    /// See [`MlCache::calc_output_error()`] for details.
+    ///
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error(
+        &self,
+        train_out: &MathVec<OUT>,
+        output_error: &mut MathVec<OUT>
+    ) {
+        output_error.copy_from(&self.output);
+        *output_error -= train_out;
+    }
+
+    /// Calculates output error with loss function.
+    ///
+    /// - `loss` : Loss function.
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error_with_loss(
+        &self,
+        loss: &Loss,
+        train_out: &MathVec<OUT>,
+        output_error: &mut MathVec<OUT>
+    ) {
+        loss.calc_error(&self.output, train_out, output_error);
+    }
+
+    /// Gets output.
+    ///
+    /// - _Return_ : Output.
+    #[inline]
+    pub fn output(&self) -> &MathVec<OUT> {&self.output}
+}
+
+/// GRU for machine learning.
+///
+/// See [`Gru`] for details.
+///
+/// - `OUT` : Dimension of output.
+/// - `IN` : Dimension of input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlGru<const OUT: usize, const IN: usize> {
+    main_layer: MlLayer<OUT, IN>,
+    z_gate: MlLayer<OUT, IN>,
+    r_gate: MlLayer<OUT, IN>,
+
+    input_error_main: MathVec<IN>,
+    input_error_z: MathVec<IN>,
+    input_error_r: MathVec<IN>,
+
+    reset_state_error: MathVec<OUT>,
+    prev_state_error_z: MathVec<OUT>,
+    prev_state_error_r: MathVec<OUT>,
+
+    tmp_state_error: MathVec<OUT>,
+    tmp_error: MathVec<OUT>
+}
+
+impl<const OUT: usize, const IN: usize> MlGru<OUT, IN> {
+    /// Creates MlGru.
+    ///
+    /// - `gru` : Base [`Gru`].
+    /// - _Return_ : MlGru.
+    #[inline]
+    pub fn new(gru: Gru<OUT, IN>) -> Self {
+        let Gru::<OUT, IN> {main_layer, z_gate, r_gate} = gru;
+
+        Self {
+            main_layer: MlLayer::<OUT, IN>::new(main_layer),
+            z_gate: MlLayer::<OUT, IN>::new(z_gate),
+            r_gate: MlLayer::<OUT, IN>::new(r_gate),
+
+            input_error_main: MathVec::<IN>::new(),
+            input_error_z: MathVec::<IN>::new(),
+            input_error_r: MathVec::<IN>::new(),
+
+            reset_state_error: MathVec::<OUT>::new(),
+            prev_state_error_z: MathVec::<OUT>::new(),
+            prev_state_error_r: MathVec::<OUT>::new(),
+
+            tmp_state_error: MathVec::<OUT>::new(),
+            tmp_error: MathVec::<OUT>::new()
+        }
+    }
+
+    /// Drops base [`Gru`].
+    ///
+    /// - _Return_ : [`Gru`].
+    #[inline]
+    pub fn drop(self) -> Gru<OUT, IN> {
+        let Self {main_layer, z_gate, r_gate, ..} = self;
+
+        Gru::<OUT, IN> {
+            main_layer: main_layer.drop(),
+
+            z_gate: z_gate.drop(),
+            r_gate: r_gate.drop()
+        }
+    }
+
+    /// Clears internal data for study.
+    #[inline]
+    pub fn clear_study_data(&mut self) {
+        self.main_layer.clear_study_data();
+        self.z_gate.clear_study_data();
+        self.r_gate.clear_study_data();
+    }
+
+    /// Sets [`Optimizer`] to all layers.
+    ///
+    /// See [`MlLayer::set_optimizer()`] for details.
+    ///
+    /// - `optimizer` : Optimizer.
+    #[inline]
+    pub fn set_optimizer(&mut self, optimizer: Optimizer) {
+        self.main_layer.set_optimizer(optimizer);
+        self.z_gate.set_optimizer(optimizer);
+        self.r_gate.set_optimizer(optimizer);
+    }
+
+    /// Writes information on [`MlGruStateCache`] for [`study_state()`](Self::study_state()) or [`MlGru::study()`].
+    ///
+    /// - `input` : Input.
+    /// - `prev_state` : Previous state.
+    /// - `cache` : Cache.
+    pub fn ready_state_cache(
+        &self,
+        input: &MathVec<IN>,
+        prev_state: &MathVec<OUT>,
+        cache: &mut MlGruStateCache<OUT, IN>
+    ) {
+        cache.input.copy_from(input);
+        cache.prev_state.copy_from(prev_state);
+
+        self.z_gate.ready(input, Some(prev_state), &mut cache.z_gate_cache);
+        self.r_gate.ready(input, Some(prev_state), &mut cache.r_gate_cache);
+
+        // state is used as buffer for reset state.
+        cache.state.copy_from(prev_state);
+        cache.state.pointwise_mul_assign(&cache.r_gate_cache.output);
+
+        self.main_layer.ready(
+            input,
+            Some(&cache.state),
+            &mut cache.main_layer_cache
+        );
+
+        cache.state.as_mut_array().iter_mut().zip(
+            prev_state.as_array().iter()
+        ).zip(
+            cache.z_gate_cache.output.as_array().iter()
+        ).zip(
+            cache.main_layer_cache.output.as_array().iter()
+        ).for_each(|(((state_one, p_state), z_out), main_out)| {
+            *state_one = ((1.0 - *z_out) * *p_state) + (*z_out * *main_out);
+        });
+    }
+
+    /// Writes information on [`MlGruOutputCache`] for [`study()`](Self::study()).
+    ///
+    /// - `last_state_cache` : Cache written at [`ready_state_cache()`](Self::ready_state_cache()) just before.
+    /// - `cache` : Cache.
+    #[inline]
+    pub fn ready_output_cache(
+        &self,
+        last_state_cache: &MlGruStateCache<OUT, IN>,
+        output_cache: &mut MlGruOutputCache<OUT, IN>
+    ) {
+        output_cache.output.copy_from(&last_state_cache.state);
+    }
+
+    /// Accumulates gradient.
+    ///
+    /// - `state_error` : Backpropagated state error.
+    /// - `cache` : Cache written at [`ready_state_cache()`](Self::ready_state_cache()).
+    /// - `input_error` : Error to backpropagate for previous output error.
+    /// - `prev_state_error` : Error to backpropagate previous state error.
+    #[inline]
+    pub fn study_state(
+        &mut self,
+        state_error: &MathVec<OUT>,
+        cache: &MlGruStateCache<OUT, IN>,
+        input_error: &mut MathVec<IN>,
+        prev_state_error: &mut MathVec<OUT>
+    ) {
+        self.tmp_state_error.copy_from(state_error);
+
+        self.study_tmp_state_error(cache, input_error, prev_state_error);
+    }
+
+    /// Studies weights with output error and state_error.
+    ///
+    /// Output is the same as state, so this is the same as [`study_state()`](Self::study_state()) with sum of `output_error` and `state_error`.
+    ///
+    /// - `output_error` : Backpropagated output error.
+    /// - `state_error` : Backpropagated state error.
+    /// - `state_cache` : Cache written at [`ready_state_cache()`](Self::ready_state_cache()).
+    /// - `output_cache` : Cache written at [`ready_output_cache()`](Self::ready_output_cache()).
+    /// - `input_error` : Error to backpropagate for previous output error.
+    /// - `prev_state_error` : Error to backpropagate for previous state error.
+    #[inline]
+    pub fn study(
+        &mut self,
+        output_error: &MathVec<OUT>,
+        state_error: &MathVec<OUT>,
+        state_cache: &MlGruStateCache<OUT, IN>,
+        _output_cache: &MlGruOutputCache<OUT, IN>,
+        input_error: &mut MathVec<IN>,
+        prev_state_error: &mut MathVec<OUT>
+    ) {
+        self.tmp_state_error.copy_from(output_error);
+        self.tmp_state_error += state_error;
+
+        self.study_tmp_state_error(state_cache, input_error, prev_state_error);
+    }
+
+    fn study_tmp_state_error(
+        &mut self,
+        cache: &MlGruStateCache<OUT, IN>,
+        input_error: &mut MathVec<IN>,
+        prev_state_error: &mut MathVec<OUT>
+    ) {
+        self.study_main_layer(cache);
+        self.study_z_gate(cache);
+        self.study_r_gate(cache);
+
+        input_error.copy_from(&self.input_error_main);
+        *input_error += &self.input_error_z;
+        *input_error += &self.input_error_r;
+
+        prev_state_error.copy_from(&self.prev_state_error_z);
+        *prev_state_error += &self.prev_state_error_r;
+
+        prev_state_error.as_mut_array().iter_mut().zip(
+            self.tmp_state_error.as_array().iter()
+        ).zip(
+            cache.z_gate_cache.output.as_array().iter()
+        ).zip(
+            self.reset_state_error.as_array().iter()
+        ).zip(
+            cache.r_gate_cache.output.as_array().iter()
+        ).for_each(|((((p_state_e, state_e), z_out), reset_e), r_out)| {
+            *p_state_e += *state_e * (1.0 - *z_out);
+            *p_state_e += *reset_e * *r_out;
+        });
+    }
+
+    fn study_main_layer(&mut self, cache: &MlGruStateCache<OUT, IN>) {
+        self.tmp_error.as_mut_array().iter_mut().zip(
+            self.tmp_state_error.as_array().iter()
+        ).zip(
+            cache.z_gate_cache.output.as_array().iter()
+        ).for_each(|((tmp_e, state_e), z_out)| {
+            *tmp_e = *state_e * *z_out;
+        });
+
+        self.main_layer.study(
+            &self.tmp_error,
+            None,
+            &cache.main_layer_cache,
+            &mut self.input_error_main,
+            Some(&mut self.reset_state_error)
+        );
+    }
+
+    fn study_z_gate(&mut self, cache: &MlGruStateCache<OUT, IN>) {
+        self.tmp_error.as_mut_array().iter_mut().zip(
+            self.tmp_state_error.as_array().iter()
+        ).zip(
+            cache.main_layer_cache.output.as_array().iter()
+        ).zip(
+            cache.prev_state.as_array().iter()
+        ).for_each(|(((tmp_e, state_e), main_out), p_state)| {
+            *tmp_e = *state_e * (*main_out - *p_state);
+        });
+
+        self.z_gate.study(
+            &self.tmp_error,
+            None,
+            &cache.z_gate_cache,
+            &mut self.input_error_z,
+            Some(&mut self.prev_state_error_z)
+        );
+    }
+
+    fn study_r_gate(&mut self, cache: &MlGruStateCache<OUT, IN>) {
+        self.tmp_error.as_mut_array().iter_mut().zip(
+            self.reset_state_error.as_array().iter()
+        ).zip(
+            cache.prev_state.as_array().iter()
+        ).for_each(|((tmp_e, reset_e), p_state)| {
+            *tmp_e = *reset_e * *p_state;
+        });
+
+        self.r_gate.study(
+            &self.tmp_error,
+            None,
+            &cache.r_gate_cache,
+            &mut self.input_error_r,
+            Some(&mut self.prev_state_error_r)
+        );
+    }
+
+    /// Updates weights with learning rate from [`LrScheduler`], and advances step of scheduler.
+    ///
+    /// - `scheduler` : Scheduler of learning rate.
+    #[inline]
+    pub fn update_with_scheduler(&mut self, scheduler: &mut LrScheduler) {
+        self.update(scheduler.next_rate());
+    }
+
+    /// Update weights.
+    ///
+    /// - `rate` : Learning rate.
+    #[inline]
+    pub fn update(&mut self, rate: f32) {
+        self.main_layer.update(rate);
+        self.z_gate.update(rate);
+        self.r_gate.update(rate);
+    }
+
+    /// Accesses each immutable total gradient with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_total_grad<F>(
+        &self,
+        mut f: F
+    ) where F: FnMut(&f32) {
+        self.main_layer.for_each_grad(&mut f);
+        self.z_gate.for_each_grad(&mut f);
+        self.r_gate.for_each_grad(&mut f);
+    }
+
+    /// Accesses each mutable total gradient with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_total_grad_mut<F>(
+        &mut self,
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.main_layer.for_each_grad_mut(&mut f);
+        self.z_gate.for_each_grad_mut(&mut f);
+        self.r_gate.for_each_grad_mut(&mut f);
+    }
+
+    /// Clips each total gradient into `[-limit, limit]`.
+    ///
+    /// - `limit` : Limit of absolute value. Negative value is treated as its absolute value. If NaN, nothing is clipped.
+    #[inline]
+    pub fn clip_grad_by_value(&mut self, limit: f32) {
+        clip_by_value(limit, |f| self.for_each_total_grad_mut(f));
+    }
+
+    /// Scales all total gradients so that L2 norm of them is `max_norm` or less.
+    ///
+    /// - `max_norm` : Max L2 norm. Negative value is treated as its absolute value.
+    /// - _Return_ : L2 norm before clipping. If it is not finite, nothing is scaled.
+    #[inline]
+    pub fn clip_grad_by_global_norm(&mut self, max_norm: f32) -> f32 {
+        clip_by_global_norm(max_norm, |f| self.for_each_total_grad_mut(f))
+    }
+
+    /// Adds gradient of L1 and L2 penalty of weights into total gradients of all layers.
+    ///
+    /// See [`MlLayer::add_weight_penalty()`] for details.
+    ///
+    /// - `l1` : Coefficient of L1 penalty.
+    /// - `l2` : Coefficient of L2 penalty.
+    #[inline]
+    pub fn add_weight_penalty(&mut self, l1: f32, l2: f32) {
+        self.main_layer.add_weight_penalty(l1, l2);
+        self.z_gate.add_weight_penalty(l1, l2);
+        self.r_gate.add_weight_penalty(l1, l2);
+    }
+
+    /// Checks that all total gradients and all weights are finite.
+    ///
+    /// Total gradients are checked first, in order of main layer, update gate and reset gate.
+    /// If error is returned, you can abort machine learning, or skip the batch by clearing total gradients with [`for_each_total_grad_mut()`](Self::for_each_total_grad_mut()).
+    ///
+    /// - _Return_ : [`ChobitAiError::NonFinite`] if non-finite number is found.
+    pub fn check_finite(&self) -> Result<(), ChobitAiError> {
+        const NAMES: [&str; 3] = ["main_layer", "z_gate", "r_gate"];
+
+        self.check_grad(&NAMES)?;
+        self.check_weights(&NAMES)?;
+
+        Ok(())
+    }
+
+    fn check_grad(
+        &self,
+        names: &[&'static str; 3]
+    ) -> Result<(), ChobitAiError> {
+        self.main_layer.check_grad(names[0])?;
+        self.z_gate.check_grad(names[1])?;
+        self.r_gate.check_grad(names[2])
+    }
+
+    fn check_weights(
+        &self,
+        names: &[&'static str; 3]
+    ) -> Result<(), ChobitAiError> {
+        self.main_layer.check_weights(names[0])?;
+        self.z_gate.check_weights(names[1])?;
+        self.r_gate.check_weights(names[2])
+    }
+
+    /// Writes full state for machine learning into buffer as binary data.
+    ///
+    /// See [`MlLayer::save_checkpoint()`] for details.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save_checkpoint(&self, buffer: &mut Vec<u8>) {
+        write_header(buffer, ModelKind::MlGru, &[OUT, IN]);
+
+        self.write_state(buffer);
+    }
+
+    /// Creates MlGru from binary data written by [`save_checkpoint()`](Self::save_checkpoint()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : MlGru.
+    pub fn load_checkpoint(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(ModelKind::MlGru, &[("OUT", OUT), ("IN", IN)])?;
+
+        Self::read_state(&mut reader)
+    }
+
+    fn write_state(&self, buffer: &mut Vec<u8>) {
+        self.main_layer.write_state(buffer);
+        self.z_gate.write_state(buffer);
+        self.r_gate.write_state(buffer);
+    }
+
+    fn read_state(reader: &mut ByteReader) -> Result<Self, ChobitAiError> {
+        let mut ret = Self::new(Gru::<OUT, IN>::new());
+
+        ret.main_layer = MlLayer::<OUT, IN>::read_state(reader)?;
+        ret.z_gate = MlLayer::<OUT, IN>::read_state(reader)?;
+        ret.r_gate = MlLayer::<OUT, IN>::read_state(reader)?;
+
+        Ok(ret)
+    }
+}
+
+/// Scaled dot-product attention. (Luong style)
+///
+/// Query attends over memory, and context made from memory is combined with query by a layer.
+///
+/// | Formula |
+/// |:-:|
+/// | `s_i = (q · m_i) / sqrt(N)` |
+/// | `a = softmax(s)` |
+/// | `c = Σ a_i * m_i` |
+/// | `y = φ(W c + U q + b)` |
+///
+/// - `q` : Query.
+/// - `m_i` : i-th memory.
+/// - `a` : Attention weights.
+/// - `c` : Context.
+/// - `W`, `U`, `b` : Input weights, state weights and bias of the layer. Context is input and query is state of it.
+/// - `y` : Output.
+///
+/// - `N` : Dimension of query, memory and output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attention<const N: usize> {
+    layer: Layer<N, N>
+}
+
+impl<const N: usize> Attention<N> {
+    /// Creates Attention.
+    ///
+    /// - `activation` : Activation function of the layer.
+    /// - _Return_ : Attention.
+    #[inline]
+    pub fn new(activation: Activation) -> Self {
+        Self::with_layer(Layer::<N, N>::new(activation, true))
+    }
+
+    /// Creates Attention with layer.
+    ///
+    /// - `layer` : Layer which combines context and query. It must have state weights, otherwise query is ignored.
+    /// - _Return_ : Attention.
+    #[inline]
+    pub fn with_layer(layer: Layer<N, N>) -> Self {
+        Self {
+            layer: layer
+        }
+    }
+
+    /// Gets immutable layer.
+    ///
+    /// - _Return_ : Layer.
+    #[inline]
+    pub fn layer(&self) -> &Layer<N, N> {&self.layer}
+
+    /// Gets mutable layer.
+    ///
+    /// - _Return_ : Layer.
+    #[inline]
+    pub fn layer_mut(&mut self) -> &mut Layer<N, N> {&mut self.layer}
+
+    /// Initializes weights with [`Initializer`].
+    ///
+    /// - `input_initializer` : Initializer for input weights.
+    /// - `state_initializer` : Initializer for state weights.
+    /// - `rand` : Closure that returns uniform random number in `[0.0, 1.0)`.
+    pub fn init_weights<F>(
+        &mut self,
+        input_initializer: Initializer,
+        state_initializer: Initializer,
+        mut rand: F
+    ) where F: FnMut() -> f32 {
+        input_initializer.init(&mut self.layer.weights, &mut rand);
+        state_initializer.init_state(&mut self.layer.weights, &mut rand);
+    }
+
+    /// Calculates attention weights, context and output.
+    ///
+    /// If `memory` is empty, context is zero.
+    ///
+    /// - `query` : Query.
+    /// - `memory` : Memory.
+    /// - `weights` : Buffer for attention weights. Its length must be the same as `memory`.
+    /// - `context` : Buffer for context.
+    /// - `output` : Buffer for output.
+    pub fn calc(
+        &self,
+        query: &MathVec<N>,
+        memory: &[MathVec<N>],
+        weights: &mut [f32],
+        context: &mut MathVec<N>,
+        output: &mut MathVec<N>
+    ) {
+        Self::calc_context(query, memory, weights, context);
+
+        self.layer.calc(context, Some(query), output);
+    }
+
+    fn calc_context(
+        query: &MathVec<N>,
+        memory: &[MathVec<N>],
+        weights: &mut [f32],
+        context: &mut MathVec<N>
+    ) {
+        let scale = Self::scale();
+
+        weights.iter_mut().zip(memory.iter()).for_each(|(weight, memory)| {
+            *weight = query.iter().zip(memory.iter()).map(
+                |(q, m)| *q * *m
+            ).sum::<f32>() * scale;
+        });
+
+        softmax_slice(weights);
+
+        context.clear();
+
+        weights.iter().zip(memory.iter()).for_each(|(weight, memory)| {
+            context.iter_mut().zip(memory.iter()).for_each(|(c, m)| {
+                *c += *weight * *m;
+            });
+        });
+    }
+
+    #[inline]
+    fn scale() -> f32 {
+        accurate_sqrt(N as f32).recip()
+    }
+
+    /// Accesses each immutable weight with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_weight<F>(&self, mut f: F) where F: FnMut(&f32) {
+        self.layer.for_each_weight(|val| {f(val)});
+    }
+
+    /// Accesses each mutable weight with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_weight_mut<F>(
+        &mut self,
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.layer.for_each_weight_mut(|val| {f(val)});
+    }
+
+    fn write_descriptor(&self, buffer: &mut Vec<u8>) {
+        self.layer.write_descriptor(buffer);
+    }
+
+    fn read_descriptor(reader: &mut ByteReader) -> Result<Self, ChobitAiError> {
+        Ok(Self::with_layer(Layer::<N, N>::read_descriptor(reader)?))
+    }
+
+    fn write_body(&self, buffer: &mut Vec<u8>) {
+        self.layer.weights.write_body(buffer);
+    }
+
+    fn read_body(
+        &mut self,
+        reader: &mut ByteReader
+    ) -> Result<(), ChobitAiError> {
+        self.layer.weights.read_body(reader)
+    }
+}
+
+/// Cache for [`MlAttention`].
+///
+/// - `N` : `N` of [`MlAttention`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlAttentionCache<const N: usize> {
+    query: MathVec<N>,
+    weights: Vec<f32>,
+    context: MathVec<N>,
+
+    layer_cache: MlCache<N, N>
+}
+
+impl<const N: usize> MlAttentionCache<N> {
+    /// Creates MlAttentionCache.
+    ///
+    /// - _Return_ : MlAttentionCache.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            query: MathVec::<N>::new(),
+            weights: Vec::<f32>::new(),
+            context: MathVec::<N>::new(),
+
+            layer_cache: MlCache::<N, N>::new()
+        }
+    }
+
+    /// Gets query.
+    ///
+    /// - _Return_ : Query.
+    #[inline]
+    pub fn query(&self) -> &MathVec<N> {&self.query}
+
+    /// Gets attention weights.
+    ///
+    /// - _Return_ : Attention weights for each memory.
+    #[inline]
+    pub fn weights(&self) -> &[f32] {&self.weights}
+
+    /// Gets context.
+    ///
+    /// - _Return_ : Context.
+    #[inline]
+    pub fn context(&self) -> &MathVec<N> {&self.context}
+
+    /// Gets ['MlCache'] of the layer.
+    ///
+    /// - _Return_ : [`MlCache`].
+    #[inline]
+    pub fn layer_cache(&self) -> &MlCache<N, N> {&self.layer_cache}
+
+    /// Gets output.
+    ///
+    /// - _Return_ : Output.
+    #[inline]
+    pub fn output(&self) -> &MathVec<N> {&self.layer_cache.output}
+}
+
+/// Wrapper of [`Attention`] for machine learning.
+///
+/// See [`Attention`] for details.
+///
+/// - `N` : Dimension of query, memory and output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlAttention<const N: usize> {
+    layer: MlLayer<N, N>,
+
+    tmp_context_error: MathVec<N>,
+    tmp_weight_errors: Vec<f32>
+}
+
+impl<const N: usize> MlAttention<N> {
+    /// Creates MlAttention.
+    ///
+    /// - `attention` : Base [`Attention`].
+    /// - _Return_ : MlAttention.
+    #[inline]
+    pub fn new(attention: Attention<N>) -> Self {
+        let Attention::<N> {layer} = attention;
+
+        Self {
+            layer: MlLayer::<N, N>::new(layer),
+
+            tmp_context_error: MathVec::<N>::new(),
+            tmp_weight_errors: Vec::<f32>::new()
+        }
+    }
+
+    /// Drops base [`Attention`].
+    ///
+    /// - _Return_ : [`Attention`].
+    #[inline]
+    pub fn drop(self) -> Attention<N> {
+        Attention::<N>::with_layer(self.layer.drop())
+    }
+
+    /// Gets immutable layer.
+    ///
+    /// - _Return_ : Layer.
+    #[inline]
+    pub fn layer(&self) -> &MlLayer<N, N> {&self.layer}
+
+    /// Gets mutable layer.
+    ///
+    /// - _Return_ : Layer.
+    #[inline]
+    pub fn layer_mut(&mut self) -> &mut MlLayer<N, N> {&mut self.layer}
+
+    /// Clears internal data for study.
+    #[inline]
+    pub fn clear_study_data(&mut self) {
+        self.layer.clear_study_data();
+    }
+
+    /// Sets [`Optimizer`] to the layer.
+    ///
+    /// See [`MlLayer::set_optimizer()`] for details.
+    ///
+    /// - `optimizer` : Optimizer.
+    #[inline]
+    pub fn set_optimizer(&mut self, optimizer: Optimizer) {
+        self.layer.set_optimizer(optimizer);
+    }
+
+    /// Writes information on [`MlAttentionCache`] for [`study()`](Self::study()).
+    ///
+    /// - `query` : Query.
+    /// - `memory` : Memory.
+    /// - `cache` : Cache.
+    pub fn ready(
+        &self,
+        query: &MathVec<N>,
+        memory: &[MathVec<N>],
+        cache: &mut MlAttentionCache<N>
+    ) {
+        cache.query.copy_from(query);
+        cache.weights.resize(memory.len(), 0.0);
+
+        Attention::<N>::calc_context(
+            query,
+            memory,
+            &mut cache.weights,
+            &mut cache.context
+        );
+
+        self.layer.ready(
+            &cache.context,
+            Some(query),
+            &mut cache.layer_cache
+        );
+    }
+
+    /// Accumulates gradient.
+    ///
+    /// - `output_error` : Backpropagated output error.
+    /// - `memory` : Memory given to [`ready()`](Self::ready()).
+    /// - `cache` : Cache.
+    /// - `query_error` : Error to backpropagate for query.
+    /// - `memory_error` : Error to backpropagate for memory. Errors are added to it, because the same memory is usually attended by several queries.
+    pub fn study(
+        &mut self,
+        output_error: &MathVec<N>,
+        memory: &[MathVec<N>],
+        cache: &MlAttentionCache<N>,
+        query_error: &mut MathVec<N>,
+        memory_error: &mut [MathVec<N>]
+    ) {
+        let scale = Attention::<N>::scale();
+
+        self.layer.study(
+            output_error,
+            None,
+            &cache.layer_cache,
+            &mut self.tmp_context_error,
+            Some(query_error)
+        );
+
+        // Error of each attention weight.
+        self.tmp_weight_errors.resize(cache.weights.len(), 0.0);
+
+        self.tmp_weight_errors.iter_mut().zip(
+            memory.iter()
+        ).for_each(|(weight_e, memory)| {
+            *weight_e = self.tmp_context_error.iter().zip(
+                memory.iter()
+            ).map(|(c_e, m)| *c_e * *m).sum::<f32>();
+        });
+
+        // Error of each score through softmax.
+        let weighted_sum = cache.weights.iter().zip(
+            self.tmp_weight_errors.iter()
+        ).map(|(weight, weight_e)| *weight * *weight_e).sum::<f32>();
+
+        self.tmp_weight_errors.iter_mut().zip(
+            cache.weights.iter()
+        ).for_each(|(weight_e, weight)| {
+            *weight_e = *weight * (*weight_e - weighted_sum) * scale;
+        });
+
+        cache.weights.iter().zip(
+            self.tmp_weight_errors.iter()
+        ).zip(
+            memory.iter()
+        ).zip(
+            memory_error.iter_mut()
+        ).for_each(|(((weight, score_e), memory), memory_error)| {
+            query_error.iter_mut().zip(
+                memory.iter()
+            ).zip(
+                memory_error.iter_mut()
+            ).zip(
+                self.tmp_context_error.iter()
+            ).zip(
+                cache.query.iter()
+            ).for_each(|((((q_e, m), m_e), c_e), q)| {
+                *q_e += *score_e * *m;
+                *m_e += (*weight * *c_e) + (*score_e * *q);
+            });
+        });
+    }
+
+    /// Updates weights with learning rate from [`LrScheduler`], and advances step of scheduler.
+    ///
+    /// - `scheduler` : Scheduler of learning rate.
+    #[inline]
+    pub fn update_with_scheduler(&mut self, scheduler: &mut LrScheduler) {
+        self.update(scheduler.next_rate());
+    }
+
+    /// Updates weights.
+    ///
+    /// - `rate` : Learning rate.
+    #[inline]
+    pub fn update(&mut self, rate: f32) {
+        self.layer.update(rate);
+    }
+
+    /// Accesses each immutable total gradient with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_total_grad<F>(
+        &self,
+        mut f: F
+    ) where F: FnMut(&f32) {
+        self.layer.for_each_grad(&mut f);
+    }
+
+    /// Accesses each mutable total gradient with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_total_grad_mut<F>(
+        &mut self,
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.layer.for_each_grad_mut(&mut f);
+    }
+
+    /// Adds gradient of L1 and L2 penalty of weights into total gradients.
+    ///
+    /// See [`MlLayer::add_weight_penalty()`] for details.
+    ///
+    /// - `l1` : Coefficient of L1 penalty.
+    /// - `l2` : Coefficient of L2 penalty.
+    #[inline]
+    pub fn add_weight_penalty(&mut self, l1: f32, l2: f32) {
+        self.layer.add_weight_penalty(l1, l2);
+    }
+
+    fn check_grad(&self, name: &'static str) -> Result<(), ChobitAiError> {
+        self.layer.check_grad(name)
+    }
+
+    fn check_weights(&self, name: &'static str) -> Result<(), ChobitAiError> {
+        self.layer.check_weights(name)
+    }
+
+    fn write_state(&self, buffer: &mut Vec<u8>) {
+        self.layer.write_state(buffer);
+    }
+
+    fn read_state(reader: &mut ByteReader) -> Result<Self, ChobitAiError> {
+        let mut ret = Self::new(Attention::<N>::new(Activation::Linear));
+
+        ret.layer = MlLayer::<N, N>::read_state(reader)?;
+
+        Ok(ret)
+    }
+}
+
+/// Encoder from sequence data to fixed length data.
+///
+/// - `OUT` : Dimension of output.
+/// - `MIDDLE` : Dimension of hidden layer.
+/// - `IN` : Dimension of input.
+///
+/// # Example
+///
+/// Variable length word classifier.
+/// - If inputs Japanese word, outputs '日'.
+/// - If inputs English word, outputs 'E'.
+///
+/// (1) Defines word generator and ID.
+///
+/// ```ignore
+/// extern crate chobitlibs;
+/// 
+/// use chobitlibs::chobit_ai::{
+///     MathVec,
+///     Activation,
+///     ChobitEncoder,
+///     ChobitMlEncoder,
+///     MlEncoderCache
+/// };
+/// 
+/// use chobitlibs::chobit_rand::ChobitRand;
+/// 
+/// fn japanese_letter(rng: &mut ChobitRand) -> char {
+///     let letters = [
+///         'あ', 'い', 'う', 'え', 'お',
+///         'か', 'き', 'く', 'け', 'こ',
+///         'さ', 'し', 'す', 'せ', 'そ'
+///     ];
+/// 
+///     letters[(rng.next_u64() as usize) % letters.len()]
+/// }
+/// 
+/// fn english_letter(rng: &mut ChobitRand) -> char {
+///     let letters = [
+///         'a', 'b', 'c', 'd', 'e',
+///         'f', 'g', 'h', 'i', 'j',
+///         'k', 'l', 'm', 'n', 'o'
+///     ];
+/// 
+///     letters[(rng.next_u64() as usize) % letters.len()]
+/// }
+/// 
+/// const JAPANESE_ID: char = '日';
+/// const ENGLISH_ID: char = 'E';
+/// 
+/// fn gen_word(
+///     f: fn(&mut ChobitRand) -> char,
+///     rng: &mut ChobitRand,
+///     max_len: usize
+/// ) -> String {
+///     let word_len = ((rng.next_u64() as usize) % max_len) + 1;
+/// 
+///     let mut ret = String::with_capacity(word_len);
+/// 
+///     for _ in 0..word_len {
+///         ret.push(f(rng));
+///     }
+/// 
+///     ret
+/// }
+/// 
+/// fn write_string_to_slice(string: &str, slice: &mut [MathVec<32>]) {
+///     string.chars().zip(slice.iter_mut()).for_each(|(c, s)| {
+///         s.load_u32_label(c as u32);
+///     });
+/// }
+/// ```
+///
+/// (2) Creates [`ChobitEncoder`] and randomises weights.
+///
+/// ```ignore
+/// const OUT: usize = 32;
+/// const MIDDLE: usize = 64;
+/// const IN: usize = 32;
+///
+/// const MAX_WORD_LEN: usize = 10;
+///
+/// let mut rng = ChobitRand::new(b"ChobitEncoder Example");
+///
+/// let mut encoder =
+///     ChobitEncoder::<OUT, MIDDLE, IN>::new(Activation::SoftSign);
+///
+/// // Randomises weights.
+/// encoder.for_each_weight_mut(|weight| {
+///     *weight = ((rng.next_f64() as f32) * 2.0) - 1.0;
+/// });
+///
+/// let mut input = vec![MathVec::<IN>::new(); MAX_WORD_LEN];
+/// let mut output = MathVec::<OUT>::new();
+/// let initial_state = MathVec::<MIDDLE>::new();
+/// ```
+///
+/// (3) Wraps AI with [`ChobitMlEncoder`] for machine learning.
+///
+/// ```ignore
+/// let mut encoder = ChobitMlEncoder::<OUT, MIDDLE, IN>::new(encoder);
+/// let mut cache = MlEncoderCache::<OUT, MIDDLE, IN>::new(MAX_WORD_LEN);
+///
+/// let mut input_error = vec![MathVec::<IN>::new(); MAX_WORD_LEN];
+/// let mut output_error = MathVec::<OUT>::new();
+/// let mut prev_state_error = MathVec::<MIDDLE>::new();
+/// ```
+///
+/// (4) Machine learning.
+///
+/// ```ignore
+/// const EPOCH: usize = 1000;
+/// const BATCH_SIZE: usize = 100;
+/// const RATE: f32 = 0.01;
+///
+/// for _ in 0..EPOCH {
+///     for _ in 0..BATCH_SIZE {
+///         //--- Learns Japanese ---//
+///         let string = gen_word(japanese_letter, &mut rng, MAX_WORD_LEN);
+///
+///         write_string_to_slice(&string, &mut input);
+///
+///         output.load_u32_label(JAPANESE_ID as u32);
+///
+///         // Writes cache.
+///         encoder.ready(
+///             &input[..string.chars().count()],
+///             &initial_state,
+///             &mut cache
+///         );
+///
+///         // Calculates error.
+///         cache.calc_output_error(&output, &mut output_error);
+///
+///         // Studies.
+///         encoder.study(
+///             &output_error,
+///             &cache,
+///             &mut input_error,
+///             &mut prev_state_error
+///         );
+///
+///         //--- Learns English ---//
+///         let string = gen_word(english_letter, &mut rng, MAX_WORD_LEN);
+///
+///         write_string_to_slice(&string, &mut input);
+///
+///         output.load_u32_label(ENGLISH_ID as u32);
+///
+///         // Writes cache.
+///         encoder.ready(
+///             &input[..string.chars().count()],
+///             &initial_state,
+///             &mut cache
+///         );
+///
+///         // Calculates error.
+///         cache.calc_output_error(&output, &mut output_error);
+///
+///         // Studies.
+///         encoder.study(
+///             &output_error,
+///             &cache,
+///             &mut input_error,
+///             &mut prev_state_error
+///         );
+///     }
+///
+///     // Updates weights.
+///     encoder.update(RATE);
+/// }
+/// ```
+///
+/// (5) Tests encoder.
+///
+/// ```ignore
+/// // Unwrap Encoder.
+/// let mut encoder = encoder.drop();
+///
+/// // Tests Japanese.
+/// for _ in 0..10 {
+///     let string = gen_word(japanese_letter, &mut rng, MAX_WORD_LEN);
+///
+///     write_string_to_slice(&string, &mut input);
+///
+///     // Initializes state.
+///     encoder.state_mut().copy_from(&initial_state);
+///
+///     // Inputs for each one.
+///     input[..string.chars().count()].iter().for_each(|input_one| {
+///         encoder.input_next(input_one)
+///     });
+///
+///     // Outputs.
+///     encoder.output(&mut output);
+///
+///     assert_eq!(output.to_u32_label(), JAPANESE_ID as u32);
+/// }
+///
+/// // Tests English.
+/// for _ in 0..10 {
+///     let string = gen_word(english_letter, &mut rng, MAX_WORD_LEN);
+///
+///     write_string_to_slice(&string, &mut input);
+///
+///     // Initializes state.
+///     encoder.state_mut().copy_from(&initial_state);
+///
+///     // Inputs for each one.
+///     input[..string.chars().count()].iter().for_each(|input_one| {
+///         encoder.input_next(input_one)
+///     });
+///
+///     // Outputs.
+///     encoder.output(&mut output);
+///
+///     assert_eq!(output.to_u32_label(), ENGLISH_ID as u32);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChobitEncoder<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> {
+    lstm: Lstm<MIDDLE, IN>,
+    output_layer: Layer<OUT, MIDDLE>,
+
+    prev_state: MathVec<MIDDLE>,
+    state: MathVec<MIDDLE>,
+    last_input: MathVec<IN>,
+
+    middle_output: MathVec<MIDDLE>,
+    tmpbuf: MathVec<MIDDLE>
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> ChobitEncoder<OUT, MIDDLE, IN> {
+    /// Creates ChobitEncoder.
+    ///
+    /// - `activation` : Activation function for output layer.
+    /// - _Return_ : ChobitEncoder.
+    #[inline]
+    pub fn new(activation: Activation) -> Self {
+        Self::with_layers(
+            Lstm::<MIDDLE, IN>::new(),
+            Layer::<OUT, MIDDLE>::new(activation, false)
+        )
+    }
+
+    /// Creates ChobitEncoder with activation functions.
+    ///
+    /// - `hidden` : Activation function for main layer of Lstm and for state before output gate. (See [`Lstm::with_activations()`].)
+    /// - `output` : Activation function for output layer.
+    /// - _Return_ : ChobitEncoder.
+    #[inline]
+    pub fn with_activations(hidden: Activation, output: Activation) -> Self {
+        Self::with_layers(
+            Lstm::<MIDDLE, IN>::with_activations(
+                hidden,
+                Activation::Sigmoid,
+                hidden
+            ),
+            Layer::<OUT, MIDDLE>::new(output, false)
+        )
+    }
+
+    /// Creates ChobitEncoder with layers.
+    ///
+    /// - `lstm` : Lstm.
+    /// - `output_layer` : Output layer. State weights of it are not used.
+    /// - _Return_ : ChobitEncoder.
+    #[inline]
+    pub fn with_layers(
+        lstm: Lstm<MIDDLE, IN>,
+        output_layer: Layer<OUT, MIDDLE>
+    ) -> Self {
+        Self {
+            lstm: lstm,
+            output_layer: output_layer,
+
+            prev_state: MathVec::<MIDDLE>::new(),
+            state: MathVec::<MIDDLE>::new(),
+            last_input: MathVec::<IN>::new(),
+
+            middle_output: MathVec::<MIDDLE>::new(),
+            tmpbuf: MathVec::<MIDDLE>::new()
+        }
+    }
+
+    /// Gets immutable Lstm.
+    ///
+    /// - _Return_ : Lstm.
+    #[inline]
+    pub fn lstm(&self) -> &Lstm<MIDDLE, IN> {&self.lstm}
+
+    /// Gets mutable Lstm.
+    ///
+    /// - _Return_ : Lstm.
+    #[inline]
+    pub fn lstm_mut(&mut self) -> &mut Lstm<MIDDLE, IN> {&mut self.lstm}
+
+    /// Gets immutable output layer.
+    ///
+    /// - _Return_ : Output layer.
+    #[inline]
+    pub fn output_layer(&self) -> &Layer<OUT, MIDDLE> {&self.output_layer}
+
+    /// Gets mutable output layer.
+    ///
+    /// - _Return_ : Output layer.
+    #[inline]
+    pub fn output_layer_mut(&mut self) -> &mut Layer<OUT, MIDDLE> {
+        &mut self.output_layer
+    }
+
+    /// Initializes weights with [`Initializer`].
+    ///
+    /// Lstm is initialized by [`Lstm::init_weights()`] with `initializer` for input weights and [`Initializer::Orthogonal`] for state weights.
+    ///
+    /// - `initializer` : Initializer for output layer and input weights of Lstm.
+    /// - `rand` : Closure that returns uniform random number in `[0.0, 1.0)`.
+    pub fn init_weights<F>(&mut self, initializer: Initializer, mut rand: F)
+    where F: FnMut() -> f32 {
+        self.lstm.init_weights(
+            initializer,
+            Initializer::Orthogonal,
+            &mut rand
+        );
+
+        self.output_layer.init_weights(initializer, &mut rand);
+    }
+
+    /// Gets immutable state.
+    ///
+    /// This should be initialized before the first [`input_next()`](Self::input_next).
+    ///
+    /// - _Return_ : State.
+    #[inline]
+    pub fn state(&self) -> &MathVec<MIDDLE> {&self.state}
+
+    /// Gets mutable state.
+    ///
+    /// This should be initialized before the first [`input_next()`](Self::input_next).
+    ///
+    /// - _Return_ : State.
+    #[inline]
+    pub fn state_mut(&mut self) -> &mut MathVec<MIDDLE> {&mut self.state}
+
+    /// Gets last input.
+    ///
+    /// - _Return_ : Last input.
+    #[inline]
+    pub fn last_input(&self) -> &MathVec<IN> {&self.last_input}
+
+    /// Accesses each immutable weight with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_weight<F>(&self, mut f: F) where F: FnMut(&f32) {
+        self.lstm.for_each_weight(|val| {f(val)});
+        self.output_layer.for_each_weight(|val| {f(val)});
+    }
+
+    /// Accesses each mutable weight with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_weight_mut<F>(
+        &mut self,
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.lstm.for_each_weight_mut(|val| {f(val)});
+        self.output_layer.for_each_weight_mut(|val| {f(val)});
+    }
+
+    /// Input next data.
+    ///
+    /// - `input` : Next data.
+    #[inline]
+    pub fn input_next(&mut self, input: &MathVec<IN>) {
+        self.prev_state.copy_from(&self.state);
+        self.last_input.copy_from(input);
+
+        self.lstm.calc_state(
+            input,
+            &self.prev_state,
+            &mut self.state,
+            &mut self.tmpbuf
+        );
+    }
+
+    /// Output data calculated by current state and last input.
+    ///
+    /// - `output` : Buffer for output.
+    #[inline]
+    pub fn output(&mut self, output: &mut MathVec<OUT>) {
+        self.prev_state.copy_from(&self.state);
+
+        self.lstm.calc(
+            &self.last_input,
+            &self.prev_state,
+            &mut self.middle_output,
+            &mut self.state,
+            &mut self.tmpbuf
+        );
+
+        self.output_layer.calc(&self.middle_output, None, output);
+    }
+
+    /// Writes this encoder into buffer as binary data.
+    ///
+    /// State is not written. See [`ChobitAi::save()`] for details.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save(&self, buffer: &mut Vec<u8>) {
+        write_header(buffer, ModelKind::ChobitEncoder, &[OUT, MIDDLE, IN]);
+
+        self.lstm.write_descriptor(buffer);
+        self.output_layer.write_descriptor(buffer);
+
+        self.lstm.write_body(buffer);
+        self.output_layer.weights.write_body(buffer);
+    }
+
+    /// Creates ChobitEncoder from binary data written by [`save()`](Self::save()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : ChobitEncoder.
+    pub fn load(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(
+            ModelKind::ChobitEncoder,
+            &[("OUT", OUT), ("MIDDLE", MIDDLE), ("IN", IN)]
+        )?;
+
+        let mut lstm = Lstm::<MIDDLE, IN>::read_descriptor(&mut reader)?;
+        let mut output_layer =
+            Layer::<OUT, MIDDLE>::read_descriptor(&mut reader)?;
+
+        lstm.read_body(&mut reader)?;
+        output_layer.weights.read_body(&mut reader)?;
+
+        let mut ret = Self::new(Activation::Linear);
+
+        ret.lstm = lstm;
+        ret.output_layer = output_layer;
+
+        Ok(ret)
+    }
+}
+
+/// Cache for [`ChobitMlEncoder`].
+///
+/// - `OUT` : `OUT` of [`ChobitMlEncoder`].
+/// - `MIDDLE` : `MIDDLE` of [`ChobitMlEncoder`].
+/// - `IN` : `IN` of [`ChobitMlEncoder`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlEncoderCache<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> {
+    lstm_state_caches: Vec<MlLstmStateCache<MIDDLE, IN>>,
+    lstm_state_caches_len: usize,
+
+    lstm_output_cache: MlLstmOutputCache<MIDDLE, IN>,
+
+    output_layer_cache: MlCache<OUT, MIDDLE>
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> MlEncoderCache<OUT, MIDDLE, IN> {
+    /// Creates MlEncoderCache.
+    ///
+    /// - _Return_ : MlEncoderCache.
+    #[inline]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            lstm_state_caches: vec![
+                MlLstmStateCache::<MIDDLE, IN>::new(); capacity
+            ],
+            lstm_state_caches_len: 0,
+
+            lstm_output_cache: MlLstmOutputCache::<MIDDLE, IN>::new(),
+
+            output_layer_cache: MlCache::<OUT, MIDDLE>::new()
+        }
+    }
+
+    /// Calculates output error.
+    ///
+    /// | Formula |
+    /// |:-:|
+    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mi>e</mi> <mo stretchy="false">=</mo> <mrow> <mi>o</mi> <mo stretchy="false">−</mo> <mi>t</mi> </mrow> </mrow> </semantics> </math> |
+    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mtable columnalign="left"> <mtr> <mtd> <mrow> <mi>e</mi> <mo stretchy="false">≝</mo> <mtext>Error.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>o</mi> <mo stretchy="false">≝</mo> <mtext>Actual output.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>t</mi> <mo stretchy="false">≝</mo> <mtext>Correct output.</mtext> </mrow> </mtd> </mtr> </mtable> </semantics> </math> |
+    ///
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error(
+        &self,
+        train_out: &MathVec<OUT>,
+        output_error: &mut MathVec<OUT>
+    ) {
+        output_error.copy_from(&self.output_layer_cache.output);
+        *output_error -= train_out;
+    }
+
+    /// Calculates output error with loss function.
+    ///
+    /// - `loss` : Loss function.
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error_with_loss(
+        &self,
+        loss: &Loss,
+        train_out: &MathVec<OUT>,
+        output_error: &mut MathVec<OUT>
+    ) {
+        loss.calc_error(
+            &self.output_layer_cache.output,
+            train_out,
+            output_error
+        );
+    }
+
+    /// Gets slice of ['MlLstmStateCache'] of lstm layer.
+    ///
+    /// - _Return_ : Slice of ['MlLstmStateCache'].
+    #[inline]
+    pub fn lstm_state_caches(&self) -> &[MlLstmStateCache<MIDDLE, IN>] {
+        &self.lstm_state_caches[..self.lstm_state_caches_len]
+    }
+
+    /// Gets ['MlLstmOutputCache'] of lstm layer.
+    ///
+    /// - _Return_ : ['MlLstmOutputCache'].
+    #[inline]
+    pub fn lstm_output_cache(&self) -> &MlLstmOutputCache<MIDDLE, IN> {
+        &self.lstm_output_cache
+    }
+
+    /// Gets ['MlCache'] of output layer.
+    ///
+    /// - _Return_ : [`MlCache`].
+    #[inline]
+    pub fn output_layer_cache(&self) -> &MlCache<OUT, MIDDLE> {
+        &self.output_layer_cache
+    }
+
+    /// Gets output.
+    ///
+    /// - _Return_ : output.
+    #[inline]
+    pub fn output(&self) -> &MathVec<OUT> {
+        &self.output_layer_cache.output
+    }
+}
+
+/// Wrapper of [`ChobitEncoder`] for machine learning.
+///
+/// See [`ChobitEncoder`] for details.
+///
+/// - `OUT` : Dimension of output.
+/// - `MIDDLE` : Dimension of hidden layer.
+/// - `IN` : Dimension of input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChobitMlEncoder<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> {
+    lstm: MlLstm<MIDDLE, IN>,
+    output_layer: MlLayer<OUT, MIDDLE>,
+
+    prev_state: MathVec<MIDDLE>,
+
+    tmp_middle_output_error: MathVec<MIDDLE>,
+    tmp_state_error: MathVec<MIDDLE>,
+
+    original_prev_state: MathVec<MIDDLE>,
+    original_state: MathVec<MIDDLE>,
+    original_last_input: MathVec<IN>,
+
+    original_middle_output: MathVec<MIDDLE>,
+    original_tmpbuf: MathVec<MIDDLE>
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> ChobitMlEncoder<OUT, MIDDLE, IN> {
+    /// Creates ChobitMlEncoder.
+    ///
+    /// - `encoder` : Base [`ChobitEncoder`].
+    /// - _Return_ : ChobitMlEncoder.
+    #[inline]
+    pub fn new(encoder: ChobitEncoder<OUT, MIDDLE, IN>) -> Self {
+        let ChobitEncoder::<OUT, MIDDLE, IN> {
+            lstm,
+            output_layer,
+            prev_state,
+            state,
+            last_input,
+            middle_output,
+            tmpbuf
+        } = encoder;
+
+        Self {
+            lstm: MlLstm::<MIDDLE, IN>::new(lstm),
+            output_layer: MlLayer::<OUT, MIDDLE>::new(output_layer),
+
+            prev_state: MathVec::<MIDDLE>::new(),
+
+            tmp_middle_output_error: MathVec::<MIDDLE>::new(),
+            tmp_state_error: MathVec::<MIDDLE>::new(),
+
+            original_prev_state: prev_state,
+            original_state: state,
+            original_last_input: last_input,
+            original_middle_output: middle_output,
+            original_tmpbuf: tmpbuf
+        }
+    }
+
+    /// Drops base [`ChobitEncoder`].
+    ///
+    /// - _Return_ : [`ChobitEncoder`].
+    #[inline]
+    pub fn drop(self) -> ChobitEncoder<OUT, MIDDLE, IN> {
+        let Self {
+            lstm,
+            output_layer,
+            original_prev_state,
+            original_state,
+            original_last_input,
+            original_middle_output,
+            original_tmpbuf,
+            ..
+        } = self;
+
+        ChobitEncoder::<OUT, MIDDLE, IN> {
+            lstm: lstm.drop(),
+            output_layer: output_layer.drop(),
+            prev_state: original_prev_state,
+            state: original_state,
+            last_input: original_last_input,
+            middle_output: original_middle_output,
+            tmpbuf: original_tmpbuf
+        }
+    }
+
+    /// Clears internal data for study.
+    #[inline]
+    pub fn clear_study_data(&mut self) {
+        self.lstm.clear_study_data();
+        self.output_layer.clear_study_data();
+    }
+
+    /// Sets [`Optimizer`] to all layers.
+    ///
+    /// See [`MlLayer::set_optimizer()`] for details.
+    ///
+    /// - `optimizer` : Optimizer.
+    #[inline]
+    pub fn set_optimizer(&mut self, optimizer: Optimizer) {
+        self.lstm.set_optimizer(optimizer);
+        self.output_layer.set_optimizer(optimizer);
+    }
+
+    /// Writes information on [`MlEncoderCache`] for [`study()`](Self::study()).
+    ///
+    /// - `train_in` : Input data sequence.
+    /// - `prev_state` : Previous state.
+    /// - `cache` : Cache.
+    #[inline]
+    pub fn ready(
+        &mut self,
+        train_in: &[MathVec<IN>],
+        prev_state: &MathVec<MIDDLE>,
+        cache: &mut MlEncoderCache<OUT, MIDDLE, IN>
+    ) {
+        self.ready_state_cache(train_in, prev_state, cache);
+        self.ready_output_cache(cache);
+    }
+
+    fn ready_state_cache(
+        &mut self,
+        train_in: &[MathVec<IN>],
+        prev_state: &MathVec<MIDDLE>,
+        cache: &mut MlEncoderCache<OUT, MIDDLE, IN>
+    ) {
+        cache.lstm_state_caches_len = train_in.len();
+        if cache.lstm_state_caches.len() < train_in.len() {
+            cache.lstm_state_caches.resize(
+                train_in.len(),
+                MlLstmStateCache::<MIDDLE, IN>::new()
+            );
+        }
+
+        self.prev_state.copy_from(prev_state);
+
+        train_in.iter().zip(
+            cache.lstm_state_caches.iter_mut()
+        ).for_each(|(train_in_one, cache)| {
+            self.lstm.ready_state_cache(
+                train_in_one,
+                &self.prev_state,
+                cache
+            );
+
+            self.prev_state.copy_from(&cache.state);
+        })
+    }
+
+    fn ready_output_cache(
+        &self,
+        cache: &mut MlEncoderCache<OUT, MIDDLE, IN>
+    ) {
+        if let Some(last_state_cache) = cache.lstm_state_caches.get(
+            cache.lstm_state_caches_len.wrapping_sub(1)
+        ) {
+            self.lstm.ready_output_cache(
+                last_state_cache,
+                &mut cache.lstm_output_cache
+            );
+
+            self.output_layer.ready(
+                &cache.lstm_output_cache.output,
+                None,
+                &mut cache.output_layer_cache
+            )
+        }
+    }
+
+    /// Accumulates gradient.
+    ///
+    /// - `output_error` : Backpropagated ouput error.
+    /// - `cache` : Cache.
+    /// - `input_error` : Error to backpropagate for previous output error.
+    /// - `prev_state_error` : Error to backpropagate for previous state error.
+    pub fn study(
+        &mut self,
+        output_error: &MathVec<OUT>,
+        cache: &MlEncoderCache<OUT, MIDDLE, IN>,
+        input_error: &mut [MathVec<IN>],
+        prev_state_error: &mut MathVec<MIDDLE>
+    ) {
+        let mut iter = cache.lstm_state_caches().iter().zip(
+            input_error.iter_mut()
+        ).rev();
+
+        self.tmp_state_error.clear();
+
+        if let Some((lstm_state_cache, input_error)) = iter.next() {
+            self.output_layer.study(
+                &output_error,
+                None,
+                &cache.output_layer_cache,
+                &mut self.tmp_middle_output_error,
+                None
+            );
+
+            self.lstm.study(
+                &self.tmp_middle_output_error,
+                &self.tmp_state_error,
+                &lstm_state_cache,
+                &cache.lstm_output_cache,
+                input_error,
+                prev_state_error
+            );
+        }
+
+        self.tmp_state_error.copy_from(prev_state_error);
+
+        iter.for_each(|(lstm_state_cache, input_error)| {
+            self.lstm.study_state(
+                &self.tmp_state_error,
+                lstm_state_cache,
+                input_error,
+                prev_state_error
+            );
+
+            self.tmp_state_error.copy_from(prev_state_error);
+        });
+    }
+
+    /// Updates weights with learning rate from [`LrScheduler`], and advances step of scheduler.
+    ///
+    /// - `scheduler` : Scheduler of learning rate.
+    #[inline]
+    pub fn update_with_scheduler(&mut self, scheduler: &mut LrScheduler) {
+        self.update(scheduler.next_rate());
+    }
+
+    /// Updates weights.
+    ///
+    /// - `rate` : Learning rate.
+    #[inline]
+    pub fn update(&mut self, rate: f32) {
+        self.lstm.update(rate);
+        self.output_layer.update(rate);
+    }
+
+    /// Accesses each immutable total gradient with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_total_grad<F>(
+        &self,
+        mut f: F
+    ) where F: FnMut(&f32) {
+        self.lstm.for_each_total_grad(|val| {f(val)});
+        self.output_layer.for_each_grad(&mut f);
+    }
+
+    /// Accesses each mutable total gradient with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_total_grad_mut<F>(
+        &mut self,
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.lstm.for_each_total_grad_mut(|val| {f(val)});
+        self.output_layer.for_each_grad_mut(&mut f);
+    }
+
+    /// Clips each total gradient into `[-limit, limit]`.
+    ///
+    /// - `limit` : Limit of absolute value. Negative value is treated as its absolute value. If NaN, nothing is clipped.
+    #[inline]
+    pub fn clip_grad_by_value(&mut self, limit: f32) {
+        clip_by_value(limit, |f| self.for_each_total_grad_mut(f));
+    }
+
+    /// Scales all total gradients so that L2 norm of them is `max_norm` or less.
+    ///
+    /// - `max_norm` : Max L2 norm. Negative value is treated as its absolute value.
+    /// - _Return_ : L2 norm before clipping. If it is not finite, nothing is scaled.
+    #[inline]
+    pub fn clip_grad_by_global_norm(&mut self, max_norm: f32) -> f32 {
+        clip_by_global_norm(max_norm, |f| self.for_each_total_grad_mut(f))
+    }
+
+    /// Adds gradient of L1 and L2 penalty of weights into total gradients of all layers.
+    ///
+    /// See [`MlLayer::add_weight_penalty()`] for details.
+    ///
+    /// - `l1` : Coefficient of L1 penalty.
+    /// - `l2` : Coefficient of L2 penalty.
+    #[inline]
+    pub fn add_weight_penalty(&mut self, l1: f32, l2: f32) {
+        self.lstm.add_weight_penalty(l1, l2);
+        self.output_layer.add_weight_penalty(l1, l2);
+    }
+
+    /// Checks that all total gradients and all weights are finite.
+    ///
+    /// See [`ChobitAiError::NonFinite`] for order of checking.
+    /// If error is returned, you can abort machine learning, or skip the batch by clearing total gradients with [`for_each_total_grad_mut()`](Self::for_each_total_grad_mut()).
+    ///
+    /// - _Return_ : [`ChobitAiError::NonFinite`] if non-finite number is found.
+    pub fn check_finite(&self) -> Result<(), ChobitAiError> {
+        const NAMES: [&str; 9] = [
+            "lstm.main_layer",
+            "lstm.f_gate",
+            "lstm.i_gate",
+            "lstm.o_gate",
+            "lstm.main_layer_norm",
+            "lstm.f_gate_norm",
+            "lstm.i_gate_norm",
+            "lstm.o_gate_norm",
+            "lstm.state_norm"
+        ];
+
+        self.output_layer.check_grad("output_layer")?;
+        self.lstm.check_grad(&NAMES)?;
+
+        self.output_layer.check_weights("output_layer")?;
+        self.lstm.check_weights(&NAMES)?;
+
+        Ok(())
+    }
+
+    /// Writes full state for machine learning into buffer as binary data.
+    ///
+    /// See [`MlLayer::save_checkpoint()`] for details.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save_checkpoint(&self, buffer: &mut Vec<u8>) {
+        write_header(buffer, ModelKind::ChobitMlEncoder, &[OUT, MIDDLE, IN]);
+
+        self.lstm.write_state(buffer);
+        self.output_layer.write_state(buffer);
+    }
+
+    /// Creates ChobitMlEncoder from binary data written by [`save_checkpoint()`](Self::save_checkpoint()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : ChobitMlEncoder.
+    pub fn load_checkpoint(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(
+            ModelKind::ChobitMlEncoder,
+            &[("OUT", OUT), ("MIDDLE", MIDDLE), ("IN", IN)]
+        )?;
+
+        let mut ret =
+            Self::new(ChobitEncoder::<OUT, MIDDLE, IN>::new(Activation::Linear));
+
+        ret.lstm = MlLstm::<MIDDLE, IN>::read_state(&mut reader)?;
+        ret.output_layer = MlLayer::<OUT, MIDDLE>::read_state(&mut reader)?;
+
+        Ok(ret)
+    }
+}
+
+/// Encoder from sequence data to fixed length data with stacked [`Lstm`].
+///
+/// At each step, output of each Lstm is input of the Lstm above it. Output of the top Lstm at the last step is input of output layer.
+///
+/// - `OUT` : Dimension of output.
+/// - `MIDDLE` : Dimension of hidden layers.
+/// - `IN` : Dimension of input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChobitStackedEncoder<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> {
+    first_layer: Lstm<MIDDLE, IN>,
+    upper_layers: Vec<Lstm<MIDDLE, MIDDLE>>,
+    output_layer: Layer<OUT, MIDDLE>,
+
+    prev_state: MathVec<MIDDLE>,
+    states: Vec<MathVec<MIDDLE>>,
+
+    middle_input: MathVec<MIDDLE>,
+    middle_output: MathVec<MIDDLE>,
+    tmpbuf: MathVec<MIDDLE>
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> ChobitStackedEncoder<OUT, MIDDLE, IN> {
+    /// Creates ChobitStackedEncoder.
+    ///
+    /// - `depth` : Number of Lstm. If `0`, it is regarded as `1`.
+    /// - `activation` : Activation function for output layer.
+    /// - _Return_ : ChobitStackedEncoder.
+    #[inline]
+    pub fn new(depth: usize, activation: Activation) -> Self {
+        Self::with_layers(
+            Lstm::<MIDDLE, IN>::new(),
+            vec![Lstm::<MIDDLE, MIDDLE>::new(); depth.saturating_sub(1)],
+            Layer::<OUT, MIDDLE>::new(activation, false)
+        )
+    }
+
+    /// Creates ChobitStackedEncoder with activation functions.
+    ///
+    /// - `depth` : Number of Lstm. If `0`, it is regarded as `1`.
+    /// - `hidden` : Activation function for main layers of all Lstm and for their states before output gate. (See [`Lstm::with_activations()`].)
+    /// - `output` : Activation function for output layer.
+    /// - _Return_ : ChobitStackedEncoder.
+    #[inline]
+    pub fn with_activations(
+        depth: usize,
+        hidden: Activation,
+        output: Activation
+    ) -> Self {
+        Self::with_layers(
+            Lstm::<MIDDLE, IN>::with_activations(
+                hidden,
+                Activation::Sigmoid,
+                hidden
+            ),
+            vec![
+                Lstm::<MIDDLE, MIDDLE>::with_activations(
+                    hidden,
+                    Activation::Sigmoid,
+                    hidden
+                );
+                depth.saturating_sub(1)
+            ],
+            Layer::<OUT, MIDDLE>::new(output, false)
+        )
+    }
+
+    /// Creates ChobitStackedEncoder with layers.
+    ///
+    /// - `first_layer` : Bottom Lstm which inputs data.
+    /// - `upper_layers` : Lstm stacked on `first_layer` from the bottom.
+    /// - `output_layer` : Output layer. State weights of it are not used.
+    /// - _Return_ : ChobitStackedEncoder.
+    #[inline]
+    pub fn with_layers(
+        first_layer: Lstm<MIDDLE, IN>,
+        upper_layers: Vec<Lstm<MIDDLE, MIDDLE>>,
+        output_layer: Layer<OUT, MIDDLE>
+    ) -> Self {
+        let depth = upper_layers.len() + 1;
+
+        Self {
+            first_layer: first_layer,
+            upper_layers: upper_layers,
+            output_layer: output_layer,
+
+            prev_state: MathVec::<MIDDLE>::new(),
+            states: vec![MathVec::<MIDDLE>::new(); depth],
+
+            middle_input: MathVec::<MIDDLE>::new(),
+            middle_output: MathVec::<MIDDLE>::new(),
+            tmpbuf: MathVec::<MIDDLE>::new()
+        }
+    }
+
+    /// Gets number of Lstm.
+    ///
+    /// - _Return_ : Number of Lstm.
+    #[inline]
+    pub fn depth(&self) -> usize {self.upper_layers.len() + 1}
+
+    /// Gets immutable bottom Lstm.
+    ///
+    /// - _Return_ : Bottom Lstm.
+    #[inline]
+    pub fn first_layer(&self) -> &Lstm<MIDDLE, IN> {&self.first_layer}
+
+    /// Gets mutable bottom Lstm.
+    ///
+    /// - _Return_ : Bottom Lstm.
+    #[inline]
+    pub fn first_layer_mut(&mut self) -> &mut Lstm<MIDDLE, IN> {
+        &mut self.first_layer
+    }
+
+    /// Gets immutable Lstm stacked on the bottom Lstm.
+    ///
+    /// - _Return_ : Lstm from the bottom.
+    #[inline]
+    pub fn upper_layers(&self) -> &[Lstm<MIDDLE, MIDDLE>] {&self.upper_layers}
+
+    /// Gets mutable Lstm stacked on the bottom Lstm.
+    ///
+    /// - _Return_ : Lstm from the bottom.
+    #[inline]
+    pub fn upper_layers_mut(&mut self) -> &mut [Lstm<MIDDLE, MIDDLE>] {
+        &mut self.upper_layers
+    }
+
+    /// Gets immutable output layer.
+    ///
+    /// - _Return_ : Output layer.
+    #[inline]
+    pub fn output_layer(&self) -> &Layer<OUT, MIDDLE> {&self.output_layer}
+
+    /// Gets mutable output layer.
+    ///
+    /// - _Return_ : Output layer.
+    #[inline]
+    pub fn output_layer_mut(&mut self) -> &mut Layer<OUT, MIDDLE> {
+        &mut self.output_layer
+    }
+
+    /// Initializes weights with [`Initializer`].
+    ///
+    /// Each Lstm is initialized by [`Lstm::init_weights()`] with `initializer` for input weights and [`Initializer::Orthogonal`] for state weights.
+    ///
+    /// - `initializer` : Initializer for output layer and input weights of Lstm.
+    /// - `rand` : Closure that returns uniform random number in `[0.0, 1.0)`.
+    pub fn init_weights<F>(&mut self, initializer: Initializer, mut rand: F)
+    where F: FnMut() -> f32 {
+        self.first_layer.init_weights(
+            initializer,
+            Initializer::Orthogonal,
+            &mut rand
+        );
+
+        self.upper_layers.iter_mut().for_each(|layer| {
+            layer.init_weights(
+                initializer,
+                Initializer::Orthogonal,
+                &mut rand
+            );
+        });
+
+        self.output_layer.init_weights(initializer, &mut rand);
+    }
+
+    /// Gets immutable states of all Lstm from the bottom.
+    ///
+    /// These should be initialized before the first [`input_next()`](Self::input_next).
+    ///
+    /// - _Return_ : States.
+    #[inline]
+    pub fn states(&self) -> &[MathVec<MIDDLE>] {&self.states}
+
+    /// Gets mutable states of all Lstm from the bottom.
+    ///
+    /// These should be initialized before the first [`input_next()`](Self::input_next).
+    ///
+    /// - _Return_ : States.
+    #[inline]
+    pub fn states_mut(&mut self) -> &mut [MathVec<MIDDLE>] {
+        &mut self.states
+    }
+
+    /// Accesses each immutable weight with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_weight<F>(&self, mut f: F) where F: FnMut(&f32) {
+        self.first_layer.for_each_weight(|val| {f(val)});
+
+        self.upper_layers.iter().for_each(|layer| {
+            layer.for_each_weight(|val| {f(val)});
+        });
+
+        self.output_layer.for_each_weight(|val| {f(val)});
+    }
+
+    /// Accesses each mutable weight with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_weight_mut<F>(
+        &mut self,
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.first_layer.for_each_weight_mut(|val| {f(val)});
+
+        self.upper_layers.iter_mut().for_each(|layer| {
+            layer.for_each_weight_mut(|val| {f(val)});
+        });
+
+        self.output_layer.for_each_weight_mut(|val| {f(val)});
+    }
+
+    /// Input next data.
+    ///
+    /// - `input` : Next data.
+    pub fn input_next(&mut self, input: &MathVec<IN>) {
+        let (first_state, upper_states) = self.states.split_at_mut(1);
+
+        self.prev_state.copy_from(&first_state[0]);
+
+        self.first_layer.calc(
+            input,
+            &self.prev_state,
+            &mut self.middle_output,
+            &mut first_state[0],
+            &mut self.tmpbuf
+        );
+
+        self.upper_layers.iter().zip(
+            upper_states.iter_mut()
+        ).for_each(|(layer, state)| {
+            self.middle_input.copy_from(&self.middle_output);
+            self.prev_state.copy_from(state);
+
+            layer.calc(
+                &self.middle_input,
+                &self.prev_state,
+                &mut self.middle_output,
+                state,
+                &mut self.tmpbuf
+            );
+        });
+    }
+
+    /// Output data calculated by output of the top Lstm at last input.
+    ///
+    /// - `output` : Buffer for output.
+    #[inline]
+    pub fn output(&self, output: &mut MathVec<OUT>) {
+        self.output_layer.calc(&self.middle_output, None, output);
+    }
+
+    /// Writes this encoder into buffer as binary data.
+    ///
+    /// States are not written. See [`ChobitAi::save()`] for details.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save(&self, buffer: &mut Vec<u8>) {
+        write_header(
+            buffer,
+            ModelKind::ChobitStackedEncoder,
+            &[OUT, MIDDLE, IN]
+        );
+        write_u32(buffer, self.upper_layers.len() as u32);
+
+        self.first_layer.write_descriptor(buffer);
+        self.upper_layers.iter().for_each(|layer| {
+            layer.write_descriptor(buffer);
+        });
+        self.output_layer.write_descriptor(buffer);
+
+        self.first_layer.write_body(buffer);
+        self.upper_layers.iter().for_each(|layer| {
+            layer.write_body(buffer);
+        });
+        self.output_layer.weights.write_body(buffer);
+    }
+
+    /// Creates ChobitStackedEncoder from binary data written by [`save()`](Self::save()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : ChobitStackedEncoder.
+    pub fn load(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(
+            ModelKind::ChobitStackedEncoder,
+            &[("OUT", OUT), ("MIDDLE", MIDDLE), ("IN", IN)]
+        )?;
+        let upper_len = reader.read_u32()? as usize;
+
+        let mut first_layer = Lstm::<MIDDLE, IN>::read_descriptor(&mut reader)?;
+        let mut upper_layers = Vec::<Lstm<MIDDLE, MIDDLE>>::new();
+        for _ in 0..upper_len {
+            upper_layers.push(
+                Lstm::<MIDDLE, MIDDLE>::read_descriptor(&mut reader)?
+            );
+        }
+        let mut output_layer =
+            Layer::<OUT, MIDDLE>::read_descriptor(&mut reader)?;
+
+        first_layer.read_body(&mut reader)?;
+        for layer in upper_layers.iter_mut() {
+            layer.read_body(&mut reader)?;
+        }
+        output_layer.weights.read_body(&mut reader)?;
+
+        Ok(Self::with_layers(first_layer, upper_layers, output_layer))
+    }
+}
+
+/// Cache for [`ChobitMlStackedEncoder`].
+///
+/// - `OUT` : `OUT` of [`ChobitMlStackedEncoder`].
+/// - `MIDDLE` : `MIDDLE` of [`ChobitMlStackedEncoder`].
+/// - `IN` : `IN` of [`ChobitMlStackedEncoder`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlStackedEncoderCache<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> {
+    first_layer_caches: Vec<(
+        MlLstmStateCache<MIDDLE, IN>,
+        MlLstmOutputCache<MIDDLE, IN>
+    )>,
+    upper_layer_caches: Vec<Vec<(
+        MlLstmStateCache<MIDDLE, MIDDLE>,
+        MlLstmOutputCache<MIDDLE, MIDDLE>
+    )>>,
+    caches_len: usize,
+
+    output_layer_cache: MlCache<OUT, MIDDLE>
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> MlStackedEncoderCache<OUT, MIDDLE, IN> {
+    /// Creates MlStackedEncoderCache.
+    ///
+    /// - `depth` : Number of Lstm.
+    /// - `capacity` : Capacity of length of input sequence.
+    /// - _Return_ : MlStackedEncoderCache.
+    #[inline]
+    pub fn new(depth: usize, capacity: usize) -> Self {
+        Self {
+            first_layer_caches: vec![
+                (
+                    MlLstmStateCache::<MIDDLE, IN>::new(),
+                    MlLstmOutputCache::<MIDDLE, IN>::new()
+                );
+                capacity
+            ],
+            upper_layer_caches: vec![
+                vec![
+                    (
+                        MlLstmStateCache::<MIDDLE, MIDDLE>::new(),
+                        MlLstmOutputCache::<MIDDLE, MIDDLE>::new()
+                    );
+                    capacity
+                ];
+                depth.saturating_sub(1)
+            ],
+            caches_len: 0,
+
+            output_layer_cache: MlCache::<OUT, MIDDLE>::new()
+        }
+    }
+
+    /// Calculates output error.
+    ///
+    /// See [`MlEncoderCache::calc_output_error()`] for details.
+    ///
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error(
+        &self,
+        train_out: &MathVec<OUT>,
+        output_error: &mut MathVec<OUT>
+    ) {
+        output_error.copy_from(&self.output_layer_cache.output);
+        *output_error -= train_out;
+    }
+
+    /// Calculates output error with loss function.
+    ///
+    /// - `loss` : Loss function.
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error_with_loss(
+        &self,
+        loss: &Loss,
+        train_out: &MathVec<OUT>,
+        output_error: &mut MathVec<OUT>
+    ) {
+        loss.calc_error(
+            &self.output_layer_cache.output,
+            train_out,
+            output_error
+        );
+    }
+
+    /// Gets caches of the bottom Lstm for each step.
+    ///
+    /// - _Return_ : Slice of caches.
+    #[inline]
+    pub fn first_layer_caches(&self) -> &[(
+        MlLstmStateCache<MIDDLE, IN>,
+        MlLstmOutputCache<MIDDLE, IN>
+    )] {
+        &self.first_layer_caches[..self.caches_len]
+    }
+
+    /// Gets caches of an upper Lstm for each step.
+    ///
+    /// - `index` : Index of upper Lstm from the bottom. (`0` is the Lstm just above the bottom Lstm.)
+    /// - _Return_ : Slice of caches. If `index` is out of range, `None`.
+    #[inline]
+    pub fn upper_layer_caches(&self, index: usize) -> Option<&[(
+        MlLstmStateCache<MIDDLE, MIDDLE>,
+        MlLstmOutputCache<MIDDLE, MIDDLE>
+    )]> {
+        self.upper_layer_caches.get(index).map(
+            |caches| &caches[..self.caches_len]
+        )
+    }
+
+    /// Gets ['MlCache'] of output layer.
+    ///
+    /// - _Return_ : [`MlCache`].
+    #[inline]
+    pub fn output_layer_cache(&self) -> &MlCache<OUT, MIDDLE> {
+        &self.output_layer_cache
+    }
+
+    /// Gets output.
+    ///
+    /// - _Return_ : output.
+    #[inline]
+    pub fn output(&self) -> &MathVec<OUT> {
+        &self.output_layer_cache.output
+    }
+}
+
+/// Wrapper of [`ChobitStackedEncoder`] for machine learning.
+///
+/// See [`ChobitStackedEncoder`] for details.
+///
+/// - `OUT` : Dimension of output.
+/// - `MIDDLE` : Dimension of hidden layers.
+/// - `IN` : Dimension of input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChobitMlStackedEncoder<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> {
+    first_layer: MlLstm<MIDDLE, IN>,
+    upper_layers: Vec<MlLstm<MIDDLE, MIDDLE>>,
+    output_layer: MlLayer<OUT, MIDDLE>,
+
+    prev_state: MathVec<MIDDLE>,
+
+    middle_output_errors: Vec<MathVec<MIDDLE>>,
+    middle_input_errors: Vec<MathVec<MIDDLE>>,
+    tmp_state_error: MathVec<MIDDLE>,
+    tmp_prev_state_error: MathVec<MIDDLE>,
+
+    original_prev_state: MathVec<MIDDLE>,
+    original_states: Vec<MathVec<MIDDLE>>,
+
+    original_middle_input: MathVec<MIDDLE>,
+    original_middle_output: MathVec<MIDDLE>,
+    original_tmpbuf: MathVec<MIDDLE>
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> ChobitMlStackedEncoder<OUT, MIDDLE, IN> {
+    /// Creates ChobitMlStackedEncoder.
+    ///
+    /// - `encoder` : Base [`ChobitStackedEncoder`].
+    /// - _Return_ : ChobitMlStackedEncoder.
+    #[inline]
+    pub fn new(encoder: ChobitStackedEncoder<OUT, MIDDLE, IN>) -> Self {
+        let ChobitStackedEncoder::<OUT, MIDDLE, IN> {
+            first_layer,
+            upper_layers,
+            output_layer,
+            prev_state,
+            states,
+            middle_input,
+            middle_output,
+            tmpbuf
+        } = encoder;
+
+        Self {
+            first_layer: MlLstm::<MIDDLE, IN>::new(first_layer),
+            upper_layers: upper_layers.into_iter().map(
+                MlLstm::<MIDDLE, MIDDLE>::new
+            ).collect(),
+            output_layer: MlLayer::<OUT, MIDDLE>::new(output_layer),
+
+            prev_state: MathVec::<MIDDLE>::new(),
+
+            middle_output_errors: Vec::<MathVec<MIDDLE>>::new(),
+            middle_input_errors: Vec::<MathVec<MIDDLE>>::new(),
+            tmp_state_error: MathVec::<MIDDLE>::new(),
+            tmp_prev_state_error: MathVec::<MIDDLE>::new(),
+
+            original_prev_state: prev_state,
+            original_states: states,
+            original_middle_input: middle_input,
+            original_middle_output: middle_output,
+            original_tmpbuf: tmpbuf
+        }
+    }
+
+    /// Drops base [`ChobitStackedEncoder`].
+    ///
+    /// - _Return_ : [`ChobitStackedEncoder`].
+    #[inline]
+    pub fn drop(self) -> ChobitStackedEncoder<OUT, MIDDLE, IN> {
+        let Self {
+            first_layer,
+            upper_layers,
+            output_layer,
+            original_prev_state,
+            original_states,
+            original_middle_input,
+            original_middle_output,
+            original_tmpbuf,
+            ..
+        } = self;
+
+        ChobitStackedEncoder::<OUT, MIDDLE, IN> {
+            first_layer: first_layer.drop(),
+            upper_layers: upper_layers.into_iter().map(
+                |layer| layer.drop()
+            ).collect(),
+            output_layer: output_layer.drop(),
+            prev_state: original_prev_state,
+            states: original_states,
+            middle_input: original_middle_input,
+            middle_output: original_middle_output,
+            tmpbuf: original_tmpbuf
+        }
+    }
+
+    /// Gets number of Lstm.
+    ///
+    /// - _Return_ : Number of Lstm.
+    #[inline]
+    pub fn depth(&self) -> usize {self.upper_layers.len() + 1}
+
+    /// Clears internal data for study.
+    #[inline]
+    pub fn clear_study_data(&mut self) {
+        self.first_layer.clear_study_data();
+        self.upper_layers.iter_mut().for_each(|layer| {
+            layer.clear_study_data();
+        });
+        self.output_layer.clear_study_data();
+    }
+
+    /// Sets [`Optimizer`] to all layers.
+    ///
+    /// See [`MlLayer::set_optimizer()`] for details.
+    ///
+    /// - `optimizer` : Optimizer.
+    #[inline]
+    pub fn set_optimizer(&mut self, optimizer: Optimizer) {
+        self.first_layer.set_optimizer(optimizer);
+        self.upper_layers.iter_mut().for_each(|layer| {
+            layer.set_optimizer(optimizer);
+        });
+        self.output_layer.set_optimizer(optimizer);
+    }
+
+    /// Writes information on [`MlStackedEncoderCache`] for [`study()`](Self::study()).
+    ///
+    /// - `train_in` : Input data sequence.
+    /// - `prev_states` : Previous states of all Lstm from the bottom. If it is shorter than depth, previous states of the rest are zero.
+    /// - `cache` : Cache.
+    pub fn ready(
+        &mut self,
+        train_in: &[MathVec<IN>],
+        prev_states: &[MathVec<MIDDLE>],
+        cache: &mut MlStackedEncoderCache<OUT, MIDDLE, IN>
+    ) {
+        let len = train_in.len();
+
+        cache.caches_len = len;
+        if cache.first_layer_caches.len() < len {
+            cache.first_layer_caches.resize(
+                len,
+                (
+                    MlLstmStateCache::<MIDDLE, IN>::new(),
+                    MlLstmOutputCache::<MIDDLE, IN>::new()
+                )
+            );
+        }
+        if cache.upper_layer_caches.len() < self.upper_layers.len() {
+            cache.upper_layer_caches.resize(
+                self.upper_layers.len(),
+                Vec::new()
+            );
+        }
+        cache.upper_layer_caches.iter_mut().for_each(|caches| {
+            if caches.len() < len {
+                caches.resize(
+                    len,
+                    (
+                        MlLstmStateCache::<MIDDLE, MIDDLE>::new(),
+                        MlLstmOutputCache::<MIDDLE, MIDDLE>::new()
+                    )
+                );
+            }
+        });
+
+        Self::load_prev_state(prev_states, 0, &mut self.prev_state);
+
+        train_in.iter().zip(
+            cache.first_layer_caches.iter_mut()
+        ).for_each(|(train_in_one, (state_cache, output_cache))| {
+            self.first_layer.ready_state_cache(
+                train_in_one,
+                &self.prev_state,
+                state_cache
+            );
+            self.first_layer.ready_output_cache(state_cache, output_cache);
+
+            self.prev_state.copy_from(&state_cache.state);
+        });
+
+        for i in 0..self.upper_layers.len() {
+            Self::load_prev_state(prev_states, i + 1, &mut self.prev_state);
+
+            let (lower_caches, upper_caches) =
+                cache.upper_layer_caches.split_at_mut(i);
+
+            match lower_caches.last() {
+                Some(lower_caches) => Self::ready_upper_layer(
+                    &self.upper_layers[i],
+                    lower_caches[..len].iter().map(|(_, cache)| &cache.output),
+                    &mut self.prev_state,
+                    &mut upper_caches[0][..len]
+                ),
+
+                None => Self::ready_upper_layer(
+                    &self.upper_layers[i],
+                    cache.first_layer_caches[..len].iter().map(
+                        |(_, cache)| &cache.output
+                    ),
+                    &mut self.prev_state,
+                    &mut upper_caches[0][..len]
+                )
+            }
+        }
+
+        let top_output = match cache.upper_layer_caches[
+            ..self.upper_layers.len()
+        ].last() {
+            Some(caches) => caches[..len].last().map(
+                |(_, cache)| &cache.output
+            ),
+
+            None => cache.first_layer_caches[..len].last().map(
+                |(_, cache)| &cache.output
+            )
+        };
+
+        if let Some(top_output) = top_output {
+            self.output_layer.ready(
+                top_output,
+                None,
+                &mut cache.output_layer_cache
+            );
+        }
+    }
+
+    #[inline]
+    fn load_prev_state(
+        prev_states: &[MathVec<MIDDLE>],
+        index: usize,
+        prev_state: &mut MathVec<MIDDLE>
+    ) {
+        match prev_states.get(index) {
+            Some(state) => prev_state.copy_from(state),
+            None => prev_state.clear()
+        }
+    }
+
+    fn ready_upper_layer<'a, I>(
+        layer: &MlLstm<MIDDLE, MIDDLE>,
+        inputs: I,
+        prev_state: &mut MathVec<MIDDLE>,
+        caches: &mut [(
+            MlLstmStateCache<MIDDLE, MIDDLE>,
+            MlLstmOutputCache<MIDDLE, MIDDLE>
+        )]
+    ) where I: Iterator<Item = &'a MathVec<MIDDLE>> {
+        inputs.zip(
+            caches.iter_mut()
+        ).for_each(|(input, (state_cache, output_cache))| {
+            layer.ready_state_cache(input, prev_state, state_cache);
+            layer.ready_output_cache(state_cache, output_cache);
+
+            prev_state.copy_from(&state_cache.state);
+        });
+    }
+
+    /// Accumulates gradient.
+    ///
+    /// - `output_error` : Backpropagated ouput error.
+    /// - `cache` : Cache.
+    /// - `input_error` : Error to backpropagate for previous output error.
+    /// - `prev_state_errors` : Errors to backpropagate for previous states of all Lstm from the bottom. If it is shorter than depth, errors of the rest are not written.
+    pub fn study(
+        &mut self,
+        output_error: &MathVec<OUT>,
+        cache: &MlStackedEncoderCache<OUT, MIDDLE, IN>,
+        input_error: &mut [MathVec<IN>],
+        prev_state_errors: &mut [MathVec<MIDDLE>]
+    ) {
+        let len = cache.caches_len;
+
+        if len == 0 {
+            return;
+        }
+
+        if self.middle_output_errors.len() < len {
+            self.middle_output_errors.resize(len, MathVec::<MIDDLE>::new());
+            self.middle_input_errors.resize(len, MathVec::<MIDDLE>::new());
+        }
+
+        // Only output of the top Lstm at the last step has error.
+        self.middle_output_errors[..len].iter_mut().for_each(|error| {
+            error.clear();
+        });
+
+        self.output_layer.study(
+            output_error,
+            None,
+            &cache.output_layer_cache,
+            &mut self.middle_output_errors[len - 1],
+            None
+        );
+
+        for i in (0..self.upper_layers.len()).rev() {
+            let layer = &mut self.upper_layers[i];
+
+            self.tmp_state_error.clear();
+
+            cache.upper_layer_caches[i][..len].iter().zip(
+                self.middle_output_errors.iter()
+            ).zip(
+                self.middle_input_errors.iter_mut()
+            ).rev().for_each(
+                |(((state_cache, output_cache), output_error), input_error)| {
+                    layer.study(
+                        output_error,
+                        &self.tmp_state_error,
+                        state_cache,
+                        output_cache,
+                        input_error,
+                        &mut self.tmp_prev_state_error
+                    );
+
+                    self.tmp_state_error.copy_from(&self.tmp_prev_state_error);
+                }
+            );
+
+            if let Some(error) = prev_state_errors.get_mut(i + 1) {
+                error.copy_from(&self.tmp_state_error);
+            }
+
+            // Input errors of this Lstm are output errors of the lower one.
+            mem::swap(
+                &mut self.middle_output_errors,
+                &mut self.middle_input_errors
+            );
+        }
+
+        self.tmp_state_error.clear();
+
+        cache.first_layer_caches[..len].iter().zip(
+            self.middle_output_errors.iter()
+        ).zip(
+            input_error.iter_mut()
+        ).rev().for_each(
+            |(((state_cache, output_cache), output_error), input_error)| {
+                self.first_layer.study(
+                    output_error,
+                    &self.tmp_state_error,
+                    state_cache,
+                    output_cache,
+                    input_error,
+                    &mut self.tmp_prev_state_error
+                );
+
+                self.tmp_state_error.copy_from(&self.tmp_prev_state_error);
+            }
+        );
+
+        if let Some(error) = prev_state_errors.get_mut(0) {
+            error.copy_from(&self.tmp_state_error);
+        }
+    }
+
+    /// Updates weights with learning rate from [`LrScheduler`], and advances step of scheduler.
+    ///
+    /// - `scheduler` : Scheduler of learning rate.
+    #[inline]
+    pub fn update_with_scheduler(&mut self, scheduler: &mut LrScheduler) {
+        self.update(scheduler.next_rate());
+    }
+
+    /// Updates weights.
+    ///
+    /// - `rate` : Learning rate.
+    #[inline]
+    pub fn update(&mut self, rate: f32) {
+        self.first_layer.update(rate);
+        self.upper_layers.iter_mut().for_each(|layer| {layer.update(rate);});
+        self.output_layer.update(rate);
+    }
+
+    /// Accesses each immutable total gradient with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_total_grad<F>(
+        &self,
+        mut f: F
+    ) where F: FnMut(&f32) {
+        self.first_layer.for_each_total_grad(|val| {f(val)});
+
+        self.upper_layers.iter().for_each(|layer| {
+            layer.for_each_total_grad(|val| {f(val)});
+        });
+
+        self.output_layer.for_each_grad(&mut f);
+    }
+
+    /// Accesses each mutable total gradient with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_total_grad_mut<F>(
+        &mut self,
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.first_layer.for_each_total_grad_mut(|val| {f(val)});
+
+        self.upper_layers.iter_mut().for_each(|layer| {
+            layer.for_each_total_grad_mut(|val| {f(val)});
+        });
+
+        self.output_layer.for_each_grad_mut(&mut f);
+    }
+
+    /// Clips each total gradient into `[-limit, limit]`.
+    ///
+    /// - `limit` : Limit of absolute value. Negative value is treated as its absolute value. If NaN, nothing is clipped.
+    #[inline]
+    pub fn clip_grad_by_value(&mut self, limit: f32) {
+        clip_by_value(limit, |f| self.for_each_total_grad_mut(f));
+    }
+
+    /// Scales all total gradients so that L2 norm of them is `max_norm` or less.
+    ///
+    /// - `max_norm` : Max L2 norm. Negative value is treated as its absolute value.
+    /// - _Return_ : L2 norm before clipping. If it is not finite, nothing is scaled.
+    #[inline]
+    pub fn clip_grad_by_global_norm(&mut self, max_norm: f32) -> f32 {
+        clip_by_global_norm(max_norm, |f| self.for_each_total_grad_mut(f))
+    }
+
+    /// Adds gradient of L1 and L2 penalty of weights into total gradients of all layers.
+    ///
+    /// See [`MlLayer::add_weight_penalty()`] for details.
+    ///
+    /// - `l1` : Coefficient of L1 penalty.
+    /// - `l2` : Coefficient of L2 penalty.
+    #[inline]
+    pub fn add_weight_penalty(&mut self, l1: f32, l2: f32) {
+        self.first_layer.add_weight_penalty(l1, l2);
+        self.upper_layers.iter_mut().for_each(|layer| {
+            layer.add_weight_penalty(l1, l2);
+        });
+        self.output_layer.add_weight_penalty(l1, l2);
+    }
+
+    /// Checks that all total gradients and all weights are finite.
+    ///
+    /// See [`ChobitMlEncoder::check_finite()`] for details. Names of upper Lstm don't include their index.
+    ///
+    /// - _Return_ : [`ChobitAiError::NonFinite`] if non-finite number is found.
+    pub fn check_finite(&self) -> Result<(), ChobitAiError> {
+        const FIRST_NAMES: [&str; 9] = [
+            "first_layer.main_layer",
+            "first_layer.f_gate",
+            "first_layer.i_gate",
+            "first_layer.o_gate",
+            "first_layer.main_layer_norm",
+            "first_layer.f_gate_norm",
+            "first_layer.i_gate_norm",
+            "first_layer.o_gate_norm",
+            "first_layer.state_norm"
+        ];
+        const UPPER_NAMES: [&str; 9] = [
+            "upper_layers.main_layer",
+            "upper_layers.f_gate",
+            "upper_layers.i_gate",
+            "upper_layers.o_gate",
+            "upper_layers.main_layer_norm",
+            "upper_layers.f_gate_norm",
+            "upper_layers.i_gate_norm",
+            "upper_layers.o_gate_norm",
+            "upper_layers.state_norm"
+        ];
+
+        self.output_layer.check_grad("output_layer")?;
+        for layer in self.upper_layers.iter().rev() {
+            layer.check_grad(&UPPER_NAMES)?;
+        }
+        self.first_layer.check_grad(&FIRST_NAMES)?;
+
+        self.output_layer.check_weights("output_layer")?;
+        for layer in self.upper_layers.iter().rev() {
+            layer.check_weights(&UPPER_NAMES)?;
+        }
+        self.first_layer.check_weights(&FIRST_NAMES)?;
+
+        Ok(())
+    }
+
+    /// Writes full state for machine learning into buffer as binary data.
+    ///
+    /// See [`MlLayer::save_checkpoint()`] for details.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save_checkpoint(&self, buffer: &mut Vec<u8>) {
+        write_header(
+            buffer,
+            ModelKind::ChobitMlStackedEncoder,
+            &[OUT, MIDDLE, IN]
+        );
+        write_u32(buffer, self.upper_layers.len() as u32);
+
+        self.first_layer.write_state(buffer);
+        self.upper_layers.iter().for_each(|layer| {
+            layer.write_state(buffer);
+        });
+        self.output_layer.write_state(buffer);
+    }
+
+    /// Creates ChobitMlStackedEncoder from binary data written by [`save_checkpoint()`](Self::save_checkpoint()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : ChobitMlStackedEncoder.
+    pub fn load_checkpoint(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(
+            ModelKind::ChobitMlStackedEncoder,
+            &[("OUT", OUT), ("MIDDLE", MIDDLE), ("IN", IN)]
+        )?;
+        let upper_len = reader.read_u32()? as usize;
+
+        let encoder = ChobitStackedEncoder::<OUT, MIDDLE, IN>::new(
+            upper_len + 1,
+            Activation::Linear
+        );
+        let mut ret = Self::new(encoder);
+
+        ret.first_layer = MlLstm::<MIDDLE, IN>::read_state(&mut reader)?;
+        for layer in ret.upper_layers.iter_mut() {
+            *layer = MlLstm::<MIDDLE, MIDDLE>::read_state(&mut reader)?;
+        }
+        ret.output_layer = MlLayer::<OUT, MIDDLE>::read_state(&mut reader)?;
+
+        Ok(ret)
+    }
+}
+
+/// Bidirectional encoder from sequence data to fixed length data.
+///
+/// Forward Lstm inputs sequence from the head, and backward Lstm inputs it from the tail. Their outputs at the last step are concatenated and input to output layer.
+///
+/// - `OUT` : Dimension of output.
+/// - `CAT` : Dimension of concatenated outputs of both Lstm. It must be `MIDDLE * 2`, otherwise compile error occurs.
+/// - `MIDDLE` : Dimension of hidden layers.
+/// - `IN` : Dimension of input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChobitBiEncoder<
+    const OUT: usize,
+    const CAT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> {
+    forward_layer: Lstm<MIDDLE, IN>,
+    backward_layer: Lstm<MIDDLE, IN>,
+    output_layer: Layer<OUT, CAT>,
+
+    prev_state: MathVec<MIDDLE>,
+    state: MathVec<MIDDLE>,
+    middle_output: MathVec<MIDDLE>,
+    concat: MathVec<CAT>,
+    tmpbuf: MathVec<MIDDLE>
+}
+
+impl<
+    const OUT: usize,
+    const CAT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> ChobitBiEncoder<OUT, CAT, MIDDLE, IN> {
+    // Head of output of backward Lstm in concatenated outputs.
+    const BACKWARD_HEAD: usize = {
+        assert!(CAT == MIDDLE * 2, "CAT must be MIDDLE * 2");
+
+        MIDDLE
+    };
+
+    /// Creates ChobitBiEncoder.
+    ///
+    /// - `activation` : Activation function for output layer.
+    /// - _Return_ : ChobitBiEncoder.
+    #[inline]
+    pub fn new(activation: Activation) -> Self {
+        Self::with_layers(
+            Lstm::<MIDDLE, IN>::new(),
+            Lstm::<MIDDLE, IN>::new(),
+            Layer::<OUT, CAT>::new(activation, false)
+        )
+    }
+
+    /// Creates ChobitBiEncoder with activation functions.
+    ///
+    /// - `hidden` : Activation function for main layers of both Lstm and for their states before output gate. (See [`Lstm::with_activations()`].)
+    /// - `output` : Activation function for output layer.
+    /// - _Return_ : ChobitBiEncoder.
+    #[inline]
+    pub fn with_activations(hidden: Activation, output: Activation) -> Self {
+        Self::with_layers(
+            Lstm::<MIDDLE, IN>::with_activations(
+                hidden,
+                Activation::Sigmoid,
+                hidden
+            ),
+            Lstm::<MIDDLE, IN>::with_activations(
+                hidden,
+                Activation::Sigmoid,
+                hidden
+            ),
+            Layer::<OUT, CAT>::new(output, false)
+        )
+    }
+
+    /// Creates ChobitBiEncoder with layers.
+    ///
+    /// - `forward_layer` : Lstm which inputs sequence from the head.
+    /// - `backward_layer` : Lstm which inputs sequence from the tail.
+    /// - `output_layer` : Output layer. State weights of it are not used.
+    /// - _Return_ : ChobitBiEncoder.
+    #[inline]
+    pub fn with_layers(
+        forward_layer: Lstm<MIDDLE, IN>,
+        backward_layer: Lstm<MIDDLE, IN>,
+        output_layer: Layer<OUT, CAT>
+    ) -> Self {
+        Self {
+            forward_layer: forward_layer,
+            backward_layer: backward_layer,
+            output_layer: output_layer,
+
+            prev_state: MathVec::<MIDDLE>::new(),
+            state: MathVec::<MIDDLE>::new(),
+            middle_output: MathVec::<MIDDLE>::new(),
+            concat: MathVec::<CAT>::new(),
+            tmpbuf: MathVec::<MIDDLE>::new()
+        }
+    }
+
+    /// Gets immutable forward Lstm.
+    ///
+    /// - _Return_ : Forward Lstm.
+    #[inline]
+    pub fn forward_layer(&self) -> &Lstm<MIDDLE, IN> {&self.forward_layer}
+
+    /// Gets mutable forward Lstm.
+    ///
+    /// - _Return_ : Forward Lstm.
+    #[inline]
+    pub fn forward_layer_mut(&mut self) -> &mut Lstm<MIDDLE, IN> {
+        &mut self.forward_layer
+    }
+
+    /// Gets immutable backward Lstm.
+    ///
+    /// - _Return_ : Backward Lstm.
+    #[inline]
+    pub fn backward_layer(&self) -> &Lstm<MIDDLE, IN> {&self.backward_layer}
+
+    /// Gets mutable backward Lstm.
+    ///
+    /// - _Return_ : Backward Lstm.
+    #[inline]
+    pub fn backward_layer_mut(&mut self) -> &mut Lstm<MIDDLE, IN> {
+        &mut self.backward_layer
+    }
+
+    /// Gets immutable output layer.
+    ///
+    /// - _Return_ : Output layer.
+    #[inline]
+    pub fn output_layer(&self) -> &Layer<OUT, CAT> {&self.output_layer}
+
+    /// Gets mutable output layer.
+    ///
+    /// - _Return_ : Output layer.
+    #[inline]
+    pub fn output_layer_mut(&mut self) -> &mut Layer<OUT, CAT> {
+        &mut self.output_layer
+    }
+
+    /// Initializes weights with [`Initializer`].
+    ///
+    /// Both Lstm are initialized by [`Lstm::init_weights()`] with `initializer` for input weights and [`Initializer::Orthogonal`] for state weights.
+    ///
+    /// - `initializer` : Initializer for output layer and input weights of Lstm.
+    /// - `rand` : Closure that returns uniform random number in `[0.0, 1.0)`.
+    pub fn init_weights<F>(&mut self, initializer: Initializer, mut rand: F)
+    where F: FnMut() -> f32 {
+        self.forward_layer.init_weights(
+            initializer,
+            Initializer::Orthogonal,
+            &mut rand
+        );
+        self.backward_layer.init_weights(
+            initializer,
+            Initializer::Orthogonal,
+            &mut rand
+        );
+
+        self.output_layer.init_weights(initializer, &mut rand);
+    }
+
+    /// Accesses each immutable weight with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_weight<F>(&self, mut f: F) where F: FnMut(&f32) {
+        self.forward_layer.for_each_weight(|val| {f(val)});
+        self.backward_layer.for_each_weight(|val| {f(val)});
+        self.output_layer.for_each_weight(|val| {f(val)});
+    }
+
+    /// Accesses each mutable weight with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_weight_mut<F>(
+        &mut self,
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.forward_layer.for_each_weight_mut(|val| {f(val)});
+        self.backward_layer.for_each_weight_mut(|val| {f(val)});
+        self.output_layer.for_each_weight_mut(|val| {f(val)});
+    }
+
+    /// Calculates output from whole sequence.
+    ///
+    /// States of both Lstm start from zero.
+    ///
+    /// - `input` : Input data sequence.
+    /// - `output` : Buffer for output.
+    pub fn calc(&mut self, input: &[MathVec<IN>], output: &mut MathVec<OUT>) {
+        Self::calc_direction(
+            &self.forward_layer,
+            input.iter(),
+            &mut self.prev_state,
+            &mut self.state,
+            &mut self.middle_output,
+            &mut self.tmpbuf
+        );
+        self.concat[..Self::BACKWARD_HEAD].copy_from_slice(&self.middle_output);
+
+        Self::calc_direction(
+            &self.backward_layer,
+            input.iter().rev(),
+            &mut self.prev_state,
+            &mut self.state,
+            &mut self.middle_output,
+            &mut self.tmpbuf
+        );
+        self.concat[Self::BACKWARD_HEAD..].copy_from_slice(&self.middle_output);
+
+        self.output_layer.calc(&self.concat, None, output);
+    }
+
+    fn calc_direction<'a, I>(
+        layer: &Lstm<MIDDLE, IN>,
+        input: I,
+        prev_state: &mut MathVec<MIDDLE>,
+        state: &mut MathVec<MIDDLE>,
+        output: &mut MathVec<MIDDLE>,
+        tmpbuf: &mut MathVec<MIDDLE>
+    ) where I: Iterator<Item = &'a MathVec<IN>> {
+        state.clear();
+        output.clear();
+
+        input.for_each(|input_one| {
+            prev_state.copy_from(state);
+
+            layer.calc(input_one, prev_state, output, state, tmpbuf);
+        });
+    }
+
+    /// Writes this encoder into buffer as binary data.
+    ///
+    /// See [`ChobitAi::save()`] for details.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save(&self, buffer: &mut Vec<u8>) {
+        write_header(
+            buffer,
+            ModelKind::ChobitBiEncoder,
+            &[OUT, CAT, MIDDLE, IN]
+        );
+
+        self.forward_layer.write_descriptor(buffer);
+        self.backward_layer.write_descriptor(buffer);
+        self.output_layer.write_descriptor(buffer);
+
+        self.forward_layer.write_body(buffer);
+        self.backward_layer.write_body(buffer);
+        self.output_layer.weights.write_body(buffer);
+    }
+
+    /// Creates ChobitBiEncoder from binary data written by [`save()`](Self::save()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : ChobitBiEncoder.
+    pub fn load(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(
+            ModelKind::ChobitBiEncoder,
+            &[("OUT", OUT), ("CAT", CAT), ("MIDDLE", MIDDLE), ("IN", IN)]
+        )?;
+
+        let mut forward_layer =
+            Lstm::<MIDDLE, IN>::read_descriptor(&mut reader)?;
+        let mut backward_layer =
+            Lstm::<MIDDLE, IN>::read_descriptor(&mut reader)?;
+        let mut output_layer = Layer::<OUT, CAT>::read_descriptor(&mut reader)?;
+
+        forward_layer.read_body(&mut reader)?;
+        backward_layer.read_body(&mut reader)?;
+        output_layer.weights.read_body(&mut reader)?;
+
+        Ok(Self::with_layers(forward_layer, backward_layer, output_layer))
+    }
+}
+
+/// Cache for [`ChobitMlBiEncoder`].
+///
+/// - `OUT` : `OUT` of [`ChobitMlBiEncoder`].
+/// - `CAT` : `CAT` of [`ChobitMlBiEncoder`].
+/// - `MIDDLE` : `MIDDLE` of [`ChobitMlBiEncoder`].
+/// - `IN` : `IN` of [`ChobitMlBiEncoder`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlBiEncoderCache<
+    const OUT: usize,
+    const CAT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> {
+    forward_state_caches: Vec<MlLstmStateCache<MIDDLE, IN>>,
+    backward_state_caches: Vec<MlLstmStateCache<MIDDLE, IN>>,
+    state_caches_len: usize,
+
+    forward_output_cache: MlLstmOutputCache<MIDDLE, IN>,
+    backward_output_cache: MlLstmOutputCache<MIDDLE, IN>,
+
+    concat: MathVec<CAT>,
+    output_layer_cache: MlCache<OUT, CAT>
+}
+
+impl<
+    const OUT: usize,
+    const CAT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> MlBiEncoderCache<OUT, CAT, MIDDLE, IN> {
+    /// Creates MlBiEncoderCache.
+    ///
+    /// - `capacity` : Capacity of length of input sequence.
+    /// - _Return_ : MlBiEncoderCache.
+    #[inline]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            forward_state_caches: vec![
+                MlLstmStateCache::<MIDDLE, IN>::new(); capacity
+            ],
+            backward_state_caches: vec![
+                MlLstmStateCache::<MIDDLE, IN>::new(); capacity
+            ],
+            state_caches_len: 0,
+
+            forward_output_cache: MlLstmOutputCache::<MIDDLE, IN>::new(),
+            backward_output_cache: MlLstmOutputCache::<MIDDLE, IN>::new(),
+
+            concat: MathVec::<CAT>::new(),
+            output_layer_cache: MlCache::<OUT, CAT>::new()
+        }
+    }
+
+    /// Calculates output error.
+    ///
+    /// See [`MlEncoderCache::calc_output_error()`] for details.
+    ///
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error(
+        &self,
+        train_out: &MathVec<OUT>,
+        output_error: &mut MathVec<OUT>
+    ) {
+        output_error.copy_from(&self.output_layer_cache.output);
+        *output_error -= train_out;
+    }
+
+    /// Calculates output error with loss function.
+    ///
+    /// - `loss` : Loss function.
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error_with_loss(
+        &self,
+        loss: &Loss,
+        train_out: &MathVec<OUT>,
+        output_error: &mut MathVec<OUT>
+    ) {
+        loss.calc_error(
+            &self.output_layer_cache.output,
+            train_out,
+            output_error
+        );
+    }
+
+    /// Gets slice of ['MlLstmStateCache'] of forward Lstm in order of input.
+    ///
+    /// - _Return_ : Slice of ['MlLstmStateCache'].
+    #[inline]
+    pub fn forward_state_caches(&self) -> &[MlLstmStateCache<MIDDLE, IN>] {
+        &self.forward_state_caches[..self.state_caches_len]
+    }
+
+    /// Gets slice of ['MlLstmStateCache'] of backward Lstm in reverse order of input.
+    ///
+    /// - _Return_ : Slice of ['MlLstmStateCache'].
+    #[inline]
+    pub fn backward_state_caches(&self) -> &[MlLstmStateCache<MIDDLE, IN>] {
+        &self.backward_state_caches[..self.state_caches_len]
+    }
+
+    /// Gets ['MlLstmOutputCache'] of forward Lstm.
+    ///
+    /// - _Return_ : ['MlLstmOutputCache'].
+    #[inline]
+    pub fn forward_output_cache(&self) -> &MlLstmOutputCache<MIDDLE, IN> {
+        &self.forward_output_cache
+    }
+
+    /// Gets ['MlLstmOutputCache'] of backward Lstm.
+    ///
+    /// - _Return_ : ['MlLstmOutputCache'].
+    #[inline]
+    pub fn backward_output_cache(&self) -> &MlLstmOutputCache<MIDDLE, IN> {
+        &self.backward_output_cache
+    }
+
+    /// Gets concatenated outputs of forward and backward Lstm.
+    ///
+    /// - _Return_ : Concatenated outputs.
+    #[inline]
+    pub fn concat(&self) -> &MathVec<CAT> {&self.concat}
+
+    /// Gets ['MlCache'] of output layer.
+    ///
+    /// - _Return_ : [`MlCache`].
+    #[inline]
+    pub fn output_layer_cache(&self) -> &MlCache<OUT, CAT> {
+        &self.output_layer_cache
+    }
+
+    /// Gets output.
+    ///
+    /// - _Return_ : output.
+    #[inline]
+    pub fn output(&self) -> &MathVec<OUT> {
+        &self.output_layer_cache.output
+    }
+}
+
+/// Wrapper of [`ChobitBiEncoder`] for machine learning.
+///
+/// See [`ChobitBiEncoder`] for details.
+///
+/// - `OUT` : Dimension of output.
+/// - `CAT` : Dimension of concatenated outputs of both Lstm. (`MIDDLE * 2`)
+/// - `MIDDLE` : Dimension of hidden layers.
+/// - `IN` : Dimension of input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChobitMlBiEncoder<
+    const OUT: usize,
+    const CAT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> {
+    forward_layer: MlLstm<MIDDLE, IN>,
+    backward_layer: MlLstm<MIDDLE, IN>,
+    output_layer: MlLayer<OUT, CAT>,
+
+    prev_state: MathVec<MIDDLE>,
+
+    tmp_concat_error: MathVec<CAT>,
+    tmp_middle_output_error: MathVec<MIDDLE>,
+    tmp_state_error: MathVec<MIDDLE>,
+    tmp_prev_state_error: MathVec<MIDDLE>,
+    tmp_input_error: MathVec<IN>,
+
+    original_prev_state: MathVec<MIDDLE>,
+    original_state: MathVec<MIDDLE>,
+    original_middle_output: MathVec<MIDDLE>,
+    original_concat: MathVec<CAT>,
+    original_tmpbuf: MathVec<MIDDLE>
+}
+
+impl<
+    const OUT: usize,
+    const CAT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> ChobitMlBiEncoder<OUT, CAT, MIDDLE, IN> {
+    const BACKWARD_HEAD: usize =
+        ChobitBiEncoder::<OUT, CAT, MIDDLE, IN>::BACKWARD_HEAD;
+
+    /// Creates ChobitMlBiEncoder.
+    ///
+    /// - `encoder` : Base [`ChobitBiEncoder`].
+    /// - _Return_ : ChobitMlBiEncoder.
+    #[inline]
+    pub fn new(encoder: ChobitBiEncoder<OUT, CAT, MIDDLE, IN>) -> Self {
+        let ChobitBiEncoder::<OUT, CAT, MIDDLE, IN> {
+            forward_layer,
+            backward_layer,
+            output_layer,
+            prev_state,
+            state,
+            middle_output,
+            concat,
+            tmpbuf
+        } = encoder;
+
+        Self {
+            forward_layer: MlLstm::<MIDDLE, IN>::new(forward_layer),
+            backward_layer: MlLstm::<MIDDLE, IN>::new(backward_layer),
+            output_layer: MlLayer::<OUT, CAT>::new(output_layer),
+
+            prev_state: MathVec::<MIDDLE>::new(),
+
+            tmp_concat_error: MathVec::<CAT>::new(),
+            tmp_middle_output_error: MathVec::<MIDDLE>::new(),
+            tmp_state_error: MathVec::<MIDDLE>::new(),
+            tmp_prev_state_error: MathVec::<MIDDLE>::new(),
+            tmp_input_error: MathVec::<IN>::new(),
+
+            original_prev_state: prev_state,
+            original_state: state,
+            original_middle_output: middle_output,
+            original_concat: concat,
+            original_tmpbuf: tmpbuf
+        }
+    }
+
+    /// Drops base [`ChobitBiEncoder`].
+    ///
+    /// - _Return_ : [`ChobitBiEncoder`].
+    #[inline]
+    pub fn drop(self) -> ChobitBiEncoder<OUT, CAT, MIDDLE, IN> {
+        let Self {
+            forward_layer,
+            backward_layer,
+            output_layer,
+            original_prev_state,
+            original_state,
+            original_middle_output,
+            original_concat,
+            original_tmpbuf,
+            ..
+        } = self;
+
+        ChobitBiEncoder::<OUT, CAT, MIDDLE, IN> {
+            forward_layer: forward_layer.drop(),
+            backward_layer: backward_layer.drop(),
+            output_layer: output_layer.drop(),
+            prev_state: original_prev_state,
+            state: original_state,
+            middle_output: original_middle_output,
+            concat: original_concat,
+            tmpbuf: original_tmpbuf
+        }
+    }
+
+    /// Clears internal data for study.
+    #[inline]
+    pub fn clear_study_data(&mut self) {
+        self.forward_layer.clear_study_data();
+        self.backward_layer.clear_study_data();
+        self.output_layer.clear_study_data();
+    }
+
+    /// Sets [`Optimizer`] to all layers.
+    ///
+    /// See [`MlLayer::set_optimizer()`] for details.
+    ///
+    /// - `optimizer` : Optimizer.
+    #[inline]
+    pub fn set_optimizer(&mut self, optimizer: Optimizer) {
+        self.forward_layer.set_optimizer(optimizer);
+        self.backward_layer.set_optimizer(optimizer);
+        self.output_layer.set_optimizer(optimizer);
+    }
+
+    /// Writes information on [`MlBiEncoderCache`] for [`study()`](Self::study()).
+    ///
+    /// States of both Lstm start from zero.
+    ///
+    /// - `train_in` : Input data sequence.
+    /// - `cache` : Cache.
+    pub fn ready(
+        &mut self,
+        train_in: &[MathVec<IN>],
+        cache: &mut MlBiEncoderCache<OUT, CAT, MIDDLE, IN>
+    ) {
+        let len = train_in.len();
+
+        cache.state_caches_len = len;
+        if cache.forward_state_caches.len() < len {
+            cache.forward_state_caches.resize(
+                len,
+                MlLstmStateCache::<MIDDLE, IN>::new()
+            );
+            cache.backward_state_caches.resize(
+                len,
+                MlLstmStateCache::<MIDDLE, IN>::new()
+            );
+        }
+
+        Self::ready_direction(
+            &self.forward_layer,
+            train_in.iter(),
+            &mut self.prev_state,
+            &mut cache.forward_state_caches[..len],
+            &mut cache.forward_output_cache
+        );
+        cache.concat[..Self::BACKWARD_HEAD].copy_from_slice(
+            &cache.forward_output_cache.output
+        );
+
+        Self::ready_direction(
+            &self.backward_layer,
+            train_in.iter().rev(),
+            &mut self.prev_state,
+            &mut cache.backward_state_caches[..len],
+            &mut cache.backward_output_cache
+        );
+        cache.concat[Self::BACKWARD_HEAD..].copy_from_slice(
+            &cache.backward_output_cache.output
+        );
+
+        self.output_layer.ready(
+            &cache.concat,
+            None,
+            &mut cache.output_layer_cache
+        );
+    }
+
+    fn ready_direction<'a, I>(
+        layer: &MlLstm<MIDDLE, IN>,
+        train_in: I,
+        prev_state: &mut MathVec<MIDDLE>,
+        state_caches: &mut [MlLstmStateCache<MIDDLE, IN>],
+        output_cache: &mut MlLstmOutputCache<MIDDLE, IN>
+    ) where I: Iterator<Item = &'a MathVec<IN>> {
+        prev_state.clear();
+
+        train_in.zip(
+            state_caches.iter_mut()
+        ).for_each(|(train_in_one, cache)| {
+            layer.ready_state_cache(train_in_one, prev_state, cache);
+
+            prev_state.copy_from(&cache.state);
+        });
+
+        match state_caches.last() {
+            Some(last_state_cache) => {
+                layer.ready_output_cache(last_state_cache, output_cache);
+            },
+
+            None => {output_cache.output.clear();}
+        }
+    }
+
+    /// Accumulates gradient.
+    ///
+    /// - `output_error` : Backpropagated ouput error.
+    /// - `cache` : Cache.
+    /// - `input_error` : Error to backpropagate for previous output error.
+    pub fn study(
+        &mut self,
+        output_error: &MathVec<OUT>,
+        cache: &MlBiEncoderCache<OUT, CAT, MIDDLE, IN>,
+        input_error: &mut [MathVec<IN>]
+    ) {
+        let len = cache.state_caches_len.min(input_error.len());
+
+        if len == 0 {
+            return;
+        }
+
+        self.output_layer.study(
+            output_error,
+            None,
+            &cache.output_layer_cache,
+            &mut self.tmp_concat_error,
+            None
+        );
+
+        // Forward Lstm.
+        self.tmp_middle_output_error.copy_from_slice(
+            &self.tmp_concat_error[..Self::BACKWARD_HEAD]
+        );
+        self.tmp_state_error.clear();
+
+        let mut iter = cache.forward_state_caches[..len].iter().zip(
+            input_error.iter_mut()
+        ).rev();
+
+        if let Some((state_cache, input_error_one)) = iter.next() {
+            self.forward_layer.study(
+                &self.tmp_middle_output_error,
+                &self.tmp_state_error,
+                state_cache,
+                &cache.forward_output_cache,
+                input_error_one,
+                &mut self.tmp_prev_state_error
+            );
+
+            self.tmp_state_error.copy_from(&self.tmp_prev_state_error);
+        }
+
+        iter.for_each(|(state_cache, input_error_one)| {
+            self.forward_layer.study_state(
+                &self.tmp_state_error,
+                state_cache,
+                input_error_one,
+                &mut self.tmp_prev_state_error
+            );
+
+            self.tmp_state_error.copy_from(&self.tmp_prev_state_error);
+        });
+
+        // Backward Lstm. Its caches are in reverse order of input.
+        self.tmp_middle_output_error.copy_from_slice(
+            &self.tmp_concat_error[Self::BACKWARD_HEAD..]
+        );
+        self.tmp_state_error.clear();
+
+        let mut iter = cache.backward_state_caches[..len].iter().zip(
+            input_error[..len].iter_mut().rev()
+        ).rev();
+
+        if let Some((state_cache, input_error_one)) = iter.next() {
+            self.backward_layer.study(
+                &self.tmp_middle_output_error,
+                &self.tmp_state_error,
+                state_cache,
+                &cache.backward_output_cache,
+                &mut self.tmp_input_error,
+                &mut self.tmp_prev_state_error
+            );
+
+            *input_error_one += &self.tmp_input_error;
+            self.tmp_state_error.copy_from(&self.tmp_prev_state_error);
+        }
+
+        iter.for_each(|(state_cache, input_error_one)| {
+            self.backward_layer.study_state(
+                &self.tmp_state_error,
+                state_cache,
+                &mut self.tmp_input_error,
+                &mut self.tmp_prev_state_error
+            );
+
+            *input_error_one += &self.tmp_input_error;
+            self.tmp_state_error.copy_from(&self.tmp_prev_state_error);
+        });
+    }
+
+    /// Updates weights with learning rate from [`LrScheduler`], and advances step of scheduler.
+    ///
+    /// - `scheduler` : Scheduler of learning rate.
+    #[inline]
+    pub fn update_with_scheduler(&mut self, scheduler: &mut LrScheduler) {
+        self.update(scheduler.next_rate());
+    }
+
+    /// Updates weights.
+    ///
+    /// - `rate` : Learning rate.
+    #[inline]
+    pub fn update(&mut self, rate: f32) {
+        self.forward_layer.update(rate);
+        self.backward_layer.update(rate);
+        self.output_layer.update(rate);
+    }
+
+    /// Accesses each immutable total gradient with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_total_grad<F>(
+        &self,
+        mut f: F
+    ) where F: FnMut(&f32) {
+        self.forward_layer.for_each_total_grad(|val| {f(val)});
+        self.backward_layer.for_each_total_grad(|val| {f(val)});
+        self.output_layer.for_each_grad(&mut f);
+    }
+
+    /// Accesses each mutable total gradient with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_total_grad_mut<F>(
+        &mut self,
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.forward_layer.for_each_total_grad_mut(|val| {f(val)});
+        self.backward_layer.for_each_total_grad_mut(|val| {f(val)});
+        self.output_layer.for_each_grad_mut(&mut f);
+    }
+
+    /// Clips each total gradient into `[-limit, limit]`.
+    ///
+    /// - `limit` : Limit of absolute value. Negative value is treated as its absolute value. If NaN, nothing is clipped.
+    #[inline]
+    pub fn clip_grad_by_value(&mut self, limit: f32) {
+        clip_by_value(limit, |f| self.for_each_total_grad_mut(f));
+    }
+
+    /// Scales all total gradients so that L2 norm of them is `max_norm` or less.
+    ///
+    /// - `max_norm` : Max L2 norm. Negative value is treated as its absolute value.
+    /// - _Return_ : L2 norm before clipping. If it is not finite, nothing is scaled.
+    #[inline]
+    pub fn clip_grad_by_global_norm(&mut self, max_norm: f32) -> f32 {
+        clip_by_global_norm(max_norm, |f| self.for_each_total_grad_mut(f))
+    }
+
+    /// Adds gradient of L1 and L2 penalty of weights into total gradients of all layers.
+    ///
+    /// See [`MlLayer::add_weight_penalty()`] for details.
+    ///
+    /// - `l1` : Coefficient of L1 penalty.
+    /// - `l2` : Coefficient of L2 penalty.
+    #[inline]
+    pub fn add_weight_penalty(&mut self, l1: f32, l2: f32) {
+        self.forward_layer.add_weight_penalty(l1, l2);
+        self.backward_layer.add_weight_penalty(l1, l2);
+        self.output_layer.add_weight_penalty(l1, l2);
+    }
+
+    /// Checks that all total gradients and all weights are finite.
+    ///
+    /// See [`ChobitMlEncoder::check_finite()`] for details.
+    ///
+    /// - _Return_ : [`ChobitAiError::NonFinite`] if non-finite number is found.
+    pub fn check_finite(&self) -> Result<(), ChobitAiError> {
+        const FORWARD_NAMES: [&str; 9] = [
+            "forward_layer.main_layer",
+            "forward_layer.f_gate",
+            "forward_layer.i_gate",
+            "forward_layer.o_gate",
+            "forward_layer.main_layer_norm",
+            "forward_layer.f_gate_norm",
+            "forward_layer.i_gate_norm",
+            "forward_layer.o_gate_norm",
+            "forward_layer.state_norm"
+        ];
+        const BACKWARD_NAMES: [&str; 9] = [
+            "backward_layer.main_layer",
+            "backward_layer.f_gate",
+            "backward_layer.i_gate",
+            "backward_layer.o_gate",
+            "backward_layer.main_layer_norm",
+            "backward_layer.f_gate_norm",
+            "backward_layer.i_gate_norm",
+            "backward_layer.o_gate_norm",
+            "backward_layer.state_norm"
+        ];
+
+        self.output_layer.check_grad("output_layer")?;
+        self.forward_layer.check_grad(&FORWARD_NAMES)?;
+        self.backward_layer.check_grad(&BACKWARD_NAMES)?;
+
+        self.output_layer.check_weights("output_layer")?;
+        self.forward_layer.check_weights(&FORWARD_NAMES)?;
+        self.backward_layer.check_weights(&BACKWARD_NAMES)?;
+
+        Ok(())
+    }
+
+    /// Writes full state for machine learning into buffer as binary data.
+    ///
+    /// See [`MlLayer::save_checkpoint()`] for details.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save_checkpoint(&self, buffer: &mut Vec<u8>) {
+        write_header(
+            buffer,
+            ModelKind::ChobitMlBiEncoder,
+            &[OUT, CAT, MIDDLE, IN]
+        );
+
+        self.forward_layer.write_state(buffer);
+        self.backward_layer.write_state(buffer);
+        self.output_layer.write_state(buffer);
+    }
+
+    /// Creates ChobitMlBiEncoder from binary data written by [`save_checkpoint()`](Self::save_checkpoint()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : ChobitMlBiEncoder.
+    pub fn load_checkpoint(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(
+            ModelKind::ChobitMlBiEncoder,
+            &[("OUT", OUT), ("CAT", CAT), ("MIDDLE", MIDDLE), ("IN", IN)]
+        )?;
+
+        let encoder =
+            ChobitBiEncoder::<OUT, CAT, MIDDLE, IN>::new(Activation::Linear);
+        let mut ret = Self::new(encoder);
+
+        ret.forward_layer = MlLstm::<MIDDLE, IN>::read_state(&mut reader)?;
+        ret.backward_layer = MlLstm::<MIDDLE, IN>::read_state(&mut reader)?;
+        ret.output_layer = MlLayer::<OUT, CAT>::read_state(&mut reader)?;
+
+        Ok(ret)
+    }
+}
+
+/// Strategy to choose next token in generation, such as [`ChobitDecoder::generate()`] and [`ChobitSeqAi::generate()`].
+///
+/// Output of AI is regarded as scores (logits) of `OUT` tokens. Token is index of output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Decoding {
+    /// Chooses token of max score.
+    Greedy,
+
+    /// Samples token from `softmax(output / temperature)`.
+    ///
+    /// Candidates are limited to `top_k` tokens of highest probability, and then to the fewest tokens whose total probability reaches `top_p`.
+    ///
+    /// - `temperature` : Temperature. If it is `0.0` or less, this is the same as [`Decoding::Greedy`].
+    /// - `top_k` : Max number of candidates. `0` means no limit.
+    /// - `top_p` : Threshold of total probability of candidates. `1.0` means no limit.
+    Sampling {temperature: f32, top_k: usize, top_p: f32},
+
+    /// Beam search.
+    ///
+    /// Score of a sequence is sum of log softmax of output for each token. The sequence of the best score is returned.
+    ///
+    /// - `beam_width` : Number of sequences kept in each step.
+    BeamSearch {beam_width: usize}
+}
+
+impl Default for Decoding {
+    #[inline]
+    fn default() -> Self {Self::Greedy}
+}
+
+impl Decoding {
+    /// Creates [`Decoding::Sampling`] with `top_k = 0` and `top_p = 1.0`.
+    ///
+    /// - `temperature` : Temperature.
+    /// - _Return_ : Decoding.
+    #[inline]
+    pub fn sampling(temperature: f32) -> Self {
+        Self::Sampling {temperature: temperature, top_k: 0, top_p: 1.0}
+    }
+
+    /// Creates [`Decoding::BeamSearch`].
+    ///
+    /// - `beam_width` : Number of sequences kept in each step.
+    /// - _Return_ : Decoding.
+    #[inline]
+    pub fn beam_search(beam_width: usize) -> Self {
+        Self::BeamSearch {beam_width: beam_width}
+    }
+}
+
+#[inline]
+fn argmax(values: &[f32]) -> usize {
+    values.iter().enumerate().fold(
+        (0, f32::NEG_INFINITY),
+        |(index, max), (i, val)| if *val > max {(i, *val)} else {(index, max)}
+    ).0
+}
+
+fn sample_token<F>(
+    values: &[f32],
+    temperature: f32,
+    top_k: usize,
+    top_p: f32,
+    rand: &mut F
+) -> usize where F: FnMut() -> f32 {
+    if temperature <= 0.0 {
+        return argmax(values);
+    }
+
+    let temperature_inv = temperature.recip();
+
+    let mut probs: Vec<f32> =
+        values.iter().map(|val| *val * temperature_inv).collect();
+
+    softmax_slice(&mut probs);
+
+    let mut candidates: Vec<usize> = (0..probs.len()).collect();
+
+    candidates.sort_by(|a, b| probs[*b].total_cmp(&probs[*a]));
+
+    if top_k > 0 {
+        candidates.truncate(top_k);
+    }
+
+    let mut total: f32 = 0.0;
+    let mut len: usize = 0;
+
+    for index in candidates.iter() {
+        total += probs[*index];
+        len += 1;
+
+        if total >= top_p {break;}
+    }
+
+    candidates.truncate(len.max(1));
+
+    let mut point = rand() * total;
+
+    for index in candidates.iter() {
+        point -= probs[*index];
+
+        if point < 0.0 {return *index;}
+    }
+
+    *candidates.last().unwrap_or(&0)
+}
+
+#[inline]
+fn log_softmax_slice(values: &mut [f32]) {
+    let max = values.iter().fold(f32::NEG_INFINITY, |a, b| a.max(*b));
+
+    let sum = values.iter().fold(0.0, |sum, val| sum + exp(*val - max));
+    let log_sum = ln(sum) + max;
+
+    values.iter_mut().for_each(|val| {*val -= log_sum;});
+}
+
+struct Beam<S> {
+    tokens: Vec<usize>,
+    score: f32,
+    state: S,
+    finished: bool
+}
+
+// `step` writes output from state and previous token, then advances state.
+fn generate_tokens<const OUT: usize, S, F, G, R>(
+    decoding: &Decoding,
+    max_len: usize,
+    state: S,
+    mut step: F,
+    mut stop: G,
+    mut rand: R
+) -> (Vec<usize>, S)
+where
+    S: Clone,
+    F: FnMut(&mut S, Option<usize>, &mut MathVec<OUT>),
+    G: FnMut(&[usize]) -> bool,
+    R: FnMut() -> f32
+{
+    let mut output = MathVec::<OUT>::new();
+
+    let beam_width = match decoding {
+        Decoding::BeamSearch {beam_width} => *beam_width,
+
+        _ => {
+            let mut state = state;
+            let mut tokens = Vec::<usize>::with_capacity(max_len);
+
+            while tokens.len() < max_len {
+                step(&mut state, tokens.last().copied(), &mut output);
+
+                tokens.push(match decoding {
+                    Decoding::Sampling {temperature, top_k, top_p} =>
+                        sample_token(
+                            &output,
+                            *temperature,
+                            *top_k,
+                            *top_p,
+                            &mut rand
+                        ),
+
+                    _ => argmax(&output)
+                });
+
+                if stop(&tokens) {break;}
+            }
+
+            return (tokens, state);
+        }
+    };
+
+    let mut beams = vec![Beam::<S> {
+        tokens: Vec::<usize>::new(),
+        score: 0.0,
+        state: state,
+        finished: max_len == 0
+    }];
+
+    let mut candidates = Vec::<(f32, usize, Option<usize>)>::new();
+    let mut indices: Vec<usize> = (0..OUT).collect();
+
+    while !beams.iter().all(|beam| beam.finished) {
+        candidates.clear();
+
+        for (i, beam) in beams.iter_mut().enumerate() {
+            if beam.finished {
+                candidates.push((beam.score, i, None));
+                continue;
+            }
+
+            step(&mut beam.state, beam.tokens.last().copied(), &mut output);
+            log_softmax_slice(&mut output);
+
+            indices.sort_by(|a, b| output[*b].total_cmp(&output[*a]));
+
+            indices.iter().take(beam_width.max(1)).for_each(|token| {
+                candidates.push((beam.score + output[*token], i, Some(*token)));
+            });
+        }
+
+        candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+        candidates.truncate(beam_width.max(1));
+
+        beams = candidates.iter().map(|(score, i, token)| {
+            let beam = &beams[*i];
+            let mut tokens = beam.tokens.clone();
+
+            let finished = match token {
+                Some(token) => {
+                    tokens.push(*token);
+
+                    (tokens.len() >= max_len) || stop(&tokens)
+                },
+
+                None => true
+            };
+
+            Beam::<S> {
+                tokens: tokens,
+                score: *score,
+                state: beam.state.clone(),
+                finished: finished
+            }
+        }).collect();
+    }
+
+    let best = beams.into_iter().reduce(
+        |best, beam| if beam.score > best.score {beam} else {best}
+    ).unwrap();
+
+    (best.tokens, best.state)
+}
+
+/// Schedule of probability of teacher forcing for [`InputFeeding::ScheduledSampling`]. ([Scheduled Sampling](https://arxiv.org/abs/1506.03099))
+///
+/// `step` is count of [`SamplingSchedule::prob()`] argument. It is count of update or count of epoch, as you like.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SamplingSchedule {
+    /// Constant probability.
+    ///
+    /// - `prob` : Probability.
+    Constant {prob: f32},
+
+    /// Probability changes linearly from `start` to `end` in `steps` steps, and then stays at `end`.
+    ///
+    /// `start - (start - end) * step / steps`
+    ///
+    /// - `start` : Initial probability.
+    /// - `end` : Final probability.
+    /// - `steps` : Length of decrease.
+    Linear {start: f32, end: f32, steps: u64},
+
+    /// Probability is multiplied by `gamma` every step.
+    ///
+    /// `gamma^step`
+    ///
+    /// - `gamma` : Decay rate. (`(0.0, 1.0)`)
+    Exponential {gamma: f32},
+
+    /// Inverse sigmoid decay.
+    ///
+    /// `k / (k + exp(step / k))`
+    ///
+    /// - `k` : Speed of decay. The larger, the slower. (`k >= 1.0`)
+    InverseSigmoid {k: f32}
+}
+
+impl SamplingSchedule {
+    /// Calculates probability of teacher forcing.
+    ///
+    /// - `step` : Count of step.
+    /// - _Return_ : Probability.
+    pub fn prob(&self, step: u64) -> f32 {
+        match *self {
+            Self::Constant {prob} => prob,
+
+            Self::Linear {start, end, steps} => if step >= steps {
+                end
+            } else {
+                start - ((start - end) * (step as f32) / (steps as f32))
+            },
+
+            Self::Exponential {gamma} => powi(gamma, step),
+
+            Self::InverseSigmoid {k} => k / (k + exp((step as f32) / k))
+        }
+    }
+}
+
+/// How to make input of decoder from the second step in machine learning.
+///
+/// It is given to [`ChobitMlDecoder::ready_with_feeding()`] and [`ChobitMlSeqAi::ready_with_feeding()`] through [`chooser()`](InputFeeding::chooser()).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputFeeding {
+    /// Previous correct output is fed. (Teacher forcing)
+    TeacherForcing,
+
+    /// Previous output of AI is fed. (Free running)
+    FreeRunning,
+
+    /// Previous correct output is fed with probability `teacher_prob`, otherwise previous output of AI is fed.
+    ///
+    /// Use [`SamplingSchedule`] to decrease `teacher_prob` through machine learning.
+    ///
+    /// - `teacher_prob` : Probability of teacher forcing.
+    ScheduledSampling {teacher_prob: f32}
+}
+
+impl InputFeeding {
+    /// Creates [`InputFeeding::ScheduledSampling`] with probability from [`SamplingSchedule`].
+    ///
+    /// - `schedule` : Schedule.
+    /// - `step` : Count of step.
+    /// - _Return_ : InputFeeding.
+    #[inline]
+    pub fn scheduled(schedule: &SamplingSchedule, step: u64) -> Self {
+        Self::ScheduledSampling {teacher_prob: schedule.prob(step)}
+    }
+
+    /// Creates closure that returns `true` if previous correct output should be fed.
+    ///
+    /// The closure is given to `use_teacher` of [`ChobitMlDecoder::ready_with_feeding()`] and [`ChobitMlSeqAi::ready_with_feeding()`].
+    ///
+    /// - `rand` : Closure that returns uniform random number in `[0.0, 1.0)`. It is used only by [`InputFeeding::ScheduledSampling`].
+    /// - _Return_ : Closure.
+    #[inline]
+    pub fn chooser<R>(self, mut rand: R) -> impl FnMut() -> bool
+    where R: FnMut() -> f32 {
+        move || match self {
+            Self::TeacherForcing => true,
+            Self::FreeRunning => false,
+            Self::ScheduledSampling {teacher_prob} => rand() < teacher_prob
+        }
+    }
+}
+
+/// Decoder from fixed length data to sequence data.
+///
+/// - `OUT` : Dimension of output.
+/// - `MIDDLE` : Dimension of hidden layer.
+/// - `IN` : Dimension of input.
+///
+/// # Example
+///
+/// Letter commentator.
+/// - If inputs '日', outputs "これは日本語です。"
+/// - If inputs 'E', outputs "This is English."
+///
+/// (1) Defines ID and messages.
+///
+/// ```ignore
+/// extern crate chobitlibs;
+/// 
+/// use chobitlibs::chobit_ai::{
+///     MathVec,
+///     Activation,
+///     ChobitDecoder,
+///     ChobitMlDecoder,
+///     MlDecoderCache
+/// };
+/// 
+/// use chobitlibs::chobit_rand::ChobitRand;
+/// 
+/// const JAPANESE_ID: char = '日';
+/// const ENGLISH_ID: char = 'E';
+/// 
+/// const JAPANESE_MESSAGE: &str = "これは日本語です。";
+/// const ENGLISH_MESSAGE: &str = "This is English.";
+/// 
+/// fn write_string_to_slice(string: &str, slice: &mut [MathVec<32>]) {
+///     string.chars().zip(slice.iter_mut()).for_each(|(c, s)| {
+///         s.load_u32_label(c as u32);
+///     });
+/// }
+/// ```
+///
+/// (2) Creates [`ChobitDecoder`] and randomises weights.
+///
+/// ```ignore
+/// const OUT: usize = 32;
+/// const MIDDLE: usize = 64;
+/// const IN: usize = 32;
+///
+/// let max_message_len = JAPANESE_MESSAGE.len().max(ENGLISH_MESSAGE.len());
+///
+/// let mut rng = ChobitRand::new(b"ChobitDecoder Example");
+///
+/// let mut decoder =
+///     ChobitDecoder::<OUT, MIDDLE, IN>::new(Activation::SoftSign);
+///
+/// // Randomises weights.
+/// decoder.for_each_weight_mut(|weight| {
+///     *weight = ((rng.next_f64() as f32) * 2.0) - 1.0;
+/// });
+///
+/// let mut input = MathVec::<IN>::new();
+/// let mut output = vec![MathVec::<OUT>::new(); max_message_len];
+/// let initial_state = MathVec::<MIDDLE>::new();
+/// ```
+///
+/// (3) Wraps AI with [`ChobitMlDecoder`] for machine learning.
+///
+/// ```ignore
+/// let mut decoder = ChobitMlDecoder::<OUT, MIDDLE, IN>::new(decoder);
+/// let mut cache = MlDecoderCache::<OUT, MIDDLE, IN>::new(max_message_len);
+///
+/// let mut input_error = MathVec::<IN>::new();
+/// let mut output_error = vec![MathVec::<OUT>::new(); max_message_len];
+/// let mut prev_state_error = MathVec::<MIDDLE>::new();
+/// ```
+///
+/// (4) Machine learning.
+///
+/// ```ignore
+/// const EPOCH: usize = 10000;
+/// const BATCH_SIZE: usize = 1;
+/// const RATE: f32 = 0.01;
+///
+/// let japanese_message_len = JAPANESE_MESSAGE.chars().count();
+/// let english_message_len = ENGLISH_MESSAGE.chars().count();
+///
+/// for _ in 0..EPOCH {
+///     for _ in 0..BATCH_SIZE {
+///         //--- Learns Japanese ---//
+///         input.load_u32_label(JAPANESE_ID as u32);
+///
+///         write_string_to_slice(&JAPANESE_MESSAGE, &mut output);
+///
+///         // Writes cache.
+///         decoder.ready(
+///             &input,
+///             &initial_state,
+///             japanese_message_len,
+///             &mut cache
+///         );
+///
+///         // Calculates error.
+///         cache.calc_output_error(
+///             &output[..japanese_message_len],
+///             &mut output_error[..japanese_message_len]
+///         );
+///
+///         // Studies.
+///         decoder.study(
+///             &output_error[..japanese_message_len],
+///             &cache,
+///             &mut input_error,
+///             &mut prev_state_error
+///         );
+///
+///         //--- Learns English ---//
+///         input.load_u32_label(ENGLISH_ID as u32);
+///
+///         write_string_to_slice(&ENGLISH_MESSAGE, &mut output);
+///
+///         // Writes cache.
+///         decoder.ready(
+///             &input,
+///             &initial_state,
+///             english_message_len,
+///             &mut cache
+///         );
+///
+///         // Calculates error.
+///         cache.calc_output_error(
+///             &output[..english_message_len],
+///             &mut output_error[..english_message_len]
+///         );
+///
+///         // Studies.
+///         decoder.study(
+///             &output_error[..english_message_len],
+///             &cache,
+///             &mut input_error,
+///             &mut prev_state_error
+///         );
+///     }
+///
+///     // Updates weights.
+///     decoder.update(RATE);
+/// }
+/// ```
+///
+/// (5) Tests AI.
+///
+/// ```ignore
+/// // Unwrap Decoder.
+/// let mut decoder = decoder.drop();
+///
+/// let mut output = MathVec::<OUT>::new();
+///
+/// // Tests Japanese.
+/// // Sets input.
+/// decoder.input_mut().load_u32_label(JAPANESE_ID as u32);
+///
+/// // Initializes state.
+/// decoder.state_mut().copy_from(&initial_state);
+///
+/// // Outputs for each one.
+/// JAPANESE_MESSAGE.chars().for_each(|c| {
+///     decoder.output_next(&mut output);
+///
+///     assert_eq!(output.to_u32_label(), c as u32);
+/// });
+///
+/// // Tests English.
+/// // Sets input.
+/// decoder.input_mut().load_u32_label(ENGLISH_ID as u32);
+///
+/// // Initializes state.
+/// decoder.state_mut().copy_from(&initial_state);
+///
+/// // Outputs for each one.
+/// ENGLISH_MESSAGE.chars().for_each(|c| {
+///     decoder.output_next(&mut output);
+///
+///     assert_eq!(output.to_u32_label(), c as u32);
+/// });
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChobitDecoder<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> {
+    lstm: Lstm<MIDDLE, IN>,
+    output_layer: Layer<OUT, MIDDLE>,
+
+    input: MathVec<IN>,
+    prev_state: MathVec<MIDDLE>,
+    state: MathVec<MIDDLE>,
+
+    middle_output: MathVec<MIDDLE>,
+    tmpbuf: MathVec<MIDDLE>
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> ChobitDecoder<OUT, MIDDLE, IN> {
+    /// Creates ChobitDecoder.
+    ///
+    /// - `activation` : Activation function for output layer.
+    /// - _Return_ : ChobitDecoder.
+    pub fn new(activation: Activation) -> Self {
+        Self::with_layers(
+            Lstm::<MIDDLE, IN>::new(),
+            Layer::<OUT, MIDDLE>::new(activation, false)
+        )
+    }
+
+    /// Creates ChobitDecoder with activation functions.
+    ///
+    /// - `hidden` : Activation function for main layer of Lstm and for state before output gate. (See [`Lstm::with_activations()`].)
+    /// - `output` : Activation function for output layer.
+    /// - _Return_ : ChobitDecoder.
+    #[inline]
+    pub fn with_activations(hidden: Activation, output: Activation) -> Self {
+        Self::with_layers(
+            Lstm::<MIDDLE, IN>::with_activations(
+                hidden,
+                Activation::Sigmoid,
+                hidden
+            ),
+            Layer::<OUT, MIDDLE>::new(output, false)
+        )
+    }
+
+    /// Creates ChobitDecoder with layers.
+    ///
+    /// - `lstm` : Lstm.
+    /// - `output_layer` : Output layer. State weights of it are not used.
+    /// - _Return_ : ChobitDecoder.
+    #[inline]
+    pub fn with_layers(
+        lstm: Lstm<MIDDLE, IN>,
+        output_layer: Layer<OUT, MIDDLE>
+    ) -> Self {
+        Self {
+            lstm: lstm,
+            output_layer: output_layer,
+
+            input: MathVec::<IN>::new(),
+            prev_state: MathVec::<MIDDLE>::new(),
+            state: MathVec::<MIDDLE>::new(),
+
+            middle_output: MathVec::<MIDDLE>::new(),
+            tmpbuf: MathVec::<MIDDLE>::new()
+        }
+    }
+
+    /// Gets immutable Lstm.
+    ///
+    /// - _Return_ : Lstm.
+    #[inline]
+    pub fn lstm(&self) -> &Lstm<MIDDLE, IN> {&self.lstm}
+
+    /// Gets mutable Lstm.
+    ///
+    /// - _Return_ : Lstm.
+    #[inline]
+    pub fn lstm_mut(&mut self) -> &mut Lstm<MIDDLE, IN> {&mut self.lstm}
+
+    /// Gets immutable output layer.
+    ///
+    /// - _Return_ : Output layer.
+    #[inline]
+    pub fn output_layer(&self) -> &Layer<OUT, MIDDLE> {&self.output_layer}
+
+    /// Gets mutable output layer.
+    ///
+    /// - _Return_ : Output layer.
+    #[inline]
+    pub fn output_layer_mut(&mut self) -> &mut Layer<OUT, MIDDLE> {
+        &mut self.output_layer
+    }
+
+    /// Initializes weights with [`Initializer`].
+    ///
+    /// Lstm is initialized by [`Lstm::init_weights()`] with `initializer` for input weights and [`Initializer::Orthogonal`] for state weights.
+    ///
+    /// - `initializer` : Initializer for output layer and input weights of Lstm.
+    /// - `rand` : Closure that returns uniform random number in `[0.0, 1.0)`.
+    pub fn init_weights<F>(&mut self, initializer: Initializer, mut rand: F)
+    where F: FnMut() -> f32 {
+        self.lstm.init_weights(
+            initializer,
+            Initializer::Orthogonal,
+            &mut rand
+        );
+
+        self.output_layer.init_weights(initializer, &mut rand);
+    }
+
+    /// Gets immutable input.
+    ///
+    /// This should be set before to call [`output_next()`](Self::output_next()).
+    ///
+    /// - _Return_ : Input.
+    #[inline]
+    pub fn input(&self) -> &MathVec<IN> {&self.input}
+
+    /// Gets mutable input.
+    ///
+    /// This should be set before to call [`output_next()`](Self::output_next()).
+    ///
+    /// - _Return_ : Input.
+    #[inline]
+    pub fn input_mut(&mut self) -> &mut MathVec<IN> {&mut self.input}
+
+    /// Gets immutable state.
+    ///
+    /// This should be initialized before to call [`output_next()`](Self::output_next()).
+    ///
+    /// - _Return_ : State.
+    #[inline]
+    pub fn state(&self) -> &MathVec<MIDDLE> {&self.state}
+
+    /// Gets mutable state.
+    ///
+    /// This should be initialized before to call [`output_next()`](Self::output_next()).
+    ///
+    /// - _Return_ : State.
+    #[inline]
+    pub fn state_mut(&mut self) -> &mut MathVec<MIDDLE> {&mut self.state}
+
+    /// Output next data calculated by current state and input.
+    ///
+    /// - `output` : Buffer for next data.
+    pub fn output_next(&mut self, output: &mut MathVec<OUT>) {
+        self.prev_state.copy_from(&self.state);
+
+        self.lstm.calc(
+            &self.input,
+            &self.prev_state,
+            &mut self.middle_output,
+            &mut self.state,
+            &mut self.tmpbuf,
+        );
+
+        self.output_layer.calc(&self.middle_output, None, output);
+    }
+
+    /// Outputs prediction for loss function used in machine learning.
+    ///
+    /// This is [`output_next()`](Self::output_next()) and [`Loss::predict()`].
+    ///
+    /// - `loss` : Loss function used in machine learning.
+    /// - `output` : Buffer for prediction.
+    #[inline]
+    pub fn output_next_with_loss(
+        &mut self,
+        loss: &Loss,
+        output: &mut MathVec<OUT>
+    ) {
+        self.output_next(output);
+        loss.predict(output);
+    }
+
+    /// Generates sequence of tokens, feeding each token back as next input.
+    ///
+    /// See [`Decoding`] for how to choose token. [`input()`](Self::input()) and [`state()`](Self::state()) should be set before. After generation, they are ready to output the token next to the returned sequence.
+    ///
+    /// ```ignore
+    /// use chobitlibs::chobit_ai::{MathVec, Activation, ChobitDecoder, Decoding};
+    /// use chobitlibs::chobit_rand::ChobitRand;
+    ///
+    /// const END: usize = 0;
+    ///
+    /// let mut rng = ChobitRand::new(b"generate");
+    /// let mut decoder = ChobitDecoder::<64, 32, 64>::new(Activation::Linear);
+    ///
+    /// decoder.input_mut().load_u32_label(END as u32);
+    ///
+    /// let tokens = decoder.generate(
+    ///     &Decoding::Sampling {temperature: 0.8, top_k: 10, top_p: 0.9},
+    ///     100,
+    ///     || rng.next_f64() as f32,
+    ///     |token, input| input.load_u32_label(token as u32),
+    ///     |tokens| tokens.last() == Some(&END)
+    /// );
+    /// ```
+    ///
+    /// - `decoding` : Strategy to choose token.
+    /// - `max_len` : Max length of sequence.
+    /// - `rand` : Closure that returns uniform random number in `[0.0, 1.0)`. It is used only by [`Decoding::Sampling`].
+    /// - `to_input` : Closure that writes input for a token.
+    /// - `stop` : Closure that receives generated sequence and returns `true` to stop generation. The sequence includes the last token.
+    /// - _Return_ : Generated sequence of tokens.
+    pub fn generate<R, M, S>(
+        &mut self,
+        decoding: &Decoding,
+        max_len: usize,
+        rand: R,
+        mut to_input: M,
+        stop: S
+    ) -> Vec<usize>
+    where
+        R: FnMut() -> f32,
+        M: FnMut(usize, &mut MathVec<IN>),
+        S: FnMut(&[usize]) -> bool
+    {
+        let first_input = self.input.clone();
+
+        let (tokens, state) = generate_tokens::<OUT, _, _, _, _>(
+            decoding,
+            max_len,
+            self.state.clone(),
+            |state, prev_token, output| {
+                match prev_token {
+                    Some(token) => to_input(token, &mut self.input),
+                    None => self.input.copy_from(&first_input)
+                }
+
+                self.state.copy_from(state);
+                self.output_next(output);
+                state.copy_from(&self.state);
+            },
+            stop,
+            rand
+        );
+
+        self.state.copy_from(&state);
+
+        match tokens.last() {
+            Some(token) => to_input(*token, &mut self.input),
+            None => self.input.copy_from(&first_input)
+        }
+
+        tokens
+    }
+
+    /// Accesses each immutable weight with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_weight<F>(&self, mut f: F) where F: FnMut(&f32) {
+        self.lstm.for_each_weight(|val| {f(val)});
+        self.output_layer.for_each_weight(|val| {f(val)});
+    }
+
+    /// Accesses each mutable weight with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_weight_mut<F>(
+        &mut self,
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.lstm.for_each_weight_mut(|val| {f(val)});
+        self.output_layer.for_each_weight_mut(|val| {f(val)});
+    }
+
+    /// Writes this decoder into buffer as binary data.
+    ///
+    /// Input and state are not written. See [`ChobitAi::save()`] for details.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save(&self, buffer: &mut Vec<u8>) {
+        write_header(buffer, ModelKind::ChobitDecoder, &[OUT, MIDDLE, IN]);
+
+        self.lstm.write_descriptor(buffer);
+        self.output_layer.write_descriptor(buffer);
+
+        self.lstm.write_body(buffer);
+        self.output_layer.weights.write_body(buffer);
+    }
+
+    /// Creates ChobitDecoder from binary data written by [`save()`](Self::save()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : ChobitDecoder.
+    pub fn load(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(
+            ModelKind::ChobitDecoder,
+            &[("OUT", OUT), ("MIDDLE", MIDDLE), ("IN", IN)]
+        )?;
+
+        let mut lstm = Lstm::<MIDDLE, IN>::read_descriptor(&mut reader)?;
+        let mut output_layer =
+            Layer::<OUT, MIDDLE>::read_descriptor(&mut reader)?;
+
+        lstm.read_body(&mut reader)?;
+        output_layer.weights.read_body(&mut reader)?;
+
+        let mut ret = Self::new(Activation::Linear);
+
+        ret.lstm = lstm;
+        ret.output_layer = output_layer;
+
+        Ok(ret)
+    }
+}
+
+/// Cache for [`ChobitMlDecoder`].
+///
+/// - `OUT` : `OUT` of [`ChobitMlDecoder`].
+/// - `MIDDLE` : `MIDDLE` of [`ChobitMlDecoder`].
+/// - `IN` : `IN` of [`ChobitMlDecoder`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlDecoderCache<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> {
+    caches: Vec<(
+        MlLstmStateCache<MIDDLE, IN>,
+        MlLstmOutputCache<MIDDLE, IN>,
+        MlCache<OUT, MIDDLE>
+    )>,
+
+    caches_len: usize,
+    feeds_back: bool
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> MlDecoderCache<OUT, MIDDLE, IN> {
+    /// Creates MlDecoderCache.
+    ///
+    /// - _Return_ : MlDecoderCache.
+    #[inline]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            caches: vec![
+                (
+                    MlLstmStateCache::<MIDDLE, IN>::new(),
+                    MlLstmOutputCache::<MIDDLE, IN>::new(),
+                    MlCache::<OUT, MIDDLE>::new(),
+                ); capacity
+            ],
+            caches_len: 0,
+            feeds_back: false
+        }
+    }
+
+    /// Calculates output error.
+    ///
+    /// | Formula |
+    /// |:-:|
+    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mi>e</mi> <mo stretchy="false">=</mo> <mrow> <mi>o</mi> <mo stretchy="false">−</mo> <mi>t</mi> </mrow> </mrow> </semantics> </math> |
+    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mtable columnalign="left"> <mtr> <mtd> <mrow> <mi>e</mi> <mo stretchy="false">≝</mo> <mtext>Error.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>o</mi> <mo stretchy="false">≝</mo> <mtext>Actual output.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>t</mi> <mo stretchy="false">≝</mo> <mtext>Correct output.</mtext> </mrow> </mtd> </mtr> </mtable> </semantics> </math> |
+    ///
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error(
+        &self,
+        train_out: &[MathVec<OUT>],
+        output_error: &mut [MathVec<OUT>]
+    ) {
+        train_out.iter().zip(
+            &self.caches
+        ).zip(
+            output_error
+        ).for_each(|(
+            (
+                train_out_one,
+                (_, _, output_layer_cache)
+            ),
+            output_error_one
+        )| {
+            output_error_one.copy_from(&output_layer_cache.output);
+            *output_error_one -= train_out_one;
+        });
+    }
+
+    /// Calculates output error with loss function.
+    ///
+    /// - `loss` : Loss function.
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error_with_loss(
+        &self,
+        loss: &Loss,
+        train_out: &[MathVec<OUT>],
+        output_error: &mut [MathVec<OUT>]
+    ) {
+        train_out.iter().zip(
+            &self.caches
+        ).zip(
+            output_error
+        ).for_each(
+            |((train_out_one, (_, _, cache)), output_error_one)| {
+                loss.calc_error(&cache.output, train_out_one, output_error_one);
+            }
+        );
+    }
+
+    /// Gets immutable slice of caches.
+    ///
+    /// - _Return_ : Slice of caches.
+    #[inline]
+    pub fn caches(&self) -> &[(
+        MlLstmStateCache<MIDDLE, IN>,
+        MlLstmOutputCache<MIDDLE, IN>,
+        MlCache<OUT, MIDDLE>
+    )] {
+        &self.caches[..self.caches_len]
+    }
+
+    /// Gets mutable slice of caches.
+    ///
+    /// - _Return_ : Slice of caches.
+    #[inline]
+    pub(self) fn caches_mut(&mut self) -> &mut [(
+        MlLstmStateCache<MIDDLE, IN>,
+        MlLstmOutputCache<MIDDLE, IN>,
+        MlCache<OUT, MIDDLE>
+    )] {
+        &mut self.caches[..self.caches_len]
+    }
+
+    /// Accesses each output with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_output<F>(&self, mut f: F) where F: FnMut(&MathVec<OUT>) {
+        self.caches[..self.caches_len].iter().for_each(
+            |(_, _, cache)| {f(&cache.output);}
+        );
+    }
+}
+
+/// Wrapper of [`ChobitDecoder`] for machine learning.
+///
+/// See [`ChobitDecoder`] for details.
+///
+/// - `OUT` : Dimension of output.
+/// - `MIDDLE` : Dimension of hidden layer.
+/// - `IN` : Dimension of input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChobitMlDecoder<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> {
+    lstm: MlLstm<MIDDLE, IN>,
+    output_layer: MlLayer<OUT, MIDDLE>,
+
+    prev_state: MathVec<MIDDLE>,
+
+    tmp_input: MathVec<IN>,
+    tmp_middle_output_error: MathVec<MIDDLE>,
+    tmp_state_error: MathVec<MIDDLE>,
+    tmp_input_error: MathVec<IN>,
+
+    original_input: MathVec<IN>,
+    original_prev_state: MathVec<MIDDLE>,
+    original_state: MathVec<MIDDLE>,
+
+    original_middle_output: MathVec<MIDDLE>,
+    original_tmpbuf: MathVec<MIDDLE>
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> ChobitMlDecoder<OUT, MIDDLE, IN> {
+    /// Creates ChobitMlDecoder.
+    ///
+    /// - `decoder` : Base [`ChobitDecoder`].
+    /// - _Return_ : ChobitMlDecoder.
+    #[inline]
+    pub fn new(decoder: ChobitDecoder<OUT, MIDDLE, IN>) -> Self {
+        let ChobitDecoder::<OUT, MIDDLE, IN> {
+            lstm,
+            output_layer,
+            input,
+            prev_state,
+            state,
+            middle_output,
+            tmpbuf
+        } = decoder;
+
+        Self {
+            lstm: MlLstm::<MIDDLE, IN>::new(lstm),
+            output_layer: MlLayer::<OUT, MIDDLE>::new(output_layer),
+
+            prev_state: MathVec::<MIDDLE>::new(),
+
+            tmp_input: MathVec::<IN>::new(),
+            tmp_middle_output_error: MathVec::<MIDDLE>::new(),
+            tmp_state_error: MathVec::<MIDDLE>::new(),
+            tmp_input_error: MathVec::<IN>::new(),
+
+            original_input: input,
+            original_prev_state: prev_state,
+            original_state: state,
+            original_middle_output: middle_output,
+            original_tmpbuf: tmpbuf
+        }
+    }
+
+    /// Drops base [`ChobitDecoder`].
+    ///
+    /// - _Return_ : [`ChobitDecoder`].
+    #[inline]
+    pub fn drop(self) -> ChobitDecoder<OUT, MIDDLE, IN> {
+        let Self {
+            lstm,
+            output_layer,
+            original_input,
+            original_prev_state,
+            original_state,
+            original_middle_output,
+            original_tmpbuf,
+            ..
+        } = self;
+
+        ChobitDecoder::<OUT, MIDDLE, IN> {
+            lstm: lstm.drop(),
+            output_layer: output_layer.drop(),
+            input: original_input,
+            prev_state: original_prev_state,
+            state: original_state,
+            middle_output: original_middle_output,
+            tmpbuf: original_tmpbuf
+        }
+    }
+
+    /// Clears internal data for study.
+    #[inline]
+    pub fn clear_study_data(&mut self) {
+        self.lstm.clear_study_data();
+        self.output_layer.clear_study_data();
+    }
+
+    /// Sets [`Optimizer`] to all layers.
+    ///
+    /// See [`MlLayer::set_optimizer()`] for details.
+    ///
+    /// - `optimizer` : Optimizer.
+    #[inline]
+    pub fn set_optimizer(&mut self, optimizer: Optimizer) {
+        self.lstm.set_optimizer(optimizer);
+        self.output_layer.set_optimizer(optimizer);
+    }
+
+    /// Writes information on [`MlDecoderCache`] for [`study()`](Self::study()).
+    ///
+    /// - `input` : Input.
+    /// - `prev_state` : Previous state.
+    /// - `output_len` : Length of output sequence.
+    /// - `cache` : Cache.
+    pub fn ready(
+        &mut self,
+        input: &MathVec<IN>,
+        prev_state: &MathVec<MIDDLE>,
+        output_len: usize,
+        cache: &mut MlDecoderCache<OUT, MIDDLE, IN>
+    ) {
+        self.ready_steps(input, prev_state, output_len, cache, |_, _, _| {});
+
+        cache.feeds_back = false;
+    }
+
+    /// Writes information on [`MlDecoderCache`] for [`study()`](Self::study()), feeding previous output as input.
+    ///
+    /// Input of the first step is `input`. From the second step, input is made by `to_input` from previous correct output if `use_teacher` returns `true`, otherwise from previous output of AI.
+    ///
+    /// In [`study()`](Self::study()), `input_error` is error only for `input` of the first step, because error is not backpropagated through previous output.
+    ///
+    /// ```ignore
+    /// // Probability of teacher forcing decreases by epoch.
+    /// let schedule = SamplingSchedule::InverseSigmoid {k: 10.0};
+    ///
+    /// for epoch in 0..EPOCH {
+    ///     let feeding = InputFeeding::scheduled(&schedule, epoch);
+    ///
+    ///     decoder.ready_with_feeding(
+    ///         &input,
+    ///         &initial_state,
+    ///         &train_out,
+    ///         feeding.chooser(|| rng.next_f64() as f32),
+    ///         |output, input| input.load_u32_label(output.to_u32_label()),
+    ///         &mut cache
+    ///     );
+    ///
+    ///     // Calculates error, studies and updates as ready().
+    /// }
+    /// ```
+    ///
+    /// - `input` : Input of the first step.
+    /// - `prev_state` : Previous state.
+    /// - `train_out` : Correct output. Its length is length of output sequence.
+    /// - `use_teacher` : Closure that returns `true` to feed previous correct output. It is made by [`InputFeeding::chooser()`].
+    /// - `to_input` : Closure that writes input made from previous output.
+    /// - `cache` : Cache.
+    pub fn ready_with_feeding<T, M>(
+        &mut self,
+        input: &MathVec<IN>,
+        prev_state: &MathVec<MIDDLE>,
+        train_out: &[MathVec<OUT>],
+        mut use_teacher: T,
+        mut to_input: M,
+        cache: &mut MlDecoderCache<OUT, MIDDLE, IN>
+    ) where
+        T: FnMut() -> bool,
+        M: FnMut(&MathVec<OUT>, &mut MathVec<IN>)
+    {
+        self.ready_steps(
+            input,
+            prev_state,
+            train_out.len(),
+            cache,
+            |i, output, next_input| if use_teacher() {
+                to_input(&train_out[i], next_input);
+            } else {
+                to_input(output, next_input);
+            }
+        );
+
+        cache.feeds_back = true;
+    }
+
+    // `next_input` receives index of step, output of the step and buffer for
+    // input of the next step.
+    fn ready_steps<F>(
+        &mut self,
+        input: &MathVec<IN>,
+        prev_state: &MathVec<MIDDLE>,
+        output_len: usize,
+        cache: &mut MlDecoderCache<OUT, MIDDLE, IN>,
+        mut next_input: F
+    ) where F: FnMut(usize, &MathVec<OUT>, &mut MathVec<IN>) {
+        cache.caches_len = output_len;
+        if cache.caches.len() < output_len {
+            cache.caches.resize(
+                output_len,
+                (
+                    MlLstmStateCache::<MIDDLE, IN>::new(),
+                    MlLstmOutputCache::<MIDDLE, IN>::new(),
+                    MlCache::<OUT, MIDDLE>::new()
+                )
+            );
+        }
+
+        self.prev_state.copy_from(prev_state);
+        self.tmp_input.copy_from(input);
+
+        cache.caches_mut().iter_mut().enumerate().for_each(|(i, (
+            lstm_state_cache,
+            lstm_output_cache,
+            output_layer_cache
+        ))| {
+            self.lstm.ready_state_cache(
+                &self.tmp_input,
+                &self.prev_state,
+                lstm_state_cache
+            );
+            self.prev_state.copy_from(&lstm_state_cache.state);
+
+            self.lstm.ready_output_cache(
+                lstm_state_cache,
+                lstm_output_cache
+            );
+
+            self.output_layer.ready(
+                &lstm_output_cache.output,
+                None,
+                output_layer_cache
+            );
+
+            if i + 1 < output_len {
+                next_input(i, &output_layer_cache.output, &mut self.tmp_input);
+            }
+        });
+    }
+
+    /// Accumulates gradient.
+    ///
+    /// - `output_error` : Backpropagated ouput error.
+    /// - `cache` : Cache.
+    /// - `input_error` : Error to backpropagate for previous output error.
+    /// - `prev_state_error` : Error to backpropagate for previous state error.
+    pub fn study(
+        &mut self,
+        output_error: &[MathVec<OUT>],
+        cache: &MlDecoderCache<OUT, MIDDLE, IN>,
+        input_error: &mut MathVec<IN>,
+        prev_state_error: &mut MathVec<MIDDLE>
+    ) {
+        self.tmp_state_error.clear();
+        input_error.clear();
+
+        cache.caches().iter().zip(
+            output_error.iter()
+        ).enumerate().rev().for_each(|(i, (
+            (
+                lstm_state_cache,
+                lstm_output_cache,
+                output_layer_cache
+            ),
+            output_error_one
+        ))| {
+            self.output_layer.study(
+                output_error_one,
+                None,
+                &output_layer_cache,
+                &mut self.tmp_middle_output_error,
+                None
+            );
+
+            self.lstm.study(
+                &self.tmp_middle_output_error,
+                &self.tmp_state_error,
+                lstm_state_cache,
+                lstm_output_cache,
+                &mut self.tmp_input_error,
+                prev_state_error
+            );
+            self.tmp_state_error.copy_from(prev_state_error);
+
+            if !cache.feeds_back || (i == 0) {
+                *input_error += &self.tmp_input_error;
+            }
+        });
+    }
+
+    /// Updates weights with learning rate from [`LrScheduler`], and advances step of scheduler.
+    ///
+    /// - `scheduler` : Scheduler of learning rate.
+    #[inline]
+    pub fn update_with_scheduler(&mut self, scheduler: &mut LrScheduler) {
+        self.update(scheduler.next_rate());
+    }
+
+    /// Updates weights.
+    ///
+    /// - `rate` : Learning rate.
+    #[inline]
+    pub fn update(&mut self, rate: f32) {
+        self.lstm.update(rate);
+        self.output_layer.update(rate);
+    }
+
+    /// Accesses each immutable total gradient with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_total_grad<F>(
+        &self,
+        mut f: F
+    ) where F: FnMut(&f32) {
+        self.lstm.for_each_total_grad(|val| {f(val)});
+        self.output_layer.for_each_grad(&mut f);
+    }
+
+    /// Accesses each mutable total gradient with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_total_grad_mut<F>(
+        &mut self,
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.lstm.for_each_total_grad_mut(|val| {f(val)});
+        self.output_layer.for_each_grad_mut(&mut f);
+    }
+
+    /// Clips each total gradient into `[-limit, limit]`.
+    ///
+    /// - `limit` : Limit of absolute value. Negative value is treated as its absolute value. If NaN, nothing is clipped.
+    #[inline]
+    pub fn clip_grad_by_value(&mut self, limit: f32) {
+        clip_by_value(limit, |f| self.for_each_total_grad_mut(f));
+    }
+
+    /// Scales all total gradients so that L2 norm of them is `max_norm` or less.
+    ///
+    /// - `max_norm` : Max L2 norm. Negative value is treated as its absolute value.
+    /// - _Return_ : L2 norm before clipping. If it is not finite, nothing is scaled.
+    #[inline]
+    pub fn clip_grad_by_global_norm(&mut self, max_norm: f32) -> f32 {
+        clip_by_global_norm(max_norm, |f| self.for_each_total_grad_mut(f))
+    }
+
+    /// Adds gradient of L1 and L2 penalty of weights into total gradients of all layers.
+    ///
+    /// See [`MlLayer::add_weight_penalty()`] for details.
+    ///
+    /// - `l1` : Coefficient of L1 penalty.
+    /// - `l2` : Coefficient of L2 penalty.
+    #[inline]
+    pub fn add_weight_penalty(&mut self, l1: f32, l2: f32) {
+        self.lstm.add_weight_penalty(l1, l2);
+        self.output_layer.add_weight_penalty(l1, l2);
+    }
+
+    /// Checks that all total gradients and all weights are finite.
+    ///
+    /// See [`ChobitAiError::NonFinite`] for order of checking.
+    /// If error is returned, you can abort machine learning, or skip the batch by clearing total gradients with [`for_each_total_grad_mut()`](Self::for_each_total_grad_mut()).
+    ///
+    /// - _Return_ : [`ChobitAiError::NonFinite`] if non-finite number is found.
+    pub fn check_finite(&self) -> Result<(), ChobitAiError> {
+        const NAMES: [&str; 9] = [
+            "lstm.main_layer",
+            "lstm.f_gate",
+            "lstm.i_gate",
+            "lstm.o_gate",
+            "lstm.main_layer_norm",
+            "lstm.f_gate_norm",
+            "lstm.i_gate_norm",
+            "lstm.o_gate_norm",
+            "lstm.state_norm"
+        ];
+
+        self.output_layer.check_grad("output_layer")?;
+        self.lstm.check_grad(&NAMES)?;
+
+        self.output_layer.check_weights("output_layer")?;
+        self.lstm.check_weights(&NAMES)?;
+
+        Ok(())
+    }
+
+    /// Writes full state for machine learning into buffer as binary data.
+    ///
+    /// See [`MlLayer::save_checkpoint()`] for details.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save_checkpoint(&self, buffer: &mut Vec<u8>) {
+        write_header(buffer, ModelKind::ChobitMlDecoder, &[OUT, MIDDLE, IN]);
+
+        self.lstm.write_state(buffer);
+        self.output_layer.write_state(buffer);
+    }
+
+    /// Creates ChobitMlDecoder from binary data written by [`save_checkpoint()`](Self::save_checkpoint()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : ChobitMlDecoder.
+    pub fn load_checkpoint(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(
+            ModelKind::ChobitMlDecoder,
+            &[("OUT", OUT), ("MIDDLE", MIDDLE), ("IN", IN)]
+        )?;
+
+        let mut ret =
+            Self::new(ChobitDecoder::<OUT, MIDDLE, IN>::new(Activation::Linear));
+
+        ret.lstm = MlLstm::<MIDDLE, IN>::read_state(&mut reader)?;
+        ret.output_layer = MlLayer::<OUT, MIDDLE>::read_state(&mut reader)?;
+
+        Ok(ret)
+    }
+}
+
+/// Seq2Seq AI.
+///
+/// - `OUT` : Dimension of output.
+/// - `MIDDLE` : Dimension of hidden layer.
+/// - `IN` : Dimension of input.
+///
+/// # Example
+///
+/// ChatCHOBIT
+/// - If inputs Japanese word, outputs "これは日本語です。"
+/// - If inputs English word, outputs "This is English."
+///
+/// (1) Defines word generator and messages.
+///
+/// ```ignore
+/// extern crate chobitlibs;
+/// 
+/// use chobitlibs::chobit_ai::{
+///     MathVec,
+///     Activation,
+///     ChobitSeqAi,
+///     ChobitMlSeqAi,
+///     MlSeqAiCache
+/// };
+/// 
+/// use chobitlibs::chobit_rand::ChobitRand;
+/// 
+/// fn japanese_letter(rng: &mut ChobitRand) -> char {
+///     let letters = [
+///         'あ', 'い', 'う', 'え', 'お',
+///         'か', 'き', 'く', 'け', 'こ',
+///         'さ', 'し', 'す', 'せ', 'そ'
+///     ];
+/// 
+///     letters[(rng.next_u64() as usize) % letters.len()]
+/// }
+/// 
+/// fn english_letter(rng: &mut ChobitRand) -> char {
+///     let letters = [
+///         'a', 'b', 'c', 'd', 'e',
+///         'f', 'g', 'h', 'i', 'j',
+///         'k', 'l', 'm', 'n', 'o'
+///     ];
+/// 
+///     letters[(rng.next_u64() as usize) % letters.len()]
+/// }
+/// 
+/// fn gen_word(
+///     f: fn(&mut ChobitRand) -> char,
+///     rng: &mut ChobitRand,
+///     max_len: usize
+/// ) -> String {
+///     let word_len = ((rng.next_u64() as usize) % max_len) + 1;
+/// 
+///     let mut ret = String::with_capacity(word_len);
+/// 
+///     for _ in 0..word_len {
+///         ret.push(f(rng));
+///     }
+/// 
+///     ret
+/// }
+/// 
+/// fn write_string_to_slice(string: &str, slice: &mut [MathVec<32>]) {
+///     string.chars().zip(slice.iter_mut()).for_each(|(c, s)| {
+///         s.load_u32_label(c as u32);
+///     });
+/// }
+/// 
+/// const JAPANESE_MESSAGE: &str = "これは日本語です。";
+/// const ENGLISH_MESSAGE: &str = "This is English.";
+/// ```
+///
+/// (2) Creates [`ChobitSeqAi`] and randomises weights.
+///
+/// ```ignore
+/// const OUT: usize = 32;
+/// const MIDDLE: usize = 64;
+/// const IN: usize = 32;
+///
+/// const MAX_WORD_LEN: usize = 10;
+/// let max_message_len = JAPANESE_MESSAGE.len().max(ENGLISH_MESSAGE.len());
+///
+/// let mut rng = ChobitRand::new(b"ChobitSeqAi Example");
+///
+/// let mut ai = ChobitSeqAi::<OUT, MIDDLE, IN>::new(Activation::SoftSign);
+///
+/// // Randomises weights.
+/// ai.for_each_weight_mut(|weight| {
+///     *weight = ((rng.next_f64() as f32) * 2.0) - 1.0;
+/// });
+///
+/// let mut input = vec![MathVec::<IN>::new(); MAX_WORD_LEN];
+/// let mut output = vec![MathVec::<OUT>::new(); max_message_len];
+/// let initial_state = MathVec::<MIDDLE>::new();
+/// ```
+///
+/// (3) Wraps AI with [`ChobitMlSeqAi`] for machine learning.
+///
+/// ```ignore
+/// let mut ai = ChobitMlSeqAi::<OUT, MIDDLE, IN>::new(ai);
+/// let mut cache = MlSeqAiCache::<OUT, MIDDLE, IN>::new(
+///     MAX_WORD_LEN,
+///     max_message_len
+/// );
+///
+/// let mut input_error = vec![MathVec::<IN>::new(); MAX_WORD_LEN];
+/// let mut output_error = vec![MathVec::<OUT>::new(); max_message_len];
+/// let mut prev_state_error = MathVec::<MIDDLE>::new();
+/// ```
+///
+/// (4) Machine learning.
+///
+/// ```ignore
+/// const EPOCH: usize = 10000;
+/// const BATCH_SIZE: usize = 10;
+/// const RATE: f32 = 0.01;
+///
+/// let japanese_message_len = JAPANESE_MESSAGE.chars().count();
+/// let english_message_len = ENGLISH_MESSAGE.chars().count();
+///
+/// for _ in 0..EPOCH {
+///     for _ in 0..BATCH_SIZE {
+///         //--- Learns Japanese ---//
+///         let string = gen_word(japanese_letter, &mut rng, MAX_WORD_LEN);
+///
+///         write_string_to_slice(&string, &mut input);
+///         write_string_to_slice(&JAPANESE_MESSAGE, &mut output);
+///
+///         // Writes cache.
+///         ai.ready(
+///             &input[..string.chars().count()],
+///             &initial_state,
+///             japanese_message_len,
+///             &mut cache
+///         );
+///
+///         // Calculates error.
+///         cache.calc_output_error(
+///             &output[..japanese_message_len],
+///             &mut output_error[..japanese_message_len]
+///         );
+///
+///         // Studies.
+///         ai.study(
+///             &output_error[..japanese_message_len],
+///             &cache,
+///             &mut input_error,
+///             &mut prev_state_error
+///         );
+///
+///         //--- Learns English ---//
+///         let string = gen_word(english_letter, &mut rng, MAX_WORD_LEN);
+///
+///         write_string_to_slice(&string, &mut input);
+///         write_string_to_slice(&ENGLISH_MESSAGE, &mut output);
+///
+///         // Writes cache.
+///         ai.ready(
+///             &input[..string.chars().count()],
+///             &initial_state,
+///             english_message_len,
+///             &mut cache
+///         );
+///
+///         // Calculates error.
+///         cache.calc_output_error(
+///             &output[..english_message_len],
+///             &mut output_error[..english_message_len]
+///         );
+///
+///         // Studies.
+///         ai.study(
+///             &output_error[..english_message_len],
+///             &cache,
+///             &mut input_error,
+///             &mut prev_state_error
+///         );
+///     }
+///
+///     // Updates weights.
+///     ai.update(RATE);
+/// }
+/// ```
+///
+/// (5) Tests AI.
+///
+/// ```ignore
+/// // Unwrap AI.
+/// let mut ai = ai.drop();
+///
+/// let mut output = MathVec::<OUT>::new();
+///
+/// // Tests Japanese.
+/// for _ in 0..10 {
+///     let string = gen_word(japanese_letter, &mut rng, MAX_WORD_LEN);
+///
+///     write_string_to_slice(&string, &mut input);
+///
+///     // Initializes state.
+///     ai.state_mut().copy_from(&initial_state);
+///     
+///     // Inputs for each one.
+///     input.iter().for_each(|input_one| {
+///         ai.input_next(input_one);
+///     });
+///
+///     // Outputs for each one.
+///     JAPANESE_MESSAGE.chars().for_each(|c| {
+///         ai.output_next(&mut output);
+///
+///         assert_eq!(output.to_u32_label(), c as u32);
+///     });
+/// }
+///
+/// // Tests English.
+/// for _ in 0..10 {
+///     let string = gen_word(english_letter, &mut rng, MAX_WORD_LEN);
+///
+///     write_string_to_slice(&string, &mut input);
+///
+///     // Initializes state.
+///     ai.state_mut().copy_from(&initial_state);
+///     
+///     // Inputs for each one.
+///     input.iter().for_each(|input_one| {
+///         ai.input_next(input_one);
+///     });
+///
+///     // Outputs for each one.
+///     ENGLISH_MESSAGE.chars().for_each(|c| {
+///         ai.output_next(&mut output);
+///
+///         assert_eq!(output.to_u32_label(), c as u32);
+///     });
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChobitSeqAi<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> {
+    enc_layer: Lstm<MIDDLE, IN>,
+    dec_layer: Lstm<MIDDLE, MIDDLE>,
+    output_layer: Layer<OUT, MIDDLE>,
+
+    prev_state: MathVec<MIDDLE>,
+    state: MathVec<MIDDLE>,
+    enc_output: MathVec<MIDDLE>,
+    dec_output: MathVec<MIDDLE>,
+
+    tmpbuf: MathVec<MIDDLE>
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> ChobitSeqAi<OUT, MIDDLE, IN> {
+    /// Creates ChobitSeqAi.
+    ///
+    /// - `activation` : Activation function for output layer.
+    /// - _Return_ : ChobitSeqAi.
+    #[inline]
+    pub fn new(activation: Activation) -> Self {
+        Self::with_layers(
+            Lstm::<MIDDLE, IN>::new(),
+            Lstm::<MIDDLE, MIDDLE>::new(),
+            Layer::<OUT, MIDDLE>::new(activation, false)
+        )
+    }
+
+    /// Creates ChobitSeqAi with activation functions.
+    ///
+    /// - `hidden` : Activation function for main layers of both Lstm and for their states before output gate. (See [`Lstm::with_activations()`].)
+    /// - `output` : Activation function for output layer.
+    /// - _Return_ : ChobitSeqAi.
+    #[inline]
+    pub fn with_activations(hidden: Activation, output: Activation) -> Self {
+        Self::with_layers(
+            Lstm::<MIDDLE, IN>::with_activations(
+                hidden,
+                Activation::Sigmoid,
+                hidden
+            ),
+            Lstm::<MIDDLE, MIDDLE>::with_activations(
+                hidden,
+                Activation::Sigmoid,
+                hidden
+            ),
+            Layer::<OUT, MIDDLE>::new(output, false)
+        )
+    }
+
+    /// Creates ChobitSeqAi with layers.
+    ///
+    /// - `enc_layer` : Lstm for encoding.
+    /// - `dec_layer` : Lstm for decoding.
+    /// - `output_layer` : Output layer. State weights of it are not used.
+    /// - _Return_ : ChobitSeqAi.
+    #[inline]
+    pub fn with_layers(
+        enc_layer: Lstm<MIDDLE, IN>,
+        dec_layer: Lstm<MIDDLE, MIDDLE>,
+        output_layer: Layer<OUT, MIDDLE>
+    ) -> Self {
+        Self {
+            enc_layer: enc_layer,
+            dec_layer: dec_layer,
+            output_layer: output_layer,
+
+            prev_state: MathVec::<MIDDLE>::new(),
+            state: MathVec::<MIDDLE>::new(),
+            enc_output: MathVec::<MIDDLE>::new(),
+            dec_output: MathVec::<MIDDLE>::new(),
+
+            tmpbuf: MathVec::<MIDDLE>::new()
+        }
+    }
+
+    /// Gets immutable encoding layer of Lstm.
+    ///
+    /// - _Return_ : Lstm.
+    #[inline]
+    pub fn enc_layer(&self) -> &Lstm<MIDDLE, IN> {&self.enc_layer}
+
+    /// Gets mutable encoding layer of Lstm.
+    ///
+    /// - _Return_ : Lstm.
+    #[inline]
+    pub fn enc_layer_mut(&mut self) -> &mut Lstm<MIDDLE, IN> {
+        &mut self.enc_layer
+    }
+
+    /// Gets immutable decoding layer of Lstm.
+    ///
+    /// - _Return_ : Lstm.
+    #[inline]
+    pub fn dec_layer(&self) -> &Lstm<MIDDLE, MIDDLE> {&self.dec_layer}
+
+    /// Gets mutable decoding layer of Lstm.
+    ///
+    /// - _Return_ : Lstm.
+    #[inline]
+    pub fn dec_layer_mut(&mut self) -> &mut Lstm<MIDDLE, MIDDLE> {
+        &mut self.dec_layer
+    }
+
+    /// Gets immutable output layer.
+    ///
+    /// - _Return_ : Output layer.
+    #[inline]
+    pub fn output_layer(&self) -> &Layer<OUT, MIDDLE> {&self.output_layer}
+
+    /// Gets mutable output layer.
+    ///
+    /// - _Return_ : Output layer.
+    #[inline]
+    pub fn output_layer_mut(&mut self) -> &mut Layer<OUT, MIDDLE> {
+        &mut self.output_layer
+    }
+
+    /// Initializes weights with [`Initializer`].
+    ///
+    /// Lstm is initialized by [`Lstm::init_weights()`] with `initializer` for input weights and [`Initializer::Orthogonal`] for state weights.
+    ///
+    /// - `initializer` : Initializer for output layer and input weights of Lstm.
+    /// - `rand` : Closure that returns uniform random number in `[0.0, 1.0)`.
+    pub fn init_weights<F>(&mut self, initializer: Initializer, mut rand: F)
+    where F: FnMut() -> f32 {
+        self.enc_layer.init_weights(
+            initializer,
+            Initializer::Orthogonal,
+            &mut rand
+        );
+        self.dec_layer.init_weights(
+            initializer,
+            Initializer::Orthogonal,
+            &mut rand
+        );
+
+        self.output_layer.init_weights(initializer, &mut rand);
+    }
+
+    /// Gets immutable state.
+    ///
+    /// This should be initialized before the first [`input_next()`](Self::input_next).
+    ///
+    /// - _Return_ : State.
+    #[inline]
+    pub fn state(&self) -> &MathVec<MIDDLE> {&self.state}
+
+    /// Gets mutable state.
+    ///
+    /// This should be initialized before the first [`input_next()`](Self::input_next).
+    ///
+    /// - _Return_ : State.
+    #[inline]
+    pub fn state_mut(&mut self) -> &mut MathVec<MIDDLE> {
+        &mut self.state
+    }
+
+    /// Gets immutable input of decoder.
+    ///
+    /// It is the last output of encoder, written by [`input_next()`](Self::input_next()).
+    ///
+    /// - _Return_ : Input of decoder.
+    #[inline]
+    pub fn decoder_input(&self) -> &MathVec<MIDDLE> {&self.enc_output}
+
+    /// Gets mutable input of decoder.
+    ///
+    /// For AI trained by [`ChobitMlSeqAi::ready_with_feeding()`], overwrite it with input made from previous output before [`output_next()`](Self::output_next()).
+    ///
+    /// - _Return_ : Input of decoder.
+    #[inline]
+    pub fn decoder_input_mut(&mut self) -> &mut MathVec<MIDDLE> {
+        &mut self.enc_output
+    }
+
+    /// Input next data.
+    ///
+    /// - `input` : Next data.
+    #[inline]
+    pub fn input_next(&mut self, input: &MathVec<IN>) {
+        self.prev_state.copy_from(&self.state);
+
+        self.enc_layer.calc(
+            input,
+            &self.prev_state,
+            &mut self.enc_output,
+            &mut self.state,
+            &mut self.tmpbuf
+        );
+    }
+
+    /// Output next data calculated by current state and input.
+    ///
+    /// - `output` : Buffer for next data.
+    #[inline]
+    pub fn output_next(&mut self, output: &mut MathVec<OUT>) {
+        self.prev_state.copy_from(&self.state);
+
+        self.dec_layer.calc(
+            &self.enc_output,
+            &self.prev_state,
+            &mut self.dec_output,
+            &mut self.state,
+            &mut self.tmpbuf
+        );
+
+        self.output_layer.calc(&self.dec_output, None, output);
+    }
+
+    /// Outputs prediction for loss function used in machine learning.
+    ///
+    /// This is [`output_next()`](Self::output_next()) and [`Loss::predict()`].
+    ///
+    /// - `loss` : Loss function used in machine learning.
+    /// - `output` : Buffer for prediction.
+    #[inline]
+    pub fn output_next_with_loss(
+        &mut self,
+        loss: &Loss,
+        output: &mut MathVec<OUT>
+    ) {
+        self.output_next(output);
+        loss.predict(output);
+    }
+
+    /// Generates sequence of tokens.
+    ///
+    /// See [`Decoding`] for how to choose token. Input should be given by [`input_next()`](Self::input_next()) before. After generation, state and [`decoder_input()`](Self::decoder_input()) are ready to output the token next to the returned sequence.
+    ///
+    /// Input of decoder of the first step is the last output of encoder. From the second step, it is written into [`decoder_input_mut()`](Self::decoder_input_mut()) by `to_input` from previous token, like [`ChobitMlSeqAi::ready_with_feeding()`]. For AI trained by [`ChobitMlSeqAi::ready()`], give `to_input` that does nothing.
+    ///
+    /// - `decoding` : Strategy to choose token.
+    /// - `max_len` : Max length of sequence.
+    /// - `rand` : Closure that returns uniform random number in `[0.0, 1.0)`. It is used only by [`Decoding::Sampling`].
+    /// - `to_input` : Closure that writes input of decoder for a token.
+    /// - `stop` : Closure that receives generated sequence and returns `true` to stop generation. The sequence includes the last token.
+    /// - _Return_ : Generated sequence of tokens.
+    pub fn generate<R, M, S>(
+        &mut self,
+        decoding: &Decoding,
+        max_len: usize,
+        rand: R,
+        mut to_input: M,
+        stop: S
+    ) -> Vec<usize>
+    where
+        R: FnMut() -> f32,
+        M: FnMut(usize, &mut MathVec<MIDDLE>),
+        S: FnMut(&[usize]) -> bool
+    {
+        let first_input = self.enc_output.clone();
+
+        let (tokens, state) = generate_tokens::<OUT, _, _, _, _>(
+            decoding,
+            max_len,
+            self.state.clone(),
+            |state, prev_token, output| {
+                match prev_token {
+                    Some(token) => to_input(token, &mut self.enc_output),
+                    None => self.enc_output.copy_from(&first_input)
+                }
+
+                self.state.copy_from(state);
+                self.output_next(output);
+                state.copy_from(&self.state);
+            },
+            stop,
+            rand
+        );
+
+        self.state.copy_from(&state);
+
+        match tokens.last() {
+            Some(token) => to_input(*token, &mut self.enc_output),
+            None => self.enc_output.copy_from(&first_input)
+        }
+
+        tokens
+    }
+
+    /// Accesses each immutable weight with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_weight<F>(&self, mut f: F) where F: FnMut(&f32) {
+        self.enc_layer.for_each_weight(|val| {f(val)});
+        self.dec_layer.for_each_weight(|val| {f(val)});
+        self.output_layer.for_each_weight(|val| {f(val)});
+    }
+
+    /// Accesses each mutable weight with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_weight_mut<F>(
+        &mut self,
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.enc_layer.for_each_weight_mut(|val| {f(val)});
+        self.dec_layer.for_each_weight_mut(|val| {f(val)});
+        self.output_layer.for_each_weight_mut(|val| {f(val)});
+    }
+
+    /// Writes this AI into buffer as binary data.
+    ///
+    /// State is not written. See [`ChobitAi::save()`] for details.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save(&self, buffer: &mut Vec<u8>) {
+        write_header(buffer, ModelKind::ChobitSeqAi, &[OUT, MIDDLE, IN]);
+
+        self.enc_layer.write_descriptor(buffer);
+        self.dec_layer.write_descriptor(buffer);
+        self.output_layer.write_descriptor(buffer);
+
+        self.enc_layer.write_body(buffer);
+        self.dec_layer.write_body(buffer);
+        self.output_layer.weights.write_body(buffer);
+    }
+
+    /// Creates ChobitSeqAi from binary data written by [`save()`](Self::save()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : ChobitSeqAi.
+    pub fn load(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(
+            ModelKind::ChobitSeqAi,
+            &[("OUT", OUT), ("MIDDLE", MIDDLE), ("IN", IN)]
+        )?;
+
+        let mut enc_layer = Lstm::<MIDDLE, IN>::read_descriptor(&mut reader)?;
+        let mut dec_layer =
+            Lstm::<MIDDLE, MIDDLE>::read_descriptor(&mut reader)?;
+        let mut output_layer =
+            Layer::<OUT, MIDDLE>::read_descriptor(&mut reader)?;
+
+        enc_layer.read_body(&mut reader)?;
+        dec_layer.read_body(&mut reader)?;
+        output_layer.weights.read_body(&mut reader)?;
+
+        let mut ret = Self::new(Activation::Linear);
+
+        ret.enc_layer = enc_layer;
+        ret.dec_layer = dec_layer;
+        ret.output_layer = output_layer;
+
+        Ok(ret)
+    }
+}
+
+/// Cache for [`ChobitMlSeqAi`].
+///
+/// - `OUT` : `OUT` of [`ChobitMlSeqAi`].
+/// - `MIDDLE` : `MIDDLE` of [`ChobitMlSeqAi`].
+/// - `IN` : `IN` of [`ChobitMlSeqAi`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlSeqAiCache<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> {
+    enc_state_caches: Vec<MlLstmStateCache<MIDDLE, IN>>,
+    enc_state_caches_len: usize,
+    enc_output_cache: Option<MlLstmOutputCache<MIDDLE, IN>>,
+
+    dec_caches: Vec<(
+        MlLstmStateCache<MIDDLE, MIDDLE>,
+        MlLstmOutputCache<MIDDLE, MIDDLE>,
+        MlCache<OUT, MIDDLE>
+    )>,
+    dec_caches_len: usize,
+    dec_feeds_back: bool
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> MlSeqAiCache<OUT, MIDDLE, IN> {
+    /// Creates MlSeqAiCache.
+    ///
+    /// - `input_capacity` : Capacity of input caches.
+    /// - `output_capacity` : Capacity of output caches.
+    /// - _Return_ : MlSeqAiCache.
+    #[inline]
+    pub fn new(input_capacity: usize, output_capacity: usize) -> Self {
+        Self {
+            enc_state_caches: vec![
+                MlLstmStateCache::<MIDDLE, IN>::new(); input_capacity
+            ],
+            enc_state_caches_len: 0,
+
+            enc_output_cache: Some(MlLstmOutputCache::<MIDDLE, IN>::new()),
+
+            dec_caches: vec![
+                (
+                    MlLstmStateCache::<MIDDLE, MIDDLE>::new(),
+                    MlLstmOutputCache::<MIDDLE, MIDDLE>::new(),
+                    MlCache::<OUT, MIDDLE>::new(),
+                ); output_capacity
+            ],
+            dec_caches_len: 0,
+            dec_feeds_back: false
+        }
+    }
+
+    /// Calculates output error.
+    ///
+    /// | Formula |
+    /// |:-:|
+    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mi>e</mi> <mo stretchy="false">=</mo> <mrow> <mi>o</mi> <mo stretchy="false">−</mo> <mi>t</mi> </mrow> </mrow> </semantics> </math> |
+    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mtable columnalign="left"> <mtr> <mtd> <mrow> <mi>e</mi> <mo stretchy="false">≝</mo> <mtext>Error.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>o</mi> <mo stretchy="false">≝</mo> <mtext>Actual output.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>t</mi> <mo stretchy="false">≝</mo> <mtext>Correct output.</mtext> </mrow> </mtd> </mtr> </mtable> </semantics> </math> |
+    ///
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error(
+        &self,
+        train_out: &[MathVec<OUT>],
+        output_error: &mut [MathVec<OUT>]
+    ) {
+        train_out.iter().zip(
+            &self.dec_caches
+        ).zip(
+            output_error
+        ).for_each(
+            |((train_out_one, (_, _, cache)), output_error_one)| {
+                output_error_one.copy_from(&cache.output);
+                *output_error_one -= train_out_one;
+            }
+        );
+    }
+
+    /// Calculates output error with loss function.
+    ///
+    /// - `loss` : Loss function.
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error_with_loss(
+        &self,
+        loss: &Loss,
+        train_out: &[MathVec<OUT>],
+        output_error: &mut [MathVec<OUT>]
+    ) {
+        train_out.iter().zip(
+            &self.dec_caches
+        ).zip(
+            output_error
+        ).for_each(
+            |((train_out_one, (_, _, cache)), output_error_one)| {
+                loss.calc_error(&cache.output, train_out_one, output_error_one);
+            }
+        );
+    }
+
+    /// Gets slice of ['MlLstmStateCache'] for encoding layer.
+    ///
+    /// - _Return_ : slice of [`MlLstmStateCache`].
+    #[inline]
+    pub fn enc_state_caches(&self) -> &[MlLstmStateCache<MIDDLE, IN>] {
+        &self.enc_state_caches[..self.enc_state_caches_len]
+    }
+
+
+    /// Gets ['MlLstmOutputCache'] for encoding layer.
+    ///
+    /// - _Return_ : [`MlLstmOutputCache`].
+    #[inline]
+    pub fn enc_output_cache(&self) -> &MlLstmOutputCache<MIDDLE, IN> {
+        self.enc_output_cache.as_ref().unwrap()
+    }
+
+    #[inline]
+    pub(self) fn take_enc_output_cache(
+        &mut self
+    ) -> Option<MlLstmOutputCache<MIDDLE, IN>> {
+        self.enc_output_cache.take()
+    }
+
+    #[inline]
+    pub(self) fn set_enc_output_cache(
+        &mut self,
+        output_cache: MlLstmOutputCache<MIDDLE, IN>
+    ) {
+        self.enc_output_cache = Some(output_cache);
+    }
+
+    /// Gets slice of caches for decoding layer.
+    ///
+    /// - _Return_ : slice of caches.
+    #[inline]
+    pub fn dec_caches(&self) -> &[(
+        MlLstmStateCache<MIDDLE, MIDDLE>,
+        MlLstmOutputCache<MIDDLE, MIDDLE>,
+        MlCache<OUT, MIDDLE>
+    )] {
+        &self.dec_caches[..self.dec_caches_len]
+    }
+
+    #[inline]
+    pub(self) fn dec_caches_mut(&mut self) -> &mut [(
+        MlLstmStateCache<MIDDLE, MIDDLE>,
+        MlLstmOutputCache<MIDDLE, MIDDLE>,
+        MlCache<OUT, MIDDLE>
+    )] {
+        &mut self.dec_caches[..self.dec_caches_len]
+    }
+
+    #[inline]
+    pub(self) fn enc_last_state_cache(
+        &self
+    ) -> Option<&MlLstmStateCache<MIDDLE, IN>> {
+        self.enc_state_caches.get(self.enc_state_caches_len.wrapping_sub(1))
+    }
+
+    /// Accesses each output with closure.
+    ///
+    /// - `f` : Closure.
+    pub fn for_each_output<F>(&self, mut f: F) where F: FnMut(&MathVec<OUT>) {
+        self.dec_caches[..self.dec_caches_len].iter().for_each(
+            |(_, _, cache)| {f(&cache.output);}
+        );
+    }
+}
+
+/// Wrapper of [`ChobitSeqAi`] for machine learning.
+///
+/// See [`ChobitSeqAi`] for details.
+///
+/// - `OUT` : Dimension of output.
+/// - `MIDDLE` : Dimension of hidden layer.
+/// - `IN` : Dimension of input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChobitMlSeqAi<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> {
+    enc_layer: MlLstm<MIDDLE, IN>,
+    dec_layer: MlLstm<MIDDLE, MIDDLE>,
+    output_layer: MlLayer<OUT, MIDDLE>,
+
+    tmp_prev_state: MathVec<MIDDLE>,
+    tmp_state_error: MathVec<MIDDLE>,
+    tmp_enc_output: MathVec<MIDDLE>,
+    tmp_output_error: Vec<MathVec<OUT>>,
+    tmp_dec_output_error: MathVec<MIDDLE>,
+    tmp_enc_output_error: MathVec<MIDDLE>,
+    tmp_enc_output_error_one: MathVec<MIDDLE>,
+
+    original_prev_state: MathVec<MIDDLE>,
+    original_state: MathVec<MIDDLE>,
+    original_enc_output: MathVec<MIDDLE>,
+    original_dec_output: MathVec<MIDDLE>,
+
+    original_tmpbuf: MathVec<MIDDLE>
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> ChobitMlSeqAi<OUT, MIDDLE, IN> {
+    /// Creates ChobitMlSeqAi.
+    ///
+    /// - `ai` : Base [`ChobitSeqAi`].
+    /// - _Return_ : ChobitMlSeqAi.
+    #[inline]
+    pub fn new(ai: ChobitSeqAi<OUT, MIDDLE, IN>) -> Self {
+        let ChobitSeqAi::<OUT, MIDDLE, IN> {
+            enc_layer,
+            dec_layer,
+            output_layer,
+            prev_state,
+            state,
+            enc_output,
+            dec_output,
+            tmpbuf
+        } = ai;
+
+        Self {
+            enc_layer: MlLstm::<MIDDLE, IN>::new(enc_layer),
+            dec_layer: MlLstm::<MIDDLE, MIDDLE>::new(dec_layer),
+            output_layer: MlLayer::<OUT, MIDDLE>::new(output_layer),
+
+            tmp_prev_state: MathVec::<MIDDLE>::new(),
+            tmp_state_error: MathVec::<MIDDLE>::new(),
+            tmp_enc_output: MathVec::<MIDDLE>::new(),
+            tmp_output_error: Vec::<MathVec<OUT>>::new(),
+            tmp_dec_output_error: MathVec::<MIDDLE>::new(),
+            tmp_enc_output_error: MathVec::<MIDDLE>::new(),
+            tmp_enc_output_error_one: MathVec::<MIDDLE>::new(),
+
+            original_prev_state: prev_state,
+            original_state: state,
+            original_enc_output: enc_output,
+            original_dec_output: dec_output,
+            original_tmpbuf: tmpbuf
+        }
+    }
+
+    /// Drops base [`ChobitSeqAi`].
+    ///
+    /// - _Return_ : [`ChobitSeqAi`].
+    #[inline]
+    pub fn drop(self) -> ChobitSeqAi<OUT, MIDDLE, IN> {
+        let ChobitMlSeqAi::<OUT, MIDDLE, IN> {
+            enc_layer,
+            dec_layer,
+            output_layer,
+            original_prev_state,
+            original_state,
+            original_enc_output,
+            original_dec_output,
+            original_tmpbuf,
+            ..
+        } = self;
+
+        ChobitSeqAi::<OUT, MIDDLE, IN> {
+            enc_layer: enc_layer.drop(),
+            dec_layer: dec_layer.drop(),
+            output_layer: output_layer.drop(),
+
+            prev_state: original_prev_state,
+            state: original_state,
+            enc_output: original_enc_output,
+            dec_output: original_dec_output,
+
+            tmpbuf: original_tmpbuf
+        }
+    }
+
+    /// Clears internal data for study.
+    #[inline]
+    pub fn clear_study_data(&mut self) {
+        self.enc_layer.clear_study_data();
+        self.dec_layer.clear_study_data();
+        self.output_layer.clear_study_data();
+    }
+
+    /// Sets [`Optimizer`] to all layers.
+    ///
+    /// See [`MlLayer::set_optimizer()`] for details.
+    ///
+    /// - `optimizer` : Optimizer.
+    #[inline]
+    pub fn set_optimizer(&mut self, optimizer: Optimizer) {
+        self.enc_layer.set_optimizer(optimizer);
+        self.dec_layer.set_optimizer(optimizer);
+        self.output_layer.set_optimizer(optimizer);
+    }
+
+    /// Writes information on [`MlSeqAiCache`] for [`study()`](Self::study()).
+    ///
+    /// - `input` : Input.
+    /// - `prev_state` : Previous state.
+    /// - `output_len` : Length of output sequence.
+    /// - `cache` : Cache.
+    #[inline]
+    pub fn ready(
+        &mut self,
+        input: &[MathVec<IN>],
+        prev_state: &MathVec<MIDDLE>,
+        output_len: usize,
+        cache: &mut MlSeqAiCache<OUT, MIDDLE, IN>
+    ) {
+        self.ready_enc_layer(input, prev_state, cache);
+        self.ready_dec_layer(output_len, cache, |_, _, _| {});
+
+        cache.dec_feeds_back = false;
+    }
+
+    /// Writes information on [`MlSeqAiCache`] for [`study()`](Self::study()), feeding previous output as input of decoder.
+    ///
+    /// Input of decoder of the first step is the last output of encoder. From the second step, input of decoder is made by `to_input` from previous correct output if `use_teacher` returns `true`, otherwise from previous output of AI.
+    ///
+    /// Error is not backpropagated through previous output. For inference of AI trained by this, write input of decoder into [`ChobitSeqAi::decoder_input_mut()`] before each [`ChobitSeqAi::output_next()`] except the first, or use [`ChobitSeqAi::generate()`].
+    ///
+    /// See [`ChobitMlDecoder::ready_with_feeding()`] for example.
+    ///
+    /// - `input` : Input.
+    /// - `prev_state` : Previous state.
+    /// - `train_out` : Correct output. Its length is length of output sequence.
+    /// - `use_teacher` : Closure that returns `true` to feed previous correct output. It is made by [`InputFeeding::chooser()`].
+    /// - `to_input` : Closure that writes input of decoder made from previous output.
+    /// - `cache` : Cache.
+    pub fn ready_with_feeding<T, M>(
+        &mut self,
+        input: &[MathVec<IN>],
+        prev_state: &MathVec<MIDDLE>,
+        train_out: &[MathVec<OUT>],
+        mut use_teacher: T,
+        mut to_input: M,
+        cache: &mut MlSeqAiCache<OUT, MIDDLE, IN>
+    ) where
+        T: FnMut() -> bool,
+        M: FnMut(&MathVec<OUT>, &mut MathVec<MIDDLE>)
+    {
+        self.ready_enc_layer(input, prev_state, cache);
+        self.ready_dec_layer(
+            train_out.len(),
+            cache,
+            |i, output, next_input| if use_teacher() {
+                to_input(&train_out[i], next_input);
+            } else {
+                to_input(output, next_input);
+            }
+        );
+
+        cache.dec_feeds_back = true;
+    }
+
+    fn ready_enc_layer(
+        &mut self,
+        input: &[MathVec<IN>],
+        prev_state: &MathVec<MIDDLE>,
+        cache: &mut MlSeqAiCache<OUT, MIDDLE, IN>
+    ) {
+        cache.enc_state_caches_len = input.len();
+        if cache.enc_state_caches.len() < input.len() {
+            cache.enc_state_caches.resize(
+                input.len(),
+                MlLstmStateCache::<MIDDLE, IN>::new(),
+            );
+        }
+
+        self.tmp_prev_state.copy_from(prev_state);
+
+        input.iter().zip(
+            cache.enc_state_caches.iter_mut()
+        ).for_each(|(input_one, cache)| {
+            self.enc_layer.ready_state_cache(
+                input_one,
+                &self.tmp_prev_state,
+                cache
+            );
+
+            self.tmp_prev_state.copy_from(&cache.state);
+        });
+
+        if let Some(mut output_cache) = cache.take_enc_output_cache() {
+            if let Some(state_cache) = cache.enc_last_state_cache() {
+                self.enc_layer.ready_output_cache(
+                    state_cache,
+                    &mut output_cache
+                );
+            }
+
+            cache.set_enc_output_cache(output_cache);
+        }
+    }
+
+    // `next_input` receives index of step, output of the step and buffer for
+    // input of decoder of the next step.
+    fn ready_dec_layer<F>(
+        &mut self,
+        output_len: usize,
+        cache: &mut MlSeqAiCache<OUT, MIDDLE, IN>,
+        mut next_input: F
+    ) where F: FnMut(usize, &MathVec<OUT>, &mut MathVec<MIDDLE>) {
+        cache.dec_caches_len = output_len;
+        if cache.dec_caches.len() < output_len {
+            cache.dec_caches.resize(
+                output_len,
+                (
+                    MlLstmStateCache::<MIDDLE, MIDDLE>::new(),
+                    MlLstmOutputCache::<MIDDLE, MIDDLE>::new(),
+                    MlCache::<OUT, MIDDLE>::new()
+                )
+            );
+        }
+
+        match cache.enc_last_state_cache() {
+            Some(state_cache) => {
+                self.tmp_prev_state.copy_from(&state_cache.state);
+            },
+
+            None => {self.tmp_prev_state.clear();}
+        }
+
+        if let Some(enc_output_cache) = cache.take_enc_output_cache() {
+            self.tmp_enc_output.copy_from(&enc_output_cache.output);
+
+            cache.dec_caches_mut().iter_mut().enumerate().for_each(
+                |(i, (dec_state_cache, dec_output_cache, output_layer_cache))| {
+                    self.dec_layer.ready_state_cache(
+                        &self.tmp_enc_output,
+                        &self.tmp_prev_state,
+                        dec_state_cache
+                    );
+
+                    self.dec_layer.ready_output_cache(
+                        dec_state_cache,
+                        dec_output_cache
+                    );
+
+                    self.output_layer.ready(
+                        &dec_output_cache.output,
+                        None,
+                        output_layer_cache
+                    );
+
+                    self.tmp_prev_state.copy_from(&dec_state_cache.state);
+
+                    if i + 1 < output_len {
+                        next_input(
+                            i,
+                            &output_layer_cache.output,
+                            &mut self.tmp_enc_output
+                        );
+                    }
+                }
+            );
+
+            cache.set_enc_output_cache(enc_output_cache);
+        }
+    }
+
+    /// Accumulates gradient.
+    ///
+    /// - `output_error` : Backpropagated ouput error.
+    /// - `cache` : Cache.
+    /// - `input_error` : Error to backpropagate for previous output error.
+    /// - `prev_state_error` : Error to backpropagate for previous state error.
+    pub fn study(
+        &mut self,
+        output_error: &[MathVec<OUT>],
+        cache: &MlSeqAiCache<OUT, MIDDLE, IN>,
+        input_error: &mut [MathVec<IN>],
+        prev_state_error: &mut MathVec<MIDDLE>,
+    ) {
+        self.tmp_state_error.clear();
+        self.tmp_enc_output_error.clear();
+
+        cache.dec_caches().iter().zip(
+            output_error.iter()
+        ).enumerate().rev().for_each(
+            |(i, (
+                (dec_state_cache, dec_output_cache, output_layer_cache),
+                output_error_one
+            ))| {
+                self.output_layer.study(
+                    output_error_one,
+                    None,
+                    output_layer_cache,
+                    &mut self.tmp_dec_output_error,
+                    None
+                );
+
+                self.dec_layer.study(
+                    &self.tmp_dec_output_error,
+                    &self.tmp_state_error,
+                    dec_state_cache,
+                    dec_output_cache,
+                    &mut self.tmp_enc_output_error_one,
+                    prev_state_error
+                );
+
+                self.tmp_state_error.copy_from(prev_state_error);
+
+                if !cache.dec_feeds_back || (i == 0) {
+                    self.tmp_enc_output_error += &self.tmp_enc_output_error_one;
+                }
+            }
+        );
+
+        if let Some(output_cache) = cache.enc_output_cache.as_ref() {
+            let mut enc_state_caches_iter =
+                cache.enc_state_caches().iter().zip(
+                    input_error.iter_mut()
+                ).rev();
+
+            if let Some((state_cache, input_error_one)) =
+                enc_state_caches_iter.next()
+            {
+                self.enc_layer.study(
+                    &self.tmp_enc_output_error,
+                    &self.tmp_state_error,
+                    state_cache,
+                    output_cache,
+                    input_error_one,
+                    prev_state_error
+                );
+
+                self.tmp_state_error.copy_from(prev_state_error);
+            }
+
+            enc_state_caches_iter.for_each(
+                |(cache, input_error_one)| {
+                    self.enc_layer.study_state(
+                        &self.tmp_state_error,
+                        cache,
+                        input_error_one,
+                        prev_state_error
+                    );
+
+                    self.tmp_state_error.copy_from(prev_state_error);
+                }
+            );
+        }
+    }
+
+    /// Updates weights with learning rate from [`LrScheduler`], and advances step of scheduler.
+    ///
+    /// - `scheduler` : Scheduler of learning rate.
+    #[inline]
+    pub fn update_with_scheduler(&mut self, scheduler: &mut LrScheduler) {
+        self.update(scheduler.next_rate());
+    }
+
+    /// Updates weights.
+    ///
+    /// - `rate` : Learning rate.
+    #[inline]
+    pub fn update(&mut self, rate: f32) {
+        self.enc_layer.update(rate);
+        self.dec_layer.update(rate);
+        self.output_layer.update(rate);
+    }
+
+    /// Accesses each immutable total gradient with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_total_grad<F>(
+        &self,
+        mut f: F
+    ) where F: FnMut(&f32) {
+        self.enc_layer.for_each_total_grad(|val| {f(val)});
+        self.dec_layer.for_each_total_grad(|val| {f(val)});
+        self.output_layer.for_each_grad(&mut f);
+    }
+
+    /// Accesses each mutable total gradient with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_total_grad_mut<F>(
+        &mut self,
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.enc_layer.for_each_total_grad_mut(|val| {f(val)});
+        self.dec_layer.for_each_total_grad_mut(|val| {f(val)});
+        self.output_layer.for_each_grad_mut(&mut f);
+    }
+
+    /// Clips each total gradient into `[-limit, limit]`.
+    ///
+    /// - `limit` : Limit of absolute value. Negative value is treated as its absolute value. If NaN, nothing is clipped.
+    #[inline]
+    pub fn clip_grad_by_value(&mut self, limit: f32) {
+        clip_by_value(limit, |f| self.for_each_total_grad_mut(f));
+    }
+
+    /// Scales all total gradients so that L2 norm of them is `max_norm` or less.
+    ///
+    /// - `max_norm` : Max L2 norm. Negative value is treated as its absolute value.
+    /// - _Return_ : L2 norm before clipping. If it is not finite, nothing is scaled.
+    #[inline]
+    pub fn clip_grad_by_global_norm(&mut self, max_norm: f32) -> f32 {
+        clip_by_global_norm(max_norm, |f| self.for_each_total_grad_mut(f))
+    }
+
+    /// Adds gradient of L1 and L2 penalty of weights into total gradients of all layers.
+    ///
+    /// See [`MlLayer::add_weight_penalty()`] for details.
+    ///
+    /// - `l1` : Coefficient of L1 penalty.
+    /// - `l2` : Coefficient of L2 penalty.
+    #[inline]
+    pub fn add_weight_penalty(&mut self, l1: f32, l2: f32) {
+        self.enc_layer.add_weight_penalty(l1, l2);
+        self.dec_layer.add_weight_penalty(l1, l2);
+        self.output_layer.add_weight_penalty(l1, l2);
+    }
+
+    /// Checks that all total gradients and all weights are finite.
+    ///
+    /// See [`ChobitAiError::NonFinite`] for order of checking.
+    /// If error is returned, you can abort machine learning, or skip the batch by clearing total gradients with [`for_each_total_grad_mut()`](Self::for_each_total_grad_mut()).
+    ///
+    /// - _Return_ : [`ChobitAiError::NonFinite`] if non-finite number is found.
+    pub fn check_finite(&self) -> Result<(), ChobitAiError> {
+        const ENC_NAMES: [&str; 9] = [
+            "enc_layer.main_layer",
+            "enc_layer.f_gate",
+            "enc_layer.i_gate",
+            "enc_layer.o_gate",
+            "enc_layer.main_layer_norm",
+            "enc_layer.f_gate_norm",
+            "enc_layer.i_gate_norm",
+            "enc_layer.o_gate_norm",
+            "enc_layer.state_norm"
+        ];
+        const DEC_NAMES: [&str; 9] = [
+            "dec_layer.main_layer",
+            "dec_layer.f_gate",
+            "dec_layer.i_gate",
+            "dec_layer.o_gate",
+            "dec_layer.main_layer_norm",
+            "dec_layer.f_gate_norm",
+            "dec_layer.i_gate_norm",
+            "dec_layer.o_gate_norm",
+            "dec_layer.state_norm"
+        ];
+
+        self.output_layer.check_grad("output_layer")?;
+        self.dec_layer.check_grad(&DEC_NAMES)?;
+        self.enc_layer.check_grad(&ENC_NAMES)?;
+
+        self.output_layer.check_weights("output_layer")?;
+        self.dec_layer.check_weights(&DEC_NAMES)?;
+        self.enc_layer.check_weights(&ENC_NAMES)?;
+
+        Ok(())
+    }
+
+    /// Writes full state for machine learning into buffer as binary data.
+    ///
+    /// See [`MlLayer::save_checkpoint()`] for details.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save_checkpoint(&self, buffer: &mut Vec<u8>) {
+        write_header(buffer, ModelKind::ChobitMlSeqAi, &[OUT, MIDDLE, IN]);
+
+        self.enc_layer.write_state(buffer);
+        self.dec_layer.write_state(buffer);
+        self.output_layer.write_state(buffer);
+    }
+
+    /// Creates ChobitMlSeqAi from binary data written by [`save_checkpoint()`](Self::save_checkpoint()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : ChobitMlSeqAi.
+    pub fn load_checkpoint(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(
+            ModelKind::ChobitMlSeqAi,
+            &[("OUT", OUT), ("MIDDLE", MIDDLE), ("IN", IN)]
+        )?;
+
+        let mut ret =
+            Self::new(ChobitSeqAi::<OUT, MIDDLE, IN>::new(Activation::Linear));
+
+        ret.enc_layer = MlLstm::<MIDDLE, IN>::read_state(&mut reader)?;
+        ret.dec_layer = MlLstm::<MIDDLE, MIDDLE>::read_state(&mut reader)?;
+        ret.output_layer = MlLayer::<OUT, MIDDLE>::read_state(&mut reader)?;
+
+        Ok(ret)
+    }
+}
+
+/// Seq2Seq AI with [`Attention`].
+///
+/// This is a variant of [`ChobitSeqAi`] which keeps all outputs of encoding layer, and attends over them at each output.
+///
+/// [`ChobitSeqAi`] compresses whole input sequence into one state, so it tends to fail on long sequences. This AI looks back at the encoder output of every input step.
+///
+/// - [`input_next()`](Self::input_next()) pushes output of encoding layer into memory.
+/// - [`output_next()`](Self::output_next()) runs decoding layer, and its output attends over the memory by [`Attention`]. The output of [`Attention`] goes into output layer.
+///
+/// Usage is the same as [`ChobitSeqAi`], but the memory must be cleared by [`clear_enc_outputs()`](Self::clear_enc_outputs()) before next sequence.
+///
+/// - `OUT` : Dimension of output.
+/// - `MIDDLE` : Dimension of hidden layer.
+/// - `IN` : Dimension of input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChobitAttentionSeqAi<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> {
+    enc_layer: Lstm<MIDDLE, IN>,
+    dec_layer: Lstm<MIDDLE, MIDDLE>,
+    attention: Attention<MIDDLE>,
+    output_layer: Layer<OUT, MIDDLE>,
+
+    prev_state: MathVec<MIDDLE>,
+    state: MathVec<MIDDLE>,
+    enc_output: MathVec<MIDDLE>,
+    dec_output: MathVec<MIDDLE>,
+
+    enc_outputs: Vec<MathVec<MIDDLE>>,
+    enc_outputs_len: usize,
+    attention_weights: Vec<f32>,
+    context: MathVec<MIDDLE>,
+    attention_output: MathVec<MIDDLE>,
+
+    tmpbuf: MathVec<MIDDLE>
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> ChobitAttentionSeqAi<OUT, MIDDLE, IN> {
+    /// Creates ChobitAttentionSeqAi.
+    ///
+    /// - `activation` : Activation function for output layer.
+    /// - _Return_ : ChobitAttentionSeqAi.
+    #[inline]
+    pub fn new(activation: Activation) -> Self {
+        Self::with_layers(
+            Lstm::<MIDDLE, IN>::new(),
+            Lstm::<MIDDLE, MIDDLE>::new(),
+            Attention::<MIDDLE>::new(Activation::Tanh),
+            Layer::<OUT, MIDDLE>::new(activation, false)
+        )
+    }
+
+    /// Creates ChobitAttentionSeqAi with activation functions.
+    ///
+    /// - `hidden` : Activation function for main layers of both Lstm, for their states before output gate and for [`Attention`]. (See [`Lstm::with_activations()`].)
+    /// - `output` : Activation function for output layer.
+    /// - _Return_ : ChobitAttentionSeqAi.
+    #[inline]
+    pub fn with_activations(hidden: Activation, output: Activation) -> Self {
+        Self::with_layers(
+            Lstm::<MIDDLE, IN>::with_activations(
+                hidden,
+                Activation::Sigmoid,
+                hidden
+            ),
+            Lstm::<MIDDLE, MIDDLE>::with_activations(
+                hidden,
+                Activation::Sigmoid,
+                hidden
+            ),
+            Attention::<MIDDLE>::new(hidden),
+            Layer::<OUT, MIDDLE>::new(output, false)
+        )
+    }
+
+    /// Creates ChobitAttentionSeqAi with layers.
+    ///
+    /// - `enc_layer` : Lstm for encoding.
+    /// - `dec_layer` : Lstm for decoding.
+    /// - `attention` : Attention over outputs of encoding layer.
+    /// - `output_layer` : Output layer. State weights of it are not used.
+    /// - _Return_ : ChobitAttentionSeqAi.
+    #[inline]
+    pub fn with_layers(
+        enc_layer: Lstm<MIDDLE, IN>,
+        dec_layer: Lstm<MIDDLE, MIDDLE>,
+        attention: Attention<MIDDLE>,
+        output_layer: Layer<OUT, MIDDLE>
+    ) -> Self {
+        Self {
+            enc_layer: enc_layer,
+            dec_layer: dec_layer,
+            attention: attention,
+            output_layer: output_layer,
+
+            prev_state: MathVec::<MIDDLE>::new(),
+            state: MathVec::<MIDDLE>::new(),
+            enc_output: MathVec::<MIDDLE>::new(),
+            dec_output: MathVec::<MIDDLE>::new(),
+
+            enc_outputs: Vec::<MathVec<MIDDLE>>::new(),
+            enc_outputs_len: 0,
+            attention_weights: Vec::<f32>::new(),
+            context: MathVec::<MIDDLE>::new(),
+            attention_output: MathVec::<MIDDLE>::new(),
+
+            tmpbuf: MathVec::<MIDDLE>::new()
+        }
+    }
+
+    /// Gets immutable encoding layer of Lstm.
+    ///
+    /// - _Return_ : Lstm.
+    #[inline]
+    pub fn enc_layer(&self) -> &Lstm<MIDDLE, IN> {&self.enc_layer}
+
+    /// Gets mutable encoding layer of Lstm.
+    ///
+    /// - _Return_ : Lstm.
+    #[inline]
+    pub fn enc_layer_mut(&mut self) -> &mut Lstm<MIDDLE, IN> {
+        &mut self.enc_layer
+    }
+
+    /// Gets immutable decoding layer of Lstm.
+    ///
+    /// - _Return_ : Lstm.
+    #[inline]
+    pub fn dec_layer(&self) -> &Lstm<MIDDLE, MIDDLE> {&self.dec_layer}
+
+    /// Gets mutable decoding layer of Lstm.
+    ///
+    /// - _Return_ : Lstm.
+    #[inline]
+    pub fn dec_layer_mut(&mut self) -> &mut Lstm<MIDDLE, MIDDLE> {
+        &mut self.dec_layer
+    }
+
+    /// Gets immutable attention.
+    ///
+    /// - _Return_ : Attention.
+    #[inline]
+    pub fn attention(&self) -> &Attention<MIDDLE> {&self.attention}
+
+    /// Gets mutable attention.
+    ///
+    /// - _Return_ : Attention.
+    #[inline]
+    pub fn attention_mut(&mut self) -> &mut Attention<MIDDLE> {
+        &mut self.attention
+    }
+
+    /// Gets immutable output layer.
+    ///
+    /// - _Return_ : Output layer.
+    #[inline]
+    pub fn output_layer(&self) -> &Layer<OUT, MIDDLE> {&self.output_layer}
+
+    /// Gets mutable output layer.
+    ///
+    /// - _Return_ : Output layer.
+    #[inline]
+    pub fn output_layer_mut(&mut self) -> &mut Layer<OUT, MIDDLE> {
+        &mut self.output_layer
+    }
+
+    /// Initializes weights with [`Initializer`].
+    ///
+    /// Lstm and [`Attention`] are initialized with `initializer` for input weights and [`Initializer::Orthogonal`] for state weights.
+    ///
+    /// - `initializer` : Initializer for output layer and input weights of Lstm and [`Attention`].
+    /// - `rand` : Closure that returns uniform random number in `[0.0, 1.0)`.
+    pub fn init_weights<F>(&mut self, initializer: Initializer, mut rand: F)
+    where F: FnMut() -> f32 {
+        self.enc_layer.init_weights(
+            initializer,
+            Initializer::Orthogonal,
+            &mut rand
+        );
+        self.dec_layer.init_weights(
+            initializer,
+            Initializer::Orthogonal,
+            &mut rand
+        );
+        self.attention.init_weights(
+            initializer,
+            Initializer::Orthogonal,
+            &mut rand
+        );
+
+        self.output_layer.init_weights(initializer, &mut rand);
+    }
+
+    /// Gets immutable state.
+    ///
+    /// This should be initialized before the first [`input_next()`](Self::input_next).
+    ///
+    /// - _Return_ : State.
+    #[inline]
+    pub fn state(&self) -> &MathVec<MIDDLE> {&self.state}
+
+    /// Gets mutable state.
+    ///
+    /// This should be initialized before the first [`input_next()`](Self::input_next).
+    ///
+    /// - _Return_ : State.
+    #[inline]
+    pub fn state_mut(&mut self) -> &mut MathVec<MIDDLE> {
+        &mut self.state
+    }
+
+    /// Gets outputs of encoding layer kept by [`input_next()`](Self::input_next).
+    ///
+    /// - _Return_ : Outputs of encoding layer.
+    #[inline]
+    pub fn enc_outputs(&self) -> &[MathVec<MIDDLE>] {
+        &self.enc_outputs[..self.enc_outputs_len]
+    }
+
+    /// Gets attention weights calculated by the last [`output_next()`](Self::output_next).
+    ///
+    /// - _Return_ : Attention weights for each output of encoding layer.
+    #[inline]
+    pub fn attention_weights(&self) -> &[f32] {&self.attention_weights}
+
+    /// Clears outputs of encoding layer.
+    ///
+    /// This should be called before the first [`input_next()`](Self::input_next) of each sequence.
+    #[inline]
+    pub fn clear_enc_outputs(&mut self) {
+        self.enc_outputs_len = 0;
+        self.enc_output.clear();
+        self.attention_weights.clear();
+    }
+
+    /// Input next data.
+    ///
+    /// - `input` : Next data.
+    #[inline]
+    pub fn input_next(&mut self, input: &MathVec<IN>) {
+        self.prev_state.copy_from(&self.state);
+
+        self.enc_layer.calc(
+            input,
+            &self.prev_state,
+            &mut self.enc_output,
+            &mut self.state,
+            &mut self.tmpbuf
+        );
+
+        if self.enc_outputs.len() <= self.enc_outputs_len {
+            self.enc_outputs.push(MathVec::<MIDDLE>::new());
+        }
+
+        self.enc_outputs[self.enc_outputs_len].copy_from(&self.enc_output);
+        self.enc_outputs_len += 1;
+    }
+
+    /// Output next data calculated by current state, input and outputs of encoding layer.
+    ///
+    /// - `output` : Buffer for next data.
+    #[inline]
+    pub fn output_next(&mut self, output: &mut MathVec<OUT>) {
+        self.prev_state.copy_from(&self.state);
+
+        self.dec_layer.calc(
+            &self.enc_output,
+            &self.prev_state,
+            &mut self.dec_output,
+            &mut self.state,
+            &mut self.tmpbuf
+        );
+
+        self.attention_weights.resize(self.enc_outputs_len, 0.0);
+
+        self.attention.calc(
+            &self.dec_output,
+            &self.enc_outputs[..self.enc_outputs_len],
+            &mut self.attention_weights,
+            &mut self.context,
+            &mut self.attention_output
+        );
+
+        self.output_layer.calc(&self.attention_output, None, output);
+    }
+
+    /// Accesses each immutable weight with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_weight<F>(&self, mut f: F) where F: FnMut(&f32) {
+        self.enc_layer.for_each_weight(|val| {f(val)});
+        self.dec_layer.for_each_weight(|val| {f(val)});
+        self.attention.for_each_weight(|val| {f(val)});
+        self.output_layer.for_each_weight(|val| {f(val)});
+    }
+
+    /// Accesses each mutable weight with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_weight_mut<F>(
+        &mut self,
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.enc_layer.for_each_weight_mut(|val| {f(val)});
+        self.dec_layer.for_each_weight_mut(|val| {f(val)});
+        self.attention.for_each_weight_mut(|val| {f(val)});
+        self.output_layer.for_each_weight_mut(|val| {f(val)});
+    }
+
+    /// Writes this AI into buffer as binary data.
+    ///
+    /// State and outputs of encoding layer are not written. See [`ChobitAi::save()`] for details.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save(&self, buffer: &mut Vec<u8>) {
+        write_header(
+            buffer,
+            ModelKind::ChobitAttentionSeqAi,
+            &[OUT, MIDDLE, IN]
+        );
+
+        self.enc_layer.write_descriptor(buffer);
+        self.dec_layer.write_descriptor(buffer);
+        self.attention.write_descriptor(buffer);
+        self.output_layer.write_descriptor(buffer);
+
+        self.enc_layer.write_body(buffer);
+        self.dec_layer.write_body(buffer);
+        self.attention.write_body(buffer);
+        self.output_layer.weights.write_body(buffer);
+    }
+
+    /// Creates ChobitAttentionSeqAi from binary data written by [`save()`](Self::save()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : ChobitAttentionSeqAi.
+    pub fn load(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(
+            ModelKind::ChobitAttentionSeqAi,
+            &[("OUT", OUT), ("MIDDLE", MIDDLE), ("IN", IN)]
+        )?;
+
+        let mut enc_layer = Lstm::<MIDDLE, IN>::read_descriptor(&mut reader)?;
+        let mut dec_layer =
+            Lstm::<MIDDLE, MIDDLE>::read_descriptor(&mut reader)?;
+        let mut attention =
+            Attention::<MIDDLE>::read_descriptor(&mut reader)?;
+        let mut output_layer =
+            Layer::<OUT, MIDDLE>::read_descriptor(&mut reader)?;
+
+        enc_layer.read_body(&mut reader)?;
+        dec_layer.read_body(&mut reader)?;
+        attention.read_body(&mut reader)?;
+        output_layer.weights.read_body(&mut reader)?;
+
+        Ok(Self::with_layers(enc_layer, dec_layer, attention, output_layer))
+    }
+}
+
+/// Cache for [`ChobitMlAttentionSeqAi`].
+///
+/// - `OUT` : `OUT` of [`ChobitMlAttentionSeqAi`].
+/// - `MIDDLE` : `MIDDLE` of [`ChobitMlAttentionSeqAi`].
+/// - `IN` : `IN` of [`ChobitMlAttentionSeqAi`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlAttentionSeqAiCache<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> {
+    enc_caches: Vec<(
+        MlLstmStateCache<MIDDLE, IN>,
+        MlLstmOutputCache<MIDDLE, IN>
+    )>,
+    enc_outputs: Vec<MathVec<MIDDLE>>,
+    enc_caches_len: usize,
+
+    dec_caches: Vec<(
+        MlLstmStateCache<MIDDLE, MIDDLE>,
+        MlLstmOutputCache<MIDDLE, MIDDLE>,
+        MlAttentionCache<MIDDLE>,
+        MlCache<OUT, MIDDLE>
+    )>,
+    dec_caches_len: usize
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> MlAttentionSeqAiCache<OUT, MIDDLE, IN> {
+    /// Creates MlAttentionSeqAiCache.
+    ///
+    /// - `input_capacity` : Capacity of input caches.
+    /// - `output_capacity` : Capacity of output caches.
+    /// - _Return_ : MlAttentionSeqAiCache.
+    #[inline]
+    pub fn new(input_capacity: usize, output_capacity: usize) -> Self {
+        Self {
+            enc_caches: vec![
+                (
+                    MlLstmStateCache::<MIDDLE, IN>::new(),
+                    MlLstmOutputCache::<MIDDLE, IN>::new()
+                );
+                input_capacity
+            ],
+            enc_outputs: vec![MathVec::<MIDDLE>::new(); input_capacity],
+            enc_caches_len: 0,
+
+            dec_caches: vec![
+                (
+                    MlLstmStateCache::<MIDDLE, MIDDLE>::new(),
+                    MlLstmOutputCache::<MIDDLE, MIDDLE>::new(),
+                    MlAttentionCache::<MIDDLE>::new(),
+                    MlCache::<OUT, MIDDLE>::new()
+                );
+                output_capacity
+            ],
+            dec_caches_len: 0
+        }
+    }
+
+    /// Calculates output error.
+    ///
+    /// See [`MlSeqAiCache::calc_output_error()`] for details.
+    ///
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error(
+        &self,
+        train_out: &[MathVec<OUT>],
+        output_error: &mut [MathVec<OUT>]
+    ) {
+        train_out.iter().zip(
+            self.dec_caches()
+        ).zip(
+            output_error
+        ).for_each(
+            |((train_out_one, (_, _, _, cache)), output_error_one)| {
+                output_error_one.copy_from(&cache.output);
+                *output_error_one -= train_out_one;
+            }
+        );
+    }
+
+    /// Calculates output error with loss function.
+    ///
+    /// - `loss` : Loss function.
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error_with_loss(
+        &self,
+        loss: &Loss,
+        train_out: &[MathVec<OUT>],
+        output_error: &mut [MathVec<OUT>]
+    ) {
+        train_out.iter().zip(
+            self.dec_caches()
+        ).zip(
+            output_error
+        ).for_each(
+            |((train_out_one, (_, _, _, cache)), output_error_one)| {
+                loss.calc_error(&cache.output, train_out_one, output_error_one);
+            }
+        );
+    }
+
+    /// Gets slice of caches for encoding layer.
+    ///
+    /// - _Return_ : slice of caches.
+    #[inline]
+    pub fn enc_caches(&self) -> &[(
+        MlLstmStateCache<MIDDLE, IN>,
+        MlLstmOutputCache<MIDDLE, IN>
+    )] {
+        &self.enc_caches[..self.enc_caches_len]
+    }
+
+    /// Gets outputs of encoding layer, which are memory of [`MlAttention`].
+    ///
+    /// - _Return_ : Outputs of encoding layer.
+    #[inline]
+    pub fn enc_outputs(&self) -> &[MathVec<MIDDLE>] {
+        &self.enc_outputs[..self.enc_caches_len]
+    }
+
+    /// Gets slice of caches for decoding layer.
+    ///
+    /// - _Return_ : slice of caches.
+    #[inline]
+    pub fn dec_caches(&self) -> &[(
+        MlLstmStateCache<MIDDLE, MIDDLE>,
+        MlLstmOutputCache<MIDDLE, MIDDLE>,
+        MlAttentionCache<MIDDLE>,
+        MlCache<OUT, MIDDLE>
+    )] {
+        &self.dec_caches[..self.dec_caches_len]
+    }
+
+    /// Accesses each output with closure.
+    ///
+    /// - `f` : Closure.
+    pub fn for_each_output<F>(&self, mut f: F) where F: FnMut(&MathVec<OUT>) {
+        self.dec_caches().iter().for_each(
+            |(_, _, _, cache)| {f(&cache.output);}
+        );
+    }
+}
+
+/// Wrapper of [`ChobitAttentionSeqAi`] for machine learning.
+///
+/// See [`ChobitAttentionSeqAi`] for details.
+///
+/// - `OUT` : Dimension of output.
+/// - `MIDDLE` : Dimension of hidden layer.
+/// - `IN` : Dimension of input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChobitMlAttentionSeqAi<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> {
+    enc_layer: MlLstm<MIDDLE, IN>,
+    dec_layer: MlLstm<MIDDLE, MIDDLE>,
+    attention: MlAttention<MIDDLE>,
+    output_layer: MlLayer<OUT, MIDDLE>,
+
+    tmp_prev_state: MathVec<MIDDLE>,
+    tmp_dec_input: MathVec<MIDDLE>,
+    tmp_state_error: MathVec<MIDDLE>,
+    tmp_attention_output_error: MathVec<MIDDLE>,
+    tmp_dec_output_error: MathVec<MIDDLE>,
+    tmp_dec_input_error: MathVec<MIDDLE>,
+    tmp_dec_input_error_one: MathVec<MIDDLE>,
+    tmp_enc_output_errors: Vec<MathVec<MIDDLE>>,
+
+    original_prev_state: MathVec<MIDDLE>,
+    original_state: MathVec<MIDDLE>,
+    original_enc_output: MathVec<MIDDLE>,
+    original_dec_output: MathVec<MIDDLE>,
+    original_enc_outputs: Vec<MathVec<MIDDLE>>,
+    original_enc_outputs_len: usize,
+    original_attention_weights: Vec<f32>,
+    original_context: MathVec<MIDDLE>,
+    original_attention_output: MathVec<MIDDLE>,
+
+    original_tmpbuf: MathVec<MIDDLE>
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> ChobitMlAttentionSeqAi<OUT, MIDDLE, IN> {
+    /// Creates ChobitMlAttentionSeqAi.
+    ///
+    /// - `ai` : Base [`ChobitAttentionSeqAi`].
+    /// - _Return_ : ChobitMlAttentionSeqAi.
+    #[inline]
+    pub fn new(ai: ChobitAttentionSeqAi<OUT, MIDDLE, IN>) -> Self {
+        let ChobitAttentionSeqAi::<OUT, MIDDLE, IN> {
+            enc_layer,
+            dec_layer,
+            attention,
+            output_layer,
+            prev_state,
+            state,
+            enc_output,
+            dec_output,
+            enc_outputs,
+            enc_outputs_len,
+            attention_weights,
+            context,
+            attention_output,
+            tmpbuf
+        } = ai;
+
+        Self {
+            enc_layer: MlLstm::<MIDDLE, IN>::new(enc_layer),
+            dec_layer: MlLstm::<MIDDLE, MIDDLE>::new(dec_layer),
+            attention: MlAttention::<MIDDLE>::new(attention),
+            output_layer: MlLayer::<OUT, MIDDLE>::new(output_layer),
+
+            tmp_prev_state: MathVec::<MIDDLE>::new(),
+            tmp_dec_input: MathVec::<MIDDLE>::new(),
+            tmp_state_error: MathVec::<MIDDLE>::new(),
+            tmp_attention_output_error: MathVec::<MIDDLE>::new(),
+            tmp_dec_output_error: MathVec::<MIDDLE>::new(),
+            tmp_dec_input_error: MathVec::<MIDDLE>::new(),
+            tmp_dec_input_error_one: MathVec::<MIDDLE>::new(),
+            tmp_enc_output_errors: Vec::<MathVec<MIDDLE>>::new(),
+
+            original_prev_state: prev_state,
+            original_state: state,
+            original_enc_output: enc_output,
+            original_dec_output: dec_output,
+            original_enc_outputs: enc_outputs,
+            original_enc_outputs_len: enc_outputs_len,
+            original_attention_weights: attention_weights,
+            original_context: context,
+            original_attention_output: attention_output,
+
+            original_tmpbuf: tmpbuf
+        }
+    }
+
+    /// Drops base [`ChobitAttentionSeqAi`].
+    ///
+    /// - _Return_ : [`ChobitAttentionSeqAi`].
+    #[inline]
+    pub fn drop(self) -> ChobitAttentionSeqAi<OUT, MIDDLE, IN> {
+        let ChobitMlAttentionSeqAi::<OUT, MIDDLE, IN> {
+            enc_layer,
+            dec_layer,
+            attention,
+            output_layer,
+            original_prev_state,
+            original_state,
+            original_enc_output,
+            original_dec_output,
+            original_enc_outputs,
+            original_enc_outputs_len,
+            original_attention_weights,
+            original_context,
+            original_attention_output,
+            original_tmpbuf,
+            ..
+        } = self;
+
+        ChobitAttentionSeqAi::<OUT, MIDDLE, IN> {
+            enc_layer: enc_layer.drop(),
+            dec_layer: dec_layer.drop(),
+            attention: attention.drop(),
+            output_layer: output_layer.drop(),
+
+            prev_state: original_prev_state,
+            state: original_state,
+            enc_output: original_enc_output,
+            dec_output: original_dec_output,
+
+            enc_outputs: original_enc_outputs,
+            enc_outputs_len: original_enc_outputs_len,
+            attention_weights: original_attention_weights,
+            context: original_context,
+            attention_output: original_attention_output,
+
+            tmpbuf: original_tmpbuf
+        }
+    }
+
+    /// Clears internal data for study.
+    #[inline]
+    pub fn clear_study_data(&mut self) {
+        self.enc_layer.clear_study_data();
+        self.dec_layer.clear_study_data();
+        self.attention.clear_study_data();
+        self.output_layer.clear_study_data();
+    }
+
+    /// Sets [`Optimizer`] to all layers.
+    ///
+    /// See [`MlLayer::set_optimizer()`] for details.
+    ///
+    /// - `optimizer` : Optimizer.
+    #[inline]
+    pub fn set_optimizer(&mut self, optimizer: Optimizer) {
+        self.enc_layer.set_optimizer(optimizer);
+        self.dec_layer.set_optimizer(optimizer);
+        self.attention.set_optimizer(optimizer);
+        self.output_layer.set_optimizer(optimizer);
+    }
+
+    /// Writes information on [`MlAttentionSeqAiCache`] for [`study()`](Self::study()).
+    ///
+    /// - `input` : Input.
+    /// - `prev_state` : Previous state.
+    /// - `output_len` : Length of output sequence.
+    /// - `cache` : Cache.
+    #[inline]
+    pub fn ready(
+        &mut self,
+        input: &[MathVec<IN>],
+        prev_state: &MathVec<MIDDLE>,
+        output_len: usize,
+        cache: &mut MlAttentionSeqAiCache<OUT, MIDDLE, IN>
+    ) {
+        self.ready_enc_layer(input, prev_state, cache);
+        self.ready_dec_layer(output_len, cache);
+    }
+
+    fn ready_enc_layer(
+        &mut self,
+        input: &[MathVec<IN>],
+        prev_state: &MathVec<MIDDLE>,
+        cache: &mut MlAttentionSeqAiCache<OUT, MIDDLE, IN>
+    ) {
+        cache.enc_caches_len = input.len();
+        if cache.enc_caches.len() < input.len() {
+            cache.enc_caches.resize(
+                input.len(),
+                (
+                    MlLstmStateCache::<MIDDLE, IN>::new(),
+                    MlLstmOutputCache::<MIDDLE, IN>::new()
+                )
+            );
+            cache.enc_outputs.resize(input.len(), MathVec::<MIDDLE>::new());
+        }
+
+        self.tmp_prev_state.copy_from(prev_state);
+
+        input.iter().zip(
+            cache.enc_caches.iter_mut()
+        ).zip(
+            cache.enc_outputs.iter_mut()
+        ).for_each(|((input_one, (state_cache, output_cache)), output)| {
+            self.enc_layer.ready_state_cache(
+                input_one,
+                &self.tmp_prev_state,
+                state_cache
+            );
+
+            self.enc_layer.ready_output_cache(state_cache, output_cache);
+
+            output.copy_from(&output_cache.output);
+            self.tmp_prev_state.copy_from(&state_cache.state);
+        });
+    }
+
+    fn ready_dec_layer(
+        &mut self,
+        output_len: usize,
+        cache: &mut MlAttentionSeqAiCache<OUT, MIDDLE, IN>
+    ) {
+        cache.dec_caches_len = output_len;
+        if cache.dec_caches.len() < output_len {
+            cache.dec_caches.resize(
+                output_len,
+                (
+                    MlLstmStateCache::<MIDDLE, MIDDLE>::new(),
+                    MlLstmOutputCache::<MIDDLE, MIDDLE>::new(),
+                    MlAttentionCache::<MIDDLE>::new(),
+                    MlCache::<OUT, MIDDLE>::new()
+                )
+            );
+        }
+
+        // tmp_prev_state is the last state of encoding layer.
+        match cache.enc_outputs().last() {
+            Some(enc_output) => {self.tmp_dec_input.copy_from(enc_output);},
+            None => {self.tmp_dec_input.clear();}
+        }
+
+        let memory = &cache.enc_outputs[..cache.enc_caches_len];
+
+        cache.dec_caches[..output_len].iter_mut().for_each(
+            |(
+                dec_state_cache,
+                dec_output_cache,
+                attention_cache,
+                output_layer_cache
+            )| {
+                self.dec_layer.ready_state_cache(
+                    &self.tmp_dec_input,
+                    &self.tmp_prev_state,
+                    dec_state_cache
+                );
+
+                self.dec_layer.ready_output_cache(
+                    dec_state_cache,
+                    dec_output_cache
+                );
+
+                self.attention.ready(
+                    &dec_output_cache.output,
+                    memory,
+                    attention_cache
+                );
+
+                self.output_layer.ready(
+                    attention_cache.output(),
+                    None,
+                    output_layer_cache
+                );
+
+                self.tmp_prev_state.copy_from(&dec_state_cache.state);
+            }
+        );
+    }
+
+    /// Accumulates gradient.
+    ///
+    /// - `output_error` : Backpropagated ouput error.
+    /// - `cache` : Cache.
+    /// - `input_error` : Error to backpropagate for previous output error.
+    /// - `prev_state_error` : Error to backpropagate for previous state error.
+    pub fn study(
+        &mut self,
+        output_error: &[MathVec<OUT>],
+        cache: &MlAttentionSeqAiCache<OUT, MIDDLE, IN>,
+        input_error: &mut [MathVec<IN>],
+        prev_state_error: &mut MathVec<MIDDLE>,
+    ) {
+        let memory = cache.enc_outputs();
+
+        self.tmp_enc_output_errors.resize(
+            memory.len(),
+            MathVec::<MIDDLE>::new()
+        );
+        self.tmp_enc_output_errors.iter_mut().for_each(
+            |error| {error.clear();}
+        );
+
+        self.tmp_state_error.clear();
+        self.tmp_dec_input_error.clear();
+        prev_state_error.clear();
+
+        cache.dec_caches().iter().zip(
+            output_error.iter()
+        ).rev().for_each(
+            |(
+                (
+                    dec_state_cache,
+                    dec_output_cache,
+                    attention_cache,
+                    output_layer_cache
+                ),
+                output_error_one
+            )| {
+                self.output_layer.study(
+                    output_error_one,
+                    None,
+                    output_layer_cache,
+                    &mut self.tmp_attention_output_error,
+                    None
+                );
+
+                self.attention.study(
+                    &self.tmp_attention_output_error,
+                    memory,
+                    attention_cache,
+                    &mut self.tmp_dec_output_error,
+                    &mut self.tmp_enc_output_errors
+                );
+
+                self.dec_layer.study(
+                    &self.tmp_dec_output_error,
+                    &self.tmp_state_error,
+                    dec_state_cache,
+                    dec_output_cache,
+                    &mut self.tmp_dec_input_error_one,
+                    prev_state_error
+                );
+
+                self.tmp_state_error.copy_from(prev_state_error);
+                self.tmp_dec_input_error += &self.tmp_dec_input_error_one;
+            }
+        );
+
+        // Input of decoding layer is the last output of encoding layer.
+        if let Some(error) = self.tmp_enc_output_errors.last_mut() {
+            *error += &self.tmp_dec_input_error;
+        }
+
+        cache.enc_caches().iter().zip(
+            self.tmp_enc_output_errors.iter()
+        ).zip(
+            input_error.iter_mut()
+        ).rev().for_each(
+            |(((state_cache, output_cache), output_error), input_error_one)| {
+                self.enc_layer.study(
+                    output_error,
+                    &self.tmp_state_error,
+                    state_cache,
+                    output_cache,
+                    input_error_one,
+                    prev_state_error
+                );
+
+                self.tmp_state_error.copy_from(prev_state_error);
+            }
+        );
+    }
+
+    /// Updates weights with learning rate from [`LrScheduler`], and advances step of scheduler.
+    ///
+    /// - `scheduler` : Scheduler of learning rate.
+    #[inline]
+    pub fn update_with_scheduler(&mut self, scheduler: &mut LrScheduler) {
+        self.update(scheduler.next_rate());
+    }
+
+    /// Updates weights.
+    ///
+    /// - `rate` : Learning rate.
+    #[inline]
+    pub fn update(&mut self, rate: f32) {
+        self.enc_layer.update(rate);
+        self.dec_layer.update(rate);
+        self.attention.update(rate);
+        self.output_layer.update(rate);
+    }
+
+    /// Accesses each immutable total gradient with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_total_grad<F>(
+        &self,
+        mut f: F
+    ) where F: FnMut(&f32) {
+        self.enc_layer.for_each_total_grad(|val| {f(val)});
+        self.dec_layer.for_each_total_grad(|val| {f(val)});
+        self.attention.for_each_total_grad(|val| {f(val)});
+        self.output_layer.for_each_grad(&mut f);
+    }
+
+    /// Accesses each mutable total gradient with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_total_grad_mut<F>(
+        &mut self,
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.enc_layer.for_each_total_grad_mut(|val| {f(val)});
+        self.dec_layer.for_each_total_grad_mut(|val| {f(val)});
+        self.attention.for_each_total_grad_mut(|val| {f(val)});
+        self.output_layer.for_each_grad_mut(&mut f);
+    }
+
+    /// Clips each total gradient into `[-limit, limit]`.
+    ///
+    /// - `limit` : Limit of absolute value. Negative value is treated as its absolute value. If NaN, nothing is clipped.
+    #[inline]
+    pub fn clip_grad_by_value(&mut self, limit: f32) {
+        clip_by_value(limit, |f| self.for_each_total_grad_mut(f));
+    }
+
+    /// Scales all total gradients so that L2 norm of them is `max_norm` or less.
+    ///
+    /// - `max_norm` : Max L2 norm. Negative value is treated as its absolute value.
+    /// - _Return_ : L2 norm before clipping. If it is not finite, nothing is scaled.
+    #[inline]
+    pub fn clip_grad_by_global_norm(&mut self, max_norm: f32) -> f32 {
+        clip_by_global_norm(max_norm, |f| self.for_each_total_grad_mut(f))
+    }
+
+    /// Adds gradient of L1 and L2 penalty of weights into total gradients of all layers.
+    ///
+    /// See [`MlLayer::add_weight_penalty()`] for details.
+    ///
+    /// - `l1` : Coefficient of L1 penalty.
+    /// - `l2` : Coefficient of L2 penalty.
+    #[inline]
+    pub fn add_weight_penalty(&mut self, l1: f32, l2: f32) {
+        self.enc_layer.add_weight_penalty(l1, l2);
+        self.dec_layer.add_weight_penalty(l1, l2);
+        self.attention.add_weight_penalty(l1, l2);
+        self.output_layer.add_weight_penalty(l1, l2);
+    }
+
+    /// Checks that all total gradients and all weights are finite.
+    ///
+    /// See [`ChobitMlSeqAi::check_finite()`] for details.
+    ///
+    /// - _Return_ : [`ChobitAiError::NonFinite`] if non-finite number is found.
+    pub fn check_finite(&self) -> Result<(), ChobitAiError> {
+        const ENC_NAMES: [&str; 9] = [
+            "enc_layer.main_layer",
+            "enc_layer.f_gate",
+            "enc_layer.i_gate",
+            "enc_layer.o_gate",
+            "enc_layer.main_layer_norm",
+            "enc_layer.f_gate_norm",
+            "enc_layer.i_gate_norm",
+            "enc_layer.o_gate_norm",
+            "enc_layer.state_norm"
+        ];
+        const DEC_NAMES: [&str; 9] = [
+            "dec_layer.main_layer",
+            "dec_layer.f_gate",
+            "dec_layer.i_gate",
+            "dec_layer.o_gate",
+            "dec_layer.main_layer_norm",
+            "dec_layer.f_gate_norm",
+            "dec_layer.i_gate_norm",
+            "dec_layer.o_gate_norm",
+            "dec_layer.state_norm"
+        ];
+
+        self.output_layer.check_grad("output_layer")?;
+        self.attention.check_grad("attention")?;
+        self.dec_layer.check_grad(&DEC_NAMES)?;
+        self.enc_layer.check_grad(&ENC_NAMES)?;
+
+        self.output_layer.check_weights("output_layer")?;
+        self.attention.check_weights("attention")?;
+        self.dec_layer.check_weights(&DEC_NAMES)?;
+        self.enc_layer.check_weights(&ENC_NAMES)?;
+
+        Ok(())
+    }
+
+    /// Writes full state for machine learning into buffer as binary data.
+    ///
+    /// See [`MlLayer::save_checkpoint()`] for details.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save_checkpoint(&self, buffer: &mut Vec<u8>) {
+        write_header(
+            buffer,
+            ModelKind::ChobitMlAttentionSeqAi,
+            &[OUT, MIDDLE, IN]
+        );
+
+        self.enc_layer.write_state(buffer);
+        self.dec_layer.write_state(buffer);
+        self.attention.write_state(buffer);
+        self.output_layer.write_state(buffer);
+    }
+
+    /// Creates ChobitMlAttentionSeqAi from binary data written by [`save_checkpoint()`](Self::save_checkpoint()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : ChobitMlAttentionSeqAi.
+    pub fn load_checkpoint(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(
+            ModelKind::ChobitMlAttentionSeqAi,
+            &[("OUT", OUT), ("MIDDLE", MIDDLE), ("IN", IN)]
+        )?;
+
+        let ai = ChobitAttentionSeqAi::<OUT, MIDDLE, IN>::new(
+            Activation::Linear
+        );
+        let mut ret = Self::new(ai);
+
+        ret.enc_layer = MlLstm::<MIDDLE, IN>::read_state(&mut reader)?;
+        ret.dec_layer = MlLstm::<MIDDLE, MIDDLE>::read_state(&mut reader)?;
+        ret.attention = MlAttention::<MIDDLE>::read_state(&mut reader)?;
+        ret.output_layer = MlLayer::<OUT, MIDDLE>::read_state(&mut reader)?;
+
+        Ok(ret)
+    }
+}
+
+/// Encoder block of [Transformer](https://arxiv.org/abs/1706.03762).
+///
+/// Each data of input sequence attends over the whole sequence by multi-head self-attention, then goes through feed-forward network. Each sublayer has residual connection and is followed by [`LayerNorm`]. (Post-LN.)
+///
+/// | Formula |
+/// |:-:|
+/// | `q_i = W_q x_i`, `k_i = W_k x_i`, `v_i = W_v x_i` |
+/// | `a_ij = softmax_j((q_i · k_j) / sqrt(N / HEADS))` (for each head) |
+/// | `z_i = W_o concat_heads(Σ_j a_ij v_j)` |
+/// | `h_i = LayerNorm(x_i + z_i)` |
+/// | `y_i = LayerNorm(h_i + W_2 φ(W_1 h_i))` |
+///
+/// Input has no information on order. Add [`MathVec::add_positional_encoding()`] to input of the first block if order matters.
+///
+/// Blocks can be stacked, because dimensions of input and output are the same.
+///
+/// - `N` : Dimension of input and output.
+/// - `HEADS` : Number of heads. `N` must be divisible by it.
+/// - `FF` : Dimension of hidden layer of feed-forward network.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChobitTransformerBlock<
+    const N: usize,
+    const HEADS: usize,
+    const FF: usize
+> {
+    query_layer: Layer<N, N>,
+    key_layer: Layer<N, N>,
+    value_layer: Layer<N, N>,
+    projection_layer: Layer<N, N>,
+    attention_norm: LayerNorm<N>,
+    ff_hidden_layer: Layer<FF, N>,
+    ff_output_layer: Layer<N, FF>,
+    ff_norm: LayerNorm<N>,
+
+    queries: Vec<MathVec<N>>,
+    keys: Vec<MathVec<N>>,
+    values: Vec<MathVec<N>>,
+    weights: Vec<f32>,
+    heads: Vec<MathVec<N>>,
+    len: usize,
+
+    tmp_sum: MathVec<N>,
+    tmp_middle: MathVec<N>,
+    tmp_ff_hidden: MathVec<FF>
+}
+
+impl<
+    const N: usize,
+    const HEADS: usize,
+    const FF: usize
+> ChobitTransformerBlock<N, HEADS, FF> {
+    const HEAD_DIM: usize = {
+        assert!(HEADS > 0, "HEADS must be more than 0");
+        assert!(N.is_multiple_of(HEADS), "N must be divisible by HEADS");
+
+        N / HEADS
+    };
+
+    /// Creates ChobitTransformerBlock.
+    ///
+    /// Layers of query, key, value and projection are linear.
+    ///
+    /// - `activation` : Activation function for hidden layer of feed-forward network.
+    /// - _Return_ : ChobitTransformerBlock.
+    pub fn new(activation: Activation) -> Self {
+        Self {
+            query_layer: Layer::<N, N>::new(Activation::Linear, false),
+            key_layer: Layer::<N, N>::new(Activation::Linear, false),
+            value_layer: Layer::<N, N>::new(Activation::Linear, false),
+            projection_layer: Layer::<N, N>::new(Activation::Linear, false),
+            attention_norm: LayerNorm::<N>::new(),
+            ff_hidden_layer: Layer::<FF, N>::new(activation, false),
+            ff_output_layer: Layer::<N, FF>::new(Activation::Linear, false),
+            ff_norm: LayerNorm::<N>::new(),
+
+            queries: Vec::<MathVec<N>>::new(),
+            keys: Vec::<MathVec<N>>::new(),
+            values: Vec::<MathVec<N>>::new(),
+            weights: Vec::<f32>::new(),
+            heads: Vec::<MathVec<N>>::new(),
+            len: 0,
+
+            tmp_sum: MathVec::<N>::new(),
+            tmp_middle: MathVec::<N>::new(),
+            tmp_ff_hidden: MathVec::<FF>::new()
+        }
+    }
+
+    /// Gets immutable layer for query.
+    ///
+    /// - _Return_ : Layer.
+    #[inline]
+    pub fn query_layer(&self) -> &Layer<N, N> {&self.query_layer}
+
+    /// Gets mutable layer for query.
+    ///
+    /// - _Return_ : Layer.
+    #[inline]
+    pub fn query_layer_mut(&mut self) -> &mut Layer<N, N> {
+        &mut self.query_layer
+    }
+
+    /// Gets immutable layer for key.
+    ///
+    /// - _Return_ : Layer.
+    #[inline]
+    pub fn key_layer(&self) -> &Layer<N, N> {&self.key_layer}
+
+    /// Gets mutable layer for key.
+    ///
+    /// - _Return_ : Layer.
+    #[inline]
+    pub fn key_layer_mut(&mut self) -> &mut Layer<N, N> {&mut self.key_layer}
+
+    /// Gets immutable layer for value.
+    ///
+    /// - _Return_ : Layer.
+    #[inline]
+    pub fn value_layer(&self) -> &Layer<N, N> {&self.value_layer}
+
+    /// Gets mutable layer for value.
+    ///
+    /// - _Return_ : Layer.
+    #[inline]
+    pub fn value_layer_mut(&mut self) -> &mut Layer<N, N> {
+        &mut self.value_layer
+    }
+
+    /// Gets immutable layer which projects concatenated heads.
+    ///
+    /// - _Return_ : Layer.
+    #[inline]
+    pub fn projection_layer(&self) -> &Layer<N, N> {&self.projection_layer}
+
+    /// Gets mutable layer which projects concatenated heads.
+    ///
+    /// - _Return_ : Layer.
+    #[inline]
+    pub fn projection_layer_mut(&mut self) -> &mut Layer<N, N> {
+        &mut self.projection_layer
+    }
+
+    /// Gets immutable [`LayerNorm`] after self-attention.
+    ///
+    /// - _Return_ : LayerNorm.
+    #[inline]
+    pub fn attention_norm(&self) -> &LayerNorm<N> {&self.attention_norm}
+
+    /// Gets mutable [`LayerNorm`] after self-attention.
+    ///
+    /// - _Return_ : LayerNorm.
+    #[inline]
+    pub fn attention_norm_mut(&mut self) -> &mut LayerNorm<N> {
+        &mut self.attention_norm
+    }
+
+    /// Gets immutable hidden layer of feed-forward network.
+    ///
+    /// - _Return_ : Layer.
+    #[inline]
+    pub fn ff_hidden_layer(&self) -> &Layer<FF, N> {&self.ff_hidden_layer}
+
+    /// Gets mutable hidden layer of feed-forward network.
+    ///
+    /// - _Return_ : Layer.
+    #[inline]
+    pub fn ff_hidden_layer_mut(&mut self) -> &mut Layer<FF, N> {
+        &mut self.ff_hidden_layer
+    }
+
+    /// Gets immutable output layer of feed-forward network.
+    ///
+    /// - _Return_ : Layer.
+    #[inline]
+    pub fn ff_output_layer(&self) -> &Layer<N, FF> {&self.ff_output_layer}
+
+    /// Gets mutable output layer of feed-forward network.
+    ///
+    /// - _Return_ : Layer.
+    #[inline]
+    pub fn ff_output_layer_mut(&mut self) -> &mut Layer<N, FF> {
+        &mut self.ff_output_layer
+    }
+
+    /// Gets immutable [`LayerNorm`] after feed-forward network.
+    ///
+    /// - _Return_ : LayerNorm.
+    #[inline]
+    pub fn ff_norm(&self) -> &LayerNorm<N> {&self.ff_norm}
+
+    /// Gets mutable [`LayerNorm`] after feed-forward network.
+    ///
+    /// - _Return_ : LayerNorm.
+    #[inline]
+    pub fn ff_norm_mut(&mut self) -> &mut LayerNorm<N> {&mut self.ff_norm}
+
+    /// Initializes weights with [`Initializer`].
+    ///
+    /// [`LayerNorm`] is not changed.
+    ///
+    /// - `initializer` : Initializer for all layers.
+    /// - `rand` : Closure that returns uniform random number in `[0.0, 1.0)`.
+    pub fn init_weights<F>(&mut self, initializer: Initializer, mut rand: F)
+    where F: FnMut() -> f32 {
+        self.query_layer.init_weights(initializer, &mut rand);
+        self.key_layer.init_weights(initializer, &mut rand);
+        self.value_layer.init_weights(initializer, &mut rand);
+        self.projection_layer.init_weights(initializer, &mut rand);
+        self.ff_hidden_layer.init_weights(initializer, &mut rand);
+        self.ff_output_layer.init_weights(initializer, &mut rand);
+    }
+
+    /// Gets attention weights calculated by the last [`calc()`](Self::calc()).
+    ///
+    /// - `position` : Position of query.
+    /// - `head` : Index of head.
+    /// - _Return_ : Attention weights for each position of key. If `position` or `head` is out of range, `None`.
+    #[inline]
+    pub fn attention_weights(
+        &self,
+        position: usize,
+        head: usize
+    ) -> Option<&[f32]> {
+        attention_weights_row::<HEADS>(&self.weights, self.len, position, head)
+    }
+
+    /// Calculates output sequence.
+    ///
+    /// - `input` : Input sequence.
+    /// - `output` : Buffer for output sequence. Its length must be the same as `input`.
+    pub fn calc(&mut self, input: &[MathVec<N>], output: &mut [MathVec<N>]) {
+        let len = input.len();
+
+        self.len = len;
+        resize_vecs(&mut self.queries, len);
+        resize_vecs(&mut self.keys, len);
+        resize_vecs(&mut self.values, len);
+        resize_vecs(&mut self.heads, len);
+        self.weights.resize(len * HEADS * len, 0.0);
+
+        input.iter().enumerate().for_each(|(i, input_one)| {
+            self.query_layer.calc(input_one, None, &mut self.queries[i]);
+            self.key_layer.calc(input_one, None, &mut self.keys[i]);
+            self.value_layer.calc(input_one, None, &mut self.values[i]);
+        });
+
+        Self::attend(
+            &self.queries[..len],
+            &self.keys[..len],
+            &self.values[..len],
+            &mut self.weights,
+            &mut self.heads[..len]
+        );
+
+        input.iter().zip(
+            self.heads.iter()
+        ).zip(
+            output.iter_mut()
+        ).for_each(|((input_one, head), output_one)| {
+            // Self-attention.
+            self.projection_layer.calc(head, None, &mut self.tmp_sum);
+            self.tmp_sum += input_one;
+            self.attention_norm.calc(&self.tmp_sum, &mut self.tmp_middle);
+
+            // Feed-forward.
+            self.ff_hidden_layer.calc(
+                &self.tmp_middle,
+                None,
+                &mut self.tmp_ff_hidden
+            );
+            self.ff_output_layer.calc(
+                &self.tmp_ff_hidden,
+                None,
+                &mut self.tmp_sum
+            );
+            self.tmp_sum += &self.tmp_middle;
+            self.ff_norm.calc(&self.tmp_sum, output_one);
+        });
+    }
+
+    fn attend(
+        queries: &[MathVec<N>],
+        keys: &[MathVec<N>],
+        values: &[MathVec<N>],
+        weights: &mut [f32],
+        heads: &mut [MathVec<N>]
+    ) {
+        let head_dim = Self::HEAD_DIM;
+        let scale = accurate_sqrt(head_dim as f32).recip();
+        let len = keys.len();
+
+        if len == 0 {
+            return;
+        }
+
+        queries.iter().zip(
+            heads.iter_mut()
+        ).zip(
+            weights.chunks_exact_mut(HEADS * len)
+        ).for_each(|((query, head), weights)| {
+            head.clear();
+
+            weights.chunks_exact_mut(len).enumerate().for_each(
+                |(h, weights)| {
+                    let range = (h * head_dim)..((h + 1) * head_dim);
+
+                    weights.iter_mut().zip(keys.iter()).for_each(
+                        |(weight, key)| {
+                            *weight = query[range.clone()].iter().zip(
+                                key[range.clone()].iter()
+                            ).map(|(q, k)| *q * *k).sum::<f32>() * scale;
+                        }
+                    );
+
+                    softmax_slice(weights);
+
+                    weights.iter().zip(values.iter()).for_each(
+                        |(weight, value)| {
+                            head[range.clone()].iter_mut().zip(
+                                value[range.clone()].iter()
+                            ).for_each(|(z, v)| {*z += *weight * *v;});
+                        }
+                    );
+                }
+            );
+        });
+    }
+
+    /// Accesses each immutable weight with closure.
+    ///
+    /// γ and β of [`LayerNorm`] are included.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_weight<F>(&self, mut f: F) where F: FnMut(&f32) {
+        self.query_layer.for_each_weight(|val| {f(val)});
+        self.key_layer.for_each_weight(|val| {f(val)});
+        self.value_layer.for_each_weight(|val| {f(val)});
+        self.projection_layer.for_each_weight(|val| {f(val)});
+        self.attention_norm.weights().iter().for_each(|val| {f(val)});
+        self.ff_hidden_layer.for_each_weight(|val| {f(val)});
+        self.ff_output_layer.for_each_weight(|val| {f(val)});
+        self.ff_norm.weights().iter().for_each(|val| {f(val)});
+    }
+
+    /// Accesses each mutable weight with closure.
+    ///
+    /// γ and β of [`LayerNorm`] are included.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_weight_mut<F>(
+        &mut self,
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.query_layer.for_each_weight_mut(|val| {f(val)});
+        self.key_layer.for_each_weight_mut(|val| {f(val)});
+        self.value_layer.for_each_weight_mut(|val| {f(val)});
+        self.projection_layer.for_each_weight_mut(|val| {f(val)});
+        self.attention_norm.weights_mut().iter_mut().for_each(
+            |val| {f(val)}
+        );
+        self.ff_hidden_layer.for_each_weight_mut(|val| {f(val)});
+        self.ff_output_layer.for_each_weight_mut(|val| {f(val)});
+        self.ff_norm.weights_mut().iter_mut().for_each(|val| {f(val)});
+    }
+
+    /// Writes this block into buffer as binary data.
+    ///
+    /// See [`ChobitAi::save()`] for details.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save(&self, buffer: &mut Vec<u8>) {
+        write_header(
+            buffer,
+            ModelKind::ChobitTransformerBlock,
+            &[N, HEADS, FF]
+        );
+
+        self.query_layer.write_descriptor(buffer);
+        self.key_layer.write_descriptor(buffer);
+        self.value_layer.write_descriptor(buffer);
+        self.projection_layer.write_descriptor(buffer);
+        self.attention_norm.write_descriptor(buffer);
+        self.ff_hidden_layer.write_descriptor(buffer);
+        self.ff_output_layer.write_descriptor(buffer);
+        self.ff_norm.write_descriptor(buffer);
+
+        self.query_layer.weights.write_body(buffer);
+        self.key_layer.weights.write_body(buffer);
+        self.value_layer.weights.write_body(buffer);
+        self.projection_layer.weights.write_body(buffer);
+        self.attention_norm.write_body(buffer);
+        self.ff_hidden_layer.weights.write_body(buffer);
+        self.ff_output_layer.weights.write_body(buffer);
+        self.ff_norm.write_body(buffer);
+    }
+
+    /// Creates ChobitTransformerBlock from binary data written by [`save()`](Self::save()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : ChobitTransformerBlock.
+    pub fn load(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(
+            ModelKind::ChobitTransformerBlock,
+            &[("N", N), ("HEADS", HEADS), ("FF", FF)]
+        )?;
+
+        let mut ret = Self::new(Activation::Linear);
+
+        ret.query_layer = Layer::<N, N>::read_descriptor(&mut reader)?;
+        ret.key_layer = Layer::<N, N>::read_descriptor(&mut reader)?;
+        ret.value_layer = Layer::<N, N>::read_descriptor(&mut reader)?;
+        ret.projection_layer = Layer::<N, N>::read_descriptor(&mut reader)?;
+        ret.attention_norm = LayerNorm::<N>::read_descriptor(&mut reader)?;
+        ret.ff_hidden_layer = Layer::<FF, N>::read_descriptor(&mut reader)?;
+        ret.ff_output_layer = Layer::<N, FF>::read_descriptor(&mut reader)?;
+        ret.ff_norm = LayerNorm::<N>::read_descriptor(&mut reader)?;
+
+        ret.query_layer.weights.read_body(&mut reader)?;
+        ret.key_layer.weights.read_body(&mut reader)?;
+        ret.value_layer.weights.read_body(&mut reader)?;
+        ret.projection_layer.weights.read_body(&mut reader)?;
+        ret.attention_norm.read_body(&mut reader)?;
+        ret.ff_hidden_layer.weights.read_body(&mut reader)?;
+        ret.ff_output_layer.weights.read_body(&mut reader)?;
+        ret.ff_norm.read_body(&mut reader)?;
+
+        Ok(ret)
+    }
+}
+
+#[inline]
+fn resize_vecs<const N: usize>(vecs: &mut Vec<MathVec<N>>, len: usize) {
+    if vecs.len() < len {
+        vecs.resize(len, MathVec::<N>::new());
+    }
+}
+
+#[inline]
+fn attention_weights_row<const HEADS: usize>(
+    weights: &[f32],
+    len: usize,
+    position: usize,
+    head: usize
+) -> Option<&[f32]> {
+    if (position < len) && (head < HEADS) {
+        let start = ((position * HEADS) + head) * len;
+
+        weights.get(start..(start + len))
+    } else {
+        None
+    }
+}
+
+/// Cache for [`ChobitMlTransformerBlock`].
+///
+/// - `N` : `N` of [`ChobitMlTransformerBlock`].
+/// - `HEADS` : `HEADS` of [`ChobitMlTransformerBlock`].
+/// - `FF` : `FF` of [`ChobitMlTransformerBlock`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlTransformerBlockCache<
+    const N: usize,
+    const HEADS: usize,
+    const FF: usize
+> {
+    query_caches: Vec<MlCache<N, N>>,
+    key_caches: Vec<MlCache<N, N>>,
+    value_caches: Vec<MlCache<N, N>>,
+
+    queries: Vec<MathVec<N>>,
+    keys: Vec<MathVec<N>>,
+    values: Vec<MathVec<N>>,
+    weights: Vec<f32>,
+    heads: Vec<MathVec<N>>,
+
+    projection_caches: Vec<MlCache<N, N>>,
+    attention_norm_caches: Vec<MlLayerNormCache<N>>,
+    ff_hidden_caches: Vec<MlCache<FF, N>>,
+    ff_output_caches: Vec<MlCache<N, FF>>,
+    ff_norm_caches: Vec<MlLayerNormCache<N>>,
+
+    len: usize
+}
+
+impl<
+    const N: usize,
+    const HEADS: usize,
+    const FF: usize
+> MlTransformerBlockCache<N, HEADS, FF> {
+    /// Creates MlTransformerBlockCache.
+    ///
+    /// - `capacity` : Capacity of length of sequence.
+    /// - _Return_ : MlTransformerBlockCache.
+    #[inline]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            query_caches: vec![MlCache::<N, N>::new(); capacity],
+            key_caches: vec![MlCache::<N, N>::new(); capacity],
+            value_caches: vec![MlCache::<N, N>::new(); capacity],
+
+            queries: vec![MathVec::<N>::new(); capacity],
+            keys: vec![MathVec::<N>::new(); capacity],
+            values: vec![MathVec::<N>::new(); capacity],
+            weights: Vec::<f32>::with_capacity(capacity * HEADS * capacity),
+            heads: vec![MathVec::<N>::new(); capacity],
+
+            projection_caches: vec![MlCache::<N, N>::new(); capacity],
+            attention_norm_caches: vec![MlLayerNormCache::<N>::new(); capacity],
+            ff_hidden_caches: vec![MlCache::<FF, N>::new(); capacity],
+            ff_output_caches: vec![MlCache::<N, FF>::new(); capacity],
+            ff_norm_caches: vec![MlLayerNormCache::<N>::new(); capacity],
+
+            len: 0
+        }
+    }
+
+    fn resize(&mut self, len: usize) {
+        self.len = len;
+
+        if self.query_caches.len() < len {
+            self.query_caches.resize(len, MlCache::<N, N>::new());
+            self.key_caches.resize(len, MlCache::<N, N>::new());
+            self.value_caches.resize(len, MlCache::<N, N>::new());
+            self.projection_caches.resize(len, MlCache::<N, N>::new());
+            self.attention_norm_caches.resize(
+                len,
+                MlLayerNormCache::<N>::new()
+            );
+            self.ff_hidden_caches.resize(len, MlCache::<FF, N>::new());
+            self.ff_output_caches.resize(len, MlCache::<N, FF>::new());
+            self.ff_norm_caches.resize(len, MlLayerNormCache::<N>::new());
+        }
+
+        resize_vecs(&mut self.queries, len);
+        resize_vecs(&mut self.keys, len);
+        resize_vecs(&mut self.values, len);
+        resize_vecs(&mut self.heads, len);
+        self.weights.resize(len * HEADS * len, 0.0);
+    }
+
+    /// Gets length of sequence given to [`ChobitMlTransformerBlock::ready()`].
+    ///
+    /// - _Return_ : Length of sequence.
+    #[inline]
+    pub fn len(&self) -> usize {self.len}
+
+    /// Returns `true` if sequence given to [`ChobitMlTransformerBlock::ready()`] is empty.
+    ///
+    /// - _Return_ : `true` if empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {self.len == 0}
+
+    /// Calculates output error.
+    ///
+    /// See [`MlCache::calc_output_error()`] for details.
+    ///
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error(
+        &self,
+        train_out: &[MathVec<N>],
+        output_error: &mut [MathVec<N>]
+    ) {
+        train_out.iter().zip(
+            self.ff_norm_caches[..self.len].iter()
+        ).zip(
+            output_error
+        ).for_each(|((train_out_one, cache), output_error_one)| {
+            cache.calc_output_error(train_out_one, output_error_one);
+        });
+    }
+
+    /// Calculates output error with loss function.
+    ///
+    /// - `loss` : Loss function.
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error_with_loss(
+        &self,
+        loss: &Loss,
+        train_out: &[MathVec<N>],
+        output_error: &mut [MathVec<N>]
+    ) {
+        train_out.iter().zip(
+            self.ff_norm_caches[..self.len].iter()
+        ).zip(
+            output_error
+        ).for_each(|((train_out_one, cache), output_error_one)| {
+            cache.calc_output_error_with_loss(
+                loss,
+                train_out_one,
+                output_error_one
+            );
+        });
+    }
+
+    /// Gets attention weights.
+    ///
+    /// See [`ChobitTransformerBlock::attention_weights()`] for details.
+    ///
+    /// - `position` : Position of query.
+    /// - `head` : Index of head.
+    /// - _Return_ : Attention weights for each position of key.
+    #[inline]
+    pub fn attention_weights(
+        &self,
+        position: usize,
+        head: usize
+    ) -> Option<&[f32]> {
+        attention_weights_row::<HEADS>(&self.weights, self.len, position, head)
+    }
+
+    /// Accesses each output with closure.
+    ///
+    /// - `f` : Closure.
+    pub fn for_each_output<F>(&self, mut f: F) where F: FnMut(&MathVec<N>) {
+        self.ff_norm_caches[..self.len].iter().for_each(
+            |cache| {f(&cache.output);}
+        );
+    }
+}
+
+/// Wrapper of [`ChobitTransformerBlock`] for machine learning.
+///
+/// See [`ChobitTransformerBlock`] for details.
+///
+/// - `N` : Dimension of input and output.
+/// - `HEADS` : Number of heads.
+/// - `FF` : Dimension of hidden layer of feed-forward network.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChobitMlTransformerBlock<
+    const N: usize,
+    const HEADS: usize,
+    const FF: usize
+> {
+    query_layer: MlLayer<N, N>,
+    key_layer: MlLayer<N, N>,
+    value_layer: MlLayer<N, N>,
+    projection_layer: MlLayer<N, N>,
+    attention_norm: MlLayerNorm<N>,
+    ff_hidden_layer: MlLayer<FF, N>,
+    ff_output_layer: MlLayer<N, FF>,
+    ff_norm: MlLayerNorm<N>,
+
+    tmp_sum: MathVec<N>,
+    tmp_sum_error: MathVec<N>,
+    tmp_middle_error: MathVec<N>,
+    tmp_ff_hidden_error: MathVec<FF>,
+    tmp_input_error: MathVec<N>,
+    tmp_weight_errors: Vec<f32>,
+    tmp_head_errors: Vec<MathVec<N>>,
+    tmp_query_errors: Vec<MathVec<N>>,
+    tmp_key_errors: Vec<MathVec<N>>,
+    tmp_value_errors: Vec<MathVec<N>>,
+
+    original_queries: Vec<MathVec<N>>,
+    original_keys: Vec<MathVec<N>>,
+    original_values: Vec<MathVec<N>>,
+    original_weights: Vec<f32>,
+    original_heads: Vec<MathVec<N>>,
+    original_len: usize,
+
+    original_tmp_sum: MathVec<N>,
+    original_tmp_middle: MathVec<N>,
+    original_tmp_ff_hidden: MathVec<FF>
+}
+
+impl<
+    const N: usize,
+    const HEADS: usize,
+    const FF: usize
+> ChobitMlTransformerBlock<N, HEADS, FF> {
+    /// Creates ChobitMlTransformerBlock.
+    ///
+    /// - `block` : Base [`ChobitTransformerBlock`].
+    /// - _Return_ : ChobitMlTransformerBlock.
+    pub fn new(block: ChobitTransformerBlock<N, HEADS, FF>) -> Self {
+        let ChobitTransformerBlock::<N, HEADS, FF> {
+            query_layer,
+            key_layer,
+            value_layer,
+            projection_layer,
+            attention_norm,
+            ff_hidden_layer,
+            ff_output_layer,
+            ff_norm,
+            queries,
+            keys,
+            values,
+            weights,
+            heads,
+            len,
+            tmp_sum,
+            tmp_middle,
+            tmp_ff_hidden
+        } = block;
+
+        Self {
+            query_layer: MlLayer::<N, N>::new(query_layer),
+            key_layer: MlLayer::<N, N>::new(key_layer),
+            value_layer: MlLayer::<N, N>::new(value_layer),
+            projection_layer: MlLayer::<N, N>::new(projection_layer),
+            attention_norm: MlLayerNorm::<N>::new(attention_norm),
+            ff_hidden_layer: MlLayer::<FF, N>::new(ff_hidden_layer),
+            ff_output_layer: MlLayer::<N, FF>::new(ff_output_layer),
+            ff_norm: MlLayerNorm::<N>::new(ff_norm),
+
+            tmp_sum: MathVec::<N>::new(),
+            tmp_sum_error: MathVec::<N>::new(),
+            tmp_middle_error: MathVec::<N>::new(),
+            tmp_ff_hidden_error: MathVec::<FF>::new(),
+            tmp_input_error: MathVec::<N>::new(),
+            tmp_weight_errors: Vec::<f32>::new(),
+            tmp_head_errors: Vec::<MathVec<N>>::new(),
+            tmp_query_errors: Vec::<MathVec<N>>::new(),
+            tmp_key_errors: Vec::<MathVec<N>>::new(),
+            tmp_value_errors: Vec::<MathVec<N>>::new(),
+
+            original_queries: queries,
+            original_keys: keys,
+            original_values: values,
+            original_weights: weights,
+            original_heads: heads,
+            original_len: len,
+
+            original_tmp_sum: tmp_sum,
+            original_tmp_middle: tmp_middle,
+            original_tmp_ff_hidden: tmp_ff_hidden
+        }
+    }
+
+    /// Drops base [`ChobitTransformerBlock`].
+    ///
+    /// - _Return_ : [`ChobitTransformerBlock`].
+    pub fn drop(self) -> ChobitTransformerBlock<N, HEADS, FF> {
+        let ChobitMlTransformerBlock::<N, HEADS, FF> {
+            query_layer,
+            key_layer,
+            value_layer,
+            projection_layer,
+            attention_norm,
+            ff_hidden_layer,
+            ff_output_layer,
+            ff_norm,
+            original_queries,
+            original_keys,
+            original_values,
+            original_weights,
+            original_heads,
+            original_len,
+            original_tmp_sum,
+            original_tmp_middle,
+            original_tmp_ff_hidden,
+            ..
+        } = self;
+
+        ChobitTransformerBlock::<N, HEADS, FF> {
+            query_layer: query_layer.drop(),
+            key_layer: key_layer.drop(),
+            value_layer: value_layer.drop(),
+            projection_layer: projection_layer.drop(),
+            attention_norm: attention_norm.drop(),
+            ff_hidden_layer: ff_hidden_layer.drop(),
+            ff_output_layer: ff_output_layer.drop(),
+            ff_norm: ff_norm.drop(),
+
+            queries: original_queries,
+            keys: original_keys,
+            values: original_values,
+            weights: original_weights,
+            heads: original_heads,
+            len: original_len,
+
+            tmp_sum: original_tmp_sum,
+            tmp_middle: original_tmp_middle,
+            tmp_ff_hidden: original_tmp_ff_hidden
+        }
+    }
+
+    /// Clears internal data for study.
+    #[inline]
+    pub fn clear_study_data(&mut self) {
+        self.query_layer.clear_study_data();
+        self.key_layer.clear_study_data();
+        self.value_layer.clear_study_data();
+        self.projection_layer.clear_study_data();
+        self.attention_norm.clear_study_data();
+        self.ff_hidden_layer.clear_study_data();
+        self.ff_output_layer.clear_study_data();
+        self.ff_norm.clear_study_data();
+    }
+
+    /// Sets [`Optimizer`] to all layers.
+    ///
+    /// See [`MlLayer::set_optimizer()`] for details.
+    ///
+    /// - `optimizer` : Optimizer.
+    #[inline]
+    pub fn set_optimizer(&mut self, optimizer: Optimizer) {
+        self.query_layer.set_optimizer(optimizer);
+        self.key_layer.set_optimizer(optimizer);
+        self.value_layer.set_optimizer(optimizer);
+        self.projection_layer.set_optimizer(optimizer);
+        self.attention_norm.set_optimizer(optimizer);
+        self.ff_hidden_layer.set_optimizer(optimizer);
+        self.ff_output_layer.set_optimizer(optimizer);
+        self.ff_norm.set_optimizer(optimizer);
+    }
+
+    /// Writes information on [`MlTransformerBlockCache`] for [`study()`](Self::study()).
+    ///
+    /// - `input` : Input sequence.
+    /// - `cache` : Cache.
+    pub fn ready(
+        &mut self,
+        input: &[MathVec<N>],
+        cache: &mut MlTransformerBlockCache<N, HEADS, FF>
+    ) {
+        let len = input.len();
+
+        cache.resize(len);
+
+        input.iter().enumerate().for_each(|(i, input_one)| {
+            self.query_layer.ready(input_one, None, &mut cache.query_caches[i]);
+            self.key_layer.ready(input_one, None, &mut cache.key_caches[i]);
+            self.value_layer.ready(input_one, None, &mut cache.value_caches[i]);
+
+            cache.queries[i].copy_from(&cache.query_caches[i].output);
+            cache.keys[i].copy_from(&cache.key_caches[i].output);
+            cache.values[i].copy_from(&cache.value_caches[i].output);
+        });
+
+        ChobitTransformerBlock::<N, HEADS, FF>::attend(
+            &cache.queries[..len],
+            &cache.keys[..len],
+            &cache.values[..len],
+            &mut cache.weights,
+            &mut cache.heads[..len]
+        );
+
+        input.iter().enumerate().for_each(|(i, input_one)| {
+            // Self-attention.
+            self.projection_layer.ready(
+                &cache.heads[i],
+                None,
+                &mut cache.projection_caches[i]
+            );
+
+            self.tmp_sum.copy_from(&cache.projection_caches[i].output);
+            self.tmp_sum += input_one;
+
+            self.attention_norm.ready(
+                &self.tmp_sum,
+                &mut cache.attention_norm_caches[i]
+            );
+
+            // Feed-forward.
+            let middle = &cache.attention_norm_caches[i].output;
+
+            self.ff_hidden_layer.ready(
+                middle,
+                None,
+                &mut cache.ff_hidden_caches[i]
+            );
+            self.ff_output_layer.ready(
+                &cache.ff_hidden_caches[i].output,
+                None,
+                &mut cache.ff_output_caches[i]
+            );
+
+            self.tmp_sum.copy_from(&cache.ff_output_caches[i].output);
+            self.tmp_sum += middle;
+
+            self.ff_norm.ready(&self.tmp_sum, &mut cache.ff_norm_caches[i]);
+        });
+    }
+
+    /// Accumulates gradient.
+    ///
+    /// - `output_error` : Backpropagated output error.
+    /// - `cache` : Cache.
+    /// - `input_error` : Error to backpropagate for input.
+    pub fn study(
+        &mut self,
+        output_error: &[MathVec<N>],
+        cache: &MlTransformerBlockCache<N, HEADS, FF>,
+        input_error: &mut [MathVec<N>]
+    ) {
+        let len = cache.len;
+
+        resize_vecs(&mut self.tmp_head_errors, len);
+        resize_vecs(&mut self.tmp_query_errors, len);
+        resize_vecs(&mut self.tmp_key_errors, len);
+        resize_vecs(&mut self.tmp_value_errors, len);
+
+        output_error.iter().zip(
+            input_error.iter_mut()
+        ).enumerate().take(len).for_each(
+            |(i, (output_error_one, input_error_one))| {
+                // Feed-forward.
+                self.ff_norm.study(
+                    output_error_one,
+                    &cache.ff_norm_caches[i],
+                    &mut self.tmp_sum_error
+                );
+
+                self.ff_output_layer.study(
+                    &self.tmp_sum_error,
+                    None,
+                    &cache.ff_output_caches[i],
+                    &mut self.tmp_ff_hidden_error,
+                    None
+                );
+                self.ff_hidden_layer.study(
+                    &self.tmp_ff_hidden_error,
+                    None,
+                    &cache.ff_hidden_caches[i],
+                    &mut self.tmp_middle_error,
+                    None
+                );
+
+                self.tmp_middle_error += &self.tmp_sum_error;
+
+                // Self-attention.
+                self.attention_norm.study(
+                    &self.tmp_middle_error,
+                    &cache.attention_norm_caches[i],
+                    input_error_one
+                );
+
+                self.projection_layer.study(
+                    input_error_one,
+                    None,
+                    &cache.projection_caches[i],
+                    &mut self.tmp_head_errors[i],
+                    None
+                );
+            }
+        );
+
+        self.study_attention(cache);
+
+        input_error.iter_mut().enumerate().take(len).for_each(
+            |(i, input_error_one)| {
+                self.query_layer.study(
+                    &self.tmp_query_errors[i],
+                    None,
+                    &cache.query_caches[i],
+                    &mut self.tmp_input_error,
+                    None
+                );
+                *input_error_one += &self.tmp_input_error;
+
+                self.key_layer.study(
+                    &self.tmp_key_errors[i],
+                    None,
+                    &cache.key_caches[i],
+                    &mut self.tmp_input_error,
+                    None
+                );
+                *input_error_one += &self.tmp_input_error;
+
+                self.value_layer.study(
+                    &self.tmp_value_errors[i],
+                    None,
+                    &cache.value_caches[i],
+                    &mut self.tmp_input_error,
+                    None
+                );
+                *input_error_one += &self.tmp_input_error;
+            }
+        );
+    }
+
+    fn study_attention(
+        &mut self,
+        cache: &MlTransformerBlockCache<N, HEADS, FF>
+    ) {
+        let head_dim = ChobitTransformerBlock::<N, HEADS, FF>::HEAD_DIM;
+        let scale = accurate_sqrt(head_dim as f32).recip();
+        let len = cache.len;
+
+        self.tmp_query_errors.iter_mut().for_each(|error| {error.clear();});
+        self.tmp_key_errors.iter_mut().for_each(|error| {error.clear();});
+        self.tmp_value_errors.iter_mut().for_each(|error| {error.clear();});
+        self.tmp_weight_errors.resize(len, 0.0);
+
+        if len == 0 {
+            return;
+        }
+
+        let keys = &cache.keys[..len];
+        let values = &cache.values[..len];
+
+        cache.queries[..len].iter().zip(
+            self.tmp_head_errors.iter()
+        ).zip(
+            self.tmp_query_errors.iter_mut()
+        ).zip(
+            cache.weights.chunks_exact(HEADS * len)
+        ).for_each(|(((query, head_error), query_error), weights)| {
+            weights.chunks_exact(len).enumerate().for_each(|(h, weights)| {
+                let range = (h * head_dim)..((h + 1) * head_dim);
+
+                // Error of each attention weight.
+                self.tmp_weight_errors.iter_mut().zip(
+                    values.iter()
+                ).zip(
+                    weights.iter()
+                ).zip(
+                    self.tmp_value_errors.iter_mut()
+                ).for_each(|(((weight_e, value), weight), value_error)| {
+                    *weight_e = head_error[range.clone()].iter().zip(
+                        value[range.clone()].iter()
+                    ).map(|(z_e, v)| *z_e * *v).sum::<f32>();
+
+                    value_error[range.clone()].iter_mut().zip(
+                        head_error[range.clone()].iter()
+                    ).for_each(|(v_e, z_e)| {*v_e += *weight * *z_e;});
+                });
+
+                // Error of each score through softmax.
+                let weighted_sum = weights.iter().zip(
+                    self.tmp_weight_errors.iter()
+                ).map(|(weight, weight_e)| *weight * *weight_e).sum::<f32>();
+
+                self.tmp_weight_errors.iter().zip(
+                    weights.iter()
+                ).zip(
+                    keys.iter()
+                ).zip(
+                    self.tmp_key_errors.iter_mut()
+                ).for_each(|(((weight_e, weight), key), key_error)| {
+                    let score_e = *weight * (*weight_e - weighted_sum) * scale;
+
+                    query_error[range.clone()].iter_mut().zip(
+                        key[range.clone()].iter()
+                    ).for_each(|(q_e, k)| {*q_e += score_e * *k;});
+
+                    key_error[range.clone()].iter_mut().zip(
+                        query[range.clone()].iter()
+                    ).for_each(|(k_e, q)| {*k_e += score_e * *q;});
+                });
+            });
+        });
+    }
+
+    /// Updates weights with learning rate from [`LrScheduler`], and advances step of scheduler.
+    ///
+    /// - `scheduler` : Scheduler of learning rate.
+    #[inline]
+    pub fn update_with_scheduler(&mut self, scheduler: &mut LrScheduler) {
+        self.update(scheduler.next_rate());
+    }
+
+    /// Updates weights.
+    ///
+    /// - `rate` : Learning rate.
+    #[inline]
+    pub fn update(&mut self, rate: f32) {
+        self.query_layer.update(rate);
+        self.key_layer.update(rate);
+        self.value_layer.update(rate);
+        self.projection_layer.update(rate);
+        self.attention_norm.update(rate);
+        self.ff_hidden_layer.update(rate);
+        self.ff_output_layer.update(rate);
+        self.ff_norm.update(rate);
+    }
+
+    /// Accesses each immutable total gradient with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_total_grad<F>(
+        &self,
+        mut f: F
+    ) where F: FnMut(&f32) {
+        self.query_layer.for_each_grad(&mut f);
+        self.key_layer.for_each_grad(&mut f);
+        self.value_layer.for_each_grad(&mut f);
+        self.projection_layer.for_each_grad(&mut f);
+        self.attention_norm.total_grad().iter().for_each(|val| {f(val)});
+        self.ff_hidden_layer.for_each_grad(&mut f);
+        self.ff_output_layer.for_each_grad(&mut f);
+        self.ff_norm.total_grad().iter().for_each(|val| {f(val)});
+    }
+
+    /// Accesses each mutable total gradient with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_total_grad_mut<F>(
+        &mut self,
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.query_layer.for_each_grad_mut(&mut f);
+        self.key_layer.for_each_grad_mut(&mut f);
+        self.value_layer.for_each_grad_mut(&mut f);
+        self.projection_layer.for_each_grad_mut(&mut f);
+        self.attention_norm.total_grad_mut().iter_mut().for_each(
+            |val| {f(val)}
+        );
+        self.ff_hidden_layer.for_each_grad_mut(&mut f);
+        self.ff_output_layer.for_each_grad_mut(&mut f);
+        self.ff_norm.total_grad_mut().iter_mut().for_each(|val| {f(val)});
+    }
+
+    /// Clips each total gradient into `[-limit, limit]`.
+    ///
+    /// - `limit` : Limit of absolute value. Negative value is treated as its absolute value. If NaN, nothing is clipped.
+    #[inline]
+    pub fn clip_grad_by_value(&mut self, limit: f32) {
+        clip_by_value(limit, |f| self.for_each_total_grad_mut(f));
+    }
+
+    /// Scales all total gradients so that L2 norm of them is `max_norm` or less.
+    ///
+    /// - `max_norm` : Max L2 norm. Negative value is treated as its absolute value.
+    /// - _Return_ : L2 norm before clipping. If it is not finite, nothing is scaled.
+    #[inline]
+    pub fn clip_grad_by_global_norm(&mut self, max_norm: f32) -> f32 {
+        clip_by_global_norm(max_norm, |f| self.for_each_total_grad_mut(f))
+    }
+
+    /// Adds gradient of L1 and L2 penalty of weights into total gradients of all layers.
+    ///
+    /// γ and β of [`LayerNorm`] are not penalized.
+    ///
+    /// See [`MlLayer::add_weight_penalty()`] for details.
+    ///
+    /// - `l1` : Coefficient of L1 penalty.
+    /// - `l2` : Coefficient of L2 penalty.
+    #[inline]
+    pub fn add_weight_penalty(&mut self, l1: f32, l2: f32) {
+        self.query_layer.add_weight_penalty(l1, l2);
+        self.key_layer.add_weight_penalty(l1, l2);
+        self.value_layer.add_weight_penalty(l1, l2);
+        self.projection_layer.add_weight_penalty(l1, l2);
+        self.ff_hidden_layer.add_weight_penalty(l1, l2);
+        self.ff_output_layer.add_weight_penalty(l1, l2);
+    }
+
+    /// Checks that all total gradients and all weights are finite.
+    ///
+    /// See [`ChobitMlSeqAi::check_finite()`] for details.
+    ///
+    /// - _Return_ : [`ChobitAiError::NonFinite`] if non-finite number is found.
+    pub fn check_finite(&self) -> Result<(), ChobitAiError> {
+        self.ff_norm.layer.check_grad("ff_norm")?;
+        self.ff_output_layer.check_grad("ff_output_layer")?;
+        self.ff_hidden_layer.check_grad("ff_hidden_layer")?;
+        self.attention_norm.layer.check_grad("attention_norm")?;
+        self.projection_layer.check_grad("projection_layer")?;
+        self.value_layer.check_grad("value_layer")?;
+        self.key_layer.check_grad("key_layer")?;
+        self.query_layer.check_grad("query_layer")?;
+
+        self.ff_norm.layer.check_weights("ff_norm")?;
+        self.ff_output_layer.check_weights("ff_output_layer")?;
+        self.ff_hidden_layer.check_weights("ff_hidden_layer")?;
+        self.attention_norm.layer.check_weights("attention_norm")?;
+        self.projection_layer.check_weights("projection_layer")?;
+        self.value_layer.check_weights("value_layer")?;
+        self.key_layer.check_weights("key_layer")?;
+        self.query_layer.check_weights("query_layer")?;
+
+        Ok(())
+    }
+
+    /// Writes full state for machine learning into buffer as binary data.
+    ///
+    /// See [`MlLayer::save_checkpoint()`] for details.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save_checkpoint(&self, buffer: &mut Vec<u8>) {
+        write_header(
+            buffer,
+            ModelKind::ChobitMlTransformerBlock,
+            &[N, HEADS, FF]
+        );
+
+        self.query_layer.write_state(buffer);
+        self.key_layer.write_state(buffer);
+        self.value_layer.write_state(buffer);
+        self.projection_layer.write_state(buffer);
+        self.attention_norm.write_state(buffer);
+        self.ff_hidden_layer.write_state(buffer);
+        self.ff_output_layer.write_state(buffer);
+        self.ff_norm.write_state(buffer);
+    }
+
+    /// Creates ChobitMlTransformerBlock from binary data written by [`save_checkpoint()`](Self::save_checkpoint()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : ChobitMlTransformerBlock.
+    pub fn load_checkpoint(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(
+            ModelKind::ChobitMlTransformerBlock,
+            &[("N", N), ("HEADS", HEADS), ("FF", FF)]
+        )?;
+
+        let mut ret = Self::new(
+            ChobitTransformerBlock::<N, HEADS, FF>::new(Activation::Linear)
+        );
+
+        ret.query_layer = MlLayer::<N, N>::read_state(&mut reader)?;
+        ret.key_layer = MlLayer::<N, N>::read_state(&mut reader)?;
+        ret.value_layer = MlLayer::<N, N>::read_state(&mut reader)?;
+        ret.projection_layer = MlLayer::<N, N>::read_state(&mut reader)?;
+        ret.attention_norm = MlLayerNorm::<N>::read_state(&mut reader)?;
+        ret.ff_hidden_layer = MlLayer::<FF, N>::read_state(&mut reader)?;
+        ret.ff_output_layer = MlLayer::<N, FF>::read_state(&mut reader)?;
+        ret.ff_norm = MlLayerNorm::<N>::read_state(&mut reader)?;
+
+        Ok(ret)
+    }
+}
+
+/// Encoder from sequence data to fixed length data with [`Gru`].
+///
+/// This is a variant of [`ChobitEncoder`] with [`Gru`] instead of [`Lstm`]. Usage is the same as [`ChobitEncoder`].
+///
+/// - `OUT` : Dimension of output.
+/// - `MIDDLE` : Dimension of hidden layer.
+/// - `IN` : Dimension of input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChobitGruEncoder<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> {
+    gru: Gru<MIDDLE, IN>,
+    output_layer: Layer<OUT, MIDDLE>,
+
+    prev_state: MathVec<MIDDLE>,
+    state: MathVec<MIDDLE>,
+    last_input: MathVec<IN>,
+
+    tmpbuf: MathVec<MIDDLE>
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> ChobitGruEncoder<OUT, MIDDLE, IN> {
+    /// Creates ChobitGruEncoder.
+    ///
+    /// - `activation` : Activation function for output layer.
+    /// - _Return_ : ChobitGruEncoder.
+    #[inline]
+    pub fn new(activation: Activation) -> Self {
+        Self::with_layers(
+            Gru::<MIDDLE, IN>::new(),
+            Layer::<OUT, MIDDLE>::new(activation, false)
+        )
+    }
+
+    /// Creates ChobitGruEncoder with activation functions.
+    ///
+    /// - `hidden` : Activation function for main layer of Gru. (See [`Gru::with_activations()`].)
+    /// - `output` : Activation function for output layer.
+    /// - _Return_ : ChobitGruEncoder.
+    #[inline]
+    pub fn with_activations(hidden: Activation, output: Activation) -> Self {
+        Self::with_layers(
+            Gru::<MIDDLE, IN>::with_activations(
+                hidden,
+                Activation::Sigmoid
+            ),
+            Layer::<OUT, MIDDLE>::new(output, false)
+        )
+    }
+
+    /// Creates ChobitGruEncoder with layers.
+    ///
+    /// - `gru` : Gru.
+    /// - `output_layer` : Output layer. State weights of it are not used.
+    /// - _Return_ : ChobitGruEncoder.
+    #[inline]
+    pub fn with_layers(
+        gru: Gru<MIDDLE, IN>,
+        output_layer: Layer<OUT, MIDDLE>
+    ) -> Self {
+        Self {
+            gru: gru,
+            output_layer: output_layer,
+
+            prev_state: MathVec::<MIDDLE>::new(),
+            state: MathVec::<MIDDLE>::new(),
+            last_input: MathVec::<IN>::new(),
+
+            tmpbuf: MathVec::<MIDDLE>::new()
+        }
+    }
+
+    /// Gets immutable Gru.
+    ///
+    /// - _Return_ : Gru.
+    #[inline]
+    pub fn gru(&self) -> &Gru<MIDDLE, IN> {&self.gru}
+
+    /// Gets mutable Gru.
+    ///
+    /// - _Return_ : Gru.
+    #[inline]
+    pub fn gru_mut(&mut self) -> &mut Gru<MIDDLE, IN> {&mut self.gru}
+
+    /// Gets immutable output layer.
+    ///
+    /// - _Return_ : Output layer.
+    #[inline]
+    pub fn output_layer(&self) -> &Layer<OUT, MIDDLE> {&self.output_layer}
+
+    /// Gets mutable output layer.
+    ///
+    /// - _Return_ : Output layer.
+    #[inline]
+    pub fn output_layer_mut(&mut self) -> &mut Layer<OUT, MIDDLE> {
+        &mut self.output_layer
+    }
+
+    /// Initializes weights with [`Initializer`].
+    ///
+    /// Gru is initialized by [`Gru::init_weights()`] with `initializer` for input weights and [`Initializer::Orthogonal`] for state weights.
+    ///
+    /// - `initializer` : Initializer for output layer and input weights of Gru.
+    /// - `rand` : Closure that returns uniform random number in `[0.0, 1.0)`.
+    pub fn init_weights<F>(&mut self, initializer: Initializer, mut rand: F)
+    where F: FnMut() -> f32 {
+        self.gru.init_weights(
+            initializer,
+            Initializer::Orthogonal,
+            &mut rand
+        );
+
+        self.output_layer.init_weights(initializer, &mut rand);
+    }
+
+    /// Gets immutable state.
+    ///
+    /// This should be initialized before the first [`input_next()`](Self::input_next).
+    ///
+    /// - _Return_ : State.
+    #[inline]
+    pub fn state(&self) -> &MathVec<MIDDLE> {&self.state}
+
+    /// Gets mutable state.
+    ///
+    /// This should be initialized before the first [`input_next()`](Self::input_next).
+    ///
+    /// - _Return_ : State.
+    #[inline]
+    pub fn state_mut(&mut self) -> &mut MathVec<MIDDLE> {&mut self.state}
+
+    /// Gets last input.
+    ///
+    /// - _Return_ : Last input.
+    #[inline]
+    pub fn last_input(&self) -> &MathVec<IN> {&self.last_input}
+
+    /// Accesses each immutable weight with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_weight<F>(&self, mut f: F) where F: FnMut(&f32) {
+        self.gru.for_each_weight(|val| {f(val)});
+        self.output_layer.for_each_weight(|val| {f(val)});
+    }
+
+    /// Accesses each mutable weight with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_weight_mut<F>(
+        &mut self,
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.gru.for_each_weight_mut(|val| {f(val)});
+        self.output_layer.for_each_weight_mut(|val| {f(val)});
+    }
+
+    /// Input next data.
+    ///
+    /// - `input` : Next data.
+    #[inline]
+    pub fn input_next(&mut self, input: &MathVec<IN>) {
+        self.prev_state.copy_from(&self.state);
+        self.last_input.copy_from(input);
+
+        self.gru.calc_state(
+            input,
+            &self.prev_state,
+            &mut self.state,
+            &mut self.tmpbuf
+        );
+    }
+
+    /// Output data calculated by current state.
+    ///
+    /// Output of Gru is its state, so state is not changed.
+    ///
+    /// - `output` : Buffer for output.
+    #[inline]
+    pub fn output(&mut self, output: &mut MathVec<OUT>) {
+        self.output_layer.calc(&self.state, None, output);
+    }
+
+    /// Writes this encoder into buffer as binary data.
+    ///
+    /// State is not written. See [`ChobitAi::save()`] for details.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save(&self, buffer: &mut Vec<u8>) {
+        write_header(buffer, ModelKind::ChobitGruEncoder, &[OUT, MIDDLE, IN]);
+
+        self.gru.write_descriptor(buffer);
+        self.output_layer.write_descriptor(buffer);
+
+        self.gru.write_body(buffer);
+        self.output_layer.weights.write_body(buffer);
+    }
+
+    /// Creates ChobitGruEncoder from binary data written by [`save()`](Self::save()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : ChobitGruEncoder.
+    pub fn load(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(
+            ModelKind::ChobitGruEncoder,
+            &[("OUT", OUT), ("MIDDLE", MIDDLE), ("IN", IN)]
+        )?;
+
+        let mut gru = Gru::<MIDDLE, IN>::read_descriptor(&mut reader)?;
+        let mut output_layer =
+            Layer::<OUT, MIDDLE>::read_descriptor(&mut reader)?;
+
+        gru.read_body(&mut reader)?;
+        output_layer.weights.read_body(&mut reader)?;
+
+        let mut ret = Self::new(Activation::Linear);
+
+        ret.gru = gru;
+        ret.output_layer = output_layer;
+
+        Ok(ret)
+    }
+}
+
+/// Cache for [`ChobitMlGruEncoder`].
+///
+/// - `OUT` : `OUT` of [`ChobitMlGruEncoder`].
+/// - `MIDDLE` : `MIDDLE` of [`ChobitMlGruEncoder`].
+/// - `IN` : `IN` of [`ChobitMlGruEncoder`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlGruEncoderCache<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> {
+    gru_state_caches: Vec<MlGruStateCache<MIDDLE, IN>>,
+    gru_state_caches_len: usize,
+
+    gru_output_cache: MlGruOutputCache<MIDDLE, IN>,
+
+    output_layer_cache: MlCache<OUT, MIDDLE>
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> MlGruEncoderCache<OUT, MIDDLE, IN> {
+    /// Creates MlGruEncoderCache.
+    ///
+    /// - _Return_ : MlGruEncoderCache.
+    #[inline]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            gru_state_caches: vec![
+                MlGruStateCache::<MIDDLE, IN>::new(); capacity
+            ],
+            gru_state_caches_len: 0,
+
+            gru_output_cache: MlGruOutputCache::<MIDDLE, IN>::new(),
+
+            output_layer_cache: MlCache::<OUT, MIDDLE>::new()
+        }
+    }
+
+    /// Calculates output error.
+    ///
+    /// | Formula |
+    /// |:-:|
+    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mi>e</mi> <mo stretchy="false">=</mo> <mrow> <mi>o</mi> <mo stretchy="false">−</mo> <mi>t</mi> </mrow> </mrow> </semantics> </math> |
+    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mtable columnalign="left"> <mtr> <mtd> <mrow> <mi>e</mi> <mo stretchy="false">≝</mo> <mtext>Error.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>o</mi> <mo stretchy="false">≝</mo> <mtext>Actual output.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>t</mi> <mo stretchy="false">≝</mo> <mtext>Correct output.</mtext> </mrow> </mtd> </mtr> </mtable> </semantics> </math> |
+    ///
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error(
+        &self,
+        train_out: &MathVec<OUT>,
+        output_error: &mut MathVec<OUT>
+    ) {
+        output_error.copy_from(&self.output_layer_cache.output);
+        *output_error -= train_out;
+    }
+
+    /// Calculates output error with loss function.
+    ///
+    /// - `loss` : Loss function.
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error_with_loss(
+        &self,
+        loss: &Loss,
+        train_out: &MathVec<OUT>,
+        output_error: &mut MathVec<OUT>
+    ) {
+        loss.calc_error(
+            &self.output_layer_cache.output,
+            train_out,
+            output_error
+        );
+    }
+
+    /// Gets slice of ['MlGruStateCache'] of gru layer.
+    ///
+    /// - _Return_ : Slice of ['MlGruStateCache'].
+    #[inline]
+    pub fn gru_state_caches(&self) -> &[MlGruStateCache<MIDDLE, IN>] {
+        &self.gru_state_caches[..self.gru_state_caches_len]
+    }
+
+    /// Gets ['MlGruOutputCache'] of gru layer.
+    ///
+    /// - _Return_ : ['MlGruOutputCache'].
+    #[inline]
+    pub fn gru_output_cache(&self) -> &MlGruOutputCache<MIDDLE, IN> {
+        &self.gru_output_cache
+    }
+
+    /// Gets ['MlCache'] of output layer.
+    ///
+    /// - _Return_ : [`MlCache`].
+    #[inline]
+    pub fn output_layer_cache(&self) -> &MlCache<OUT, MIDDLE> {
+        &self.output_layer_cache
+    }
+
+    /// Gets output.
+    ///
+    /// - _Return_ : output.
+    #[inline]
+    pub fn output(&self) -> &MathVec<OUT> {
+        &self.output_layer_cache.output
+    }
+}
+
+/// Wrapper of [`ChobitGruEncoder`] for machine learning.
+///
+/// See [`ChobitGruEncoder`] for details.
+///
+/// - `OUT` : Dimension of output.
+/// - `MIDDLE` : Dimension of hidden layer.
+/// - `IN` : Dimension of input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChobitMlGruEncoder<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> {
+    gru: MlGru<MIDDLE, IN>,
+    output_layer: MlLayer<OUT, MIDDLE>,
+
+    prev_state: MathVec<MIDDLE>,
+
+    tmp_middle_output_error: MathVec<MIDDLE>,
+    tmp_state_error: MathVec<MIDDLE>,
+
+    original_prev_state: MathVec<MIDDLE>,
+    original_state: MathVec<MIDDLE>,
+    original_last_input: MathVec<IN>,
+
+    original_tmpbuf: MathVec<MIDDLE>
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> ChobitMlGruEncoder<OUT, MIDDLE, IN> {
+    /// Creates ChobitMlGruEncoder.
+    ///
+    /// - `encoder` : Base [`ChobitGruEncoder`].
+    /// - _Return_ : ChobitMlGruEncoder.
+    #[inline]
+    pub fn new(encoder: ChobitGruEncoder<OUT, MIDDLE, IN>) -> Self {
+        let ChobitGruEncoder::<OUT, MIDDLE, IN> {
+            gru,
+            output_layer,
+            prev_state,
+            state,
+            last_input,
+            tmpbuf
+        } = encoder;
+
+        Self {
+            gru: MlGru::<MIDDLE, IN>::new(gru),
+            output_layer: MlLayer::<OUT, MIDDLE>::new(output_layer),
+
+            prev_state: MathVec::<MIDDLE>::new(),
+
+            tmp_middle_output_error: MathVec::<MIDDLE>::new(),
+            tmp_state_error: MathVec::<MIDDLE>::new(),
+
+            original_prev_state: prev_state,
+            original_state: state,
+            original_last_input: last_input,
+            original_tmpbuf: tmpbuf
+        }
+    }
+
+    /// Drops base [`ChobitGruEncoder`].
+    ///
+    /// - _Return_ : [`ChobitGruEncoder`].
+    #[inline]
+    pub fn drop(self) -> ChobitGruEncoder<OUT, MIDDLE, IN> {
+        let Self {
+            gru,
+            output_layer,
+            original_prev_state,
+            original_state,
+            original_last_input,
+            original_tmpbuf,
+            ..
+        } = self;
+
+        ChobitGruEncoder::<OUT, MIDDLE, IN> {
+            gru: gru.drop(),
+            output_layer: output_layer.drop(),
+            prev_state: original_prev_state,
+            state: original_state,
+            last_input: original_last_input,
+            tmpbuf: original_tmpbuf
+        }
+    }
+
+    /// Clears internal data for study.
+    #[inline]
+    pub fn clear_study_data(&mut self) {
+        self.gru.clear_study_data();
+        self.output_layer.clear_study_data();
+    }
+
+    /// Sets [`Optimizer`] to all layers.
+    ///
+    /// See [`MlLayer::set_optimizer()`] for details.
+    ///
+    /// - `optimizer` : Optimizer.
+    #[inline]
+    pub fn set_optimizer(&mut self, optimizer: Optimizer) {
+        self.gru.set_optimizer(optimizer);
+        self.output_layer.set_optimizer(optimizer);
+    }
+
+    /// Writes information on [`MlGruEncoderCache`] for [`study()`](Self::study()).
+    ///
+    /// - `train_in` : Input data sequence.
+    /// - `prev_state` : Previous state.
+    /// - `cache` : Cache.
+    #[inline]
+    pub fn ready(
+        &mut self,
+        train_in: &[MathVec<IN>],
+        prev_state: &MathVec<MIDDLE>,
+        cache: &mut MlGruEncoderCache<OUT, MIDDLE, IN>
+    ) {
+        self.ready_state_cache(train_in, prev_state, cache);
+        self.ready_output_cache(cache);
+    }
+
+    fn ready_state_cache(
+        &mut self,
+        train_in: &[MathVec<IN>],
+        prev_state: &MathVec<MIDDLE>,
+        cache: &mut MlGruEncoderCache<OUT, MIDDLE, IN>
+    ) {
+        cache.gru_state_caches_len = train_in.len();
+        if cache.gru_state_caches.len() < train_in.len() {
+            cache.gru_state_caches.resize(
+                train_in.len(),
+                MlGruStateCache::<MIDDLE, IN>::new()
+            );
+        }
+
+        self.prev_state.copy_from(prev_state);
+
+        train_in.iter().zip(
+            cache.gru_state_caches.iter_mut()
+        ).for_each(|(train_in_one, cache)| {
+            self.gru.ready_state_cache(
+                train_in_one,
+                &self.prev_state,
+                cache
+            );
+
+            self.prev_state.copy_from(&cache.state);
+        })
+    }
+
+    fn ready_output_cache(
+        &self,
+        cache: &mut MlGruEncoderCache<OUT, MIDDLE, IN>
+    ) {
+        if let Some(last_state_cache) = cache.gru_state_caches.get(
+            cache.gru_state_caches_len.wrapping_sub(1)
+        ) {
+            self.gru.ready_output_cache(
+                last_state_cache,
+                &mut cache.gru_output_cache
+            );
+
+            self.output_layer.ready(
+                &cache.gru_output_cache.output,
+                None,
+                &mut cache.output_layer_cache
+            )
+        }
+    }
+
+    /// Accumulates gradient.
+    ///
+    /// - `output_error` : Backpropagated ouput error.
+    /// - `cache` : Cache.
+    /// - `input_error` : Error to backpropagate for previous output error.
+    /// - `prev_state_error` : Error to backpropagate for previous state error.
+    pub fn study(
+        &mut self,
+        output_error: &MathVec<OUT>,
+        cache: &MlGruEncoderCache<OUT, MIDDLE, IN>,
+        input_error: &mut [MathVec<IN>],
+        prev_state_error: &mut MathVec<MIDDLE>
+    ) {
+        let mut iter = cache.gru_state_caches().iter().zip(
+            input_error.iter_mut()
+        ).rev();
+
+        self.tmp_state_error.clear();
+
+        if let Some((gru_state_cache, input_error)) = iter.next() {
+            self.output_layer.study(
+                &output_error,
+                None,
+                &cache.output_layer_cache,
+                &mut self.tmp_middle_output_error,
+                None
+            );
+
+            self.gru.study(
+                &self.tmp_middle_output_error,
+                &self.tmp_state_error,
+                &gru_state_cache,
+                &cache.gru_output_cache,
+                input_error,
+                prev_state_error
+            );
+        }
+
+        self.tmp_state_error.copy_from(prev_state_error);
+
+        iter.for_each(|(gru_state_cache, input_error)| {
+            self.gru.study_state(
+                &self.tmp_state_error,
+                gru_state_cache,
+                input_error,
+                prev_state_error
+            );
+
+            self.tmp_state_error.copy_from(prev_state_error);
+        });
+    }
+
+    /// Updates weights with learning rate from [`LrScheduler`], and advances step of scheduler.
+    ///
+    /// - `scheduler` : Scheduler of learning rate.
+    #[inline]
+    pub fn update_with_scheduler(&mut self, scheduler: &mut LrScheduler) {
+        self.update(scheduler.next_rate());
+    }
+
+    /// Updates weights.
+    ///
+    /// - `rate` : Learning rate.
+    #[inline]
+    pub fn update(&mut self, rate: f32) {
+        self.gru.update(rate);
+        self.output_layer.update(rate);
+    }
+
+    /// Accesses each immutable total gradient with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_total_grad<F>(
+        &self,
+        mut f: F
+    ) where F: FnMut(&f32) {
+        self.gru.for_each_total_grad(|val| {f(val)});
+        self.output_layer.for_each_grad(&mut f);
+    }
+
+    /// Accesses each mutable total gradient with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_total_grad_mut<F>(
+        &mut self,
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.gru.for_each_total_grad_mut(|val| {f(val)});
+        self.output_layer.for_each_grad_mut(&mut f);
+    }
+
+    /// Clips each total gradient into `[-limit, limit]`.
+    ///
+    /// - `limit` : Limit of absolute value. Negative value is treated as its absolute value. If NaN, nothing is clipped.
+    #[inline]
+    pub fn clip_grad_by_value(&mut self, limit: f32) {
+        clip_by_value(limit, |f| self.for_each_total_grad_mut(f));
+    }
+
+    /// Scales all total gradients so that L2 norm of them is `max_norm` or less.
+    ///
+    /// - `max_norm` : Max L2 norm. Negative value is treated as its absolute value.
+    /// - _Return_ : L2 norm before clipping. If it is not finite, nothing is scaled.
+    #[inline]
+    pub fn clip_grad_by_global_norm(&mut self, max_norm: f32) -> f32 {
+        clip_by_global_norm(max_norm, |f| self.for_each_total_grad_mut(f))
+    }
+
+    /// Adds gradient of L1 and L2 penalty of weights into total gradients of all layers.
+    ///
+    /// See [`MlLayer::add_weight_penalty()`] for details.
+    ///
+    /// - `l1` : Coefficient of L1 penalty.
+    /// - `l2` : Coefficient of L2 penalty.
+    #[inline]
+    pub fn add_weight_penalty(&mut self, l1: f32, l2: f32) {
+        self.gru.add_weight_penalty(l1, l2);
+        self.output_layer.add_weight_penalty(l1, l2);
+    }
+
+    /// Checks that all total gradients and all weights are finite.
+    ///
+    /// See [`ChobitAiError::NonFinite`] for order of checking.
+    /// If error is returned, you can abort machine learning, or skip the batch by clearing total gradients with [`for_each_total_grad_mut()`](Self::for_each_total_grad_mut()).
+    ///
+    /// - _Return_ : [`ChobitAiError::NonFinite`] if non-finite number is found.
+    pub fn check_finite(&self) -> Result<(), ChobitAiError> {
+        const NAMES: [&str; 3] = [
+            "gru.main_layer",
+            "gru.z_gate",
+            "gru.r_gate"
+        ];
+
+        self.output_layer.check_grad("output_layer")?;
+        self.gru.check_grad(&NAMES)?;
+
+        self.output_layer.check_weights("output_layer")?;
+        self.gru.check_weights(&NAMES)?;
+
+        Ok(())
+    }
+
+    /// Writes full state for machine learning into buffer as binary data.
+    ///
+    /// See [`MlLayer::save_checkpoint()`] for details.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save_checkpoint(&self, buffer: &mut Vec<u8>) {
+        write_header(buffer, ModelKind::ChobitMlGruEncoder, &[OUT, MIDDLE, IN]);
+
+        self.gru.write_state(buffer);
+        self.output_layer.write_state(buffer);
+    }
+
+    /// Creates ChobitMlGruEncoder from binary data written by [`save_checkpoint()`](Self::save_checkpoint()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : ChobitMlGruEncoder.
+    pub fn load_checkpoint(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(
+            ModelKind::ChobitMlGruEncoder,
+            &[("OUT", OUT), ("MIDDLE", MIDDLE), ("IN", IN)]
+        )?;
+
+        let encoder =
+            ChobitGruEncoder::<OUT, MIDDLE, IN>::new(Activation::Linear);
+        let mut ret = Self::new(encoder);
+
+        ret.gru = MlGru::<MIDDLE, IN>::read_state(&mut reader)?;
+        ret.output_layer = MlLayer::<OUT, MIDDLE>::read_state(&mut reader)?;
+
+        Ok(ret)
+    }
+}
+
+/// Decoder from fixed length data to sequence data with [`Gru`].
+///
+/// This is a variant of [`ChobitDecoder`] with [`Gru`] instead of [`Lstm`]. Usage is the same as [`ChobitDecoder`].
+///
+/// - `OUT` : Dimension of output.
+/// - `MIDDLE` : Dimension of hidden layer.
+/// - `IN` : Dimension of input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChobitGruDecoder<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> {
+    gru: Gru<MIDDLE, IN>,
+    output_layer: Layer<OUT, MIDDLE>,
+
+    input: MathVec<IN>,
+    prev_state: MathVec<MIDDLE>,
+    state: MathVec<MIDDLE>,
+
+    middle_output: MathVec<MIDDLE>,
+    tmpbuf: MathVec<MIDDLE>
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> ChobitGruDecoder<OUT, MIDDLE, IN> {
+    /// Creates ChobitGruDecoder.
+    ///
+    /// - `activation` : Activation function for output layer.
+    /// - _Return_ : ChobitGruDecoder.
+    pub fn new(activation: Activation) -> Self {
+        Self::with_layers(
+            Gru::<MIDDLE, IN>::new(),
+            Layer::<OUT, MIDDLE>::new(activation, false)
+        )
+    }
+
+    /// Creates ChobitGruDecoder with activation functions.
+    ///
+    /// - `hidden` : Activation function for main layer of Gru. (See [`Gru::with_activations()`].)
+    /// - `output` : Activation function for output layer.
+    /// - _Return_ : ChobitGruDecoder.
+    #[inline]
+    pub fn with_activations(hidden: Activation, output: Activation) -> Self {
+        Self::with_layers(
+            Gru::<MIDDLE, IN>::with_activations(
+                hidden,
+                Activation::Sigmoid
+            ),
+            Layer::<OUT, MIDDLE>::new(output, false)
+        )
+    }
+
+    /// Creates ChobitGruDecoder with layers.
+    ///
+    /// - `gru` : Gru.
+    /// - `output_layer` : Output layer. State weights of it are not used.
+    /// - _Return_ : ChobitGruDecoder.
+    #[inline]
+    pub fn with_layers(
+        gru: Gru<MIDDLE, IN>,
+        output_layer: Layer<OUT, MIDDLE>
+    ) -> Self {
+        Self {
+            gru: gru,
+            output_layer: output_layer,
+
+            input: MathVec::<IN>::new(),
+            prev_state: MathVec::<MIDDLE>::new(),
+            state: MathVec::<MIDDLE>::new(),
+
+            middle_output: MathVec::<MIDDLE>::new(),
+            tmpbuf: MathVec::<MIDDLE>::new()
+        }
+    }
+
+    /// Gets immutable Gru.
+    ///
+    /// - _Return_ : Gru.
+    #[inline]
+    pub fn gru(&self) -> &Gru<MIDDLE, IN> {&self.gru}
+
+    /// Gets mutable Gru.
+    ///
+    /// - _Return_ : Gru.
+    #[inline]
+    pub fn gru_mut(&mut self) -> &mut Gru<MIDDLE, IN> {&mut self.gru}
+
+    /// Gets immutable output layer.
+    ///
+    /// - _Return_ : Output layer.
+    #[inline]
+    pub fn output_layer(&self) -> &Layer<OUT, MIDDLE> {&self.output_layer}
+
+    /// Gets mutable output layer.
+    ///
+    /// - _Return_ : Output layer.
+    #[inline]
+    pub fn output_layer_mut(&mut self) -> &mut Layer<OUT, MIDDLE> {
+        &mut self.output_layer
+    }
+
+    /// Initializes weights with [`Initializer`].
+    ///
+    /// Gru is initialized by [`Gru::init_weights()`] with `initializer` for input weights and [`Initializer::Orthogonal`] for state weights.
+    ///
+    /// - `initializer` : Initializer for output layer and input weights of Gru.
+    /// - `rand` : Closure that returns uniform random number in `[0.0, 1.0)`.
+    pub fn init_weights<F>(&mut self, initializer: Initializer, mut rand: F)
+    where F: FnMut() -> f32 {
+        self.gru.init_weights(
+            initializer,
+            Initializer::Orthogonal,
+            &mut rand
+        );
+
+        self.output_layer.init_weights(initializer, &mut rand);
+    }
+
+    /// Gets immutable input.
+    ///
+    /// This should be set before to call [`output_next()`](Self::output_next()).
+    ///
+    /// - _Return_ : Input.
+    #[inline]
+    pub fn input(&self) -> &MathVec<IN> {&self.input}
+
+    /// Gets mutable input.
+    ///
+    /// This should be set before to call [`output_next()`](Self::output_next()).
+    ///
+    /// - _Return_ : Input.
+    #[inline]
+    pub fn input_mut(&mut self) -> &mut MathVec<IN> {&mut self.input}
+
+    /// Gets immutable state.
+    ///
+    /// This should be initialized before to call [`output_next()`](Self::output_next()).
+    ///
+    /// - _Return_ : State.
+    #[inline]
+    pub fn state(&self) -> &MathVec<MIDDLE> {&self.state}
+
+    /// Gets mutable state.
+    ///
+    /// This should be initialized before to call [`output_next()`](Self::output_next()).
+    ///
+    /// - _Return_ : State.
+    #[inline]
+    pub fn state_mut(&mut self) -> &mut MathVec<MIDDLE> {&mut self.state}
+
+    /// Output next data calculated by current state and input.
+    ///
+    /// - `output` : Buffer for next data.
+    pub fn output_next(&mut self, output: &mut MathVec<OUT>) {
+        self.prev_state.copy_from(&self.state);
+
+        self.gru.calc(
+            &self.input,
+            &self.prev_state,
+            &mut self.middle_output,
+            &mut self.state,
+            &mut self.tmpbuf,
+        );
+
+        self.output_layer.calc(&self.middle_output, None, output);
+    }
+
+    /// Accesses each immutable weight with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_weight<F>(&self, mut f: F) where F: FnMut(&f32) {
+        self.gru.for_each_weight(|val| {f(val)});
+        self.output_layer.for_each_weight(|val| {f(val)});
+    }
+
+    /// Accesses each mutable weight with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_weight_mut<F>(
+        &mut self,
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.gru.for_each_weight_mut(|val| {f(val)});
+        self.output_layer.for_each_weight_mut(|val| {f(val)});
+    }
+
+    /// Writes this decoder into buffer as binary data.
+    ///
+    /// Input and state are not written. See [`ChobitAi::save()`] for details.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save(&self, buffer: &mut Vec<u8>) {
+        write_header(buffer, ModelKind::ChobitGruDecoder, &[OUT, MIDDLE, IN]);
+
+        self.gru.write_descriptor(buffer);
+        self.output_layer.write_descriptor(buffer);
+
+        self.gru.write_body(buffer);
+        self.output_layer.weights.write_body(buffer);
+    }
+
+    /// Creates ChobitGruDecoder from binary data written by [`save()`](Self::save()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : ChobitGruDecoder.
+    pub fn load(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(
+            ModelKind::ChobitGruDecoder,
+            &[("OUT", OUT), ("MIDDLE", MIDDLE), ("IN", IN)]
+        )?;
+
+        let mut gru = Gru::<MIDDLE, IN>::read_descriptor(&mut reader)?;
+        let mut output_layer =
+            Layer::<OUT, MIDDLE>::read_descriptor(&mut reader)?;
+
+        gru.read_body(&mut reader)?;
+        output_layer.weights.read_body(&mut reader)?;
+
+        let mut ret = Self::new(Activation::Linear);
+
+        ret.gru = gru;
+        ret.output_layer = output_layer;
+
+        Ok(ret)
+    }
+}
+
+/// Cache for [`ChobitMlGruDecoder`].
+///
+/// - `OUT` : `OUT` of [`ChobitMlGruDecoder`].
+/// - `MIDDLE` : `MIDDLE` of [`ChobitMlGruDecoder`].
+/// - `IN` : `IN` of [`ChobitMlGruDecoder`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlGruDecoderCache<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> {
+    caches: Vec<(
+        MlGruStateCache<MIDDLE, IN>,
+        MlGruOutputCache<MIDDLE, IN>,
+        MlCache<OUT, MIDDLE>
+    )>,
+
+    caches_len: usize
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> MlGruDecoderCache<OUT, MIDDLE, IN> {
+    /// Creates MlGruDecoderCache.
+    ///
+    /// - _Return_ : MlGruDecoderCache.
+    #[inline]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            caches: vec![
+                (
+                    MlGruStateCache::<MIDDLE, IN>::new(),
+                    MlGruOutputCache::<MIDDLE, IN>::new(),
+                    MlCache::<OUT, MIDDLE>::new(),
+                ); capacity
+            ],
+            caches_len: 0
+        }
+    }
+
+    /// Calculates output error.
+    ///
+    /// | Formula |
+    /// |:-:|
+    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mi>e</mi> <mo stretchy="false">=</mo> <mrow> <mi>o</mi> <mo stretchy="false">−</mo> <mi>t</mi> </mrow> </mrow> </semantics> </math> |
+    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mtable columnalign="left"> <mtr> <mtd> <mrow> <mi>e</mi> <mo stretchy="false">≝</mo> <mtext>Error.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>o</mi> <mo stretchy="false">≝</mo> <mtext>Actual output.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>t</mi> <mo stretchy="false">≝</mo> <mtext>Correct output.</mtext> </mrow> </mtd> </mtr> </mtable> </semantics> </math> |
+    ///
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error(
+        &self,
+        train_out: &[MathVec<OUT>],
+        output_error: &mut [MathVec<OUT>]
+    ) {
+        train_out.iter().zip(
+            &self.caches
+        ).zip(
+            output_error
+        ).for_each(|(
+            (
+                train_out_one,
+                (_, _, output_layer_cache)
+            ),
+            output_error_one
+        )| {
+            output_error_one.copy_from(&output_layer_cache.output);
+            *output_error_one -= train_out_one;
+        });
+    }
+
+    /// Calculates output error with loss function.
+    ///
+    /// - `loss` : Loss function.
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error_with_loss(
+        &self,
+        loss: &Loss,
+        train_out: &[MathVec<OUT>],
+        output_error: &mut [MathVec<OUT>]
+    ) {
+        train_out.iter().zip(
+            &self.caches
+        ).zip(
+            output_error
+        ).for_each(
+            |((train_out_one, (_, _, cache)), output_error_one)| {
+                loss.calc_error(&cache.output, train_out_one, output_error_one);
+            }
+        );
+    }
+
+    /// Gets immutable slice of caches.
+    ///
+    /// - _Return_ : Slice of caches.
+    #[inline]
+    pub fn caches(&self) -> &[(
+        MlGruStateCache<MIDDLE, IN>,
+        MlGruOutputCache<MIDDLE, IN>,
+        MlCache<OUT, MIDDLE>
+    )] {
+        &self.caches[..self.caches_len]
+    }
+
+    /// Gets mutable slice of caches.
+    ///
+    /// - _Return_ : Slice of caches.
+    #[inline]
+    pub(self) fn caches_mut(&mut self) -> &mut [(
+        MlGruStateCache<MIDDLE, IN>,
+        MlGruOutputCache<MIDDLE, IN>,
+        MlCache<OUT, MIDDLE>
+    )] {
+        &mut self.caches[..self.caches_len]
+    }
+
+    /// Accesses each output with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_output<F>(&self, mut f: F) where F: FnMut(&MathVec<OUT>) {
+        self.caches[..self.caches_len].iter().for_each(
+            |(_, _, cache)| {f(&cache.output);}
+        );
+    }
+}
+
+/// Wrapper of [`ChobitGruDecoder`] for machine learning.
+///
+/// See [`ChobitGruDecoder`] for details.
+///
+/// - `OUT` : Dimension of output.
+/// - `MIDDLE` : Dimension of hidden layer.
+/// - `IN` : Dimension of input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChobitMlGruDecoder<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> {
+    gru: MlGru<MIDDLE, IN>,
+    output_layer: MlLayer<OUT, MIDDLE>,
+
+    prev_state: MathVec<MIDDLE>,
+
+    tmp_middle_output_error: MathVec<MIDDLE>,
+    tmp_state_error: MathVec<MIDDLE>,
+    tmp_input_error: MathVec<IN>,
+
+    original_input: MathVec<IN>,
+    original_prev_state: MathVec<MIDDLE>,
+    original_state: MathVec<MIDDLE>,
+
+    original_middle_output: MathVec<MIDDLE>,
+    original_tmpbuf: MathVec<MIDDLE>
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> ChobitMlGruDecoder<OUT, MIDDLE, IN> {
+    /// Creates ChobitMlGruDecoder.
+    ///
+    /// - `decoder` : Base [`ChobitGruDecoder`].
+    /// - _Return_ : ChobitMlGruDecoder.
+    #[inline]
+    pub fn new(decoder: ChobitGruDecoder<OUT, MIDDLE, IN>) -> Self {
+        let ChobitGruDecoder::<OUT, MIDDLE, IN> {
+            gru,
+            output_layer,
+            input,
+            prev_state,
+            state,
+            middle_output,
+            tmpbuf
+        } = decoder;
+
+        Self {
+            gru: MlGru::<MIDDLE, IN>::new(gru),
+            output_layer: MlLayer::<OUT, MIDDLE>::new(output_layer),
+
+            prev_state: MathVec::<MIDDLE>::new(),
+
+            tmp_middle_output_error: MathVec::<MIDDLE>::new(),
+            tmp_state_error: MathVec::<MIDDLE>::new(),
+            tmp_input_error: MathVec::<IN>::new(),
+
+            original_input: input,
+            original_prev_state: prev_state,
+            original_state: state,
+            original_middle_output: middle_output,
+            original_tmpbuf: tmpbuf
+        }
+    }
+
+    /// Drops base [`ChobitGruDecoder`].
+    ///
+    /// - _Return_ : [`ChobitGruDecoder`].
+    #[inline]
+    pub fn drop(self) -> ChobitGruDecoder<OUT, MIDDLE, IN> {
+        let Self {
+            gru,
+            output_layer,
+            original_input,
+            original_prev_state,
+            original_state,
+            original_middle_output,
+            original_tmpbuf,
+            ..
+        } = self;
+
+        ChobitGruDecoder::<OUT, MIDDLE, IN> {
+            gru: gru.drop(),
+            output_layer: output_layer.drop(),
+            input: original_input,
+            prev_state: original_prev_state,
+            state: original_state,
+            middle_output: original_middle_output,
+            tmpbuf: original_tmpbuf
+        }
+    }
+
+    /// Clears internal data for study.
+    #[inline]
+    pub fn clear_study_data(&mut self) {
+        self.gru.clear_study_data();
+        self.output_layer.clear_study_data();
+    }
+
+    /// Sets [`Optimizer`] to all layers.
+    ///
+    /// See [`MlLayer::set_optimizer()`] for details.
+    ///
+    /// - `optimizer` : Optimizer.
+    #[inline]
+    pub fn set_optimizer(&mut self, optimizer: Optimizer) {
+        self.gru.set_optimizer(optimizer);
+        self.output_layer.set_optimizer(optimizer);
+    }
+
+    /// Writes information on [`MlGruDecoderCache`] for [`study()`](Self::study()).
+    ///
+    /// - `input` : Input.
+    /// - `prev_state` : Previous state.
+    /// - `output_len` : Length of output sequence.
+    /// - `cache` : Cache.
+    pub fn ready(
+        &mut self,
+        input: &MathVec<IN>,
+        prev_state: &MathVec<MIDDLE>,
+        output_len: usize,
+        cache: &mut MlGruDecoderCache<OUT, MIDDLE, IN>
+    ) {
+        cache.caches_len = output_len;
+        if cache.caches.len() < output_len {
+            cache.caches.resize(
+                output_len,
+                (
+                    MlGruStateCache::<MIDDLE, IN>::new(),
+                    MlGruOutputCache::<MIDDLE, IN>::new(),
+                    MlCache::<OUT, MIDDLE>::new()
+                )
+            );
+        }
+
+        self.prev_state.copy_from(prev_state);
+
+        cache.caches_mut().iter_mut().for_each(|(
+            gru_state_cache,
+            gru_output_cache,
+            output_layer_cache
+        )| {
+            self.gru.ready_state_cache(
+                input,
+                &self.prev_state,
+                gru_state_cache
+            );
+            self.prev_state.copy_from(&gru_state_cache.state);
+
+            self.gru.ready_output_cache(
+                gru_state_cache,
+                gru_output_cache
+            );
+
+            self.output_layer.ready(
+                &gru_output_cache.output,
+                None,
+                output_layer_cache
+            );
+        });
+    }
+
+    /// Accumulates gradient.
+    ///
+    /// - `output_error` : Backpropagated ouput error.
+    /// - `cache` : Cache.
+    /// - `input_error` : Error to backpropagate for previous output error.
+    /// - `prev_state_error` : Error to backpropagate for previous state error.
+    pub fn study(
+        &mut self,
+        output_error: &[MathVec<OUT>],
+        cache: &MlGruDecoderCache<OUT, MIDDLE, IN>,
+        input_error: &mut MathVec<IN>,
+        prev_state_error: &mut MathVec<MIDDLE>
+    ) {
+        self.tmp_state_error.clear();
+        input_error.clear();
+
+        cache.caches().iter().zip(
+            output_error.iter()
+        ).rev().for_each(|(
+            (
+                gru_state_cache,
+                gru_output_cache,
+                output_layer_cache
+            ),
+            output_error_one
+        )| {
+            self.output_layer.study(
+                output_error_one,
+                None,
+                &output_layer_cache,
+                &mut self.tmp_middle_output_error,
+                None
+            );
+
+            self.gru.study(
+                &self.tmp_middle_output_error,
+                &self.tmp_state_error,
+                gru_state_cache,
+                gru_output_cache,
+                &mut self.tmp_input_error,
+                prev_state_error
+            );
+            self.tmp_state_error.copy_from(prev_state_error);
+            *input_error += &self.tmp_input_error;
+        });
+    }
+
+    /// Updates weights with learning rate from [`LrScheduler`], and advances step of scheduler.
+    ///
+    /// - `scheduler` : Scheduler of learning rate.
+    #[inline]
+    pub fn update_with_scheduler(&mut self, scheduler: &mut LrScheduler) {
+        self.update(scheduler.next_rate());
+    }
+
+    /// Updates weights.
+    ///
+    /// - `rate` : Learning rate.
+    #[inline]
+    pub fn update(&mut self, rate: f32) {
+        self.gru.update(rate);
+        self.output_layer.update(rate);
+    }
+
+    /// Accesses each immutable total gradient with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_total_grad<F>(
+        &self,
+        mut f: F
+    ) where F: FnMut(&f32) {
+        self.gru.for_each_total_grad(|val| {f(val)});
+        self.output_layer.for_each_grad(&mut f);
+    }
+
+    /// Accesses each mutable total gradient with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_total_grad_mut<F>(
+        &mut self,
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.gru.for_each_total_grad_mut(|val| {f(val)});
+        self.output_layer.for_each_grad_mut(&mut f);
+    }
+
+    /// Clips each total gradient into `[-limit, limit]`.
+    ///
+    /// - `limit` : Limit of absolute value. Negative value is treated as its absolute value. If NaN, nothing is clipped.
+    #[inline]
+    pub fn clip_grad_by_value(&mut self, limit: f32) {
+        clip_by_value(limit, |f| self.for_each_total_grad_mut(f));
+    }
+
+    /// Scales all total gradients so that L2 norm of them is `max_norm` or less.
+    ///
+    /// - `max_norm` : Max L2 norm. Negative value is treated as its absolute value.
+    /// - _Return_ : L2 norm before clipping. If it is not finite, nothing is scaled.
+    #[inline]
+    pub fn clip_grad_by_global_norm(&mut self, max_norm: f32) -> f32 {
+        clip_by_global_norm(max_norm, |f| self.for_each_total_grad_mut(f))
+    }
+
+    /// Adds gradient of L1 and L2 penalty of weights into total gradients of all layers.
+    ///
+    /// See [`MlLayer::add_weight_penalty()`] for details.
+    ///
+    /// - `l1` : Coefficient of L1 penalty.
+    /// - `l2` : Coefficient of L2 penalty.
+    #[inline]
+    pub fn add_weight_penalty(&mut self, l1: f32, l2: f32) {
+        self.gru.add_weight_penalty(l1, l2);
+        self.output_layer.add_weight_penalty(l1, l2);
+    }
+
+    /// Checks that all total gradients and all weights are finite.
+    ///
+    /// See [`ChobitAiError::NonFinite`] for order of checking.
+    /// If error is returned, you can abort machine learning, or skip the batch by clearing total gradients with [`for_each_total_grad_mut()`](Self::for_each_total_grad_mut()).
+    ///
+    /// - _Return_ : [`ChobitAiError::NonFinite`] if non-finite number is found.
+    pub fn check_finite(&self) -> Result<(), ChobitAiError> {
+        const NAMES: [&str; 3] = [
+            "gru.main_layer",
+            "gru.z_gate",
+            "gru.r_gate"
+        ];
+
+        self.output_layer.check_grad("output_layer")?;
+        self.gru.check_grad(&NAMES)?;
+
+        self.output_layer.check_weights("output_layer")?;
+        self.gru.check_weights(&NAMES)?;
+
+        Ok(())
+    }
+
+    /// Writes full state for machine learning into buffer as binary data.
+    ///
+    /// See [`MlLayer::save_checkpoint()`] for details.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save_checkpoint(&self, buffer: &mut Vec<u8>) {
+        write_header(buffer, ModelKind::ChobitMlGruDecoder, &[OUT, MIDDLE, IN]);
+
+        self.gru.write_state(buffer);
+        self.output_layer.write_state(buffer);
+    }
+
+    /// Creates ChobitMlGruDecoder from binary data written by [`save_checkpoint()`](Self::save_checkpoint()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : ChobitMlGruDecoder.
+    pub fn load_checkpoint(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(
+            ModelKind::ChobitMlGruDecoder,
+            &[("OUT", OUT), ("MIDDLE", MIDDLE), ("IN", IN)]
+        )?;
+
+        let decoder =
+            ChobitGruDecoder::<OUT, MIDDLE, IN>::new(Activation::Linear);
+        let mut ret = Self::new(decoder);
+
+        ret.gru = MlGru::<MIDDLE, IN>::read_state(&mut reader)?;
+        ret.output_layer = MlLayer::<OUT, MIDDLE>::read_state(&mut reader)?;
+
+        Ok(ret)
+    }
+}
+
+/// Seq2Seq AI with [`Gru`].
+///
+/// This is a variant of [`ChobitSeqAi`] with [`Gru`] instead of [`Lstm`]. Usage is the same as [`ChobitSeqAi`].
+///
+/// - `OUT` : Dimension of output.
+/// - `MIDDLE` : Dimension of hidden layer.
+/// - `IN` : Dimension of input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChobitGruSeqAi<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> {
+    enc_layer: Gru<MIDDLE, IN>,
+    dec_layer: Gru<MIDDLE, MIDDLE>,
+    output_layer: Layer<OUT, MIDDLE>,
+
+    prev_state: MathVec<MIDDLE>,
+    state: MathVec<MIDDLE>,
+    enc_output: MathVec<MIDDLE>,
+    dec_output: MathVec<MIDDLE>,
+
+    tmpbuf: MathVec<MIDDLE>
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> ChobitGruSeqAi<OUT, MIDDLE, IN> {
+    /// Creates ChobitGruSeqAi.
+    ///
+    /// - `activation` : Activation function for output layer.
+    /// - _Return_ : ChobitGruSeqAi.
+    #[inline]
+    pub fn new(activation: Activation) -> Self {
+        Self::with_layers(
+            Gru::<MIDDLE, IN>::new(),
+            Gru::<MIDDLE, MIDDLE>::new(),
+            Layer::<OUT, MIDDLE>::new(activation, false)
+        )
+    }
+
+    /// Creates ChobitGruSeqAi with activation functions.
+    ///
+    /// - `hidden` : Activation function for main layers of both Gru. (See [`Gru::with_activations()`].)
+    /// - `output` : Activation function for output layer.
+    /// - _Return_ : ChobitGruSeqAi.
+    #[inline]
+    pub fn with_activations(hidden: Activation, output: Activation) -> Self {
+        Self::with_layers(
+            Gru::<MIDDLE, IN>::with_activations(
+                hidden,
+                Activation::Sigmoid
+            ),
+            Gru::<MIDDLE, MIDDLE>::with_activations(
+                hidden,
+                Activation::Sigmoid
+            ),
+            Layer::<OUT, MIDDLE>::new(output, false)
+        )
+    }
+
+    /// Creates ChobitGruSeqAi with layers.
+    ///
+    /// - `enc_layer` : Gru for encoding.
+    /// - `dec_layer` : Gru for decoding.
+    /// - `output_layer` : Output layer. State weights of it are not used.
+    /// - _Return_ : ChobitGruSeqAi.
+    #[inline]
+    pub fn with_layers(
+        enc_layer: Gru<MIDDLE, IN>,
+        dec_layer: Gru<MIDDLE, MIDDLE>,
+        output_layer: Layer<OUT, MIDDLE>
+    ) -> Self {
+        Self {
+            enc_layer: enc_layer,
+            dec_layer: dec_layer,
+            output_layer: output_layer,
+
+            prev_state: MathVec::<MIDDLE>::new(),
+            state: MathVec::<MIDDLE>::new(),
+            enc_output: MathVec::<MIDDLE>::new(),
+            dec_output: MathVec::<MIDDLE>::new(),
+
+            tmpbuf: MathVec::<MIDDLE>::new()
+        }
+    }
+
+    /// Gets immutable encoding layer of Gru.
+    ///
+    /// - _Return_ : Gru.
+    #[inline]
+    pub fn enc_layer(&self) -> &Gru<MIDDLE, IN> {&self.enc_layer}
+
+    /// Gets mutable encoding layer of Gru.
+    ///
+    /// - _Return_ : Gru.
+    #[inline]
+    pub fn enc_layer_mut(&mut self) -> &mut Gru<MIDDLE, IN> {
+        &mut self.enc_layer
+    }
+
+    /// Gets immutable decoding layer of Gru.
+    ///
+    /// - _Return_ : Gru.
+    #[inline]
+    pub fn dec_layer(&self) -> &Gru<MIDDLE, MIDDLE> {&self.dec_layer}
+
+    /// Gets mutable decoding layer of Gru.
+    ///
+    /// - _Return_ : Gru.
+    #[inline]
+    pub fn dec_layer_mut(&mut self) -> &mut Gru<MIDDLE, MIDDLE> {
+        &mut self.dec_layer
+    }
+
+    /// Gets immutable output layer.
+    ///
+    /// - _Return_ : Output layer.
+    #[inline]
+    pub fn output_layer(&self) -> &Layer<OUT, MIDDLE> {&self.output_layer}
+
+    /// Gets mutable output layer.
+    ///
+    /// - _Return_ : Output layer.
+    #[inline]
+    pub fn output_layer_mut(&mut self) -> &mut Layer<OUT, MIDDLE> {
+        &mut self.output_layer
+    }
+
+    /// Initializes weights with [`Initializer`].
+    ///
+    /// Gru is initialized by [`Gru::init_weights()`] with `initializer` for input weights and [`Initializer::Orthogonal`] for state weights.
+    ///
+    /// - `initializer` : Initializer for output layer and input weights of Gru.
+    /// - `rand` : Closure that returns uniform random number in `[0.0, 1.0)`.
+    pub fn init_weights<F>(&mut self, initializer: Initializer, mut rand: F)
+    where F: FnMut() -> f32 {
+        self.enc_layer.init_weights(
+            initializer,
+            Initializer::Orthogonal,
+            &mut rand
+        );
+        self.dec_layer.init_weights(
+            initializer,
+            Initializer::Orthogonal,
+            &mut rand
+        );
+
+        self.output_layer.init_weights(initializer, &mut rand);
+    }
+
+    /// Gets immutable state.
+    ///
+    /// This should be initialized before the first [`input_next()`](Self::input_next).
+    ///
+    /// - _Return_ : State.
+    #[inline]
+    pub fn state(&self) -> &MathVec<MIDDLE> {&self.state}
+
+    /// Gets mutable state.
+    ///
+    /// This should be initialized before the first [`input_next()`](Self::input_next).
+    ///
+    /// - _Return_ : State.
+    #[inline]
+    pub fn state_mut(&mut self) -> &mut MathVec<MIDDLE> {
+        &mut self.state
+    }
+
+    /// Input next data.
+    ///
+    /// - `input` : Next data.
+    #[inline]
+    pub fn input_next(&mut self, input: &MathVec<IN>) {
+        self.prev_state.copy_from(&self.state);
+
+        self.enc_layer.calc(
+            input,
+            &self.prev_state,
+            &mut self.enc_output,
+            &mut self.state,
+            &mut self.tmpbuf
+        );
+    }
+
+    /// Output next data calculated by current state and input.
+    ///
+    /// - `output` : Buffer for next data.
+    #[inline]
+    pub fn output_next(&mut self, output: &mut MathVec<OUT>) {
+        self.prev_state.copy_from(&self.state);
+
+        self.dec_layer.calc(
+            &self.enc_output,
+            &self.prev_state,
+            &mut self.dec_output,
+            &mut self.state,
+            &mut self.tmpbuf
+        );
+
+        self.output_layer.calc(&self.dec_output, None, output);
+    }
+
+    /// Accesses each immutable weight with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_weight<F>(&self, mut f: F) where F: FnMut(&f32) {
+        self.enc_layer.for_each_weight(|val| {f(val)});
+        self.dec_layer.for_each_weight(|val| {f(val)});
+        self.output_layer.for_each_weight(|val| {f(val)});
+    }
+
+    /// Accesses each mutable weight with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_weight_mut<F>(
+        &mut self,
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.enc_layer.for_each_weight_mut(|val| {f(val)});
+        self.dec_layer.for_each_weight_mut(|val| {f(val)});
+        self.output_layer.for_each_weight_mut(|val| {f(val)});
+    }
+
+    /// Writes this AI into buffer as binary data.
+    ///
+    /// State is not written. See [`ChobitAi::save()`] for details.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save(&self, buffer: &mut Vec<u8>) {
+        write_header(buffer, ModelKind::ChobitGruSeqAi, &[OUT, MIDDLE, IN]);
+
+        self.enc_layer.write_descriptor(buffer);
+        self.dec_layer.write_descriptor(buffer);
+        self.output_layer.write_descriptor(buffer);
+
+        self.enc_layer.write_body(buffer);
+        self.dec_layer.write_body(buffer);
+        self.output_layer.weights.write_body(buffer);
+    }
+
+    /// Creates ChobitGruSeqAi from binary data written by [`save()`](Self::save()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : ChobitGruSeqAi.
+    pub fn load(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(
+            ModelKind::ChobitGruSeqAi,
+            &[("OUT", OUT), ("MIDDLE", MIDDLE), ("IN", IN)]
+        )?;
+
+        let mut enc_layer = Gru::<MIDDLE, IN>::read_descriptor(&mut reader)?;
+        let mut dec_layer =
+            Gru::<MIDDLE, MIDDLE>::read_descriptor(&mut reader)?;
+        let mut output_layer =
+            Layer::<OUT, MIDDLE>::read_descriptor(&mut reader)?;
+
+        enc_layer.read_body(&mut reader)?;
+        dec_layer.read_body(&mut reader)?;
+        output_layer.weights.read_body(&mut reader)?;
+
+        let mut ret = Self::new(Activation::Linear);
+
+        ret.enc_layer = enc_layer;
+        ret.dec_layer = dec_layer;
+        ret.output_layer = output_layer;
+
+        Ok(ret)
+    }
+}
+
+/// Cache for [`ChobitMlGruSeqAi`].
+///
+/// - `OUT` : `OUT` of [`ChobitMlGruSeqAi`].
+/// - `MIDDLE` : `MIDDLE` of [`ChobitMlGruSeqAi`].
+/// - `IN` : `IN` of [`ChobitMlGruSeqAi`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MlGruSeqAiCache<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> {
+    enc_state_caches: Vec<MlGruStateCache<MIDDLE, IN>>,
+    enc_state_caches_len: usize,
+    enc_output_cache: Option<MlGruOutputCache<MIDDLE, IN>>,
+
+    dec_caches: Vec<(
+        MlGruStateCache<MIDDLE, MIDDLE>,
+        MlGruOutputCache<MIDDLE, MIDDLE>,
+        MlCache<OUT, MIDDLE>
+    )>,
+    dec_caches_len: usize
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> MlGruSeqAiCache<OUT, MIDDLE, IN> {
+    /// Creates MlGruSeqAiCache.
+    ///
+    /// - `input_capacity` : Capacity of input caches.
+    /// - `output_capacity` : Capacity of output caches.
+    /// - _Return_ : MlGruSeqAiCache.
+    #[inline]
+    pub fn new(input_capacity: usize, output_capacity: usize) -> Self {
+        Self {
+            enc_state_caches: vec![
+                MlGruStateCache::<MIDDLE, IN>::new(); input_capacity
+            ],
+            enc_state_caches_len: 0,
+
+            enc_output_cache: Some(MlGruOutputCache::<MIDDLE, IN>::new()),
+
+            dec_caches: vec![
+                (
+                    MlGruStateCache::<MIDDLE, MIDDLE>::new(),
+                    MlGruOutputCache::<MIDDLE, MIDDLE>::new(),
+                    MlCache::<OUT, MIDDLE>::new(),
+                ); output_capacity
+            ],
+            dec_caches_len: 0,
+        }
+    }
+
+    /// Calculates output error.
+    ///
+    /// | Formula |
+    /// |:-:|
+    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mi>e</mi> <mo stretchy="false">=</mo> <mrow> <mi>o</mi> <mo stretchy="false">−</mo> <mi>t</mi> </mrow> </mrow> </semantics> </math> |
+    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mtable columnalign="left"> <mtr> <mtd> <mrow> <mi>e</mi> <mo stretchy="false">≝</mo> <mtext>Error.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>o</mi> <mo stretchy="false">≝</mo> <mtext>Actual output.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>t</mi> <mo stretchy="false">≝</mo> <mtext>Correct output.</mtext> </mrow> </mtd> </mtr> </mtable> </semantics> </math> |
+    ///
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error(
+        &self,
+        train_out: &[MathVec<OUT>],
+        output_error: &mut [MathVec<OUT>]
+    ) {
+        train_out.iter().zip(
+            &self.dec_caches
+        ).zip(
+            output_error
+        ).for_each(
+            |((train_out_one, (_, _, cache)), output_error_one)| {
+                output_error_one.copy_from(&cache.output);
+                *output_error_one -= train_out_one;
+            }
+        );
+    }
+
+    /// Calculates output error with loss function.
+    ///
+    /// - `loss` : Loss function.
+    /// - `train_out` : Correct output.
+    /// - `output_error` : Buffer for output error.
+    #[inline]
+    pub fn calc_output_error_with_loss(
+        &self,
+        loss: &Loss,
+        train_out: &[MathVec<OUT>],
+        output_error: &mut [MathVec<OUT>]
+    ) {
+        train_out.iter().zip(
+            &self.dec_caches
+        ).zip(
+            output_error
+        ).for_each(
+            |((train_out_one, (_, _, cache)), output_error_one)| {
+                loss.calc_error(&cache.output, train_out_one, output_error_one);
+            }
+        );
+    }
+
+    /// Gets slice of ['MlGruStateCache'] for encoding layer.
+    ///
+    /// - _Return_ : slice of [`MlGruStateCache`].
+    #[inline]
+    pub fn enc_state_caches(&self) -> &[MlGruStateCache<MIDDLE, IN>] {
+        &self.enc_state_caches[..self.enc_state_caches_len]
+    }
+
+
+    /// Gets ['MlGruOutputCache'] for encoding layer.
+    ///
+    /// - _Return_ : [`MlGruOutputCache`].
+    #[inline]
+    pub fn enc_output_cache(&self) -> &MlGruOutputCache<MIDDLE, IN> {
+        self.enc_output_cache.as_ref().unwrap()
+    }
+
+    #[inline]
+    pub(self) fn take_enc_output_cache(
+        &mut self
+    ) -> Option<MlGruOutputCache<MIDDLE, IN>> {
+        self.enc_output_cache.take()
+    }
+
+    #[inline]
+    pub(self) fn set_enc_output_cache(
+        &mut self,
+        output_cache: MlGruOutputCache<MIDDLE, IN>
+    ) {
+        self.enc_output_cache = Some(output_cache);
+    }
+
+    /// Gets slice of caches for decoding layer.
+    ///
+    /// - _Return_ : slice of caches.
+    #[inline]
+    pub fn dec_caches(&self) -> &[(
+        MlGruStateCache<MIDDLE, MIDDLE>,
+        MlGruOutputCache<MIDDLE, MIDDLE>,
+        MlCache<OUT, MIDDLE>
+    )] {
+        &self.dec_caches[..self.dec_caches_len]
+    }
+
+    #[inline]
+    pub(self) fn dec_caches_mut(&mut self) -> &mut [(
+        MlGruStateCache<MIDDLE, MIDDLE>,
+        MlGruOutputCache<MIDDLE, MIDDLE>,
+        MlCache<OUT, MIDDLE>
+    )] {
+        &mut self.dec_caches[..self.dec_caches_len]
+    }
+
+    #[inline]
+    pub(self) fn enc_last_state_cache(
+        &self
+    ) -> Option<&MlGruStateCache<MIDDLE, IN>> {
+        self.enc_state_caches.get(self.enc_state_caches_len.wrapping_sub(1))
+    }
+
+    /// Accesses each output with closure.
+    ///
+    /// - `f` : Closure.
+    pub fn for_each_output<F>(&self, mut f: F) where F: FnMut(&MathVec<OUT>) {
+        self.dec_caches[..self.dec_caches_len].iter().for_each(
+            |(_, _, cache)| {f(&cache.output);}
+        );
+    }
+}
+
+/// Wrapper of [`ChobitGruSeqAi`] for machine learning.
+///
+/// See [`ChobitGruSeqAi`] for details.
+///
+/// - `OUT` : Dimension of output.
+/// - `MIDDLE` : Dimension of hidden layer.
+/// - `IN` : Dimension of input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChobitMlGruSeqAi<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> {
+    enc_layer: MlGru<MIDDLE, IN>,
+    dec_layer: MlGru<MIDDLE, MIDDLE>,
+    output_layer: MlLayer<OUT, MIDDLE>,
+
+    tmp_prev_state: MathVec<MIDDLE>,
+    tmp_state_error: MathVec<MIDDLE>,
+    tmp_enc_output: MathVec<MIDDLE>,
+    tmp_output_error: Vec<MathVec<OUT>>,
+    tmp_dec_output_error: MathVec<MIDDLE>,
+    tmp_enc_output_error: MathVec<MIDDLE>,
+    tmp_enc_output_error_one: MathVec<MIDDLE>,
+
+    original_prev_state: MathVec<MIDDLE>,
+    original_state: MathVec<MIDDLE>,
+    original_enc_output: MathVec<MIDDLE>,
+    original_dec_output: MathVec<MIDDLE>,
+
+    original_tmpbuf: MathVec<MIDDLE>
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> ChobitMlGruSeqAi<OUT, MIDDLE, IN> {
+    /// Creates ChobitMlGruSeqAi.
+    ///
+    /// - `ai` : Base [`ChobitGruSeqAi`].
+    /// - _Return_ : ChobitMlGruSeqAi.
+    #[inline]
+    pub fn new(ai: ChobitGruSeqAi<OUT, MIDDLE, IN>) -> Self {
+        let ChobitGruSeqAi::<OUT, MIDDLE, IN> {
+            enc_layer,
+            dec_layer,
+            output_layer,
+            prev_state,
+            state,
+            enc_output,
+            dec_output,
+            tmpbuf
+        } = ai;
+
+        Self {
+            enc_layer: MlGru::<MIDDLE, IN>::new(enc_layer),
+            dec_layer: MlGru::<MIDDLE, MIDDLE>::new(dec_layer),
+            output_layer: MlLayer::<OUT, MIDDLE>::new(output_layer),
+
+            tmp_prev_state: MathVec::<MIDDLE>::new(),
+            tmp_state_error: MathVec::<MIDDLE>::new(),
+            tmp_enc_output: MathVec::<MIDDLE>::new(),
+            tmp_output_error: Vec::<MathVec<OUT>>::new(),
+            tmp_dec_output_error: MathVec::<MIDDLE>::new(),
+            tmp_enc_output_error: MathVec::<MIDDLE>::new(),
+            tmp_enc_output_error_one: MathVec::<MIDDLE>::new(),
+
+            original_prev_state: prev_state,
+            original_state: state,
+            original_enc_output: enc_output,
+            original_dec_output: dec_output,
+            original_tmpbuf: tmpbuf
+        }
+    }
+
+    /// Drops base [`ChobitGruSeqAi`].
+    ///
+    /// - _Return_ : [`ChobitGruSeqAi`].
+    #[inline]
+    pub fn drop(self) -> ChobitGruSeqAi<OUT, MIDDLE, IN> {
+        let ChobitMlGruSeqAi::<OUT, MIDDLE, IN> {
+            enc_layer,
+            dec_layer,
+            output_layer,
+            original_prev_state,
+            original_state,
+            original_enc_output,
+            original_dec_output,
+            original_tmpbuf,
+            ..
+        } = self;
+
+        ChobitGruSeqAi::<OUT, MIDDLE, IN> {
+            enc_layer: enc_layer.drop(),
+            dec_layer: dec_layer.drop(),
+            output_layer: output_layer.drop(),
+
+            prev_state: original_prev_state,
+            state: original_state,
+            enc_output: original_enc_output,
+            dec_output: original_dec_output,
+
+            tmpbuf: original_tmpbuf
+        }
+    }
+
+    /// Clears internal data for study.
+    #[inline]
+    pub fn clear_study_data(&mut self) {
+        self.enc_layer.clear_study_data();
+        self.dec_layer.clear_study_data();
+        self.output_layer.clear_study_data();
+    }
+
+    /// Sets [`Optimizer`] to all layers.
+    ///
+    /// See [`MlLayer::set_optimizer()`] for details.
+    ///
+    /// - `optimizer` : Optimizer.
+    #[inline]
+    pub fn set_optimizer(&mut self, optimizer: Optimizer) {
+        self.enc_layer.set_optimizer(optimizer);
+        self.dec_layer.set_optimizer(optimizer);
+        self.output_layer.set_optimizer(optimizer);
+    }
+
+    /// Writes information on [`MlGruSeqAiCache`] for [`study()`](Self::study()).
+    ///
+    /// - `input` : Input.
+    /// - `prev_state` : Previous state.
+    /// - `output_len` : Length of output sequence.
+    /// - `cache` : Cache.
+    #[inline]
+    pub fn ready(
+        &mut self,
+        input: &[MathVec<IN>],
+        prev_state: &MathVec<MIDDLE>,
+        output_len: usize,
+        cache: &mut MlGruSeqAiCache<OUT, MIDDLE, IN>
+    ) {
+        self.ready_enc_layer(input, prev_state, cache);
+        self.ready_dec_layer(output_len, cache);
+    }
+
+    fn ready_enc_layer(
+        &mut self,
+        input: &[MathVec<IN>],
+        prev_state: &MathVec<MIDDLE>,
+        cache: &mut MlGruSeqAiCache<OUT, MIDDLE, IN>
+    ) {
+        cache.enc_state_caches_len = input.len();
+        if cache.enc_state_caches.len() < input.len() {
+            cache.enc_state_caches.resize(
+                input.len(),
+                MlGruStateCache::<MIDDLE, IN>::new(),
+            );
+        }
+
+        self.tmp_prev_state.copy_from(prev_state);
+
+        input.iter().zip(
+            cache.enc_state_caches.iter_mut()
+        ).for_each(|(input_one, cache)| {
+            self.enc_layer.ready_state_cache(
+                input_one,
+                &self.tmp_prev_state,
+                cache
+            );
+
+            self.tmp_prev_state.copy_from(&cache.state);
+        });
+
+        if let Some(mut output_cache) = cache.take_enc_output_cache() {
+            if let Some(state_cache) = cache.enc_last_state_cache() {
+                self.enc_layer.ready_output_cache(
+                    state_cache,
+                    &mut output_cache
+                );
+            }
+
+            cache.set_enc_output_cache(output_cache);
+        }
+    }
+
+    fn ready_dec_layer(
+        &mut self,
+        output_len: usize,
+        cache: &mut MlGruSeqAiCache<OUT, MIDDLE, IN>
+    ) {
+        cache.dec_caches_len = output_len;
+        if cache.dec_caches.len() < output_len {
+            cache.dec_caches.resize(
+                output_len,
+                (
+                    MlGruStateCache::<MIDDLE, MIDDLE>::new(),
+                    MlGruOutputCache::<MIDDLE, MIDDLE>::new(),
+                    MlCache::<OUT, MIDDLE>::new()
+                )
+            );
+        }
+
+        match cache.enc_last_state_cache() {
+            Some(state_cache) => {
+                self.tmp_prev_state.copy_from(&state_cache.state);
+            },
+
+            None => {self.tmp_prev_state.clear();}
+        }
+
+        if let Some(enc_output_cache) = cache.take_enc_output_cache() {
+            cache.dec_caches_mut().iter_mut().for_each(
+                |(dec_state_cache, dec_output_cache, output_layer_cache)| {
+                    self.dec_layer.ready_state_cache(
+                        &enc_output_cache.output,
+                        &self.tmp_prev_state,
+                        dec_state_cache
+                    );
+
+                    self.dec_layer.ready_output_cache(
+                        dec_state_cache,
+                        dec_output_cache
+                    );
+
+                    self.output_layer.ready(
+                        &dec_output_cache.output,
+                        None,
+                        output_layer_cache
+                    );
+
+                    self.tmp_prev_state.copy_from(&dec_state_cache.state);
+                }
+            );
+
+            cache.set_enc_output_cache(enc_output_cache);
+        }
+    }
+
+    /// Accumulates gradient.
+    ///
+    /// - `output_error` : Backpropagated ouput error.
+    /// - `cache` : Cache.
+    /// - `input_error` : Error to backpropagate for previous output error.
+    /// - `prev_state_error` : Error to backpropagate for previous state error.
+    pub fn study(
+        &mut self,
+        output_error: &[MathVec<OUT>],
+        cache: &MlGruSeqAiCache<OUT, MIDDLE, IN>,
+        input_error: &mut [MathVec<IN>],
+        prev_state_error: &mut MathVec<MIDDLE>,
+    ) {
+        self.tmp_state_error.clear();
+        self.tmp_enc_output_error.clear();
+
+        cache.dec_caches().iter().zip(
+            output_error.iter()
+        ).rev().for_each(
+            |(
+                (dec_state_cache, dec_output_cache, output_layer_cache),
+                output_error_one
+            )| {
+                self.output_layer.study(
+                    output_error_one,
+                    None,
+                    output_layer_cache,
+                    &mut self.tmp_dec_output_error,
+                    None
+                );
+
+                self.dec_layer.study(
+                    &self.tmp_dec_output_error,
+                    &self.tmp_state_error,
+                    dec_state_cache,
+                    dec_output_cache,
+                    &mut self.tmp_enc_output_error_one,
+                    prev_state_error
+                );
+
+                self.tmp_state_error.copy_from(prev_state_error);
+                self.tmp_enc_output_error += &self.tmp_enc_output_error_one;
+            }
+        );
+
+        if let Some(output_cache) = cache.enc_output_cache.as_ref() {
+            let mut enc_state_caches_iter =
+                cache.enc_state_caches().iter().zip(
+                    input_error.iter_mut()
+                ).rev();
+
+            if let Some((state_cache, input_error_one)) =
+                enc_state_caches_iter.next()
+            {
+                self.enc_layer.study(
+                    &self.tmp_enc_output_error,
+                    &self.tmp_state_error,
+                    state_cache,
+                    output_cache,
+                    input_error_one,
+                    prev_state_error
+                );
+
+                self.tmp_state_error.copy_from(prev_state_error);
+            }
+
+            enc_state_caches_iter.for_each(
+                |(cache, input_error_one)| {
+                    self.enc_layer.study_state(
+                        &self.tmp_state_error,
+                        cache,
+                        input_error_one,
+                        prev_state_error
+                    );
+
+                    self.tmp_state_error.copy_from(prev_state_error);
+                }
+            );
+        }
+    }
+
+    /// Updates weights with learning rate from [`LrScheduler`], and advances step of scheduler.
+    ///
+    /// - `scheduler` : Scheduler of learning rate.
+    #[inline]
+    pub fn update_with_scheduler(&mut self, scheduler: &mut LrScheduler) {
+        self.update(scheduler.next_rate());
+    }
+
+    /// Updates weights.
+    ///
+    /// - `rate` : Learning rate.
+    #[inline]
+    pub fn update(&mut self, rate: f32) {
+        self.enc_layer.update(rate);
+        self.dec_layer.update(rate);
+        self.output_layer.update(rate);
+    }
+
+    /// Accesses each immutable total gradient with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_total_grad<F>(
+        &self,
+        mut f: F
+    ) where F: FnMut(&f32) {
+        self.enc_layer.for_each_total_grad(|val| {f(val)});
+        self.dec_layer.for_each_total_grad(|val| {f(val)});
+        self.output_layer.for_each_grad(&mut f);
+    }
+
+    /// Accesses each mutable total gradient with closure.
+    ///
+    /// - `f` : Closure.
+    #[inline]
+    pub fn for_each_total_grad_mut<F>(
+        &mut self,
+        mut f: F
+    ) where F: FnMut(&mut f32) {
+        self.enc_layer.for_each_total_grad_mut(|val| {f(val)});
+        self.dec_layer.for_each_total_grad_mut(|val| {f(val)});
+        self.output_layer.for_each_grad_mut(&mut f);
+    }
+
+    /// Clips each total gradient into `[-limit, limit]`.
+    ///
+    /// - `limit` : Limit of absolute value. Negative value is treated as its absolute value. If NaN, nothing is clipped.
+    #[inline]
+    pub fn clip_grad_by_value(&mut self, limit: f32) {
+        clip_by_value(limit, |f| self.for_each_total_grad_mut(f));
+    }
+
+    /// Scales all total gradients so that L2 norm of them is `max_norm` or less.
+    ///
+    /// - `max_norm` : Max L2 norm. Negative value is treated as its absolute value.
+    /// - _Return_ : L2 norm before clipping. If it is not finite, nothing is scaled.
+    #[inline]
+    pub fn clip_grad_by_global_norm(&mut self, max_norm: f32) -> f32 {
+        clip_by_global_norm(max_norm, |f| self.for_each_total_grad_mut(f))
+    }
+
+    /// Adds gradient of L1 and L2 penalty of weights into total gradients of all layers.
+    ///
+    /// See [`MlLayer::add_weight_penalty()`] for details.
+    ///
+    /// - `l1` : Coefficient of L1 penalty.
+    /// - `l2` : Coefficient of L2 penalty.
+    #[inline]
+    pub fn add_weight_penalty(&mut self, l1: f32, l2: f32) {
+        self.enc_layer.add_weight_penalty(l1, l2);
+        self.dec_layer.add_weight_penalty(l1, l2);
+        self.output_layer.add_weight_penalty(l1, l2);
+    }
+
+    /// Checks that all total gradients and all weights are finite.
+    ///
+    /// See [`ChobitAiError::NonFinite`] for order of checking.
+    /// If error is returned, you can abort machine learning, or skip the batch by clearing total gradients with [`for_each_total_grad_mut()`](Self::for_each_total_grad_mut()).
+    ///
+    /// - _Return_ : [`ChobitAiError::NonFinite`] if non-finite number is found.
+    pub fn check_finite(&self) -> Result<(), ChobitAiError> {
+        const ENC_NAMES: [&str; 3] = [
+            "enc_layer.main_layer",
+            "enc_layer.z_gate",
+            "enc_layer.r_gate"
+        ];
+        const DEC_NAMES: [&str; 3] = [
+            "dec_layer.main_layer",
+            "dec_layer.z_gate",
+            "dec_layer.r_gate"
+        ];
+
+        self.output_layer.check_grad("output_layer")?;
+        self.dec_layer.check_grad(&DEC_NAMES)?;
+        self.enc_layer.check_grad(&ENC_NAMES)?;
+
+        self.output_layer.check_weights("output_layer")?;
+        self.dec_layer.check_weights(&DEC_NAMES)?;
+        self.enc_layer.check_weights(&ENC_NAMES)?;
+
+        Ok(())
+    }
+
+    /// Writes full state for machine learning into buffer as binary data.
+    ///
+    /// See [`MlLayer::save_checkpoint()`] for details.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save_checkpoint(&self, buffer: &mut Vec<u8>) {
+        write_header(buffer, ModelKind::ChobitMlGruSeqAi, &[OUT, MIDDLE, IN]);
+
+        self.enc_layer.write_state(buffer);
+        self.dec_layer.write_state(buffer);
+        self.output_layer.write_state(buffer);
+    }
+
+    /// Creates ChobitMlGruSeqAi from binary data written by [`save_checkpoint()`](Self::save_checkpoint()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : ChobitMlGruSeqAi.
+    pub fn load_checkpoint(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(
+            ModelKind::ChobitMlGruSeqAi,
+            &[("OUT", OUT), ("MIDDLE", MIDDLE), ("IN", IN)]
+        )?;
+
+        let mut ret = Self::new(
+            ChobitGruSeqAi::<OUT, MIDDLE, IN>::new(Activation::Linear)
+        );
+
+        ret.enc_layer = MlGru::<MIDDLE, IN>::read_state(&mut reader)?;
+        ret.dec_layer = MlGru::<MIDDLE, MIDDLE>::read_state(&mut reader)?;
+        ret.output_layer = MlLayer::<OUT, MIDDLE>::read_state(&mut reader)?;
+
+        Ok(ret)
+    }
+}
+
+#[inline]
+fn round_to_i32(x: f32) -> i32 {
+    (x + if x < 0.0 {-0.5} else {0.5}) as i32
+}
+
+#[inline]
+fn clamp_to_i8(x: i32) -> i8 {
+    x.clamp(i8::MIN as i32, i8::MAX as i32) as i8
+}
+
+#[inline]
+fn dot_i8(a: &[i8], b: &[i8]) -> i64 {
+    a.iter().zip(b.iter()).fold(
+        0,
+        |sum, (a, b)| sum + (((*a as i32) * (*b as i32)) as i64)
+    )
+}
+
+/// Parameters of affine quantization from `f32` into `i8`.
+///
+/// Real number `r` and quantized number `q` satisfy `r = scale * (q - zero_point)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuantParams {
+    scale: f32,
+    zero_point: i32
+}
+
+impl QuantParams {
+    /// Creates QuantParams.
+    ///
+    /// - `scale` : Scale. If it is not positive finite number, `1.0` is used.
+    /// - `zero_point` : Quantized number of `0.0`. It is clamped into `[-128, 127]`.
+    /// - _Return_ : QuantParams.
+    #[inline]
+    pub fn new(scale: f32, zero_point: i32) -> Self {
+        Self {
+            scale: if scale.is_finite() && (scale > 0.0) {scale} else {1.0},
+            zero_point: clamp_to_i8(zero_point) as i32
+        }
+    }
+
+    /// Creates QuantParams that maps `[min, max]` onto `[-128, 127]`.
+    ///
+    /// The range is extended to contain `0.0`, so `0.0` is quantized without error.
+    ///
+    /// - `min` : Min of real numbers.
+    /// - `max` : Max of real numbers.
+    /// - _Return_ : QuantParams.
+    pub fn from_range(min: f32, max: f32) -> Self {
+        let min = min.min(0.0);
+        let max = max.max(0.0);
+
+        let scale = (max - min) / 255.0;
+
+        if scale.is_finite() && (scale > 0.0) {
+            Self::new(scale, round_to_i32(-128.0 - (min / scale)))
+        } else {
+            Self::new(1.0, 0)
+        }
+    }
+
+    /// Gets scale.
+    ///
+    /// - _Return_ : Scale.
+    #[inline]
+    pub fn scale(&self) -> f32 {self.scale}
+
+    /// Gets zero point.
+    ///
+    /// - _Return_ : Zero point.
+    #[inline]
+    pub fn zero_point(&self) -> i32 {self.zero_point}
+
+    /// Quantizes real number.
+    ///
+    /// - `value` : Real number. Out of range is saturated.
+    /// - _Return_ : Quantized number.
+    #[inline]
+    pub fn quantize(&self, value: f32) -> i8 {
+        self.quantize_with(value, self.scale.recip())
+    }
+
+    /// Dequantizes quantized number.
+    ///
+    /// - `value` : Quantized number.
+    /// - _Return_ : Real number.
+    #[inline]
+    pub fn dequantize(&self, value: i8) -> f32 {
+        self.scale * (((value as i32) - self.zero_point) as f32)
+    }
+
+    #[inline]
+    fn quantize_with(&self, value: f32, scale_inv: f32) -> i8 {
+        clamp_to_i8(
+            round_to_i32(value * scale_inv).saturating_add(self.zero_point)
+        )
+    }
+
+    // Returns sum of quantized numbers.
+    #[inline]
+    fn quantize_slice(&self, values: &[f32], output: &mut [i8]) -> i64 {
+        let scale_inv = self.scale.recip();
+
+        output.iter_mut().zip(values.iter()).fold(0, |sum, (q, value)| {
+            *q = self.quantize_with(*value, scale_inv);
+
+            sum + (*q as i64)
+        })
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>) {
+        write_f32(buffer, self.scale);
+        write_u32(buffer, self.zero_point as u32);
+    }
+
+    fn read(reader: &mut ByteReader) -> Result<Self, ChobitAiError> {
+        let scale = reader.read_f32()?;
+        let zero_point = reader.read_u32()? as i32;
+
+        Ok(Self::new(scale, zero_point))
+    }
+}
+
+/// Observer of range of real numbers for calibration of [`QuantParams`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Calibrator {
+    min: f32,
+    max: f32
+}
+
+impl Calibrator {
+    /// Creates Calibrator that has observed nothing.
+    ///
+    /// - _Return_ : Calibrator.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            min: f32::INFINITY,
+            max: f32::NEG_INFINITY
+        }
+    }
+
+    /// Observes real numbers.
+    ///
+    /// - `values` : Real numbers. Non-finite numbers are ignored.
+    #[inline]
+    pub fn observe(&mut self, values: &[f32]) {
+        values.iter().filter(|val| val.is_finite()).for_each(|val| {
+            self.min = self.min.min(*val);
+            self.max = self.max.max(*val);
+        });
+    }
+
+    /// Gets min of observed numbers.
+    ///
+    /// - _Return_ : Min. If nothing is observed, `f32::INFINITY`.
+    #[inline]
+    pub fn min(&self) -> f32 {self.min}
+
+    /// Gets max of observed numbers.
+    ///
+    /// - _Return_ : Max. If nothing is observed, `f32::NEG_INFINITY`.
+    #[inline]
+    pub fn max(&self) -> f32 {self.max}
+
+    /// Makes QuantParams from observed range.
+    ///
+    /// See [`QuantParams::from_range()`] for details.
+    ///
+    /// - _Return_ : QuantParams.
+    #[inline]
+    pub fn params(&self) -> QuantParams {
+        if self.min <= self.max {
+            QuantParams::from_range(self.min, self.max)
+        } else {
+            QuantParams::from_range(0.0, 0.0)
+        }
+    }
+}
+
+// Quantized vector on stack.
+struct QuantizedVec<const N: usize> {
+    body: [i8; N],
+    params: QuantParams,
+    sum: i64
+}
+
+impl<const N: usize> QuantizedVec<N> {
+    #[inline]
+    fn new(params: &QuantParams, values: &MathVec<N>) -> Self {
+        let mut body = [0i8; N];
+        let sum = params.quantize_slice(values, &mut body);
+
+        Self {
+            body: body,
+            params: *params,
+            sum: sum
+        }
+    }
+}
+
+// Matrix quantized row by row.
+#[derive(Debug, Clone, PartialEq)]
+struct QuantizedMatrix<const ROWS: usize, const COLS: usize> {
+    body: Vec<i8>,
+    params: Vec<QuantParams>,
+
+    // Sum of each row for zero point of input.
+    sums: Vec<i64>
+}
+
+impl<const ROWS: usize, const COLS: usize> QuantizedMatrix<ROWS, COLS> {
+    fn new(rows: &[[f32; COLS]; ROWS]) -> Self {
+        let mut body = vec![0i8; ROWS * COLS];
+        let mut params = Vec::<QuantParams>::with_capacity(ROWS);
+        let mut sums = Vec::<i64>::with_capacity(ROWS);
+
+        rows.iter().zip(body.chunks_exact_mut(COLS)).for_each(
+            |(row, q_row)| {
+                let mut calibrator = Calibrator::new();
+                calibrator.observe(row);
+
+                let params_one = calibrator.params();
+
+                sums.push(params_one.quantize_slice(row, q_row));
+                params.push(params_one);
+            }
+        );
+
+        Self {
+            body: body,
+            params: params,
+            sums: sums
+        }
+    }
+
+    fn dequantize(&self, rows: &mut [[f32; COLS]; ROWS]) {
+        rows.iter_mut().zip(self.body.chunks_exact(COLS)).zip(
+            self.params.iter()
+        ).for_each(|((row, q_row), params)| {
+            row.iter_mut().zip(q_row.iter()).for_each(|(val, q)| {
+                *val = params.dequantize(*q);
+            });
+        });
+    }
+
+    // Σ_j (w_ij - z_w)(x_j - z_x) is calculated in integer,
+    // expanding it to Σ_j w_ij x_j - z_x Σ_j w_ij - z_w Σ_j x_j + n z_w z_x.
+    // Each term can be `255 * 255 * n`, so it is accumulated in i64.
+    fn add_product(&self, input: &QuantizedVec<COLS>, output: &mut [f32]) {
+        let z_x = input.params.zero_point as i64;
+        let len = COLS as i64;
+
+        output.iter_mut().zip(self.body.chunks_exact(COLS)).zip(
+            self.params.iter().zip(self.sums.iter())
+        ).for_each(|((output_one, row), (params, sum))| {
+            let z_w = params.zero_point as i64;
+
+            let acc = dot_i8(row, &input.body)
+                - (z_x * *sum)
+                - (z_w * input.sum)
+                + (len * z_w * z_x);
+
+            *output_one += params.scale * input.params.scale * (acc as f32);
+        });
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>) {
+        write_u32(buffer, self.body.len() as u32);
+
+        self.params.iter().for_each(|params| {params.write(buffer);});
+        self.body.iter().for_each(|val| {write_u8(buffer, *val as u8);});
+    }
+
+    fn read(reader: &mut ByteReader) -> Result<Self, ChobitAiError> {
+        let len = reader.read_u32()? as usize;
+
+        if len != ROWS * COLS {
+            return Err(ChobitAiError::DimensionMismatch {
+                name: "weights",
+                expected: ROWS * COLS,
+                found: len
+            });
+        }
+
+        let mut params = Vec::<QuantParams>::with_capacity(ROWS);
+
+        for _ in 0..ROWS {
+            params.push(QuantParams::read(reader)?);
+        }
+
+        let body = reader.read_bytes(len)?.iter().map(
+            |val| *val as i8
+        ).collect::<Vec<i8>>();
+
+        let sums = body.chunks_exact(COLS).map(
+            |row| row.iter().fold(0, |sum, val| sum + (*val as i64))
+        ).collect::<Vec<i64>>();
+
+        Ok(Self {
+            body: body,
+            params: params,
+            sums: sums
+        })
+    }
+}
+
+/// [`Weights`] quantized into `i8`.
 ///
-/// // Tests Japanese.
-/// for _ in 0..10 {
-///     let string = gen_word(japanese_letter, &mut rng, MAX_WORD_LEN);
+/// Weights for input and weights for state are quantized row by row, that is, each output has its own [`QuantParams`]. Bias is not quantized.  
+/// Products of weights and quantized input are accumulated in `i64`.
 ///
-///     write_string_to_slice(&string, &mut input);
+/// - `OUT` : Dimension of output.
+/// - `IN` : Dimension of input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuantizedWeights<const OUT: usize, const IN: usize> {
+    bias: MathVec<OUT>,
+
+    input_weights: QuantizedMatrix<OUT, IN>,
+    state_weights: Option<QuantizedMatrix<OUT, OUT>>
+}
+
+impl<const OUT: usize, const IN: usize> QuantizedWeights<OUT, IN> {
+    /// Quantizes weights.
+    ///
+    /// - `weights` : Weights.
+    /// - _Return_ : QuantizedWeights.
+    pub fn new(weights: &Weights<OUT, IN>) -> Self {
+        let mut bias = MathVec::<OUT>::new();
+        bias.copy_from_slice(weights.bias());
+
+        Self {
+            bias: bias,
+
+            input_weights:
+                QuantizedMatrix::<OUT, IN>::new(weights.input_weights()),
+            state_weights: weights.state_weights().map(
+                QuantizedMatrix::<OUT, OUT>::new
+            )
+        }
+    }
+
+    /// Gets bias.
+    ///
+    /// - _Return_ : Bias.
+    #[inline]
+    pub fn bias(&self) -> &[f32; OUT] {self.bias.as_array()}
+
+    /// Gets quantized weights for input.
+    ///
+    /// - _Return_ : Weights for input. Row major order of `OUT` rows and `IN` columns.
+    #[inline]
+    pub fn input_weights(&self) -> &[i8] {&self.input_weights.body}
+
+    /// Gets [`QuantParams`] of each row of weights for input.
+    ///
+    /// - _Return_ : QuantParams. Its length is `OUT`.
+    #[inline]
+    pub fn input_params(&self) -> &[QuantParams] {&self.input_weights.params}
+
+    /// Gets quantized weights for state.
+    ///
+    /// - _Return_ : Weights for state if it exists. Row major order of `OUT` rows and `OUT` columns.
+    #[inline]
+    pub fn state_weights(&self) -> Option<&[i8]> {
+        self.state_weights.as_ref().map(|matrix| matrix.body.as_slice())
+    }
+
+    /// Gets [`QuantParams`] of each row of weights for state.
+    ///
+    /// - _Return_ : QuantParams if weights for state exist. Its length is `OUT`.
+    #[inline]
+    pub fn state_params(&self) -> Option<&[QuantParams]> {
+        self.state_weights.as_ref().map(|matrix| matrix.params.as_slice())
+    }
+
+    /// Dequantizes into [`Weights`].
+    ///
+    /// - _Return_ : Weights.
+    pub fn dequantize(&self) -> Weights<OUT, IN> {
+        let mut ret = Weights::<OUT, IN>::new(self.state_weights.is_some());
+
+        ret.bias_mut().copy_from_slice(self.bias.as_array());
+
+        self.input_weights.dequantize(ret.input_weights_mut());
+
+        if let (Some(matrix), Some(state_weights)) =
+            (self.state_weights.as_ref(), ret.state_weights_mut())
+        {
+            matrix.dequantize(state_weights);
+        }
+
+        ret
+    }
+
+    #[inline]
+    fn calc(
+        &self,
+        input: &QuantizedVec<IN>,
+        state: Option<&QuantizedVec<OUT>>,
+        output: &mut MathVec<OUT>
+    ) {
+        output.copy_from(&self.bias);
+
+        self.input_weights.add_product(input, output);
+
+        if let (Some(matrix), Some(state)) =
+            (self.state_weights.as_ref(), state)
+        {
+            matrix.add_product(state, output);
+        }
+    }
+
+    fn write_body(&self, buffer: &mut Vec<u8>) {
+        self.bias.write_body(buffer);
+
+        self.input_weights.write(buffer);
+
+        if let Some(matrix) = self.state_weights.as_ref() {
+            matrix.write(buffer);
+        }
+    }
+
+    fn read_body(
+        reader: &mut ByteReader,
+        has_state_weights: bool
+    ) -> Result<Self, ChobitAiError> {
+        let mut bias = MathVec::<OUT>::new();
+        bias.read_body(reader)?;
+
+        let input_weights = QuantizedMatrix::<OUT, IN>::read(reader)?;
+
+        let state_weights = if has_state_weights {
+            Some(QuantizedMatrix::<OUT, OUT>::read(reader)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            bias: bias,
+
+            input_weights: input_weights,
+            state_weights: state_weights
+        })
+    }
+}
+
+/// [`Layer`] for inference with weights quantized into `i8`.
 ///
-///     // Initializes state.
-///     ai.state_mut().copy_from(&initial_state);
-///     
-///     // Inputs for each one.
-///     input.iter().for_each(|input_one| {
-///         ai.input_next(input_one);
-///     });
+/// Input and state are quantized with their own [`QuantParams`], and products are accumulated in integer. Bias and activation function are calculated in `f32`.  
+/// See [`QuantizedWeights`] for details.
 ///
-///     // Outputs for each one.
-///     JAPANESE_MESSAGE.chars().for_each(|c| {
-///         ai.output_next(&mut output);
+/// - `OUT` : Dimension of output.
+/// - `IN` : Dimension of input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuantizedLayer<const OUT: usize, const IN: usize> {
+    weights: QuantizedWeights<OUT, IN>,
+    activation: Activation,
+
+    input_params: QuantParams,
+    state_params: QuantParams
+}
+
+impl<const OUT: usize, const IN: usize> QuantizedLayer<OUT, IN> {
+    /// Creates QuantizedLayer by quantizing layer.
+    ///
+    /// - `layer` : Layer.
+    /// - `input_params` : Parameters to quantize input.
+    /// - `state_params` : Parameters to quantize state. If the layer doesn't accept state, it is not used.
+    /// - _Return_ : QuantizedLayer.
+    #[inline]
+    pub fn new(
+        layer: &Layer<OUT, IN>,
+        input_params: QuantParams,
+        state_params: QuantParams
+    ) -> Self {
+        Self {
+            weights: QuantizedWeights::<OUT, IN>::new(&layer.weights),
+            activation: layer.activation,
+
+            input_params: input_params,
+            state_params: state_params
+        }
+    }
+
+    /// Creates QuantizedLayer with parameters calibrated by samples.
+    ///
+    /// - `layer` : Layer.
+    /// - `inputs` : Sample inputs.
+    /// - `states` : Sample states. It can be empty if the layer doesn't accept state.
+    /// - _Return_ : QuantizedLayer.
+    pub fn calibrate(
+        layer: &Layer<OUT, IN>,
+        inputs: &[MathVec<IN>],
+        states: &[MathVec<OUT>]
+    ) -> Self {
+        let mut input_calibrator = Calibrator::new();
+        inputs.iter().for_each(|input| {input_calibrator.observe(input);});
+
+        let mut state_calibrator = Calibrator::new();
+        states.iter().for_each(|state| {state_calibrator.observe(state);});
+
+        Self::new(layer, input_calibrator.params(), state_calibrator.params())
+    }
+
+    /// Gets quantized weights.
+    ///
+    /// - _Return_ : Weights.
+    #[inline]
+    pub fn weights(&self) -> &QuantizedWeights<OUT, IN> {&self.weights}
+
+    /// Gets activation function.
+    ///
+    /// - _Return_ : Activation function.
+    #[inline]
+    pub fn activation(&self) -> &Activation {&self.activation}
+
+    /// Gets parameters to quantize input.
+    ///
+    /// - _Return_ : QuantParams.
+    #[inline]
+    pub fn input_params(&self) -> &QuantParams {&self.input_params}
+
+    /// Gets parameters to quantize state.
+    ///
+    /// - _Return_ : QuantParams.
+    #[inline]
+    pub fn state_params(&self) -> &QuantParams {&self.state_params}
+
+    /// Dequantizes into [`Layer`].
+    ///
+    /// - _Return_ : Layer.
+    #[inline]
+    pub fn dequantize(&self) -> Layer<OUT, IN> {
+        Layer::<OUT, IN> {
+            weights: self.weights.dequantize(),
+            activation: self.activation
+        }
+    }
+
+    /// Calculates neural network layer.
+    ///
+    /// See [`Layer::calc()`] for details.
+    ///
+    /// - `input` : Input.
+    /// - `state` : State if it exists.
+    /// - `output` : Buffer for output.
+    pub fn calc(
+        &self,
+        input: &MathVec<IN>,
+        state: Option<&MathVec<OUT>>,
+        output: &mut MathVec<OUT>
+    ) {
+        let input = QuantizedVec::<IN>::new(&self.input_params, input);
+
+        match state {
+            Some(state) => {
+                let state =
+                    QuantizedVec::<OUT>::new(&self.state_params, state);
+
+                self.calc_quantized(&input, Some(&state), output);
+            },
+
+            None => {
+                self.calc_quantized(&input, None, output);
+            }
+        }
+    }
+
+    #[inline]
+    fn calc_quantized(
+        &self,
+        input: &QuantizedVec<IN>,
+        state: Option<&QuantizedVec<OUT>>,
+        output: &mut MathVec<OUT>
+    ) {
+        self.weights.calc(input, state, output);
+
+        output.iter_mut().for_each(
+            |val| {*val = self.activation.activate(*val);}
+        );
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>) {
+        self.activation.write(buffer);
+        write_u8(buffer, self.weights.state_weights.is_some() as u8);
+
+        self.input_params.write(buffer);
+        self.state_params.write(buffer);
+
+        self.weights.write_body(buffer);
+    }
+
+    fn read(reader: &mut ByteReader) -> Result<Self, ChobitAiError> {
+        let activation = Activation::read(reader)?;
+        let has_state_weights = reader.read_u8()? != 0;
+
+        let input_params = QuantParams::read(reader)?;
+        let state_params = QuantParams::read(reader)?;
+
+        Ok(Self {
+            weights: QuantizedWeights::<OUT, IN>::read_body(
+                reader,
+                has_state_weights
+            )?,
+            activation: activation,
+
+            input_params: input_params,
+            state_params: state_params
+        })
+    }
+}
+
+/// [`ChobitAi`] for inference with weights quantized into `i8`.
 ///
-///         assert_eq!(output.to_u32_label(), c as u32);
-///     });
-/// }
+/// It is about 4 times smaller than [`ChobitAi`], and products are calculated in integer.
 ///
-/// // Tests English.
-/// for _ in 0..10 {
-///     let string = gen_word(english_letter, &mut rng, MAX_WORD_LEN);
+/// ```ignore
+/// use chobitlibs::chobit_ai::{
+///     MathVec,
+///     ChobitAi,
+///     QuantizedChobitAi
+/// };
 ///
-///     write_string_to_slice(&string, &mut input);
+/// // Trained AI and sample inputs for calibration.
+/// let ai: ChobitAi<8, 32, 16> = ...;
+/// let samples: Vec<MathVec<16>> = ...;
 ///
-///     // Initializes state.
-///     ai.state_mut().copy_from(&initial_state);
-///     
-///     // Inputs for each one.
-///     input.iter().for_each(|input_one| {
-///         ai.input_next(input_one);
-///     });
+/// let quantized = QuantizedChobitAi::<8, 32, 16>::calibrate(&ai, &samples);
 ///
-///     // Outputs for each one.
-///     ENGLISH_MESSAGE.chars().for_each(|c| {
-///         ai.output_next(&mut output);
+/// let mut output = MathVec::<8>::new();
+/// let mut tmpbuf = MathVec::<32>::new();
 ///
-///         assert_eq!(output.to_u32_label(), c as u32);
-///     });
-/// }
+/// quantized.calc(&samples[0], &mut output, &mut tmpbuf);
 /// ```
+///
+/// - `OUT` : Dimension of output.
+/// - `MIDDLE` : Dimension of hidden layer.
+/// - `IN` : Dimension of input.
 #[derive(Debug, Clone, PartialEq)]
-pub struct ChobitSeqAi<
+pub struct QuantizedChobitAi<
     const OUT: usize,
     const MIDDLE: usize,
     const IN: usize
 > {
-    enc_layer: Lstm<MIDDLE, IN>,
-    dec_layer: Lstm<MIDDLE, MIDDLE>,
-    output_layer: Layer<OUT, MIDDLE>,
+    middle_layer: QuantizedLayer<MIDDLE, IN>,
+    output_layer: QuantizedLayer<OUT, MIDDLE>
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> QuantizedChobitAi<OUT, MIDDLE, IN> {
+    /// Creates QuantizedChobitAi by quantizing AI.
+    ///
+    /// - `ai` : AI.
+    /// - `input_params` : Parameters to quantize input.
+    /// - `middle_params` : Parameters to quantize output of middle layer.
+    /// - _Return_ : QuantizedChobitAi.
+    #[inline]
+    pub fn new(
+        ai: &ChobitAi<OUT, MIDDLE, IN>,
+        input_params: QuantParams,
+        middle_params: QuantParams
+    ) -> Self {
+        Self {
+            middle_layer: QuantizedLayer::<MIDDLE, IN>::new(
+                &ai.middle_layer,
+                input_params,
+                input_params
+            ),
+            output_layer: QuantizedLayer::<OUT, MIDDLE>::new(
+                &ai.output_layer,
+                middle_params,
+                middle_params
+            )
+        }
+    }
+
+    /// Creates QuantizedChobitAi with parameters calibrated by sample inputs.
+    ///
+    /// Ranges of inputs and outputs of middle layer are observed with `f32` calculation of `ai`.
+    ///
+    /// - `ai` : AI.
+    /// - `inputs` : Sample inputs.
+    /// - _Return_ : QuantizedChobitAi.
+    pub fn calibrate(
+        ai: &ChobitAi<OUT, MIDDLE, IN>,
+        inputs: &[MathVec<IN>]
+    ) -> Self {
+        let mut input_calibrator = Calibrator::new();
+        let mut middle_calibrator = Calibrator::new();
+
+        let mut tmpbuf = MathVec::<MIDDLE>::new();
+
+        inputs.iter().for_each(|input| {
+            input_calibrator.observe(input);
+
+            ai.middle_layer.calc(input, None, &mut tmpbuf);
+            middle_calibrator.observe(&tmpbuf);
+        });
+
+        Self::new(ai, input_calibrator.params(), middle_calibrator.params())
+    }
+
+    /// Gets middle layer.
+    ///
+    /// - _Return_ : Middle layer.
+    #[inline]
+    pub fn middle_layer(&self) -> &QuantizedLayer<MIDDLE, IN> {
+        &self.middle_layer
+    }
+
+    /// Gets output layer.
+    ///
+    /// - _Return_ : Output layer.
+    #[inline]
+    pub fn output_layer(&self) -> &QuantizedLayer<OUT, MIDDLE> {
+        &self.output_layer
+    }
+
+    /// Dequantizes into [`ChobitAi`].
+    ///
+    /// - _Return_ : ChobitAi.
+    #[inline]
+    pub fn dequantize(&self) -> ChobitAi<OUT, MIDDLE, IN> {
+        ChobitAi::<OUT, MIDDLE, IN>::with_layers(
+            self.middle_layer.dequantize(),
+            self.output_layer.dequantize()
+        )
+    }
+
+    /// Calculates
+    ///
+    /// - `input` : Input.
+    /// - `output` : Buffer for output.
+    /// - `tmpbuf` : Temporary buffer for this function to work.
+    #[inline]
+    pub fn calc(
+        &self,
+        input: &MathVec<IN>,
+        output: &mut MathVec<OUT>,
+        tmpbuf: &mut MathVec<MIDDLE>
+    ) {
+        self.middle_layer.calc(input, None, tmpbuf);
+        self.output_layer.calc(tmpbuf, None, output);
+    }
+
+    /// Writes this AI into buffer as binary data.
+    ///
+    /// See [`ChobitAi::save()`] for details. Quantized weights are written as 1 byte for each.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save(&self, buffer: &mut Vec<u8>) {
+        write_header(
+            buffer,
+            ModelKind::QuantizedChobitAi,
+            &[OUT, MIDDLE, IN]
+        );
+
+        self.middle_layer.write(buffer);
+        self.output_layer.write(buffer);
+    }
+
+    /// Creates QuantizedChobitAi from binary data written by [`save()`](Self::save()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : QuantizedChobitAi.
+    pub fn load(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(
+            ModelKind::QuantizedChobitAi,
+            &[("OUT", OUT), ("MIDDLE", MIDDLE), ("IN", IN)]
+        )?;
+
+        Ok(Self {
+            middle_layer: QuantizedLayer::<MIDDLE, IN>::read(&mut reader)?,
+            output_layer: QuantizedLayer::<OUT, MIDDLE>::read(&mut reader)?
+        })
+    }
+}
+
+/// [`Lstm`] for inference with weights quantized into `i8`.
+///
+/// Input and previous state are quantized once for each step, and used by all gates.  
+/// See [`QuantizedChobitAi`] and [`QuantizedLayer`] for details.
+///
+/// [`LayerNorm`] of LSTM is not quantized and calculated in `f32`.
+///
+/// - `OUT` : Dimension of output.
+/// - `IN` : Dimension of input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuantizedLstm<const OUT: usize, const IN: usize> {
+    main_layer: QuantizedLayer<OUT, IN>,
 
-    prev_state: MathVec<MIDDLE>,
-    state: MathVec<MIDDLE>,
-    enc_output: MathVec<MIDDLE>,
-    dec_output: MathVec<MIDDLE>,
+    f_gate: QuantizedLayer<OUT, IN>,
+    i_gate: QuantizedLayer<OUT, IN>,
+    o_gate: QuantizedLayer<OUT, IN>,
 
-    tmpbuf: MathVec<MIDDLE>
+    tanh: Activation,
+
+    main_layer_norm: Option<LayerNorm<OUT>>,
+    f_gate_norm: Option<LayerNorm<OUT>>,
+    i_gate_norm: Option<LayerNorm<OUT>>,
+    o_gate_norm: Option<LayerNorm<OUT>>,
+    state_norm: Option<LayerNorm<OUT>>
 }
 
-impl<
-    const OUT: usize,
-    const MIDDLE: usize,
-    const IN: usize
-> ChobitSeqAi<OUT, MIDDLE, IN> {
-    /// Creates ChobitSeqAi.
+// Same as calc_lstm_layer() with quantized input and state.
+#[inline]
+fn calc_quantized_lstm_layer<const OUT: usize, const IN: usize>(
+    layer: &QuantizedLayer<OUT, IN>,
+    norm: Option<&LayerNorm<OUT>>,
+    input: &QuantizedVec<IN>,
+    state: &QuantizedVec<OUT>,
+    output: &mut MathVec<OUT>
+) {
+    match norm {
+        Some(norm) => {
+            layer.weights.calc(input, Some(state), output);
+            norm.calc_in_place(output);
+
+            output.iter_mut().for_each(
+                |val| {*val = layer.activation.activate(*val);}
+            );
+        },
+
+        None => {
+            layer.calc_quantized(input, Some(state), output);
+        }
+    }
+}
+
+impl<const OUT: usize, const IN: usize> QuantizedLstm<OUT, IN> {
+    /// Creates QuantizedLstm by quantizing LSTM.
     ///
-    /// - `activation` : Activation function for output layer.
-    /// - _Return_ : ChobitSeqAi.
-    #[inline]
-    pub fn new(activation: Activation) -> Self {
+    /// - `lstm` : LSTM.
+    /// - `input_params` : Parameters to quantize input.
+    /// - `state_params` : Parameters to quantize state.
+    /// - _Return_ : QuantizedLstm.
+    pub fn new(
+        lstm: &Lstm<OUT, IN>,
+        input_params: QuantParams,
+        state_params: QuantParams
+    ) -> Self {
+        let quantize = |layer: &Layer<OUT, IN>| {
+            QuantizedLayer::<OUT, IN>::new(layer, input_params, state_params)
+        };
+
         Self {
-            enc_layer: Lstm::<MIDDLE, IN>::new(),
-            dec_layer: Lstm::<MIDDLE, MIDDLE>::new(),
-            output_layer: Layer::<OUT, MIDDLE>::new(activation, false),
+            main_layer: quantize(&lstm.main_layer),
 
-            prev_state: MathVec::<MIDDLE>::new(),
-            state: MathVec::<MIDDLE>::new(),
-            enc_output: MathVec::<MIDDLE>::new(),
-            dec_output: MathVec::<MIDDLE>::new(),
+            f_gate: quantize(&lstm.f_gate),
+            i_gate: quantize(&lstm.i_gate),
+            o_gate: quantize(&lstm.o_gate),
 
-            tmpbuf: MathVec::<MIDDLE>::new()
+            tanh: lstm.tanh,
+
+            main_layer_norm: lstm.main_layer_norm.clone(),
+            f_gate_norm: lstm.f_gate_norm.clone(),
+            i_gate_norm: lstm.i_gate_norm.clone(),
+            o_gate_norm: lstm.o_gate_norm.clone(),
+            state_norm: lstm.state_norm.clone()
         }
     }
 
-    /// Gets immutable encoding layer of Lstm.
+    /// Creates QuantizedLstm with parameters calibrated by sample sequences.
     ///
-    /// - _Return_ : Lstm.
-    #[inline]
-    pub fn enc_layer(&self) -> &Lstm<MIDDLE, IN> {&self.enc_layer}
+    /// Each sequence is calculated from zero state with `f32` calculation of `lstm`, and ranges of inputs and states are observed.
+    ///
+    /// - `lstm` : LSTM.
+    /// - `sequences` : Sample sequences of input.
+    /// - _Return_ : QuantizedLstm.
+    pub fn calibrate(
+        lstm: &Lstm<OUT, IN>,
+        sequences: &[&[MathVec<IN>]]
+    ) -> Self {
+        let mut input_calibrator = Calibrator::new();
+        let mut state_calibrator = Calibrator::new();
+
+        let mut prev_state = MathVec::<OUT>::new();
+        let mut next_state = MathVec::<OUT>::new();
+        let mut tmpbuf = MathVec::<OUT>::new();
+
+        sequences.iter().for_each(|sequence| {
+            prev_state.clear();
+
+            sequence.iter().for_each(|input| {
+                input_calibrator.observe(input);
+
+                lstm.calc_state(
+                    input,
+                    &prev_state,
+                    &mut next_state,
+                    &mut tmpbuf
+                );
+                state_calibrator.observe(&next_state);
 
-    /// Gets mutable encoding layer of Lstm.
+                prev_state.copy_from(&next_state);
+            });
+        });
+
+        Self::new(lstm, input_calibrator.params(), state_calibrator.params())
+    }
+
+    /// Gets activation function applied to state before output gate.
     ///
-    /// - _Return_ : Lstm.
+    /// - _Return_ : Activation function.
     #[inline]
-    pub fn enc_layer_mut(&mut self) -> &mut Lstm<MIDDLE, IN> {
-        &mut self.enc_layer
-    }
+    pub fn tanh(&self) -> &Activation {&self.tanh}
 
-    /// Gets immutable decoding layer of Lstm.
+    /// Gets main layer.
     ///
-    /// - _Return_ : Lstm.
+    /// - _Return_ : Main layer.
     #[inline]
-    pub fn dec_layer(&self) -> &Lstm<MIDDLE, MIDDLE> {&self.dec_layer}
+    pub fn main_layer(&self) -> &QuantizedLayer<OUT, IN> {&self.main_layer}
 
-    /// Gets mutable decoding layer of Lstm.
+    /// Gets forget gate.
     ///
-    /// - _Return_ : Lstm.
+    /// - _Return_ : Forget gate.
     #[inline]
-    pub fn dec_layer_mut(&mut self) -> &mut Lstm<MIDDLE, MIDDLE> {
-        &mut self.dec_layer
-    }
+    pub fn f_gate(&self) -> &QuantizedLayer<OUT, IN> {&self.f_gate}
 
-    /// Gets immutable output layer.
+    /// Gets input gate.
     ///
-    /// - _Return_ : Output layer.
+    /// - _Return_ : Input gate.
     #[inline]
-    pub fn output_layer(&self) -> &Layer<OUT, MIDDLE> {&self.output_layer}
+    pub fn i_gate(&self) -> &QuantizedLayer<OUT, IN> {&self.i_gate}
 
-    /// Gets mutable output layer.
+    /// Gets output gate.
     ///
-    /// - _Return_ : Output layer.
+    /// - _Return_ : Output gate.
     #[inline]
-    pub fn output_layer_mut(&mut self) -> &mut Layer<OUT, MIDDLE> {
-        &mut self.output_layer
+    pub fn o_gate(&self) -> &QuantizedLayer<OUT, IN> {&self.o_gate}
+
+    // Layer normalizations in order of main layer, forget gate, input gate,
+    // output gate and state.
+    #[inline]
+    fn norms(&self) -> [&Option<LayerNorm<OUT>>; 5] {
+        [
+            &self.main_layer_norm,
+            &self.f_gate_norm,
+            &self.i_gate_norm,
+            &self.o_gate_norm,
+            &self.state_norm
+        ]
     }
 
-    /// Gets immutable state.
-    ///
-    /// This should be initialized before the first [`input_next()`](Self::input_next).
-    ///
-    /// - _Return_ : State.
     #[inline]
-    pub fn state(&self) -> &MathVec<MIDDLE> {&self.state}
+    fn norms_mut(&mut self) -> [&mut Option<LayerNorm<OUT>>; 5] {
+        [
+            &mut self.main_layer_norm,
+            &mut self.f_gate_norm,
+            &mut self.i_gate_norm,
+            &mut self.o_gate_norm,
+            &mut self.state_norm
+        ]
+    }
 
-    /// Gets mutable state.
-    ///
-    /// This should be initialized before the first [`input_next()`](Self::input_next).
+    /// Dequantizes into [`Lstm`].
     ///
-    /// - _Return_ : State.
-    #[inline]
-    pub fn state_mut(&mut self) -> &mut MathVec<MIDDLE> {
-        &mut self.state
+    /// - _Return_ : Lstm.
+    pub fn dequantize(&self) -> Lstm<OUT, IN> {
+        Lstm::<OUT, IN> {
+            main_layer: self.main_layer.dequantize(),
+
+            f_gate: self.f_gate.dequantize(),
+            i_gate: self.i_gate.dequantize(),
+            o_gate: self.o_gate.dequantize(),
+
+            tanh: self.tanh,
+
+            main_layer_norm: self.main_layer_norm.clone(),
+            f_gate_norm: self.f_gate_norm.clone(),
+            i_gate_norm: self.i_gate_norm.clone(),
+            o_gate_norm: self.o_gate_norm.clone(),
+            state_norm: self.state_norm.clone()
+        }
     }
 
-    /// Input next data.
+    /// Calculates only state.
     ///
-    /// - `input` : Next data.
-    #[inline]
-    pub fn input_next(&mut self, input: &MathVec<IN>) {
-        self.prev_state.copy_from(&self.state);
+    /// See [`Lstm::calc_state()`] for details.
+    ///
+    /// - `input` : Input.
+    /// - `prev_state` : Previous state.
+    /// - `next_state` : Buffer for next state.
+    /// - `tmpbuf` : Temporary buffer for this function to work.
+    pub fn calc_state(
+        &self,
+        input: &MathVec<IN>,
+        prev_state: &MathVec<OUT>,
+        next_state: &mut MathVec<OUT>,
+        tmpbuf: &mut MathVec<OUT>
+    ) {
+        let (q_input, q_state) = self.quantize(input, prev_state);
 
-        self.enc_layer.calc(
-            input,
-            &self.prev_state,
-            &mut self.enc_output,
-            &mut self.state,
-            &mut self.tmpbuf
+        self.calc_state_quantized(
+            &q_input,
+            &q_state,
+            prev_state,
+            next_state,
+            tmpbuf
         );
     }
 
-    /// Output next data calculated by current state and input.
+    /// Calculates state and output.
     ///
-    /// - `output` : Buffer for next data.
+    /// See [`Lstm::calc()`] for details.
+    ///
+    /// - `input` : Input.
+    /// - `prev_state` : Previous state.
+    /// - `output` : Buffer for output.
+    /// - `next_state` : Buffer for next state.
+    /// - `tmpbuf` : Temporary buffer for this function to work.
+    pub fn calc(
+        &self,
+        input: &MathVec<IN>,
+        prev_state: &MathVec<OUT>,
+        output: &mut MathVec<OUT>,
+        next_state: &mut MathVec<OUT>,
+        tmpbuf: &mut MathVec<OUT>
+    ) {
+        let (q_input, q_state) = self.quantize(input, prev_state);
+
+        self.calc_state_quantized(
+            &q_input,
+            &q_state,
+            prev_state,
+            next_state,
+            tmpbuf
+        );
+
+        // output = o_gate * tanh(state)
+        calc_quantized_lstm_layer(
+            &self.o_gate,
+            self.o_gate_norm.as_ref(),
+            &q_input,
+            &q_state,
+            output
+        );
+
+        tmpbuf.copy_from(next_state);
+
+        if let Some(norm) = &self.state_norm {
+            norm.calc_in_place(tmpbuf);
+        }
+
+        output.as_mut_array().iter_mut().zip(
+            tmpbuf.as_array().iter()
+        ).for_each(|(output_one, next_s)| {
+            *output_one *= self.tanh.activate(*next_s);
+        });
+    }
+
     #[inline]
-    pub fn output_next(&mut self, output: &mut MathVec<OUT>) {
-        self.prev_state.copy_from(&self.state);
+    fn quantize(
+        &self,
+        input: &MathVec<IN>,
+        prev_state: &MathVec<OUT>
+    ) -> (QuantizedVec<IN>, QuantizedVec<OUT>) {
+        (
+            QuantizedVec::<IN>::new(&self.main_layer.input_params, input),
+            QuantizedVec::<OUT>::new(&self.main_layer.state_params, prev_state)
+        )
+    }
 
-        self.dec_layer.calc(
-            &self.enc_output,
-            &self.prev_state,
-            &mut self.dec_output,
-            &mut self.state,
-            &mut self.tmpbuf
+    fn calc_state_quantized(
+        &self,
+        input: &QuantizedVec<IN>,
+        state: &QuantizedVec<OUT>,
+        prev_state: &MathVec<OUT>,
+        next_state: &mut MathVec<OUT>,
+        tmpbuf: &mut MathVec<OUT>
+    ) {
+        // state = (f_gate * prev_state) + (i_gate * main_layer);
+        calc_quantized_lstm_layer(
+            &self.main_layer,
+            self.main_layer_norm.as_ref(),
+            input,
+            state,
+            next_state
+        );
+        calc_quantized_lstm_layer(
+            &self.i_gate,
+            self.i_gate_norm.as_ref(),
+            input,
+            state,
+            tmpbuf
         );
+        next_state.pointwise_mul_assign(tmpbuf);
 
-        self.output_layer.calc(&self.dec_output, None, output);
+        calc_quantized_lstm_layer(
+            &self.f_gate,
+            self.f_gate_norm.as_ref(),
+            input,
+            state,
+            tmpbuf
+        );
+        tmpbuf.pointwise_mul_assign(prev_state);
+
+        *next_state += tmpbuf;
     }
 
-    /// Accesses each immutable weight with closure.
+    /// Writes this LSTM into buffer as binary data.
     ///
-    /// - `f` : Closure.
-    #[inline]
-    pub fn for_each_weight<F>(&self, mut f: F) where F: FnMut(&f32) {
-        self.enc_layer.for_each_weight(|val| {f(val)});
-        self.dec_layer.for_each_weight(|val| {f(val)});
-        self.output_layer.weights().iter().for_each(|val| {f(val)});
+    /// See [`QuantizedChobitAi::save()`] for details.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save(&self, buffer: &mut Vec<u8>) {
+        write_header(buffer, ModelKind::QuantizedLstm, &[OUT, IN]);
+
+        self.main_layer.write(buffer);
+        self.f_gate.write(buffer);
+        self.i_gate.write(buffer);
+        self.o_gate.write(buffer);
+
+        self.tanh.write(buffer);
+
+        for norm in self.norms() {
+            LayerNorm::write_optional_descriptor(norm.as_ref(), buffer);
+
+            if let Some(norm) = norm {
+                norm.write_body(buffer);
+            }
+        }
     }
 
-    /// Accesses each mutable weight with closure.
+    /// Creates QuantizedLstm from binary data written by [`save()`](Self::save()).
     ///
-    /// - `f` : Closure.
-    #[inline]
-    pub fn for_each_weight_mut<F>(
-        &mut self,
-        mut f: F
-    ) where F: FnMut(&mut f32) {
-        self.enc_layer.for_each_weight_mut(|val| {f(val)});
-        self.dec_layer.for_each_weight_mut(|val| {f(val)});
-        self.output_layer.mut_weights().iter_mut().for_each(|val| {f(val)});
+    /// - `data` : Binary data.
+    /// - _Return_ : QuantizedLstm.
+    pub fn load(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(
+            ModelKind::QuantizedLstm,
+            &[("OUT", OUT), ("IN", IN)]
+        )?;
+
+        let mut ret = Self {
+            main_layer: QuantizedLayer::<OUT, IN>::read(&mut reader)?,
+
+            f_gate: QuantizedLayer::<OUT, IN>::read(&mut reader)?,
+            i_gate: QuantizedLayer::<OUT, IN>::read(&mut reader)?,
+            o_gate: QuantizedLayer::<OUT, IN>::read(&mut reader)?,
+
+            tanh: Activation::read(&mut reader)?,
+
+            main_layer_norm: None,
+            f_gate_norm: None,
+            i_gate_norm: None,
+            o_gate_norm: None,
+            state_norm: None
+        };
+
+        for norm in ret.norms_mut() {
+            *norm = LayerNorm::read_optional_descriptor(&mut reader)?;
+
+            if let Some(norm) = norm {
+                norm.read_body(&mut reader)?;
+            }
+        }
+
+        Ok(ret)
     }
 }
 
-/// Cache for [`ChobitMlSeqAi`].
-///
-/// - `OUT` : `OUT` of [`ChobitMlSeqAi`].
-/// - `MIDDLE` : `MIDDLE` of [`ChobitMlSeqAi`].
-/// - `IN` : `IN` of [`ChobitMlSeqAi`].
+// Matrix in CSR format.
 #[derive(Debug, Clone, PartialEq)]
-pub struct MlSeqAiCache<
-    const OUT: usize,
-    const MIDDLE: usize,
-    const IN: usize
-> {
-    enc_state_caches: Vec<MlLstmStateCache<MIDDLE, IN>>,
-    enc_state_caches_len: usize,
-    enc_output_cache: Option<MlLstmOutputCache<MIDDLE, IN>>,
+struct SparseMatrix<const ROWS: usize, const COLS: usize> {
+    // Position of the first non-zero weight of each row,
+    // and number of all non-zero weights at the end.
+    row_starts: Vec<usize>,
 
-    dec_caches: Vec<(
-        MlLstmStateCache<MIDDLE, MIDDLE>,
-        MlLstmOutputCache<MIDDLE, MIDDLE>,
-        MlCache<OUT, MIDDLE>
-    )>,
-    dec_caches_len: usize
+    cols: Vec<u32>,
+    values: Vec<f32>
 }
 
-impl<
-    const OUT: usize,
-    const MIDDLE: usize,
-    const IN: usize
-> MlSeqAiCache<OUT, MIDDLE, IN> {
-    /// Creates MlSeqAiCache.
-    ///
-    /// - `input_capacity` : Capacity of input caches.
-    /// - `output_capacity` : Capacity of output caches.
-    /// - _Return_ : MlSeqAiCache.
-    #[inline]
-    pub fn new(input_capacity: usize, output_capacity: usize) -> Self {
-        Self {
-            enc_state_caches: vec![
-                MlLstmStateCache::<MIDDLE, IN>::new(); input_capacity
-            ],
-            enc_state_caches_len: 0,
+impl<const ROWS: usize, const COLS: usize> SparseMatrix<ROWS, COLS> {
+    fn new(rows: &[[f32; COLS]; ROWS]) -> Self {
+        let mut row_starts = Vec::<usize>::with_capacity(ROWS + 1);
+        let mut cols = Vec::<u32>::new();
+        let mut values = Vec::<f32>::new();
+
+        row_starts.push(0);
+
+        rows.iter().for_each(|row| {
+            row.iter().enumerate().filter(|(_, val)| **val != 0.0).for_each(
+                |(j, val)| {
+                    cols.push(j as u32);
+                    values.push(*val);
+                }
+            );
 
-            enc_output_cache: Some(MlLstmOutputCache::<MIDDLE, IN>::new()),
+            row_starts.push(values.len());
+        });
 
-            dec_caches: vec![
-                (
-                    MlLstmStateCache::<MIDDLE, MIDDLE>::new(),
-                    MlLstmOutputCache::<MIDDLE, MIDDLE>::new(),
-                    MlCache::<OUT, MIDDLE>::new(),
-                ); output_capacity
-            ],
-            dec_caches_len: 0,
+        Self {
+            row_starts: row_starts,
+
+            cols: cols,
+            values: values
         }
     }
 
-    /// Calculates output error.
-    ///
-    /// | Formula |
-    /// |:-:|
-    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mrow> <mi>e</mi> <mo stretchy="false">=</mo> <mrow> <mi>o</mi> <mo stretchy="false">−</mo> <mi>t</mi> </mrow> </mrow> </semantics> </math> |
-    /// | <math xmlns="http://www.w3.org/1998/Math/MathMl" display="block"> <semantics> <mtable columnalign="left"> <mtr> <mtd> <mrow> <mi>e</mi> <mo stretchy="false">≝</mo> <mtext>Error.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>o</mi> <mo stretchy="false">≝</mo> <mtext>Actual output.</mtext> </mrow> </mtd> </mtr> <mtr> <mtd> <mrow> <mi>t</mi> <mo stretchy="false">≝</mo> <mtext>Correct output.</mtext> </mrow> </mtd> </mtr> </mtable> </semantics> </math> |
-    ///
-    /// - `train_out` : Correct output.
-    /// - `output_error` : Buffer for output error.
-    #[inline]
-    pub fn calc_output_error(
-        &self,
-        train_out: &[MathVec<OUT>],
-        output_error: &mut [MathVec<OUT>]
-    ) {
-        train_out.iter().zip(
-            &self.dec_caches
-        ).zip(
-            output_error
-        ).for_each(
-            |((train_out_one, (_, _, cache)), output_error_one)| {
-                output_error_one.copy_from(&cache.output);
-                *output_error_one -= train_out_one;
+    fn to_dense(&self, rows: &mut [[f32; COLS]; ROWS]) {
+        rows.iter_mut().zip(self.row_starts.windows(2)).for_each(
+            |(row, range)| {
+                row.fill(0.0);
+
+                self.cols[range[0]..range[1]].iter().zip(
+                    self.values[range[0]..range[1]].iter()
+                ).for_each(|(j, val)| {
+                    row[*j as usize] = *val;
+                });
             }
         );
     }
 
-    /// Gets slice of ['MlLstmStateCache'] for encoding layer.
-    ///
-    /// - _Return_ : slice of [`MlLstmStateCache`].
-    #[inline]
-    pub fn enc_state_caches(&self) -> &[MlLstmStateCache<MIDDLE, IN>] {
-        &self.enc_state_caches[..self.enc_state_caches_len]
+    // The same order of summation as Weights::calc() without SIMD.
+    fn add_product(&self, input: &[f32; COLS], output: &mut [f32]) {
+        output.iter_mut().zip(self.row_starts.windows(2)).for_each(
+            |(output_one, range)| {
+                *output_one = self.cols[range[0]..range[1]].iter().zip(
+                    self.values[range[0]..range[1]].iter()
+                ).fold(*output_one, |sum, (j, val)| {
+                    sum + (*val * input[*j as usize])
+                });
+            }
+        );
     }
 
+    fn write(&self, buffer: &mut Vec<u8>) {
+        self.row_starts.windows(2).for_each(|range| {
+            write_u32(buffer, (range[1] - range[0]) as u32);
 
-    /// Gets ['MlLstmOutputCache'] for encoding layer.
-    ///
-    /// - _Return_ : [`MlLstmOutputCache`].
-    #[inline]
-    pub fn enc_output_cache(&self) -> &MlLstmOutputCache<MIDDLE, IN> {
-        self.enc_output_cache.as_ref().unwrap()
+            self.cols[range[0]..range[1]].iter().zip(
+                self.values[range[0]..range[1]].iter()
+            ).for_each(|(j, val)| {
+                write_u32(buffer, *j);
+                write_f32(buffer, *val);
+            });
+        });
     }
 
-    #[inline]
-    pub(self) fn take_enc_output_cache(
-        &mut self
-    ) -> Option<MlLstmOutputCache<MIDDLE, IN>> {
-        self.enc_output_cache.take()
-    }
+    fn read(reader: &mut ByteReader) -> Result<Self, ChobitAiError> {
+        let mut row_starts = Vec::<usize>::with_capacity(ROWS + 1);
+        let mut cols = Vec::<u32>::new();
+        let mut values = Vec::<f32>::new();
 
-    #[inline]
-    pub(self) fn set_enc_output_cache(
-        &mut self,
-        output_cache: MlLstmOutputCache<MIDDLE, IN>
-    ) {
-        self.enc_output_cache = Some(output_cache);
-    }
+        row_starts.push(0);
 
-    /// Gets slice of caches for decoding layer.
-    ///
-    /// - _Return_ : slice of caches.
-    #[inline]
-    pub fn dec_caches(&self) -> &[(
-        MlLstmStateCache<MIDDLE, MIDDLE>,
-        MlLstmOutputCache<MIDDLE, MIDDLE>,
-        MlCache<OUT, MIDDLE>
-    )] {
-        &self.dec_caches[..self.dec_caches_len]
-    }
+        for _ in 0..ROWS {
+            let len = reader.read_u32()? as usize;
 
-    #[inline]
-    pub(self) fn dec_caches_mut(&mut self) -> &mut [(
-        MlLstmStateCache<MIDDLE, MIDDLE>,
-        MlLstmOutputCache<MIDDLE, MIDDLE>,
-        MlCache<OUT, MIDDLE>
-    )] {
-        &mut self.dec_caches[..self.dec_caches_len]
-    }
+            if len > COLS {
+                return Err(ChobitAiError::DimensionMismatch {
+                    name: "sparse_row",
+                    expected: COLS,
+                    found: len
+                });
+            }
 
-    #[inline]
-    pub(self) fn enc_last_state_cache(
-        &self
-    ) -> Option<&MlLstmStateCache<MIDDLE, IN>> {
-        self.enc_state_caches.get(self.enc_state_caches_len.wrapping_sub(1))
-    }
+            for _ in 0..len {
+                let j = reader.read_u32()?;
 
-    /// Accesses each output with closure.
-    ///
-    /// - `f` : Closure.
-    pub fn for_each_output<F>(&self, mut f: F) where F: FnMut(&MathVec<OUT>) {
-        self.dec_caches[..self.dec_caches_len].iter().for_each(
-            |(_, _, cache)| {f(&cache.output);}
-        );
+                if (j as usize) >= COLS {
+                    return Err(ChobitAiError::DimensionMismatch {
+                        name: "sparse_column",
+                        expected: COLS,
+                        found: j as usize
+                    });
+                }
+
+                cols.push(j);
+                values.push(reader.read_f32()?);
+            }
+
+            row_starts.push(values.len());
+        }
+
+        Ok(Self {
+            row_starts: row_starts,
+
+            cols: cols,
+            values: values
+        })
     }
 }
 
-/// Wrapper of [`ChobitSeqAi`] for machine learning.
+/// [`Weights`] in sparse representation that has only non-zero weights.
 ///
-/// See [`ChobitSeqAi`] for details.
+/// Weights for input and weights for state are in [CSR](https://en.wikipedia.org/wiki/Sparse_matrix#Compressed_sparse_row_(CSR,_CRS_or_Yale_format)) format, and each non-zero weight has its column index. So it is smaller than [`Weights`] if more than half of weights are `0.0`, e.g. after [`Weights::prune()`] or [`MlLayer::prune()`]. Bias is dense.
 ///
 /// - `OUT` : Dimension of output.
-/// - `MIDDLE` : Dimension of hidden layer.
 /// - `IN` : Dimension of input.
 #[derive(Debug, Clone, PartialEq)]
-pub struct ChobitMlSeqAi<
-    const OUT: usize,
-    const MIDDLE: usize,
-    const IN: usize
-> {
-    enc_layer: MlLstm<MIDDLE, IN>,
-    dec_layer: MlLstm<MIDDLE, MIDDLE>,
-    output_layer: MlLayer<OUT, MIDDLE>,
-
-    tmp_prev_state: MathVec<MIDDLE>,
-    tmp_state_error: MathVec<MIDDLE>,
-    tmp_enc_output: MathVec<MIDDLE>,
-    tmp_output_error: Vec<MathVec<OUT>>,
-    tmp_dec_output_error: MathVec<MIDDLE>,
-    tmp_enc_output_error: MathVec<MIDDLE>,
-    tmp_enc_output_error_one: MathVec<MIDDLE>,
-
-    original_prev_state: MathVec<MIDDLE>,
-    original_state: MathVec<MIDDLE>,
-    original_enc_output: MathVec<MIDDLE>,
-    original_dec_output: MathVec<MIDDLE>,
+pub struct SparseWeights<const OUT: usize, const IN: usize> {
+    bias: MathVec<OUT>,
 
-    original_tmpbuf: MathVec<MIDDLE>
+    input_weights: SparseMatrix<OUT, IN>,
+    state_weights: Option<SparseMatrix<OUT, OUT>>
 }
 
-impl<
-    const OUT: usize,
-    const MIDDLE: usize,
-    const IN: usize
-> ChobitMlSeqAi<OUT, MIDDLE, IN> {
-    /// Creates ChobitMlSeqAi.
+impl<const OUT: usize, const IN: usize> SparseWeights<OUT, IN> {
+    /// Creates SparseWeights from non-zero weights.
     ///
-    /// - `ai` : Base [`ChobitSeqAi`].
-    /// - _Return_ : ChobitMlSeqAi.
-    #[inline]
-    pub fn new(ai: ChobitSeqAi<OUT, MIDDLE, IN>) -> Self {
-        let ChobitSeqAi::<OUT, MIDDLE, IN> {
-            enc_layer,
-            dec_layer,
-            output_layer,
-            prev_state,
-            state,
-            enc_output,
-            dec_output,
-            tmpbuf
-        } = ai;
+    /// - `weights` : Weights.
+    /// - _Return_ : SparseWeights.
+    pub fn new(weights: &Weights<OUT, IN>) -> Self {
+        let mut bias = MathVec::<OUT>::new();
+        bias.copy_from_slice(weights.bias());
 
         Self {
-            enc_layer: MlLstm::<MIDDLE, IN>::new(enc_layer),
-            dec_layer: MlLstm::<MIDDLE, MIDDLE>::new(dec_layer),
-            output_layer: MlLayer::<OUT, MIDDLE>::new(output_layer),
-
-            tmp_prev_state: MathVec::<MIDDLE>::new(),
-            tmp_state_error: MathVec::<MIDDLE>::new(),
-            tmp_enc_output: MathVec::<MIDDLE>::new(),
-            tmp_output_error: Vec::<MathVec<OUT>>::new(),
-            tmp_dec_output_error: MathVec::<MIDDLE>::new(),
-            tmp_enc_output_error: MathVec::<MIDDLE>::new(),
-            tmp_enc_output_error_one: MathVec::<MIDDLE>::new(),
+            bias: bias,
 
-            original_prev_state: prev_state,
-            original_state: state,
-            original_enc_output: enc_output,
-            original_dec_output: dec_output,
-            original_tmpbuf: tmpbuf
+            input_weights:
+                SparseMatrix::<OUT, IN>::new(weights.input_weights()),
+            state_weights: weights.state_weights().map(
+                SparseMatrix::<OUT, OUT>::new
+            )
         }
     }
 
-    /// Drops base [`ChobitSeqAi`].
+    /// Gets bias.
     ///
-    /// - _Return_ : [`ChobitSeqAi`].
-    #[inline]
-    pub fn drop(self) -> ChobitSeqAi<OUT, MIDDLE, IN> {
-        let ChobitMlSeqAi::<OUT, MIDDLE, IN> {
-            enc_layer,
-            dec_layer,
-            output_layer,
-            original_prev_state,
-            original_state,
-            original_enc_output,
-            original_dec_output,
-            original_tmpbuf,
-            ..
-        } = self;
-
-        ChobitSeqAi::<OUT, MIDDLE, IN> {
-            enc_layer: enc_layer.drop(),
-            dec_layer: dec_layer.drop(),
-            output_layer: output_layer.drop(),
-
-            prev_state: original_prev_state,
-            state: original_state,
-            enc_output: original_enc_output,
-            dec_output: original_dec_output,
-
-            tmpbuf: original_tmpbuf
-        }
-    }
-
-    /// Clears internal data for study.
+    /// - _Return_ : Bias.
     #[inline]
-    pub fn clear_study_data(&mut self) {
-        self.enc_layer.clear_study_data();
-        self.dec_layer.clear_study_data();
-        self.output_layer.clear_study_data();
-    }
+    pub fn bias(&self) -> &[f32; OUT] {self.bias.as_array()}
 
-    /// Writes information on [`MlSeqAiCache`] for [`study()`](Self::study()).
+    /// Gets number of non-zero weights except bias.
     ///
-    /// - `input` : Input.
-    /// - `prev_state` : Previous state.
-    /// - `output_len` : Length of output sequence.
-    /// - `cache` : Cache.
+    /// - _Return_ : Number of non-zero weights.
     #[inline]
-    pub fn ready(
-        &mut self,
-        input: &[MathVec<IN>],
-        prev_state: &MathVec<MIDDLE>,
-        output_len: usize,
-        cache: &mut MlSeqAiCache<OUT, MIDDLE, IN>
-    ) {
-        self.ready_enc_layer(input, prev_state, cache);
-        self.ready_dec_layer(output_len, cache);
+    pub fn non_zero_len(&self) -> usize {
+        self.input_weights.values.len() + self.state_weights.as_ref().map_or(
+            0,
+            |matrix| matrix.values.len()
+        )
     }
 
-    fn ready_enc_layer(
-        &mut self,
-        input: &[MathVec<IN>],
-        prev_state: &MathVec<MIDDLE>,
-        cache: &mut MlSeqAiCache<OUT, MIDDLE, IN>
-    ) {
-        cache.enc_state_caches_len = input.len();
-        if cache.enc_state_caches.len() < input.len() {
-            cache.enc_state_caches.resize(
-                input.len(),
-                MlLstmStateCache::<MIDDLE, IN>::new(),
-            );
-        }
-
-        self.tmp_prev_state.copy_from(prev_state);
-
-        input.iter().zip(
-            cache.enc_state_caches.iter_mut()
-        ).for_each(|(input_one, cache)| {
-            self.enc_layer.ready_state_cache(
-                input_one,
-                &self.tmp_prev_state,
-                cache
-            );
+    /// Converts into dense [`Weights`].
+    ///
+    /// - _Return_ : Weights.
+    pub fn to_weights(&self) -> Weights<OUT, IN> {
+        let mut ret = Weights::<OUT, IN>::new(self.state_weights.is_some());
 
-            self.tmp_prev_state.copy_from(&cache.state);
-        });
+        ret.bias_mut().copy_from_slice(self.bias.as_array());
 
-        if let Some(mut output_cache) = cache.take_enc_output_cache() {
-            if let Some(state_cache) = cache.enc_last_state_cache() {
-                self.enc_layer.ready_output_cache(
-                    state_cache,
-                    &mut output_cache
-                );
-            }
+        self.input_weights.to_dense(ret.input_weights_mut());
 
-            cache.set_enc_output_cache(output_cache);
+        if let (Some(matrix), Some(state_weights)) =
+            (self.state_weights.as_ref(), ret.state_weights_mut())
+        {
+            matrix.to_dense(state_weights);
         }
+
+        ret
     }
 
-    fn ready_dec_layer(
-        &mut self,
-        output_len: usize,
-        cache: &mut MlSeqAiCache<OUT, MIDDLE, IN>
+    /// Calculates weights.
+    ///
+    /// See [`Weights::calc()`] for details. Without `simd` feature, result is the same as [`Weights::calc()`] of [`to_weights()`](Self::to_weights()).
+    ///
+    /// - `input` : Input.
+    /// - `state` : State if it exists.
+    /// - `output` : Buffer for output.
+    pub fn calc(
+        &self,
+        input: &MathVec<IN>,
+        state: Option<&MathVec<OUT>>,
+        output: &mut MathVec<OUT>
     ) {
-        cache.dec_caches_len = output_len;
-        if cache.dec_caches.len() < output_len {
-            cache.dec_caches.resize(
-                output_len,
-                (
-                    MlLstmStateCache::<MIDDLE, MIDDLE>::new(),
-                    MlLstmOutputCache::<MIDDLE, MIDDLE>::new(),
-                    MlCache::<OUT, MIDDLE>::new()
-                )
-            );
+        output.copy_from(&self.bias);
+
+        self.input_weights.add_product(input.as_array(), output);
+
+        if let (Some(matrix), Some(state)) =
+            (self.state_weights.as_ref(), state)
+        {
+            matrix.add_product(state.as_array(), output);
         }
+    }
 
-        match cache.enc_last_state_cache() {
-            Some(state_cache) => {
-                self.tmp_prev_state.copy_from(&state_cache.state);
-            },
+    fn write_body(&self, buffer: &mut Vec<u8>) {
+        self.bias.write_body(buffer);
 
-            None => {self.tmp_prev_state.clear();}
+        self.input_weights.write(buffer);
+
+        if let Some(matrix) = self.state_weights.as_ref() {
+            matrix.write(buffer);
         }
+    }
 
-        if let Some(enc_output_cache) = cache.take_enc_output_cache() {
-            cache.dec_caches_mut().iter_mut().for_each(
-                |(dec_state_cache, dec_output_cache, output_layer_cache)| {
-                    self.dec_layer.ready_state_cache(
-                        &enc_output_cache.output,
-                        &self.tmp_prev_state,
-                        dec_state_cache
-                    );
+    fn read_body(
+        reader: &mut ByteReader,
+        has_state_weights: bool
+    ) -> Result<Self, ChobitAiError> {
+        let mut bias = MathVec::<OUT>::new();
+        bias.read_body(reader)?;
 
-                    self.dec_layer.ready_output_cache(
-                        dec_state_cache,
-                        dec_output_cache
-                    );
+        let input_weights = SparseMatrix::<OUT, IN>::read(reader)?;
 
-                    self.output_layer.ready(
-                        &dec_output_cache.output,
-                        None,
-                        output_layer_cache
-                    );
+        let state_weights = if has_state_weights {
+            Some(SparseMatrix::<OUT, OUT>::read(reader)?)
+        } else {
+            None
+        };
 
-                    self.tmp_prev_state.copy_from(&dec_state_cache.state);
-                }
-            );
+        Ok(Self {
+            bias: bias,
 
-            cache.set_enc_output_cache(enc_output_cache);
+            input_weights: input_weights,
+            state_weights: state_weights
+        })
+    }
+}
+
+/// [`Layer`] for inference with [`SparseWeights`].
+///
+/// - `OUT` : Dimension of output.
+/// - `IN` : Dimension of input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SparseLayer<const OUT: usize, const IN: usize> {
+    weights: SparseWeights<OUT, IN>,
+    activation: Activation
+}
+
+impl<const OUT: usize, const IN: usize> SparseLayer<OUT, IN> {
+    /// Creates SparseLayer from non-zero weights of layer.
+    ///
+    /// - `layer` : Layer.
+    /// - _Return_ : SparseLayer.
+    #[inline]
+    pub fn new(layer: &Layer<OUT, IN>) -> Self {
+        Self {
+            weights: SparseWeights::<OUT, IN>::new(&layer.weights),
+            activation: layer.activation
         }
     }
 
-    /// Accumulates gradient.
+    /// Gets weights.
     ///
-    /// - `output_error` : Backpropagated ouput error.
-    /// - `cache` : Cache.
-    /// - `input_error` : Error to backpropagate for previous output error.
-    /// - `prev_state_error` : Error to backpropagate for previous state error.
-    pub fn study(
-        &mut self,
-        output_error: &[MathVec<OUT>],
-        cache: &MlSeqAiCache<OUT, MIDDLE, IN>,
-        input_error: &mut [MathVec<IN>],
-        prev_state_error: &mut MathVec<MIDDLE>,
-    ) {
-        self.tmp_state_error.clear();
-        self.tmp_enc_output_error.clear();
+    /// - _Return_ : Weights.
+    #[inline]
+    pub fn weights(&self) -> &SparseWeights<OUT, IN> {&self.weights}
 
-        cache.dec_caches().iter().zip(
-            output_error.iter()
-        ).rev().for_each(
-            |(
-                (dec_state_cache, dec_output_cache, output_layer_cache),
-                output_error_one
-            )| {
-                self.output_layer.study(
-                    output_error_one,
-                    None,
-                    output_layer_cache,
-                    &mut self.tmp_dec_output_error,
-                    None
-                );
+    /// Gets activation function.
+    ///
+    /// - _Return_ : Activation function.
+    #[inline]
+    pub fn activation(&self) -> &Activation {&self.activation}
 
-                self.dec_layer.study(
-                    &self.tmp_dec_output_error,
-                    &self.tmp_state_error,
-                    dec_state_cache,
-                    dec_output_cache,
-                    &mut self.tmp_enc_output_error_one,
-                    prev_state_error
-                );
+    /// Converts into [`Layer`].
+    ///
+    /// - _Return_ : Layer.
+    #[inline]
+    pub fn to_layer(&self) -> Layer<OUT, IN> {
+        Layer::<OUT, IN> {
+            weights: self.weights.to_weights(),
+            activation: self.activation
+        }
+    }
 
-                self.tmp_state_error.copy_from(prev_state_error);
-                self.tmp_enc_output_error += &self.tmp_enc_output_error_one;
-            }
+    /// Calculates neural network layer.
+    ///
+    /// See [`Layer::calc()`] and [`SparseWeights::calc()`] for details.
+    ///
+    /// - `input` : Input.
+    /// - `state` : State if it exists.
+    /// - `output` : Buffer for output.
+    #[inline]
+    pub fn calc(
+        &self,
+        input: &MathVec<IN>,
+        state: Option<&MathVec<OUT>>,
+        output: &mut MathVec<OUT>
+    ) {
+        self.weights.calc(input, state, output);
+
+        output.iter_mut().for_each(
+            |val| {*val = self.activation.activate(*val);}
         );
+    }
 
-        if let Some(output_cache) = cache.enc_output_cache.as_ref() {
-            let mut enc_state_caches_iter =
-                cache.enc_state_caches().iter().zip(
-                    input_error.iter_mut()
-                ).rev();
+    fn write(&self, buffer: &mut Vec<u8>) {
+        self.activation.write(buffer);
+        write_u8(buffer, self.weights.state_weights.is_some() as u8);
 
-            if let Some((state_cache, input_error_one)) =
-                enc_state_caches_iter.next()
-            {
-                self.enc_layer.study(
-                    &self.tmp_enc_output_error,
-                    &self.tmp_state_error,
-                    state_cache,
-                    output_cache,
-                    input_error_one,
-                    prev_state_error
-                );
+        self.weights.write_body(buffer);
+    }
 
-                self.tmp_state_error.copy_from(prev_state_error);
-            }
+    fn read(reader: &mut ByteReader) -> Result<Self, ChobitAiError> {
+        let activation = Activation::read(reader)?;
+        let has_state_weights = reader.read_u8()? != 0;
 
-            enc_state_caches_iter.for_each(
-                |(cache, input_error_one)| {
-                    self.enc_layer.study_state(
-                        &self.tmp_state_error,
-                        cache,
-                        input_error_one,
-                        prev_state_error
-                    );
+        Ok(Self {
+            weights: SparseWeights::<OUT, IN>::read_body(
+                reader,
+                has_state_weights
+            )?,
+            activation: activation
+        })
+    }
+}
 
-                    self.tmp_state_error.copy_from(prev_state_error);
-                }
-            );
+/// [`ChobitAi`] for inference with [`SparseWeights`].
+///
+/// ```ignore
+/// use chobitlibs::chobit_ai::{
+///     MathVec,
+///     ChobitMlAi,
+///     SparseChobitAi,
+///     Pruning
+/// };
+///
+/// // Trained AI.
+/// let mut ai: ChobitMlAi<8, 32, 16> = ...;
+///
+/// // Prunes 90% of weights, and fine-tunes.
+/// ai.prune_global(Pruning::Percentage(90.0));
+///
+/// for _ in 0..EPOCH {
+///     // ai.study(...) and ai.update(...).
+/// }
+///
+/// let sparse = SparseChobitAi::<8, 32, 16>::new(&ai.drop());
+///
+/// let mut data = Vec::<u8>::new();
+/// sparse.save(&mut data);
+/// ```
+///
+/// - `OUT` : Dimension of output.
+/// - `MIDDLE` : Dimension of hidden layer.
+/// - `IN` : Dimension of input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SparseChobitAi<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> {
+    middle_layer: SparseLayer<MIDDLE, IN>,
+    output_layer: SparseLayer<OUT, MIDDLE>
+}
+
+impl<
+    const OUT: usize,
+    const MIDDLE: usize,
+    const IN: usize
+> SparseChobitAi<OUT, MIDDLE, IN> {
+    /// Creates SparseChobitAi from non-zero weights of AI.
+    ///
+    /// - `ai` : AI.
+    /// - _Return_ : SparseChobitAi.
+    #[inline]
+    pub fn new(ai: &ChobitAi<OUT, MIDDLE, IN>) -> Self {
+        Self {
+            middle_layer: SparseLayer::<MIDDLE, IN>::new(&ai.middle_layer),
+            output_layer: SparseLayer::<OUT, MIDDLE>::new(&ai.output_layer)
         }
     }
 
-    /// Updates weights.
+    /// Gets middle layer.
     ///
-    /// - `rate` : Learning rate.
+    /// - _Return_ : Middle layer.
     #[inline]
-    pub fn update(&mut self, rate: f32) {
-        self.enc_layer.update(rate);
-        self.dec_layer.update(rate);
-        self.output_layer.update(rate);
+    pub fn middle_layer(&self) -> &SparseLayer<MIDDLE, IN> {
+        &self.middle_layer
     }
 
-    /// Accesses each immutable total gradient with closure.
+    /// Gets output layer.
     ///
-    /// - `f` : Closure.
+    /// - _Return_ : Output layer.
     #[inline]
-    pub fn for_each_total_grad<F>(
-        &self,
-        mut f: F
-    ) where F: FnMut(&f32) {
-        self.enc_layer.for_each_total_grad(|val| {f(val)});
-        self.dec_layer.for_each_total_grad(|val| {f(val)});
-        self.output_layer.total_grad.iter().for_each(|val| {f(val)});
+    pub fn output_layer(&self) -> &SparseLayer<OUT, MIDDLE> {
+        &self.output_layer
     }
 
-    /// Accesses each mutable total gradient with closure.
+    /// Converts into [`ChobitAi`].
     ///
-    /// - `f` : Closure.
+    /// - _Return_ : ChobitAi.
     #[inline]
-    pub fn for_each_total_grad_mut<F>(
-        &mut self,
-        mut f: F
-    ) where F: FnMut(&mut f32) {
-        self.enc_layer.for_each_total_grad_mut(|val| {f(val)});
-        self.dec_layer.for_each_total_grad_mut(|val| {f(val)});
-        self.output_layer.total_grad.iter_mut().for_each(|val| {f(val)});
+    pub fn to_ai(&self) -> ChobitAi<OUT, MIDDLE, IN> {
+        ChobitAi::<OUT, MIDDLE, IN>::with_layers(
+            self.middle_layer.to_layer(),
+            self.output_layer.to_layer()
+        )
+    }
+
+    /// Calculates
+    ///
+    /// - `input` : Input.
+    /// - `output` : Buffer for output.
+    /// - `tmpbuf` : Temporary buffer for this function to work.
+    #[inline]
+    pub fn calc(
+        &self,
+        input: &MathVec<IN>,
+        output: &mut MathVec<OUT>,
+        tmpbuf: &mut MathVec<MIDDLE>
+    ) {
+        self.middle_layer.calc(input, None, tmpbuf);
+        self.output_layer.calc(tmpbuf, None, output);
+    }
+
+    /// Writes this AI into buffer as binary data.
+    ///
+    /// See [`ChobitAi::save()`] for details. Only non-zero weights are written with their column indices.
+    ///
+    /// - `buffer` : Buffer. Data is appended to the end.
+    pub fn save(&self, buffer: &mut Vec<u8>) {
+        write_header(buffer, ModelKind::SparseChobitAi, &[OUT, MIDDLE, IN]);
+
+        self.middle_layer.write(buffer);
+        self.output_layer.write(buffer);
+    }
+
+    /// Creates SparseChobitAi from binary data written by [`save()`](Self::save()).
+    ///
+    /// - `data` : Binary data.
+    /// - _Return_ : SparseChobitAi.
+    pub fn load(data: &[u8]) -> Result<Self, ChobitAiError> {
+        let mut reader = ByteReader::new(data);
+
+        reader.read_header(
+            ModelKind::SparseChobitAi,
+            &[("OUT", OUT), ("MIDDLE", MIDDLE), ("IN", IN)]
+        )?;
+
+        Ok(Self {
+            middle_layer: SparseLayer::<MIDDLE, IN>::read(&mut reader)?,
+            output_layer: SparseLayer::<OUT, MIDDLE>::read(&mut reader)?
+        })
     }
 }
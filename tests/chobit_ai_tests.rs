@@ -1816,6 +1816,389 @@ fn lstm_test_4() {
     assert_eq!(lstm_1, lstm_2);
 }
 
+#[test]
+fn lstm_test_5() {
+    const OUT: usize = 6;
+    const IN: usize = 4;
+    const H: f32 = 1.0e-2;
+
+    let mut rng = ChobitRand::new("lstm_test_5".as_bytes());
+
+    let mut lstm = Lstm::<OUT, IN>::new();
+    rand_weights(&mut rng, lstm.main_layer_mut().mut_weights());
+    rand_weights(&mut rng, lstm.f_gate_mut().mut_weights());
+    rand_weights(&mut rng, lstm.i_gate_mut().mut_weights());
+    rand_weights(&mut rng, lstm.o_gate_mut().mut_weights());
+
+    let mut input = MathVec::<IN>::new();
+    rand_math_vec(&mut rng, &mut input);
+
+    let mut prev_state = MathVec::<OUT>::new();
+    rand_math_vec(&mut rng, &mut prev_state);
+
+    let mut output_coef = MathVec::<OUT>::new();
+    rand_math_vec(&mut rng, &mut output_coef);
+
+    // Not zero, so that the next state also makes loss.
+    let mut state_coef = MathVec::<OUT>::new();
+    rand_math_vec(&mut rng, &mut state_coef);
+
+    // Loss = Σ(c_i * y_i) + Σ(d_i * s_i).
+    let loss = |
+        lstm: &Lstm<OUT, IN>,
+        input: &MathVec<IN>,
+        prev_state: &MathVec<OUT>
+    | -> f32 {
+        let mut output = MathVec::<OUT>::new();
+        let mut state = MathVec::<OUT>::new();
+        let mut tmpbuf = MathVec::<OUT>::new();
+
+        lstm.calc(input, prev_state, &mut output, &mut state, &mut tmpbuf);
+
+        output.iter().zip(output_coef.iter()).map(|(y, c)| y * c)
+            .sum::<f32>()
+            + state.iter().zip(state_coef.iter()).map(|(s, d)| s * d)
+                .sum::<f32>()
+    };
+
+    let mut ml_lstm = MlLstm::<OUT, IN>::new(lstm.clone());
+    let mut state_cache = MlLstmStateCache::<OUT, IN>::new();
+    let mut output_cache = MlLstmOutputCache::<OUT, IN>::new();
+
+    ml_lstm.ready_state_cache(&input, &prev_state, &mut state_cache);
+    ml_lstm.ready_output_cache(&state_cache, &mut output_cache);
+
+    let mut input_error = MathVec::<IN>::new();
+    let mut prev_state_error = MathVec::<OUT>::new();
+
+    ml_lstm.study(
+        &output_coef,
+        &state_coef,
+        &state_cache,
+        &output_cache,
+        &mut input_error,
+        &mut prev_state_error
+    );
+
+    // Gradient of previous state.
+    for i in 0..OUT {
+        let mut plus = prev_state.clone();
+        plus[i] += H;
+        let mut minus = prev_state.clone();
+        minus[i] -= H;
+
+        let numerical =
+            (loss(&lstm, &input, &plus) - loss(&lstm, &input, &minus))
+                / (2.0 * H);
+
+        assert!(
+            (numerical - prev_state_error[i]).abs() < 1.0e-2,
+            "{} : {} != {}",
+            i,
+            numerical,
+            prev_state_error[i]
+        );
+    }
+
+    // Gradient of input.
+    for i in 0..IN {
+        let mut plus = input.clone();
+        plus[i] += H;
+        let mut minus = input.clone();
+        minus[i] -= H;
+
+        let numerical =
+            (loss(&lstm, &plus, &prev_state) - loss(&lstm, &minus, &prev_state))
+                / (2.0 * H);
+
+        assert!(
+            (numerical - input_error[i]).abs() < 1.0e-2,
+            "{} : {} != {}",
+            i,
+            numerical,
+            input_error[i]
+        );
+    }
+
+    // Gradient of weights.
+    let mut grads = Vec::<f32>::new();
+    ml_lstm.for_each_total_grad(|val| {grads.push(*val)});
+
+    let add = |lstm: &Lstm<OUT, IN>, index: usize, delta: f32| {
+        let mut ret = lstm.clone();
+        let mut count: usize = 0;
+
+        ret.for_each_weight_mut(|val| {
+            if count == index {
+                *val += delta;
+            }
+
+            count += 1;
+        });
+
+        ret
+    };
+
+    for (index, grad) in grads.iter().enumerate() {
+        let numerical = (
+            loss(&add(&lstm, index, H), &input, &prev_state)
+                - loss(&add(&lstm, index, -H), &input, &prev_state)
+        ) / (2.0 * H);
+
+        assert!(
+            (numerical - grad).abs() < 1.0e-2,
+            "{} : {} != {}",
+            index,
+            numerical,
+            grad
+        );
+    }
+}
+
+#[test]
+fn lstm_test_6() {
+    const OUT: usize = 6;
+    const IN: usize = 4;
+    const H: f32 = 1.0e-2;
+
+    let mut rng = ChobitRand::new("lstm_test_6".as_bytes());
+
+    // γ and β of layer normalization are random too.
+    let mut lstm = Lstm::<OUT, IN>::with_layer_norm();
+    lstm.for_each_weight_mut(|val| {*val = rand_num(&mut rng)});
+
+    let mut input = MathVec::<IN>::new();
+    rand_math_vec(&mut rng, &mut input);
+
+    let mut prev_state = MathVec::<OUT>::new();
+    rand_math_vec(&mut rng, &mut prev_state);
+
+    let mut output_coef = MathVec::<OUT>::new();
+    rand_math_vec(&mut rng, &mut output_coef);
+
+    let mut state_coef = MathVec::<OUT>::new();
+    rand_math_vec(&mut rng, &mut state_coef);
+
+    // Loss = Σ(c_i * y_i) + Σ(d_i * s_i).
+    let loss = |
+        lstm: &Lstm<OUT, IN>,
+        input: &MathVec<IN>,
+        prev_state: &MathVec<OUT>
+    | -> f32 {
+        let mut output = MathVec::<OUT>::new();
+        let mut state = MathVec::<OUT>::new();
+        let mut tmpbuf = MathVec::<OUT>::new();
+
+        lstm.calc(input, prev_state, &mut output, &mut state, &mut tmpbuf);
+
+        output.iter().zip(output_coef.iter()).map(|(y, c)| y * c)
+            .sum::<f32>()
+            + state.iter().zip(state_coef.iter()).map(|(s, d)| s * d)
+                .sum::<f32>()
+    };
+
+    let mut ml_lstm = MlLstm::<OUT, IN>::new(lstm.clone());
+    let mut state_cache = MlLstmStateCache::<OUT, IN>::new();
+    let mut output_cache = MlLstmOutputCache::<OUT, IN>::new();
+
+    ml_lstm.ready_state_cache(&input, &prev_state, &mut state_cache);
+    ml_lstm.ready_output_cache(&state_cache, &mut output_cache);
+
+    let mut output = MathVec::<OUT>::new();
+    let mut state = MathVec::<OUT>::new();
+    let mut tmpbuf = MathVec::<OUT>::new();
+
+    lstm.calc(&input, &prev_state, &mut output, &mut state, &mut tmpbuf);
+
+    output_cache.output().iter().zip(output.iter()).chain(
+        state_cache.state().iter().zip(state.iter())
+    ).for_each(|(a, b)| {assert_close(*a, *b)});
+
+    let mut input_error = MathVec::<IN>::new();
+    let mut prev_state_error = MathVec::<OUT>::new();
+
+    ml_lstm.study(
+        &output_coef,
+        &state_coef,
+        &state_cache,
+        &output_cache,
+        &mut input_error,
+        &mut prev_state_error
+    );
+
+    // Gradient of previous state.
+    for i in 0..OUT {
+        let mut plus = prev_state.clone();
+        plus[i] += H;
+        let mut minus = prev_state.clone();
+        minus[i] -= H;
+
+        let numerical =
+            (loss(&lstm, &input, &plus) - loss(&lstm, &input, &minus))
+                / (2.0 * H);
+
+        assert!(
+            (numerical - prev_state_error[i]).abs() < 1.0e-2,
+            "{} : {} != {}",
+            i,
+            numerical,
+            prev_state_error[i]
+        );
+    }
+
+    // Gradient of input.
+    for i in 0..IN {
+        let mut plus = input.clone();
+        plus[i] += H;
+        let mut minus = input.clone();
+        minus[i] -= H;
+
+        let numerical =
+            (loss(&lstm, &plus, &prev_state) - loss(&lstm, &minus, &prev_state))
+                / (2.0 * H);
+
+        assert!(
+            (numerical - input_error[i]).abs() < 1.0e-2,
+            "{} : {} != {}",
+            i,
+            numerical,
+            input_error[i]
+        );
+    }
+
+    // Gradient of weights, γ and β.
+    let mut grads = Vec::<f32>::new();
+    ml_lstm.for_each_total_grad(|val| {grads.push(*val)});
+
+    let mut weights = Vec::<f32>::new();
+    lstm.for_each_weight(|val| {weights.push(*val)});
+
+    check_numerical_grad(&grads, &weights, |weights| {
+        let mut lstm = lstm.clone();
+        let mut iter = weights.iter();
+        lstm.for_each_weight_mut(|val| {*val = *iter.next().unwrap()});
+
+        loss(&lstm, &input, &prev_state)
+    });
+}
+
+#[test]
+fn lstm_test_7() {
+    const OUT: usize = 6;
+    const IN: usize = 4;
+    const LAYER_LEN: usize = OUT * (IN + OUT + 1);
+    const NORM_LEN: usize = OUT * 2;
+
+    let mut rng = ChobitRand::new("lstm_test_7".as_bytes());
+
+    let mut lstm = Lstm::<OUT, IN>::with_layer_norm();
+
+    lstm.init_weights(
+        Initializer::XavierUniform,
+        Initializer::Orthogonal,
+        || rng.next_f64() as f32
+    );
+
+    let f_gate_norm = lstm.f_gate_norm().unwrap();
+    assert!(f_gate_norm.weights().bias().iter().all(|val| *val == 1.0));
+    assert!(
+        f_gate_norm.weights().input_weights().iter().all(|val| val[0] == 1.0)
+    );
+
+    let i_gate_norm = lstm.i_gate_norm().unwrap();
+    assert!(i_gate_norm.weights().bias().iter().all(|val| *val == 0.0));
+
+    lstm.for_each_weight_mut(|val| {*val = rand_num(&mut rng)});
+
+    let mut count: usize = 0;
+    lstm.for_each_weight(|_| {count += 1});
+    assert_eq!(count, (LAYER_LEN * 4) + (NORM_LEN * 5));
+
+    // Save and load.
+    let mut data = Vec::<u8>::new();
+    lstm.save(&mut data);
+
+    assert_eq!(Lstm::<OUT, IN>::load(&data), Ok(lstm.clone()));
+
+    // One step of machine learning, and checkpoint.
+    let mut ml_lstm = MlLstm::<OUT, IN>::new(lstm.clone());
+
+    let mut count: usize = 0;
+    ml_lstm.for_each_total_grad(|_| {count += 1});
+    assert_eq!(count, (LAYER_LEN * 4) + (NORM_LEN * 5));
+
+    let mut state_cache = MlLstmStateCache::<OUT, IN>::new();
+    let mut output_cache = MlLstmOutputCache::<OUT, IN>::new();
+
+    let mut input = MathVec::<IN>::new();
+    rand_math_vec(&mut rng, &mut input);
+
+    let mut prev_state = MathVec::<OUT>::new();
+    rand_math_vec(&mut rng, &mut prev_state);
+
+    let mut output_error = MathVec::<OUT>::new();
+    rand_math_vec(&mut rng, &mut output_error);
+
+    ml_lstm.ready_state_cache(&input, &prev_state, &mut state_cache);
+    ml_lstm.ready_output_cache(&state_cache, &mut output_cache);
+
+    let mut input_error = MathVec::<IN>::new();
+    let mut prev_state_error = MathVec::<OUT>::new();
+
+    ml_lstm.study(
+        &output_error,
+        &MathVec::<OUT>::new(),
+        &state_cache,
+        &output_cache,
+        &mut input_error,
+        &mut prev_state_error
+    );
+    ml_lstm.update(0.01);
+
+    assert_ne!(ml_lstm.clone().drop(), lstm);
+    assert_eq!(ml_lstm.check_finite(), Ok(()));
+
+    data.clear();
+    ml_lstm.save_checkpoint(&mut data);
+
+    let loaded = MlLstm::<OUT, IN>::load_checkpoint(&data).unwrap();
+
+    let mut data_2 = Vec::<u8>::new();
+    loaded.save_checkpoint(&mut data_2);
+    assert_eq!(data, data_2);
+
+    assert_eq!(loaded.drop(), ml_lstm.clone().drop());
+
+    // The first total gradient of state_norm.
+    let mut i: usize = 0;
+    ml_lstm.for_each_total_grad_mut(|val| {
+        if i == (LAYER_LEN * 4) + (NORM_LEN * 4) {
+            *val = f32::NAN;
+        }
+
+        i += 1;
+    });
+
+    assert_eq!(
+        ml_lstm.check_finite(),
+        Err(ChobitAiError::NonFinite {
+            layer: "state_norm",
+            part: "total_grad"
+        })
+    );
+
+    // Layer normalization is kept in quantized LSTM.
+    let sequence = [input.clone(), input];
+    let quantized = QuantizedLstm::<OUT, IN>::calibrate(&lstm, &[&sequence]);
+
+    assert_eq!(quantized.dequantize().state_norm(), lstm.state_norm());
+
+    data.clear();
+    quantized.save(&mut data);
+
+    assert_eq!(QuantizedLstm::<OUT, IN>::load(&data).unwrap(), quantized);
+}
+
 fn gen_encoder<
     const OUT: usize,
     const MIDDLE: usize,
@@ -2781,3 +3164,5433 @@ fn chobit_seq_ai_test_4() {
 
     assert_eq!(ai_1, ai_2);
 }
+
+#[test]
+fn save_load_test_1() {
+    const OUT: usize = 11;
+    const MIDDLE: usize = 7;
+    const IN: usize = 5;
+
+    let mut rng = ChobitRand::new("save_load_test_1".as_bytes());
+
+    let mut ai = ChobitAi::<OUT, MIDDLE, IN>::new(Activation::SoftSign);
+    ai.for_each_weight_mut(|val| {*val = rand_num(&mut rng)});
+
+    let mut data = Vec::<u8>::new();
+    ai.save(&mut data);
+
+    assert_eq!(&data[..4], &FORMAT_MAGIC);
+
+    let ai_2 = ChobitAi::<OUT, MIDDLE, IN>::load(&data).unwrap();
+    assert_eq!(ai, ai_2);
+
+    let mut lstm = gen_lstm::<MIDDLE, IN>(&mut rng);
+    *lstm.main_layer_mut().mut_activation() = Activation::ReLU;
+
+    data.clear();
+    lstm.save(&mut data);
+
+    let lstm_2 = Lstm::<MIDDLE, IN>::load(&data).unwrap();
+    assert_eq!(lstm, lstm_2);
+
+    let mut encoder =
+        ChobitEncoder::<OUT, MIDDLE, IN>::new(Activation::Sigmoid);
+    encoder.for_each_weight_mut(|val| {*val = rand_num(&mut rng)});
+
+    data.clear();
+    encoder.save(&mut data);
+
+    let encoder_2 = ChobitEncoder::<OUT, MIDDLE, IN>::load(&data).unwrap();
+    assert_eq!(encoder, encoder_2);
+
+    let mut decoder =
+        ChobitDecoder::<OUT, MIDDLE, IN>::new(Activation::Linear);
+    decoder.for_each_weight_mut(|val| {*val = rand_num(&mut rng)});
+
+    data.clear();
+    decoder.save(&mut data);
+
+    let decoder_2 = ChobitDecoder::<OUT, MIDDLE, IN>::load(&data).unwrap();
+    assert_eq!(decoder, decoder_2);
+
+    let mut seq_ai = ChobitSeqAi::<OUT, MIDDLE, IN>::new(Activation::ReLU);
+    seq_ai.for_each_weight_mut(|val| {*val = rand_num(&mut rng)});
+
+    data.clear();
+    seq_ai.save(&mut data);
+
+    let seq_ai_2 = ChobitSeqAi::<OUT, MIDDLE, IN>::load(&data).unwrap();
+    assert_eq!(seq_ai, seq_ai_2);
+}
+
+#[test]
+fn save_load_test_2() {
+    const OUT: usize = 11;
+    const MIDDLE: usize = 7;
+    const IN: usize = 5;
+
+    let mut rng = ChobitRand::new("save_load_test_2".as_bytes());
+
+    let mut ai = ChobitAi::<OUT, MIDDLE, IN>::new(Activation::SoftSign);
+    ai.for_each_weight_mut(|val| {*val = rand_num(&mut rng)});
+
+    let mut data = Vec::<u8>::new();
+    ai.save(&mut data);
+
+    assert_eq!(
+        ChobitAi::<OUT, 8, IN>::load(&data),
+        Err(ChobitAiError::DimensionMismatch {
+            name: "MIDDLE",
+            expected: 8,
+            found: MIDDLE
+        })
+    );
+
+    assert_eq!(
+        ChobitEncoder::<OUT, MIDDLE, IN>::load(&data),
+        Err(ChobitAiError::WrongModelKind {
+            expected: ModelKind::ChobitEncoder,
+            found: ModelKind::ChobitAi as u8
+        })
+    );
+
+    assert_eq!(
+        ChobitAi::<OUT, MIDDLE, IN>::load(&data[..(data.len() - 1)]),
+        Err(ChobitAiError::UnexpectedEnd)
+    );
+
+    let mut broken = data.clone();
+    broken[0] = b'X';
+
+    assert_eq!(
+        ChobitAi::<OUT, MIDDLE, IN>::load(&broken),
+        Err(ChobitAiError::InvalidMagic)
+    );
+
+    let mut broken = data.clone();
+    broken[4] = 0xff;
+
+    assert!(matches!(
+        ChobitAi::<OUT, MIDDLE, IN>::load(&broken),
+        Err(ChobitAiError::UnsupportedVersion {..})
+    ));
+}
+
+#[test]
+fn checkpoint_test_1() {
+    const OUT: usize = 8;
+    const MIDDLE: usize = 16;
+    const IN: usize = 8;
+
+    const EPOCH: usize = 4;
+    const BATCH_SIZE: usize = 3;
+    const RATE: f32 = 0.01;
+
+    let mut rng = ChobitRand::new("checkpoint_test_1".as_bytes());
+
+    let mut ai = ChobitAi::<OUT, MIDDLE, IN>::new(Activation::SoftSign);
+    rand_weights(&mut rng, ai.middle_layer_mut().mut_weights());
+    rand_weights(&mut rng, ai.output_layer_mut().mut_weights());
+
+    let mut ai_1 = ChobitMlAi::<OUT, MIDDLE, IN>::new(ai.clone());
+    let mut cache = MlAiCache::<OUT, MIDDLE, IN>::new();
+    let mut input = MathVec::<IN>::new();
+    let mut output = MathVec::<OUT>::new();
+    let mut output_error = MathVec::<OUT>::new();
+    let mut input_error = MathVec::<IN>::new();
+
+    let mut labels = Vec::<u32>::new();
+    for _ in 0..(EPOCH * 2 * BATCH_SIZE) {
+        labels.push(rng.next_u64() as u32);
+    }
+
+    let mut train = |ai: &mut ChobitMlAi<OUT, MIDDLE, IN>, labels: &[u32]| {
+        for batch in labels.chunks(BATCH_SIZE) {
+            for label in batch {
+                input.load_u8_label(*label as u8);
+                output.load_u8_label((*label >> 8) as u8);
+
+                ai.ready(&input, &mut cache);
+                cache.calc_output_error(&output, &mut output_error);
+                ai.study(&output_error, &cache, &mut input_error);
+            }
+
+            ai.update(RATE);
+        }
+    };
+
+    let (first, second) = labels.split_at(EPOCH * BATCH_SIZE);
+
+    // Interrupted in the middle of a batch so that total_grad is not empty.
+    train(&mut ai_1, first);
+    train(&mut ai_1, &second[..1]);
+
+    let mut data = Vec::<u8>::new();
+    ai_1.save_checkpoint(&mut data);
+
+    let mut ai_2 = ChobitMlAi::<OUT, MIDDLE, IN>::load_checkpoint(&data)
+        .unwrap();
+
+    train(&mut ai_1, &second[1..]);
+    train(&mut ai_2, &second[1..]);
+
+    assert_eq!(ai_1.drop(), ai_2.drop());
+
+    assert_eq!(
+        ChobitMlAi::<OUT, MIDDLE, IN>::load_checkpoint(&data[..data.len() - 1]),
+        Err(ChobitAiError::UnexpectedEnd)
+    );
+
+    let mut data = Vec::<u8>::new();
+    ai.save(&mut data);
+
+    assert_eq!(
+        ChobitMlAi::<OUT, MIDDLE, IN>::load_checkpoint(&data),
+        Err(ChobitAiError::WrongModelKind {
+            expected: ModelKind::ChobitMlAi,
+            found: ModelKind::ChobitAi as u8
+        })
+    );
+}
+
+#[test]
+fn checkpoint_test_2() {
+    const OUT: usize = 8;
+    const MIDDLE: usize = 16;
+    const IN: usize = 8;
+
+    const BATCH_SIZE: usize = 2;
+    const RATE: f32 = 0.01;
+
+    let mut rng = ChobitRand::new("checkpoint_test_2".as_bytes());
+
+    let ai = gen_seq_ai::<OUT, MIDDLE, IN>(&mut rng);
+
+    let mut ai_1 = ChobitMlSeqAi::<OUT, MIDDLE, IN>::new(ai);
+    let mut cache = MlSeqAiCache::<OUT, MIDDLE, IN>::new(0, 0);
+    let mut input_error = vec![MathVec::<IN>::new(); 4];
+    let mut output_error = vec![MathVec::<OUT>::new(); 3];
+    let mut prev_state_error = MathVec::<MIDDLE>::new();
+    let prev_state = MathVec::<MIDDLE>::new();
+
+    let mut samples = Vec::<(Vec<MathVec<IN>>, Vec<MathVec<OUT>>)>::new();
+    for _ in 0..(BATCH_SIZE * 4) {
+        let mut input = vec![MathVec::<IN>::new(); 4];
+        input.iter_mut().for_each(|vec| {rand_math_vec(&mut rng, vec)});
+
+        let mut output = vec![MathVec::<OUT>::new(); 3];
+        output.iter_mut().for_each(|vec| {rand_math_vec(&mut rng, vec)});
+
+        samples.push((input, output));
+    }
+
+    let mut train = |
+        ai: &mut ChobitMlSeqAi<OUT, MIDDLE, IN>,
+        samples: &[(Vec<MathVec<IN>>, Vec<MathVec<OUT>>)]
+    | {
+        for batch in samples.chunks(BATCH_SIZE) {
+            for (input, output) in batch {
+                ai.ready(input, &prev_state, output.len(), &mut cache);
+                cache.calc_output_error(output, &mut output_error);
+                ai.study(
+                    &output_error,
+                    &cache,
+                    &mut input_error,
+                    &mut prev_state_error
+                );
+            }
+
+            ai.update(RATE);
+        }
+    };
+
+    let (first, second) = samples.split_at(BATCH_SIZE * 2);
+
+    train(&mut ai_1, first);
+
+    let mut data = Vec::<u8>::new();
+    ai_1.save_checkpoint(&mut data);
+
+    let mut ai_2 = ChobitMlSeqAi::<OUT, MIDDLE, IN>::load_checkpoint(&data)
+        .unwrap();
+
+    train(&mut ai_1, second);
+    train(&mut ai_2, second);
+
+    assert_eq!(ai_1.drop(), ai_2.drop());
+}
+
+fn reference_update(
+    optimizer: &Optimizer,
+    step: u64,
+    rate: f32,
+    weights: &mut [f32],
+    grads: &[f32],
+    momentum: &mut [f32],
+    square_average: &mut [f32]
+) {
+    for i in 0..weights.len() {
+        let (w, g) = (&mut weights[i], grads[i]);
+        let (v, s) = (&mut momentum[i], &mut square_average[i]);
+
+        match *optimizer {
+            Optimizer::Sgd => {
+                *w -= rate * g;
+            },
+
+            Optimizer::Momentum {momentum, nesterov} => {
+                *v = momentum * *v + g;
+                *w -= if nesterov {rate * (g + momentum * *v)} else {rate * *v};
+            },
+
+            Optimizer::RmsProp {decay, epsilon} => {
+                *s = decay * *s + (1.0 - decay) * g * g;
+                *w -= rate * g / (s.sqrt() + epsilon);
+            },
+
+            Optimizer::BiasCorrectedAdam {beta_1, beta_2, epsilon}
+                | Optimizer::AdamW {beta_1, beta_2, epsilon, ..} =>
+            {
+                if let Optimizer::AdamW {weight_decay, ..} = *optimizer {
+                    *w -= rate * weight_decay * *w;
+                }
+
+                let t = (step + 1) as i32;
+
+                *v = beta_1 * *v + (1.0 - beta_1) * g;
+                *s = beta_2 * *s + (1.0 - beta_2) * g * g;
+
+                let v_hat = *v / (1.0 - beta_1.powi(t));
+                let s_hat = *s / (1.0 - beta_2.powi(t));
+
+                *w -= rate * v_hat / (s_hat.sqrt() + epsilon);
+            },
+
+            Optimizer::Adam => unreachable!()
+        }
+    }
+}
+
+#[test]
+fn optimizer_test_1() {
+    const OUT: usize = 3;
+    const IN: usize = 4;
+
+    const STEP: u64 = 10;
+    const RATE: f32 = 0.01;
+
+    let mut rng = ChobitRand::new("optimizer_test_1".as_bytes());
+
+    let optimizers = [
+        Optimizer::Sgd,
+        Optimizer::momentum(),
+        Optimizer::Momentum {momentum: 0.8, nesterov: true},
+        Optimizer::rms_prop(),
+        Optimizer::bias_corrected_adam(),
+        Optimizer::adam_w()
+    ];
+
+    for optimizer in optimizers.iter() {
+        let mut layer = Layer::<OUT, IN>::new(Activation::SoftSign, true);
+        rand_weights(&mut rng, layer.mut_weights());
+
+        let mut weights = layer.weights().as_slice().to_vec();
+        let mut momentum = vec![0.0f32; weights.len()];
+        let mut square_average = vec![0.0f32; weights.len()];
+
+        let mut ml_layer = MlLayer::<OUT, IN>::new(layer);
+        ml_layer.set_optimizer(*optimizer);
+        assert_eq!(ml_layer.optimizer(), optimizer);
+
+        for step in 0..STEP {
+            rand_weights(&mut rng, ml_layer.total_grad_mut());
+            let grads = ml_layer.total_grad().as_slice().to_vec();
+
+            ml_layer.update(RATE);
+
+            reference_update(
+                optimizer,
+                step,
+                RATE,
+                &mut weights,
+                &grads,
+                &mut momentum,
+                &mut square_average
+            );
+        }
+
+        assert_eq!(ml_layer.step(), STEP);
+        assert!(ml_layer.total_grad().iter().all(|val| *val == 0.0));
+
+        let layer = ml_layer.drop();
+
+        // MlLayer uses approximate square root.
+        layer.weights().iter().zip(weights.iter()).for_each(|(a, b)| {
+            assert!((a - b).abs() < 1.0e-3, "{:?} : {} != {}", optimizer, a, b);
+        });
+    }
+}
+
+#[test]
+fn optimizer_test_2() {
+    const OUT: usize = 8;
+    const MIDDLE: usize = 16;
+    const IN: usize = 8;
+
+    const RATE: f32 = 0.01;
+
+    let mut rng = ChobitRand::new("optimizer_test_2".as_bytes());
+
+    let mut ai = ChobitAi::<OUT, MIDDLE, IN>::new(Activation::SoftSign);
+    rand_weights(&mut rng, ai.middle_layer_mut().mut_weights());
+    rand_weights(&mut rng, ai.output_layer_mut().mut_weights());
+
+    let mut ai = ChobitMlAi::<OUT, MIDDLE, IN>::new(ai);
+    ai.set_optimizer(Optimizer::adam_w());
+
+    let mut cache = MlAiCache::<OUT, MIDDLE, IN>::new();
+    let mut input = MathVec::<IN>::new();
+    let mut output = MathVec::<OUT>::new();
+    let mut output_error = MathVec::<OUT>::new();
+    let mut input_error = MathVec::<IN>::new();
+
+    let mut train = |ai: &mut ChobitMlAi<OUT, MIDDLE, IN>, seed: u32| {
+        for label in seed..(seed + 3) {
+            input.load_u8_label(label as u8);
+            output.load_u8_label((label * 7) as u8);
+
+            ai.ready(&input, &mut cache);
+            cache.calc_output_error(&output, &mut output_error);
+            ai.study(&output_error, &cache, &mut input_error);
+        }
+
+        ai.update(RATE);
+    };
+
+    train(&mut ai, 0);
+    train(&mut ai, 3);
+
+    let mut data = Vec::<u8>::new();
+    ai.save_checkpoint(&mut data);
+
+    let mut ai_2 = ChobitMlAi::<OUT, MIDDLE, IN>::load_checkpoint(&data)
+        .unwrap();
+
+    train(&mut ai, 6);
+    train(&mut ai_2, 6);
+
+    assert_eq!(ai.drop(), ai_2.drop());
+
+    // Unknown optimizer tag is the 18th byte from the end of the data,
+    // followed by 16 bytes of parameters and 1 byte of mask flag.
+    let pos = data.len() - 18;
+    data[pos] = 0xff;
+
+    assert_eq!(
+        ChobitMlAi::<OUT, MIDDLE, IN>::load_checkpoint(&data),
+        Err(ChobitAiError::UnknownOptimizer {tag: 0xff})
+    );
+}
+
+fn assert_rate(schedule: &LrSchedule, step: u64, expected: f32) {
+    let rate = schedule.rate(step);
+
+    assert!(
+        (rate - expected).abs() <= expected.abs() * 1.0e-4 + 1.0e-9,
+        "{:?} step {} : {} != {}", schedule, step, rate, expected
+    );
+}
+
+#[test]
+fn lr_schedule_test_1() {
+    let schedule = LrSchedule::Constant {rate: 0.1};
+    assert_rate(&schedule, 0, 0.1);
+    assert_rate(&schedule, 1000, 0.1);
+
+    let schedule = LrSchedule::StepDecay {rate: 0.1, gamma: 0.5, step_size: 10};
+    assert_rate(&schedule, 0, 0.1);
+    assert_rate(&schedule, 9, 0.1);
+    assert_rate(&schedule, 10, 0.05);
+    assert_rate(&schedule, 35, 0.0125);
+
+    let schedule = LrSchedule::Exponential {rate: 0.1, gamma: 0.9};
+    assert_rate(&schedule, 0, 0.1);
+    assert_rate(&schedule, 7, 0.1 * 0.9f32.powi(7));
+
+    let schedule = LrSchedule::CosineAnnealing {
+        max_rate: 0.1,
+        min_rate: 0.0,
+        period: 10,
+        period_mult: 2
+    };
+    assert_rate(&schedule, 0, 0.1);
+    assert_rate(&schedule, 5, 0.05);
+    assert_rate(&schedule, 3, 0.05 * (1.0 + (core::f32::consts::PI * 0.3).cos()));
+    // restart with period 20.
+    assert_rate(&schedule, 10, 0.1);
+    assert_rate(&schedule, 20, 0.05);
+    // restart with period 40.
+    assert_rate(&schedule, 30, 0.1);
+    assert_rate(&schedule, 50, 0.05);
+    // restart with period 80.
+    assert_rate(&schedule, 70, 0.1);
+
+    // Period 10 * 2^59 starts at 10 + 20 + ... + 10 * 2^58.
+    assert_rate(&schedule, (10 * ((1 << 59) - 1)) + (5 << 59), 0.05);
+    assert_rate(&schedule, 10 * ((1 << 60) - 1), 0.1);
+    assert!(schedule.rate(u64::MAX).is_finite());
+
+    let schedule = LrSchedule::CosineAnnealing {
+        max_rate: 0.1,
+        min_rate: 0.0,
+        period: 4,
+        period_mult: 1
+    };
+    assert_rate(&schedule, 2, 0.05);
+    assert_rate(&schedule, 4, 0.1);
+    assert_rate(&schedule, 4_000_000_000_000_002, 0.05);
+
+    // Period 0 is treated as 1.
+    let schedule = LrSchedule::CosineAnnealing {
+        max_rate: 0.1,
+        min_rate: 0.0,
+        period: 0,
+        period_mult: 3
+    };
+    assert_rate(&schedule, 0, 0.1);
+    assert_rate(&schedule, 1, 0.1);
+    assert_rate(
+        &schedule,
+        2,
+        0.05 * (1.0 + (core::f32::consts::PI / 3.0).cos())
+    );
+    assert_rate(&schedule, 4, 0.1);
+
+    let schedule = LrSchedule::warmup(
+        4,
+        LrSchedule::Exponential {rate: 0.1, gamma: 0.5}
+    );
+    assert_rate(&schedule, 0, 0.025);
+    assert_rate(&schedule, 3, 0.1);
+    assert_rate(&schedule, 4, 0.1);
+    assert_rate(&schedule, 5, 0.05);
+
+    let schedule = LrSchedule::one_cycle(1.0, 100);
+    assert_rate(&schedule, 0, 1.0 / 25.0);
+    assert_rate(&schedule, 15, (1.0 + 1.0 / 25.0) / 2.0);
+    assert_rate(&schedule, 30, 1.0);
+    assert_rate(&schedule, 100, 1.0 / 25.0 / 1.0e4);
+    assert_rate(&schedule, 1000, 1.0 / 25.0 / 1.0e4);
+
+    for step in 0..100 {
+        let rate = schedule.rate(step);
+        assert!(rate > 0.0 && rate <= 1.0);
+    }
+}
+
+#[test]
+fn lr_schedule_test_2() {
+    const OUT: usize = 8;
+    const MIDDLE: usize = 16;
+    const IN: usize = 8;
+
+    let mut rng = ChobitRand::new("lr_schedule_test_2".as_bytes());
+
+    let mut ai = ChobitAi::<OUT, MIDDLE, IN>::new(Activation::SoftSign);
+    rand_weights(&mut rng, ai.middle_layer_mut().mut_weights());
+    rand_weights(&mut rng, ai.output_layer_mut().mut_weights());
+
+    let mut ai_1 = ChobitMlAi::<OUT, MIDDLE, IN>::new(ai);
+    let mut ai_2 = ai_1.clone();
+
+    let schedule = LrSchedule::warmup(
+        3,
+        LrSchedule::StepDecay {rate: 0.01, gamma: 0.5, step_size: 2}
+    );
+    let mut scheduler = LrScheduler::new(schedule.clone());
+
+    let mut cache = MlAiCache::<OUT, MIDDLE, IN>::new();
+    let mut input = MathVec::<IN>::new();
+    let mut output = MathVec::<OUT>::new();
+    let mut output_error = MathVec::<OUT>::new();
+    let mut input_error = MathVec::<IN>::new();
+
+    for step in 0..10u64 {
+        input.load_u8_label(step as u8);
+        output.load_u8_label((step * 3) as u8);
+
+        for ai in [&mut ai_1, &mut ai_2] {
+            ai.ready(&input, &mut cache);
+            cache.calc_output_error(&output, &mut output_error);
+            ai.study(&output_error, &cache, &mut input_error);
+        }
+
+        assert_eq!(scheduler.step(), step);
+        assert_eq!(scheduler.rate(), schedule.rate(step));
+
+        ai_1.update_with_scheduler(&mut scheduler);
+        ai_2.update(schedule.rate(step));
+    }
+
+    assert_eq!(scheduler.step(), 10);
+    assert_eq!(ai_1.drop(), ai_2.drop());
+}
+
+#[test]
+fn clip_grad_test_1() {
+    const OUT: usize = 8;
+    const MIDDLE: usize = 16;
+    const IN: usize = 8;
+
+    let mut rng = ChobitRand::new("clip_grad_test_1".as_bytes());
+
+    let mut ai = ChobitMlSeqAi::<OUT, MIDDLE, IN>::new(
+        gen_seq_ai::<OUT, MIDDLE, IN>(&mut rng)
+    );
+
+    ai.for_each_total_grad_mut(|val| {*val = rand_num(&mut rng) * 10.0});
+
+    let mut ai_2 = ai.clone();
+
+    ai.clip_grad_by_value(0.5);
+    ai.for_each_total_grad(|val| {assert!(val.abs() <= 0.5)});
+
+    let mut sum: f32 = 0.0;
+    ai_2.for_each_total_grad(|val| {sum += *val * *val});
+    let expected_norm = sum.sqrt();
+
+    let norm = ai_2.clip_grad_by_global_norm(1.0);
+    assert!((norm - expected_norm).abs() < expected_norm * 1.0e-2);
+
+    let mut sum: f32 = 0.0;
+    ai_2.for_each_total_grad(|val| {sum += *val * *val});
+    assert!((sum.sqrt() - 1.0).abs() < 1.0e-2);
+
+    // small gradient is not changed.
+    let mut ai_3 = ai_2.clone();
+    ai_3.clip_grad_by_global_norm(100.0);
+    assert_eq!(ai_2, ai_3);
+}
+
+#[test]
+fn clip_grad_test_2() {
+    const OUT: usize = 8;
+    const IN: usize = 8;
+
+    let mut rng = ChobitRand::new("clip_grad_test_2".as_bytes());
+
+    let mut layer = MlLayer::<OUT, IN>::new(
+        Layer::<OUT, IN>::new(Activation::SoftSign, false)
+    );
+
+    layer.total_grad_mut().iter_mut().for_each(|val| {
+        *val = rand_num(&mut rng) * 10.0;
+    });
+
+    // Negative limit is treated as its absolute value.
+    let mut layer_1 = layer.clone();
+    let mut layer_2 = layer.clone();
+    layer_1.clip_grad_by_value(-0.5);
+    layer_2.clip_grad_by_value(0.5);
+    assert_eq!(layer_1, layer_2);
+    assert!(layer_1.total_grad().iter().all(|val| val.abs() <= 0.5));
+
+    // NaN limit does nothing.
+    let mut layer_1 = layer.clone();
+    layer_1.clip_grad_by_value(f32::NAN);
+    assert_eq!(layer_1, layer);
+
+    let mut layer_1 = layer.clone();
+    let mut layer_2 = layer.clone();
+    layer_1.clip_grad_by_global_norm(-1.0);
+    layer_2.clip_grad_by_global_norm(1.0);
+    assert_eq!(layer_1, layer_2);
+
+    // Sum of squares of large gradients overflows f32.
+    let mut layer_1 = layer.clone();
+    layer_1.total_grad_mut().iter_mut().for_each(|val| {*val *= 1.0e30;});
+
+    let mut sum: f64 = 0.0;
+    layer_1.total_grad().iter().for_each(|val| {
+        sum += (*val as f64) * (*val as f64);
+    });
+    let expected_norm = sum.sqrt() as f32;
+
+    let mut expected = layer_1.clone();
+    expected.total_grad_mut().iter_mut().for_each(|val| {
+        *val = ((*val as f64) * 2.0 / sum.sqrt()) as f32;
+    });
+
+    let norm = layer_1.clip_grad_by_global_norm(2.0);
+    assert!(norm.is_finite());
+    assert!((norm - expected_norm).abs() < expected_norm * 1.0e-2);
+
+    layer_1.total_grad().iter().zip(expected.total_grad().iter()).for_each(
+        |(val, expected)| {
+            assert!((val - expected).abs() < 1.0e-2 * (1.0 + expected.abs()));
+        }
+    );
+
+    // Non-finite gradients are not scaled.
+    let mut layer_1 = layer.clone();
+    layer_1.total_grad_mut().as_mut_slice()[3] = f32::NAN;
+    assert!(layer_1.clip_grad_by_global_norm(1.0).is_nan());
+    assert_eq!(
+        layer_1.total_grad().as_slice()[0],
+        layer.total_grad().as_slice()[0]
+    );
+
+    let mut layer_1 = layer.clone();
+    layer_1.total_grad_mut().as_mut_slice()[3] = f32::INFINITY;
+    assert_eq!(layer_1.clip_grad_by_global_norm(1.0), f32::INFINITY);
+    assert_eq!(
+        layer_1.total_grad().as_slice()[0],
+        layer.total_grad().as_slice()[0]
+    );
+
+    // Zero gradients.
+    let mut layer_1 = layer.clone();
+    layer_1.total_grad_mut().clear();
+    assert_eq!(layer_1.clip_grad_by_global_norm(1.0), 0.0);
+}
+
+#[test]
+fn check_finite_test_1() {
+    const OUT: usize = 8;
+    const MIDDLE: usize = 16;
+    const IN: usize = 8;
+
+    let mut rng = ChobitRand::new("check_finite_test_1".as_bytes());
+
+    let mut ai = ChobitMlSeqAi::<OUT, MIDDLE, IN>::new(
+        gen_seq_ai::<OUT, MIDDLE, IN>(&mut rng)
+    );
+
+    assert_eq!(ai.check_finite(), Ok(()));
+
+    let mut first = true;
+    ai.for_each_total_grad_mut(|val| {
+        if first {*val = f32::NAN;}
+        first = false;
+    });
+
+    assert_eq!(
+        ai.check_finite(),
+        Err(ChobitAiError::NonFinite {
+            layer: "enc_layer.main_layer",
+            part: "total_grad"
+        })
+    );
+
+    let mut count = 0;
+    ai.for_each_total_grad(|_| {count += 1});
+
+    let mut i = 0;
+    ai.for_each_total_grad_mut(|val| {
+        if i == count - 1 {*val = f32::INFINITY;}
+        i += 1;
+    });
+
+    // Layer nearest to output is reported first.
+    assert_eq!(
+        ai.check_finite(),
+        Err(ChobitAiError::NonFinite {
+            layer: "output_layer",
+            part: "total_grad"
+        })
+    );
+
+    let mut ai = ChobitAi::<OUT, MIDDLE, IN>::new(Activation::SoftSign);
+    ai.middle_layer_mut().mut_weights().as_mut_slice()[0] = f32::NAN;
+
+    let ai = ChobitMlAi::<OUT, MIDDLE, IN>::new(ai);
+
+    assert_eq!(
+        ai.check_finite(),
+        Err(ChobitAiError::NonFinite {
+            layer: "middle_layer",
+            part: "weights"
+        })
+    );
+
+    assert_eq!(
+        format!("{}", ai.check_finite().unwrap_err()),
+        r#"{"error":"ChobitAiError","kind":"NonFinite","layer":"middle_layer","part":"weights"}"#
+    );
+}
+
+#[test]
+fn activation_test_1() {
+    const H: f32 = 1.0e-3;
+
+    let activations = [
+        Activation::Linear,
+        Activation::ReLU,
+        Activation::SoftSign,
+        Activation::Sigmoid,
+        Activation::LeakyReLU(0.1),
+        Activation::ELU(1.5),
+        Activation::GELU,
+        Activation::Swish,
+        Activation::Tanh,
+        Activation::HardSigmoid,
+        Activation::HardTanh,
+        Activation::PReLU(0.25)
+    ];
+
+    // Points away from kinks of ReLU-like and hard functions.
+    let points = [-5.0f32, -2.5, -0.7, -0.3, 0.2, 0.6, 1.7, 4.0];
+
+    for activation in activations.iter() {
+        for x in points.iter() {
+            let numerical = (activation.activate(*x + H)
+                - activation.activate(*x - H)) / (2.0 * H);
+            let analytical = activation.d_activate(*x);
+
+            assert!(
+                (numerical - analytical).abs() < 2.0e-3,
+                "{:?} at {} : {} != {}",
+                activation,
+                x,
+                numerical,
+                analytical
+            );
+        }
+    }
+}
+
+#[test]
+fn activation_test_2() {
+    for i in -40..=40 {
+        let x = (i as f32) * 0.25;
+
+        let tanh = Activation::Tanh.activate(x);
+        assert!((tanh - x.tanh()).abs() < 1.0e-5, "{} : {}", x, tanh);
+
+        let expected = x / (1.0 + (-x).exp());
+        let swish = Activation::Swish.activate(x);
+        assert!((swish - expected).abs() < 1.0e-5, "{} : {}", x, swish);
+
+        let expected = if x > 0.0 {x} else {0.5 * (x.exp() - 1.0)};
+        let elu = Activation::ELU(0.5).activate(x);
+        assert!((elu - expected).abs() < 1.0e-5, "{} : {}", x, elu);
+    }
+
+    assert_eq!(Activation::Tanh.activate(100.0), 1.0);
+    assert_eq!(Activation::Tanh.activate(-100.0), -1.0);
+    assert_eq!(Activation::Swish.activate(-100.0), 0.0);
+    assert_eq!(Activation::HardSigmoid.activate(-3.0), 0.0);
+    assert_eq!(Activation::HardSigmoid.activate(0.0), 0.5);
+    assert_eq!(Activation::HardTanh.activate(2.0), 1.0);
+    assert_eq!(Activation::LeakyReLU(0.1).activate(-2.0), -0.2);
+
+    // GELU(1) = 0.8412 (tanh approximation)
+    assert!((Activation::GELU.activate(1.0) - 0.8412).abs() < 1.0e-4);
+}
+
+#[test]
+fn activation_test_3() {
+    const OUT: usize = 8;
+    const MIDDLE: usize = 16;
+    const IN: usize = 8;
+
+    let mut rng = ChobitRand::new("activation_test_3".as_bytes());
+
+    let mut lstm = Lstm::<OUT, IN>::with_activations(
+        Activation::Tanh,
+        Activation::HardSigmoid,
+        Activation::Tanh
+    );
+    rand_weights(&mut rng, lstm.main_layer_mut().mut_weights());
+    rand_weights(&mut rng, lstm.f_gate_mut().mut_weights());
+    rand_weights(&mut rng, lstm.i_gate_mut().mut_weights());
+    rand_weights(&mut rng, lstm.o_gate_mut().mut_weights());
+
+    assert_eq!(lstm.tanh(), &Activation::Tanh);
+    assert_eq!(lstm.f_gate().activation(), &Activation::HardSigmoid);
+
+    let mut data = Vec::<u8>::new();
+    lstm.save(&mut data);
+    assert_eq!(Lstm::<OUT, IN>::load(&data), Ok(lstm.clone()));
+
+    *lstm.tanh_mut() = Activation::ELU(0.5);
+
+    let mut data = Vec::<u8>::new();
+    lstm.save(&mut data);
+    assert_eq!(Lstm::<OUT, IN>::load(&data), Ok(lstm));
+
+    let mut ai = ChobitAi::<OUT, MIDDLE, IN>::new(Activation::GELU);
+    *ai.middle_layer_mut().mut_activation() = Activation::LeakyReLU(0.01);
+
+    let mut data = Vec::<u8>::new();
+    ai.save(&mut data);
+    assert_eq!(ChobitAi::<OUT, MIDDLE, IN>::load(&data), Ok(ai));
+}
+
+#[test]
+fn prelu_test_1() {
+    const OUT: usize = 6;
+    const MIDDLE: usize = 8;
+    const IN: usize = 5;
+    const LEN: usize = 4;
+
+    let mut rng = ChobitRand::new("prelu_test_1".as_bytes());
+
+    let mut layer = Layer::<OUT, IN>::new(Activation::PReLU(0.2), true);
+    rand_weights(&mut rng, layer.mut_weights());
+
+    let mut inputs = vec![MathVec::<IN>::new(); LEN];
+    let mut states = vec![MathVec::<OUT>::new(); LEN];
+    let mut train_outs = vec![MathVec::<OUT>::new(); LEN];
+
+    for i in 0..LEN {
+        rand_math_vec(&mut rng, &mut inputs[i]);
+        rand_math_vec(&mut rng, &mut states[i]);
+        rand_math_vec(&mut rng, &mut train_outs[i]);
+    }
+
+    // Loss is sum((o - t)^2) / 2 of all samples.
+    let calc_loss = |layer: &Layer<OUT, IN>| -> f32 {
+        let mut output = MathVec::<OUT>::new();
+
+        (0..LEN).fold(0.0, |loss, i| {
+            layer.calc(&inputs[i], Some(&states[i]), &mut output);
+
+            output.iter().zip(train_outs[i].iter()).fold(
+                loss,
+                |loss, (o, t)| loss + ((o - t) * (o - t) / 2.0)
+            )
+        })
+    };
+
+    let mut ml_layer = MlLayer::<OUT, IN>::new(layer.clone());
+    ml_layer.set_optimizer(Optimizer::Sgd);
+
+    let mut cache = MlCache::<OUT, IN>::new();
+    let mut output_error = MathVec::<OUT>::new();
+    let mut input_error = MathVec::<IN>::new();
+
+    for i in 0..LEN {
+        ml_layer.ready(&inputs[i], Some(&states[i]), &mut cache);
+        cache.calc_output_error(&train_outs[i], &mut output_error);
+        ml_layer.study(&output_error, None, &cache, &mut input_error, None);
+    }
+
+    let slope_grad = ml_layer.slope_grad().unwrap();
+    assert!(slope_grad != 0.0);
+
+    check_numerical_grad(&[slope_grad], &[0.2], |slope| {
+        let mut layer = layer.clone();
+        *layer.mut_activation() = Activation::PReLU(slope[0]);
+
+        calc_loss(&layer)
+    });
+
+    // Batch gives the same gradient.
+    let mut ml_layer_2 = MlLayer::<OUT, IN>::new(layer.clone());
+    let mut batch_cache = MlBatchCache::<OUT, IN>::new();
+    let mut output_errors = vec![MathVec::<OUT>::new(); LEN];
+    let mut input_errors = vec![MathVec::<IN>::new(); LEN];
+
+    ml_layer_2.ready_batch(&inputs, Some(&states), &mut batch_cache);
+    batch_cache.calc_output_error(&train_outs, &mut output_errors);
+    ml_layer_2.study_batch(
+        &output_errors,
+        None,
+        &batch_cache,
+        &mut input_errors,
+        None
+    );
+
+    assert_close(ml_layer_2.slope_grad().unwrap(), slope_grad);
+
+    // Dropped outputs have no gradient of slope.
+    let mut ml_layer_2 = MlLayer::<OUT, IN>::new(layer.clone());
+    ml_layer_2.ready_with_dropout(
+        &inputs[0],
+        Some(&states[0]),
+        &mut cache,
+        0.5,
+        || 0.0
+    );
+    cache.calc_output_error(&train_outs[0], &mut output_error);
+    ml_layer_2.study(&output_error, None, &cache, &mut input_error, None);
+    assert_eq!(ml_layer_2.slope_grad(), Some(0.0));
+
+    // Gradient of slope is a part of total gradients.
+    let mut ml_layer_2 = ml_layer.clone();
+    ml_layer_2.clip_grad_by_value(slope_grad.abs() / 2.0);
+    assert_eq!(ml_layer_2.slope_grad(), Some(slope_grad / 2.0));
+
+    // Checkpoint keeps gradient of slope.
+    let mut data = Vec::<u8>::new();
+    ml_layer.save_checkpoint(&mut data);
+
+    let loaded = MlLayer::<OUT, IN>::load_checkpoint(&data).unwrap();
+    assert_eq!(loaded.slope_grad(), Some(slope_grad));
+
+    let mut data_2 = Vec::<u8>::new();
+    loaded.save_checkpoint(&mut data_2);
+    assert_eq!(data, data_2);
+
+    // Slope is updated.
+    ml_layer.update(0.1);
+
+    let layer = ml_layer.drop();
+    assert_close(
+        match layer.activation() {
+            Activation::PReLU(slope) => *slope,
+            _ => panic!()
+        },
+        0.2 - (0.1 * slope_grad)
+    );
+
+    // Non-PReLU layer has no gradient of slope.
+    let mut ml_layer = MlLayer::<OUT, IN>::new(
+        Layer::<OUT, IN>::new(Activation::LeakyReLU(0.2), true)
+    );
+    assert_eq!(ml_layer.slope_grad(), None);
+    assert_eq!(ml_layer.slope_grad_mut(), None);
+
+    // Slope of each PReLU layer is counted in total gradients.
+    let ai = ChobitAi::<OUT, MIDDLE, IN>::with_activations(
+        Activation::PReLU(0.1),
+        Activation::PReLU(0.1)
+    );
+
+    let mut count = 0;
+    ChobitMlAi::<OUT, MIDDLE, IN>::new(ai.clone()).for_each_total_grad(
+        |_| {count += 1;}
+    );
+
+    assert_eq!(count, (MIDDLE * (IN + 1)) + (OUT * (MIDDLE + 1)) + 2);
+
+    // Weights and total gradients have the same count.
+    let mut weight_count = 0;
+    ai.for_each_weight(|_| {weight_count += 1;});
+    assert_eq!(weight_count, count);
+
+    let mut lstm = Lstm::<OUT, IN>::new();
+    *lstm.main_layer_mut().mut_activation() = Activation::PReLU(0.1);
+
+    let mut weight_count = 0;
+    lstm.for_each_weight(|_| {weight_count += 1;});
+
+    let mut count = 0;
+    MlLstm::<OUT, IN>::new(lstm).for_each_total_grad(|_| {count += 1;});
+    assert_eq!(weight_count, count);
+
+    let network = Layer::<MIDDLE, IN>::new(Activation::PReLU(0.1), false)
+        .stack(Layer::<OUT, MIDDLE>::new(Activation::PReLU(0.1), false));
+
+    let mut weight_count = 0;
+    network.for_each_weight(|_| {weight_count += 1;});
+
+    let mut count = 0;
+    network.into_ml().for_each_total_grad(|_| {count += 1;});
+    assert_eq!(weight_count, count);
+
+    // Slope is copied with weights.
+    let mut ai_2 = ChobitAi::<OUT, MIDDLE, IN>::with_activations(
+        Activation::PReLU(0.3),
+        Activation::PReLU(0.3)
+    );
+
+    let mut weights = Vec::<f32>::new();
+    ai.for_each_weight(|val| {weights.push(*val)});
+
+    let mut iter = weights.iter();
+    ai_2.for_each_weight_mut(|val| {*val = *iter.next().unwrap()});
+    assert_eq!(ai_2, ai);
+}
+
+#[test]
+fn softmax_test_1() {
+    let mut vec = MathVec::<4>::new();
+    vec.as_mut_array().copy_from_slice(&[1.0, 3.0, -2.0, 0.5]);
+
+    assert_eq!(vec.argmax(), 1);
+
+    let sum_exp: f32 = [1.0f32, 3.0, -2.0, 0.5].iter().map(|x| x.exp()).sum();
+
+    let mut softmax = vec.clone();
+    softmax.softmax();
+
+    softmax.iter().zip(vec.iter()).for_each(|(s, x)| {
+        assert!((s - x.exp() / sum_exp).abs() < 1.0e-5);
+    });
+
+    assert_eq!(softmax.argmax(), 1);
+
+    // Large values don't overflow.
+    vec.as_mut_array().copy_from_slice(&[1000.0, 1000.0, -1000.0, 0.0]);
+    vec.softmax();
+    assert!((vec[0] - 0.5).abs() < 1.0e-5);
+    assert!((vec[1] - 0.5).abs() < 1.0e-5);
+    assert_eq!(vec.argmax(), 0);
+}
+
+#[test]
+fn loss_test_1() {
+    const N: usize = 6;
+    const H: f32 = 1.0e-2;
+
+    let mut rng = ChobitRand::new("loss_test_1".as_bytes());
+
+    let mut output = MathVec::<N>::new();
+    rand_math_vec(&mut rng, &mut output);
+    output *= 3.0;
+
+    let mut one_hot = MathVec::<N>::new();
+    one_hot[2] = 1.0;
+
+    let mut label = MathVec::<N>::new();
+    label.iter_mut().enumerate().for_each(|(i, val)| {
+        *val = if (i % 2) == 0 {1.0} else {-1.0};
+    });
+
+    let mut target = MathVec::<N>::new();
+    rand_math_vec(&mut rng, &mut target);
+
+    let cases = [
+        (Loss::Mse, &target),
+        (Loss::Huber(0.5), &target),
+        (Loss::CategoricalCrossEntropy, &one_hot),
+        (Loss::BinaryCrossEntropy, &label)
+    ];
+
+    let mut error = MathVec::<N>::new();
+
+    for (loss, train_out) in cases.iter() {
+        loss.calc_error(&output, train_out, &mut error);
+
+        for i in 0..N {
+            let mut plus = output.clone();
+            plus[i] += H;
+
+            let mut minus = output.clone();
+            minus[i] -= H;
+
+            let numerical = (loss.calc_loss(&plus, train_out)
+                - loss.calc_loss(&minus, train_out)) / (2.0 * H);
+
+            assert!(
+                (numerical - error[i]).abs() < 2.0e-3,
+                "{:?} [{}] : {} != {}",
+                loss,
+                i,
+                numerical,
+                error[i]
+            );
+        }
+    }
+
+    // Cross entropy of correct and confident prediction is almost 0.
+    let mut confident = MathVec::<N>::new();
+    confident[2] = 20.0;
+    assert!(Loss::CategoricalCrossEntropy.calc_loss(&confident, &one_hot) < 1.0e-5);
+
+    let mut confident = label.clone();
+    confident *= 20.0;
+    assert!(Loss::BinaryCrossEntropy.calc_loss(&confident, &label) < 1.0e-5);
+}
+
+#[test]
+fn loss_test_2() {
+    const CLASSES: usize = 4;
+    const MIDDLE: usize = 16;
+    const IN: usize = 8;
+
+    const EPOCH: usize = 300;
+    const BATCH_SIZE: usize = 8;
+    const RATE: f32 = 0.01;
+
+    let mut rng = ChobitRand::new("loss_test_2".as_bytes());
+
+    let mut ai = ChobitAi::<CLASSES, MIDDLE, IN>::new(Activation::Linear);
+    rand_weights(&mut rng, ai.middle_layer_mut().mut_weights());
+    rand_weights(&mut rng, ai.output_layer_mut().mut_weights());
+
+    let mut ai = ChobitMlAi::<CLASSES, MIDDLE, IN>::new(ai);
+    let mut cache = MlAiCache::<CLASSES, MIDDLE, IN>::new();
+
+    let mut input = MathVec::<IN>::new();
+    let mut one_hot = MathVec::<CLASSES>::new();
+    let mut output_error = MathVec::<CLASSES>::new();
+    let mut input_error = MathVec::<IN>::new();
+
+    let loss = Loss::CategoricalCrossEntropy;
+
+    for _ in 0..EPOCH {
+        for _ in 0..BATCH_SIZE {
+            let label = rng.next_u64() as u8;
+
+            input.load_u8_label(label);
+            one_hot.clear();
+            one_hot[(label % CLASSES as u8) as usize] = 1.0;
+
+            ai.ready(&input, &mut cache);
+            cache.calc_output_error_with_loss(
+                &loss,
+                &one_hot,
+                &mut output_error
+            );
+            ai.study(&output_error, &cache, &mut input_error);
+        }
+
+        ai.update(RATE);
+    }
+
+    let ai = ai.drop();
+    let mut output = MathVec::<CLASSES>::new();
+    let mut tmpbuf = MathVec::<MIDDLE>::new();
+
+    for label in 0..=255u8 {
+        input.load_u8_label(label);
+        ai.calc(&input, &mut output, &mut tmpbuf);
+
+        assert_eq!(output.argmax(), (label % CLASSES as u8) as usize);
+    }
+}
+
+#[test]
+fn loss_test_3() {
+    const OUT: usize = 8;
+    const MIDDLE: usize = 16;
+    const IN: usize = 8;
+
+    const EPOCH: usize = 300;
+    const BATCH_SIZE: usize = 8;
+    const RATE: f32 = 0.01;
+
+    let mut rng = ChobitRand::new("loss_test_3".as_bytes());
+
+    let mut ai = ChobitAi::<OUT, MIDDLE, IN>::new(Activation::Linear);
+    rand_weights(&mut rng, ai.middle_layer_mut().mut_weights());
+    rand_weights(&mut rng, ai.output_layer_mut().mut_weights());
+
+    let mut ai = ChobitMlAi::<OUT, MIDDLE, IN>::new(ai);
+    let mut cache = MlAiCache::<OUT, MIDDLE, IN>::new();
+
+    let mut input = MathVec::<IN>::new();
+    let mut train_out = MathVec::<OUT>::new();
+    let mut output_error = MathVec::<OUT>::new();
+    let mut input_error = MathVec::<IN>::new();
+
+    let loss = Loss::BinaryCrossEntropy;
+
+    for _ in 0..EPOCH {
+        for _ in 0..BATCH_SIZE {
+            let label = rng.next_u64() as u8;
+
+            input.load_u8_label(label);
+            train_out.load_u8_label(!label);
+
+            ai.ready(&input, &mut cache);
+            cache.calc_output_error_with_loss(
+                &loss,
+                &train_out,
+                &mut output_error
+            );
+            ai.study(&output_error, &cache, &mut input_error);
+        }
+
+        ai.update(RATE);
+    }
+
+    let ai = ai.drop();
+    let mut output = MathVec::<OUT>::new();
+    let mut tmpbuf = MathVec::<MIDDLE>::new();
+
+    for label in 0..=255u8 {
+        input.load_u8_label(label);
+        ai.calc(&input, &mut output, &mut tmpbuf);
+
+        assert_eq!(output.to_u8_label(), !label);
+    }
+}
+
+#[test]
+fn loss_test_4() {
+    const OUT: usize = 8;
+    const MIDDLE: usize = 16;
+    const IN: usize = 8;
+
+    let mut rng = ChobitRand::new("loss_test_4".as_bytes());
+
+    let mut ai = ChobitMlSeqAi::<OUT, MIDDLE, IN>::new(
+        gen_seq_ai::<OUT, MIDDLE, IN>(&mut rng)
+    );
+    let mut cache = MlSeqAiCache::<OUT, MIDDLE, IN>::new(0, 0);
+
+    let mut input = vec![MathVec::<IN>::new(); 3];
+    input.iter_mut().for_each(|vec| {rand_math_vec(&mut rng, vec)});
+
+    let mut train_out = vec![MathVec::<OUT>::new(); 4];
+    train_out.iter_mut().for_each(|vec| {rand_math_vec(&mut rng, vec)});
+
+    ai.ready(&input, &MathVec::<MIDDLE>::new(), train_out.len(), &mut cache);
+
+    let mut error_1 = vec![MathVec::<OUT>::new(); 4];
+    let mut error_2 = vec![MathVec::<OUT>::new(); 4];
+
+    cache.calc_output_error(&train_out, &mut error_1);
+    cache.calc_output_error_with_loss(&Loss::Mse, &train_out, &mut error_2);
+
+    assert_eq!(error_1, error_2);
+
+    cache.calc_output_error_with_loss(
+        &Loss::CategoricalCrossEntropy,
+        &train_out,
+        &mut error_2
+    );
+
+    error_1.iter().zip(error_2.iter()).zip(train_out.iter()).for_each(
+        |((e_1, e_2), t)| {
+            // e_1 = output - t
+            let mut expected = e_1 + t;
+            expected.softmax();
+            expected -= t;
+
+            e_2.iter().zip(expected.iter()).for_each(|(a, b)| {
+                assert!((a - b).abs() < 1.0e-5);
+            });
+        }
+    );
+}
+
+#[test]
+fn loss_test_5() {
+    const OUT: usize = 8;
+    const MIDDLE: usize = 16;
+    const IN: usize = 8;
+
+    let mut rng = ChobitRand::new("loss_test_5".as_bytes());
+
+    let mut output = MathVec::<OUT>::new();
+    rand_math_vec(&mut rng, &mut output);
+    output *= 3.0;
+
+    let mut target = MathVec::<OUT>::new();
+    rand_math_vec(&mut rng, &mut target);
+
+    // Huber with negative delta is the same as its absolute value.
+    let mut error_1 = MathVec::<OUT>::new();
+    let mut error_2 = MathVec::<OUT>::new();
+
+    Loss::Huber(0.5).calc_error(&output, &target, &mut error_1);
+    Loss::Huber(-0.5).calc_error(&output, &target, &mut error_2);
+    assert_eq!(error_1, error_2);
+    assert_eq!(
+        Loss::Huber(0.5).calc_loss(&output, &target),
+        Loss::Huber(-0.5).calc_loss(&output, &target)
+    );
+
+    // Huber with NaN delta doesn't panic and is the same as Mse.
+    Loss::Mse.calc_error(&output, &target, &mut error_1);
+    Loss::Huber(f32::NAN).calc_error(&output, &target, &mut error_2);
+    assert_eq!(error_1, error_2);
+    assert_close(
+        Loss::Huber(f32::NAN).calc_loss(&output, &target),
+        Loss::Mse.calc_loss(&output, &target)
+    );
+
+    // Prediction.
+    let mut prediction = output.clone();
+    Loss::Mse.predict(&mut prediction);
+    assert_eq!(prediction, output);
+
+    let mut prediction = output.clone();
+    Loss::CategoricalCrossEntropy.predict(&mut prediction);
+    let mut expected = output.clone();
+    expected.softmax();
+    assert_eq!(prediction, expected);
+
+    let mut prediction = output.clone();
+    Loss::BinaryCrossEntropy.predict(&mut prediction);
+    prediction.iter().zip(output.iter()).for_each(|(p, o)| {
+        assert!((-1.0..=1.0).contains(p));
+        assert_eq!(*p >= 0.0, *o >= 0.0);
+    });
+    assert_eq!(prediction.to_u8_label(), output.to_u8_label());
+
+    // Inference with loss function.
+    let loss = Loss::CategoricalCrossEntropy;
+
+    let mut ai = ChobitAi::<OUT, MIDDLE, IN>::new(Activation::Linear);
+    rand_weights(&mut rng, ai.middle_layer_mut().mut_weights());
+    rand_weights(&mut rng, ai.output_layer_mut().mut_weights());
+
+    let mut input = MathVec::<IN>::new();
+    rand_math_vec(&mut rng, &mut input);
+
+    let mut output_1 = MathVec::<OUT>::new();
+    let mut output_2 = MathVec::<OUT>::new();
+    let mut tmpbuf = MathVec::<MIDDLE>::new();
+
+    ai.calc(&input, &mut output_1, &mut tmpbuf);
+    output_1.softmax();
+    ai.calc_with_loss(&loss, &input, &mut output_2, &mut tmpbuf);
+    assert_eq!(output_1, output_2);
+
+    let mut seq_ai_1 = gen_seq_ai::<OUT, MIDDLE, IN>(&mut rng);
+    let mut seq_ai_2 = seq_ai_1.clone();
+
+    seq_ai_1.input_next(&input);
+    seq_ai_2.input_next(&input);
+
+    for _ in 0..3 {
+        seq_ai_1.output_next(&mut output_1);
+        output_1.softmax();
+        seq_ai_2.output_next_with_loss(&loss, &mut output_2);
+        assert_eq!(output_1, output_2);
+    }
+}
+
+#[test]
+fn network_test_1() {
+    const OUT: usize = 8;
+    const MIDDLE: usize = 16;
+    const IN: usize = 8;
+
+    const RATE: f32 = 0.01;
+
+    let mut rng = ChobitRand::new("network_test_1".as_bytes());
+
+    let mut ai = ChobitAi::<OUT, MIDDLE, IN>::new(Activation::SoftSign);
+    rand_weights(&mut rng, ai.middle_layer_mut().mut_weights());
+    rand_weights(&mut rng, ai.output_layer_mut().mut_weights());
+
+    // The same network as ChobitAi.
+    let network = ai.middle_layer().clone().stack(ai.output_layer().clone());
+
+    let mut input = MathVec::<IN>::new();
+    let mut output_1 = MathVec::<OUT>::new();
+    let mut output_2 = MathVec::<OUT>::new();
+    let mut tmpbuf = MathVec::<MIDDLE>::new();
+    let mut buffer = network.new_buffer();
+
+    for label in 0..10u8 {
+        input.load_u8_label(label);
+
+        ai.calc(&input, &mut output_1, &mut tmpbuf);
+        network.calc(&input, &mut output_2, &mut buffer);
+
+        assert_eq!(output_1, output_2);
+    }
+
+    let mut ai = ChobitMlAi::<OUT, MIDDLE, IN>::new(ai);
+    let mut network = network.into_ml();
+
+    let mut ai_cache = MlAiCache::<OUT, MIDDLE, IN>::new();
+    let mut network_cache = network.new_cache();
+
+    let mut train_out = MathVec::<OUT>::new();
+    let mut output_error_1 = MathVec::<OUT>::new();
+    let mut output_error_2 = MathVec::<OUT>::new();
+    let mut input_error_1 = MathVec::<IN>::new();
+    let mut input_error_2 = MathVec::<IN>::new();
+
+    for label in 0..30u8 {
+        input.load_u8_label(label);
+        train_out.load_u8_label(label.wrapping_mul(3));
+
+        ai.ready(&input, &mut ai_cache);
+        network.ready(&input, &mut network_cache);
+
+        ai_cache.calc_output_error(&train_out, &mut output_error_1);
+        network_cache.calc_output_error(&train_out, &mut output_error_2);
+        assert_eq!(output_error_1, output_error_2);
+
+        ai.study(&output_error_1, &ai_cache, &mut input_error_1);
+        network.study(&output_error_2, &network_cache, &mut input_error_2);
+        assert_eq!(input_error_1, input_error_2);
+
+        if (label % 5) == 4 {
+            ai.update(RATE);
+            network.update(RATE);
+        }
+    }
+
+    let ai = ai.drop();
+    let network = network.drop();
+
+    assert_eq!(ai.middle_layer(), network.lower());
+    assert_eq!(ai.output_layer(), network.layer());
+}
+
+#[test]
+fn network_test_2() {
+    const OUT: usize = 8;
+    const IN: usize = 8;
+
+    const EPOCH: usize = 1000;
+    const BATCH_SIZE: usize = 8;
+    const RATE: f32 = 0.01;
+
+    let mut rng = ChobitRand::new("network_test_2".as_bytes());
+
+    // 8 -> 32 -> 24 -> 16 -> 8
+    let mut network = Layer::<32, IN>::new(Activation::ReLU, false)
+        .stack(Layer::<24, 32>::new(Activation::SoftSign, false))
+        .stack(Layer::<16, 24>::new(Activation::SoftSign, false))
+        .stack(Layer::<OUT, 16>::new(Activation::Linear, false));
+
+    network.for_each_weight_mut(|val| {*val = rand_num(&mut rng)});
+
+    let mut count: usize = 0;
+    network.for_each_weight(|_| {count += 1});
+    assert_eq!(
+        count,
+        (32 * (IN + 1)) + (24 * 33) + (16 * 25) + (OUT * 17)
+    );
+
+    let mut network = network.into_ml();
+    let mut cache = network.new_cache();
+
+    let mut input = MathVec::<IN>::new();
+    let mut train_out = MathVec::<OUT>::new();
+    let mut output_error = MathVec::<OUT>::new();
+    let mut input_error = MathVec::<IN>::new();
+
+    for _ in 0..EPOCH {
+        for _ in 0..BATCH_SIZE {
+            let label = rng.next_u64() as u8;
+
+            input.load_u8_label(label);
+            train_out.load_u8_label(label.rotate_left(3));
+
+            network.ready(&input, &mut cache);
+            cache.calc_output_error_with_loss(
+                &Loss::BinaryCrossEntropy,
+                &train_out,
+                &mut output_error
+            );
+            network.study(&output_error, &cache, &mut input_error);
+        }
+
+        network.update(RATE);
+    }
+
+    let network = network.drop();
+    let mut output = MathVec::<OUT>::new();
+    let mut buffer = network.new_buffer();
+
+    for label in 0..=255u8 {
+        input.load_u8_label(label);
+        network.calc(&input, &mut output, &mut buffer);
+
+        assert_eq!(output.to_u8_label(), label.rotate_left(3));
+    }
+}
+
+#[test]
+fn constructor_test_1() {
+    const OUT: usize = 8;
+    const MIDDLE: usize = 16;
+    const IN: usize = 8;
+
+    assert_eq!(
+        ChobitAi::<OUT, MIDDLE, IN>::new(Activation::SoftSign),
+        ChobitAi::<OUT, MIDDLE, IN>::with_activations(
+            Activation::ReLU,
+            Activation::SoftSign
+        )
+    );
+
+    let ai = ChobitAi::<OUT, MIDDLE, IN>::with_activations(
+        Activation::SoftSign,
+        Activation::Linear
+    );
+    assert_eq!(ai.middle_layer().activation(), &Activation::SoftSign);
+    assert_eq!(ai.output_layer().activation(), &Activation::Linear);
+
+    let ai = ChobitAi::<OUT, MIDDLE, IN>::with_layers(
+        Layer::<MIDDLE, IN>::new(Activation::GELU, false),
+        Layer::<OUT, MIDDLE>::new(Activation::Tanh, true)
+    );
+    assert_eq!(ai.middle_layer().activation(), &Activation::GELU);
+    assert!(ai.output_layer().weights().state_weights().is_some());
+
+    let mut data = Vec::<u8>::new();
+    ai.save(&mut data);
+    assert_eq!(ChobitAi::<OUT, MIDDLE, IN>::load(&data), Ok(ai));
+
+    assert_eq!(
+        ChobitEncoder::<OUT, MIDDLE, IN>::new(Activation::SoftSign),
+        ChobitEncoder::<OUT, MIDDLE, IN>::with_activations(
+            Activation::SoftSign,
+            Activation::SoftSign
+        )
+    );
+
+    let encoder = ChobitEncoder::<OUT, MIDDLE, IN>::with_activations(
+        Activation::Tanh,
+        Activation::Linear
+    );
+    assert_eq!(encoder.lstm().main_layer().activation(), &Activation::Tanh);
+    assert_eq!(encoder.lstm().tanh(), &Activation::Tanh);
+    assert_eq!(encoder.lstm().f_gate().activation(), &Activation::Sigmoid);
+
+    assert_eq!(
+        ChobitDecoder::<OUT, MIDDLE, IN>::new(Activation::SoftSign),
+        ChobitDecoder::<OUT, MIDDLE, IN>::with_activations(
+            Activation::SoftSign,
+            Activation::SoftSign
+        )
+    );
+
+    let decoder = ChobitDecoder::<OUT, MIDDLE, IN>::with_layers(
+        Lstm::<MIDDLE, IN>::with_activations(
+            Activation::Swish,
+            Activation::HardSigmoid,
+            Activation::Tanh
+        ),
+        Layer::<OUT, MIDDLE>::new(Activation::Linear, false)
+    );
+    assert_eq!(decoder.lstm().i_gate().activation(), &Activation::HardSigmoid);
+
+    assert_eq!(
+        ChobitSeqAi::<OUT, MIDDLE, IN>::new(Activation::SoftSign),
+        ChobitSeqAi::<OUT, MIDDLE, IN>::with_activations(
+            Activation::SoftSign,
+            Activation::SoftSign
+        )
+    );
+
+    let ai = ChobitSeqAi::<OUT, MIDDLE, IN>::with_activations(
+        Activation::Tanh,
+        Activation::Linear
+    );
+    assert_eq!(ai.enc_layer().main_layer().activation(), &Activation::Tanh);
+    assert_eq!(ai.dec_layer().tanh(), &Activation::Tanh);
+    assert_eq!(ai.output_layer().activation(), &Activation::Linear);
+
+    let mut data = Vec::<u8>::new();
+    ai.save(&mut data);
+    assert_eq!(ChobitSeqAi::<OUT, MIDDLE, IN>::load(&data), Ok(ai));
+}
+
+fn mean_and_std(values: &[f32]) -> (f32, f32) {
+    let len = values.len() as f32;
+    let mean = values.iter().sum::<f32>() / len;
+    let var =
+        values.iter().map(|x| (x - mean) * (x - mean)).sum::<f32>() / len;
+
+    (mean, var.sqrt())
+}
+
+#[test]
+fn initializer_test_1() {
+    const OUT: usize = 64;
+    const IN: usize = 128;
+
+    let mut rng = ChobitRand::new("initializer_test_1".as_bytes());
+
+    let cases = [
+        (Initializer::XavierUniform, (2.0 / (IN + OUT) as f32).sqrt()),
+        (Initializer::XavierNormal, (2.0 / (IN + OUT) as f32).sqrt()),
+        (Initializer::HeUniform, (2.0 / IN as f32).sqrt()),
+        (Initializer::HeNormal, (2.0 / IN as f32).sqrt())
+    ];
+
+    for (initializer, expected_std) in cases.iter() {
+        let mut layer = Layer::<OUT, IN>::new(Activation::ReLU, false);
+        layer.mut_weights().iter_mut().for_each(|val| {*val = 1.0});
+
+        layer.init_weights(*initializer, || rng.next_f64() as f32);
+
+        assert!(layer.weights().bias().iter().all(|val| *val == 0.0));
+
+        let values = layer.weights().input_weights().as_flattened();
+        let (mean, std) = mean_and_std(values);
+
+        assert!(
+            mean.abs() < expected_std * 0.05,
+            "{:?} : {}",
+            initializer,
+            mean
+        );
+        assert!(
+            (std - expected_std).abs() < expected_std * 0.05,
+            "{:?} : {} != {}",
+            initializer,
+            std,
+            expected_std
+        );
+
+        if let Initializer::XavierUniform = initializer {
+            let a = (6.0 / (IN + OUT) as f32).sqrt();
+            assert!(values.iter().all(|val| val.abs() <= a));
+        }
+    }
+
+    // Reproducible.
+    let mut layer_1 = Layer::<OUT, IN>::new(Activation::ReLU, true);
+    let mut layer_2 = Layer::<OUT, IN>::new(Activation::ReLU, true);
+
+    let mut rng_1 = ChobitRand::new("seed".as_bytes());
+    let mut rng_2 = ChobitRand::new("seed".as_bytes());
+
+    layer_1.init_weights(Initializer::HeNormal, || rng_1.next_f64() as f32);
+    layer_2.init_weights(Initializer::HeNormal, || rng_2.next_f64() as f32);
+
+    assert_eq!(layer_1, layer_2);
+}
+
+fn assert_orthonormal(matrix: &[f32], rows: usize, cols: usize) {
+    let (count, len, at): (usize, usize, Box<dyn Fn(usize, usize) -> usize>) =
+        if rows <= cols {
+            (rows, cols, Box::new(move |v, i| v * cols + i))
+        } else {
+            (cols, rows, Box::new(move |v, i| i * cols + v))
+        };
+
+    for v in 0..count {
+        for u in 0..count {
+            let dot: f32 = (0..len).map(|i| matrix[at(v, i)] * matrix[at(u, i)])
+                .sum();
+
+            let expected = if u == v {1.0} else {0.0};
+            assert!(
+                (dot - expected).abs() < 1.0e-4,
+                "({}, {}) : {}",
+                v,
+                u,
+                dot
+            );
+        }
+    }
+}
+
+#[test]
+fn initializer_test_2() {
+    let mut rng = ChobitRand::new("initializer_test_2".as_bytes());
+
+    let mut layer = Layer::<8, 16>::new(Activation::Linear, true);
+    layer.init_weights(Initializer::Orthogonal, || rng.next_f64() as f32);
+
+    assert_orthonormal(layer.weights().input_weights().as_flattened(), 8, 16);
+    assert_orthonormal(
+        layer.weights().state_weights().unwrap().as_flattened(),
+        8,
+        8
+    );
+
+    let mut layer = Layer::<16, 8>::new(Activation::Linear, false);
+    layer.init_weights(Initializer::Orthogonal, || rng.next_f64() as f32);
+
+    assert_orthonormal(layer.weights().input_weights().as_flattened(), 16, 8);
+}
+
+#[test]
+fn initializer_test_3() {
+    const OUT: usize = 8;
+    const MIDDLE: usize = 16;
+    const IN: usize = 8;
+
+    let mut rng = ChobitRand::new("initializer_test_3".as_bytes());
+
+    let mut lstm = Lstm::<MIDDLE, IN>::new();
+    lstm.init_weights(
+        Initializer::XavierUniform,
+        Initializer::Orthogonal,
+        || rng.next_f64() as f32
+    );
+
+    assert!(lstm.f_gate().weights().bias().iter().all(|val| *val == 1.0));
+    assert!(lstm.i_gate().weights().bias().iter().all(|val| *val == 0.0));
+    assert!(lstm.main_layer().weights().bias().iter().all(|val| *val == 0.0));
+
+    let layers =
+        [lstm.main_layer(), lstm.f_gate(), lstm.i_gate(), lstm.o_gate()];
+
+    for layer in layers {
+        assert!(layer.weights().input_weights().as_flattened().iter().any(
+            |val| *val != 0.0
+        ));
+
+        assert_orthonormal(
+            layer.weights().state_weights().unwrap().as_flattened(),
+            MIDDLE,
+            MIDDLE
+        );
+    }
+
+    let mut ai = ChobitSeqAi::<OUT, MIDDLE, IN>::new(Activation::SoftSign);
+    ai.init_weights(Initializer::XavierUniform, || rng.next_f64() as f32);
+
+    let enc_bias = ai.enc_layer().f_gate().weights().bias();
+    let dec_bias = ai.dec_layer().f_gate().weights().bias();
+
+    assert!(enc_bias.iter().chain(dec_bias.iter()).all(|val| *val == 1.0));
+    assert!(ai.output_layer().weights().iter().any(|val| *val != 0.0));
+
+    let mut ai = ChobitAi::<OUT, MIDDLE, IN>::new(Activation::SoftSign);
+    ai.init_weights(Initializer::HeUniform, || rng.next_f64() as f32);
+
+    let values = ai.middle_layer().weights().input_weights().as_flattened();
+    assert!(values.iter().all(|val| *val != 0.0));
+}
+
+#[test]
+fn dropout_test_1() {
+    const OUT: usize = 64;
+    const IN: usize = 16;
+    const DROPOUT: f32 = 0.5;
+
+    let mut rng = ChobitRand::new("dropout_test_1".as_bytes());
+
+    let mut layer = Layer::<OUT, IN>::new(Activation::SoftSign, true);
+    rand_weights(&mut rng, layer.mut_weights());
+    let mut layer = MlLayer::<OUT, IN>::new(layer);
+
+    let mut input = MathVec::<IN>::new();
+    rand_math_vec(&mut rng, &mut input);
+    let mut state = MathVec::<OUT>::new();
+    rand_math_vec(&mut rng, &mut state);
+
+    let mut cache = MlCache::<OUT, IN>::new();
+    layer.ready(&input, Some(&state), &mut cache);
+
+    let mut cache_1 = MlCache::<OUT, IN>::new();
+    let mut rng_1 = ChobitRand::new("mask".as_bytes());
+    layer.ready_with_dropout(
+        &input,
+        Some(&state),
+        &mut cache_1,
+        DROPOUT,
+        || rng_1.next_f64() as f32
+    );
+
+    let mut cache_2 = MlCache::<OUT, IN>::new();
+    let mut rng_2 = ChobitRand::new("mask".as_bytes());
+    layer.ready_with_dropout(
+        &input,
+        Some(&state),
+        &mut cache_2,
+        DROPOUT,
+        || rng_2.next_f64() as f32
+    );
+
+    // Deterministic.
+    assert_eq!(cache_1, cache_2);
+
+    // Dropped outputs are 0 and the others are scaled.
+    let mut dropped: Vec<usize> = Vec::new();
+
+    for i in 0..OUT {
+        if cache_1.output()[i] == 0.0 {
+            dropped.push(i);
+            assert_eq!(cache_1.d_activate()[i], 0.0);
+        } else {
+            assert!(
+                (cache_1.output()[i] - (cache.output()[i] * 2.0)).abs() < 1.0e-6
+            );
+            assert!(
+                (cache_1.d_activate()[i] - (cache.d_activate()[i] * 2.0)).abs()
+                    < 1.0e-6
+            );
+        }
+    }
+
+    assert!(dropped.len() > OUT / 4);
+    assert!(dropped.len() < (OUT * 3) / 4);
+
+    // Gradient of dropped outputs is 0.
+    let mut output_error = MathVec::<OUT>::new();
+    output_error.iter_mut().for_each(|val| {*val = 1.0;});
+    let mut input_error = MathVec::<IN>::new();
+
+    layer.study(&output_error, None, &cache_1, &mut input_error, None);
+
+    for i in dropped.iter() {
+        assert_eq!(layer.total_grad().bias()[*i], 0.0);
+        assert!(layer.total_grad().input_weights()[*i].iter().all(
+            |val| *val == 0.0
+        ));
+    }
+
+    // No dropout is same as ready().
+    let mut cache_3 = MlCache::<OUT, IN>::new();
+    layer.ready_with_dropout(
+        &input,
+        Some(&state),
+        &mut cache_3,
+        0.0,
+        || rng.next_f64() as f32
+    );
+
+    assert_eq!(cache, cache_3);
+}
+
+#[test]
+fn weight_penalty_test_1() {
+    const OUT: usize = 2;
+    const IN: usize = 3;
+    const L1: f32 = 0.1;
+    const L2: f32 = 0.01;
+
+    let mut layer = Layer::<OUT, IN>::new(Activation::Linear, true);
+
+    *layer.mut_weights().bias_mut() = [3.0, -3.0];
+    *layer.mut_weights().input_weights_mut() = [
+        [1.0, -2.0, 0.0],
+        [-0.5, 4.0, 2.0]
+    ];
+    *layer.mut_weights().state_weights_mut().unwrap() = [
+        [0.0, -1.0],
+        [2.0, 0.25]
+    ];
+
+    let mut layer = MlLayer::<OUT, IN>::new(layer);
+    layer.total_grad_mut().iter_mut().for_each(|val| {*val = 1.0;});
+
+    layer.add_weight_penalty(L1, L2);
+
+    let expected = |grad: f32, weight: f32| -> f32 {
+        let sign = if weight == 0.0 {0.0} else {weight.signum()};
+
+        grad + ((L1 * sign) + (L2 * weight))
+    };
+
+    let weights = layer.clone().drop();
+
+    // Bias is not penalized.
+    assert!(layer.total_grad().bias().iter().all(|val| *val == 1.0));
+
+    for (grad, weight) in layer.total_grad().input_weights().as_flattened()
+        .iter().zip(weights.weights().input_weights().as_flattened().iter())
+    {
+        assert_eq!(*grad, expected(1.0, *weight));
+    }
+
+    for (grad, weight) in layer.total_grad().state_weights().unwrap()
+        .as_flattened().iter().zip(
+            weights.weights().state_weights().unwrap().as_flattened().iter()
+        )
+    {
+        assert_eq!(*grad, expected(1.0, *weight));
+    }
+
+    // L2 penalty pulls weights to 0.
+    layer.total_grad_mut().clear();
+    layer.set_optimizer(Optimizer::Sgd);
+    layer.add_weight_penalty(0.0, 0.5);
+    layer.update(1.0);
+
+    let decayed = layer.drop();
+
+    for (after, before) in decayed.weights().input_weights().as_flattened()
+        .iter().zip(weights.weights().input_weights().as_flattened().iter())
+    {
+        assert_eq!(*after, *before * 0.5);
+    }
+}
+
+#[test]
+fn early_stopping_test_1() {
+    const LOSSES: [f32; 8] = [5.0, 4.0, 4.5, 3.0, 3.0, 2.999, f32::NAN, 3.5];
+
+    let mut early_stopping = EarlyStopping::<Vec<f32>>::new(3, 0.01);
+
+    assert_eq!(early_stopping.best(), None);
+    assert!(!early_stopping.restore(&mut vec![]));
+
+    let mut stopped_at = 0;
+
+    for (i, loss) in LOSSES.iter().enumerate() {
+        // The model is just a record of epoch.
+        let model = vec![i as f32; 4];
+
+        if early_stopping.check(*loss, &model) {
+            stopped_at = i + 1;
+            break;
+        }
+    }
+
+    // 3.0 at 4th epoch is the best. 2.999 is not improvement by min_delta.
+    assert_eq!(stopped_at, 7);
+    assert_eq!(early_stopping.epoch(), 7);
+    assert_eq!(early_stopping.best_epoch(), 4);
+    assert_eq!(early_stopping.best_loss(), 3.0);
+
+    let mut model = vec![0.0; 4];
+    assert!(early_stopping.restore(&mut model));
+    assert_eq!(model, vec![3.0; 4]);
+
+    early_stopping.reset();
+
+    assert_eq!(early_stopping.epoch(), 0);
+    assert_eq!(early_stopping.best(), None);
+    assert_eq!(early_stopping.best_loss(), f32::INFINITY);
+}
+
+#[test]
+fn layer_norm_test_1() {
+    const N: usize = 8;
+    const H: f32 = 1.0e-2;
+
+    let mut rng = ChobitRand::new("layer_norm_test_1".as_bytes());
+
+    let mut input = MathVec::<N>::new();
+    rand_math_vec(&mut rng, &mut input);
+
+    // Normalized by default.
+    let layer_norm = LayerNorm::<N>::new();
+    let mut output = MathVec::<N>::new();
+    layer_norm.calc(&input, &mut output);
+
+    let mean = output.iter().sum::<f32>() / (N as f32);
+    let var = output.iter().map(|x| (x - mean) * (x - mean)).sum::<f32>()
+        / (N as f32);
+
+    assert!(mean.abs() < 1.0e-5);
+    assert!((var - 1.0).abs() < 1.0e-3);
+
+    // Same output as calc().
+    let mut layer_norm = LayerNorm::<N>::new();
+    rand_weights(&mut rng, layer_norm.weights_mut());
+    layer_norm.calc(&input, &mut output);
+
+    let mut ml_layer_norm = MlLayerNorm::<N>::new(layer_norm.clone());
+    let mut cache = MlLayerNormCache::<N>::new();
+    ml_layer_norm.ready(&input, &mut cache);
+
+    assert_eq!(cache.output(), &output);
+
+    // Numerical gradient of loss = Σ(c_i * y_i).
+    let mut coef = MathVec::<N>::new();
+    rand_math_vec(&mut rng, &mut coef);
+
+    let loss = |layer_norm: &LayerNorm<N>, input: &MathVec<N>| -> f32 {
+        let mut output = MathVec::<N>::new();
+        layer_norm.calc(input, &mut output);
+
+        output.iter().zip(coef.iter()).map(|(y, c)| y * c).sum::<f32>()
+    };
+
+    let mut input_error = MathVec::<N>::new();
+    ml_layer_norm.study(&coef, &cache, &mut input_error);
+
+    for i in 0..N {
+        let mut plus = input.clone();
+        plus[i] += H;
+        let mut minus = input.clone();
+        minus[i] -= H;
+
+        let numerical =
+            (loss(&layer_norm, &plus) - loss(&layer_norm, &minus)) / (2.0 * H);
+
+        assert!(
+            (numerical - input_error[i]).abs() < 1.0e-2,
+            "{} : {} != {}",
+            i,
+            numerical,
+            input_error[i]
+        );
+    }
+
+    for i in 0..N {
+        let mut plus = layer_norm.clone();
+        plus.weights_mut().input_weights_mut()[i][0] += H;
+        let mut minus = layer_norm.clone();
+        minus.weights_mut().input_weights_mut()[i][0] -= H;
+
+        let numerical =
+            (loss(&plus, &input) - loss(&minus, &input)) / (2.0 * H);
+        let grad = ml_layer_norm.total_grad().input_weights()[i][0];
+
+        assert!((numerical - grad).abs() < 1.0e-2);
+        assert_eq!(ml_layer_norm.total_grad().bias()[i], coef[i]);
+    }
+
+    // Update.
+    ml_layer_norm.set_optimizer(Optimizer::Sgd);
+    ml_layer_norm.update(0.1);
+
+    let updated = ml_layer_norm.drop();
+
+    assert_ne!(updated, layer_norm);
+}
+
+#[test]
+fn layer_norm_test_2() {
+    const OUT: usize = 4;
+    const IN: usize = 8;
+
+    let mut rng = ChobitRand::new("layer_norm_test_2".as_bytes());
+
+    let mut layer = Layer::<OUT, IN>::new(Activation::SoftSign, false);
+    rand_weights(&mut rng, layer.mut_weights());
+
+    let network = LayerNorm::<IN>::new().stack(layer);
+
+    let mut input = MathVec::<IN>::new();
+    rand_math_vec(&mut rng, &mut input);
+
+    let mut train_out = MathVec::<OUT>::new();
+    rand_math_vec(&mut rng, &mut train_out);
+    train_out *= 0.5;
+
+    let mut ml_network = network.into_ml();
+    let mut cache = ml_network.new_cache();
+    let mut output_error = MathVec::<OUT>::new();
+    let mut input_error = MathVec::<IN>::new();
+
+    let mut first_error = 0.0;
+
+    for epoch in 0..200 {
+        ml_network.clear_study_data();
+
+        ml_network.ready(&input, &mut cache);
+        cache.calc_output_error(&train_out, &mut output_error);
+        ml_network.study(&output_error, &cache, &mut input_error);
+
+        if epoch == 0 {
+            first_error = output_error.iter().map(|x| x.abs()).sum::<f32>();
+        }
+
+        ml_network.update(0.01);
+    }
+
+    let network = ml_network.drop();
+    let mut buffer = network.new_buffer();
+    let mut output = MathVec::<OUT>::new();
+    network.calc(&input, &mut output, &mut buffer);
+
+    let last_error = output.iter().zip(train_out.iter()).map(
+        |(y, t)| (y - t).abs()
+    ).sum::<f32>();
+
+    assert!(last_error < first_error * 0.1, "{} {}", first_error, last_error);
+}
+
+#[test]
+fn batch_norm_test_1() {
+    const N: usize = 4;
+    const BATCH: usize = 6;
+    const H: f32 = 1.0e-2;
+
+    let mut rng = ChobitRand::new("batch_norm_test_1".as_bytes());
+
+    let mut train_in = vec![MathVec::<N>::new(); BATCH];
+    train_in.iter_mut().for_each(|input| {rand_math_vec(&mut rng, input)});
+
+    // Normalized over the batch by default.
+    let mut ml_batch_norm = MlBatchNorm::<N>::new(BatchNorm::<N>::new());
+    let mut cache = MlBatchNormCache::<N>::new(2);
+    ml_batch_norm.ready(&train_in, &mut cache);
+
+    assert_eq!(cache.output().len(), BATCH);
+
+    for i in 0..N {
+        let mean = cache.output().iter().map(|y| y[i]).sum::<f32>()
+            / (BATCH as f32);
+        let var = cache.output().iter().map(|y| (y[i] - mean) * (y[i] - mean))
+            .sum::<f32>() / (BATCH as f32);
+
+        assert!(mean.abs() < 1.0e-5);
+        assert!((var - 1.0).abs() < 1.0e-3);
+
+        // Running statistics.
+        let batch_mean = train_in.iter().map(|x| x[i]).sum::<f32>()
+            / (BATCH as f32);
+        let batch_var = train_in.iter().map(
+            |x| (x[i] - batch_mean) * (x[i] - batch_mean)
+        ).sum::<f32>() / ((BATCH - 1) as f32);
+
+        assert!(
+            (ml_batch_norm.running_mean()[i] - (0.1 * batch_mean)).abs()
+                < 1.0e-6
+        );
+        assert!(
+            (ml_batch_norm.running_var()[i] - (0.9 + (0.1 * batch_var))).abs()
+                < 1.0e-5
+        );
+    }
+
+    // Numerical gradient of loss = Σ(c_bi * y_bi).
+    let mut batch_norm = BatchNorm::<N>::new();
+    rand_weights(&mut rng, batch_norm.weights_mut());
+
+    let mut coef = vec![MathVec::<N>::new(); BATCH];
+    coef.iter_mut().for_each(|c| {rand_math_vec(&mut rng, c)});
+
+    let loss = |batch_norm: &BatchNorm<N>, train_in: &[MathVec<N>]| -> f32 {
+        let mut ml_batch_norm = MlBatchNorm::<N>::new(batch_norm.clone());
+        let mut cache = MlBatchNormCache::<N>::new(BATCH);
+        ml_batch_norm.ready(train_in, &mut cache);
+
+        cache.output().iter().zip(coef.iter()).map(|(y, c)| {
+            y.iter().zip(c.iter()).map(|(y, c)| y * c).sum::<f32>()
+        }).sum::<f32>()
+    };
+
+    let mut ml_batch_norm = MlBatchNorm::<N>::new(batch_norm.clone());
+    ml_batch_norm.ready(&train_in, &mut cache);
+
+    let mut input_error = vec![MathVec::<N>::new(); BATCH];
+    ml_batch_norm.study(&coef, &cache, &mut input_error);
+
+    for b in 0..BATCH {
+        for i in 0..N {
+            let mut plus = train_in.clone();
+            plus[b][i] += H;
+            let mut minus = train_in.clone();
+            minus[b][i] -= H;
+
+            let numerical = (loss(&batch_norm, &plus)
+                - loss(&batch_norm, &minus)) / (2.0 * H);
+
+            assert!(
+                (numerical - input_error[b][i]).abs() < 1.0e-2,
+                "({}, {}) : {} != {}",
+                b,
+                i,
+                numerical,
+                input_error[b][i]
+            );
+        }
+    }
+
+    for i in 0..N {
+        let mut plus = batch_norm.clone();
+        plus.weights_mut().input_weights_mut()[i][0] += H;
+        let mut minus = batch_norm.clone();
+        minus.weights_mut().input_weights_mut()[i][0] -= H;
+
+        let numerical =
+            (loss(&plus, &train_in) - loss(&minus, &train_in)) / (2.0 * H);
+        let grad = ml_batch_norm.total_grad().input_weights()[i][0];
+
+        assert!((numerical - grad).abs() < 1.0e-2);
+    }
+
+    // calc() uses running statistics.
+    let batch_norm = ml_batch_norm.drop();
+    let mut output = MathVec::<N>::new();
+    batch_norm.calc(&train_in[0], &mut output);
+
+    for i in 0..N {
+        let gain = batch_norm.weights().input_weights()[i][0];
+        let shift = batch_norm.weights().bias()[i];
+        let expected = (gain * (train_in[0][i] - batch_norm.running_mean()[i])
+            / (batch_norm.running_var()[i] + batch_norm.epsilon()).sqrt())
+            + shift;
+
+        assert!((output[i] - expected).abs() < 1.0e-4);
+    }
+}
+
+fn gen_gru<const OUT: usize, const IN: usize>(
+    rng: &mut ChobitRand
+) -> Gru<OUT, IN> {
+    let mut ret = Gru::<OUT, IN>::new();
+
+    rand_weights(rng, ret.main_layer_mut().mut_weights());
+    rand_weights(rng, ret.z_gate_mut().mut_weights());
+    rand_weights(rng, ret.r_gate_mut().mut_weights());
+
+    ret
+}
+
+#[test]
+fn gru_test_1() {
+    const OUT: usize = 4;
+    const IN: usize = 3;
+    const LEN: usize = 3;
+    const H: f32 = 1.0e-2;
+
+    let mut rng = ChobitRand::new("gru_test_1".as_bytes());
+
+    let gru = gen_gru::<OUT, IN>(&mut rng);
+
+    let mut data = vec![MathVec::<IN>::new(); LEN];
+    data.iter_mut().for_each(|vec| rand_math_vec(&mut rng, vec));
+
+    let mut first_state = MathVec::<OUT>::new();
+    rand_math_vec(&mut rng, &mut first_state);
+
+    let mut coef = MathVec::<OUT>::new();
+    rand_math_vec(&mut rng, &mut coef);
+
+    // Loss = Σ(c_i * y_i) of the last output.
+    let loss = |gru: &Gru<OUT, IN>, first_state: &MathVec<OUT>| -> f32 {
+        let mut prev_state = first_state.clone();
+        let mut state = MathVec::<OUT>::new();
+        let mut output = MathVec::<OUT>::new();
+        let mut tmpbuf = MathVec::<OUT>::new();
+
+        for i in 0..(LEN - 1) {
+            gru.calc_state(&data[i], &prev_state, &mut state, &mut tmpbuf);
+            prev_state.copy_from(&state);
+        }
+
+        gru.calc(
+            &data[LEN - 1],
+            &prev_state,
+            &mut output,
+            &mut state,
+            &mut tmpbuf
+        );
+
+        // Output of Gru is its state.
+        assert_eq!(output, state);
+
+        output.iter().zip(coef.iter()).map(|(y, c)| y * c).sum::<f32>()
+    };
+
+    let mut ml_gru = MlGru::<OUT, IN>::new(gru.clone());
+    let mut state_caches = vec![MlGruStateCache::<OUT, IN>::new(); LEN];
+    let mut output_cache = MlGruOutputCache::<OUT, IN>::new();
+
+    let mut prev_state = first_state.clone();
+    for i in 0..LEN {
+        ml_gru.ready_state_cache(&data[i], &prev_state, &mut state_caches[i]);
+        prev_state.copy_from(state_caches[i].state());
+    }
+
+    ml_gru.ready_output_cache(&state_caches[LEN - 1], &mut output_cache);
+
+    // Same output as calc().
+    let mut state = first_state.clone();
+    let mut output = MathVec::<OUT>::new();
+    let mut tmpbuf = MathVec::<OUT>::new();
+    for i in 0..LEN {
+        prev_state.copy_from(&state);
+        gru.calc(&data[i], &prev_state, &mut output, &mut state, &mut tmpbuf);
+    }
+
+    assert_eq!(output_cache.output(), &output);
+
+    let mut state_error = MathVec::<OUT>::new();
+    let mut input_error = MathVec::<IN>::new();
+    let mut prev_state_error = MathVec::<OUT>::new();
+
+    ml_gru.study(
+        &coef,
+        &state_error,
+        &state_caches[LEN - 1],
+        &output_cache,
+        &mut input_error,
+        &mut prev_state_error
+    );
+    state_error.copy_from(&prev_state_error);
+
+    for i in (0..(LEN - 1)).rev() {
+        ml_gru.study_state(
+            &state_error,
+            &state_caches[i],
+            &mut input_error,
+            &mut prev_state_error
+        );
+        state_error.copy_from(&prev_state_error);
+    }
+
+    // Gradient of first state.
+    for i in 0..OUT {
+        let mut plus = first_state.clone();
+        plus[i] += H;
+        let mut minus = first_state.clone();
+        minus[i] -= H;
+
+        let numerical = (loss(&gru, &plus) - loss(&gru, &minus)) / (2.0 * H);
+
+        assert!(
+            (numerical - state_error[i]).abs() < 1.0e-2,
+            "{} : {} != {}",
+            i,
+            numerical,
+            state_error[i]
+        );
+    }
+
+    // Gradient of weights.
+    let mut grads = Vec::<f32>::new();
+    ml_gru.for_each_total_grad(|val| {grads.push(*val)});
+
+    let add = |gru: &Gru<OUT, IN>, index: usize, delta: f32| -> Gru<OUT, IN> {
+        let mut ret = gru.clone();
+        let mut count: usize = 0;
+
+        ret.for_each_weight_mut(|val| {
+            if count == index {
+                *val += delta;
+            }
+
+            count += 1;
+        });
+
+        ret
+    };
+
+    for (index, grad) in grads.iter().enumerate() {
+        let numerical = (
+            loss(&add(&gru, index, H), &first_state)
+                - loss(&add(&gru, index, -H), &first_state)
+        ) / (2.0 * H);
+
+        assert!(
+            (numerical - grad).abs() < 1.0e-2,
+            "{} : {} != {}",
+            index,
+            numerical,
+            grad
+        );
+    }
+}
+
+#[test]
+fn gru_test_2() {
+    const OUT: usize = 11;
+    const IN: usize = 5;
+
+    let mut rng = ChobitRand::new("gru_test_2".as_bytes());
+
+    let gru_1 = gen_gru::<OUT, IN>(&mut rng);
+    let mut gru_2 = Gru::<OUT, IN>::new();
+
+    assert_ne!(gru_1, gru_2);
+
+    let mut vec = Vec::<f32>::new();
+    gru_1.for_each_weight(|val| {vec.push(*val)});
+
+    let mut vec_iter = vec.iter();
+    gru_2.for_each_weight_mut(|val| {*val = *vec_iter.next().unwrap()});
+
+    assert_eq!(gru_1, gru_2);
+
+    // Save and load.
+    let mut buffer = Vec::<u8>::new();
+    gru_1.save(&mut buffer);
+
+    assert_eq!(Gru::<OUT, IN>::load(&buffer).unwrap(), gru_1);
+    assert!(Gru::<IN, OUT>::load(&buffer).is_err());
+    assert!(Lstm::<OUT, IN>::load(&buffer).is_err());
+
+    // Total grad and checkpoint.
+    let mut ml_gru_1 = MlGru::<OUT, IN>::new(gru_1.clone());
+    ml_gru_1.for_each_total_grad_mut(|val| {*val = rand_num(&mut rng)});
+
+    let mut ml_gru_2 = MlGru::<OUT, IN>::new(gru_1.clone());
+
+    assert_ne!(ml_gru_1, ml_gru_2);
+
+    let mut vec = Vec::<f32>::new();
+    ml_gru_1.for_each_total_grad(|val| {vec.push(*val)});
+
+    let mut vec_iter = vec.iter();
+    ml_gru_2.for_each_total_grad_mut(|val| {
+        *val = *vec_iter.next().unwrap()
+    });
+
+    assert_eq!(ml_gru_1, ml_gru_2);
+
+    let mut buffer = Vec::<u8>::new();
+    ml_gru_1.save_checkpoint(&mut buffer);
+
+    assert_eq!(MlGru::<OUT, IN>::load_checkpoint(&buffer).unwrap(), ml_gru_1);
+    assert!(ml_gru_1.check_finite().is_ok());
+}
+
+#[test]
+fn gru_test_3() {
+    const OUT: usize = 4;
+    const MIDDLE: usize = 8;
+    const IN: usize = 3;
+    const LEN: usize = 5;
+
+    let mut rng = ChobitRand::new("gru_test_3".as_bytes());
+
+    let mut data = vec![MathVec::<IN>::new(); LEN];
+    data.iter_mut().for_each(|vec| rand_math_vec(&mut rng, vec));
+    let prev_state = MathVec::<MIDDLE>::new();
+
+    // Encoder.
+    let mut encoder =
+        ChobitGruEncoder::<OUT, MIDDLE, IN>::new(Activation::SoftSign);
+    encoder.init_weights(
+        Initializer::XavierUniform,
+        || rng.next_f64() as f32
+    );
+
+    let mut output = MathVec::<OUT>::new();
+    data.iter().for_each(|data_one| encoder.input_next(data_one));
+    encoder.output(&mut output);
+
+    let mut buffer = Vec::<u8>::new();
+    encoder.save(&mut buffer);
+    assert_eq!(
+        ChobitGruEncoder::<OUT, MIDDLE, IN>::load(&buffer).unwrap().gru(),
+        encoder.gru()
+    );
+    assert!(ChobitEncoder::<OUT, MIDDLE, IN>::load(&buffer).is_err());
+
+    let mut ml_encoder = ChobitMlGruEncoder::<OUT, MIDDLE, IN>::new(encoder);
+    let mut cache = MlGruEncoderCache::<OUT, MIDDLE, IN>::new(LEN);
+    ml_encoder.ready(&data, &prev_state, &mut cache);
+
+    assert_eq!(cache.output(), &output);
+
+    // Decoder.
+    let mut decoder =
+        ChobitGruDecoder::<OUT, MIDDLE, IN>::new(Activation::SoftSign);
+    decoder.init_weights(
+        Initializer::XavierUniform,
+        || rng.next_f64() as f32
+    );
+
+    let mut outputs = vec![MathVec::<OUT>::new(); LEN];
+    decoder.input_mut().copy_from(&data[0]);
+    outputs.iter_mut().for_each(|output| decoder.output_next(output));
+
+    let mut ml_decoder = ChobitMlGruDecoder::<OUT, MIDDLE, IN>::new(decoder);
+    let mut cache = MlGruDecoderCache::<OUT, MIDDLE, IN>::new(LEN);
+    ml_decoder.ready(&data[0], &prev_state, LEN, &mut cache);
+
+    cache.caches().iter().zip(outputs.iter()).for_each(
+        |((_, _, output_layer_cache), output)| {
+            assert_eq!(output_layer_cache.output(), output);
+        }
+    );
+
+    // Seq2Seq AI.
+    let mut ai = ChobitGruSeqAi::<OUT, MIDDLE, IN>::new(Activation::SoftSign);
+    ai.init_weights(
+        Initializer::XavierUniform,
+        || rng.next_f64() as f32
+    );
+
+    data.iter().for_each(|data_one| ai.input_next(data_one));
+    outputs.iter_mut().for_each(|output| ai.output_next(output));
+
+    let mut ml_ai = ChobitMlGruSeqAi::<OUT, MIDDLE, IN>::new(ai);
+    let mut cache = MlGruSeqAiCache::<OUT, MIDDLE, IN>::new(LEN, LEN);
+    ml_ai.ready(&data, &prev_state, LEN, &mut cache);
+
+    cache.dec_caches().iter().zip(outputs.iter()).for_each(
+        |((_, _, output_layer_cache), output)| {
+            assert_eq!(output_layer_cache.output(), output);
+        }
+    );
+
+    // Training decreases error.
+    let mut train_out = vec![MathVec::<OUT>::new(); LEN];
+    train_out.iter_mut().for_each(|vec| {
+        rand_math_vec(&mut rng, vec);
+        *vec *= 0.5;
+    });
+
+    let mut output_error = vec![MathVec::<OUT>::new(); LEN];
+    let mut input_error = vec![MathVec::<IN>::new(); LEN];
+    let mut prev_state_error = MathVec::<MIDDLE>::new();
+
+    let mut first_loss = 0.0;
+    let mut last_loss = 0.0;
+
+    for epoch in 0..500 {
+        ml_ai.clear_study_data();
+
+        ml_ai.ready(&data, &prev_state, LEN, &mut cache);
+        cache.calc_output_error(&train_out, &mut output_error);
+
+        let loss = output_error.iter().map(
+            |error| error.iter().map(|x| x * x).sum::<f32>()
+        ).sum::<f32>();
+
+        if epoch == 0 {
+            first_loss = loss;
+        }
+
+        last_loss = loss;
+
+        ml_ai.study(
+            &output_error,
+            &cache,
+            &mut input_error,
+            &mut prev_state_error
+        );
+
+        ml_ai.update(0.01);
+    }
+
+    assert!(last_loss < first_loss * 0.5, "{} {}", first_loss, last_loss);
+}
+
+fn check_numerical_grad<F>(grads: &[f32], weights: &[f32], mut loss: F)
+where F: FnMut(&[f32]) -> f32 {
+    const H: f32 = 1.0e-2;
+
+    assert_eq!(grads.len(), weights.len());
+
+    let mut weights = weights.to_vec();
+
+    for i in 0..weights.len() {
+        let original = weights[i];
+
+        weights[i] = original + H;
+        let plus = loss(&weights);
+        weights[i] = original - H;
+        let minus = loss(&weights);
+        weights[i] = original;
+
+        let numerical = (plus - minus) / (2.0 * H);
+
+        assert!(
+            (numerical - grads[i]).abs() < 1.0e-2,
+            "{} : {} != {}",
+            i,
+            numerical,
+            grads[i]
+        );
+    }
+}
+
+#[test]
+fn stacked_encoder_test_1() {
+    const OUT: usize = 3;
+    const MIDDLE: usize = 4;
+    const IN: usize = 2;
+    const DEPTH: usize = 3;
+    const LEN: usize = 4;
+
+    let mut rng = ChobitRand::new("stacked_encoder_test_1".as_bytes());
+
+    let mut encoder = ChobitStackedEncoder::<OUT, MIDDLE, IN>::new(
+        DEPTH,
+        Activation::SoftSign
+    );
+    encoder.for_each_weight_mut(|val| {*val = rand_num(&mut rng)});
+
+    assert_eq!(encoder.depth(), DEPTH);
+    assert_eq!(encoder.upper_layers().len(), DEPTH - 1);
+
+    let mut data = vec![MathVec::<IN>::new(); LEN];
+    data.iter_mut().for_each(|vec| rand_math_vec(&mut rng, vec));
+
+    let mut prev_states = vec![MathVec::<MIDDLE>::new(); DEPTH];
+    prev_states.iter_mut().for_each(|vec| rand_math_vec(&mut rng, vec));
+
+    let mut coef = MathVec::<OUT>::new();
+    rand_math_vec(&mut rng, &mut coef);
+
+    // Loss = Σ(c_i * y_i).
+    let loss = |
+        encoder: &mut ChobitStackedEncoder<OUT, MIDDLE, IN>,
+        data: &[MathVec<IN>],
+        prev_states: &[MathVec<MIDDLE>]
+    | -> f32 {
+        encoder.states_mut().iter_mut().zip(
+            prev_states.iter()
+        ).for_each(|(state, prev_state)| state.copy_from(prev_state));
+
+        data.iter().for_each(|data_one| encoder.input_next(data_one));
+
+        let mut output = MathVec::<OUT>::new();
+        encoder.output(&mut output);
+
+        output.iter().zip(coef.iter()).map(|(y, c)| y * c).sum::<f32>()
+    };
+
+    let mut output = MathVec::<OUT>::new();
+    let base_loss = loss(&mut encoder, &data, &prev_states);
+    encoder.output(&mut output);
+
+    // Save and load.
+    let mut buffer = Vec::<u8>::new();
+    encoder.save(&mut buffer);
+
+    let mut loaded = ChobitStackedEncoder::<OUT, MIDDLE, IN>::load(&buffer)
+        .unwrap();
+    assert_eq!(loss(&mut loaded, &data, &prev_states), base_loss);
+    assert!(ChobitEncoder::<OUT, MIDDLE, IN>::load(&buffer).is_err());
+
+    // Same output as inference.
+    let mut ml_encoder = ChobitMlStackedEncoder::<OUT, MIDDLE, IN>::new(
+        encoder.clone()
+    );
+    let mut cache = MlStackedEncoderCache::<OUT, MIDDLE, IN>::new(DEPTH, 0);
+    ml_encoder.ready(&data, &prev_states, &mut cache);
+
+    assert_eq!(cache.output(), &output);
+    assert_eq!(cache.first_layer_caches().len(), LEN);
+    assert_eq!(cache.upper_layer_caches(DEPTH - 2).unwrap().len(), LEN);
+    assert!(cache.upper_layer_caches(DEPTH - 1).is_none());
+
+    let mut input_error = vec![MathVec::<IN>::new(); LEN];
+    let mut prev_state_errors = vec![MathVec::<MIDDLE>::new(); DEPTH];
+    ml_encoder.study(&coef, &cache, &mut input_error, &mut prev_state_errors);
+
+    // Gradient of weights.
+    let mut grads = Vec::<f32>::new();
+    ml_encoder.for_each_total_grad(|val| {grads.push(*val)});
+
+    let mut weights = Vec::<f32>::new();
+    encoder.for_each_weight(|val| {weights.push(*val)});
+
+    let mut tmp_encoder = encoder.clone();
+    check_numerical_grad(&grads, &weights, |weights| {
+        let mut iter = weights.iter();
+        tmp_encoder.for_each_weight_mut(|val| {*val = *iter.next().unwrap()});
+
+        loss(&mut tmp_encoder, &data, &prev_states)
+    });
+
+    // Gradient of input.
+    let flat_data: Vec<f32> = data.iter().flat_map(|vec| vec.iter().copied())
+        .collect();
+    let flat_error: Vec<f32> = input_error.iter().flat_map(
+        |vec| vec.iter().copied()
+    ).collect();
+
+    let mut tmp_data = data.clone();
+    check_numerical_grad(&flat_error, &flat_data, |flat_data| {
+        tmp_data.iter_mut().zip(
+            flat_data.chunks(IN)
+        ).for_each(|(vec, chunk)| vec.copy_from_slice(chunk));
+
+        loss(&mut tmp_encoder, &tmp_data, &prev_states)
+    });
+
+    // Gradient of previous states.
+    let flat_states: Vec<f32> = prev_states.iter().flat_map(
+        |vec| vec.iter().copied()
+    ).collect();
+    let flat_error: Vec<f32> = prev_state_errors.iter().flat_map(
+        |vec| vec.iter().copied()
+    ).collect();
+
+    let mut tmp_states = prev_states.clone();
+    check_numerical_grad(&flat_error, &flat_states, |flat_states| {
+        tmp_states.iter_mut().zip(
+            flat_states.chunks(MIDDLE)
+        ).for_each(|(vec, chunk)| vec.copy_from_slice(chunk));
+
+        loss(&mut tmp_encoder, &data, &tmp_states)
+    });
+
+    // Checkpoint.
+    let mut buffer = Vec::<u8>::new();
+    ml_encoder.save_checkpoint(&mut buffer);
+
+    let loaded =
+        ChobitMlStackedEncoder::<OUT, MIDDLE, IN>::load_checkpoint(&buffer)
+            .unwrap();
+    assert!(loaded.check_finite().is_ok());
+
+    let mut loaded_grads = Vec::<f32>::new();
+    loaded.for_each_total_grad(|val| {loaded_grads.push(*val)});
+    assert_eq!(loaded_grads, grads);
+
+    let mut loaded_weights = Vec::<f32>::new();
+    loaded.drop().for_each_weight(|val| {loaded_weights.push(*val)});
+    assert_eq!(loaded_weights, weights);
+
+    ml_encoder.update(0.1);
+    assert_ne!(ml_encoder.drop(), encoder);
+}
+
+#[test]
+fn bi_encoder_test_1() {
+    const OUT: usize = 3;
+    const CAT: usize = 8;
+    const MIDDLE: usize = 4;
+    const IN: usize = 2;
+    const LEN: usize = 4;
+
+    let mut rng = ChobitRand::new("bi_encoder_test_1".as_bytes());
+
+    let mut encoder =
+        ChobitBiEncoder::<OUT, CAT, MIDDLE, IN>::new(Activation::SoftSign);
+    encoder.for_each_weight_mut(|val| {*val = rand_num(&mut rng)});
+
+    let mut data = vec![MathVec::<IN>::new(); LEN];
+    data.iter_mut().for_each(|vec| rand_math_vec(&mut rng, vec));
+
+    let mut coef = MathVec::<OUT>::new();
+    rand_math_vec(&mut rng, &mut coef);
+
+    // Loss = Σ(c_i * y_i).
+    let loss = |
+        encoder: &mut ChobitBiEncoder<OUT, CAT, MIDDLE, IN>,
+        data: &[MathVec<IN>]
+    | -> f32 {
+        let mut output = MathVec::<OUT>::new();
+        encoder.calc(data, &mut output);
+
+        output.iter().zip(coef.iter()).map(|(y, c)| y * c).sum::<f32>()
+    };
+
+    // Direction matters.
+    let mut output = MathVec::<OUT>::new();
+    let mut reversed_output = MathVec::<OUT>::new();
+    let reversed: Vec<MathVec<IN>> = data.iter().rev().cloned().collect();
+    encoder.calc(&reversed, &mut reversed_output);
+    encoder.calc(&data, &mut output);
+
+    assert_ne!(output, reversed_output);
+
+    // Save and load.
+    let mut buffer = Vec::<u8>::new();
+    encoder.save(&mut buffer);
+
+    let mut loaded =
+        ChobitBiEncoder::<OUT, CAT, MIDDLE, IN>::load(&buffer).unwrap();
+    assert_eq!(loss(&mut loaded, &data), loss(&mut encoder, &data));
+    assert!(ChobitStackedEncoder::<OUT, MIDDLE, IN>::load(&buffer).is_err());
+
+    // Same output as inference.
+    let mut ml_encoder = ChobitMlBiEncoder::<OUT, CAT, MIDDLE, IN>::new(
+        encoder.clone()
+    );
+    let mut cache = MlBiEncoderCache::<OUT, CAT, MIDDLE, IN>::new(0);
+    ml_encoder.ready(&data, &mut cache);
+
+    assert_eq!(cache.output(), &output);
+
+    let mut input_error = vec![MathVec::<IN>::new(); LEN];
+    ml_encoder.study(&coef, &cache, &mut input_error);
+
+    // Gradient of weights.
+    let mut grads = Vec::<f32>::new();
+    ml_encoder.for_each_total_grad(|val| {grads.push(*val)});
+
+    let mut weights = Vec::<f32>::new();
+    encoder.for_each_weight(|val| {weights.push(*val)});
+
+    let mut tmp_encoder = encoder.clone();
+    check_numerical_grad(&grads, &weights, |weights| {
+        let mut iter = weights.iter();
+        tmp_encoder.for_each_weight_mut(|val| {*val = *iter.next().unwrap()});
+
+        loss(&mut tmp_encoder, &data)
+    });
+
+    // Gradient of input.
+    let flat_data: Vec<f32> = data.iter().flat_map(|vec| vec.iter().copied())
+        .collect();
+    let flat_error: Vec<f32> = input_error.iter().flat_map(
+        |vec| vec.iter().copied()
+    ).collect();
+
+    let mut tmp_data = data.clone();
+    check_numerical_grad(&flat_error, &flat_data, |flat_data| {
+        tmp_data.iter_mut().zip(
+            flat_data.chunks(IN)
+        ).for_each(|(vec, chunk)| vec.copy_from_slice(chunk));
+
+        loss(&mut tmp_encoder, &tmp_data)
+    });
+
+    // Checkpoint.
+    let mut buffer = Vec::<u8>::new();
+    ml_encoder.save_checkpoint(&mut buffer);
+
+    let loaded =
+        ChobitMlBiEncoder::<OUT, CAT, MIDDLE, IN>::load_checkpoint(&buffer)
+            .unwrap();
+    assert!(loaded.check_finite().is_ok());
+
+    let mut loaded_grads = Vec::<f32>::new();
+    loaded.for_each_total_grad(|val| {loaded_grads.push(*val)});
+    assert_eq!(loaded_grads, grads);
+
+    let mut loaded_weights = Vec::<f32>::new();
+    loaded.drop().for_each_weight(|val| {loaded_weights.push(*val)});
+    assert_eq!(loaded_weights, weights);
+
+    ml_encoder.update(0.1);
+    assert_ne!(ml_encoder.drop(), encoder);
+}
+
+#[test]
+fn attention_test_1() {
+    const N: usize = 4;
+    const LEN: usize = 5;
+
+    let mut rng = ChobitRand::new("attention_test_1".as_bytes());
+
+    let mut attention = Attention::<N>::new(Activation::SoftSign);
+    attention.for_each_weight_mut(|val| {*val = rand_num(&mut rng)});
+
+    let mut query = MathVec::<N>::new();
+    rand_math_vec(&mut rng, &mut query);
+
+    let mut memory = vec![MathVec::<N>::new(); LEN];
+    memory.iter_mut().for_each(|vec| rand_math_vec(&mut rng, vec));
+
+    let mut coef = MathVec::<N>::new();
+    rand_math_vec(&mut rng, &mut coef);
+
+    // Loss = Σ(c_i * y_i).
+    let loss = |
+        attention: &Attention<N>,
+        query: &MathVec<N>,
+        memory: &[MathVec<N>]
+    | -> f32 {
+        let mut weights = vec![0.0; memory.len()];
+        let mut context = MathVec::<N>::new();
+        let mut output = MathVec::<N>::new();
+
+        attention.calc(query, memory, &mut weights, &mut context, &mut output);
+
+        output.iter().zip(coef.iter()).map(|(y, c)| y * c).sum::<f32>()
+    };
+
+    let mut weights = vec![0.0; LEN];
+    let mut context = MathVec::<N>::new();
+    let mut output = MathVec::<N>::new();
+    attention.calc(&query, &memory, &mut weights, &mut context, &mut output);
+
+    assert!((weights.iter().sum::<f32>() - 1.0).abs() < 1.0e-5);
+    assert!(weights.iter().all(|weight| *weight > 0.0));
+
+    // Empty memory makes zero context.
+    let mut empty_output = MathVec::<N>::new();
+    attention.calc(&query, &[], &mut [], &mut context, &mut empty_output);
+    assert_eq!(context, MathVec::<N>::new());
+
+    // Same output as inference.
+    let mut ml_attention = MlAttention::<N>::new(attention.clone());
+    let mut cache = MlAttentionCache::<N>::new();
+    ml_attention.ready(&query, &memory, &mut cache);
+
+    assert_eq!(cache.output(), &output);
+    assert_eq!(cache.weights(), weights.as_slice());
+
+    let mut query_error = MathVec::<N>::new();
+    let mut memory_error = vec![MathVec::<N>::new(); LEN];
+    ml_attention.study(
+        &coef,
+        &memory,
+        &cache,
+        &mut query_error,
+        &mut memory_error
+    );
+
+    // Gradient of weights.
+    let mut grads = Vec::<f32>::new();
+    ml_attention.for_each_total_grad(|val| {grads.push(*val)});
+
+    let mut flat_weights = Vec::<f32>::new();
+    attention.for_each_weight(|val| {flat_weights.push(*val)});
+
+    let mut tmp_attention = attention.clone();
+    check_numerical_grad(&grads, &flat_weights, |flat_weights| {
+        let mut iter = flat_weights.iter();
+        tmp_attention.for_each_weight_mut(
+            |val| {*val = *iter.next().unwrap()}
+        );
+
+        loss(&tmp_attention, &query, &memory)
+    });
+
+    // Gradient of query.
+    let mut tmp_query = query.clone();
+    check_numerical_grad(&query_error, &query, |flat_query| {
+        tmp_query.copy_from_slice(flat_query);
+
+        loss(&attention, &tmp_query, &memory)
+    });
+
+    // Gradient of memory.
+    let flat_memory: Vec<f32> = memory.iter().flat_map(
+        |vec| vec.iter().copied()
+    ).collect();
+    let flat_error: Vec<f32> = memory_error.iter().flat_map(
+        |vec| vec.iter().copied()
+    ).collect();
+
+    let mut tmp_memory = memory.clone();
+    check_numerical_grad(&flat_error, &flat_memory, |flat_memory| {
+        tmp_memory.iter_mut().zip(
+            flat_memory.chunks(N)
+        ).for_each(|(vec, chunk)| vec.copy_from_slice(chunk));
+
+        loss(&attention, &query, &tmp_memory)
+    });
+
+    ml_attention.update(0.1);
+    assert_ne!(ml_attention.drop(), attention);
+}
+
+#[test]
+fn attention_seq_ai_test_1() {
+    const OUT: usize = 3;
+    const MIDDLE: usize = 4;
+    const IN: usize = 2;
+    const IN_LEN: usize = 4;
+    const OUT_LEN: usize = 3;
+
+    let mut rng = ChobitRand::new("attention_seq_ai_test_1".as_bytes());
+
+    let mut ai =
+        ChobitAttentionSeqAi::<OUT, MIDDLE, IN>::new(Activation::SoftSign);
+    ai.for_each_weight_mut(|val| {*val = rand_num(&mut rng)});
+
+    let mut data = vec![MathVec::<IN>::new(); IN_LEN];
+    data.iter_mut().for_each(|vec| rand_math_vec(&mut rng, vec));
+
+    let mut prev_state = MathVec::<MIDDLE>::new();
+    rand_math_vec(&mut rng, &mut prev_state);
+
+    let mut coef = vec![MathVec::<OUT>::new(); OUT_LEN];
+    coef.iter_mut().for_each(|vec| rand_math_vec(&mut rng, vec));
+
+    let calc = |
+        ai: &mut ChobitAttentionSeqAi<OUT, MIDDLE, IN>,
+        data: &[MathVec<IN>],
+        prev_state: &MathVec<MIDDLE>,
+        output: &mut [MathVec<OUT>]
+    | {
+        ai.clear_enc_outputs();
+        ai.state_mut().copy_from(prev_state);
+
+        data.iter().for_each(|data_one| ai.input_next(data_one));
+        output.iter_mut().for_each(|output_one| ai.output_next(output_one));
+    };
+
+    // Loss = Σ(c_ij * y_ij).
+    let loss = |
+        ai: &mut ChobitAttentionSeqAi<OUT, MIDDLE, IN>,
+        data: &[MathVec<IN>],
+        prev_state: &MathVec<MIDDLE>
+    | -> f32 {
+        let mut output = vec![MathVec::<OUT>::new(); OUT_LEN];
+        calc(ai, data, prev_state, &mut output);
+
+        output.iter().zip(coef.iter()).map(|(y, c)| {
+            y.iter().zip(c.iter()).map(|(y, c)| y * c).sum::<f32>()
+        }).sum::<f32>()
+    };
+
+    let mut output = vec![MathVec::<OUT>::new(); OUT_LEN];
+    calc(&mut ai, &data, &prev_state, &mut output);
+
+    assert_eq!(ai.enc_outputs().len(), IN_LEN);
+    assert_eq!(ai.attention_weights().len(), IN_LEN);
+
+    // Save and load.
+    let mut buffer = Vec::<u8>::new();
+    ai.save(&mut buffer);
+
+    let mut loaded =
+        ChobitAttentionSeqAi::<OUT, MIDDLE, IN>::load(&buffer).unwrap();
+    assert_eq!(
+        loss(&mut loaded, &data, &prev_state),
+        loss(&mut ai, &data, &prev_state)
+    );
+    assert!(ChobitSeqAi::<OUT, MIDDLE, IN>::load(&buffer).is_err());
+
+    // Same output as inference.
+    let mut ml_ai = ChobitMlAttentionSeqAi::<OUT, MIDDLE, IN>::new(ai.clone());
+    let mut cache = MlAttentionSeqAiCache::<OUT, MIDDLE, IN>::new(0, 0);
+    ml_ai.ready(&data, &prev_state, OUT_LEN, &mut cache);
+
+    assert_eq!(cache.enc_caches().len(), IN_LEN);
+    assert_eq!(cache.enc_outputs(), ai.enc_outputs());
+
+    let mut ml_output = Vec::<MathVec<OUT>>::new();
+    cache.for_each_output(|output_one| ml_output.push(output_one.clone()));
+    assert_eq!(ml_output, output);
+
+    let mut input_error = vec![MathVec::<IN>::new(); IN_LEN];
+    let mut prev_state_error = MathVec::<MIDDLE>::new();
+    ml_ai.study(&coef, &cache, &mut input_error, &mut prev_state_error);
+
+    // Gradient of weights.
+    let mut grads = Vec::<f32>::new();
+    ml_ai.for_each_total_grad(|val| {grads.push(*val)});
+
+    let mut weights = Vec::<f32>::new();
+    ai.for_each_weight(|val| {weights.push(*val)});
+
+    let mut tmp_ai = ai.clone();
+    check_numerical_grad(&grads, &weights, |weights| {
+        let mut iter = weights.iter();
+        tmp_ai.for_each_weight_mut(|val| {*val = *iter.next().unwrap()});
+
+        loss(&mut tmp_ai, &data, &prev_state)
+    });
+
+    // Gradient of input.
+    let flat_data: Vec<f32> = data.iter().flat_map(|vec| vec.iter().copied())
+        .collect();
+    let flat_error: Vec<f32> = input_error.iter().flat_map(
+        |vec| vec.iter().copied()
+    ).collect();
+
+    let mut tmp_data = data.clone();
+    check_numerical_grad(&flat_error, &flat_data, |flat_data| {
+        tmp_data.iter_mut().zip(
+            flat_data.chunks(IN)
+        ).for_each(|(vec, chunk)| vec.copy_from_slice(chunk));
+
+        loss(&mut tmp_ai, &tmp_data, &prev_state)
+    });
+
+    // Gradient of previous state.
+    let mut tmp_state = prev_state.clone();
+    check_numerical_grad(&prev_state_error, &prev_state, |flat_state| {
+        tmp_state.copy_from_slice(flat_state);
+
+        loss(&mut tmp_ai, &data, &tmp_state)
+    });
+
+    // Checkpoint.
+    let mut buffer = Vec::<u8>::new();
+    ml_ai.save_checkpoint(&mut buffer);
+
+    let loaded =
+        ChobitMlAttentionSeqAi::<OUT, MIDDLE, IN>::load_checkpoint(&buffer)
+            .unwrap();
+    assert!(loaded.check_finite().is_ok());
+
+    let mut loaded_grads = Vec::<f32>::new();
+    loaded.for_each_total_grad(|val| {loaded_grads.push(*val)});
+    assert_eq!(loaded_grads, grads);
+
+    let mut loaded_weights = Vec::<f32>::new();
+    loaded.drop().for_each_weight(|val| {loaded_weights.push(*val)});
+    assert_eq!(loaded_weights, weights);
+
+    ml_ai.update(0.1);
+    assert_ne!(ml_ai.drop(), ai);
+}
+
+#[test]
+fn positional_encoding_test() {
+    const N: usize = 8;
+
+    for position in [0usize, 1, 5, 30] {
+        let mut vec = MathVec::<N>::new();
+        vec.add_positional_encoding(position);
+
+        for i in 0..N {
+            let angle = (position as f32)
+                / 10000.0f32.powf(((i & !1) as f32) / (N as f32));
+            let expected = if (i & 1) == 0 {angle.sin()} else {angle.cos()};
+
+            assert!((vec[i] - expected).abs() < 1.0e-3);
+        }
+    }
+
+    // Added, not overwritten.
+    let mut vec = MathVec::<N>::new();
+    vec.as_mut_array().fill(1.0);
+    vec.add_positional_encoding(0);
+
+    assert!((vec[0] - 1.0).abs() < 1.0e-5);
+    assert!((vec[1] - 2.0).abs() < 1.0e-5);
+}
+
+#[test]
+fn transformer_block_test_1() {
+    const N: usize = 4;
+    const HEADS: usize = 2;
+    const FF: usize = 6;
+    const LEN: usize = 3;
+
+    let mut rng = ChobitRand::new("transformer_block_test_1".as_bytes());
+
+    let mut block =
+        ChobitTransformerBlock::<N, HEADS, FF>::new(Activation::SoftSign);
+    block.for_each_weight_mut(|val| {*val = rand_num(&mut rng)});
+
+    let mut data = vec![MathVec::<N>::new(); LEN];
+    data.iter_mut().enumerate().for_each(|(i, vec)| {
+        rand_math_vec(&mut rng, vec);
+        vec.add_positional_encoding(i);
+    });
+
+    let mut coef = vec![MathVec::<N>::new(); LEN];
+    coef.iter_mut().for_each(|vec| rand_math_vec(&mut rng, vec));
+
+    // Loss = Σ(c_ij * y_ij).
+    let loss = |
+        block: &mut ChobitTransformerBlock<N, HEADS, FF>,
+        data: &[MathVec<N>]
+    | -> f32 {
+        let mut output = vec![MathVec::<N>::new(); LEN];
+        block.calc(data, &mut output);
+
+        output.iter().zip(coef.iter()).map(|(y, c)| {
+            y.iter().zip(c.iter()).map(|(y, c)| y * c).sum::<f32>()
+        }).sum::<f32>()
+    };
+
+    let mut output = vec![MathVec::<N>::new(); LEN];
+    block.calc(&data, &mut output);
+
+    for position in 0..LEN {
+        for head in 0..HEADS {
+            let weights = block.attention_weights(position, head).unwrap();
+
+            assert_eq!(weights.len(), LEN);
+            assert!((weights.iter().sum::<f32>() - 1.0).abs() < 1.0e-5);
+        }
+    }
+    assert!(block.attention_weights(LEN, 0).is_none());
+    assert!(block.attention_weights(0, HEADS).is_none());
+
+    // Save and load.
+    let mut buffer = Vec::<u8>::new();
+    block.save(&mut buffer);
+
+    let mut loaded =
+        ChobitTransformerBlock::<N, HEADS, FF>::load(&buffer).unwrap();
+    assert_eq!(loss(&mut loaded, &data), loss(&mut block, &data));
+    assert!(ChobitTransformerBlock::<N, 1, FF>::load(&buffer).is_err());
+
+    // Same output as inference.
+    let mut ml_block =
+        ChobitMlTransformerBlock::<N, HEADS, FF>::new(block.clone());
+    let mut cache = MlTransformerBlockCache::<N, HEADS, FF>::new(0);
+    ml_block.ready(&data, &mut cache);
+
+    assert_eq!(cache.len(), LEN);
+    assert_eq!(
+        cache.attention_weights(1, 1),
+        block.attention_weights(1, 1)
+    );
+
+    let mut ml_output = Vec::<MathVec<N>>::new();
+    cache.for_each_output(|output_one| ml_output.push(output_one.clone()));
+    assert_eq!(ml_output, output);
+
+    let mut input_error = vec![MathVec::<N>::new(); LEN];
+    ml_block.study(&coef, &cache, &mut input_error);
+
+    // Gradient of weights.
+    let mut grads = Vec::<f32>::new();
+    ml_block.for_each_total_grad(|val| {grads.push(*val)});
+
+    let mut weights = Vec::<f32>::new();
+    block.for_each_weight(|val| {weights.push(*val)});
+
+    let mut tmp_block = block.clone();
+    check_numerical_grad(&grads, &weights, |weights| {
+        let mut iter = weights.iter();
+        tmp_block.for_each_weight_mut(|val| {*val = *iter.next().unwrap()});
+
+        loss(&mut tmp_block, &data)
+    });
+
+    // Gradient of input.
+    let flat_data: Vec<f32> = data.iter().flat_map(|vec| vec.iter().copied())
+        .collect();
+    let flat_error: Vec<f32> = input_error.iter().flat_map(
+        |vec| vec.iter().copied()
+    ).collect();
+
+    let mut tmp_data = data.clone();
+    check_numerical_grad(&flat_error, &flat_data, |flat_data| {
+        tmp_data.iter_mut().zip(
+            flat_data.chunks(N)
+        ).for_each(|(vec, chunk)| vec.copy_from_slice(chunk));
+
+        loss(&mut tmp_block, &tmp_data)
+    });
+
+    // Checkpoint.
+    let mut buffer = Vec::<u8>::new();
+    ml_block.save_checkpoint(&mut buffer);
+
+    let loaded =
+        ChobitMlTransformerBlock::<N, HEADS, FF>::load_checkpoint(&buffer)
+            .unwrap();
+    assert!(loaded.check_finite().is_ok());
+
+    let mut loaded_grads = Vec::<f32>::new();
+    loaded.for_each_total_grad(|val| {loaded_grads.push(*val)});
+    assert_eq!(loaded_grads, grads);
+
+    let mut loaded_weights = Vec::<f32>::new();
+    loaded.drop().for_each_weight(|val| {loaded_weights.push(*val)});
+    assert_eq!(loaded_weights, weights);
+
+    ml_block.update(0.1);
+    assert_ne!(ml_block.drop(), block);
+}
+
+#[test]
+fn conv1d_test_1() {
+    // 7 frames of 2 channels -> 3 frames of 3 channels.
+    const OUT: usize = 9;
+    const IN: usize = 14;
+    const OUT_CH: usize = 3;
+    const IN_CH: usize = 2;
+    const KERNEL: usize = 3;
+    const STRIDE: usize = 2;
+    const WINDOW: usize = 6;
+
+    type Conv = Conv1d<OUT, IN, OUT_CH, IN_CH, KERNEL, STRIDE, WINDOW>;
+
+    let mut rng = ChobitRand::new("conv1d_test_1".as_bytes());
+
+    let mut conv = Conv::new(Activation::SoftSign);
+    conv.for_each_weight_mut(|val| {*val = rand_num(&mut rng)});
+
+    assert_eq!(conv.output_len(), 3);
+
+    let mut count: usize = 0;
+    conv.for_each_weight(|_| {count += 1});
+    assert_eq!(count, OUT_CH * (WINDOW + 1));
+
+    let mut input = MathVec::<IN>::new();
+    rand_math_vec(&mut rng, &mut input);
+
+    let mut coef = MathVec::<OUT>::new();
+    rand_math_vec(&mut rng, &mut coef);
+
+    // Each output frame is the kernel applied to its window.
+    let mut output = MathVec::<OUT>::new();
+    conv.calc(&input, &mut output);
+
+    let mut window = MathVec::<WINDOW>::new();
+    let mut frame = MathVec::<OUT_CH>::new();
+
+    for t in 0..conv.output_len() {
+        let start = t * STRIDE * IN_CH;
+        window.copy_from_slice(&input[start..(start + WINDOW)]);
+        conv.kernel().calc(&window, None, &mut frame);
+
+        assert_eq!(&output[(t * OUT_CH)..((t + 1) * OUT_CH)], &frame[..]);
+    }
+
+    // Loss = Σ(c_i * y_i).
+    let loss = |conv: &Conv, input: &MathVec<IN>| -> f32 {
+        let mut output = MathVec::<OUT>::new();
+        conv.calc(input, &mut output);
+
+        output.iter().zip(coef.iter()).map(|(y, c)| y * c).sum::<f32>()
+    };
+
+    // Same output as inference.
+    let mut ml_conv = conv.clone().into_ml();
+    let mut cache = ml_conv.new_cache();
+    ml_conv.ready(&input, &mut cache);
+
+    assert_eq!(cache.output(), &output);
+    assert_eq!(cache.caches().len(), conv.output_len());
+
+    let mut input_error = MathVec::<IN>::new();
+    ml_conv.study(&coef, &cache, &mut input_error);
+
+    // Gradient of weights.
+    let mut grads = Vec::<f32>::new();
+    ml_conv.for_each_total_grad(|val| {grads.push(*val)});
+
+    let mut weights = Vec::<f32>::new();
+    conv.for_each_weight(|val| {weights.push(*val)});
+
+    check_numerical_grad(&grads, &weights, |weights| {
+        let mut conv = conv.clone();
+        conv.for_each_weight_mut(
+            {let mut iter = weights.iter(); move |val| {
+                *val = *iter.next().unwrap()
+            }}
+        );
+
+        loss(&conv, &input)
+    });
+
+    // Gradient of input.
+    check_numerical_grad(&input_error, &input, |input_vec| {
+        let mut input = MathVec::<IN>::new();
+        input.copy_from_slice(input_vec);
+
+        loss(&conv, &input)
+    });
+
+    // Output frames can be input to ChobitEncoder.
+    let mut encoder = ChobitEncoder::<2, 4, OUT_CH>::new(Activation::SoftSign);
+    encoder.for_each_weight_mut(|val| {*val = rand_num(&mut rng)});
+
+    output.chunks(OUT_CH).for_each(|frame_slice| {
+        frame.copy_from_slice(frame_slice);
+        encoder.input_next(&frame);
+    });
+
+    let mut encoded = MathVec::<2>::new();
+    encoder.output(&mut encoded);
+    assert!(encoded.iter().all(|val| val.is_finite()));
+
+    // Update.
+    ml_conv.update(0.1);
+    assert_ne!(ml_conv.drop(), conv);
+}
+
+#[test]
+fn pool1d_test_1() {
+    // 6 frames of 2 channels -> 3 frames of 2 channels.
+    const OUT: usize = 6;
+    const IN: usize = 12;
+    const CH: usize = 2;
+    const SIZE: usize = 2;
+    const STRIDE: usize = 2;
+
+    type Pool = Pool1d<OUT, IN, CH, SIZE, STRIDE>;
+
+    let mut input = MathVec::<IN>::new();
+    input.copy_from_slice(&[
+        1.0, -1.0,
+        3.0, -2.0,
+        -4.0, 5.0,
+        2.0, 6.0,
+        0.5, -7.0,
+        0.25, -8.0
+    ]);
+
+    let mut coef = MathVec::<OUT>::new();
+    coef.copy_from_slice(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+
+    for (pooling, expected, expected_error) in [
+        (
+            Pooling::Max,
+            [3.0, -1.0, 2.0, 6.0, 0.5, -7.0],
+            [0.0, 2.0, 1.0, 0.0, 0.0, 0.0, 3.0, 4.0, 5.0, 6.0, 0.0, 0.0]
+        ),
+        (
+            Pooling::Average,
+            [2.0, -1.5, -1.0, 5.5, 0.375, -7.5],
+            [0.5, 1.0, 0.5, 1.0, 1.5, 2.0, 1.5, 2.0, 2.5, 3.0, 2.5, 3.0]
+        )
+    ] {
+        let pool = Pool::new(pooling);
+        assert_eq!(pool.pooling(), pooling);
+        assert_eq!(pool.output_len(), 3);
+
+        let mut output = MathVec::<OUT>::new();
+        pool.calc(&input, &mut output);
+        assert_eq!(&output[..], &expected[..]);
+
+        let ml_pool = pool.clone().into_ml();
+        let mut cache = ml_pool.new_cache();
+        ml_pool.ready(&input, &mut cache);
+        assert_eq!(cache.output(), &output);
+
+        let mut input_error = MathVec::<IN>::new();
+        ml_pool.study(&coef, &cache, &mut input_error);
+        assert_eq!(&input_error[..], &expected_error[..]);
+
+        let mut count: usize = 0;
+        ml_pool.for_each_total_grad(|_| {count += 1});
+        assert_eq!(count, 0);
+
+        assert_eq!(ml_pool.drop(), pool);
+    }
+}
+
+#[test]
+fn conv1d_network_test_1() {
+    // 10 frames of 2 channels -> 4 frames of 3 channels
+    //     -> 3 frames of 3 channels -> 4
+    const OUT: usize = 4;
+    const IN: usize = 20;
+
+    type Net = StackedLayer<
+        OUT,
+        9,
+        IN,
+        StackedNetwork<
+            9,
+            12,
+            IN,
+            Conv1d<12, IN, 3, 2, 4, 2, 8>,
+            Pool1d<9, 12, 3, 2, 1>
+        >
+    >;
+
+    let mut rng = ChobitRand::new("conv1d_network_test_1".as_bytes());
+
+    let mut network: Net =
+        Conv1d::<12, IN, 3, 2, 4, 2, 8>::new(Activation::SoftSign)
+            .stack_network(Pool1d::<9, 12, 3, 2, 1>::new(Pooling::Average))
+            .stack(Layer::<OUT, 9>::new(Activation::Linear, false));
+
+    network.for_each_weight_mut(|val| {*val = rand_num(&mut rng)});
+
+    let mut count: usize = 0;
+    network.for_each_weight(|_| {count += 1});
+    assert_eq!(count, (3 * (8 + 1)) + (OUT * (9 + 1)));
+
+    let mut input = MathVec::<IN>::new();
+    rand_math_vec(&mut rng, &mut input);
+
+    let mut coef = MathVec::<OUT>::new();
+    rand_math_vec(&mut rng, &mut coef);
+
+    let mut output = MathVec::<OUT>::new();
+    let mut buffer = network.new_buffer();
+    network.calc(&input, &mut output, &mut buffer);
+
+    // Same output as inference.
+    let mut ml_network = network.clone().into_ml();
+    let mut cache = ml_network.new_cache();
+    ml_network.ready(&input, &mut cache);
+    assert_eq!(cache.output(), &output);
+
+    let mut input_error = MathVec::<IN>::new();
+    ml_network.study(&coef, &cache, &mut input_error);
+
+    // Loss = Σ(c_i * y_i).
+    let loss = |network: &Net, input: &MathVec<IN>| -> f32 {
+        let mut output = MathVec::<OUT>::new();
+        let mut buffer = network.new_buffer();
+        network.calc(input, &mut output, &mut buffer);
+
+        output.iter().zip(coef.iter()).map(|(y, c)| y * c).sum::<f32>()
+    };
+
+    // Gradient of weights.
+    let mut grads = Vec::<f32>::new();
+    ml_network.for_each_total_grad(|val| {grads.push(*val)});
+
+    let mut weights = Vec::<f32>::new();
+    network.for_each_weight(|val| {weights.push(*val)});
+
+    check_numerical_grad(&grads, &weights, |weights| {
+        let mut network = network.clone();
+        network.for_each_weight_mut(
+            {let mut iter = weights.iter(); move |val| {
+                *val = *iter.next().unwrap()
+            }}
+        );
+
+        loss(&network, &input)
+    });
+
+    // Gradient of input.
+    check_numerical_grad(&input_error, &input, |input_vec| {
+        let mut input = MathVec::<IN>::new();
+        input.copy_from_slice(input_vec);
+
+        loss(&network, &input)
+    });
+
+    ml_network.update(0.1);
+    assert_ne!(ml_network.drop(), network);
+}
+
+#[test]
+fn embedding_test_1() {
+    const VOCAB: usize = 6;
+    const DIM: usize = 4;
+    const OUT: usize = 3;
+
+    let mut rng = ChobitRand::new("embedding_test_1".as_bytes());
+
+    let mut embedding = Embedding::<VOCAB, DIM>::new();
+    embedding.init_weights(Initializer::XavierNormal, || rng.next_f64() as f32);
+
+    let mut layer = Layer::<OUT, DIM>::new(Activation::SoftSign, false);
+    rand_weights(&mut rng, layer.mut_weights());
+
+    let ids = [4usize, 1, 4, 2];
+
+    let mut output = MathVec::<DIM>::new();
+    embedding.calc(3, &mut output);
+    assert_eq!(&output[..], embedding.row(3).unwrap());
+
+    embedding.calc(VOCAB, &mut output);
+    assert!(output.iter().all(|val| *val == 0.0));
+    assert!(embedding.row(VOCAB).is_none());
+
+    let mut coef = MathVec::<OUT>::new();
+    rand_math_vec(&mut rng, &mut coef);
+
+    // Loss = Σ(c_i * y_i) for each token.
+    let loss = |embedding: &Embedding<VOCAB, DIM>| -> f32 {
+        let mut input = MathVec::<DIM>::new();
+        let mut output = MathVec::<OUT>::new();
+
+        ids.iter().map(|id| {
+            embedding.calc(*id, &mut input);
+            layer.calc(&input, None, &mut output);
+
+            output.iter().zip(coef.iter()).map(|(y, c)| y * c).sum::<f32>()
+        }).sum::<f32>()
+    };
+
+    let mut ml_embedding = MlEmbedding::<VOCAB, DIM>::new(embedding.clone());
+    let mut ml_layer = MlLayer::<OUT, DIM>::new(layer.clone());
+    let mut cache = MlCache::<OUT, DIM>::new();
+    let mut input = MathVec::<DIM>::new();
+    let mut input_error = MathVec::<DIM>::new();
+
+    ids.iter().for_each(|id| {
+        ml_embedding.embedding().calc(*id, &mut input);
+        ml_layer.ready(&input, None, &mut cache);
+        ml_layer.study(&coef, None, &cache, &mut input_error, None);
+
+        ml_embedding.study(*id, &input_error);
+    });
+
+    ml_embedding.study(VOCAB, &input_error);
+    assert_eq!(ml_embedding.used_ids(), &[4, 1, 2]);
+
+    // Gradient of weights.
+    let mut grads = Vec::<f32>::new();
+    ml_embedding.for_each_total_grad(|val| {grads.push(*val)});
+
+    check_numerical_grad(&grads, embedding.table(), |weights| {
+        let mut embedding = embedding.clone();
+        embedding.table_mut().copy_from_slice(weights);
+
+        loss(&embedding)
+    });
+
+    // Rows of unused tokens have no gradient.
+    [0usize, 3, 5].iter().for_each(|id| {
+        assert!(
+            grads[(id * DIM)..((id + 1) * DIM)].iter().all(|val| *val == 0.0)
+        );
+    });
+
+    // Save and load.
+    let mut buffer = Vec::<u8>::new();
+    embedding.save(&mut buffer);
+
+    assert_eq!(Embedding::<VOCAB, DIM>::load(&buffer), Ok(embedding.clone()));
+    assert!(Embedding::<VOCAB, 3>::load(&buffer).is_err());
+    assert!(MlEmbedding::<VOCAB, DIM>::load_checkpoint(&buffer).is_err());
+
+    // Checkpoint.
+    let mut buffer = Vec::<u8>::new();
+    ml_embedding.save_checkpoint(&mut buffer);
+
+    let mut loaded = MlEmbedding::<VOCAB, DIM>::load_checkpoint(&buffer)
+        .unwrap();
+    assert_eq!(loaded, ml_embedding);
+
+    // Update touches only used rows.
+    loaded.update(0.1);
+    ml_embedding.update(0.1);
+    assert_eq!(loaded, ml_embedding);
+
+    assert_eq!(ml_embedding.step(), 1);
+    assert!(ml_embedding.used_ids().is_empty());
+    assert!(ml_embedding.total_grad().iter().all(|val| *val == 0.0));
+
+    let updated = ml_embedding.drop();
+
+    (0..VOCAB).for_each(|id| {
+        if ids.contains(&id) {
+            assert_ne!(updated.row(id), embedding.row(id));
+        } else {
+            assert_eq!(updated.row(id), embedding.row(id));
+        }
+    });
+}
+
+#[test]
+fn embedding_test_2() {
+    const VOCAB: usize = 5;
+    const DIM: usize = 3;
+
+    const STEP: u64 = 10;
+    const RATE: f32 = 0.01;
+
+    let mut rng = ChobitRand::new("embedding_test_2".as_bytes());
+
+    let optimizers = [
+        Optimizer::Sgd,
+        Optimizer::momentum(),
+        Optimizer::Momentum {momentum: 0.8, nesterov: true},
+        Optimizer::rms_prop(),
+        Optimizer::bias_corrected_adam(),
+        Optimizer::adam_w()
+    ];
+
+    for optimizer in optimizers.iter() {
+        let mut embedding = Embedding::<VOCAB, DIM>::new();
+        embedding.for_each_weight_mut(|val| {*val = rand_num(&mut rng)});
+
+        let mut weights = embedding.table().to_vec();
+        let mut momentum = vec![0.0f32; weights.len()];
+        let mut square_average = vec![0.0f32; weights.len()];
+
+        let mut ml_embedding = MlEmbedding::<VOCAB, DIM>::new(embedding);
+        ml_embedding.set_optimizer(*optimizer);
+        assert_eq!(ml_embedding.optimizer(), optimizer);
+
+        let mut error = MathVec::<DIM>::new();
+
+        for step in 0..STEP {
+            // Only 2 tokens are used in each step.
+            let ids = [
+                (rng.next_u64() as usize) % VOCAB,
+                (rng.next_u64() as usize) % VOCAB
+            ];
+
+            ids.iter().for_each(|id| {
+                rand_math_vec(&mut rng, &mut error);
+                ml_embedding.study(*id, &error);
+            });
+
+            let grads = ml_embedding.total_grad().to_vec();
+
+            ml_embedding.used_ids().iter().for_each(|id| {
+                let range = (id * DIM)..((id + 1) * DIM);
+
+                reference_update(
+                    optimizer,
+                    step,
+                    RATE,
+                    &mut weights[range.clone()],
+                    &grads[range.clone()],
+                    &mut momentum[range.clone()],
+                    &mut square_average[range]
+                );
+            });
+
+            ml_embedding.update(RATE);
+        }
+
+        assert_eq!(ml_embedding.step(), STEP);
+
+        // MlEmbedding uses approximate square root.
+        ml_embedding.drop().table().iter().zip(weights.iter()).for_each(
+            |(a, b)| {
+                assert!(
+                    (a - b).abs() < 1.0e-3,
+                    "{:?} : {} != {}",
+                    optimizer,
+                    a,
+                    b
+                );
+            }
+        );
+    }
+}
+
+fn log_softmax_score(output: &MathVec<5>, token: usize) -> f32 {
+    let max = output.iter().fold(f32::NEG_INFINITY, |a, b| a.max(*b));
+    let sum = output.iter().map(|val| (val - max).exp()).sum::<f32>();
+
+    output[token] - max - sum.ln()
+}
+
+fn argmax_index(output: &[f32]) -> usize {
+    (0..output.len()).fold(0, |a, b| if output[b] > output[a] {b} else {a})
+}
+
+#[test]
+fn generate_test_1() {
+    const OUT: usize = 5;
+    const MIDDLE: usize = 6;
+    const IN: usize = 5;
+    const LEN: usize = 3;
+
+    let mut rng = ChobitRand::new("generate_test_1".as_bytes());
+
+    let mut decoder = ChobitDecoder::<OUT, MIDDLE, IN>::new(Activation::Linear);
+    decoder.for_each_weight_mut(|val| {*val = rand_num(&mut rng) * 2.0});
+    rand_math_vec(&mut rng, decoder.input_mut());
+    rand_math_vec(&mut rng, decoder.state_mut());
+
+    let to_input = |token: usize, input: &mut MathVec<IN>| {
+        input.clear();
+        input[token] = 1.0;
+    };
+
+    // Runs decoder along tokens and returns score.
+    let replay = |
+        decoder: &mut ChobitDecoder<OUT, MIDDLE, IN>,
+        tokens: &[usize]
+    | -> f32 {
+        let mut output = MathVec::<OUT>::new();
+
+        tokens.iter().map(|token| {
+            decoder.output_next(&mut output);
+            to_input(*token, decoder.input_mut());
+
+            log_softmax_score(&output, *token)
+        }).sum::<f32>()
+    };
+
+    // Greedy is the same as manual loop.
+    let mut expected = decoder.clone();
+    let mut expected_tokens = Vec::<usize>::new();
+    let mut output = MathVec::<OUT>::new();
+
+    for _ in 0..LEN {
+        expected.output_next(&mut output);
+
+        let token = argmax_index(&output);
+        expected_tokens.push(token);
+        to_input(token, expected.input_mut());
+    }
+
+    let mut greedy = decoder.clone();
+    let tokens = greedy.generate(
+        &Decoding::Greedy,
+        LEN,
+        || rng.next_f64() as f32,
+        to_input,
+        |_| false
+    );
+
+    assert_eq!(tokens, expected_tokens);
+    assert_eq!(greedy.state(), expected.state());
+    assert_eq!(greedy.input(), expected.input());
+
+    // Stop condition.
+    let tokens = decoder.clone().generate(
+        &Decoding::Greedy,
+        LEN,
+        || rng.next_f64() as f32,
+        to_input,
+        |tokens| tokens.len() == 2
+    );
+    assert_eq!(tokens, &expected_tokens[..2]);
+
+    let tokens = decoder.clone().generate(
+        &Decoding::Greedy,
+        0,
+        || rng.next_f64() as f32,
+        to_input,
+        |_| false
+    );
+    assert!(tokens.is_empty());
+
+    // Degenerate settings are the same as greedy.
+    for decoding in [
+        Decoding::sampling(0.0),
+        Decoding::Sampling {temperature: 1.0, top_k: 1, top_p: 1.0},
+        Decoding::Sampling {temperature: 1.0, top_k: 0, top_p: 1.0e-6},
+        Decoding::beam_search(1)
+    ] {
+        let mut ai = decoder.clone();
+        let tokens = ai.generate(
+            &decoding,
+            LEN,
+            || rng.next_f64() as f32,
+            to_input,
+            |_| false
+        );
+
+        assert_eq!(tokens, expected_tokens, "{:?}", decoding);
+        assert_eq!(ai.state(), expected.state(), "{:?}", decoding);
+    }
+
+    // Beam search with enough width finds the best sequence.
+    let mut best_tokens = Vec::<usize>::new();
+    let mut best_score = f32::NEG_INFINITY;
+
+    for i in 0..(OUT * OUT * OUT) {
+        let tokens = [i / (OUT * OUT), (i / OUT) % OUT, i % OUT];
+        let score = replay(&mut decoder.clone(), &tokens);
+
+        if score > best_score {
+            best_score = score;
+            best_tokens = tokens.to_vec();
+        }
+    }
+
+    let mut ai = decoder.clone();
+    let tokens = ai.generate(
+        &Decoding::beam_search(OUT * OUT),
+        LEN,
+        || rng.next_f64() as f32,
+        to_input,
+        |_| false
+    );
+    assert_eq!(tokens, best_tokens);
+
+    let mut expected = decoder.clone();
+    replay(&mut expected, &best_tokens);
+    assert_eq!(ai.state(), expected.state());
+    assert_eq!(ai.input(), expected.input());
+
+    assert!(replay(&mut decoder.clone(), &tokens) >= replay(
+        &mut decoder.clone(),
+        &expected_tokens
+    ));
+
+    // Finished sequence is kept by beam search.
+    let tokens = decoder.clone().generate(
+        &Decoding::beam_search(OUT),
+        LEN,
+        || rng.next_f64() as f32,
+        to_input,
+        |tokens| tokens.len() == 1
+    );
+    assert_eq!(tokens.len(), 1);
+}
+
+#[test]
+fn generate_test_2() {
+    const OUT: usize = 5;
+    const MIDDLE: usize = 4;
+    const IN: usize = 5;
+    const COUNT: usize = 20000;
+
+    let mut rng = ChobitRand::new("generate_test_2".as_bytes());
+
+    // Output is always the bias.
+    let logits = [2.0f32, 1.0, 0.0, -1.0, -3.0];
+
+    let mut decoder = ChobitDecoder::<OUT, MIDDLE, IN>::new(Activation::Linear);
+    decoder.for_each_weight_mut(|val| {*val = rand_num(&mut rng)});
+    decoder.output_layer_mut().mut_weights().input_weights_mut().iter_mut()
+        .for_each(|row| row.fill(0.0));
+    decoder.output_layer_mut().mut_weights().bias_mut()
+        .copy_from_slice(&logits);
+
+    let mut frequency = |decoding: Decoding| -> [f32; OUT] {
+        let mut counts = [0usize; OUT];
+
+        let tokens = decoder.generate(
+            &decoding,
+            COUNT,
+            || rng.next_f64() as f32,
+            |_, _| {},
+            |_| false
+        );
+
+        tokens.iter().for_each(|token| {counts[*token] += 1;});
+
+        counts.map(|count| count as f32 / COUNT as f32)
+    };
+
+    let softmax = |temperature: f32, len: usize| -> [f32; OUT] {
+        let sum = logits[..len].iter()
+            .map(|val| (val / temperature).exp()).sum::<f32>();
+
+        let mut ret = [0.0f32; OUT];
+
+        (0..len).for_each(|i| {ret[i] = (logits[i] / temperature).exp() / sum});
+
+        ret
+    };
+
+    let assert_close = |a: [f32; OUT], b: [f32; OUT]| {
+        a.iter().zip(b.iter()).for_each(|(a, b)| {
+            assert!((a - b).abs() < 0.02, "{:?} != {:?}", a, b);
+        });
+    };
+
+    // Temperature.
+    assert_close(frequency(Decoding::sampling(1.0)), softmax(1.0, OUT));
+    assert_close(frequency(Decoding::sampling(0.5)), softmax(0.5, OUT));
+    assert_close(frequency(Decoding::sampling(2.0)), softmax(2.0, OUT));
+
+    // Top-k.
+    assert_close(
+        frequency(Decoding::Sampling {temperature: 1.0, top_k: 2, top_p: 1.0}),
+        softmax(1.0, 2)
+    );
+
+    // Top-p. (Probabilities are 0.641, 0.236, 0.087, 0.032, 0.004.)
+    assert_close(
+        frequency(Decoding::Sampling {temperature: 1.0, top_k: 0, top_p: 0.9}),
+        softmax(1.0, 3)
+    );
+}
+
+#[test]
+fn generate_test_3() {
+    const OUT: usize = 5;
+    const MIDDLE: usize = 6;
+    const IN: usize = 3;
+    const LEN: usize = 4;
+
+    let mut rng = ChobitRand::new("generate_test_3".as_bytes());
+
+    let mut ai = ChobitSeqAi::<OUT, MIDDLE, IN>::new(Activation::Linear);
+    ai.for_each_weight_mut(|val| {*val = rand_num(&mut rng) * 2.0});
+
+    let mut input = MathVec::<IN>::new();
+
+    for _ in 0..3 {
+        rand_math_vec(&mut rng, &mut input);
+        ai.input_next(&input);
+    }
+
+    // Greedy is the same as manual loop.
+    let mut expected = ai.clone();
+    let mut output = MathVec::<OUT>::new();
+
+    let expected_tokens: Vec<usize> = (0..LEN).map(|_| {
+        expected.output_next(&mut output);
+        argmax_index(&output)
+    }).collect();
+
+    for decoding in [Decoding::Greedy, Decoding::beam_search(1)] {
+        let mut generated = ai.clone();
+        let tokens = generated.generate(
+            &decoding,
+            LEN,
+            || rng.next_f64() as f32,
+            |_, _| {},
+            |_| false
+        );
+
+        assert_eq!(tokens, expected_tokens, "{:?}", decoding);
+        assert_eq!(generated.state(), expected.state(), "{:?}", decoding);
+    }
+
+    // Stop condition.
+    let stop_token = expected_tokens[1];
+    let tokens = ai.clone().generate(
+        &Decoding::Greedy,
+        LEN,
+        || rng.next_f64() as f32,
+        |_, _| {},
+        |tokens| tokens.last() == Some(&stop_token)
+    );
+
+    let position = expected_tokens.iter().position(|t| *t == stop_token)
+        .unwrap();
+    assert_eq!(tokens, &expected_tokens[..=position]);
+
+    // Beam search result is not worse than greedy in sum of log softmax.
+    let score = |tokens: &[usize]| -> f32 {
+        let mut ai = ai.clone();
+        let mut output = MathVec::<OUT>::new();
+
+        tokens.iter().map(|token| {
+            ai.output_next(&mut output);
+            log_softmax_score(&output, *token)
+        }).sum::<f32>()
+    };
+
+    let tokens = ai.clone().generate(
+        &Decoding::beam_search(OUT * OUT * OUT),
+        LEN,
+        || rng.next_f64() as f32,
+        |_, _| {},
+        |_| false
+    );
+    assert_eq!(tokens.len(), LEN);
+    assert!(score(&tokens) >= score(&expected_tokens) - 1.0e-4);
+
+    // Feeding each token back as input of decoder.
+    let to_input = |token: usize, input: &mut MathVec<MIDDLE>| {
+        input.clear();
+        input[token] = 1.0;
+    };
+
+    let mut expected = ai.clone();
+
+    let expected_tokens: Vec<usize> = (0..LEN).map(|i| {
+        if i > 0 {
+            to_input(argmax_index(&output), expected.decoder_input_mut());
+        }
+
+        expected.output_next(&mut output);
+        argmax_index(&output)
+    }).collect();
+
+    to_input(expected_tokens[LEN - 1], expected.decoder_input_mut());
+
+    for decoding in [Decoding::Greedy, Decoding::beam_search(1)] {
+        let mut generated = ai.clone();
+        let tokens = generated.generate(
+            &decoding,
+            LEN,
+            || rng.next_f64() as f32,
+            to_input,
+            |_| false
+        );
+
+        assert_eq!(tokens, expected_tokens, "{:?}", decoding);
+        assert_eq!(generated.state(), expected.state(), "{:?}", decoding);
+        assert_eq!(
+            generated.decoder_input(),
+            expected.decoder_input(),
+            "{:?}",
+            decoding
+        );
+    }
+}
+
+#[test]
+fn sampling_schedule_test() {
+    let schedule = SamplingSchedule::Constant {prob: 0.3};
+    assert_eq!(schedule.prob(0), 0.3);
+    assert_eq!(schedule.prob(1000), 0.3);
+
+    let schedule = SamplingSchedule::Linear {start: 1.0, end: 0.2, steps: 4};
+    assert!((schedule.prob(0) - 1.0).abs() < 1.0e-6);
+    assert!((schedule.prob(2) - 0.6).abs() < 1.0e-6);
+    assert_eq!(schedule.prob(4), 0.2);
+    assert_eq!(schedule.prob(100), 0.2);
+
+    let schedule = SamplingSchedule::Exponential {gamma: 0.5};
+    assert_eq!(schedule.prob(0), 1.0);
+    assert!((schedule.prob(3) - 0.125).abs() < 1.0e-6);
+
+    let schedule = SamplingSchedule::InverseSigmoid {k: 10.0};
+    let mut prev = 1.0;
+    for step in 0..100 {
+        let prob = schedule.prob(step);
+        assert!((prob - (10.0 / (10.0 + (step as f32 / 10.0).exp()))).abs()
+            < 1.0e-3);
+        assert!(prob <= prev);
+        prev = prob;
+    }
+
+    assert_eq!(
+        InputFeeding::scheduled(&SamplingSchedule::Constant {prob: 0.3}, 5),
+        InputFeeding::ScheduledSampling {teacher_prob: 0.3}
+    );
+
+    let mut rng = ChobitRand::new("sampling_schedule_test".as_bytes());
+
+    {
+        let mut chooser =
+            InputFeeding::TeacherForcing.chooser(|| rng.next_f64() as f32);
+        assert!((0..10).all(|_| chooser()));
+    }
+
+    {
+        let mut chooser =
+            InputFeeding::FreeRunning.chooser(|| rng.next_f64() as f32);
+        assert!((0..10).all(|_| !chooser()));
+    }
+
+    let mut chooser = InputFeeding::ScheduledSampling {teacher_prob: 0.25}
+        .chooser(|| rng.next_f64() as f32);
+    let count = (0..10000).filter(|_| chooser()).count();
+    assert!((2000..3000).contains(&count), "{}", count);
+}
+
+#[test]
+fn teacher_forcing_test_1() {
+    const OUT: usize = 3;
+    const MIDDLE: usize = 4;
+    const IN: usize = 3;
+    const LEN: usize = 4;
+
+    let mut rng = ChobitRand::new("teacher_forcing_test_1".as_bytes());
+
+    let mut decoder =
+        ChobitDecoder::<OUT, MIDDLE, IN>::new(Activation::SoftSign);
+    decoder.for_each_weight_mut(|val| {*val = rand_num(&mut rng)});
+
+    let mut input = MathVec::<IN>::new();
+    rand_math_vec(&mut rng, &mut input);
+
+    let mut prev_state = MathVec::<MIDDLE>::new();
+    rand_math_vec(&mut rng, &mut prev_state);
+
+    let mut train_out = vec![MathVec::<OUT>::new(); LEN];
+    train_out.iter_mut().for_each(|vec| rand_math_vec(&mut rng, vec));
+
+    let mut coef = vec![MathVec::<OUT>::new(); LEN];
+    coef.iter_mut().for_each(|vec| rand_math_vec(&mut rng, vec));
+
+    let to_input = |output: &MathVec<OUT>, input: &mut MathVec<IN>| {
+        input.copy_from(output);
+    };
+
+    // Feeds correct output if `teacher` is true, otherwise own output.
+    let calc = |
+        decoder: &mut ChobitDecoder<OUT, MIDDLE, IN>,
+        input: &MathVec<IN>,
+        prev_state: &MathVec<MIDDLE>,
+        teacher: bool
+    | -> Vec<MathVec<OUT>> {
+        decoder.input_mut().copy_from(input);
+        decoder.state_mut().copy_from(prev_state);
+
+        (0..LEN).map(|i| {
+            let mut output = MathVec::<OUT>::new();
+            decoder.output_next(&mut output);
+
+            if teacher {
+                to_input(&train_out[i], decoder.input_mut());
+            } else {
+                to_input(&output, decoder.input_mut());
+            }
+
+            output
+        }).collect()
+    };
+
+    // Loss = Σ(c_ij * y_ij).
+    let loss = |
+        decoder: &mut ChobitDecoder<OUT, MIDDLE, IN>,
+        input: &MathVec<IN>
+    | -> f32 {
+        calc(decoder, input, &prev_state, true).iter().zip(coef.iter())
+            .map(|(y, c)| {
+                y.iter().zip(c.iter()).map(|(y, c)| y * c).sum::<f32>()
+            }).sum::<f32>()
+    };
+
+    let mut ml_decoder = ChobitMlDecoder::<OUT, MIDDLE, IN>::new(
+        decoder.clone()
+    );
+    let mut cache = MlDecoderCache::<OUT, MIDDLE, IN>::new(0);
+
+    let outputs = |cache: &MlDecoderCache<OUT, MIDDLE, IN>| {
+        let mut ret = Vec::<MathVec<OUT>>::new();
+        cache.for_each_output(|output| ret.push(output.clone()));
+        ret
+    };
+
+    // Same output as inference.
+    for (feeding, teacher) in [
+        (InputFeeding::TeacherForcing, true),
+        (InputFeeding::ScheduledSampling {teacher_prob: 1.0}, true),
+        (InputFeeding::FreeRunning, false),
+        (InputFeeding::ScheduledSampling {teacher_prob: 0.0}, false)
+    ] {
+        ml_decoder.ready_with_feeding(
+            &input,
+            &prev_state,
+            &train_out,
+            feeding.chooser(|| rng.next_f64() as f32),
+            to_input,
+            &mut cache
+        );
+
+        assert_eq!(
+            outputs(&cache),
+            calc(&mut decoder.clone(), &input, &prev_state, teacher),
+            "{:?}",
+            feeding
+        );
+    }
+
+    // Fixed input is unchanged.
+    ml_decoder.ready(&input, &prev_state, LEN, &mut cache);
+
+    let mut expected = decoder.clone();
+    expected.input_mut().copy_from(&input);
+    expected.state_mut().copy_from(&prev_state);
+
+    let expected_output: Vec<MathVec<OUT>> = (0..LEN).map(|_| {
+        let mut output = MathVec::<OUT>::new();
+        expected.output_next(&mut output);
+        output
+    }).collect();
+    assert_eq!(outputs(&cache), expected_output);
+
+    // Gradient with teacher forcing.
+    ml_decoder.ready_with_feeding(
+        &input,
+        &prev_state,
+        &train_out,
+        InputFeeding::TeacherForcing.chooser(|| rng.next_f64() as f32),
+        to_input,
+        &mut cache
+    );
+
+    let mut input_error = MathVec::<IN>::new();
+    let mut prev_state_error = MathVec::<MIDDLE>::new();
+    ml_decoder.study(&coef, &cache, &mut input_error, &mut prev_state_error);
+
+    let mut grads = Vec::<f32>::new();
+    ml_decoder.for_each_total_grad(|val| {grads.push(*val)});
+
+    let mut weights = Vec::<f32>::new();
+    decoder.for_each_weight(|val| {weights.push(*val)});
+
+    let mut tmp_decoder = decoder.clone();
+    check_numerical_grad(&grads, &weights, |weights| {
+        let mut iter = weights.iter();
+        tmp_decoder.for_each_weight_mut(|val| {*val = *iter.next().unwrap()});
+
+        loss(&mut tmp_decoder, &input)
+    });
+
+    // Input is used only in the first step.
+    let mut tmp_input = input.clone();
+    check_numerical_grad(&input_error, &input, |flat_input| {
+        tmp_input.copy_from_slice(flat_input);
+
+        loss(&mut tmp_decoder, &tmp_input)
+    });
+}
+
+#[test]
+fn teacher_forcing_test_2() {
+    const OUT: usize = 3;
+    const MIDDLE: usize = 4;
+    const IN: usize = 2;
+    const IN_LEN: usize = 3;
+    const OUT_LEN: usize = 4;
+
+    let mut rng = ChobitRand::new("teacher_forcing_test_2".as_bytes());
+
+    let mut ai = ChobitSeqAi::<OUT, MIDDLE, IN>::new(Activation::SoftSign);
+    ai.for_each_weight_mut(|val| {*val = rand_num(&mut rng)});
+
+    let mut data = vec![MathVec::<IN>::new(); IN_LEN];
+    data.iter_mut().for_each(|vec| rand_math_vec(&mut rng, vec));
+
+    let mut prev_state = MathVec::<MIDDLE>::new();
+    rand_math_vec(&mut rng, &mut prev_state);
+
+    let mut train_out = vec![MathVec::<OUT>::new(); OUT_LEN];
+    train_out.iter_mut().for_each(|vec| rand_math_vec(&mut rng, vec));
+
+    let mut coef = vec![MathVec::<OUT>::new(); OUT_LEN];
+    coef.iter_mut().for_each(|vec| rand_math_vec(&mut rng, vec));
+
+    let to_input = |output: &MathVec<OUT>, input: &mut MathVec<MIDDLE>| {
+        input.clear();
+        input[..OUT].copy_from_slice(output);
+    };
+
+    // Feeds correct output if `teacher` is true, otherwise own output.
+    let calc = |
+        ai: &mut ChobitSeqAi<OUT, MIDDLE, IN>,
+        data: &[MathVec<IN>],
+        teacher: bool
+    | -> Vec<MathVec<OUT>> {
+        ai.state_mut().copy_from(&prev_state);
+        data.iter().for_each(|data_one| ai.input_next(data_one));
+
+        (0..OUT_LEN).map(|i| {
+            let mut output = MathVec::<OUT>::new();
+            ai.output_next(&mut output);
+
+            if teacher {
+                to_input(&train_out[i], ai.decoder_input_mut());
+            } else {
+                to_input(&output, ai.decoder_input_mut());
+            }
+
+            output
+        }).collect()
+    };
+
+    // Loss = Σ(c_ij * y_ij).
+    let loss = |
+        ai: &mut ChobitSeqAi<OUT, MIDDLE, IN>,
+        data: &[MathVec<IN>]
+    | -> f32 {
+        calc(ai, data, true).iter().zip(coef.iter()).map(|(y, c)| {
+            y.iter().zip(c.iter()).map(|(y, c)| y * c).sum::<f32>()
+        }).sum::<f32>()
+    };
+
+    let mut ml_ai = ChobitMlSeqAi::<OUT, MIDDLE, IN>::new(ai.clone());
+    let mut cache = MlSeqAiCache::<OUT, MIDDLE, IN>::new(0, 0);
+
+    let outputs = |cache: &MlSeqAiCache<OUT, MIDDLE, IN>| {
+        let mut ret = Vec::<MathVec<OUT>>::new();
+        cache.for_each_output(|output| ret.push(output.clone()));
+        ret
+    };
+
+    // Same output as inference.
+    for (feeding, teacher) in [
+        (InputFeeding::TeacherForcing, true),
+        (InputFeeding::FreeRunning, false)
+    ] {
+        ml_ai.ready_with_feeding(
+            &data,
+            &prev_state,
+            &train_out,
+            feeding.chooser(|| rng.next_f64() as f32),
+            to_input,
+            &mut cache
+        );
+
+        assert_eq!(
+            outputs(&cache),
+            calc(&mut ai.clone(), &data, teacher),
+            "{:?}",
+            feeding
+        );
+    }
+
+    // Gradient with teacher forcing.
+    ml_ai.ready_with_feeding(
+        &data,
+        &prev_state,
+        &train_out,
+        InputFeeding::TeacherForcing.chooser(|| rng.next_f64() as f32),
+        to_input,
+        &mut cache
+    );
+
+    let mut input_error = vec![MathVec::<IN>::new(); IN_LEN];
+    let mut prev_state_error = MathVec::<MIDDLE>::new();
+    ml_ai.study(&coef, &cache, &mut input_error, &mut prev_state_error);
+
+    let mut grads = Vec::<f32>::new();
+    ml_ai.for_each_total_grad(|val| {grads.push(*val)});
+
+    let mut weights = Vec::<f32>::new();
+    ai.for_each_weight(|val| {weights.push(*val)});
+
+    let mut tmp_ai = ai.clone();
+    check_numerical_grad(&grads, &weights, |weights| {
+        let mut iter = weights.iter();
+        tmp_ai.for_each_weight_mut(|val| {*val = *iter.next().unwrap()});
+
+        loss(&mut tmp_ai, &data)
+    });
+
+    // Output of encoder is used only in the first step.
+    let flat_data: Vec<f32> = data.iter().flat_map(|vec| vec.iter().copied())
+        .collect();
+    let flat_error: Vec<f32> = input_error.iter().flat_map(
+        |vec| vec.iter().copied()
+    ).collect();
+
+    let mut tmp_data = data.clone();
+    check_numerical_grad(&flat_error, &flat_data, |flat_data| {
+        tmp_data.iter_mut().zip(
+            flat_data.chunks(IN)
+        ).for_each(|(vec, chunk)| vec.copy_from_slice(chunk));
+
+        loss(&mut tmp_ai, &tmp_data)
+    });
+}
+
+#[test]
+fn batch_test_1() {
+    const OUT: usize = 5;
+    const IN: usize = 7;
+    const BATCH: usize = 11;
+
+    let mut rng = ChobitRand::new("batch_test_1".as_bytes());
+
+    let mut layer = Layer::<OUT, IN>::new(Activation::SoftSign, true);
+    layer.mut_weights().iter_mut().for_each(|val| {*val = rand_num(&mut rng)});
+
+    let mut inputs = vec![MathVec::<IN>::new(); BATCH];
+    inputs.iter_mut().for_each(|vec| rand_math_vec(&mut rng, vec));
+
+    let mut states = vec![MathVec::<OUT>::new(); BATCH];
+    states.iter_mut().for_each(|vec| rand_math_vec(&mut rng, vec));
+
+    let mut output_errors = vec![MathVec::<OUT>::new(); BATCH];
+    output_errors.iter_mut().for_each(|vec| rand_math_vec(&mut rng, vec));
+
+    let mut next_state_errors = vec![MathVec::<OUT>::new(); BATCH];
+    next_state_errors.iter_mut().for_each(
+        |vec| rand_math_vec(&mut rng, vec)
+    );
+
+    // Calculation.
+    for state in [None, Some(states.as_slice())] {
+        let mut outputs = vec![MathVec::<OUT>::new(); BATCH];
+        layer.calc_batch(&inputs, state, &mut outputs);
+
+        let mut expected = MathVec::<OUT>::new();
+        for i in 0..BATCH {
+            layer.calc(&inputs[i], state.map(|s| &s[i]), &mut expected);
+            assert_eq!(outputs[i], expected);
+        }
+    }
+
+    // Machine learning.
+    let mut ml_layer_1 = MlLayer::<OUT, IN>::new(layer.clone());
+    let mut ml_layer_2 = MlLayer::<OUT, IN>::new(layer.clone());
+
+    let mut cache = MlCache::<OUT, IN>::new();
+    let mut input_error = MathVec::<IN>::new();
+    let mut prev_state_error = MathVec::<OUT>::new();
+
+    let mut expected_input_errors = Vec::<MathVec<IN>>::new();
+    let mut expected_prev_state_errors = Vec::<MathVec<OUT>>::new();
+
+    for i in 0..BATCH {
+        ml_layer_1.ready(&inputs[i], Some(&states[i]), &mut cache);
+        ml_layer_1.study(
+            &output_errors[i],
+            Some(&next_state_errors[i]),
+            &cache,
+            &mut input_error,
+            Some(&mut prev_state_error)
+        );
+
+        expected_input_errors.push(input_error.clone());
+        expected_prev_state_errors.push(prev_state_error.clone());
+    }
+
+    let mut batch_cache = MlBatchCache::<OUT, IN>::new();
+    ml_layer_2.ready_batch(&inputs, Some(&states), &mut batch_cache);
+
+    assert_eq!(batch_cache.batch_size(), BATCH);
+    assert_eq!(batch_cache.inputs(), inputs.as_slice());
+    assert_eq!(batch_cache.states(), Some(states.as_slice()));
+    assert_eq!(batch_cache.outputs()[BATCH - 1], *cache.output());
+
+    let mut input_errors = vec![MathVec::<IN>::new(); BATCH];
+    let mut prev_state_errors = vec![MathVec::<OUT>::new(); BATCH];
+    ml_layer_2.study_batch(
+        &output_errors,
+        Some(&next_state_errors),
+        &batch_cache,
+        &mut input_errors,
+        Some(&mut prev_state_errors)
+    );
+
+    assert_eq!(input_errors, expected_input_errors);
+    assert_eq!(prev_state_errors, expected_prev_state_errors);
+    assert_eq!(ml_layer_2.total_grad(), ml_layer_1.total_grad());
+
+    ml_layer_1.update(0.1);
+    ml_layer_2.update(0.1);
+    assert_eq!(ml_layer_2.drop(), ml_layer_1.drop());
+}
+
+#[test]
+fn batch_test_2() {
+    const OUT: usize = 3;
+    const MIDDLE: usize = 10;
+    const IN: usize = 4;
+    const BATCH: usize = 11;
+
+    let mut rng = ChobitRand::new("batch_test_2".as_bytes());
+
+    let mut ai = ChobitAi::<OUT, MIDDLE, IN>::new(Activation::Sigmoid);
+    ai.for_each_weight_mut(|val| {*val = rand_num(&mut rng)});
+
+    let mut inputs = vec![MathVec::<IN>::new(); BATCH];
+    inputs.iter_mut().for_each(|vec| rand_math_vec(&mut rng, vec));
+
+    let mut train_out = vec![MathVec::<OUT>::new(); BATCH];
+    train_out.iter_mut().for_each(|vec| rand_math_vec(&mut rng, vec));
+
+    // Calculation.
+    let mut outputs = vec![MathVec::<OUT>::new(); BATCH];
+    let mut tmpbufs = vec![MathVec::<MIDDLE>::new(); BATCH];
+    ai.calc_batch(&inputs, &mut outputs, &mut tmpbufs);
+
+    let mut expected = MathVec::<OUT>::new();
+    let mut tmpbuf = MathVec::<MIDDLE>::new();
+    for i in 0..BATCH {
+        ai.calc(&inputs[i], &mut expected, &mut tmpbuf);
+        assert_eq!(outputs[i], expected);
+    }
+
+    // Machine learning.
+    let mut ai_1 = ChobitMlAi::<OUT, MIDDLE, IN>::new(ai.clone());
+    let mut ai_2 = ChobitMlAi::<OUT, MIDDLE, IN>::new(ai.clone());
+
+    let mut cache = MlAiCache::<OUT, MIDDLE, IN>::new();
+    let mut batch_cache = MlAiBatchCache::<OUT, MIDDLE, IN>::new();
+
+    let mut output_error = MathVec::<OUT>::new();
+    let mut input_error = MathVec::<IN>::new();
+    let mut output_errors = vec![MathVec::<OUT>::new(); BATCH];
+    let mut input_errors = vec![MathVec::<IN>::new(); BATCH];
+
+    for _ in 0..3 {
+        let mut expected_input_errors = Vec::<MathVec<IN>>::new();
+
+        for i in 0..BATCH {
+            ai_1.ready(&inputs[i], &mut cache);
+            cache.calc_output_error(&train_out[i], &mut output_error);
+            ai_1.study(&output_error, &cache, &mut input_error);
+
+            expected_input_errors.push(input_error.clone());
+        }
+
+        ai_2.ready_batch(&inputs, &mut batch_cache);
+        assert_eq!(batch_cache.batch_size(), BATCH);
+        assert_eq!(batch_cache.outputs()[BATCH - 1], *cache.output());
+
+        batch_cache.calc_output_error(&train_out, &mut output_errors);
+        ai_2.study_batch(&output_errors, &batch_cache, &mut input_errors);
+
+        assert_eq!(input_errors, expected_input_errors);
+
+        let mut grads_1 = Vec::<f32>::new();
+        ai_1.for_each_total_grad(|val| {grads_1.push(*val)});
+
+        let mut grads_2 = Vec::<f32>::new();
+        ai_2.for_each_total_grad(|val| {grads_2.push(*val)});
+
+        assert_eq!(grads_2, grads_1);
+
+        ai_1.update(0.1);
+        ai_2.update(0.1);
+    }
+
+    assert_eq!(ai_2.drop(), ai_1.drop());
+}
+
+fn assert_close(a: f32, b: f32) {
+    assert!((a - b).abs() <= 1.0e-4 * (1.0 + b.abs()), "{} != {}", a, b);
+}
+
+#[test]
+fn simd_test_1() {
+    const OUT: usize = 13;
+    const IN: usize = 37;
+
+    let mut rng = ChobitRand::new("simd_test_1".as_bytes());
+
+    let mut weights = Weights::<OUT, IN>::new(true);
+    weights.iter_mut().for_each(|val| {*val = rand_num(&mut rng)});
+
+    let mut input = MathVec::<IN>::new();
+    rand_math_vec(&mut rng, &mut input);
+
+    let mut state = MathVec::<OUT>::new();
+    rand_math_vec(&mut rng, &mut state);
+
+    let mut coefficient = MathVec::<OUT>::new();
+    rand_math_vec(&mut rng, &mut coefficient);
+
+    let input_weights = *weights.input_weights();
+    let state_weights = *weights.state_weights().unwrap();
+
+    // Inner product.
+    let mut other = MathVec::<IN>::new();
+    rand_math_vec(&mut rng, &mut other);
+
+    let mut expected: f32 = 0.0;
+    for i in 0..IN {
+        expected += input[i] * other[i];
+    }
+    assert_close(&input * &other, expected);
+
+    // Linear function.
+    let mut output = MathVec::<OUT>::new();
+    weights.calc(&input, Some(&state), &mut output);
+
+    for i in 0..OUT {
+        let mut expected = weights.bias()[i];
+
+        for j in 0..IN {
+            expected += input_weights[i][j] * input[j];
+        }
+
+        for k in 0..OUT {
+            expected += state_weights[i][k] * state[k];
+        }
+
+        assert_close(output[i], expected);
+    }
+
+    // Gradient with respect to input and state.
+    let mut input_grad = MathVec::<IN>::new();
+    weights.grad_with_input(&coefficient, &mut input_grad);
+
+    for j in 0..IN {
+        let mut expected: f32 = 0.0;
+
+        for i in 0..OUT {
+            expected += coefficient[i] * input_weights[i][j];
+        }
+
+        assert_close(input_grad[j], expected);
+    }
+
+    let mut state_grad = MathVec::<OUT>::new();
+    weights.grad_with_state(&coefficient, &mut state_grad);
+
+    for k in 0..OUT {
+        let mut expected: f32 = 0.0;
+
+        for i in 0..OUT {
+            expected += coefficient[i] * state_weights[i][k];
+        }
+
+        assert_close(state_grad[k], expected);
+    }
+
+    // Gradient with respect to weights.
+    let mut grad = Weights::<OUT, IN>::new(true);
+    Weights::grad_with_weights(&coefficient, &input, Some(&state), &mut grad);
+
+    for i in 0..OUT {
+        assert_close(grad.bias()[i], coefficient[i]);
+
+        for j in 0..IN {
+            assert_close(
+                grad.input_weights()[i][j],
+                coefficient[i] * input[j]
+            );
+        }
+
+        for k in 0..OUT {
+            assert_close(
+                grad.state_weights().unwrap()[i][k],
+                coefficient[i] * state[k]
+            );
+        }
+    }
+
+    // Batch is the same as single.
+    let inputs = vec![input.clone(); 3];
+    let states = vec![state.clone(); 3];
+    let mut outputs = vec![MathVec::<OUT>::new(); 3];
+    weights.calc_batch(&inputs, Some(&states), &mut outputs);
+
+    outputs.iter().for_each(|batch_output| {
+        assert_eq!(*batch_output, output);
+    });
+}
+
+#[test]
+fn data_parallel_test_1() {
+    const OUT: usize = 3;
+    const MIDDLE: usize = 10;
+    const IN: usize = 4;
+    const BATCH: usize = 11;
+
+    let mut rng = ChobitRand::new("data_parallel_test_1".as_bytes());
+
+    let mut ai = ChobitAi::<OUT, MIDDLE, IN>::new(Activation::Sigmoid);
+    ai.for_each_weight_mut(|val| {*val = rand_num(&mut rng)});
+
+    let mut inputs = vec![MathVec::<IN>::new(); BATCH];
+    inputs.iter_mut().for_each(|vec| rand_math_vec(&mut rng, vec));
+
+    let mut train_out = vec![MathVec::<OUT>::new(); BATCH];
+    train_out.iter_mut().for_each(|vec| rand_math_vec(&mut rng, vec));
+
+    let mut thread_runner = |tasks: &mut [&mut (dyn FnMut() + Send)]| {
+        std::thread::scope(|scope| {
+            tasks.iter_mut().for_each(|task| {
+                scope.spawn(move || task());
+            });
+        });
+    };
+
+    assert_eq!(
+        ChobitDataParallel::<OUT, MIDDLE, IN>::new(
+            ChobitMlAi::<OUT, MIDDLE, IN>::new(ai.clone()),
+            0
+        ).num_workers(),
+        1
+    );
+
+    let mut ai_1 = ChobitMlAi::<OUT, MIDDLE, IN>::new(ai.clone());
+
+    // 4, 4 and 3 samples.
+    let mut parallel_1 = ChobitDataParallel::<OUT, MIDDLE, IN>::new(
+        ChobitMlAi::<OUT, MIDDLE, IN>::new(ai.clone()),
+        3
+    );
+
+    let mut parallel_2 = parallel_1.clone();
+
+    // More workers than samples.
+    let mut parallel_3 = ChobitDataParallel::<OUT, MIDDLE, IN>::new(
+        ChobitMlAi::<OUT, MIDDLE, IN>::new(ai.clone()),
+        20
+    );
+
+    assert_eq!(parallel_1.num_workers(), 3);
+    assert_eq!(parallel_3.num_workers(), 20);
+
+    let mut cache = MlAiCache::<OUT, MIDDLE, IN>::new();
+    let mut output_error = MathVec::<OUT>::new();
+    let mut input_error = MathVec::<IN>::new();
+
+    let collect_grads = |ai: &ChobitMlAi<OUT, MIDDLE, IN>| -> Vec<f32> {
+        let mut grads = Vec::<f32>::new();
+        ai.for_each_total_grad(|val| {grads.push(*val)});
+        grads
+    };
+
+    for _ in 0..3 {
+        for i in 0..BATCH {
+            ai_1.ready(&inputs[i], &mut cache);
+            cache.calc_output_error(&train_out[i], &mut output_error);
+            ai_1.study(&output_error, &cache, &mut input_error);
+        }
+
+        let loss = Loss::Mse;
+        parallel_1.study(&inputs, &train_out, &loss, &mut SequentialRunner);
+        parallel_2.study(&inputs, &train_out, &loss, &mut thread_runner);
+        parallel_3.study(&inputs, &train_out, &loss, &mut thread_runner);
+
+        // Gradients are not in the master until all_reduce().
+        assert!(
+            collect_grads(parallel_1.master()).iter().all(|val| *val == 0.0)
+        );
+
+        parallel_1.all_reduce();
+        parallel_2.all_reduce();
+        parallel_3.all_reduce();
+
+        let expected = collect_grads(&ai_1);
+
+        // Order of running tasks doesn't matter.
+        assert_eq!(
+            collect_grads(parallel_2.master()),
+            collect_grads(parallel_1.master())
+        );
+
+        collect_grads(parallel_1.master()).iter().zip(expected.iter())
+            .for_each(|(a, b)| assert_close(*a, *b));
+
+        collect_grads(parallel_3.master()).iter().zip(expected.iter())
+            .for_each(|(a, b)| assert_close(*a, *b));
+
+        ai_1.update(0.1);
+
+        // Gradients of workers have already been moved by all_reduce().
+        parallel_1.update(0.1);
+        parallel_2.update(0.1);
+        parallel_3.update(0.1);
+    }
+
+    let expected = ai_1.drop();
+
+    let mut weights_1 = Vec::<f32>::new();
+    expected.for_each_weight(|val| {weights_1.push(*val)});
+
+    assert_eq!(parallel_2.drop().drop(), parallel_1.clone().drop().drop());
+
+    [parallel_1, parallel_3].into_iter().for_each(|parallel| {
+        let mut weights_2 = Vec::<f32>::new();
+        parallel.drop().drop()
+            .for_each_weight(|val| {weights_2.push(*val)});
+
+        weights_2.iter().zip(weights_1.iter())
+            .for_each(|(a, b)| assert_close(*a, *b));
+    });
+}
+
+#[test]
+fn quantize_test_1() {
+    const OUT: usize = 16;
+    const IN: usize = 24;
+    const SAMPLES: usize = 50;
+
+    let mut rng = ChobitRand::new("quantize_test_1".as_bytes());
+
+    // QuantParams.
+    let params = QuantParams::from_range(-1.0, 3.0);
+
+    assert_eq!(params.dequantize(params.quantize(0.0)), 0.0);
+    assert_eq!(params.quantize(100.0), 127);
+    assert_eq!(params.quantize(-100.0), -128);
+    assert_eq!(params.quantize(f32::NAN), params.zero_point() as i8);
+
+    for _ in 0..100 {
+        let val = (rand_num(&mut rng) * 2.0) + 1.0;
+
+        assert!(
+            (params.dequantize(params.quantize(val)) - val).abs()
+                <= params.scale() * 0.5001
+        );
+    }
+
+    assert_eq!(
+        QuantParams::from_range(0.0, 0.0),
+        QuantParams::new(1.0, 0)
+    );
+    assert_eq!(QuantParams::new(-1.0, 1000), QuantParams::new(1.0, 127));
+
+    // Calibrator.
+    let mut calibrator = Calibrator::new();
+    assert_eq!(calibrator.params(), QuantParams::from_range(0.0, 0.0));
+
+    calibrator.observe(&[0.5, -0.25, f32::NAN, f32::INFINITY, 2.0]);
+    calibrator.observe(&[1.0]);
+
+    assert_eq!(calibrator.min(), -0.25);
+    assert_eq!(calibrator.max(), 2.0);
+    assert_eq!(calibrator.params(), QuantParams::from_range(-0.25, 2.0));
+
+    // QuantizedWeights.
+    let mut layer = Layer::<OUT, IN>::new(Activation::SoftSign, true);
+    layer.mut_weights().iter_mut().for_each(|val| {*val = rand_num(&mut rng)});
+
+    let weights = QuantizedWeights::<OUT, IN>::new(layer.weights());
+    let dequantized = weights.dequantize();
+
+    assert_eq!(dequantized.bias(), layer.weights().bias());
+    assert_eq!(weights.input_weights().len(), OUT * IN);
+    assert_eq!(weights.state_weights().unwrap().len(), OUT * OUT);
+
+    for i in 0..OUT {
+        let input_scale = weights.input_params()[i].scale();
+        let state_scale = weights.state_params().unwrap()[i].scale();
+
+        for j in 0..IN {
+            assert!((
+                dequantized.input_weights()[i][j]
+                    - layer.weights().input_weights()[i][j]
+            ).abs() <= input_scale * 0.5001);
+        }
+
+        for k in 0..OUT {
+            assert!((
+                dequantized.state_weights().unwrap()[i][k]
+                    - layer.weights().state_weights().unwrap()[i][k]
+            ).abs() <= state_scale * 0.5001);
+        }
+    }
+
+    // QuantizedLayer.
+    let mut inputs = vec![MathVec::<IN>::new(); SAMPLES];
+    inputs.iter_mut().for_each(|vec| rand_math_vec(&mut rng, vec));
+
+    let mut states = vec![MathVec::<OUT>::new(); SAMPLES];
+    states.iter_mut().for_each(|vec| rand_math_vec(&mut rng, vec));
+
+    let quantized = QuantizedLayer::<OUT, IN>::calibrate(
+        &layer,
+        &inputs,
+        &states
+    );
+
+    assert_eq!(quantized.weights(), &weights);
+    assert_eq!(quantized.dequantize().weights(), &dequantized);
+
+    let dequantized_layer = quantized.dequantize();
+
+    let mut output = MathVec::<OUT>::new();
+    let mut expected = MathVec::<OUT>::new();
+    let mut approx = MathVec::<OUT>::new();
+
+    let mut dequantized_input = MathVec::<IN>::new();
+    let mut dequantized_state = MathVec::<OUT>::new();
+
+    let mut max_error: f32 = 0.0;
+
+    for (input, state) in inputs.iter().zip(states.iter()) {
+        quantized.calc(input, Some(state), &mut output);
+
+        // Integer calculation is the same as f32 calculation
+        // with dequantized weights, input and state.
+        dequantized_input.iter_mut().zip(input.iter()).for_each(|(x, y)| {
+            let params = quantized.input_params();
+            *x = params.dequantize(params.quantize(*y));
+        });
+
+        dequantized_state.iter_mut().zip(state.iter()).for_each(|(x, y)| {
+            let params = quantized.state_params();
+            *x = params.dequantize(params.quantize(*y));
+        });
+
+        dequantized_layer.calc(
+            &dequantized_input,
+            Some(&dequantized_state),
+            &mut approx
+        );
+
+        output.iter().zip(approx.iter()).for_each(|(a, b)| {
+            assert_close(*a, *b);
+        });
+
+        // Accuracy against f32 layer.
+        layer.calc(input, Some(state), &mut expected);
+
+        output.iter().zip(expected.iter()).for_each(|(a, b)| {
+            max_error = max_error.max((*a - *b).abs());
+        });
+    }
+
+    assert!(max_error < 0.05, "{}", max_error);
+}
+
+#[test]
+fn quantize_test_2() {
+    const OUT: usize = 8;
+    const MIDDLE: usize = 32;
+    const IN: usize = 16;
+    const SAMPLES: usize = 200;
+
+    let mut rng = ChobitRand::new("quantize_test_2".as_bytes());
+
+    let mut ai = ChobitAi::<OUT, MIDDLE, IN>::with_activations(
+        Activation::ReLU,
+        Activation::SoftSign
+    );
+    ai.init_weights(Initializer::HeUniform, || rng.next_f64() as f32);
+
+    let mut samples = vec![MathVec::<IN>::new(); SAMPLES];
+    samples.iter_mut().for_each(|vec| rand_math_vec(&mut rng, vec));
+
+    let quantized = QuantizedChobitAi::<OUT, MIDDLE, IN>::calibrate(
+        &ai,
+        &samples[..(SAMPLES / 2)]
+    );
+
+    // Accuracy against f32 model with inputs not used for calibration.
+    let mut output = MathVec::<OUT>::new();
+    let mut expected = MathVec::<OUT>::new();
+    let mut tmpbuf = MathVec::<MIDDLE>::new();
+
+    let mut max_error: f32 = 0.0;
+    let mut sum_error: f32 = 0.0;
+    let mut same_argmax: usize = 0;
+
+    for input in samples[(SAMPLES / 2)..].iter() {
+        quantized.calc(input, &mut output, &mut tmpbuf);
+        ai.calc(input, &mut expected, &mut tmpbuf);
+
+        output.iter().zip(expected.iter()).for_each(|(a, b)| {
+            max_error = max_error.max((*a - *b).abs());
+            sum_error += (*a - *b).abs();
+        });
+
+        if argmax_index(&output) == argmax_index(&expected) {
+            same_argmax += 1;
+        }
+    }
+
+    let mean_error = sum_error / ((SAMPLES / 2) * OUT) as f32;
+
+    assert!(max_error < 0.05, "{}", max_error);
+    assert!(mean_error < 0.01, "{}", mean_error);
+    assert!(same_argmax >= (SAMPLES / 2) * 95 / 100, "{}", same_argmax);
+
+    // Save and load.
+    let mut data = Vec::<u8>::new();
+    quantized.save(&mut data);
+
+    let loaded = QuantizedChobitAi::<OUT, MIDDLE, IN>::load(&data).unwrap();
+    assert_eq!(loaded, quantized);
+
+    let mut f32_data = Vec::<u8>::new();
+    ai.save(&mut f32_data);
+    assert!(data.len() * 2 < f32_data.len());
+
+    assert_eq!(
+        ChobitAi::<OUT, MIDDLE, IN>::load(&data),
+        Err(ChobitAiError::WrongModelKind {
+            expected: ModelKind::ChobitAi,
+            found: ModelKind::QuantizedChobitAi as u8
+        })
+    );
+
+    assert!(QuantizedChobitAi::<OUT, MIDDLE, IN>::load(
+        &data[..(data.len() - 1)]
+    ).is_err());
+
+    // Dequantized AI is close to original AI.
+    let mut original = Vec::<f32>::new();
+    ai.for_each_weight(|val| {original.push(*val)});
+
+    let mut dequantized = Vec::<f32>::new();
+    quantized.dequantize().for_each_weight(|val| {dequantized.push(*val)});
+
+    original.iter().zip(dequantized.iter()).for_each(|(a, b)| {
+        assert!((*a - *b).abs() < 0.01);
+    });
+}
+
+#[test]
+fn quantize_test_3() {
+    const OUT: usize = 8;
+    const IN: usize = 6;
+    const SEQ_LEN: usize = 10;
+
+    let mut rng = ChobitRand::new("quantize_test_3".as_bytes());
+
+    let mut lstm = Lstm::<OUT, IN>::new();
+    lstm.for_each_weight_mut(|val| {*val = rand_num(&mut rng) * 0.5});
+
+    let mut sequences = vec![vec![MathVec::<IN>::new(); SEQ_LEN]; 6];
+    sequences.iter_mut().for_each(|sequence| {
+        sequence.iter_mut().for_each(|vec| rand_math_vec(&mut rng, vec));
+    });
+
+    let calibration = sequences[..5].iter().map(
+        |sequence| sequence.as_slice()
+    ).collect::<Vec<&[MathVec<IN>]>>();
+
+    let quantized = QuantizedLstm::<OUT, IN>::calibrate(&lstm, &calibration);
+
+    // Accuracy against f32 model through a sequence.
+    let mut state = MathVec::<OUT>::new();
+    let mut expected_state = MathVec::<OUT>::new();
+    let mut next_state = MathVec::<OUT>::new();
+    let mut output = MathVec::<OUT>::new();
+    let mut expected = MathVec::<OUT>::new();
+    let mut tmpbuf = MathVec::<OUT>::new();
+
+    let mut max_error: f32 = 0.0;
+
+    for input in sequences[5].iter() {
+        let mut only_state = MathVec::<OUT>::new();
+        quantized.calc_state(input, &state, &mut only_state, &mut tmpbuf);
+
+        quantized.calc(
+            input,
+            &state,
+            &mut output,
+            &mut next_state,
+            &mut tmpbuf
+        );
+        assert_eq!(only_state, next_state);
+        state.copy_from(&next_state);
+
+        lstm.calc(
+            input,
+            &expected_state,
+            &mut expected,
+            &mut next_state,
+            &mut tmpbuf
+        );
+        expected_state.copy_from(&next_state);
+
+        output.iter().zip(expected.iter()).chain(
+            state.iter().zip(expected_state.iter())
+        ).for_each(|(a, b)| {
+            max_error = max_error.max((*a - *b).abs());
+        });
+    }
+
+    assert!(max_error < 0.05, "{}", max_error);
+
+    // Save and load.
+    let mut data = Vec::<u8>::new();
+    quantized.save(&mut data);
+
+    assert_eq!(QuantizedLstm::<OUT, IN>::load(&data).unwrap(), quantized);
+
+    assert_eq!(
+        QuantizedLstm::<OUT, 5>::load(&data),
+        Err(ChobitAiError::DimensionMismatch {
+            name: "IN",
+            expected: 5,
+            found: IN
+        })
+    );
+
+    // Dequantized LSTM has the same structure.
+    let dequantized = quantized.dequantize();
+    assert_eq!(dequantized.tanh(), lstm.tanh());
+    assert_eq!(
+        dequantized.main_layer().activation(),
+        lstm.main_layer().activation()
+    );
+}
+
+#[test]
+fn quantize_test_4() {
+    // (127 - (-128))^2 * IN exceeds i32::MAX.
+    const OUT: usize = 2;
+    const IN: usize = 40000;
+
+    let mut layer = Layer::<OUT, IN>::new(Activation::Linear, false);
+    layer.mut_weights().iter_mut().for_each(|val| {*val = 1.0});
+
+    let input_params = QuantParams::new(1.0 / 255.0, -128);
+
+    let quantized = QuantizedLayer::<OUT, IN>::new(
+        &layer,
+        input_params,
+        input_params
+    );
+
+    quantized.weights().input_params().iter().for_each(|params| {
+        assert_eq!(params.zero_point(), -128);
+    });
+
+    // Saturated into 127.
+    let mut input = MathVec::<IN>::new();
+    input.iter_mut().for_each(|val| {*val = 2.0});
+    assert_eq!(input_params.quantize(input[0]), 127);
+
+    let mut output = MathVec::<OUT>::new();
+    quantized.calc(&input, None, &mut output);
+
+    let mut dequantized_input = MathVec::<IN>::new();
+    dequantized_input.iter_mut().for_each(|val| {
+        *val = input_params.dequantize(127);
+    });
+
+    let mut expected = MathVec::<OUT>::new();
+    quantized.dequantize().calc(&dequantized_input, None, &mut expected);
+
+    output.iter().zip(expected.iter()).for_each(|(a, b)| {
+        assert!(*b > 0.0);
+        assert_close(*a, *b);
+    });
+}
+
+#[test]
+fn prune_test_1() {
+    const OUT: usize = 6;
+    const MIDDLE: usize = 10;
+    const IN: usize = 5;
+
+    let mut rng = ChobitRand::new("prune_test_1".as_bytes());
+
+    // Weights.
+    let mut weights = Weights::<OUT, IN>::new(true);
+    weights.iter_mut().for_each(|val| {*val = rand_num(&mut rng)});
+
+    let bias = *weights.bias();
+    let len = (OUT * IN) + (OUT * OUT);
+
+    let prune = |values: &mut [f32]| -> usize {
+        values.iter_mut().fold(0, |count, val| if val.abs() <= 0.3 {
+            *val = 0.0;
+            count + 1
+        } else {
+            count
+        })
+    };
+
+    let mut expected = weights.clone();
+    let count = prune(expected.input_weights_mut().as_flattened_mut())
+        + prune(expected.state_weights_mut().unwrap().as_flattened_mut());
+
+    assert_eq!(weights.clone().prune(Pruning::Threshold(0.3)), count);
+
+    let mut pruned = weights.clone();
+    pruned.prune(Pruning::Threshold(0.3));
+    assert_eq!(pruned, expected);
+    assert_eq!(*pruned.bias(), bias);
+
+    // 25% of 66 weights are 17 weights.
+    let mut pruned = weights.clone();
+    assert_eq!(
+        pruned.prune(Pruning::Percentage(25.0)),
+        (len * 25).div_ceil(100)
+    );
+    assert_eq!(pruned.clone().prune(Pruning::Percentage(0.0)), 0);
+    assert_eq!(*pruned.bias(), bias);
+
+    // MlLayer keeps masked weights 0.
+    let mut layer = Layer::<OUT, IN>::new(Activation::SoftSign, true);
+    *layer.mut_weights() = weights.clone();
+
+    let mut ml_layer = MlLayer::<OUT, IN>::new(layer);
+    assert!(ml_layer.mask().is_none());
+
+    let masked = ml_layer.prune(Pruning::Percentage(50.0));
+    assert_eq!(masked, len / 2);
+
+    let mask = ml_layer.mask().unwrap().to_vec();
+    assert_eq!(mask.len(), len);
+    assert_eq!(mask.iter().filter(|kept| !**kept).count(), masked);
+
+    // Non-zero weights of the layer.
+    let non_zero = |ml_layer: &MlLayer<OUT, IN>| -> Vec<bool> {
+        let layer = ml_layer.clone().drop();
+
+        layer.weights().input_weights().as_flattened().iter()
+            .chain(layer.weights().state_weights().unwrap().as_flattened())
+            .map(|val| *val != 0.0)
+            .collect()
+    };
+
+    assert_eq!(non_zero(&ml_layer), mask);
+
+    let mut input = MathVec::<IN>::new();
+    let mut state = MathVec::<OUT>::new();
+    let mut output_error = MathVec::<OUT>::new();
+    let mut input_error = MathVec::<IN>::new();
+    let mut state_error = MathVec::<OUT>::new();
+    let mut cache = MlCache::<OUT, IN>::new();
+
+    let mut study = |ml_layer: &mut MlLayer<OUT, IN>, rng: &mut ChobitRand| {
+        for _ in 0..5 {
+            rand_math_vec(rng, &mut input);
+            rand_math_vec(rng, &mut state);
+            rand_math_vec(rng, &mut output_error);
+
+            ml_layer.ready(&input, Some(&state), &mut cache);
+            ml_layer.study(
+                &output_error,
+                None,
+                &cache,
+                &mut input_error,
+                Some(&mut state_error)
+            );
+
+            ml_layer.update(0.1);
+        }
+    };
+
+    study(&mut ml_layer, &mut rng);
+    assert_eq!(non_zero(&ml_layer), mask);
+
+    // Pruning again keeps old mask.
+    let masked_2 = ml_layer.prune(Pruning::Threshold(0.0));
+    assert_eq!(masked_2, masked);
+
+    let masked_3 = ml_layer.prune(Pruning::Percentage(75.0));
+    assert!(masked_3 >= (len * 75) / 100);
+    assert!(ml_layer.mask().unwrap().iter().zip(mask.iter()).all(
+        |(kept, old_kept)| *old_kept || !*kept
+    ));
+
+    // Mask is restored from checkpoint.
+    let mut data = Vec::<u8>::new();
+    ml_layer.save_checkpoint(&mut data);
+
+    let mut loaded = MlLayer::<OUT, IN>::load_checkpoint(&data).unwrap();
+    assert_eq!(loaded.mask(), ml_layer.mask());
+
+    let mut data_2 = Vec::<u8>::new();
+    loaded.save_checkpoint(&mut data_2);
+    assert_eq!(data_2, data);
+
+    study(&mut loaded, &mut rng);
+    assert_eq!(non_zero(&loaded), loaded.mask().unwrap());
+
+    let mut data = Vec::<u8>::new();
+    MlLayer::<OUT, IN>::new(ml_layer.clone().drop()).save_checkpoint(&mut data);
+
+    let loaded = MlLayer::<OUT, IN>::load_checkpoint(&data).unwrap();
+    assert!(loaded.mask().is_none());
+
+    // Pruned weights are updated after clear_mask().
+    ml_layer.clear_mask();
+    assert!(ml_layer.mask().is_none());
+
+    study(&mut ml_layer, &mut rng);
+    assert!(non_zero(&ml_layer).iter().all(|non_zero| *non_zero));
+
+    // Global and per-layer pruning.
+    let mut ai = ChobitAi::<OUT, MIDDLE, IN>::new(Activation::SoftSign);
+    ai.for_each_weight_mut(|val| {*val = rand_num(&mut rng)});
+
+    // Output layer has smaller weights.
+    ai.output_layer_mut().mut_weights().iter_mut().for_each(|val| {
+        *val *= 0.1;
+    });
+
+    let middle_len = MIDDLE * IN;
+    let output_len = OUT * MIDDLE;
+
+    let mut ml_ai = ChobitMlAi::<OUT, MIDDLE, IN>::new(ai.clone());
+    assert_eq!(
+        ml_ai.prune(Pruning::Percentage(40.0)),
+        (middle_len * 40).div_ceil(100) + (output_len * 40).div_ceil(100)
+    );
+
+    let mut ml_ai = ChobitMlAi::<OUT, MIDDLE, IN>::new(ai.clone());
+    let masked = ml_ai.prune_global(Pruning::Percentage(40.0));
+    assert_eq!(masked, ((middle_len + output_len) * 40).div_ceil(100));
+
+    // All pruned weights are smaller than all remaining weights.
+    let pruned_ai = ml_ai.drop();
+
+    let original = ai.middle_layer().weights().input_weights().as_flattened()
+        .iter().chain(
+            ai.output_layer().weights().input_weights().as_flattened()
+        );
+
+    let current = pruned_ai.middle_layer().weights().input_weights()
+        .as_flattened().iter().chain(
+            pruned_ai.output_layer().weights().input_weights().as_flattened()
+        );
+
+    let (max_pruned, min_kept) = original.zip(current).fold(
+        (0.0f32, f32::INFINITY),
+        |(max_pruned, min_kept), (original, current)| if *current == 0.0 {
+            (max_pruned.max(original.abs()), min_kept)
+        } else {
+            (max_pruned, min_kept.min(original.abs()))
+        }
+    );
+
+    assert!(max_pruned < min_kept);
+
+    // Output layer with smaller weights is pruned more.
+    let output_zeros = pruned_ai.output_layer().weights().input_weights()
+        .as_flattened().iter().filter(|val| **val == 0.0).count();
+
+    assert!(output_zeros * (middle_len + output_len) > masked * output_len);
+}
+
+#[test]
+fn sparse_test_1() {
+    const OUT: usize = 8;
+    const MIDDLE: usize = 32;
+    const IN: usize = 16;
+
+    let mut rng = ChobitRand::new("sparse_test_1".as_bytes());
+
+    // SparseLayer with state.
+    let mut layer = Layer::<OUT, IN>::new(Activation::SoftSign, true);
+    layer.mut_weights().iter_mut().for_each(|val| {*val = rand_num(&mut rng)});
+    layer.mut_weights().prune(Pruning::Percentage(70.0));
+
+    let sparse_layer = SparseLayer::<OUT, IN>::new(&layer);
+
+    let zeros = layer.weights().input_weights().as_flattened().iter()
+        .chain(layer.weights().state_weights().unwrap().as_flattened())
+        .filter(|val| **val == 0.0)
+        .count();
+
+    assert_eq!(
+        sparse_layer.weights().non_zero_len(),
+        (OUT * IN) + (OUT * OUT) - zeros
+    );
+    assert_eq!(sparse_layer.to_layer(), layer);
+    assert_eq!(sparse_layer.weights().bias(), layer.weights().bias());
+
+    let mut input = MathVec::<IN>::new();
+    let mut state = MathVec::<OUT>::new();
+    let mut output = MathVec::<OUT>::new();
+    let mut expected = MathVec::<OUT>::new();
+
+    for _ in 0..10 {
+        rand_math_vec(&mut rng, &mut input);
+        rand_math_vec(&mut rng, &mut state);
+
+        sparse_layer.calc(&input, Some(&state), &mut output);
+        layer.calc(&input, Some(&state), &mut expected);
+
+        output.iter().zip(expected.iter()).for_each(|(a, b)| {
+            assert_close(*a, *b);
+        });
+
+        sparse_layer.calc(&input, None, &mut output);
+        layer.calc(&input, None, &mut expected);
+
+        output.iter().zip(expected.iter()).for_each(|(a, b)| {
+            assert_close(*a, *b);
+        });
+    }
+
+    // SparseChobitAi.
+    let mut ai = ChobitAi::<OUT, MIDDLE, IN>::new(Activation::SoftSign);
+    ai.for_each_weight_mut(|val| {*val = rand_num(&mut rng)});
+
+    let mut ml_ai = ChobitMlAi::<OUT, MIDDLE, IN>::new(ai);
+    ml_ai.prune_global(Pruning::Percentage(80.0));
+
+    let ai = ml_ai.drop();
+    let sparse = SparseChobitAi::<OUT, MIDDLE, IN>::new(&ai);
+
+    assert_eq!(sparse.to_ai(), ai);
+
+    let mut input = MathVec::<IN>::new();
+    let mut output = MathVec::<OUT>::new();
+    let mut expected = MathVec::<OUT>::new();
+    let mut tmpbuf = MathVec::<MIDDLE>::new();
+
+    for _ in 0..10 {
+        rand_math_vec(&mut rng, &mut input);
+
+        sparse.calc(&input, &mut output, &mut tmpbuf);
+        ai.calc(&input, &mut expected, &mut tmpbuf);
+
+        output.iter().zip(expected.iter()).for_each(|(a, b)| {
+            assert_close(*a, *b);
+        });
+    }
+
+    // Save and load.
+    let mut data = Vec::<u8>::new();
+    sparse.save(&mut data);
+
+    assert_eq!(SparseChobitAi::<OUT, MIDDLE, IN>::load(&data).unwrap(), sparse);
+
+    let mut dense_data = Vec::<u8>::new();
+    ai.save(&mut dense_data);
+    assert!(data.len() * 2 < dense_data.len());
+
+    assert!(SparseChobitAi::<OUT, MIDDLE, IN>::load(
+        &data[..(data.len() - 1)]
+    ).is_err());
+
+    assert_eq!(
+        SparseChobitAi::<OUT, MIDDLE, IN>::load(&dense_data),
+        Err(ChobitAiError::WrongModelKind {
+            expected: ModelKind::SparseChobitAi,
+            found: ModelKind::ChobitAi as u8
+        })
+    );
+}